@@ -0,0 +1,251 @@
+//! Breaks the bundle of a Worker down into the sizes of its modules, for `wrangler build
+//! --analyze`, to find what takes the most of the script size limit.
+
+use crate::bundler;
+use crate::settings::toml::{Bundler, Target, TargetType, UploadFormat};
+use crate::upload::krate::Krate;
+use crate::wranglerjs;
+use crate::wranglerjs::output::{human_size, ModuleSize};
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use ignore::WalkBuilder;
+use prettytable::{Cell, Row, Table};
+
+use super::{build_target, define};
+
+/// How many of the largest dependencies the report lists.
+const TOP_DEPENDENCIES: usize = 10;
+
+/// Builds `target`, returning the sizes of the modules of what is uploaded, largest first.
+pub fn analyze(target: &Target) -> Result<Vec<ModuleSize>> {
+    let mut modules = match target.target_type {
+        TargetType::Webpack => {
+            // the build is not skipped even if nothing changed, since only bundlers know the
+            // modules of the bundle
+            let defines = define::definitions(target)?;
+            let output = if target.bundler == Bundler::Builtin {
+                bundler::run_build(target, &defines)?
+            } else {
+                wranglerjs::run_build(target, &defines)?
+            };
+            let mut modules = output.modules;
+            if let Some(wasm) = &output.wasm {
+                modules.push(ModuleSize::new(
+                    "module.wasm".to_string(),
+                    &base64::decode(wasm)?,
+                ));
+            }
+            modules
+        }
+        TargetType::Rust => {
+            build_target(target)?;
+            let name = Krate::for_target(target)?.name.replace("-", "_");
+            let files = [
+                format!("pkg/{}_bg.wasm", name),
+                format!("pkg/{}.js", name),
+                "worker/worker.js".to_string(),
+            ];
+            files
+                .iter()
+                .map(|file| file_size(Path::new(file), file))
+                .collect::<Result<_>>()?
+        }
+        TargetType::JavaScript => {
+            build_target(target)?;
+            match target.build.as_ref().map(|build| &build.upload) {
+                Some(UploadFormat::Modules { dir, .. }) => {
+                    let mut modules = Vec::new();
+                    for entry in WalkBuilder::new(dir).build() {
+                        let entry = entry?;
+                        if entry.path().is_file() {
+                            let name = entry.path().strip_prefix(dir)?.display().to_string();
+                            modules.push(file_size(entry.path(), &name)?);
+                        }
+                    }
+                    modules
+                }
+                _ => {
+                    let script = bundler::script_path(target)?;
+                    vec![file_size(&script, &script.display().to_string())?]
+                }
+            }
+        }
+    };
+    modules.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    Ok(modules)
+}
+
+fn file_size(path: &Path, name: &str) -> Result<ModuleSize> {
+    Ok(ModuleSize::new(name.to_string(), &fs::read(path)?))
+}
+
+/// A table of the sizes of the modules, followed by the dependencies that take the most.
+pub fn report(modules: &[ModuleSize]) -> String {
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Module"),
+        Cell::new("Size"),
+        Cell::new("Gzip"),
+    ]));
+    for module in modules {
+        table.add_row(Row::new(vec![
+            Cell::new(&module.name),
+            Cell::new(&human_size(module.size)),
+            Cell::new(&human_size(module.gzip_size)),
+        ]));
+    }
+    table.add_row(Row::new(vec![
+        Cell::new("Total"),
+        Cell::new(&human_size(modules.iter().map(|module| module.size).sum())),
+        Cell::new(&human_size(
+            modules.iter().map(|module| module.gzip_size).sum(),
+        )),
+    ]));
+
+    let mut report = table.to_string();
+    let dependencies = dependencies(modules);
+    if !dependencies.is_empty() {
+        report.push_str("\nLargest dependencies:\n");
+        for (name, size, gzip_size) in dependencies.iter().take(TOP_DEPENDENCIES) {
+            report.push_str(&format!(
+                "- {} ({}, {} gzipped)\n",
+                name,
+                human_size(*size),
+                human_size(*gzip_size)
+            ));
+        }
+    }
+    report
+}
+
+/// The packages in node_modules that the modules are from, with their total sizes, largest
+/// first.
+fn dependencies(modules: &[ModuleSize]) -> Vec<(String, u64, u64)> {
+    let mut packages: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+    for module in modules {
+        if let Some(package) = package_of(&module.name) {
+            let sizes = packages.entry(package).or_default();
+            sizes.0 += module.size;
+            sizes.1 += module.gzip_size;
+        }
+    }
+    let mut packages: Vec<_> = packages
+        .into_iter()
+        .map(|(name, (size, gzip_size))| (name, size, gzip_size))
+        .collect();
+    packages.sort_by(|a, b| b.1.cmp(&a.1));
+    packages
+}
+
+/// The package of a module like `./node_modules/@scope/name/index.js`, if it is in node_modules.
+fn package_of(module: &str) -> Option<String> {
+    let path = &module[module.rfind("node_modules/")? + "node_modules/".len()..];
+    let mut parts = path.split('/');
+    let name = parts.next()?;
+    if name.starts_with('@') {
+        Some(format!("{}/{}", name, parts.next()?))
+    } else {
+        Some(name.to_string())
+    }
+}
+
+/// An HTML page that draws the modules as a treemap, grouped by package.
+pub fn treemap(modules: &[ModuleSize]) -> String {
+    let mut groups: BTreeMap<String, Vec<&ModuleSize>> = BTreeMap::new();
+    for module in modules {
+        let group = package_of(&module.name).unwrap_or_else(|| "(your code)".to_string());
+        groups.entry(group).or_default().push(module);
+    }
+    let mut groups: Vec<_> = groups.into_iter().collect();
+    groups.sort_by_key(|(_, modules)| {
+        std::cmp::Reverse(modules.iter().map(|module| module.size).sum::<u64>())
+    });
+
+    let mut boxes = String::new();
+    for (group, modules) in groups {
+        let size: u64 = modules.iter().map(|module| module.size).sum();
+        boxes.push_str(&format!(
+            "<div class=\"group\" style=\"flex-grow: {}\"><h2>{} ({})</h2><div class=\"modules\">",
+            size.max(1),
+            escape(&group),
+            human_size(size)
+        ));
+        for module in modules {
+            boxes.push_str(&format!(
+                "<div class=\"module\" style=\"flex-grow: {}\" title=\"{}: {}, {} gzipped\">{}</div>",
+                module.size.max(1),
+                escape(&module.name),
+                human_size(module.size),
+                human_size(module.gzip_size),
+                escape(&module.name)
+            ));
+        }
+        boxes.push_str("</div></div>\n");
+    }
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Bundle composition</title>
+<style>
+body {{ margin: 0; font: 12px sans-serif; }}
+.treemap {{ display: flex; flex-wrap: wrap; height: 100vh; }}
+.group {{ display: flex; flex-direction: column; flex-basis: 0; min-width: 120px; border: 1px solid #fff; background: #f6821f; }}
+.group h2 {{ margin: 0; padding: 4px; font-size: 13px; }}
+.modules {{ display: flex; flex-wrap: wrap; flex-grow: 1; }}
+.module {{ flex-basis: 0; min-width: 40px; overflow: hidden; padding: 2px; border: 1px solid #f6821f; background: #fbad41; word-break: break-all; }}
+</style>
+</head>
+<body>
+<div class="treemap">
+{}</div>
+</body>
+</html>
+"#,
+        boxes
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(name: &str, size: u64) -> ModuleSize {
+        ModuleSize {
+            name: name.to_string(),
+            size,
+            gzip_size: size / 2,
+        }
+    }
+
+    #[test]
+    fn it_sums_the_sizes_of_dependencies() {
+        let modules = [
+            module("./src/index.js", 100),
+            module("./node_modules/@scope/a/index.js", 300),
+            module("./node_modules/b/lib/x.js", 200),
+            module("./node_modules/b/lib/y.js", 200),
+            module("./node_modules/b/node_modules/c/index.js", 50),
+        ];
+        assert_eq!(
+            dependencies(&modules),
+            vec![
+                ("b".to_string(), 400, 200),
+                ("@scope/a".to_string(), 300, 150),
+                ("c".to_string(), 50, 25),
+            ]
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! Skips builds whose inputs have not changed since the last successful build. A hash of the
+//! inputs of that build is kept in `.wrangler/cache`, which can be deleted to force a rebuild.
+//! With `WRANGLER_CARGO_CACHE` set, the artifacts of cargo are kept there too.
+
+use crate::build::reproducible;
+use crate::bundler;
+use crate::settings::project::project_cache_dir;
+use crate::settings::toml::{Target, TargetType};
+use crate::upload::krate;
+use crate::wranglerjs::Bundle;
+
+use std::env;
+use std::fs;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::Result;
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use twox_hash::XxHash64;
+
+const BUILD_HASH_FILE: &str = "build";
+const CARGO_TARGET_DIR: &str = "cargo";
+// Set to keep the artifacts of cargo in `.wrangler/cache`
+const CARGO_CACHE_VAR: &str = "WRANGLER_CARGO_CACHE";
+// The lock files of the workspaces of npm, yarn and pnpm, which may be above a package
+const JS_LOCK_FILES: &[&str] = &["package-lock.json", "yarn.lock", "pnpm-lock.yaml"];
+
+// Paths that are outputs of builds rather than inputs, or too large to hash, in which case
+// lock files stand for them
+const RUST_OUTPUTS: &[&str] = &["pkg", "target", "worker/generated", ".wrangler", ".git"];
+const WEBPACK_OUTPUTS: &[&str] = &["worker", "dist", "node_modules", ".wrangler", ".git"];
+
+pub struct BuildCache {
+    path: PathBuf,
+    hash: String,
+    output: PathBuf,
+}
+
+impl BuildCache {
+    /// Hashes the inputs of the build of `target`. There is no cache of javascript projects,
+    /// nor of the projects built by their own command, which may read any file. `defines` are the
+    /// expanded values of `[build.define]`, which may come from commands and the environment.
+    pub fn new(target: &Target, defines: &[(String, String)]) -> Result<Option<BuildCache>> {
+        let (root, outputs, output) = match (&target.target_type, target.custom_build()) {
+            (TargetType::JavaScript, _) | (_, Some(_)) => return Ok(None),
+            (TargetType::Rust, None) => (PathBuf::from("./"), RUST_OUTPUTS, PathBuf::from("./pkg")),
+            (TargetType::Webpack, None) => {
+                let package_dir = target.package_dir()?;
+                let bundle = Bundle::new(&package_dir);
+                let output = match target.entries {
+                    Some(_) => bundle.modules_dir().join(bundler::MAIN_MODULE),
+                    None => bundle.script_path(),
+                };
+                (package_dir, WEBPACK_OUTPUTS, output)
+            }
+        };
+
+        let mut hasher = XxHash64::default();
+        hasher.write(env!("CARGO_PKG_VERSION").as_bytes());
+        // the settings that change the output of the build
+        hasher.write(
+            format!(
+                "{} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?} {}",
+                target.target_type,
+                target.main,
+                target.entries,
+                target.webpack_config,
+                target.node_compat,
+                target.bundler,
+                target.minify,
+                target.build,
+                target.site,
+                reproducible::is_reproducible()
+            )
+            .as_bytes(),
+        );
+        for (key, code) in defines {
+            hasher.write(format!("{}={}", key, code).as_bytes());
+        }
+        for file in input_files(&root, outputs)? {
+            hasher.write(file.to_string_lossy().as_bytes());
+            hasher.write(&fs::read(root.join(&file))?);
+        }
+        for input in external_inputs(target, &root)? {
+            let files = if input.is_dir() {
+                input_files(&input, outputs)?
+                    .into_iter()
+                    .map(|file| input.join(file))
+                    .collect()
+            } else {
+                vec![input]
+            };
+            for file in files {
+                hasher.write(file.to_string_lossy().as_bytes());
+                hasher.write(&fs::read(&file)?);
+            }
+        }
+
+        Ok(Some(BuildCache {
+            path: project_cache_dir().join(BUILD_HASH_FILE),
+            hash: format!("{:x}", hasher.finish()),
+            output,
+        }))
+    }
+
+    /// Whether the last successful build had the same inputs, and its output is still there.
+    pub fn is_fresh(&self) -> bool {
+        self.output.exists()
+            && fs::read_to_string(&self.path).map_or(false, |hash| hash.trim() == self.hash)
+    }
+
+    /// Remembers the inputs of a successful build.
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, &self.hash)?;
+        Ok(())
+    }
+}
+
+/// Keeps the artifacts of cargo in `.wrangler/cache`, with incremental compilation, when
+/// `WRANGLER_CARGO_CACHE` is set and the user has not configured them otherwise, so that CI can
+/// cache them with the hash of the inputs. It is opt-in since cargo builds everything again in
+/// the new target directory, and tools looking for `target` don't find the artifacts there.
+pub fn use_cargo_cache(command: &mut Command) {
+    if env::var_os(CARGO_CACHE_VAR).is_none() {
+        return;
+    }
+    if env::var_os("CARGO_TARGET_DIR").is_none() {
+        command.env(
+            "CARGO_TARGET_DIR",
+            project_cache_dir().join(CARGO_TARGET_DIR),
+        );
+    }
+    if env::var_os("CARGO_INCREMENTAL").is_none() {
+        command.env("CARGO_INCREMENTAL", "1");
+    }
+}
+
+/// The inputs of the build of `target` outside of `root`, which a change of them would not be
+/// noticed in: the crates that a Rust project depends on by path and the lock file of its cargo
+/// workspace, or the webpack configuration and the lock files of the workspaces of npm, yarn or
+/// pnpm that a webpack project is in, like the project around a `[site]`.
+fn external_inputs(target: &Target, root: &Path) -> Result<Vec<PathBuf>> {
+    let root = fs::canonicalize(root)?;
+    let mut inputs = Vec::new();
+    match target.target_type {
+        TargetType::Rust => {
+            let sources = krate::local_sources("./")?;
+            inputs.push(sources.workspace_root.join("Cargo.lock"));
+            inputs.extend(sources.crate_dirs);
+        }
+        TargetType::Webpack => {
+            inputs.extend(target.webpack_config.iter().map(PathBuf::from));
+            for dir in root.ancestors().skip(1) {
+                inputs.extend(JS_LOCK_FILES.iter().map(|lock_file| dir.join(lock_file)));
+            }
+        }
+        TargetType::JavaScript => {}
+    }
+    // the inputs that don't exist are not read either
+    let mut external: Vec<PathBuf> = inputs
+        .iter()
+        .filter_map(|input| fs::canonicalize(input).ok())
+        .filter(|input| !input.starts_with(&root))
+        .collect();
+    external.sort();
+    external.dedup();
+    Ok(external)
+}
+
+/// The files under `root` other than `outputs`, relative to `root` and sorted so that the hash
+/// does not depend on the order of the walk.
+fn input_files(root: &Path, outputs: &[&str]) -> Result<Vec<PathBuf>> {
+    let mut overrides = OverrideBuilder::new(root);
+    for output in outputs {
+        overrides.add(&format!("!{}", output))?;
+    }
+    let mut files = Vec::new();
+    for entry in WalkBuilder::new(root)
+        .hidden(false)
+        .overrides(overrides.build()?)
+        .build()
+    {
+        let entry = entry?;
+        if entry.path().is_file() {
+            files.push(entry.path().strip_prefix(root)?.to_path_buf());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_hashes_inputs_but_not_outputs() {
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path();
+        for file in &[
+            "src/index.js",
+            "package.json",
+            "worker/script.js",
+            "dist/worker.js",
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        assert_eq!(
+            input_files(root, WEBPACK_OUTPUTS).unwrap(),
+            vec![PathBuf::from("package.json"), PathBuf::from("src/index.js")]
+        );
+    }
+
+    #[test]
+    fn it_hashes_the_lock_files_and_webpack_config_outside_of_the_root() {
+        let tmp_dir = tempdir().unwrap();
+        let project = fs::canonicalize(tmp_dir.path()).unwrap();
+        for file in &[
+            "yarn.lock",
+            "webpack.config.js",
+            "workers-site/package-lock.json",
+            "workers-site/index.js",
+        ] {
+            let path = project.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        let target = Target {
+            target_type: TargetType::Webpack,
+            webpack_config: Some(project.join("webpack.config.js").display().to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            external_inputs(&target, &project.join("workers-site")).unwrap(),
+            vec![project.join("webpack.config.js"), project.join("yarn.lock")]
+        );
+    }
+}
@@ -0,0 +1,70 @@
+//! Checks the scripts built for a Worker for APIs that the Workers runtime does not have, as
+//! configured by `[build.compat]`, so that they are caught before the Worker is uploaded.
+
+use crate::build::reproducible;
+use crate::bundler;
+use crate::settings::toml::{Severity, Target};
+use crate::terminal::message::{Message, StdErr};
+
+use std::fs;
+
+use anyhow::Result;
+
+// The artifacts that are scripts
+const SCRIPT_EXTENSIONS: &[&str] = &["js", "mjs", "cjs"];
+
+/// Reports the unsupported APIs used by the scripts built for `target` when `[build.compat]` is
+/// set, failing the build if their severity is `error`. The check is opt-in since bundles have
+/// code for other environments that never runs in Workers, which it can't tell apart.
+pub fn check(target: &Target) -> Result<()> {
+    let config = match target.build.as_ref().and_then(|build| build.compat.clone()) {
+        Some(config) if config.severity != Severity::Off => config,
+        _ => return Ok(()),
+    };
+
+    let mut count = 0;
+    for path in reproducible::artifacts(target)? {
+        let is_script = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map_or(false, |ext| SCRIPT_EXTENSIONS.contains(&ext));
+        if !is_script || !path.is_file() {
+            continue;
+        }
+        let name = path.display().to_string();
+        let script = fs::read_to_string(&path)?;
+        let usages = match bundler::find_unsupported(&script, &name, &config.allow) {
+            Ok(usages) => usages,
+            Err(e) => {
+                StdErr::warn(&format!(
+                    "Could not check {} for unsupported APIs: {}",
+                    name, e
+                ));
+                continue;
+            }
+        };
+        for usage in usages {
+            let msg = format!(
+                "{}:{}:{}: `{}` is not available in the Workers runtime",
+                name, usage.line, usage.column, usage.api
+            );
+            match config.severity {
+                Severity::Error => StdErr::user_error(&msg),
+                _ => StdErr::warn(&msg),
+            }
+            count += 1;
+        }
+    }
+
+    if count > 0 {
+        let msg = format!(
+            "Found {} uses of APIs that the Workers runtime does not have. Add those that never run to `allow` in [build.compat].",
+            count
+        );
+        if config.severity == Severity::Error {
+            anyhow::bail!(msg);
+        }
+        StdErr::info(&msg);
+    }
+    Ok(())
+}
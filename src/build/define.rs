@@ -0,0 +1,142 @@
+//! Expands the values of `[build.define]` to the code that bundlers replace expressions with.
+
+use crate::settings::toml::Target;
+
+use std::env;
+use std::process::Command;
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+/// The expressions to replace when bundling `target`, and the code to replace them with.
+pub fn definitions(target: &Target) -> Result<Vec<(String, String)>> {
+    let define = match target
+        .build
+        .as_ref()
+        .and_then(|build| build.define.as_ref())
+    {
+        Some(define) => define,
+        None => return Ok(Vec::new()),
+    };
+    define
+        .iter()
+        .map(|(key, value)| {
+            let expression = key.split('.').all(|part| {
+                part.chars()
+                    .next()
+                    .map_or(false, |c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+                    && part
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+            });
+            anyhow::ensure!(
+                expression,
+                "`{}` in [build.define] is neither an identifier nor a dotted path like `process.env.NAME`",
+                key
+            );
+            let code = match value {
+                Value::String(value) => serde_json::to_string(
+                    &expand(value).with_context(|| format!("Could not expand `{}` in [build.define]", key))?,
+                )?,
+                Value::Bool(_) | Value::Number(_) => value.to_string(),
+                _ => anyhow::bail!(
+                    "`{}` in [build.define] must be a string, a number or a boolean",
+                    key
+                ),
+            };
+            Ok((key.clone(), code))
+        })
+        .collect()
+}
+
+/// Replaces `$(command)` with the output of the command, and `$NAME` or `${NAME}` with the
+/// environment variable. `$$` is a `$`.
+fn expand(value: &str) -> Result<String> {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(dollar) = rest.find('$') {
+        expanded.push_str(&rest[..dollar]);
+        rest = &rest[dollar + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            expanded.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('(') {
+            let end = closing_paren(after)
+                .ok_or_else(|| anyhow::anyhow!("`$(` is not closed in \"{}\"", value))?;
+            expanded.push_str(&output_of(&after[..end])?);
+            rest = &after[end + 1..];
+        } else if let Some(after) = rest.strip_prefix('{') {
+            let end = after
+                .find('}')
+                .ok_or_else(|| anyhow::anyhow!("`${{` is not closed in \"{}\"", value))?;
+            expanded.push_str(&env_var(&after[..end])?);
+            rest = &after[end + 1..];
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or_else(|| rest.len());
+            if end == 0 {
+                expanded.push('$');
+            } else {
+                expanded.push_str(&env_var(&rest[..end])?);
+                rest = &rest[end..];
+            }
+        }
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+fn closing_paren(command: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in command.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+fn env_var(name: &str) -> Result<String> {
+    env::var(name).with_context(|| format!("The environment variable {} is not set", name))
+}
+
+/// The output of a command run by the shell, without its trailing newlines.
+fn output_of(command: &str) -> Result<String> {
+    let output = if cfg!(target_os = "windows") {
+        Command::new("cmd").arg("/C").arg(command).output()
+    } else {
+        Command::new("sh").arg("-c").arg(command).output()
+    }
+    .with_context(|| format!("Could not run `{}`", command))?;
+    anyhow::ensure!(
+        output.status.success(),
+        "`{}` exited with {}:\n{}",
+        command,
+        output.status,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end_matches(&['\r', '\n'][..])
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_expands_commands_and_environment_variables() {
+        env::set_var("WRANGLER_TEST_DEFINE", "eu");
+        assert_eq!(
+            expand("$WRANGLER_TEST_DEFINE-${WRANGLER_TEST_DEFINE}1 costs $$5").unwrap(),
+            "eu-eu1 costs $5"
+        );
+        assert_eq!(expand("$(echo 0123abc) $").unwrap(), "0123abc $");
+        assert!(expand("$WRANGLER_TEST_DEFINE_UNSET").is_err());
+        assert!(expand("$(echo").is_err());
+    }
+}
@@ -0,0 +1,262 @@
+//! The errors and warnings of the tools building a worker, parsed out of their output so that they
+//! are reported the same way whichever tool emitted them. The output of rustc (through wasm-pack
+//! or cargo), webpack, tsc, esbuild and the `file:line:column: message` format of most other tools
+//! are understood; the lines of anything else are only echoed.
+
+use std::error::Error;
+use std::fmt;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, ExitStatus, Stdio};
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::terminal::styles;
+
+// error[E0425]: cannot find value `x` in this scope
+static RUSTC_MESSAGE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(error|warning)(?:\[\w+\])?: (.+)$").unwrap());
+//   --> src/lib.rs:10:5
+static RUSTC_LOCATION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*--> (.+):(\d+):(\d+)$").unwrap());
+// ✘ [ERROR] Could not resolve "x"
+static ESBUILD_MESSAGE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*(?:✘ |▲ )?\[(ERROR|WARNING)\] (.+)$").unwrap());
+//     src/index.ts:3:20:
+static ESBUILD_LOCATION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s+(\S+):(\d+):(\d+):$").unwrap());
+// src/index.ts(10,5): error TS2304: Cannot find name 'x'.
+static TSC: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(\S+)\((\d+),(\d+)\): (error|warning) (TS\d+: .+)$").unwrap());
+// ERROR in ./src/index.js 10:5-12, followed by the message
+static WEBPACK_LOCATION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:(ERROR|WARNING) in )?(\S+\.\w+) (\d+):(\d+)(?:-\d+)?$").unwrap());
+// src/index.js:10:5: error: message
+static GENERIC: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^([^\s:]+):(\d+):(\d+): (?:(error|warning):? )?(.+)$").unwrap());
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn parse(s: &str) -> Severity {
+        if s.eq_ignore_ascii_case("warning") {
+            Severity::Warning
+        } else {
+            Severity::Error
+        }
+    }
+}
+
+/// An error or a warning at a position in a source file.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub file: String,
+    pub line: u32,
+    pub column: Option<u32>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    /// Formats the diagnostic as `file:line:column: severity: message`, which editors and
+    /// terminals link to the position in the file.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => styles::warning("error"),
+            Severity::Warning => styles::highlight("warning"),
+        };
+        write!(f, "{}:{}", self.file, self.line)?;
+        if let Some(column) = self.column {
+            write!(f, ":{}", column)?;
+        }
+        write!(f, ": {}: {}", severity, self.message)
+    }
+}
+
+/// Parses the diagnostics out of the output of a build tool. The messages that span several lines
+/// are found by their first line and the line giving their position, in either order.
+pub fn parse(output: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    // the severity and message of rustc and esbuild, whose position comes on a following line
+    let mut pending: Option<(Severity, String)> = None;
+    // the position of webpack, whose message comes on the following line
+    let mut located: Option<(Severity, String, u32, u32)> = None;
+
+    for line in output.lines() {
+        let line = line.trim_end();
+        if let Some((severity, file, line_number, column)) = located.take() {
+            if !line.trim().is_empty() {
+                diagnostics.push(Diagnostic {
+                    file,
+                    line: line_number,
+                    column: Some(column),
+                    severity,
+                    message: line.trim().to_string(),
+                });
+                continue;
+            }
+        }
+
+        let location = RUSTC_LOCATION
+            .captures(line)
+            .or_else(|| ESBUILD_LOCATION.captures(line));
+        if let Some(caps) = location {
+            if let Some((severity, message)) = pending.take() {
+                diagnostics.push(Diagnostic {
+                    file: caps[1].to_string(),
+                    line: caps[2].parse().unwrap_or_default(),
+                    column: caps[3].parse().ok(),
+                    severity,
+                    message,
+                });
+            }
+        } else if let Some(caps) = RUSTC_MESSAGE
+            .captures(line)
+            .or_else(|| ESBUILD_MESSAGE.captures(line))
+        {
+            // rustc sums the errors up with a message of no position at the end
+            pending = Some((Severity::parse(&caps[1]), caps[2].to_string()));
+        } else if let Some(caps) = TSC.captures(line) {
+            diagnostics.push(Diagnostic {
+                file: caps[1].to_string(),
+                line: caps[2].parse().unwrap_or_default(),
+                column: caps[3].parse().ok(),
+                severity: Severity::parse(&caps[4]),
+                message: caps[5].to_string(),
+            });
+        } else if let Some(caps) = GENERIC.captures(line) {
+            diagnostics.push(Diagnostic {
+                file: caps[1].to_string(),
+                line: caps[2].parse().unwrap_or_default(),
+                column: caps[3].parse().ok(),
+                severity: caps
+                    .get(4)
+                    .map_or(Severity::Error, |s| Severity::parse(s.as_str())),
+                message: caps[5].to_string(),
+            });
+        } else if let Some(caps) = WEBPACK_LOCATION.captures(line) {
+            located = Some((
+                caps.get(1)
+                    .map_or(Severity::Error, |s| Severity::parse(s.as_str())),
+                caps[2].to_string(),
+                caps[3].parse().unwrap_or_default(),
+                caps[4].parse().unwrap_or_default(),
+            ));
+        }
+    }
+    diagnostics
+}
+
+/// Runs a build tool with its stderr echoed as it comes, and parses the diagnostics out of it.
+pub fn run(command: &mut Command) -> Result<(ExitStatus, Vec<Diagnostic>)> {
+    log::info!("Running {:?}", command);
+    let mut child = command.stderr(Stdio::piped()).spawn()?;
+
+    let mut output = String::new();
+    if let Some(stderr) = child.stderr.take() {
+        for line in BufReader::new(stderr).lines() {
+            let line = line?;
+            eprintln!("{}", line);
+            output.push_str(&line);
+            output.push('\n');
+        }
+    }
+    let status = child.wait()?;
+    Ok((status, parse(&output)))
+}
+
+/// A build that failed, with the diagnostics of the tool that built it.
+#[derive(Debug)]
+pub struct BuildError {
+    pub message: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl BuildError {
+    pub fn new(message: String, diagnostics: Vec<Diagnostic>) -> BuildError {
+        BuildError {
+            message,
+            diagnostics,
+        }
+    }
+}
+
+impl Error for BuildError {}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.message.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostic(file: &str, line: u32, column: u32, message: &str) -> Diagnostic {
+        Diagnostic {
+            file: file.to_string(),
+            line,
+            column: Some(column),
+            severity: Severity::Error,
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn it_parses_rustc_errors() {
+        let output = "   Compiling worker v0.1.0\nerror[E0425]: cannot find value `x` in this scope\n  --> src/lib.rs:10:5\n   |\n10 |     x\n   |     ^ not found in this scope\n\nerror: aborting due to previous error\n";
+        assert_eq!(
+            parse(output),
+            vec![diagnostic(
+                "src/lib.rs",
+                10,
+                5,
+                "cannot find value `x` in this scope"
+            )]
+        );
+    }
+
+    #[test]
+    fn it_parses_webpack_errors() {
+        let output = "ERROR in ./src/index.js 3:8\nModule parse failed: Unexpected token (3:8)\nYou may need an appropriate loader.\n";
+        assert_eq!(
+            parse(output),
+            vec![diagnostic(
+                "./src/index.js",
+                3,
+                8,
+                "Module parse failed: Unexpected token (3:8)"
+            )]
+        );
+    }
+
+    #[test]
+    fn it_parses_tsc_and_esbuild_errors() {
+        let output = "src/index.ts(4,12): error TS2304: Cannot find name 'foo'.\n✘ [ERROR] Could not resolve \"bar\"\n\n    src/util.ts:1:17:\n      1 │ import bar from \"bar\";\n";
+        assert_eq!(
+            parse(output),
+            vec![
+                diagnostic("src/index.ts", 4, 12, "TS2304: Cannot find name 'foo'."),
+                diagnostic("src/util.ts", 1, 17, "Could not resolve \"bar\""),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_parses_generic_errors() {
+        let mut warning = diagnostic("src/index.js", 2, 1, "unused variable");
+        warning.severity = Severity::Warning;
+        assert_eq!(
+            parse("src/index.js:2:1: warning: unused variable\nnothing to see here\n"),
+            vec![warning]
+        );
+    }
+}
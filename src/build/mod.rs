@@ -1,39 +1,111 @@
-use crate::settings::toml::{Target, TargetType};
+pub mod analyze;
+mod cache;
+mod compat;
+pub mod define;
+pub mod diagnostics;
+pub mod reproducible;
+pub mod wasi;
+mod wasm_opt;
+
+pub use cache::use_cargo_cache;
+
+use crate::settings::toml::{Builder, Bundler, Target, TargetType};
 use crate::terminal::message::{Message, StdErr};
 use crate::terminal::styles;
+use crate::upload::krate::Krate;
 use crate::wranglerjs;
-use crate::{commands, install};
+use crate::{bundler, install};
 
+use std::env;
+use std::ffi::OsStr;
 use std::path::Path;
 use std::process::Command;
 
 use anyhow::{anyhow, Result};
 
+use cache::BuildCache;
+use diagnostics::BuildError;
+
+/// The target that wasm-pack has cargo build Rust projects for.
+pub const WASM_PACK_TRIPLE: &str = "wasm32-unknown-unknown";
+
 // Internal build logic, called by both `build` and `publish`
 // TODO: return a struct containing optional build info and construct output at command layer
 pub fn build_target(target: &Target) -> Result<String> {
-    let target_type = &target.target_type;
-    match target_type {
+    let hooks = target.build.as_ref().and_then(|build| {
+        build
+            .hooks
+            .as_ref()
+            .map(|hooks| (build, &hooks.pre, &hooks.post))
+    });
+    // pre hooks may generate inputs of the build, so they run before the inputs are hashed
+    if let Some((build, pre, _)) = hooks {
+        run_hooks(build, "pre", pre)?;
+    }
+
+    // the commands of [build.define] run once per build, for both the hash and the bundler
+    let defines = define::definitions(target)?;
+    let cache = BuildCache::new(target, &defines).unwrap_or_else(|e| {
+        log::debug!("Could not hash the inputs of the build: {}", e);
+        None
+    });
+    let fresh = cache.as_ref().map_or(false, BuildCache::is_fresh);
+    let msg = if fresh {
+        "Nothing changed since the last build, skipping it".to_string()
+    } else {
+        let msg = build(target, &defines)?;
+        if reproducible::is_reproducible() {
+            reproducible::strip_paths(target)?;
+        }
+        msg
+    };
+    // a skipped build is checked and followed by the post hooks like the pre hooks preceded it,
+    // since they may do something with the artifacts that is not kept between builds
+    compat::check(target)?;
+    if let Some((build, _, post)) = hooks {
+        run_hooks(build, "post", post)?;
+    }
+    if let (Some(cache), false) = (&cache, fresh) {
+        cache.save()?;
+    }
+    Ok(msg)
+}
+
+/// Runs the hooks of a stage of the build in order, with their output streamed.
+fn run_hooks(build: &Builder, stage: &str, hooks: &[String]) -> Result<()> {
+    for hook in hooks {
+        StdErr::working(&format!("Running the {} build hook `{}`", stage, hook));
+        let status = build.shell_command(hook).status()?;
+        if !status.success() {
+            anyhow::bail!("The {} build hook `{}` failed: {}", stage, hook, status);
+        }
+    }
+    Ok(())
+}
+
+fn build(target: &Target, defines: &[(String, String)]) -> Result<String> {
+    // the build command of any type of project replaces the build of its type
+    if let Some((cmd_str, mut cmd)) = target.custom_build().and_then(Builder::build_command) {
+        StdErr::working(format!("Running {}", cmd_str).as_ref());
+        let (build_result, diagnostics) = diagnostics::run(&mut cmd)?;
+        return if build_result.success() {
+            Ok(String::from("Build completed successfully!"))
+        } else if let Some(code) = build_result.code() {
+            let message = format!("Build failed! Status Code: {}", code);
+            Err(BuildError::new(message, diagnostics).into())
+        } else {
+            Err(BuildError::new("Build failed.".to_string(), diagnostics).into())
+        };
+    }
+
+    match &target.target_type {
+        TargetType::JavaScript if bundler::transpiles(target) => bundler::transpile(target),
         TargetType::JavaScript => match &target.build {
             None => {
                 let msg = "Basic JavaScript project found. Skipping unnecessary build!".to_string();
                 Ok(msg)
             }
-            Some(config) => {
-                if let Some((cmd_str, mut cmd)) = config.build_command() {
-                    StdErr::working(format!("Running {}", cmd_str).as_ref());
-                    let build_result = cmd.spawn()?.wait()?;
-                    if build_result.success() {
-                        Ok(String::from("Build completed successfully!"))
-                    } else if let Some(code) = build_result.code() {
-                        Err(anyhow!("Build failed! Status Code: {}", code))
-                    } else {
-                        Err(anyhow!("Build failed."))
-                    }
-                } else {
-                    Ok(String::from("No build command specified, skipping build."))
-                }
-            }
+            Some(_) => Ok(String::from("No build command specified, skipping build.")),
         },
         TargetType::Rust => {
             let _ = which::which("rustc").map_err(|e| {
@@ -44,18 +116,39 @@ pub fn build_target(target: &Target) -> Result<String> {
                 )
             })?;
 
-            let binary_path = install::install_wasm_pack()?;
-            let args = ["build", "--target", "no-modules"];
+            let krate = Krate::for_target(target)?;
+            if wasi::is_enabled(target) {
+                wasi::build_rust(target, &krate)?;
+            } else {
+                let binary_path = install::install_wasm_pack()?;
+                let args = wasm_pack_args(target, &krate)?;
 
-            let command = command(&args, &binary_path);
-            let command_name = format!("{:?}", command);
+                let mut command = command(&args, &binary_path);
+                cache::use_cargo_cache(&mut command);
+                reproducible::remap_paths(&mut command, WASM_PACK_TRIPLE)?;
+                let command_name = format!("{:?}", command);
 
-            StdErr::working("Compiling your project to WebAssembly...");
-            commands::run(command, &command_name)?;
+                StdErr::working("Compiling your project to WebAssembly...");
+                let (status, diagnostics) = diagnostics::run(&mut command)?;
+                if !status.success() {
+                    let message = format!(
+                        "tried running command:\n{}\nexited with {}",
+                        command_name.replace("\"", ""),
+                        status
+                    );
+                    return Err(BuildError::new(message, diagnostics).into());
+                }
+            }
+            if let Some(wasm) = target.build.as_ref().and_then(|build| build.wasm.as_ref()) {
+                StdErr::info(&wasm_opt::optimize(wasm, &krate.name)?);
+            }
             let msg = "Build succeeded".to_string();
             Ok(msg)
         }
-        TargetType::Webpack => match wranglerjs::run_build(target) {
+        TargetType::Webpack if target.bundler == Bundler::Builtin => {
+            bundler::build(target, defines)
+        }
+        TargetType::Webpack => match wranglerjs::run_build(target, defines) {
             Ok(output) => {
                 let msg = format!(
                     "Built successfully, built project size is {}",
@@ -68,7 +161,26 @@ pub fn build_target(target: &Target) -> Result<String> {
     }
 }
 
-pub fn command(args: &[&str], binary_path: &Path) -> Command {
+/// The arguments of wasm-pack that build the crate of a Rust project. The member of a cargo
+/// workspace is built to ./pkg as well, where publish looks for it, while cargo compiles it in the
+/// target directory of the workspace, shared by all of its members.
+pub fn wasm_pack_args(target: &Target, krate: &Krate) -> Result<Vec<String>> {
+    let mut args = vec!["build".to_string()];
+    if target
+        .build
+        .as_ref()
+        .map_or(false, |build| build.rust.is_some())
+    {
+        args.push(krate.dir.display().to_string());
+        args.push("--out-dir".to_string());
+        args.push(env::current_dir()?.join("pkg").display().to_string());
+    }
+    args.push("--target".to_string());
+    args.push("no-modules".to_string());
+    Ok(args)
+}
+
+pub fn command<S: AsRef<OsStr>>(args: &[S], binary_path: &Path) -> Command {
     let mut c = if cfg!(target_os = "windows") {
         let mut c = Command::new("cmd");
         c.arg("/C");
@@ -0,0 +1,338 @@
+//! Reproducible builds, whose artifacts are the same bytes whenever and wherever a commit is
+//! built, so that what was published can be verified by building it again and comparing hashes.
+
+use crate::bundler;
+use crate::settings::toml::{Target, TargetType, UploadFormat};
+use crate::upload::krate::Krate;
+use crate::wranglerjs::Bundle;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::Result;
+use ignore::WalkBuilder;
+use path_slash::PathExt;
+use sha2::{Digest, Sha256};
+
+/// Whether `--reproducible` was passed.
+static REPRODUCIBLE: AtomicBool = AtomicBool::new(false);
+
+// Artifacts whose absolute paths are rewritten to relative ones
+const TEXT_EXTENSIONS: &[&str] = &["js", "mjs", "cjs", "map"];
+
+// The configuration files of cargo in a `.cargo` directory, the first of which cargo prefers
+const CARGO_CONFIG_FILES: &[&str] = &["config", "config.toml"];
+
+/// Makes the builds of this run reproducible. The tools run by builds inherit
+/// `SOURCE_DATE_EPOCH`, the time of the last commit, to write instead of the current time, and
+/// rustc writes the paths of the project and of cargo's home as relative ones, as
+/// [`remap_paths`] tells it.
+pub fn set_reproducible() {
+    REPRODUCIBLE.store(true, Ordering::Relaxed);
+    if env::var_os("SOURCE_DATE_EPOCH").is_none() {
+        env::set_var("SOURCE_DATE_EPOCH", source_date_epoch());
+    }
+}
+
+/// Has the rustc run by the cargo of `command`, which builds for `triple`, write the paths of the
+/// project and of cargo's home as relative ones when builds are reproducible. The flags are added
+/// to those cargo would use otherwise, which a `RUSTFLAGS` of wrangler's own would replace, with
+/// `CARGO_ENCODED_RUSTFLAGS`.
+pub fn remap_paths(command: &mut Command, triple: &str) -> Result<()> {
+    if !is_reproducible() {
+        return Ok(());
+    }
+    let dir = env::current_dir()?;
+    let mut rustflags = match env::var("CARGO_ENCODED_RUSTFLAGS") {
+        Ok(flags) => flags
+            .split('\x1f')
+            .filter(|flag| !flag.is_empty())
+            .map(str::to_string)
+            .collect(),
+        Err(_) => match env::var("RUSTFLAGS") {
+            Ok(flags) => flags.split_whitespace().map(str::to_string).collect(),
+            Err(_) => configured_rustflags(&cargo_config_files(&dir, cargo_home()), triple)?,
+        },
+    };
+    rustflags.push(format!("--remap-path-prefix={}=.", dir.display()));
+    if let Some(cargo_home) = cargo_home() {
+        rustflags.push(format!(
+            "--remap-path-prefix={}=/cargo",
+            cargo_home.display()
+        ));
+    }
+    command.env_remove("RUSTFLAGS");
+    command.env("CARGO_ENCODED_RUSTFLAGS", rustflags.join("\x1f"));
+    Ok(())
+}
+
+fn cargo_home() -> Option<PathBuf> {
+    env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")))
+}
+
+/// The configuration files of cargo for a build in `dir`, in the order cargo merges them: the one
+/// of cargo's home, then those of the directories from the root of the file system to `dir`.
+fn cargo_config_files(dir: &Path, cargo_home: Option<PathBuf>) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = dir.ancestors().map(|dir| dir.join(".cargo")).collect();
+    if let Some(cargo_home) = cargo_home {
+        if !dirs.contains(&cargo_home) {
+            dirs.push(cargo_home);
+        }
+    }
+    dirs.iter()
+        .rev()
+        .filter_map(|dir| {
+            CARGO_CONFIG_FILES
+                .iter()
+                .map(|name| dir.join(name))
+                .find(|path| path.is_file())
+        })
+        .collect()
+}
+
+/// The flags of rustc that the configuration `files` of cargo set for `triple`, like cargo
+/// resolves them: the `rustflags` of `[target.<triple>]` of all files if any sets them, else
+/// those of `[build]`, along with their environment variables. `cfg()` targets are not
+/// supported.
+fn configured_rustflags(files: &[PathBuf], triple: &str) -> Result<Vec<String>> {
+    let mut configs = Vec::new();
+    for file in files {
+        let config: toml::Value = toml::from_str(&fs::read_to_string(file)?)
+            .map_err(|e| anyhow::anyhow!("Could not read {}: {}", file.display(), e))?;
+        configs.push(config);
+    }
+    let target_var = format!(
+        "CARGO_TARGET_{}_RUSTFLAGS",
+        triple.to_uppercase().replace('-', "_")
+    );
+    let target_flags = rustflags_of(&configs, &["target", triple], &target_var);
+    if !target_flags.is_empty() {
+        return Ok(target_flags);
+    }
+    Ok(rustflags_of(&configs, &["build"], "CARGO_BUILD_RUSTFLAGS"))
+}
+
+/// The `rustflags` of the table at `path` of each configuration joined, followed by those of the
+/// environment variable `var`. They are either an array or a string of flags separated by spaces.
+fn rustflags_of(configs: &[toml::Value], path: &[&str], var: &str) -> Vec<String> {
+    let mut flags = Vec::new();
+    for config in configs {
+        let rustflags = path
+            .iter()
+            .try_fold(config, |table, key| table.get(key))
+            .and_then(|table| table.get("rustflags"));
+        match rustflags {
+            Some(toml::Value::String(rustflags)) => {
+                flags.extend(rustflags.split_whitespace().map(str::to_string))
+            }
+            Some(toml::Value::Array(rustflags)) => flags.extend(
+                rustflags
+                    .iter()
+                    .filter_map(|flag| flag.as_str())
+                    .map(str::to_string),
+            ),
+            _ => {}
+        }
+    }
+    if let Ok(rustflags) = env::var(var) {
+        flags.extend(rustflags.split_whitespace().map(str::to_string));
+    }
+    flags
+}
+
+pub fn is_reproducible() -> bool {
+    REPRODUCIBLE.load(Ordering::Relaxed)
+}
+
+/// The time of the last commit in seconds, or 0 outside of git repositories.
+fn source_date_epoch() -> String {
+    Command::new("git")
+        .args(&["log", "-1", "--format=%ct"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|epoch| !epoch.is_empty())
+        .unwrap_or_else(|| "0".to_string())
+}
+
+/// The files built for `target` that are uploaded, sorted so that they are hashed in the same
+/// order everywhere.
+pub fn artifacts(target: &Target) -> Result<Vec<PathBuf>> {
+    let mut paths = match (&target.target_type, target.custom_build()) {
+        (TargetType::Rust, None) => {
+            let name = Krate::for_target(target)?.name.replace("-", "_");
+            vec![
+                PathBuf::from(format!("pkg/{}.js", name)),
+                PathBuf::from(format!("pkg/{}_bg.wasm", name)),
+                PathBuf::from("worker/worker.js"),
+            ]
+        }
+        (TargetType::Webpack, None) => {
+            let bundle = Bundle::new(&target.package_dir()?);
+            if target.entries.is_some() {
+                files(&bundle.modules_dir())?
+            } else {
+                let mut paths = vec![bundle.script_path()];
+                if bundle.has_wasm() {
+                    paths.push(bundle.wasm_path());
+                }
+                paths
+            }
+        }
+        (TargetType::JavaScript, _) | (_, Some(_)) => {
+            match target.build.as_ref().map(|build| &build.upload) {
+                Some(UploadFormat::Modules { dir, .. }) => files(dir)?,
+                _ => vec![bundler::script_path(target)?],
+            }
+        }
+    };
+    paths.extend(
+        target
+            .wasm_modules
+            .iter()
+            .flatten()
+            .map(|(_, path)| path.clone()),
+    );
+    paths.extend(
+        target
+            .text_blobs
+            .iter()
+            .flatten()
+            .map(|(_, path)| path.clone()),
+    );
+    paths.sort();
+    Ok(paths)
+}
+
+fn files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in WalkBuilder::new(dir).build() {
+        let entry = entry?;
+        if entry.path().is_file() {
+            paths.push(entry.into_path());
+        }
+    }
+    Ok(paths)
+}
+
+/// Rewrites the absolute paths of the project left in the scripts built for `target` and in
+/// their source maps, e.g. by the module IDs of webpack, to relative ones.
+pub fn strip_paths(target: &Target) -> Result<()> {
+    let dir = target.package_dir()?.display().to_string();
+    let texts = artifacts(target)?.into_iter().flat_map(|path| {
+        let mut map = path.clone().into_os_string();
+        map.push(".map");
+        vec![path, PathBuf::from(map)]
+    });
+    for path in texts {
+        let is_text = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map_or(false, |ext| TEXT_EXTENSIONS.contains(&ext));
+        if !is_text || !path.is_file() {
+            continue;
+        }
+        let text = fs::read_to_string(&path)?;
+        let stripped = text
+            .replace(&format!("{}{}", dir, MAIN_SEPARATOR), "")
+            .replace(&dir, ".");
+        if stripped != text {
+            log::info!("Stripped the absolute paths of {}", path.display());
+            fs::write(&path, stripped)?;
+        }
+    }
+    Ok(())
+}
+
+/// The SHA-256 of the artifacts of `target`, each hashed with its path, like `sha256:<hex>`.
+pub fn content_hash(target: &Target) -> Result<String> {
+    let cwd = env::current_dir()?;
+    let mut hasher = Sha256::new();
+    for path in artifacts(target)? {
+        let content = fs::read(&path)?;
+        let name = path.strip_prefix(&cwd).unwrap_or(&path);
+        let name = name.strip_prefix(".").unwrap_or(name);
+        hasher.update(name.to_slash_lossy().as_bytes());
+        hasher.update(&[0]);
+        hasher.update(&(content.len() as u64).to_le_bytes());
+        hasher.update(&content);
+    }
+    Ok(format!("sha256:{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_hashes_the_artifacts_with_their_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("index.js");
+        fs::write(&script, "addEventListener('fetch', () => {});").unwrap();
+        let target = Target {
+            target_type: TargetType::JavaScript,
+            main: Some(script.clone()),
+            ..Default::default()
+        };
+
+        let hash = content_hash(&target).unwrap();
+        assert!(hash.starts_with("sha256:"));
+        assert_eq!(hash.len(), "sha256:".len() + 64);
+        assert_eq!(content_hash(&target).unwrap(), hash);
+        fs::write(&script, "addEventListener('fetch', () => {}); ").unwrap();
+        assert_ne!(content_hash(&target).unwrap(), hash);
+    }
+
+    #[test]
+    fn it_merges_the_rustflags_of_the_configuration_of_cargo() {
+        let root = tempfile::tempdir().unwrap();
+        let project = root.path().join("workspace/worker");
+        let home = root.path().join("home/.cargo");
+        for dir in &[
+            project.join(".cargo"),
+            root.path().join("workspace/.cargo"),
+            home.clone(),
+        ] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        fs::write(
+            root.path().join("workspace/.cargo/config.toml"),
+            "[build]\nrustflags = [\"-C\", \"opt-level=s\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            project.join(".cargo/config"),
+            "[build]\nrustflags = \"--cfg worker\"\n",
+        )
+        .unwrap();
+        fs::write(
+            home.join("config.toml"),
+            "[target.wasm32-wasi]\nrustflags = [\"-Clink-arg=-zstack-size=65536\"]\n",
+        )
+        .unwrap();
+
+        let files = cargo_config_files(&project, Some(home.clone()));
+        assert_eq!(
+            files,
+            vec![
+                home.join("config.toml"),
+                root.path().join("workspace/.cargo/config.toml"),
+                project.join(".cargo/config"),
+            ]
+        );
+        assert_eq!(
+            configured_rustflags(&files, "wasm32-unknown-unknown").unwrap(),
+            vec!["-C", "opt-level=s", "--cfg", "worker"]
+        );
+        assert_eq!(
+            configured_rustflags(&files, "wasm32-wasi").unwrap(),
+            vec!["-Clink-arg=-zstack-size=65536"]
+        );
+    }
+}
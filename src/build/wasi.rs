@@ -0,0 +1,298 @@
+//! Workers of WASI commands, which `[build.wasm] wasi = true` makes of the WebAssembly of a Rust
+//! project, built by cargo for wasm32-wasi, or of the `module` that the build command of a
+//! javascript project builds, e.g. with wasi-sdk. A generated worker runs the command for every
+//! request like a CGI script: the body of the request is its stdin, its stdout is the body of the
+//! response, and the request is described by its environment variables.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::Result;
+
+use crate::build::{reproducible, use_cargo_cache};
+use crate::settings::project::project_cache_dir;
+use crate::settings::toml::{Target, TargetType};
+use crate::terminal::message::{Message, StdErr};
+use crate::upload::krate::Krate;
+
+const WASI_TARGET: &str = "wasm32-wasi";
+
+/// The worker that runs the WASI command bound to `wasm`, with a WASI implementation of its own
+/// that has stdin, stdout, stderr, the environment, clocks and randomness but no files.
+const SHIM: &str = r#"// Generated by wrangler from the WASI command bound to `wasm`
+class ProcExit {
+  constructor(code) {
+    this.code = code;
+  }
+}
+
+const EBADF = 8;
+const ENOSYS = 52;
+const ESPIPE = 70;
+
+addEventListener("fetch", (event) => {
+  event.respondWith(handle(event.request));
+});
+
+async function handle(request) {
+  const stdin = new Uint8Array(await request.arrayBuffer());
+  const url = new URL(request.url);
+  const env = [
+    `REQUEST_METHOD=${request.method}`,
+    `PATH_INFO=${url.pathname}`,
+    `QUERY_STRING=${url.search.slice(1)}`,
+    ...[...request.headers].map(
+      ([name, value]) => `HTTP_${name.toUpperCase().replace(/-/g, "_")}=${value}`
+    ),
+  ];
+  const args = ["worker"];
+  const stdout = [];
+  const stderr = [];
+  let stdinOffset = 0;
+  let memory;
+
+  const view = () => new DataView(memory.buffer);
+  const bytes = () => new Uint8Array(memory.buffer);
+  const iovecs = (iovs, iovsLen) =>
+    Array.from({ length: iovsLen }, (_, i) => [
+      view().getUint32(iovs + i * 8, true),
+      view().getUint32(iovs + i * 8 + 4, true),
+    ]);
+  const encoded = (strings) => strings.map((s) => new TextEncoder().encode(`${s}\0`));
+  const sizesGet = (strings) => (countPtr, sizePtr) => {
+    const list = encoded(strings);
+    view().setUint32(countPtr, list.length, true);
+    view().setUint32(sizePtr, list.reduce((size, s) => size + s.length, 0), true);
+    return 0;
+  };
+  const stringsGet = (strings) => (ptrs, buf) => {
+    for (const s of encoded(strings)) {
+      view().setUint32(ptrs, buf, true);
+      bytes().set(s, buf);
+      ptrs += 4;
+      buf += s.length;
+    }
+    return 0;
+  };
+
+  const wasi = {
+    args_sizes_get: sizesGet(args),
+    args_get: stringsGet(args),
+    environ_sizes_get: sizesGet(env),
+    environ_get: stringsGet(env),
+    clock_res_get(id, resPtr) {
+      view().setBigUint64(resPtr, 1000000n, true);
+      return 0;
+    },
+    clock_time_get(id, precision, timePtr) {
+      view().setBigUint64(timePtr, BigInt(Date.now()) * 1000000n, true);
+      return 0;
+    },
+    random_get(buf, len) {
+      for (let offset = 0; offset < len; offset += 65536) {
+        crypto.getRandomValues(bytes().subarray(buf + offset, buf + Math.min(len, offset + 65536)));
+      }
+      return 0;
+    },
+    fd_read(fd, iovs, iovsLen, nreadPtr) {
+      if (fd !== 0) {
+        return EBADF;
+      }
+      let nread = 0;
+      for (const [ptr, len] of iovecs(iovs, iovsLen)) {
+        const chunk = stdin.subarray(stdinOffset, stdinOffset + len);
+        bytes().set(chunk, ptr);
+        stdinOffset += chunk.length;
+        nread += chunk.length;
+      }
+      view().setUint32(nreadPtr, nread, true);
+      return 0;
+    },
+    fd_write(fd, iovs, iovsLen, nwrittenPtr) {
+      const output = fd === 1 ? stdout : fd === 2 ? stderr : null;
+      if (!output) {
+        return EBADF;
+      }
+      let nwritten = 0;
+      for (const [ptr, len] of iovecs(iovs, iovsLen)) {
+        output.push(bytes().slice(ptr, ptr + len));
+        nwritten += len;
+      }
+      view().setUint32(nwrittenPtr, nwritten, true);
+      return 0;
+    },
+    fd_fdstat_get(fd, statPtr) {
+      if (fd > 2) {
+        return EBADF;
+      }
+      // a character device without flags or rights
+      bytes().fill(0, statPtr, statPtr + 24);
+      view().setUint8(statPtr, 2);
+      return 0;
+    },
+    fd_close: (fd) => (fd > 2 ? EBADF : 0),
+    fd_seek: (fd) => (fd > 2 ? EBADF : ESPIPE),
+    // there are no preopened directories
+    fd_prestat_get: () => EBADF,
+    fd_prestat_dir_name: () => EBADF,
+    sched_yield: () => 0,
+    proc_exit(code) {
+      throw new ProcExit(code);
+    },
+  };
+  const imports = {
+    wasi_snapshot_preview1: new Proxy(wasi, {
+      get: (target, name) => target[name] || (() => ENOSYS),
+    }),
+  };
+
+  const instance = new WebAssembly.Instance(wasm, imports);
+  memory = instance.exports.memory;
+  let code = 0;
+  try {
+    instance.exports._start();
+  } catch (e) {
+    if (!(e instanceof ProcExit)) {
+      throw e;
+    }
+    code = e.code;
+  }
+  if (stderr.length > 0) {
+    console.error(await new Blob(stderr).text());
+  }
+  return new Response(new Blob(stdout), { status: code === 0 ? 200 : 500 });
+}
+"#;
+
+/// Whether `[build.wasm]` of a target has `wasi = true`.
+pub fn is_enabled(target: &Target) -> bool {
+    target
+        .build
+        .as_ref()
+        .and_then(|build| build.wasm.as_ref())
+        .map_or(false, |wasm| wasm.wasi)
+}
+
+/// The WASI module of a target, if it is one.
+pub fn module(target: &Target) -> Result<Option<PathBuf>> {
+    if !is_enabled(target) {
+        return Ok(None);
+    }
+    let module = target
+        .build
+        .as_ref()
+        .and_then(|build| build.wasm.as_ref())
+        .and_then(|wasm| wasm.module.clone());
+    match (&target.target_type, module) {
+        (TargetType::Rust, None) => Ok(Some(rust_module(&Krate::for_target(target)?.name))),
+        (TargetType::Rust, Some(_)) => {
+            anyhow::bail!("`module` of [build.wasm] is not used by rust projects, whose WASI module is built by cargo")
+        }
+        (TargetType::JavaScript, Some(module)) => Ok(Some(module)),
+        (TargetType::JavaScript, None) => anyhow::bail!(
+            "Set `module` of [build.wasm] to the WASI module that your build command builds"
+        ),
+        (TargetType::Webpack, _) => {
+            anyhow::bail!("WASI modules are not supported by webpack projects")
+        }
+    }
+}
+
+/// Builds the binary of the crate of a Rust project for WASI with cargo, to where wasm-pack would
+/// build its WebAssembly so that wasm-opt and the upload find it.
+pub fn build_rust(target: &Target, krate: &Krate) -> Result<()> {
+    let mut command = Command::new("cargo");
+    command.args(&[
+        "build",
+        "--release",
+        "--target",
+        WASI_TARGET,
+        "--message-format",
+        "json-render-diagnostics",
+    ]);
+    if let Some(rust) = target.build.as_ref().and_then(|build| build.rust.as_ref()) {
+        command.args(&["--package", &rust.package]);
+    }
+    use_cargo_cache(&mut command);
+    reproducible::remap_paths(&mut command, WASI_TARGET)?;
+    // the messages of cargo are read from stdout, and the diagnostics are shown
+    command.stdout(Stdio::piped()).stderr(Stdio::inherit());
+
+    StdErr::working("Compiling your project to WebAssembly for WASI...");
+    let output = command.output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to build {} for {}. Install the target with `rustup target add {}` if it is missing",
+            krate.name,
+            WASI_TARGET,
+            WASI_TARGET
+        );
+    }
+    let built = built_module(&output.stdout, &krate.name).ok_or_else(|| {
+        anyhow::anyhow!(
+            "cargo built no binary of {} for {}",
+            krate.name,
+            WASI_TARGET
+        )
+    })?;
+    let module = rust_module(&krate.name);
+    fs::create_dir_all("./pkg")?;
+    fs::copy(&built, &module)?;
+    Ok(())
+}
+
+/// Writes the worker that runs the WASI module, which is uploaded instead of a script of the
+/// project.
+pub fn write_shim() -> Result<PathBuf> {
+    let dir = project_cache_dir().join("wasi");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join("worker.js");
+    fs::write(&path, SHIM)?;
+    Ok(path)
+}
+
+fn rust_module(crate_name: &str) -> PathBuf {
+    Path::new("./pkg").join(format!("{}_bg.wasm", crate_name.replace("-", "_")))
+}
+
+/// The binary of the crate in the messages of `cargo build`, or else the last binary built.
+fn built_module(messages: &[u8], crate_name: &str) -> Option<PathBuf> {
+    let executables: Vec<(String, PathBuf)> = String::from_utf8_lossy(messages)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter(|message| message["reason"] == "compiler-artifact")
+        .filter_map(|message| {
+            let name = message["target"]["name"].as_str()?.to_string();
+            let executable = PathBuf::from(message["executable"].as_str()?);
+            Some((name, executable))
+        })
+        .collect();
+    executables
+        .iter()
+        .find(|(name, _)| name == crate_name)
+        .or_else(|| executables.last())
+        .map(|(_, executable)| executable.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_finds_the_binary_built_by_cargo() {
+        let messages = br#"{"reason":"compiler-artifact","target":{"name":"cfg-if"},"executable":null}
+{"reason":"compiler-artifact","target":{"name":"hello-wasi"},"executable":"/target/wasm32-wasi/release/hello-wasi.wasm"}
+{"reason":"build-finished","success":true}
+"#;
+        assert_eq!(
+            built_module(messages, "hello-wasi"),
+            Some(PathBuf::from("/target/wasm32-wasi/release/hello-wasi.wasm"))
+        );
+        assert_eq!(
+            built_module(messages, "other"),
+            Some(PathBuf::from("/target/wasm32-wasi/release/hello-wasi.wasm"))
+        );
+        assert_eq!(built_module(b"", "hello-wasi"), None);
+    }
+}
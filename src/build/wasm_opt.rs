@@ -0,0 +1,42 @@
+use crate::build::command;
+use crate::commands;
+use crate::install;
+use crate::settings::toml::WasmOpt;
+use crate::terminal::message::{Message, StdErr};
+use crate::wranglerjs::output::human_size;
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+/// Optimizes the WebAssembly that wasm-pack built for the crate in the current directory with
+/// wasm-opt, in place. wasm-opt is used from the PATH if it is there, and installed otherwise.
+/// Returns a message with the size of the WebAssembly before and after.
+pub fn optimize(config: &WasmOpt, crate_name: &str) -> Result<String> {
+    let flag = config.flag()?;
+    let name = crate_name.replace("-", "_");
+    let wasm = PathBuf::from(format!("./pkg/{}_bg.wasm", name));
+    let before = fs::metadata(&wasm)?.len();
+
+    let binary_path = match which::which("wasm-opt") {
+        Ok(path) => path,
+        Err(_) => install::install_wasm_opt()?,
+    };
+    let wasm_path = wasm.to_string_lossy().to_string();
+    let args = [wasm_path.as_str(), flag.as_str(), "-o", wasm_path.as_str()];
+    let command = command(&args, &binary_path);
+    let command_name = format!("{:?}", command);
+
+    StdErr::working("Optimizing your WebAssembly with wasm-opt...");
+    commands::run(command, &command_name)?;
+    let after = fs::metadata(&wasm)?.len();
+
+    Ok(format!(
+        "wasm-opt {} shrank {} from {} to {}",
+        flag,
+        wasm.display(),
+        human_size(before),
+        human_size(after)
+    ))
+}
@@ -0,0 +1,135 @@
+//! Finds the APIs of Node.js and of browsers that the Workers runtime does not have in the
+//! scripts of a Worker, so that they fail the build instead of the Worker at runtime.
+
+use anyhow::Result;
+
+use super::lexer::{self, Token, TokenKind};
+use super::resolve::is_node_builtin;
+
+/// Globals of browsers that Workers do not have, or that the runtime forbids.
+const GLOBALS: &[&str] = &[
+    "XMLHttpRequest",
+    "importScripts",
+    "localStorage",
+    "sessionStorage",
+    "indexedDB",
+    "eval",
+];
+
+/// The code of `new Function` with which webpack and polyfills find the global object.
+const GLOBAL_SHIM: &str = "return this";
+
+/// Methods of `process` that Workers cannot run.
+const PROCESS_METHODS: &[&str] = &["exit", "abort", "chdir", "cwd", "binding", "dlopen", "kill"];
+
+/// Where a script uses an API that the Workers runtime does not have.
+#[derive(Debug, PartialEq)]
+pub struct Usage {
+    /// The API, like `fs`, `process.exit` or `XMLHttpRequest`.
+    pub api: String,
+    /// The line and column, counted from 1.
+    pub line: u32,
+    pub column: u32,
+}
+
+/// Finds the unsupported APIs used by `script`, other than those in `allow`. Code that tests
+/// whether an API exists, like `typeof XMLHttpRequest`, is not a use of it, nor is the code of
+/// polyfills and of the runtime of webpack that defines `process.cwd` and the like, or that finds
+/// the global object with `new Function("return this")` where `this` is not it.
+pub fn find_unsupported(script: &str, name: &str, allow: &[String]) -> Result<Vec<Usage>> {
+    let tokens = lexer::tokenize(script, name)?;
+    let mut usages = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let previous = i.checked_sub(1).map(|i| &tokens[i]);
+        let next = |n: usize| tokens.get(i + n);
+        let api = match token.kind {
+            TokenKind::String => {
+                let imported = previous.map_or(false, |previous| previous.is("from"))
+                    || (previous.map_or(false, |previous| previous.is("("))
+                        && i >= 2
+                        && (tokens[i - 2].is("require") || tokens[i - 2].is("import")));
+                token
+                    .string_value()
+                    .filter(|specifier| imported && is_node_builtin(specifier))
+            }
+            TokenKind::Ident if is_global(previous, next(1)) => {
+                if GLOBALS.contains(&token.text.as_str()) {
+                    Some(token.text.clone())
+                } else if token.is("Function")
+                    && previous.map_or(false, |previous| previous.is("new"))
+                {
+                    let global_shim = next(1).map_or(false, |next| next.is("("))
+                        && next(2).and_then(Token::string_value).as_deref() == Some(GLOBAL_SHIM);
+                    if global_shim {
+                        None
+                    } else {
+                        Some("new Function".to_string())
+                    }
+                } else if token.is("process") && next(1).map_or(false, |next| next.is(".")) {
+                    let defined = next(3).map_or(false, |next| next.is("="));
+                    next(2)
+                        .filter(|method| {
+                            !defined && PROCESS_METHODS.contains(&method.text.as_str())
+                        })
+                        .map(|method| format!("process.{}", method.text))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        if let (Some(api), Some(pos)) = (api, token.pos) {
+            if !allow.contains(&api) {
+                usages.push(Usage {
+                    api,
+                    line: pos.line + 1,
+                    column: pos.column + 1,
+                });
+            }
+        }
+    }
+    Ok(usages)
+}
+
+/// Whether an identifier between `previous` and `next` refers to a global, rather than to a
+/// property or to a global whose existence is tested.
+fn is_global(previous: Option<&Token>, next: Option<&Token>) -> bool {
+    let property = previous.map_or(false, |previous| {
+        previous.is(".") || previous.is("?.") || previous.is("typeof")
+    });
+    let key = next.map_or(false, |next| next.is(":"));
+    !property && !key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_finds_apis_that_workers_do_not_have() {
+        let script = "const fs = require(\"fs\");\nimport path from 'node:path';\nif (typeof XMLHttpRequest !== 'undefined') new XMLHttpRequest();\nprocess.exit(1); lib.process.exit(); process.env.DEBUG;\nconst f = new Function('return 1'), o = { eval: 1 };\n";
+        let apis: Vec<(String, u32)> = find_unsupported(script, "worker.js", &[])
+            .unwrap()
+            .into_iter()
+            .map(|usage| (usage.api, usage.line))
+            .collect();
+        assert_eq!(
+            apis,
+            vec![
+                ("fs".to_string(), 1),
+                ("node:path".to_string(), 2),
+                ("XMLHttpRequest".to_string(), 3),
+                ("process.exit".to_string(), 4),
+                ("new Function".to_string(), 5),
+            ]
+        );
+
+        let polyfills = "process.cwd = function () { return '/' };\nprocess.chdir = function (dir) {\n  throw new Error('process.chdir is not supported');\n};\ng = g || new Function(\"return this\")();\n";
+        let usages = find_unsupported(polyfills, "worker.js", &[]);
+        assert_eq!(usages.unwrap(), Vec::new());
+
+        let allow = vec!["fs".to_string(), "process.exit".to_string()];
+        let usages = find_unsupported("require('fs'); process.exit();", "worker.js", &allow);
+        assert_eq!(usages.unwrap(), Vec::new());
+    }
+}
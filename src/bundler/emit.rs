@@ -0,0 +1,377 @@
+//! Writes the modules of a bundle to a single script and its source map.
+
+use std::ops::Range;
+
+use serde_json::json;
+
+use super::lexer::{is_ident_part, Token, TokenKind};
+
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The functions shared by the modules of a bundle.
+const RUNTIME: &str = r#"function __wrangler_require(id) {
+  var cached = __wrangler_cache[id];
+  if (cached) return cached.exports;
+  var module = __wrangler_cache[id] = { exports: {} };
+  __wrangler_modules[id].call(module.exports, module, module.exports);
+  return module.exports;
+}
+function __wrangler_export(exports, getters) {
+  Object.defineProperty(exports, "__esModule", { value: true });
+  Object.keys(getters).forEach(function (name) {
+    Object.defineProperty(exports, name, { get: getters[name], enumerable: true });
+  });
+}
+function __wrangler_reexport(exports, from) {
+  Object.keys(from).forEach(function (name) {
+    if (name !== "default" && !Object.prototype.hasOwnProperty.call(exports, name)) {
+      Object.defineProperty(exports, name, { get: function () { return from[name]; }, enumerable: true });
+    }
+  });
+}
+function __wrangler_interop(exports) {
+  return exports && exports.__esModule ? exports : Object.assign(Object.create(null), exports, { default: exports });
+}
+"#;
+
+/// The runtime module of a bundle split into chunks, with which each chunk registers its modules.
+const RUNTIME_MODULE: &str = r#"var __wrangler_modules = {};
+var __wrangler_cache = {};
+function __wrangler_define(modules) {
+  Object.assign(__wrangler_modules, modules);
+}
+"#;
+
+/// The names exported by [`RUNTIME_MODULE`], which every chunk imports.
+pub const RUNTIME_EXPORTS: &str = "__wrangler_define, __wrangler_require, __wrangler_export, __wrangler_reexport, __wrangler_interop";
+
+/// A module of the bundle.
+pub struct Source {
+    /// The path of the module in the source map.
+    pub name: String,
+    pub content: String,
+    pub tokens: Vec<Token>,
+}
+
+/// A bundle written by [`Emitter::emit`].
+#[derive(Debug)]
+pub struct Emitted {
+    pub script: String,
+    pub source_map: String,
+    /// The name of each source and the part of the script that its code became.
+    pub modules: Vec<(String, Range<usize>)>,
+}
+
+pub struct Emitter {
+    minify: bool,
+    /// Where the sources are, relative to the script.
+    source_root: String,
+    code: String,
+    /// The column in UTF-16 code units, as source maps count them.
+    column: u32,
+    mappings: String,
+    /// The last mapping, which the fields of the next one are relative to.
+    previous: Mapping,
+    /// The column of the last mapping on the current line.
+    previous_column: u32,
+    last: Option<Token>,
+}
+
+#[derive(Default)]
+struct Mapping {
+    source: i64,
+    line: i64,
+    column: i64,
+}
+
+impl Emitter {
+    pub fn new(minify: bool) -> Self {
+        Emitter {
+            minify,
+            source_root: "..".to_string(),
+            code: String::new(),
+            column: 0,
+            mappings: String::new(),
+            previous: Mapping::default(),
+            previous_column: 0,
+            last: None,
+        }
+    }
+
+    pub fn with_source_root(mut self, source_root: &str) -> Self {
+        self.source_root = source_root.to_string();
+        self
+    }
+
+    /// Emits the bundle, which runs the module at index 0.
+    pub fn emit(mut self, sources: &[Source], file: &str) -> Emitted {
+        self.write_code("(() => {\nvar global = globalThis;\nvar __wrangler_cache = {};\n");
+        self.write_code(RUNTIME);
+        self.write_code("var __wrangler_modules = {\n");
+        let modules = self.write_modules(sources.iter().enumerate());
+        self.write_code("};\n__wrangler_require(0);\n})();\n");
+        self.finish(sources.iter(), modules, file)
+    }
+
+    /// Emits a chunk of a split bundle, an ES module registering the sources with the runtime
+    /// module by their IDs, between `prelude` and `epilogue`.
+    pub fn emit_chunk(
+        mut self,
+        sources: &[(usize, Source)],
+        prelude: &str,
+        epilogue: &str,
+        file: &str,
+    ) -> Emitted {
+        self.write_code(prelude);
+        self.write_code("var global = globalThis;\n__wrangler_define({\n");
+        let modules = self.write_modules(sources.iter().map(|(id, source)| (*id, source)));
+        self.write_code("});\n");
+        self.write_code(epilogue);
+        self.finish(sources.iter().map(|(_, source)| source), modules, file)
+    }
+
+    /// Emits the runtime module of a split bundle.
+    pub fn emit_runtime(mut self, file: &str) -> Emitted {
+        self.write_code(RUNTIME_MODULE);
+        self.write_code(RUNTIME);
+        self.write_code(&format!("export {{ {} }};\n", RUNTIME_EXPORTS));
+        self.finish(std::iter::empty::<&Source>(), Vec::new(), file)
+    }
+
+    /// Writes each source as a property of an object, its function keyed by its ID.
+    fn write_modules<'a>(
+        &mut self,
+        sources: impl Iterator<Item = (usize, &'a Source)>,
+    ) -> Vec<(String, Range<usize>)> {
+        let mut modules = Vec::new();
+        for (index, (id, source)) in sources.enumerate() {
+            if !self.minify {
+                self.write_code(&format!("// {}\n", source.name));
+            }
+            self.write_code(&format!("{}: function (module, exports) {{\n", id));
+            let start = self.code.len();
+            for token in &source.tokens {
+                self.write_token(token, index);
+            }
+            modules.push((source.name.clone(), start..self.code.len()));
+            self.write_code("\n},\n");
+        }
+        modules
+    }
+
+    fn finish<'a>(
+        mut self,
+        sources: impl Iterator<Item = &'a Source> + Clone,
+        modules: Vec<(String, Range<usize>)>,
+        file: &str,
+    ) -> Emitted {
+        self.write_code(&format!("//# sourceMappingURL={}.map\n", file));
+
+        let source_map = json!({
+            "version": 3,
+            "file": file,
+            "sourceRoot": self.source_root,
+            "sources": sources.clone().map(|source| &source.name).collect::<Vec<_>>(),
+            "sourcesContent": sources.map(|source| &source.content).collect::<Vec<_>>(),
+            "names": [],
+            "mappings": self.mappings,
+        });
+        Emitted {
+            script: self.code,
+            source_map: source_map.to_string(),
+            modules,
+        }
+    }
+
+    /// Writes code added by the bundler, which maps to no source.
+    fn write_code(&mut self, code: &str) {
+        if self.minify {
+            for line in code.lines().map(str::trim).filter(|line| !line.is_empty()) {
+                if line.starts_with("//") && !line.starts_with("//#") {
+                    continue;
+                }
+                self.write(line);
+                self.write("\n");
+            }
+        } else {
+            self.write(code);
+        }
+        self.last = None;
+    }
+
+    fn write_token(&mut self, token: &Token, source: usize) {
+        if self.minify {
+            let separator = self.separator(token);
+            self.write(separator);
+        } else {
+            self.write(&token.leading);
+        }
+        if let Some(pos) = token.pos {
+            self.add_mapping(source, pos.line, pos.column);
+        }
+        self.write(&token.text);
+        self.last = Some(token.clone());
+    }
+
+    /// The least whitespace that keeps the meaning of the code between the last token and the
+    /// next one when minifying.
+    fn separator(&self, token: &Token) -> &'static str {
+        let last = match &self.last {
+            Some(last) => last,
+            None => return "",
+        };
+        if token.newline_before() && ends_expression(last) && may_start_statement(token) {
+            return "\n";
+        }
+        let (end, start) = match (last.text.chars().last(), token.text.chars().next()) {
+            (Some(end), Some(start)) => (end, start),
+            _ => return "",
+        };
+        let joins = (is_ident_part(end) && is_ident_part(start))
+            || (end == '+' && start == '+')
+            || (end == '-' && start == '-')
+            || (end == '/' && (start == '/' || start == '*'))
+            || (last.kind == TokenKind::Number && start == '.');
+        if joins {
+            " "
+        } else {
+            ""
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        for c in text.chars() {
+            if c == '\n' {
+                self.column = 0;
+                self.previous_column = 0;
+                self.mappings.push(';');
+            } else {
+                self.column += c.len_utf16() as u32;
+            }
+        }
+        self.code.push_str(text);
+    }
+
+    fn add_mapping(&mut self, source: usize, line: u32, column: u32) {
+        let mapping = Mapping {
+            source: source as i64,
+            line: i64::from(line),
+            column: i64::from(column),
+        };
+        if !self.mappings.is_empty() && !self.mappings.ends_with(';') {
+            self.mappings.push(',');
+        }
+        encode_vlq(
+            &mut self.mappings,
+            i64::from(self.column) - i64::from(self.previous_column),
+        );
+        encode_vlq(&mut self.mappings, mapping.source - self.previous.source);
+        encode_vlq(&mut self.mappings, mapping.line - self.previous.line);
+        encode_vlq(&mut self.mappings, mapping.column - self.previous.column);
+        self.previous = mapping;
+        self.previous_column = self.column;
+    }
+}
+
+/// Whether automatic semicolon insertion may end a statement after the token.
+fn ends_expression(token: &Token) -> bool {
+    match token.kind {
+        TokenKind::Punct => matches!(token.text.as_str(), ")" | "]" | "}" | "++" | "--"),
+        TokenKind::Template => token.text.ends_with('`'),
+        _ => true,
+    }
+}
+
+fn may_start_statement(token: &Token) -> bool {
+    match token.kind {
+        TokenKind::Punct => matches!(token.text.as_str(), "{" | "!" | "~" | "++" | "--"),
+        _ => true,
+    }
+}
+
+/// Appends a field of a mapping in base64 VLQ.
+fn encode_vlq(out: &mut String, value: i64) {
+    let mut vlq = if value < 0 {
+        ((-value) << 1) | 1
+    } else {
+        value << 1
+    };
+    loop {
+        let mut digit = vlq & 0b11111;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 0b100000;
+        }
+        out.push(BASE64[digit as usize] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bundler::lexer::tokenize;
+
+    fn vlq(value: i64) -> String {
+        let mut out = String::new();
+        encode_vlq(&mut out, value);
+        out
+    }
+
+    #[test]
+    fn it_encodes_vlq() {
+        assert_eq!(vlq(0), "A");
+        assert_eq!(vlq(1), "C");
+        assert_eq!(vlq(-1), "D");
+        assert_eq!(vlq(16), "gB");
+        assert_eq!(vlq(-123), "3H");
+    }
+
+    #[test]
+    fn it_minifies_without_changing_the_meaning() {
+        let source = "let a = b + +c\nreturn\na\n/* x */ x = 1 / /re/.source.length; 1 .toString()";
+        let mut emitter = Emitter::new(true);
+        for token in tokenize(source, "test.js").unwrap() {
+            emitter.write_token(&token, 0);
+        }
+        assert_eq!(
+            emitter.code,
+            "let a=b+ +c\nreturn\na\nx=1/ /re/.source.length;1 .toString()"
+        );
+    }
+
+    #[test]
+    fn it_maps_tokens_to_their_sources() {
+        let source = "const a = 1;\nexport { a };";
+        let emitter = Emitter::new(false);
+        let Emitted {
+            script: code,
+            source_map: map,
+            modules,
+        } = emitter.emit(
+            &[Source {
+                name: "src/index.js".to_string(),
+                content: source.to_string(),
+                tokens: tokenize(source, "src/index.js").unwrap(),
+            }],
+            "script.js",
+        );
+        assert!(code.starts_with("(() => {\nvar global = globalThis;"));
+        assert!(code.contains("// src/index.js\n0: function (module, exports) {\nconst a = 1;"));
+        assert_eq!(&code[modules[0].1.clone()], source);
+        let map: serde_json::Value = serde_json::from_str(&map).unwrap();
+        assert_eq!(map["sources"], json!(["src/index.js"]));
+        let mappings = map["mappings"].as_str().unwrap();
+        let line = code
+            .lines()
+            .position(|line| line == "const a = 1;")
+            .unwrap();
+        assert_eq!(
+            mappings.split(';').nth(line),
+            Some("AAAA,MAAM,EAAE,EAAE,CAAC")
+        );
+    }
+}
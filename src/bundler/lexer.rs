@@ -0,0 +1,531 @@
+use anyhow::Result;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Identifiers, keywords and private names like `#count`.
+    Ident,
+    Punct,
+    Number,
+    String,
+    /// A template literal, or a part of one that is delimited by substitutions, e.g. `` `a${ ``,
+    /// `}b${` and `` }c` ``.
+    Template,
+    Regex,
+}
+
+/// Where a token starts in its source file, counted from 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pos {
+    pub line: u32,
+    pub column: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    /// The whitespace and comments preceding the token.
+    pub leading: String,
+    /// `None` for tokens added by the bundler.
+    pub pos: Option<Pos>,
+}
+
+impl Token {
+    /// A token that does not come from a source file.
+    pub fn new(kind: TokenKind, text: &str) -> Self {
+        Token {
+            kind,
+            text: text.to_string(),
+            leading: String::new(),
+            pos: None,
+        }
+    }
+
+    pub fn ident(text: &str) -> Self {
+        Token::new(TokenKind::Ident, text)
+    }
+
+    pub fn punct(text: &str) -> Self {
+        Token::new(TokenKind::Punct, text)
+    }
+
+    pub fn string(value: &str) -> Self {
+        Token::new(
+            TokenKind::String,
+            &serde_json::to_string(value).expect("strings can always be serialized"),
+        )
+    }
+
+    pub fn number(value: usize) -> Self {
+        Token::new(TokenKind::Number, &value.to_string())
+    }
+
+    /// Sets the whitespace before a token, e.g. to separate added tokens.
+    pub fn with_leading(mut self, leading: &str) -> Self {
+        self.leading = leading.to_string();
+        self
+    }
+
+    pub fn is(&self, text: &str) -> bool {
+        matches!(self.kind, TokenKind::Ident | TokenKind::Punct) && self.text == text
+    }
+
+    pub fn is_ident(&self) -> bool {
+        self.kind == TokenKind::Ident
+    }
+
+    /// Whether a line break separates this token from the previous one.
+    pub fn newline_before(&self) -> bool {
+        self.leading.contains(|c| c == '\n' || c == '\r')
+    }
+
+    /// Whether the token opens a bracket that a later token closes.
+    pub fn opens(&self) -> bool {
+        match self.kind {
+            TokenKind::Punct => matches!(self.text.as_str(), "(" | "[" | "{"),
+            TokenKind::Template => self.text.ends_with("${"),
+            _ => false,
+        }
+    }
+
+    /// Whether the token closes a bracket opened by an earlier token.
+    pub fn closes(&self) -> bool {
+        match self.kind {
+            TokenKind::Punct => matches!(self.text.as_str(), ")" | "]" | "}"),
+            TokenKind::Template => self.text.starts_with('}'),
+            _ => false,
+        }
+    }
+
+    /// The value of a string literal, for the specifiers of imports.
+    pub fn string_value(&self) -> Option<String> {
+        if self.kind != TokenKind::String {
+            return None;
+        }
+        let inner = &self.text[1..self.text.len() - 1];
+        let mut value = String::new();
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                value.push(c);
+                continue;
+            }
+            match chars.next()? {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                'r' => value.push('\r'),
+                '0' => value.push('\0'),
+                '\n' => {}
+                c => value.push(c),
+            }
+        }
+        Some(value)
+    }
+}
+
+/// Words after which a `/` starts a regular expression rather than a division.
+const REGEX_KEYWORDS: &[&str] = &[
+    "return",
+    "typeof",
+    "instanceof",
+    "in",
+    "of",
+    "new",
+    "delete",
+    "void",
+    "throw",
+    "case",
+    "do",
+    "else",
+    "yield",
+    "await",
+];
+
+/// Punctuators, longest first so that the longest one matching is picked.
+const PUNCTUATORS: &[&str] = &[
+    ">>>=", "...", "===", "!==", "**=", "<<=", ">>=", ">>>", "&&=", "||=", "??=", "=>", "==", "!=",
+    "<=", ">=", "&&", "||", "??", "?.", "++", "--", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=",
+    "**", "<<", ">>", "{", "}", "(", ")", "[", "]", ";", ",", "<", ">", "+", "-", "*", "/", "%",
+    "&", "|", "^", "!", "~", "?", ":", "=", ".", "@",
+];
+
+/// Splits JavaScript or TypeScript source code into tokens. `name` is only used in errors.
+pub fn tokenize(source: &str, name: &str) -> Result<Vec<Token>> {
+    Lexer {
+        chars: source.chars().collect(),
+        index: 0,
+        line: 0,
+        column: 0,
+        name,
+    }
+    .run()
+}
+
+struct Lexer<'a> {
+    chars: Vec<char>,
+    index: usize,
+    line: u32,
+    column: u32,
+    name: &'a str,
+}
+
+impl<'a> Lexer<'a> {
+    fn run(mut self) -> Result<Vec<Token>> {
+        let mut tokens: Vec<Token> = Vec::new();
+        // The depth of braces when each enclosing template substitution was opened
+        let mut templates: Vec<usize> = Vec::new();
+        let mut braces = 0;
+
+        loop {
+            let leading = self.trivia(tokens.is_empty())?;
+            let pos = Pos {
+                line: self.line,
+                column: self.column,
+            };
+            let c = match self.peek(0) {
+                Some(c) => c,
+                None => {
+                    anyhow::ensure!(
+                        templates.is_empty(),
+                        "{}: unterminated template literal",
+                        self.name
+                    );
+                    return Ok(tokens);
+                }
+            };
+            let start = self.index;
+
+            let kind = if c == '`' {
+                self.advance();
+                self.template()?
+            } else if c == '}' && templates.last() == Some(&braces) {
+                templates.pop();
+                self.advance();
+                self.template()?
+            } else if c == '"' || c == '\'' {
+                self.string(c, pos)?;
+                TokenKind::String
+            } else if c.is_ascii_digit()
+                || (c == '.' && self.peek(1).map_or(false, |c| c.is_ascii_digit()))
+            {
+                self.number();
+                TokenKind::Number
+            } else if is_ident_start(c) || c == '#' || c == '\\' {
+                self.advance();
+                while self.peek(0).map_or(false, is_ident_part) {
+                    self.advance();
+                }
+                TokenKind::Ident
+            } else if c == '/' && regex_allowed(tokens.last()) {
+                self.regex(pos)?;
+                TokenKind::Regex
+            } else {
+                let punct = PUNCTUATORS
+                    .iter()
+                    .find(|punct| {
+                        punct.chars().enumerate().all(|(i, p)| self.peek(i) == Some(p))
+                            // `a?.5:b` is a conditional
+                            && !(**punct == "?."
+                                && self.peek(2).map_or(false, |c| c.is_ascii_digit()))
+                    })
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "{}:{}:{}: unexpected character {:?}",
+                            self.name,
+                            pos.line + 1,
+                            pos.column + 1,
+                            c
+                        )
+                    })?;
+                for _ in 0..punct.chars().count() {
+                    self.advance();
+                }
+                match *punct {
+                    "{" => braces += 1,
+                    "}" => braces = braces.saturating_sub(1),
+                    _ => {}
+                }
+                TokenKind::Punct
+            };
+
+            let text = self.chars[start..self.index].iter().collect::<String>();
+            if kind == TokenKind::Template && text.ends_with("${") {
+                templates.push(braces);
+            }
+            tokens.push(Token {
+                kind,
+                text,
+                leading,
+                pos: Some(pos),
+            });
+        }
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.index + offset).copied()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek(0)?;
+        self.index += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 0;
+        } else {
+            // Source maps count columns in UTF-16 code units
+            self.column += c.len_utf16() as u32;
+        }
+        Some(c)
+    }
+
+    fn error(&self, pos: Pos, message: &str) -> anyhow::Error {
+        anyhow::anyhow!(
+            "{}:{}:{}: {}",
+            self.name,
+            pos.line + 1,
+            pos.column + 1,
+            message
+        )
+    }
+
+    /// Consumes whitespace and comments, including a hashbang at the start of the file.
+    fn trivia(&mut self, at_start: bool) -> Result<String> {
+        let start = self.index;
+        if at_start && self.peek(0) == Some('#') && self.peek(1) == Some('!') {
+            while self.peek(0).map_or(false, |c| c != '\n') {
+                self.advance();
+            }
+        }
+        loop {
+            match (self.peek(0), self.peek(1)) {
+                (Some(c), _) if c.is_whitespace() || c == '\u{feff}' => {
+                    self.advance();
+                }
+                (Some('/'), Some('/')) => {
+                    while self.peek(0).map_or(false, |c| c != '\n') {
+                        self.advance();
+                    }
+                }
+                (Some('/'), Some('*')) => {
+                    let pos = Pos {
+                        line: self.line,
+                        column: self.column,
+                    };
+                    self.advance();
+                    self.advance();
+                    loop {
+                        match (self.peek(0), self.peek(1)) {
+                            (Some('*'), Some('/')) => {
+                                self.advance();
+                                self.advance();
+                                break;
+                            }
+                            (Some(_), _) => {
+                                self.advance();
+                            }
+                            (None, _) => return Err(self.error(pos, "unterminated comment")),
+                        }
+                    }
+                }
+                _ => break,
+            }
+        }
+        Ok(self.chars[start..self.index].iter().collect())
+    }
+
+    fn string(&mut self, quote: char, pos: Pos) -> Result<()> {
+        self.advance();
+        loop {
+            match self.advance() {
+                Some('\\') => {
+                    self.advance();
+                }
+                Some(c) if c == quote => return Ok(()),
+                Some('\n') | None => return Err(self.error(pos, "unterminated string literal")),
+                Some(_) => {}
+            }
+        }
+    }
+
+    /// Consumes the rest of a template literal, or of its part up to the next substitution.
+    fn template(&mut self) -> Result<TokenKind> {
+        let pos = Pos {
+            line: self.line,
+            column: self.column,
+        };
+        loop {
+            match self.advance() {
+                Some('\\') => {
+                    self.advance();
+                }
+                Some('`') => return Ok(TokenKind::Template),
+                Some('$') if self.peek(0) == Some('{') => {
+                    self.advance();
+                    return Ok(TokenKind::Template);
+                }
+                Some(_) => {}
+                None => return Err(self.error(pos, "unterminated template literal")),
+            }
+        }
+    }
+
+    fn number(&mut self) {
+        let hex = self.peek(0) == Some('0') && matches!(self.peek(1), Some('x') | Some('X'));
+        while let Some(c) = self.peek(0) {
+            if !hex && (c == 'e' || c == 'E') && matches!(self.peek(1), Some('+') | Some('-')) {
+                self.advance();
+                self.advance();
+            } else if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn regex(&mut self, pos: Pos) -> Result<()> {
+        self.advance();
+        let mut in_class = false;
+        loop {
+            match self.advance() {
+                Some('\\') => {
+                    self.advance();
+                }
+                Some('[') => in_class = true,
+                Some(']') => in_class = false,
+                Some('/') if !in_class => break,
+                Some('\n') | None => return Err(self.error(pos, "unterminated regular expression")),
+                Some(_) => {}
+            }
+        }
+        // flags
+        while self.peek(0).map_or(false, is_ident_part) {
+            self.advance();
+        }
+        Ok(())
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '$'
+}
+
+pub fn is_ident_part(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$' || c == '\\' || c == '\u{200c}' || c == '\u{200d}'
+}
+
+/// Whether a `/` after this token starts a regular expression.
+fn regex_allowed(previous: Option<&Token>) -> bool {
+    match previous {
+        None => true,
+        Some(token) => match token.kind {
+            TokenKind::Ident => REGEX_KEYWORDS.contains(&token.text.as_str()),
+            TokenKind::Punct => !matches!(token.text.as_str(), ")" | "]" | "}"),
+            TokenKind::Template => token.text.ends_with("${"),
+            _ => false,
+        },
+    }
+}
+
+/// Finds the index of the token closing each bracket, including template substitutions.
+pub fn match_brackets(tokens: &[Token]) -> Vec<Option<usize>> {
+    let mut matching = vec![None; tokens.len()];
+    let mut open = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if token.closes() {
+            if let Some(start) = open.pop() {
+                matching[start] = Some(i);
+                matching[i] = Some(start);
+            }
+        }
+        if token.opens() {
+            open.push(i);
+        }
+    }
+    matching
+}
+
+/// Whether an expression may start after this token, which tells an object literal from a
+/// block, among other things.
+pub fn starts_expression(previous: Option<&Token>) -> bool {
+    match previous {
+        None => false,
+        Some(token) => match token.kind {
+            TokenKind::Ident => matches!(
+                token.text.as_str(),
+                "return"
+                    | "typeof"
+                    | "instanceof"
+                    | "in"
+                    | "of"
+                    | "new"
+                    | "delete"
+                    | "void"
+                    | "throw"
+                    | "case"
+                    | "yield"
+                    | "await"
+                    | "default"
+            ),
+            TokenKind::Punct => !matches!(token.text.as_str(), ")" | "]" | "}" | ";" | "++" | "--"),
+            TokenKind::Template => token.text.ends_with("${"),
+            _ => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(source: &str) -> Vec<String> {
+        tokenize(source, "test.js")
+            .unwrap()
+            .into_iter()
+            .map(|token| token.text)
+            .collect()
+    }
+
+    #[test]
+    fn it_tokenizes_javascript() {
+        assert_eq!(
+            texts("const a = b / 2 / c; // half\nconst re = /[/]+/g;"),
+            vec![
+                "const", "a", "=", "b", "/", "2", "/", "c", ";", "const", "re", "=", "/[/]+/g", ";"
+            ]
+        );
+        assert_eq!(
+            texts("`a${ {b: `c${d}`}.b }e` + x?.y ?? 1.5e-3"),
+            vec![
+                "`a${", "{", "b", ":", "`c${", "d", "}`", "}", ".", "b", "}e`", "+", "x", "?.",
+                "y", "??", "1.5e-3"
+            ]
+        );
+    }
+
+    #[test]
+    fn it_keeps_comments_in_the_leading_trivia() {
+        let tokens = tokenize("#!/usr/bin/env node\n/* a */ x\n// b\ny", "test.js").unwrap();
+        assert_eq!(tokens[0].text, "x");
+        assert_eq!(tokens[0].leading, "#!/usr/bin/env node\n/* a */ ");
+        assert_eq!(tokens[0].pos, Some(Pos { line: 1, column: 8 }));
+        assert_eq!(tokens[1].leading, "\n// b\n");
+        assert!(tokens[1].newline_before());
+    }
+
+    #[test]
+    fn it_fails_on_unterminated_literals() {
+        assert!(tokenize("'abc", "test.js").is_err());
+        assert!(tokenize("`abc${d}", "test.js").is_err());
+        assert!(tokenize("/* abc", "test.js").is_err());
+    }
+
+    #[test]
+    fn it_matches_brackets() {
+        let tokens = tokenize("f(`${[a]}`)", "test.js").unwrap();
+        let matching = match_brackets(&tokens);
+        assert_eq!(matching[1], Some(7));
+        assert_eq!(matching[2], Some(6));
+        assert_eq!(matching[3], Some(5));
+    }
+}
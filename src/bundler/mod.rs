@@ -0,0 +1,450 @@
+//! A bundler built into wrangler, used instead of webpack with `bundler = "builtin"`.
+//!
+//! Like esbuild, it needs neither node nor npm: TypeScript is transpiled by stripping its types,
+//! each module becomes a function of a single script, and a source map is written next to the
+//! script. Imports whose bindings are never used are left out of the bundle when the imported
+//! module has no side effects, which is tree shaking at the level of modules.
+//!
+//! It also transpiles the TypeScript entry points of `type = "javascript"` projects that have no
+//! build command, so that such Workers need no build setup at all.
+
+mod compat;
+mod emit;
+mod lexer;
+mod module;
+mod resolve;
+mod split;
+mod typescript;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+
+use crate::settings::toml::{Target, TargetType, UploadFormat};
+use crate::terminal::message::{Message, StdErr};
+use crate::wranglerjs::output::{ModuleSize, WranglerjsOutput};
+use crate::wranglerjs::Bundle;
+
+pub use compat::{find_unsupported, Usage};
+pub use emit::Emitted;
+use emit::{Emitter, Source};
+use module::{Link, Module};
+use resolve::Resolver;
+pub use split::MAIN_MODULE;
+
+/// Extensions of the files that are TypeScript, whose unused imports are always dropped as
+/// `tsc` does.
+const TYPESCRIPT_EXTENSIONS: &[&str] = &["ts", "mts", "cts"];
+
+/// Bundles the entry point of a webpack project, writing the script and its source map where
+/// webpack would write them. `defines` are the expanded values of `[build.define]`.
+pub fn build(target: &Target, defines: &[(String, String)]) -> Result<String> {
+    let output = run_build(target, defines)?;
+    Ok(format!(
+        "Built successfully with the builtin bundler, built project size is {}",
+        output.project_size()
+    ))
+}
+
+/// Like [`build`], returning the bundle written as `wranglerjs::run_build` does.
+pub fn run_build(target: &Target, defines: &[(String, String)]) -> Result<WranglerjsOutput> {
+    anyhow::ensure!(
+        !target.node_compat,
+        "node_compat is not supported by the builtin bundler; set `bundler = \"webpack\"` to bundle polyfills of Node.js modules"
+    );
+    if target.webpack_config.is_some() {
+        StdErr::warn("webpack_config is ignored by the builtin bundler");
+    }
+    let package_dir = target.package_dir()?;
+    if let Some(site) = &target.site {
+        site.scaffold_worker()?;
+    }
+    let start = Instant::now();
+    let bundle = Bundle::new(&package_dir);
+    if let Some(entries) = &target.entries {
+        let files = split::bundle_modules(entries, &package_dir, target.minify, defines)?;
+        log::info!(
+            "Bundled {} handlers in {:?}",
+            entries.len(),
+            start.elapsed()
+        );
+        return write_modules(&bundle, files);
+    }
+
+    let entry = target.entry_point()?;
+    let Emitted {
+        script,
+        source_map,
+        modules,
+    } = bundle(&entry, &package_dir, target.minify, defines)?;
+    log::info!("Bundled {} in {:?}", entry.display(), start.elapsed());
+
+    // A module.wasm left by an earlier webpack build would be bound to the script otherwise.
+    if bundle.has_wasm() {
+        fs::remove_file(bundle.wasm_path())?;
+    }
+    let modules = modules
+        .into_iter()
+        .map(|(name, span)| ModuleSize::new(name, script[span].as_bytes()))
+        .collect();
+    let output = WranglerjsOutput {
+        wasm: None,
+        script,
+        errors: Vec::new(),
+        polyfills: Vec::new(),
+        modules,
+    };
+    bundle.write(&output)?;
+    fs::write(source_map_path(&bundle.script_path()), source_map)?;
+    Ok(output)
+}
+
+/// Writes the files of a split bundle, and their source maps, to the modules directory of the
+/// bundle, which is uploaded in the modules format.
+fn write_modules(bundle: &Bundle, files: Vec<(String, Emitted)>) -> Result<WranglerjsOutput> {
+    let dir = bundle.modules_dir();
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    fs::create_dir_all(&dir)?;
+
+    let mut script = String::new();
+    let mut modules = Vec::new();
+    for (file, emitted) in files {
+        modules.extend(
+            emitted
+                .modules
+                .into_iter()
+                .map(|(name, span)| ModuleSize::new(name, emitted.script[span].as_bytes())),
+        );
+        let path = dir.join(&file);
+        fs::write(&path, &emitted.script)?;
+        fs::write(source_map_path(&path), emitted.source_map)?;
+        script.push_str(&emitted.script);
+    }
+    Ok(WranglerjsOutput {
+        wasm: None,
+        script,
+        errors: Vec::new(),
+        polyfills: Vec::new(),
+        modules,
+    })
+}
+
+/// Whether a `type = "javascript"` project is a TypeScript Worker without a build of its own,
+/// which [`transpile`] builds.
+pub fn transpiles(target: &Target) -> bool {
+    let custom_build = target.build.as_ref().map_or(false, |build| {
+        build.command.is_some() || !matches!(build.upload, UploadFormat::ServiceWorker {})
+    });
+    target.target_type == TargetType::JavaScript
+        && !custom_build
+        && target
+            .entry_point()
+            .map_or(false, |entry| has_extension(&entry, TYPESCRIPT_EXTENSIONS))
+}
+
+/// Transpiles the TypeScript entry point of a `type = "javascript"` project, and what it imports,
+/// to the script returned by [`script_path`].
+pub fn transpile(target: &Target) -> Result<String> {
+    let package_dir = target.package_dir()?;
+    let entry = target.entry_point()?;
+    let Emitted {
+        script, source_map, ..
+    } = bundle(&entry, &package_dir, target.minify, &[])?;
+
+    let script_path = Bundle::new(&package_dir).script_path();
+    if let Some(dir) = script_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&script_path, script)?;
+    fs::write(source_map_path(&script_path), source_map)?;
+    Ok(format!(
+        "Transpiled {} to {}",
+        entry.display(),
+        script_path.display()
+    ))
+}
+
+/// The script uploaded for a `type = "javascript"` project: its entry point, or the script that
+/// it was transpiled to.
+pub fn script_path(target: &Target) -> Result<PathBuf> {
+    if transpiles(target) {
+        Ok(Bundle::new(&target.package_dir()?).script_path())
+    } else {
+        target.entry_point()
+    }
+}
+
+/// Bundles the module at `entry` and everything it imports into a script with a source map.
+/// Sources are named relative to `root` in the source map, and imports are resolved with the
+/// path aliases of the tsconfig.json in `root`. `defines` are expressions replaced by code, as
+/// with `[build.define]`.
+pub fn bundle(
+    entry: &Path,
+    root: &Path,
+    minify: bool,
+    defines: &[(String, String)],
+) -> Result<Emitted> {
+    let graph = Graph::load(&[entry.to_path_buf()], root, defines)?;
+    if graph.modules[0].0.has_exports() {
+        log::warn!(
+            "The exports of {} are ignored, since the builtin bundler builds Workers in the service worker format",
+            entry.display()
+        );
+    }
+
+    let sources = graph.into_sources(root, None);
+    log::info!("Bundled {} modules", sources.len());
+
+    Ok(Emitter::new(minify).emit(&sources, "script.js"))
+}
+
+/// The modules imported by the entry points of a bundle, by ID.
+struct Graph {
+    paths: Vec<PathBuf>,
+    /// Each module with its content, and the ID of the module linked to each of its dependencies.
+    modules: Vec<(Module, String, Vec<Option<usize>>)>,
+    /// The ID of each entry point, in the order that they were given.
+    entries: Vec<usize>,
+}
+
+impl Graph {
+    /// Loads the entry points and everything they import, resolved with the path aliases of the
+    /// tsconfig.json in `root`.
+    fn load(entries: &[PathBuf], root: &Path, defines: &[(String, String)]) -> Result<Graph> {
+        let mut resolver = Resolver::new();
+        resolver.read_tsconfig(root)?;
+        let mut paths = Vec::new();
+        let mut ids = HashMap::new();
+        let entries = entries
+            .iter()
+            .map(|entry| {
+                let next_id = paths.len();
+                let id = *ids.entry(canonical(entry)).or_insert(next_id);
+                if id == next_id {
+                    paths.push(entry.clone());
+                }
+                id
+            })
+            .collect();
+        let mut modules = Vec::new();
+
+        let mut id = 0;
+        while id < paths.len() {
+            let path = paths[id].clone();
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Could not read {}", path.display()))?;
+            let module = load(&path, &content, defines)?;
+            let typescript = has_extension(&path, TYPESCRIPT_EXTENSIONS);
+
+            let mut links = Vec::with_capacity(module.dependencies.len());
+            for dependency in &module.dependencies {
+                let resolved = resolver.resolve(&dependency.specifier, &path)?;
+                if dependency.unused() && (typescript || resolver.side_effects_free(&resolved)) {
+                    log::debug!(
+                        "Dropping the unused import of {} from {}",
+                        dependency.specifier,
+                        path.display()
+                    );
+                    links.push(None);
+                    continue;
+                }
+                let next_id = paths.len();
+                let linked = *ids.entry(canonical(&resolved)).or_insert(next_id);
+                if linked == next_id {
+                    paths.push(resolved);
+                }
+                links.push(Some(linked));
+            }
+            modules.push((module, content, links));
+            id += 1;
+        }
+        Ok(Graph {
+            paths,
+            modules,
+            entries,
+        })
+    }
+
+    /// Renders the modules, named relative to `root`. When the bundle is split, `chunks` has the
+    /// chunk of each module, which `import()` loads when it imports a module of another chunk.
+    fn into_sources(self, root: &Path, chunks: Option<&[Option<usize>]>) -> Vec<Source> {
+        let esm: Vec<bool> = self
+            .modules
+            .iter()
+            .map(|(module, _, _)| module.esm)
+            .collect();
+        self.modules
+            .into_iter()
+            .zip(&self.paths)
+            .enumerate()
+            .map(|(importer, ((module, content, links), path))| {
+                let chunk = |id: usize| {
+                    chunks.and_then(|chunks| chunks[id].filter(|_| chunks[id] != chunks[importer]))
+                };
+                let links: Vec<Option<Link>> = links
+                    .into_iter()
+                    .map(|id| {
+                        id.map(|id| Link {
+                            id,
+                            esm: esm[id],
+                            chunk: chunk(id),
+                        })
+                    })
+                    .collect();
+                Source {
+                    name: source_name(path, root),
+                    content,
+                    tokens: module.render(&links),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Parses a file to a module, transpiling TypeScript.
+fn load(path: &Path, content: &str, defines: &[(String, String)]) -> Result<Module> {
+    let name = path.display().to_string();
+    if has_extension(path, &["tsx", "jsx"]) {
+        anyhow::bail!(
+            "{}: JSX is not supported by the builtin bundler. Set `bundler = \"webpack\"` to bundle it with webpack.",
+            name
+        );
+    }
+    let tokens = lexer::tokenize(content, &name)?;
+    if has_extension(path, &["json"]) {
+        return Ok(Module::json(tokens));
+    }
+    let tokens = if has_extension(path, TYPESCRIPT_EXTENSIONS) {
+        typescript::strip_types(tokens, &name)?
+    } else {
+        tokens
+    };
+    Module::parse(tokens, &name, defines)
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(false, |ext| extensions.contains(&ext))
+}
+
+/// The key of a file in the bundle, so that a module imported through different paths is only
+/// bundled once.
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn source_name(path: &Path, root: &Path) -> String {
+    let relative = canonical(path)
+        .strip_prefix(canonical(root))
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|_| path.to_path_buf());
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn source_map_path(script_path: &Path) -> PathBuf {
+    let mut name = script_path.as_os_str().to_os_string();
+    name.push(".map");
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::TempDir;
+
+    fn write_project(files: &[(&str, &str)]) -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, content) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn it_bundles_typescript_and_packages() {
+        let project = write_project(
+            &[
+                (
+                    "src/index.ts",
+                    "import { greet, type Name } from './greet';\nimport { unused } from 'pure';\nimport config from './config.json';\naddEventListener('fetch', (event: FetchEvent) => {\n  const name: Name = config.name;\n  event.respondWith(new Response(greet(name)));\n});\n",
+                ),
+                (
+                    "src/greet.ts",
+                    "export type Name = string;\nexport function greet(name: Name): string {\n  return `Hello, ${name}!`;\n}\n",
+                ),
+                ("src/config.json", "{ \"name\": \"world\" }"),
+                (
+                    "node_modules/pure/package.json",
+                    "{ \"main\": \"index.js\", \"sideEffects\": false }",
+                ),
+                ("node_modules/pure/index.js", "exports.unused = 1;"),
+            ],
+        );
+        let dir = project.path();
+
+        let Emitted {
+            script, source_map, ..
+        } = bundle(&dir.join("src/index.ts"), dir, false, &[]).unwrap();
+        assert!(script.contains("// src/greet.ts\n"));
+        assert!(script.contains("function greet(name) {"));
+        assert!(script.contains("__module_1.greet(name)"));
+        assert!(script.contains("module.exports = { \"name\": \"world\" };"));
+        assert!(!script.contains("exports.unused"));
+        let source_map: serde_json::Value = serde_json::from_str(&source_map).unwrap();
+        assert_eq!(
+            source_map["sources"],
+            serde_json::json!(["src/index.ts", "src/greet.ts", "src/config.json"])
+        );
+    }
+
+    #[test]
+    fn it_resolves_the_path_aliases_of_tsconfig() {
+        let project = write_project(
+            &[
+                (
+                    "tsconfig.json",
+                    "{\n  // aliases\n  \"compilerOptions\": {\n    \"baseUrl\": \".\",\n    \"paths\": { \"@/*\": [\"src/*\"], },\n  },\n}\n",
+                ),
+                (
+                    "src/index.ts",
+                    "import { hello } from '@/hello';\nimport { name } from 'lib/name';\nconsole.log(hello(name));\n",
+                ),
+                (
+                    "src/hello.ts",
+                    "export const hello = (name: string) => `Hello, ${name}!`;\n",
+                ),
+                ("lib/name.ts", "export const name = 'world';\n"),
+            ],
+        );
+        let dir = project.path();
+
+        let Emitted { source_map, .. } =
+            bundle(&dir.join("src/index.ts"), dir, false, &[]).unwrap();
+        let source_map: serde_json::Value = serde_json::from_str(&source_map).unwrap();
+        assert_eq!(
+            source_map["sources"],
+            serde_json::json!(["src/index.ts", "src/hello.ts", "lib/name.ts"])
+        );
+    }
+
+    #[test]
+    fn it_rejects_node_builtins() {
+        let project = write_project(&[("index.js", "const fs = require('fs');")]);
+        let dir = project.path();
+        let err = bundle(&dir.join("index.js"), dir, false, &[]).unwrap_err();
+        assert!(err.to_string().contains("built-in module of Node.js"));
+    }
+}
@@ -0,0 +1,1169 @@
+//! Turns each module into a function of the bundle.
+//!
+//! ES modules are rewritten the way webpack and esbuild do: `import` statements become calls to
+//! `__wrangler_require` at the top of the module, references to imported bindings read the
+//! properties of the imported module so that they stay live, and exports are getters defined
+//! before the body of the module runs. CommonJS modules are kept as they are, apart from their
+//! calls to `require` with a string literal.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+
+use super::lexer::{is_ident_part, match_brackets, starts_expression, tokenize, Token, TokenKind};
+
+/// Words before parentheses that do not hold the parameters of a method.
+const CONTROL_KEYWORDS: &[&str] = &["if", "while", "for", "switch", "catch", "with", "return"];
+
+/// A module imported or required by another module.
+#[derive(Debug)]
+pub struct Dependency {
+    pub specifier: String,
+    /// Whether the module has to be bundled even though no binding imported from it is used:
+    /// it is required, re-exported or imported for its side effects.
+    pub required: bool,
+    /// Whether a binding imported from the module is used.
+    used: bool,
+    /// Whether `import` or `export ... from` statements import the module, which then gets a
+    /// variable at the top of the module.
+    imported: bool,
+    /// Whether the module is only imported by `import()`, which may load it from another chunk.
+    pub dynamic: bool,
+}
+
+impl Dependency {
+    /// Whether the module is only imported for bindings that are never used, so that the import
+    /// can be dropped when the module has no side effects.
+    pub fn unused(&self) -> bool {
+        !self.required && !self.used
+    }
+}
+
+/// Where an imported module ends up in the bundle.
+#[derive(Clone, Copy, Debug)]
+pub struct Link {
+    pub id: usize,
+    pub esm: bool,
+    /// The chunk to load before the module is required, when `import()` imports it from
+    /// another chunk.
+    pub chunk: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Imported {
+    Default,
+    Namespace,
+    Named(String),
+}
+
+#[derive(Clone, Debug)]
+enum Export {
+    /// A binding declared in the module.
+    Local(String),
+    /// A binding imported from a dependency, by `import` or `export ... from`.
+    Import(usize, Imported),
+}
+
+#[derive(Debug)]
+enum Replacement {
+    Code(String),
+    /// A reference to an imported binding.
+    Binding(String),
+    /// A shorthand property of an object literal, e.g. `{ name }`.
+    Shorthand(String),
+    Require(usize),
+    DynamicImport(usize),
+}
+
+#[derive(Debug)]
+struct Edit {
+    start: usize,
+    /// Exclusive; equal to `start` for insertions.
+    end: usize,
+    with: Replacement,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Braces {
+    Block,
+    Object,
+    ClassBody,
+}
+
+#[derive(Debug)]
+pub struct Module {
+    tokens: Vec<Token>,
+    pub dependencies: Vec<Dependency>,
+    /// Whether the module has `import` or `export` statements.
+    pub esm: bool,
+    bindings: HashMap<String, (usize, Imported)>,
+    /// Imported bindings that a declaration in a nested scope may shadow, which are read once at
+    /// the top of the module rather than rewritten.
+    shadowed: HashSet<String>,
+    exports: Vec<(String, Export)>,
+    star_exports: Vec<usize>,
+    edits: Vec<Edit>,
+}
+
+impl Module {
+    /// Parses a module, replacing the expressions of `defines`, identifiers or dotted paths like
+    /// `process.env.API_URL`, with their code.
+    pub fn parse(tokens: Vec<Token>, name: &str, defines: &[(String, String)]) -> Result<Self> {
+        let mut parser = Parser::new(tokens, name);
+        parser.defines = defines
+            .iter()
+            .map(|(key, code)| (key.split('.').map(str::to_string).collect(), code.clone()))
+            .collect();
+        parser.parse()?;
+        let Parser {
+            tokens,
+            module,
+            edits,
+            ..
+        } = parser;
+        Ok(Module {
+            tokens,
+            edits,
+            ..module
+        })
+    }
+
+    /// A JSON file, whose value is the exports of the module.
+    pub fn json(tokens: Vec<Token>) -> Self {
+        let mut module = Module::parse(Vec::new(), "", &[]).expect("an empty module always parses");
+        let mut code = generated("module.exports =");
+        if let (Some(first), Some(assignment)) = (tokens.first(), code.first_mut()) {
+            assignment.leading = first.leading.clone();
+        }
+        code.extend(tokens.into_iter().enumerate().map(|(i, token)| {
+            if i == 0 {
+                token.with_leading(" ")
+            } else {
+                token
+            }
+        }));
+        code.push(Token::punct(";"));
+        module.tokens = code;
+        module
+    }
+
+    /// Whether the module exports bindings, which the entry of a service worker cannot do.
+    pub fn has_exports(&self) -> bool {
+        !self.exports.is_empty() || !self.star_exports.is_empty()
+    }
+
+    /// The names of the bindings exported by the module, apart from those of `export *`.
+    pub fn export_names(&self) -> impl Iterator<Item = &str> {
+        self.exports.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn has_star_exports(&self) -> bool {
+        !self.star_exports.is_empty()
+    }
+
+    /// Rewrites the module to the body of its function in the bundle. `links` has an entry for
+    /// each dependency, `None` for those that are dropped from the bundle.
+    pub fn render(&self, links: &[Option<Link>]) -> Vec<Token> {
+        let link =
+            |dependency: usize| links[dependency].expect("required dependencies are always linked");
+        let binding = |local: &str| {
+            let (dependency, imported) = &self.bindings[local];
+            access(link(*dependency), imported)
+        };
+
+        let mut header = String::new();
+        if self.esm {
+            let getters: Vec<String> = self
+                .exports
+                .iter()
+                .map(|(name, export)| {
+                    let value = match export {
+                        Export::Local(local) if self.bindings.contains_key(local) => {
+                            if self.shadowed.contains(local) {
+                                local.clone()
+                            } else {
+                                binding(local)
+                            }
+                        }
+                        Export::Local(local) => local.clone(),
+                        Export::Import(dependency, imported) => access(link(*dependency), imported),
+                    };
+                    format!("{}: () => {}", quote(name), value)
+                })
+                .collect();
+            let getters = if getters.is_empty() {
+                "{}".to_string()
+            } else {
+                format!("{{ {} }}", getters.join(", "))
+            };
+            header.push_str(&format!("__wrangler_export(exports, {});\n", getters));
+        }
+        for (i, dependency) in self.dependencies.iter().enumerate() {
+            if let (true, Some(link)) = (dependency.imported, links[i]) {
+                let require = format!("__wrangler_require({})", link.id);
+                let require = if link.esm {
+                    require
+                } else {
+                    format!("__wrangler_interop({})", require)
+                };
+                header.push_str(&format!("const __module_{} = {};\n", link.id, require));
+            }
+        }
+        for dependency in &self.star_exports {
+            header.push_str(&format!(
+                "__wrangler_reexport(exports, __module_{});\n",
+                link(*dependency).id
+            ));
+        }
+        let mut shadowed: Vec<&String> = self.shadowed.iter().collect();
+        shadowed.sort();
+        for local in shadowed {
+            header.push_str(&format!("const {} = {};\n", local, binding(local)));
+        }
+
+        let mut output = generated(&header);
+        // The whitespace before the next token, from the end of the header and removed code.
+        let mut pending_leading = if output.is_empty() {
+            None
+        } else {
+            Some("\n".to_string())
+        };
+        let mut edits = self.edits.iter().peekable();
+        let mut i = 0;
+        while i <= self.tokens.len() {
+            if let Some(edit) = edits.next_if(|edit| edit.start == i) {
+                let code = match &edit.with {
+                    Replacement::Code(code) => code.clone(),
+                    Replacement::Binding(local) => binding(local),
+                    Replacement::Shorthand(local) => format!("{}: {}", local, binding(local)),
+                    Replacement::Require(dependency) => {
+                        format!("__wrangler_require({})", link(*dependency).id)
+                    }
+                    Replacement::DynamicImport(dependency) => {
+                        let link = link(*dependency);
+                        let require = format!("__wrangler_require({})", link.id);
+                        let module = if link.esm {
+                            require
+                        } else {
+                            format!("__wrangler_interop({})", require)
+                        };
+                        match link.chunk {
+                            Some(chunk) => format!(
+                                "import({}).then(() => {})",
+                                quote(&format!("./{}", chunk_name(chunk))),
+                                module
+                            ),
+                            None => format!("Promise.resolve().then(() => {})", module),
+                        }
+                    }
+                };
+                let mut replacement = generated(&code).into_iter();
+                if edit.start < edit.end {
+                    let original = &self.tokens[edit.start];
+                    match replacement.next() {
+                        Some(first) => push(
+                            &mut output,
+                            &mut pending_leading,
+                            Token {
+                                leading: original.leading.clone(),
+                                pos: original.pos,
+                                ..first
+                            },
+                        ),
+                        None => pending_leading
+                            .get_or_insert_with(String::new)
+                            .push_str(&original.leading),
+                    }
+                    output.extend(replacement);
+                    i = edit.end;
+                    continue;
+                }
+                for (j, token) in replacement.enumerate() {
+                    if j == 0 {
+                        push(&mut output, &mut pending_leading, token.with_leading(" "));
+                    } else {
+                        output.push(token);
+                    }
+                }
+            }
+            if i == self.tokens.len() {
+                break;
+            }
+            push(&mut output, &mut pending_leading, self.tokens[i].clone());
+            i += 1;
+        }
+        output
+    }
+}
+
+/// Adds a token after removed code, whose whitespace replaces the spaces before the token, but
+/// not line breaks, comments or anything else that could matter.
+fn push(output: &mut Vec<Token>, pending_leading: &mut Option<String>, mut token: Token) {
+    if let Some(leading) = pending_leading.take() {
+        token.leading = if token.leading.trim().is_empty() && !token.newline_before() {
+            leading
+        } else {
+            format!("{}{}", leading, token.leading)
+        };
+    }
+    output.push(token);
+}
+
+/// Tokens of code added by the bundler, which map to no source.
+fn generated(code: &str) -> Vec<Token> {
+    tokenize(code, "<generated>")
+        .expect("generated code is always valid")
+        .into_iter()
+        .map(|token| Token { pos: None, ..token })
+        .collect()
+}
+
+/// The file of the chunk whose first module is `id`, in a bundle split into chunks.
+pub fn chunk_name(id: usize) -> String {
+    format!("chunk-{}.mjs", id)
+}
+
+fn quote(name: &str) -> String {
+    serde_json::to_string(name).expect("strings can always be serialized")
+}
+
+/// The expression reading an imported binding.
+fn access(link: Link, imported: &Imported) -> String {
+    match imported {
+        Imported::Namespace => format!("__module_{}", link.id),
+        Imported::Default => format!("__module_{}.default", link.id),
+        Imported::Named(name) if is_identifier(name) => format!("__module_{}.{}", link.id, name),
+        Imported::Named(name) => format!("__module_{}[{}]", link.id, quote(name)),
+    }
+}
+
+pub fn is_identifier(name: &str) -> bool {
+    name.chars().next().map_or(false, |c| !c.is_ascii_digit())
+        && name.chars().all(|c| is_ident_part(c) && c != '\\')
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    name: String,
+    matching: Vec<Option<usize>>,
+    /// The innermost bracket enclosing each token.
+    enclosing: Vec<Option<usize>>,
+    braces: HashMap<usize, Braces>,
+    removed: Vec<bool>,
+    module: Module,
+    edits: Vec<Edit>,
+    /// The dotted paths replaced by code, and the code.
+    defines: Vec<(Vec<String>, String)>,
+}
+
+impl Parser {
+    fn new(tokens: Vec<Token>, name: &str) -> Self {
+        let matching = match_brackets(&tokens);
+        let mut enclosing = Vec::with_capacity(tokens.len());
+        let mut open: Vec<usize> = Vec::new();
+        for (i, token) in tokens.iter().enumerate() {
+            if token.closes() {
+                open.pop();
+            }
+            enclosing.push(open.last().copied());
+            if token.opens() {
+                open.push(i);
+            }
+        }
+        let removed = vec![false; tokens.len()];
+        Parser {
+            tokens,
+            name: name.to_string(),
+            matching,
+            enclosing,
+            braces: HashMap::new(),
+            removed,
+            module: Module {
+                tokens: Vec::new(),
+                dependencies: Vec::new(),
+                esm: false,
+                bindings: HashMap::new(),
+                shadowed: HashSet::new(),
+                exports: Vec::new(),
+                star_exports: Vec::new(),
+                edits: Vec::new(),
+            },
+            edits: Vec::new(),
+            defines: Vec::new(),
+        }
+    }
+
+    fn parse(&mut self) -> Result<()> {
+        self.classify_braces();
+        let mut i = 0;
+        while i < self.tokens.len() {
+            i = if self.enclosing[i].is_none() && !self.after_dot(i) && self.is(i, "import") {
+                self.import_statement(i)?
+            } else if self.enclosing[i].is_none() && !self.after_dot(i) && self.is(i, "export") {
+                self.export_statement(i)?
+            } else {
+                i + 1
+            };
+        }
+        self.find_shadowed();
+        self.rewrite_references();
+        self.edits.sort_by_key(|edit| (edit.start, edit.end));
+        Ok(())
+    }
+
+    fn is(&self, i: usize, text: &str) -> bool {
+        self.tokens.get(i).map_or(false, |token| token.is(text))
+    }
+
+    fn after_dot(&self, i: usize) -> bool {
+        i > 0 && (self.is(i - 1, ".") || self.is(i - 1, "?."))
+    }
+
+    fn error(&self, i: usize, message: &str) -> anyhow::Error {
+        match self.tokens.get(i).and_then(|token| token.pos) {
+            Some(pos) => anyhow::anyhow!(
+                "{}:{}:{}: {}",
+                self.name,
+                pos.line + 1,
+                pos.column + 1,
+                message
+            ),
+            None => anyhow::anyhow!("{}: {}", self.name, message),
+        }
+    }
+
+    fn edit(&mut self, start: usize, end: usize, with: Replacement) {
+        for removed in &mut self.removed[start..end] {
+            *removed = true;
+        }
+        self.edits.push(Edit { start, end, with });
+    }
+
+    fn remove(&mut self, start: usize, end: usize) {
+        self.edit(start, end, Replacement::Code(String::new()));
+    }
+
+    /// The dependency for a specifier, shared by every statement importing it. `dynamic` is
+    /// whether `import()` imports it.
+    fn dependency(&mut self, specifier: String, dynamic: bool) -> usize {
+        let dependencies = &mut self.module.dependencies;
+        match dependencies
+            .iter()
+            .position(|dependency| dependency.specifier == specifier)
+        {
+            Some(i) => {
+                dependencies[i].dynamic &= dynamic;
+                i
+            }
+            None => {
+                dependencies.push(Dependency {
+                    specifier,
+                    required: false,
+                    used: false,
+                    imported: false,
+                    dynamic,
+                });
+                dependencies.len() - 1
+            }
+        }
+    }
+
+    /// The string literal of `from "specifier"` at `i`, and the end of the statement.
+    fn from_clause(&mut self, i: usize) -> Result<(String, usize)> {
+        if !self.is(i, "from") {
+            return Err(self.error(i, "expected `from`"));
+        }
+        let specifier = self
+            .tokens
+            .get(i + 1)
+            .and_then(Token::string_value)
+            .ok_or_else(|| self.error(i + 1, "expected the module to import from"))?;
+        Ok((specifier, self.statement_end(i + 2)))
+    }
+
+    /// Skips import assertions and the semicolon after the specifier of a statement.
+    fn statement_end(&self, mut i: usize) -> usize {
+        if (self.is(i, "assert") || self.is(i, "with"))
+            && !self.tokens[i].newline_before()
+            && self.is(i + 1, "{")
+        {
+            i = self.matching[i + 1].map_or(i + 2, |close| close + 1);
+        }
+        if self.is(i, ";") {
+            i += 1;
+        }
+        i
+    }
+
+    /// A list of specifiers like `{ a, b as c, "d" as e }`, as pairs of the name outside the
+    /// module and the name inside it, e.g. `("b", "c")` for an import.
+    fn specifiers(&self, open: usize) -> Result<(Vec<(String, String)>, usize)> {
+        let close = self.matching[open].ok_or_else(|| self.error(open, "unclosed `{`"))?;
+        let mut specifiers = Vec::new();
+        let mut j = open + 1;
+        while j < close {
+            let name = |j: usize| match self.tokens[j].kind {
+                TokenKind::Ident => Ok(self.tokens[j].text.clone()),
+                TokenKind::String => Ok(self.tokens[j].string_value().unwrap_or_default()),
+                _ => Err(self.error(j, "expected a name")),
+            };
+            let first = name(j)?;
+            if self.is(j + 1, "as") {
+                specifiers.push((first, name(j + 2)?));
+                j += 3;
+            } else {
+                specifiers.push((first.clone(), first));
+                j += 1;
+            }
+            if self.is(j, ",") {
+                j += 1;
+            } else if j != close {
+                return Err(self.error(j, "expected `,` or `}`"));
+            }
+        }
+        Ok((specifiers, close + 1))
+    }
+
+    fn import_statement(&mut self, start: usize) -> Result<usize> {
+        if self.is(start + 1, "(") || self.is(start + 1, ".") {
+            return Ok(start + 1);
+        }
+        self.module.esm = true;
+        if let Some(specifier) = self.tokens.get(start + 1).and_then(Token::string_value) {
+            let dependency = self.dependency(specifier, false);
+            self.module.dependencies[dependency].required = true;
+            self.module.dependencies[dependency].imported = true;
+            let end = self.statement_end(start + 2);
+            self.remove(start, end);
+            return Ok(end);
+        }
+
+        let mut bindings = Vec::new();
+        let mut j = start + 1;
+        if self.tokens.get(j).map_or(false, Token::is_ident) && !self.is(j, "from") {
+            bindings.push((self.tokens[j].text.clone(), Imported::Default));
+            j += 1;
+            if self.is(j, ",") {
+                j += 1;
+            }
+        }
+        if self.is(j, "*") {
+            if !self.is(j + 1, "as") || !self.tokens.get(j + 2).map_or(false, Token::is_ident) {
+                return Err(self.error(j, "expected `* as name`"));
+            }
+            bindings.push((self.tokens[j + 2].text.clone(), Imported::Namespace));
+            j += 3;
+        } else if self.is(j, "{") {
+            let (specifiers, end) = self.specifiers(j)?;
+            bindings.extend(specifiers.into_iter().map(
+                |(imported, local)| match imported.as_str() {
+                    "default" => (local, Imported::Default),
+                    _ => (local, Imported::Named(imported)),
+                },
+            ));
+            j = end;
+        }
+        let (specifier, end) = self.from_clause(j)?;
+        let dependency = self.dependency(specifier, false);
+        self.module.dependencies[dependency].imported = true;
+        for (local, imported) in bindings {
+            self.module.bindings.insert(local, (dependency, imported));
+        }
+        self.remove(start, end);
+        Ok(end)
+    }
+
+    fn export_statement(&mut self, start: usize) -> Result<usize> {
+        self.module.esm = true;
+        let next = start + 1;
+        if self.is(next, "default") {
+            return self.export_default(start);
+        }
+        if self.is(next, "{") {
+            let (specifiers, end) = self.specifiers(next)?;
+            if self.is(end, "from") {
+                let (specifier, end) = self.from_clause(end)?;
+                let dependency = self.dependency(specifier, false);
+                self.module.dependencies[dependency].required = true;
+                self.module.dependencies[dependency].imported = true;
+                for (imported, exported) in specifiers {
+                    let imported = match imported.as_str() {
+                        "default" => Imported::Default,
+                        _ => Imported::Named(imported),
+                    };
+                    self.module
+                        .exports
+                        .push((exported, Export::Import(dependency, imported)));
+                }
+                self.remove(start, end);
+                return Ok(end);
+            }
+            for (local, exported) in specifiers {
+                self.module.exports.push((exported, Export::Local(local)));
+            }
+            let end = self.statement_end(end);
+            self.remove(start, end);
+            return Ok(end);
+        }
+        if self.is(next, "*") {
+            let (exported, from) = if self.is(next + 1, "as") {
+                let name = self
+                    .tokens
+                    .get(next + 2)
+                    .map(|token| token.string_value().unwrap_or_else(|| token.text.clone()))
+                    .ok_or_else(|| self.error(next + 2, "expected a name"))?;
+                (Some(name), next + 3)
+            } else {
+                (None, next + 1)
+            };
+            let (specifier, end) = self.from_clause(from)?;
+            let dependency = self.dependency(specifier, false);
+            self.module.dependencies[dependency].required = true;
+            self.module.dependencies[dependency].imported = true;
+            match exported {
+                Some(name) => self
+                    .module
+                    .exports
+                    .push((name, Export::Import(dependency, Imported::Namespace))),
+                None => self.module.star_exports.push(dependency),
+            }
+            self.remove(start, end);
+            return Ok(end);
+        }
+
+        let mut declaration = next;
+        if self.is(declaration, "async") {
+            declaration += 1;
+        }
+        if self.is(declaration, "function") || self.is(declaration, "class") {
+            let mut name = declaration + 1;
+            if self.is(name, "*") {
+                name += 1;
+            }
+            if !self.tokens.get(name).map_or(false, Token::is_ident) {
+                return Err(self.error(name, "expected the name of the exported declaration"));
+            }
+            let name = self.tokens[name].text.clone();
+            self.module
+                .exports
+                .push((name.clone(), Export::Local(name)));
+        } else if ["const", "let", "var"]
+            .iter()
+            .any(|kind| self.is(next, kind))
+        {
+            for name in self.declared_names(next + 1) {
+                self.module
+                    .exports
+                    .push((name.clone(), Export::Local(name)));
+            }
+        } else {
+            return Err(self.error(next, "expected a declaration to export"));
+        }
+        self.remove(start, next);
+        Ok(next)
+    }
+
+    fn export_default(&mut self, start: usize) -> Result<usize> {
+        let mut declaration = start + 2;
+        if self.is(declaration, "async") && self.is(declaration + 1, "function") {
+            declaration += 1;
+        }
+        if self.is(declaration, "function") || self.is(declaration, "class") {
+            let mut name = declaration + 1;
+            if self.is(name, "*") {
+                name += 1;
+            }
+            self.remove(start, start + 2);
+            let local = if self
+                .tokens
+                .get(name)
+                .map_or(false, |token| token.is_ident() && !token.is("extends"))
+            {
+                self.tokens[name].text.clone()
+            } else {
+                self.edit(name, name, Replacement::Code("__default".to_string()));
+                "__default".to_string()
+            };
+            self.module
+                .exports
+                .push(("default".to_string(), Export::Local(local)));
+        } else {
+            self.edit(
+                start,
+                start + 2,
+                Replacement::Code("const __default =".to_string()),
+            );
+            self.module.exports.push((
+                "default".to_string(),
+                Export::Local("__default".to_string()),
+            ));
+        }
+        Ok(start + 2)
+    }
+
+    /// The names bound by the declarators of a `const`, `let` or `var` statement.
+    fn declared_names(&self, mut i: usize) -> Vec<String> {
+        let mut names = Vec::new();
+        loop {
+            match self.tokens.get(i) {
+                Some(token) if token.is("{") || token.is("[") => {
+                    let close = match self.matching[i] {
+                        Some(close) => close,
+                        None => break,
+                    };
+                    self.pattern_names(i, close, &mut names);
+                    i = close + 1;
+                }
+                Some(token) if token.is_ident() => {
+                    names.push(token.text.clone());
+                    i += 1;
+                }
+                _ => break,
+            }
+            if self.is(i, "=") {
+                i = self.skip_expression(i + 1);
+            }
+            if !self.is(i, ",") {
+                break;
+            }
+            i += 1;
+        }
+        names
+    }
+
+    /// Collects the bindings of a destructuring pattern between brackets.
+    fn pattern_names(&self, open: usize, close: usize, names: &mut Vec<String>) {
+        let object = self.is(open, "{");
+        let mut j = open + 1;
+        while j < close {
+            if self.is(j, ",") || self.is(j, "...") {
+                j += 1;
+                continue;
+            }
+            // The key of a property, e.g. `a` or `[a]` in `{ a: b }`.
+            if object {
+                let key_end = if self.is(j, "[") {
+                    self.matching[j].map_or(close, |end| end + 1)
+                } else {
+                    j + 1
+                };
+                if self.is(key_end, ":") {
+                    j = key_end + 1;
+                }
+            }
+            match self.tokens.get(j) {
+                Some(token) if token.is("{") || token.is("[") => {
+                    let end = self.matching[j].unwrap_or(close);
+                    self.pattern_names(j, end, names);
+                    j = end + 1;
+                }
+                Some(token) if token.is_ident() => {
+                    names.push(token.text.clone());
+                    j += 1;
+                }
+                _ => j += 1,
+            }
+            if self.is(j, "=") {
+                j = self.skip_expression(j + 1).min(close);
+            }
+        }
+    }
+
+    /// Finds the end of an expression: a `,` or `;` outside brackets, a closing bracket, or a
+    /// line break where automatic semicolon insertion ends the statement.
+    fn skip_expression(&self, mut i: usize) -> usize {
+        let start = i;
+        while let Some(token) = self.tokens.get(i) {
+            if token.is(",") || token.is(";") || token.closes() {
+                break;
+            }
+            if i > start && token.newline_before() && self.ends_statement(i) {
+                break;
+            }
+            i = if token.opens() {
+                self.matching[i].map_or(self.tokens.len(), |close| close + 1)
+            } else {
+                i + 1
+            };
+        }
+        i
+    }
+
+    /// Whether a line break before the token ends the previous statement.
+    fn ends_statement(&self, i: usize) -> bool {
+        let previous = &self.tokens[i - 1];
+        let previous_ends = match previous.kind {
+            TokenKind::Ident => !starts_expression(Some(previous)),
+            TokenKind::Punct => matches!(previous.text.as_str(), ")" | "]" | "}" | "++" | "--"),
+            TokenKind::Template => !previous.text.ends_with("${"),
+            _ => true,
+        };
+        let token = &self.tokens[i];
+        let token_starts = match token.kind {
+            TokenKind::Ident => !matches!(token.text.as_str(), "in" | "instanceof" | "of"),
+            TokenKind::Punct => matches!(token.text.as_str(), "{" | "!" | "~" | "++" | "--"),
+            _ => true,
+        };
+        previous_ends && token_starts
+    }
+
+    /// Tells apart the blocks, object literals and class bodies delimited by braces.
+    fn classify_braces(&mut self) {
+        for i in 0..self.tokens.len() {
+            if self.is(i, "class") && !self.after_dot(i) {
+                let mut j = i + 1;
+                while let Some(token) = self.tokens.get(j) {
+                    if token.is("{") {
+                        self.braces.insert(j, Braces::ClassBody);
+                        break;
+                    }
+                    j = if token.opens() {
+                        self.matching[j].map_or(self.tokens.len(), |close| close + 1)
+                    } else {
+                        j + 1
+                    };
+                }
+            } else if self.is(i, "{") && !self.braces.contains_key(&i) {
+                let previous = i.checked_sub(1).map(|previous| &self.tokens[previous]);
+                let object = starts_expression(previous)
+                    && !previous.map_or(false, |previous| previous.is("=>"));
+                let braces = if object {
+                    Braces::Object
+                } else {
+                    Braces::Block
+                };
+                self.braces.insert(i, braces);
+            }
+        }
+    }
+
+    fn braces_of(&self, i: usize) -> Option<Braces> {
+        self.enclosing[i].and_then(|open| self.braces.get(&open).copied())
+    }
+
+    /// Finds imported bindings that a declaration or parameter may shadow.
+    fn find_shadowed(&mut self) {
+        if self.module.bindings.is_empty() {
+            return;
+        }
+        let mut declared = HashSet::new();
+        for i in 0..self.tokens.len() {
+            if self.removed[i] || self.after_dot(i) {
+                continue;
+            }
+            let token = &self.tokens[i];
+            if ["let", "const", "var"].iter().any(|kind| token.is(kind)) {
+                let mut names = Vec::new();
+                if self.is(i + 1, "{") || self.is(i + 1, "[") {
+                    if let Some(close) = self.matching[i + 1] {
+                        self.pattern_names(i + 1, close, &mut names);
+                    }
+                } else {
+                    names.extend(self.declared_names(i + 1));
+                }
+                declared.extend(names);
+            } else if token.is("function") || token.is("class") {
+                let name = if self.is(i + 1, "*") { i + 2 } else { i + 1 };
+                if let Some(name) = self.tokens.get(name).filter(|name| name.is_ident()) {
+                    declared.insert(name.text.clone());
+                }
+            } else if token.is("=>") && i > 0 && self.tokens[i - 1].is_ident() {
+                declared.insert(self.tokens[i - 1].text.clone());
+            } else if token.is("(") && self.are_params(i) {
+                let close = self.matching[i].unwrap_or(i);
+                declared.extend(
+                    self.tokens[i + 1..close]
+                        .iter()
+                        .filter(|token| token.is_ident())
+                        .map(|token| token.text.clone()),
+                );
+            }
+        }
+        self.module.shadowed = self
+            .module
+            .bindings
+            .keys()
+            .filter(|local| declared.contains(*local))
+            .cloned()
+            .collect();
+    }
+
+    /// Whether parentheses hold the parameters of a function, arrow function or method, or the
+    /// binding of a `catch` clause.
+    fn are_params(&self, open: usize) -> bool {
+        let close = match self.matching[open] {
+            Some(close) => close,
+            None => return false,
+        };
+        if self.is(close + 1, "=>") || self.is(open.wrapping_sub(1), "catch") {
+            return true;
+        }
+        let previous = match open.checked_sub(1) {
+            Some(previous) => &self.tokens[previous],
+            None => return false,
+        };
+        if previous.is("function") || (open >= 2 && self.is(open - 2, "function")) {
+            return true;
+        }
+        self.is(close + 1, "{")
+            && previous.is_ident()
+            && !CONTROL_KEYWORDS.contains(&previous.text.as_str())
+    }
+
+    /// Rewrites references to imported bindings, `require` and `import()` calls, defined
+    /// expressions and `process.env.NODE_ENV`.
+    fn rewrite_references(&mut self) {
+        let mut i = 0;
+        while i < self.tokens.len() {
+            if self.removed[i] || !self.tokens[i].is_ident() || self.after_dot(i) {
+                i += 1;
+                continue;
+            }
+            let text = self.tokens[i].text.clone();
+            let literal_call = self.is(i + 1, "(")
+                && self.is(i + 3, ")")
+                && self
+                    .tokens
+                    .get(i + 2)
+                    .map_or(false, |token| token.kind == TokenKind::String);
+            if (text == "require" || text == "import") && literal_call {
+                let specifier = self.tokens[i + 2].string_value().unwrap_or_default();
+                let dependency = self.dependency(specifier, text == "import");
+                self.module.dependencies[dependency].required = true;
+                let with = if text == "require" {
+                    Replacement::Require(dependency)
+                } else {
+                    Replacement::DynamicImport(dependency)
+                };
+                self.edit(i, i + 4, with);
+                i += 4;
+                continue;
+            }
+            if let Some((len, with)) = self.define(i) {
+                self.edit(i, i + len, with);
+                i += len;
+                continue;
+            }
+            if text == "process"
+                && self.is(i + 1, ".")
+                && self.is(i + 2, "env")
+                && self.is(i + 3, ".")
+                && self.is(i + 4, "NODE_ENV")
+                && !self.is(i + 5, "=")
+            {
+                self.edit(i, i + 5, Replacement::Code("\"production\"".to_string()));
+                i += 5;
+                continue;
+            }
+            if let Some((dependency, _)) = self.module.bindings.get(&text) {
+                let dependency = *dependency;
+                if self.module.shadowed.contains(&text) {
+                    self.module.dependencies[dependency].used = true;
+                } else if let Some(with) = self.reference(i, &text) {
+                    self.module.dependencies[dependency].used = true;
+                    self.edit(i, i + 1, with);
+                }
+            }
+            i += 1;
+        }
+        let exported_imports: Vec<usize> = self
+            .module
+            .exports
+            .iter()
+            .filter_map(|(_, export)| match export {
+                Export::Local(local) => self
+                    .module
+                    .bindings
+                    .get(local)
+                    .map(|(dependency, _)| *dependency),
+                Export::Import(..) => None,
+            })
+            .collect();
+        for dependency in exported_imports {
+            self.module.dependencies[dependency].used = true;
+        }
+    }
+
+    /// The number of tokens of the defined expression at `i` if any, and what replaces it. A
+    /// defined identifier is not replaced where it is declared, assigned or imported.
+    fn define(&self, i: usize) -> Option<(usize, Replacement)> {
+        let (path, code) = self
+            .defines
+            .iter()
+            .filter(|(path, _)| {
+                path.iter().enumerate().all(|(n, part)| {
+                    self.is(i + 2 * n, part) && (n == 0 || self.is(i + 2 * n - 1, "."))
+                })
+            })
+            .max_by_key(|(path, _)| path.len())?;
+        let len = 2 * path.len() - 1;
+        let assigned = self.tokens.get(i + len).map_or(false, |next| {
+            matches!(next.text.as_str(), "++" | "--")
+                || (next.text.ends_with('=')
+                    && !matches!(
+                        next.text.as_str(),
+                        "==" | "===" | "!=" | "!==" | "<=" | ">="
+                    ))
+        });
+        if assigned {
+            return None;
+        }
+        if len > 1 {
+            return Some((len, Replacement::Code(code.clone())));
+        }
+        let declared = i > 0
+            && matches!(
+                self.tokens[i - 1].text.as_str(),
+                "const" | "let" | "var" | "function" | "class"
+            );
+        if declared || self.module.bindings.contains_key(&path[0]) {
+            return None;
+        }
+        match self.reference(i, &path[0])? {
+            Replacement::Shorthand(name) => {
+                Some((1, Replacement::Code(format!("{}: {}", name, code))))
+            }
+            _ => Some((1, Replacement::Code(code.clone()))),
+        }
+    }
+
+    /// How an identifier named like an imported binding is rewritten, `None` where it is the
+    /// key of a property or a class member rather than a reference.
+    fn reference(&self, i: usize, local: &str) -> Option<Replacement> {
+        let previous = i.checked_sub(1).map(|previous| &self.tokens[previous]);
+        let previous_is = |texts: &[&str]| {
+            previous.map_or(false, |previous| texts.iter().any(|text| previous.is(text)))
+        };
+        let next = self.tokens.get(i + 1);
+        let next_is = |text: &str| next.map_or(false, |next| next.is(text));
+        match self.braces_of(i) {
+            Some(Braces::Object) => {
+                if previous_is(&["{", ","]) && (next_is(":") || next_is("(")) {
+                    return None;
+                }
+                if previous_is(&["get", "set", "async", "*"]) && next_is("(") {
+                    return None;
+                }
+                if previous_is(&["{", ","]) && (next_is(",") || next_is("}")) {
+                    return Some(Replacement::Shorthand(local.to_string()));
+                }
+            }
+            Some(Braces::ClassBody) => {
+                let member_start =
+                    previous_is(&["{", ";", "}", "static", "get", "set", "async", "*"])
+                        || (self.tokens[i].newline_before()
+                            && previous.map_or(false, |previous| match previous.kind {
+                                TokenKind::Punct => matches!(previous.text.as_str(), ")" | "]"),
+                                _ => true,
+                            }));
+                if member_start {
+                    return None;
+                }
+            }
+            _ => {}
+        }
+        // A label, e.g. `outer: for (...)`.
+        if next_is(":") && (previous.is_none() || previous_is(&[";", "{", "}"])) {
+            return None;
+        }
+        Some(Replacement::Binding(local.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(source: &str) -> (Module, String) {
+        let defines = [
+            ("GIT_SHA".to_string(), "\"0123abc\"".to_string()),
+            ("process.env.DEBUG".to_string(), "false".to_string()),
+        ];
+        let module =
+            Module::parse(tokenize(source, "test.js").unwrap(), "test.js", &defines).unwrap();
+        let links: Vec<Option<Link>> = module
+            .dependencies
+            .iter()
+            .enumerate()
+            .map(|(i, _)| {
+                Some(Link {
+                    id: i + 1,
+                    esm: i == 0,
+                    chunk: None,
+                })
+            })
+            .collect();
+        let code = module
+            .render(&links)
+            .iter()
+            .map(|token| format!("{}{}", token.leading, token.text))
+            .collect();
+        (module, code)
+    }
+
+    #[test]
+    fn it_rewrites_imports_to_live_bindings() {
+        let (module, code) = render(
+            "import a, { b as c, d } from './a';\nimport * as e from 'e';\nconst o = { c, d: 1, a };\nexport default c(e.f, a);",
+        );
+        assert!(module.esm);
+        assert_eq!(module.dependencies.len(), 2);
+        assert!(!module.dependencies[0].unused());
+        assert_eq!(
+            code,
+            "__wrangler_export(exports, { \"default\": () => __default });\nconst __module_1 = __wrangler_require(1);\nconst __module_2 = __wrangler_interop(__wrangler_require(2));\n\n\nconst o = { c: __module_1.b, d: 1, a: __module_1.default };\nconst __default = __module_1.b(__module_2.f, __module_1.default);"
+        );
+    }
+
+    #[test]
+    fn it_keeps_shadowed_imports_as_constants() {
+        let (_, code) = render(
+            "import { request } from './a';\nfunction handle(request) { return request.url; }\nhandle(request);",
+        );
+        assert_eq!(
+            code,
+            "__wrangler_export(exports, {});\nconst __module_1 = __wrangler_require(1);\nconst request = __module_1.request;\n\nfunction handle(request) { return request.url; }\nhandle(request);"
+        );
+    }
+
+    #[test]
+    fn it_collects_exports() {
+        let (module, code) = render(
+            "export const { a, b: [c] } = o, d = 1;\nexport function f() {}\nexport default class {}\nexport { g as h } from './g';\nexport * from './i';",
+        );
+        let names: Vec<&str> = module
+            .exports
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(names, vec!["a", "c", "d", "f", "default", "h"]);
+        assert!(
+            code.contains("\nconst { a, b: [c] } = o, d = 1;\nfunction f() {}\nclass __default {}")
+        );
+        assert!(code.contains("__wrangler_reexport(exports, __module_2);"));
+        assert!(module
+            .dependencies
+            .iter()
+            .all(|dependency| dependency.required));
+    }
+
+    #[test]
+    fn it_finds_unused_imports() {
+        let (module, code) = render(
+            "import { unused } from 'a';\nimport 'b';\nconst c = require('c'), d = import('./d');\nif (process.env.NODE_ENV !== 'production') {}",
+        );
+        let unused: Vec<bool> = module.dependencies.iter().map(Dependency::unused).collect();
+        assert_eq!(unused, vec![true, false, false, false]);
+        assert!(code.ends_with("const c = __wrangler_require(3), d = Promise.resolve().then(() => __wrangler_interop(__wrangler_require(4)));\nif (\"production\" !== 'production') {}"));
+    }
+
+    #[test]
+    fn it_replaces_defined_expressions() {
+        let (_, code) = render(
+            "const sha = GIT_SHA, o = { GIT_SHA };\nif (process.env.DEBUG) log(GIT_SHA.length);\nfunction f(x) { return x.GIT_SHA; }",
+        );
+        assert!(code.contains("const sha = \"0123abc\", o = { GIT_SHA: \"0123abc\" };"));
+        assert!(code.contains("if (false) log(\"0123abc\".length);"));
+        assert!(code.contains("return x.GIT_SHA;"));
+    }
+}
@@ -0,0 +1,398 @@
+//! Resolves the specifiers of imports to files, the way Node.js and webpack do.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+use super::lexer::tokenize;
+
+/// Extensions tried, in order, when a specifier leaves out the extension of a file.
+const EXTENSIONS: &[&str] = &["ts", "mts", "cts", "mjs", "js", "cjs", "json"];
+
+/// Conditions of `exports` in package.json that apply to Workers, by priority.
+const CONDITIONS: &[&str] = &[
+    "worker", "browser", "import", "module", "default", "require",
+];
+
+/// The built-in modules of Node.js, which are not available in Workers.
+const NODE_BUILTINS: &[&str] = &[
+    "assert",
+    "async_hooks",
+    "buffer",
+    "child_process",
+    "cluster",
+    "console",
+    "constants",
+    "crypto",
+    "dgram",
+    "dns",
+    "domain",
+    "events",
+    "fs",
+    "http",
+    "http2",
+    "https",
+    "module",
+    "net",
+    "os",
+    "path",
+    "perf_hooks",
+    "process",
+    "punycode",
+    "querystring",
+    "readline",
+    "repl",
+    "stream",
+    "string_decoder",
+    "sys",
+    "timers",
+    "tls",
+    "tty",
+    "url",
+    "util",
+    "v8",
+    "vm",
+    "worker_threads",
+    "zlib",
+];
+
+#[derive(Default)]
+pub struct Resolver {
+    /// The parsed package.json of each directory looked at, `None` where there is none.
+    packages: HashMap<PathBuf, Option<Value>>,
+    /// The `paths` of tsconfig.json, each pattern with the files it maps to.
+    aliases: Vec<(String, Vec<PathBuf>)>,
+    /// The `baseUrl` of tsconfig.json, which bare specifiers may be relative to.
+    base_url: Option<PathBuf>,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Resolver::default()
+    }
+
+    /// Reads the `baseUrl` and `paths` of the tsconfig.json in `dir`, if there is one, to resolve
+    /// bare specifiers to files of the project as TypeScript does. A tsconfig.json that it
+    /// `extends` is not read.
+    pub fn read_tsconfig(&mut self, dir: &Path) -> Result<()> {
+        let path = dir.join("tsconfig.json");
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let name = path.display().to_string();
+        let tsconfig =
+            parse_jsonc(&content, &name).with_context(|| format!("Could not parse {}", name))?;
+
+        let options = &tsconfig["compilerOptions"];
+        self.base_url = options["baseUrl"].as_str().map(|url| dir.join(url));
+        let base = self.base_url.clone().unwrap_or_else(|| dir.to_path_buf());
+        if let Some(paths) = options["paths"].as_object() {
+            self.aliases = paths
+                .iter()
+                .map(|(pattern, targets)| {
+                    let targets = targets
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(Value::as_str)
+                        .map(|target| base.join(target))
+                        .collect();
+                    (pattern.clone(), targets)
+                })
+                .collect();
+        }
+        Ok(())
+    }
+
+    /// Finds the file imported as `specifier` by the file at `importer`.
+    pub fn resolve(&mut self, specifier: &str, importer: &Path) -> Result<PathBuf> {
+        let dir = importer.parent().unwrap_or_else(|| Path::new("."));
+        let resolved = if is_relative(specifier) {
+            self.resolve_path(&dir.join(specifier))
+        } else {
+            if is_node_builtin(specifier) {
+                anyhow::bail!(
+                    "\"{}\" imported by {} is a built-in module of Node.js, which the builtin bundler does not polyfill. Set `bundler = \"webpack\"` and `node_compat = true` to bundle polyfills of Node.js modules.",
+                    specifier,
+                    importer.display()
+                );
+            }
+            match self.resolve_alias(specifier) {
+                Some(resolved) => Some(resolved),
+                None => self.resolve_package(specifier, dir)?,
+            }
+        };
+        resolved.ok_or_else(|| {
+            anyhow::anyhow!(
+                "Could not resolve \"{}\" imported by {}. You may need to run `npm install`.",
+                specifier,
+                importer.display()
+            )
+        })
+    }
+
+    /// Whether the package containing a file declares that none of its files have side effects,
+    /// with `"sideEffects": false` in its package.json. Unused imports of such files are dropped.
+    pub fn side_effects_free(&mut self, file: &Path) -> bool {
+        for dir in file.ancestors().skip(1) {
+            if let Some(package) = self.package_json(dir) {
+                return package.get("sideEffects") == Some(&Value::Bool(false));
+            }
+            if dir.file_name().map_or(false, |name| name == "node_modules") {
+                break;
+            }
+        }
+        false
+    }
+
+    /// Resolves a bare specifier with the `paths` of tsconfig.json, of which an exact pattern or
+    /// else the longest prefix wins, and then relative to its `baseUrl`.
+    fn resolve_alias(&mut self, specifier: &str) -> Option<PathBuf> {
+        let candidates: Vec<PathBuf> = self
+            .aliases
+            .iter()
+            .filter_map(|(pattern, targets)| match pattern.find('*') {
+                Some(star) => match_wildcard(&pattern[..star], &pattern[star + 1..], specifier)
+                    .map(|matched| (star, matched, targets)),
+                None if pattern == specifier => Some((usize::MAX, "", targets)),
+                None => None,
+            })
+            .max_by_key(|(len, _, _)| *len)
+            .map(|(_, matched, targets)| {
+                targets
+                    .iter()
+                    .map(|target| PathBuf::from(target.to_string_lossy().replace('*', matched)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        for candidate in candidates {
+            if let Some(resolved) = self.resolve_path(&candidate) {
+                return Some(resolved);
+            }
+        }
+        let base_url = self.base_url.clone()?;
+        self.resolve_path(&base_url.join(specifier))
+    }
+
+    /// Resolves a bare specifier like `lodash/get` from the `node_modules` directories above
+    /// `dir`.
+    fn resolve_package(&mut self, specifier: &str, dir: &Path) -> Result<Option<PathBuf>> {
+        let mut parts = specifier.splitn(if specifier.starts_with('@') { 3 } else { 2 }, '/');
+        let name = if specifier.starts_with('@') {
+            let scope = parts.next().unwrap_or_default();
+            format!("{}/{}", scope, parts.next().unwrap_or_default())
+        } else {
+            parts.next().unwrap_or_default().to_string()
+        };
+        let subpath = parts.next();
+
+        for ancestor in dir.ancestors() {
+            let package_dir = ancestor.join("node_modules").join(&name);
+            if !package_dir.is_dir() {
+                continue;
+            }
+            let package = self.package_json(&package_dir);
+            if let Some(exports) = package.as_ref().and_then(|package| package.get("exports")) {
+                let key = match subpath {
+                    Some(subpath) => format!("./{}", subpath),
+                    None => ".".to_string(),
+                };
+                return match resolve_exports(exports, &key) {
+                    Some(target) => {
+                        Ok(Some(package_dir.join(target)).filter(|path| path.is_file()))
+                    }
+                    None => anyhow::bail!(
+                        "\"{}\" is not exported by the package {}",
+                        key,
+                        package_dir.display()
+                    ),
+                };
+            }
+            return Ok(match subpath {
+                Some(subpath) => self.resolve_path(&package_dir.join(subpath)),
+                None => self.resolve_path(&package_dir),
+            });
+        }
+        Ok(None)
+    }
+
+    /// Resolves a path to a file, trying extensions and then the entry of a directory.
+    fn resolve_path(&mut self, path: &Path) -> Option<PathBuf> {
+        if path.is_file() {
+            return Some(path.to_path_buf());
+        }
+        // TypeScript imports `./a.js` to refer to `./a.ts`.
+        if let Some(ext @ ("js" | "mjs" | "cjs")) = path.extension().and_then(|ext| ext.to_str()) {
+            let ts = path.with_extension(ext.replace("js", "ts"));
+            if ts.is_file() {
+                return Some(ts);
+            }
+        }
+        if let Some(file) = with_extensions(path) {
+            return Some(file);
+        }
+        if !path.is_dir() {
+            return None;
+        }
+        if let Some(package) = self.package_json(path) {
+            let main = ["module", "main"]
+                .iter()
+                .find_map(|field| package.get(*field).and_then(Value::as_str))
+                .or_else(|| package.get("browser").and_then(Value::as_str))
+                .map(str::to_string);
+            if let Some(main) = main {
+                let main = path.join(main);
+                if main.is_file() {
+                    return Some(main);
+                }
+                if let Some(file) =
+                    with_extensions(&main).or_else(|| with_extensions(&main.join("index")))
+                {
+                    return Some(file);
+                }
+            }
+        }
+        with_extensions(&path.join("index"))
+    }
+
+    fn package_json(&mut self, dir: &Path) -> Option<Value> {
+        self.packages
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| {
+                let json = fs::read_to_string(dir.join("package.json")).ok()?;
+                serde_json::from_str(&json).ok()
+            })
+            .clone()
+    }
+}
+
+/// Whether `specifier` imports a built-in module of Node.js, like `fs` or `node:fs/promises`.
+pub fn is_node_builtin(specifier: &str) -> bool {
+    let builtin = specifier.trim_start_matches("node:");
+    let builtin = builtin.split('/').next().unwrap_or(builtin);
+    specifier.starts_with("node:") || NODE_BUILTINS.contains(&builtin)
+}
+
+fn is_relative(specifier: &str) -> bool {
+    specifier == "."
+        || specifier == ".."
+        || specifier.starts_with("./")
+        || specifier.starts_with("../")
+        || Path::new(specifier).is_absolute()
+}
+
+fn with_extensions(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_str()?;
+    EXTENSIONS
+        .iter()
+        .map(|ext| path.with_file_name(format!("{}.{}", name, ext)))
+        .find(|path| path.is_file())
+}
+
+/// Finds the file that the `exports` of a package.json maps a subpath like `.` or `./utils` to.
+fn resolve_exports(exports: &Value, key: &str) -> Option<String> {
+    let map = match exports {
+        Value::Object(map) if map.keys().any(|key| key.starts_with('.')) => map,
+        _ if key == "." => return resolve_conditions(exports, ""),
+        _ => return None,
+    };
+    if let Some(target) = map.get(key) {
+        return resolve_conditions(target, "");
+    }
+    // Patterns like `./features/*`, of which the longest prefix wins.
+    map.iter()
+        .filter_map(|(pattern, target)| {
+            let (prefix, suffix) = match pattern.find('*') {
+                Some(star) => (&pattern[..star], &pattern[star + 1..]),
+                None if pattern.ends_with('/') => (pattern.as_str(), ""),
+                None => return None,
+            };
+            match_wildcard(prefix, suffix, key).map(|matched| (prefix.len(), matched, target))
+        })
+        .max_by_key(|(len, _, _)| *len)
+        .and_then(|(_, matched, target)| resolve_conditions(target, matched))
+}
+
+/// The part of `key` that the `*` of a pattern matches, given the parts of the pattern around it.
+fn match_wildcard<'a>(prefix: &str, suffix: &str, key: &'a str) -> Option<&'a str> {
+    if key.len() >= prefix.len() + suffix.len() && key.starts_with(prefix) && key.ends_with(suffix)
+    {
+        Some(&key[prefix.len()..key.len() - suffix.len()])
+    } else {
+        None
+    }
+}
+
+/// Parses JSON with the comments and trailing commas that tsconfig.json allows.
+fn parse_jsonc(content: &str, name: &str) -> Result<Value> {
+    let tokens = tokenize(content, name)?;
+    let mut json = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let trailing_comma = token.is(",")
+            && tokens
+                .get(i + 1)
+                .map_or(true, |next| next.is("}") || next.is("]"));
+        if !trailing_comma {
+            json.push_str(&token.text);
+        }
+    }
+    Ok(serde_json::from_str(&json)?)
+}
+
+fn resolve_conditions(target: &Value, matched: &str) -> Option<String> {
+    match target {
+        Value::String(target) if target.contains('*') => Some(target.replace('*', matched)),
+        Value::String(target) if target.ends_with('/') => Some(format!("{}{}", target, matched)),
+        Value::String(target) => Some(target.clone()),
+        Value::Array(targets) => targets
+            .iter()
+            .find_map(|target| resolve_conditions(target, matched)),
+        Value::Object(conditions) => CONDITIONS
+            .iter()
+            .filter_map(|condition| conditions.get(*condition))
+            .find_map(|target| resolve_conditions(target, matched)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn it_resolves_package_exports() {
+        let exports = json!({
+            ".": { "node": "./node.js", "import": "./index.mjs", "require": "./index.cjs" },
+            "./features/*": { "default": "./dist/features/*.js" },
+            "./package.json": "./package.json",
+        });
+        assert_eq!(
+            resolve_exports(&exports, "."),
+            Some("./index.mjs".to_string())
+        );
+        assert_eq!(
+            resolve_exports(&exports, "./features/a"),
+            Some("./dist/features/a.js".to_string())
+        );
+        assert_eq!(resolve_exports(&exports, "./internal"), None);
+        assert_eq!(
+            resolve_exports(&json!("./main.js"), "."),
+            Some("./main.js".to_string())
+        );
+        assert_eq!(
+            resolve_exports(
+                &json!({ "worker": "./worker.js", "default": "./main.js" }),
+                "."
+            ),
+            Some("./worker.js".to_string())
+        );
+    }
+}
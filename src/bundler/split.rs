@@ -0,0 +1,236 @@
+//! Splits a bundle into ES modules for Workers in the modules format: a main module, chunks that
+//! `import()` loads the first time that they are needed, and a runtime module shared by them.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use super::emit::{Emitted, Emitter, Source, RUNTIME_EXPORTS};
+use super::module::{chunk_name, is_identifier};
+use super::Graph;
+
+/// The file of the main module of a split bundle.
+pub const MAIN_MODULE: &str = "index.mjs";
+const RUNTIME_MODULE: &str = "runtime.mjs";
+
+/// Bundles the handlers of a Worker in the modules format, each from its own entry point, e.g.
+/// `scheduled` from `src/cron.ts`. The `fetch` handler is bundled into the main module, and the
+/// other handlers, like the modules imported by `import()`, into chunks loaded on their first
+/// call. The named exports of the `fetch` entry point, such as Durable Object classes, are
+/// exported by the main module.
+///
+/// Returns the files of the bundle by name, whose source maps expect them to be written two
+/// directories below `root`, like `worker/modules`.
+pub fn bundle_modules(
+    entries: &BTreeMap<String, PathBuf>,
+    root: &Path,
+    minify: bool,
+    defines: &[(String, String)],
+) -> Result<Vec<(String, Emitted)>> {
+    // the fetch handler first, so that the modules it imports are in the main module
+    let mut handlers: Vec<(&String, &PathBuf)> = entries.iter().collect();
+    handlers.sort_by_key(|(handler, _)| handler.as_str() != "fetch");
+    let paths: Vec<PathBuf> = handlers
+        .iter()
+        .map(|(_, path)| path.to_path_buf())
+        .collect();
+    let graph = Graph::load(&paths, root, defines)?;
+    let main = Some(graph.entries[0]).filter(|_| handlers[0].0 == "fetch");
+    let chunks = assign_chunks(&graph, main);
+
+    let esm: Vec<bool> = graph
+        .modules
+        .iter()
+        .map(|(module, _, _)| module.esm)
+        .collect();
+    let require = |id: usize| {
+        if esm[id] {
+            format!("__wrangler_require({})", id)
+        } else {
+            format!("__wrangler_interop(__wrangler_require({}))", id)
+        }
+    };
+
+    let mut epilogue = String::new();
+    let mut exports = Vec::new();
+    if let Some(main) = main {
+        let module = &graph.modules[main].0;
+        if module.has_star_exports() {
+            log::warn!(
+                "The bindings of `export *` in {} are not exported by the main module",
+                graph.paths[main].display()
+            );
+        }
+        exports.extend(
+            module
+                .export_names()
+                .filter(|name| *name != "default" && is_identifier(name))
+                .map(str::to_string),
+        );
+        epilogue.push_str(&format!("const __wrangler_main = {};\n", require(main)));
+    }
+    epilogue.push_str("export default {\n");
+    for ((handler, _), id) in handlers.iter().zip(&graph.entries) {
+        let module = if Some(*id) == main {
+            "__wrangler_main".to_string()
+        } else {
+            require(*id)
+        };
+        let call = format!("{}.default.{}(...args)", module, handler);
+        let call = match chunks[*id] {
+            Some(chunk) => format!("import(\"./{}\").then(() => {})", chunk_name(chunk), call),
+            None => call,
+        };
+        epilogue.push_str(&format!("  {}: (...args) => {},\n", handler, call));
+    }
+    epilogue.push_str("};\n");
+    for name in exports {
+        epilogue.push_str(&format!("export const {0} = __wrangler_main.{0};\n", name));
+    }
+
+    // The chunks whose modules each chunk imports, which have to be loaded with it. The main
+    // module, which is always loaded, imports none.
+    let mut imports: BTreeMap<Option<usize>, BTreeSet<usize>> = BTreeMap::new();
+    imports.insert(None, BTreeSet::new());
+    for (id, (module, _, links)) in graph.modules.iter().enumerate() {
+        let imported = imports.entry(chunks[id]).or_default();
+        for (dependency, link) in module.dependencies.iter().zip(links) {
+            if let (false, Some(linked)) = (dependency.dynamic, link) {
+                if let Some(chunk) = chunks[*linked].filter(|chunk| chunks[id] != Some(*chunk)) {
+                    imported.insert(chunk);
+                }
+            }
+        }
+    }
+
+    let mut sources: BTreeMap<Option<usize>, Vec<(usize, Source)>> = BTreeMap::new();
+    for (id, source) in graph
+        .into_sources(root, Some(&chunks))
+        .into_iter()
+        .enumerate()
+    {
+        sources.entry(chunks[id]).or_default().push((id, source));
+    }
+    log::info!("Split the bundle into {} chunks", imports.len());
+
+    let emitter = || Emitter::new(minify).with_source_root("../..");
+    let mut files = vec![(
+        RUNTIME_MODULE.to_string(),
+        emitter().emit_runtime(RUNTIME_MODULE),
+    )];
+    for (chunk, imported) in imports {
+        let file = match chunk {
+            Some(chunk) => chunk_name(chunk),
+            None => MAIN_MODULE.to_string(),
+        };
+        let mut prelude = format!(
+            "import {{ {} }} from \"./{}\";\n",
+            RUNTIME_EXPORTS, RUNTIME_MODULE
+        );
+        for imported in imported {
+            prelude.push_str(&format!("import \"./{}\";\n", chunk_name(imported)));
+        }
+        let epilogue = if chunk.is_none() {
+            epilogue.as_str()
+        } else {
+            ""
+        };
+        let sources = sources.remove(&chunk).unwrap_or_default();
+        let emitted = emitter().emit_chunk(&sources, &prelude, epilogue, &file);
+        files.push((file, emitted));
+    }
+    Ok(files)
+}
+
+/// The chunk of each module, `None` for the main module, and otherwise the ID of the first module
+/// of the chunk. A module imported by the main module is in the main module, and any other module
+/// is in the chunk of the first entry point or `import()` that imports it.
+fn assign_chunks(graph: &Graph, main: Option<usize>) -> Vec<Option<usize>> {
+    let mut roots: Vec<usize> = main.into_iter().chain(graph.entries.clone()).collect();
+    for (module, _, links) in &graph.modules {
+        for (dependency, link) in module.dependencies.iter().zip(links) {
+            if let (true, Some(linked)) = (dependency.dynamic, link) {
+                roots.push(*linked);
+            }
+        }
+    }
+
+    let mut chunks: Vec<Option<Option<usize>>> = vec![None; graph.modules.len()];
+    for root in roots {
+        let chunk = Some(root).filter(|root| Some(*root) != main);
+        let mut stack = vec![root];
+        while let Some(id) = stack.pop() {
+            if chunks[id].is_some() {
+                continue;
+            }
+            chunks[id] = Some(chunk);
+            let (module, _, links) = &graph.modules[id];
+            for (dependency, link) in module.dependencies.iter().zip(links) {
+                if let (false, Some(linked)) = (dependency.dynamic, link) {
+                    stack.push(*linked);
+                }
+            }
+        }
+    }
+    chunks
+        .into_iter()
+        .map(|chunk| chunk.expect("every module is imported by an entry point or by import()"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    #[test]
+    fn it_splits_handlers_and_dynamic_imports_into_chunks() {
+        let project = tempfile::tempdir().unwrap();
+        let dir = project.path();
+        fs::create_dir_all(dir.join("src")).unwrap();
+        for (path, content) in &[
+            (
+                "src/index.ts",
+                "import { greet } from './greet';\nexport class Counter {}\nexport default { fetch: () => new Response(greet()) };\n",
+            ),
+            ("src/greet.ts", "export const greet = () => 'hello';\n"),
+            (
+                "src/cron.ts",
+                "import { greet } from './greet';\nimport { report } from './report';\nexport default { scheduled: async () => (await import('./heavy')).run(report(greet())) };\n",
+            ),
+            ("src/report.ts", "export const report = (s: string) => s;\n"),
+            ("src/heavy.ts", "export const run = (s: string) => s.length;\n"),
+        ] {
+            fs::write(dir.join(path), content).unwrap();
+        }
+
+        let mut entries = BTreeMap::new();
+        entries.insert("fetch".to_string(), dir.join("src/index.ts"));
+        entries.insert("scheduled".to_string(), dir.join("src/cron.ts"));
+        let files = bundle_modules(&entries, dir, false, &[]).unwrap();
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["runtime.mjs", "index.mjs", "chunk-1.mjs", "chunk-4.mjs"]
+        );
+
+        let main = &files[1].1;
+        let modules: Vec<&str> = main.modules.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(modules, vec!["src/index.ts", "src/greet.ts"]);
+        assert!(main
+            .script
+            .contains("export const Counter = __wrangler_main.Counter;"));
+        assert!(main.script.contains(
+            "scheduled: (...args) => import(\"./chunk-1.mjs\").then(() => __wrangler_require(1).default.scheduled(...args)),"
+        ));
+        let cron = &files[2].1;
+        assert!(cron.script.starts_with("import { __wrangler_define,"));
+        assert!(cron
+            .script
+            .contains("import(\"./chunk-4.mjs\").then(() => __wrangler_require(4))"));
+        let modules: Vec<&str> = cron.modules.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(modules, vec!["src/cron.ts", "src/report.ts"]);
+    }
+}
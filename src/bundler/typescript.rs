@@ -0,0 +1,1298 @@
+//! Strips the type syntax of TypeScript, leaving JavaScript.
+//!
+//! Like esbuild, this works on each file on its own and does no type checking. Types are
+//! recognised from where they may appear: after the bindings of declarations and parameters,
+//! after the parameters of functions, in class members, after `as` and `satisfies`, and in
+//! `type`, `interface` and `declare` statements. Enums are compiled to objects. Decorators,
+//! namespaces and parameter properties are rejected, since they need more than removing types.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+
+use super::lexer::{match_brackets, starts_expression, tokenize, Token, TokenKind};
+
+/// Modifiers of class members that only exist in TypeScript.
+const TS_MODIFIERS: &[&str] = &[
+    "public",
+    "private",
+    "protected",
+    "readonly",
+    "override",
+    "declare",
+    "abstract",
+];
+
+/// Words that end a `const x: T` annotation rather than being part of an expression before it.
+const EXPRESSION_KEYWORDS: &[&str] = &[
+    "return",
+    "typeof",
+    "instanceof",
+    "in",
+    "of",
+    "new",
+    "delete",
+    "void",
+    "throw",
+    "case",
+    "yield",
+    "await",
+    "else",
+    "do",
+    "extends",
+];
+
+/// Removes the types of a TypeScript file. `name` is only used in errors.
+pub fn strip_types(tokens: Vec<Token>, name: &str) -> Result<Vec<Token>> {
+    let mut stripper = Stripper {
+        matching: match_brackets(&tokens),
+        removed: vec![false; tokens.len()],
+        replacements: HashMap::new(),
+        class_bodies: HashSet::new(),
+        method_parens: HashSet::new(),
+        signatures: HashMap::new(),
+        tokens,
+        name,
+    };
+    stripper.run()?;
+    Ok(stripper.finish())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Context {
+    Block,
+    Object,
+    ClassBody,
+    /// The parameters of a function.
+    Params,
+    /// A destructuring pattern in a declaration or parameter.
+    Pattern,
+    /// The specifiers of an import or export, like `{ a, type B }`.
+    Specifiers,
+    /// Parentheses, brackets and template substitutions in expressions.
+    Expression,
+}
+
+#[derive(Debug)]
+struct Frame {
+    context: Context,
+    /// Whether a binding that may be followed by a type is expected next.
+    binding: bool,
+    /// Whether this is the list of declarators of `let`, `const` or `var`.
+    declaring: bool,
+    /// Whether a type may follow the closing bracket, for patterns and parameters.
+    typed: bool,
+}
+
+impl Frame {
+    fn new(context: Context) -> Self {
+        Frame {
+            context,
+            binding: matches!(context, Context::Params),
+            declaring: false,
+            typed: matches!(context, Context::Params | Context::Pattern),
+        }
+    }
+}
+
+struct Stripper<'a> {
+    tokens: Vec<Token>,
+    matching: Vec<Option<usize>>,
+    removed: Vec<bool>,
+    /// Tokens replacing a removed token, e.g. the code of an enum.
+    replacements: HashMap<usize, Vec<Token>>,
+    /// The `{` opening each class body.
+    class_bodies: HashSet<usize>,
+    /// The `(` of each method of a class or object.
+    method_parens: HashSet<usize>,
+    /// The first token of each function or method declaration, keyed by its `(`, to remove it
+    /// when it turns out to be a signature without a body.
+    signatures: HashMap<usize, usize>,
+    name: &'a str,
+}
+
+impl<'a> Stripper<'a> {
+    fn run(&mut self) -> Result<()> {
+        let mut stack = vec![Frame::new(Context::Block)];
+        let mut last: Option<usize> = None;
+        let mut i = 0;
+
+        while i < self.tokens.len() {
+            if self.removed[i] {
+                i += 1;
+                continue;
+            }
+            let frame = stack.last_mut().expect("the root frame is never popped");
+            let kind = self.tokens[i].kind;
+            let text = self.tokens[i].text.clone();
+            let is_ident = kind == TokenKind::Ident;
+            let newline = self.tokens[i].newline_before();
+
+            if self.tokens[i].closes() {
+                let closed = if stack.len() > 1 { stack.pop() } else { None };
+                if self.tokens[i].opens() {
+                    // The middle of a template literal
+                    stack.push(Frame::new(Context::Expression));
+                } else if let Some(closed) = closed {
+                    if closed.typed {
+                        self.strip_after_binding(i + 1);
+                    }
+                    if closed.context == Context::Params {
+                        self.remove_signature(i);
+                    }
+                }
+                last = Some(i);
+                i += 1;
+                continue;
+            }
+
+            let member_start = frame.context == Context::ClassBody
+                && (newline || last.map_or(true, |last| self.ends_statement(last)));
+            if member_start && (kind != TokenKind::Punct || text == "[" || text == "*") {
+                if let Some(end) = self.strip_member(i)? {
+                    i = end;
+                    continue;
+                }
+                if self.removed[i] {
+                    i += 1;
+                    continue;
+                }
+            }
+
+            if self.tokens[i].opens() {
+                let context = self.context_of(i, last, frame);
+                if context == Context::Pattern {
+                    frame.binding = false;
+                }
+                stack.push(Frame::new(context));
+                last = Some(i);
+                i += 1;
+                continue;
+            }
+
+            let at_statement = frame.context == Context::Block
+                && (newline
+                    || last.map_or(true, |last| {
+                        self.ends_statement(last) || self.tokens[last].is("export")
+                    }));
+            if at_statement && is_ident {
+                if let Some(end) = self.strip_statement(i, last)? {
+                    i = end;
+                    continue;
+                }
+            }
+
+            let mut keyword = false;
+            match text.as_str() {
+                "@" if kind == TokenKind::Punct => {
+                    return Err(self.error(i, "decorators are not supported"));
+                }
+                "let" | "const" | "var" if is_ident && self.starts_declaration(i) => {
+                    frame.declaring = true;
+                    frame.binding = true;
+                    keyword = true;
+                }
+                "," if kind == TokenKind::Punct
+                    && (frame.declaring || frame.context == Context::Params) =>
+                {
+                    frame.binding = true;
+                }
+                "=" if kind == TokenKind::Punct => frame.binding = false,
+                ";" if kind == TokenKind::Punct => {
+                    frame.declaring = false;
+                    frame.binding = false;
+                }
+                "class"
+                    if is_ident
+                        && !self.after_dot(last)
+                        && self.next(i).map_or(false, |next| {
+                            self.tokens[next].is_ident() || self.tokens[next].is("{")
+                        }) =>
+                {
+                    self.strip_class_header(i);
+                }
+                "function"
+                    if is_ident && !self.after_dot(last) && frame.context == Context::Block =>
+                {
+                    self.record_function_signature(i, last);
+                }
+                "as" | "satisfies"
+                    if is_ident
+                        && frame.context != Context::Specifiers
+                        && last.map_or(false, |last| self.ends_expression(last)) =>
+                {
+                    let end = self.skip_type(i + 1);
+                    self.remove(i, end);
+                    i = end;
+                    continue;
+                }
+                "!" if kind == TokenKind::Punct
+                    && !newline
+                    && last.map_or(false, |last| self.ends_expression(last)) =>
+                {
+                    // A non-null assertion
+                    self.remove(i, i + 1);
+                    i += 1;
+                    continue;
+                }
+                "<" if kind == TokenKind::Punct => {
+                    if let Some(end) = self.type_arguments(i, last) {
+                        self.remove(i, end);
+                        i = end;
+                        continue;
+                    }
+                }
+                "type" if is_ident && frame.context == Context::Specifiers => {
+                    // `import { type A, b }`
+                    if let Some(next) = self.next(i) {
+                        if self.tokens[next].is_ident() && !self.tokens[next].is("as") {
+                            let mut end = next + 1;
+                            if self.is(end, "as") {
+                                end += 2;
+                            }
+                            if self.is(end, ",") {
+                                end += 1;
+                            }
+                            self.remove(i, end);
+                            i = end;
+                            continue;
+                        }
+                    }
+                }
+                _ => {}
+            }
+
+            if !keyword && frame.binding && is_ident && !text.starts_with('#') {
+                frame.binding = false;
+                if frame.context == Context::Params {
+                    if text == "this" {
+                        // `function f(this: Window)` declares the type of `this`
+                        let mut end = self.skip_annotation(i + 1);
+                        if self.is(end, ",") {
+                            end += 1;
+                        }
+                        self.remove(i, end);
+                        frame.binding = true;
+                        i = end;
+                        continue;
+                    }
+                    let property = TS_MODIFIERS.contains(&text.as_str())
+                        && self.next(i).map_or(false, |next| {
+                            let next = &self.tokens[next];
+                            (next.is_ident() && !next.newline_before())
+                                || next.is("{")
+                                || next.is("[")
+                        });
+                    if property {
+                        return Err(self.error(i, "parameter properties are not supported"));
+                    }
+                }
+                self.strip_after_binding(i + 1);
+            }
+
+            last = Some(i);
+            i += 1;
+        }
+        Ok(())
+    }
+
+    /// Whether `let`, `const` or `var` at `i` starts a declaration.
+    fn starts_declaration(&self, i: usize) -> bool {
+        self.next(i).map_or(false, |next| {
+            let next = &self.tokens[next];
+            (next.is_ident() && !next.is("in") && !next.is("of") && !next.is("enum"))
+                || next.is("{")
+                || next.is("[")
+        })
+    }
+
+    /// Whether a statement or class member can start after the token at `i`.
+    fn ends_statement(&self, i: usize) -> bool {
+        let token = &self.tokens[i];
+        token.is(";") || token.is("{") || token.is("}")
+    }
+
+    /// Decides what a bracket opens.
+    fn context_of(&self, i: usize, last: Option<usize>, frame: &Frame) -> Context {
+        let token = &self.tokens[i];
+        if token.kind == TokenKind::Template {
+            return Context::Expression;
+        }
+        let previous = last.map(|last| &self.tokens[last]);
+        let before = last.and_then(|last| self.previous(last));
+        match token.text.as_str() {
+            "{" => {
+                if self.class_bodies.contains(&i) {
+                    Context::ClassBody
+                } else if frame.binding {
+                    Context::Pattern
+                } else if self.opens_specifiers(previous, before) || self.after_default_import(last)
+                {
+                    Context::Specifiers
+                } else if previous.map_or(false, |previous| previous.is("=>")) {
+                    Context::Block
+                } else if starts_expression(previous) {
+                    Context::Object
+                } else {
+                    Context::Block
+                }
+            }
+            "[" if frame.binding => Context::Pattern,
+            "(" if self.is_params(i, last, frame) => Context::Params,
+            _ => Context::Expression,
+        }
+    }
+
+    /// Whether a brace after `previous` opens an import or export specifier list.
+    fn opens_specifiers(&self, previous: Option<&Token>, before: Option<usize>) -> bool {
+        let previous = match previous {
+            Some(previous) => previous,
+            None => return false,
+        };
+        previous.is("import")
+            || previous.is("export")
+            || (previous.is("type")
+                && before.map_or(false, |before| {
+                    self.tokens[before].is("import") || self.tokens[before].is("export")
+                }))
+    }
+
+    /// Whether `last` is the comma of `import a, { b } from "c"`.
+    fn after_default_import(&self, last: Option<usize>) -> bool {
+        let comma = match last {
+            Some(comma) if self.tokens[comma].is(",") => comma,
+            _ => return false,
+        };
+        self.previous(comma)
+            .and_then(|name| self.previous(name))
+            .map_or(false, |import| self.tokens[import].is("import"))
+    }
+
+    /// Whether parentheses hold the parameters of a function.
+    fn is_params(&self, i: usize, last: Option<usize>, frame: &Frame) -> bool {
+        if self.method_parens.contains(&i) {
+            return true;
+        }
+        let close = match self.matching[i] {
+            Some(close) => close,
+            None => return false,
+        };
+        let after = self.next(close);
+        let after_is = |text: &str| after.map_or(false, |after| self.tokens[after].is(text));
+        if after_is("=>") {
+            return true;
+        }
+        let last = match last {
+            Some(last) => last,
+            None => return false,
+        };
+        let previous = &self.tokens[last];
+        if previous.is("function") || previous.is("catch") {
+            return true;
+        }
+        let before = self.previous(last);
+        let before_is = |text: &str| before.map_or(false, |before| self.tokens[before].is(text));
+        let before_before_is = |text: &str| {
+            before
+                .and_then(|before| self.previous(before))
+                .map_or(false, |before| self.tokens[before].is(text))
+        };
+        // `function f(`, `function* f(` and `function* (`
+        if (previous.is_ident()
+            && (before_is("function") || before_is("*") && before_before_is("function")))
+            || (previous.is("*") && before_is("function"))
+        {
+            return true;
+        }
+        // `{ m(a) {} }` and `{ get m() {} }`
+        if frame.context == Context::Object
+            && (previous.is_ident()
+                || matches!(previous.kind, TokenKind::String | TokenKind::Number)
+                || previous.is("]"))
+            && (after_is("{") || after_is(":"))
+        {
+            let name_start = if previous.is("]") {
+                self.matching[last].and_then(|open| self.previous(open))
+            } else {
+                before
+            };
+            let at_property = name_start.map_or(false, |start| {
+                let start = &self.tokens[start];
+                ["{", ",", "get", "set", "async", "*"]
+                    .iter()
+                    .any(|text| start.is(text))
+            });
+            if at_property {
+                return true;
+            }
+        }
+        // `(a): T => a`, unless it is the consequent of a conditional
+        if after_is(":") && !previous.is("?") {
+            let end = self.skip_type(after.unwrap() + 1);
+            return self.is(end, "=>");
+        }
+        false
+    }
+
+    /// Removes `?`, `!` and a type annotation following a binding or parameters.
+    fn strip_after_binding(&mut self, i: usize) {
+        let mut i = i;
+        if self.is(i, "!") || self.is(i, "?") {
+            self.remove(i, i + 1);
+            i += 1;
+        }
+        if self.is(i, ":") {
+            let end = self.skip_type(i + 1);
+            self.remove(i, end);
+        }
+    }
+
+    /// The index after a `: T` annotation starting at `i`, or `i` when there is none.
+    fn skip_annotation(&self, i: usize) -> usize {
+        if self.is(i, ":") {
+            self.skip_type(i + 1)
+        } else {
+            i
+        }
+    }
+
+    /// Removes the TypeScript-only statements starting at `i`, returning where to continue.
+    fn strip_statement(&mut self, i: usize, last: Option<usize>) -> Result<Option<usize>> {
+        let exported = last.map_or(false, |last| self.tokens[last].is("export"));
+        let start = match last {
+            Some(last) if exported => last,
+            _ => i,
+        };
+        let next = match self.next(i) {
+            Some(next) => next,
+            None => return Ok(None),
+        };
+        let next_text = self.tokens[next].text.clone();
+        let next_is = |text: &str| next_text == text;
+        let same_line_name = {
+            let next = &self.tokens[next];
+            (next.is_ident() || next.kind == TokenKind::String) && !next.newline_before()
+        };
+        let after_text = self
+            .next(next)
+            .map(|after| self.tokens[after].text.clone())
+            .unwrap_or_default();
+        let after_is = |text: &str| after_text == text;
+
+        let text = self.tokens[i].text.clone();
+        let end = match text.as_str() {
+            "type" if same_line_name && (after_is("=") || after_is("<")) => {
+                let mut end = next + 1;
+                if self.is(end, "<") {
+                    end = self.skip_angle(end).unwrap_or(end);
+                }
+                end = self.skip_type(end + 1);
+                if self.is(end, ";") {
+                    end += 1;
+                }
+                end
+            }
+            // `export type { A }` and `export type * from "a"`
+            "type" if exported && (next_is("{") || next_is("*")) => self.import_end(next),
+            "interface" if same_line_name => {
+                let mut open = next + 1;
+                while open < self.tokens.len() && !self.is(open, "{") {
+                    open = if self.is(open, "<") {
+                        self.skip_angle(open).unwrap_or(open + 1)
+                    } else {
+                        open + 1
+                    };
+                }
+                self.block_end(open)
+            }
+            "declare" if same_line_name => match next_text.as_str() {
+                "class" | "module" | "namespace" | "global" | "enum" | "interface" | "abstract" => {
+                    let open = (next..self.tokens.len())
+                        .find(|open| self.is(*open, "{"))
+                        .unwrap_or_else(|| self.tokens.len());
+                    self.block_end(open)
+                }
+                _ => self.statement_end(next),
+            },
+            "abstract" if next_is("class") => i + 1,
+            "namespace" | "module" if same_line_name && (after_is("{") || after_is(".")) => {
+                return Err(self.error(i, "namespaces are not supported"));
+            }
+            "enum" if same_line_name => {
+                self.compile_enum(i, i)?;
+                return Ok(Some(self.block_end(i + 2)));
+            }
+            "const" if next_is("enum") => {
+                self.compile_enum(i, next)?;
+                return Ok(Some(self.block_end(next + 2)));
+            }
+            // `import type { A } from "a"`, but not `import type from "a"`
+            "import" if next_is("type") && !after_is(",") && !after_is("from") => {
+                self.import_end(next)
+            }
+            "import" if self.tokens[next].is_ident() && after_is("=") => {
+                return Err(self.error(i, "`import x = require()` is not supported"));
+            }
+            "export" if next_is("=") => {
+                return Err(self.error(i, "`export =` is not supported"));
+            }
+            // `export as namespace Name`
+            "export" if next_is("as") => self.statement_end(i),
+            _ => return Ok(None),
+        };
+        // Only `abstract` is removed from `abstract class`
+        let start = if text == "abstract" { i } else { start };
+        self.remove(start, end);
+        Ok(Some(end))
+    }
+
+    /// The index after the block opened at `open`.
+    fn block_end(&self, open: usize) -> usize {
+        self.matching
+            .get(open)
+            .copied()
+            .flatten()
+            .map_or(self.tokens.len(), |close| close + 1)
+    }
+
+    /// The end of an import or export statement, after the specifier of its module if any.
+    fn import_end(&self, i: usize) -> usize {
+        let mut end = i;
+        while end < self.tokens.len() {
+            let token = &self.tokens[end];
+            if token.is(";") {
+                return end + 1;
+            }
+            if token.kind == TokenKind::String {
+                return if self.is(end + 1, ";") {
+                    end + 2
+                } else {
+                    end + 1
+                };
+            }
+            if token.is("{") {
+                end = self.matching[end].map_or(self.tokens.len(), |close| close + 1);
+                if !self.is(end, "from") {
+                    return if self.is(end, ";") { end + 1 } else { end };
+                }
+                continue;
+            }
+            end += 1;
+        }
+        end
+    }
+
+    /// The end of a statement without a block, at a semicolon or a line break.
+    fn statement_end(&self, i: usize) -> usize {
+        let continues = |token: &Token| {
+            ["|", "&", ".", "=>", "=", ",", "?", ":", "extends"]
+                .iter()
+                .any(|text| token.is(text))
+        };
+        let mut j = i;
+        while j < self.tokens.len() {
+            let token = &self.tokens[j];
+            if token.is(";") {
+                return j + 1;
+            }
+            if j > i
+                && token.newline_before()
+                && !continues(token)
+                && !continues(&self.tokens[j - 1])
+            {
+                return j;
+            }
+            if token.closes() {
+                return j;
+            }
+            j = if token.opens() {
+                self.matching[j].map_or(self.tokens.len(), |close| close + 1)
+            } else {
+                j + 1
+            };
+        }
+        j
+    }
+
+    /// Removes the TypeScript-only parts of a class member starting at `i`, returning where to
+    /// continue when the whole member was removed.
+    fn strip_member(&mut self, i: usize) -> Result<Option<usize>> {
+        let mut j = i;
+        loop {
+            let is_modifier = self.tokens[j].is_ident()
+                && self.next(j).map_or(false, |next| {
+                    let next = &self.tokens[next];
+                    !next.newline_before()
+                        && (next.kind != TokenKind::Punct
+                            || next.is("[")
+                            || next.is("*")
+                            || next.is("{"))
+                });
+            if !is_modifier {
+                break;
+            }
+            let text = self.tokens[j].text.clone();
+            match text.as_str() {
+                "declare" | "abstract" => {
+                    // No code is emitted for these members
+                    let end = self.statement_end(j);
+                    self.remove(i, end);
+                    return Ok(Some(end));
+                }
+                text if TS_MODIFIERS.contains(&text) => self.remove(j, j + 1),
+                "static" | "async" | "get" | "set" | "accessor" => {}
+                _ => break,
+            }
+            j += 1;
+        }
+        if self.is(j, "*") {
+            j += 1;
+        }
+
+        let name_end = if self.is(j, "[") {
+            // An index signature like `[key: string]: T`
+            if self.is(j + 2, ":") && self.tokens[j + 1].is_ident() {
+                let end = self.statement_end(j);
+                self.remove(i, end);
+                return Ok(Some(end));
+            }
+            self.matching[j].map_or(j + 1, |close| close + 1)
+        } else if self.tokens[j].kind != TokenKind::Punct {
+            j + 1
+        } else {
+            return Ok(None);
+        };
+
+        let mut after = name_end;
+        let optional = self.is(after, "?")
+            && (self.is(after + 1, "(") || self.is(after + 1, "<") || self.is(after + 1, ":"));
+        if optional || self.is(after, "!") {
+            self.remove(after, after + 1);
+            after += 1;
+        }
+        if self.is(after, "<") {
+            if let Some(end) = self.skip_angle(after) {
+                self.remove(after, end);
+                after = end;
+            }
+        }
+        if self.is(after, "(") {
+            self.method_parens.insert(after);
+            self.signatures.insert(after, i);
+        } else if self.is(after, ":") {
+            let end = self.skip_type(after + 1);
+            self.remove(after, end);
+        }
+        Ok(None)
+    }
+
+    /// Removes the declaration of a function or method when it has no body, i.e. it is an
+    /// overload signature. `close` is the `)` of its parameters.
+    fn remove_signature(&mut self, close: usize) {
+        let open = match self.matching[close] {
+            Some(open) => open,
+            None => return,
+        };
+        let start = match self.signatures.get(&open) {
+            Some(start) => *start,
+            None => return,
+        };
+        let after = self.next_kept(close + 1);
+        match after {
+            Some(after) if self.is(after, "{") => {}
+            Some(after) if self.is(after, ";") => self.remove(start, after + 1),
+            Some(after) => self.remove(start, after),
+            None => self.remove(start, self.tokens.len()),
+        }
+    }
+
+    /// Remembers a function declaration, which may turn out to be an overload signature.
+    fn record_function_signature(&mut self, i: usize, last: Option<usize>) {
+        let mut start = i;
+        let mut previous = last;
+        while let Some(p) = previous {
+            if self.tokens[p].is("async")
+                || self.tokens[p].is("export")
+                || self.tokens[p].is("default")
+            {
+                start = p;
+                previous = self.previous(p);
+            } else {
+                break;
+            }
+        }
+        let mut j = i + 1;
+        if self.is(j, "*") {
+            j += 1;
+        }
+        if j < self.tokens.len() && self.tokens[j].is_ident() {
+            j += 1;
+        }
+        if self.is(j, "<") {
+            if let Some(end) = self.skip_angle(j) {
+                self.remove(j, end);
+                j = end;
+            }
+        }
+        if self.is(j, "(") {
+            self.signatures.insert(j, start);
+        }
+    }
+
+    /// Removes type parameters, `implements` and the type arguments of the base class from the
+    /// header of a class, and remembers where its body starts.
+    fn strip_class_header(&mut self, i: usize) {
+        let mut j = i + 1;
+        if j < self.tokens.len()
+            && self.tokens[j].is_ident()
+            && !self.is(j, "extends")
+            && !self.is(j, "implements")
+        {
+            j += 1;
+        }
+        if self.is(j, "<") {
+            if let Some(end) = self.skip_angle(j) {
+                self.remove(j, end);
+                j = end;
+            }
+        }
+        while j < self.tokens.len() {
+            let token = &self.tokens[j];
+            if token.is("{") {
+                self.class_bodies.insert(j);
+                return;
+            }
+            if token.is("implements") {
+                let mut end = j + 1;
+                while end < self.tokens.len() && !self.is(end, "{") {
+                    end = if self.is(end, "<") {
+                        self.skip_angle(end).unwrap_or(end + 1)
+                    } else {
+                        end + 1
+                    };
+                }
+                self.remove(j, end);
+                j = end;
+                continue;
+            }
+            if token.is("<") {
+                if let Some(end) = self.skip_angle(j) {
+                    if self.is(end, "{") || self.is(end, "implements") {
+                        self.remove(j, end);
+                        j = end;
+                        continue;
+                    }
+                }
+            }
+            j = if token.opens() {
+                self.matching[j].map_or(self.tokens.len(), |close| close + 1)
+            } else {
+                j + 1
+            };
+        }
+    }
+
+    /// The end of type arguments or parameters at `i`, e.g. `f<T>(a)` or `<T>(a: T) => a`.
+    fn type_arguments(&self, i: usize, last: Option<usize>) -> Option<usize> {
+        let previous = last.map(|last| &self.tokens[last]);
+        let after_name = previous.map_or(false, |previous| {
+            previous.is_ident() && !EXPRESSION_KEYWORDS.contains(&previous.text.as_str())
+        });
+        let at_expression = starts_expression(previous) || previous.is_none();
+        if !after_name && !at_expression {
+            return None;
+        }
+        let end = self.skip_angle(i)?;
+        let next = self.tokens.get(end)?;
+        if next.is("(") || (after_name && next.kind == TokenKind::Template) {
+            Some(end)
+        } else {
+            None
+        }
+    }
+
+    /// Skips `<...>` when it can only hold types, returning the index after the closing `>`.
+    fn skip_angle(&self, i: usize) -> Option<usize> {
+        let mut depth: i32 = 0;
+        let mut j = i;
+        while j < self.tokens.len() {
+            let token = &self.tokens[j];
+            match token.kind {
+                TokenKind::Punct if token.text.chars().all(|c| c == '>') => {
+                    depth -= token.text.len() as i32;
+                    if depth == 0 {
+                        return Some(j + 1);
+                    }
+                    if depth < 0 {
+                        return None;
+                    }
+                }
+                TokenKind::Punct if token.is("<") => depth += 1,
+                _ if token.opens() => {
+                    j = self.matching[j]?;
+                }
+                TokenKind::Punct => {
+                    if ![",", ".", "|", "&", "?", ":", "=>", "=", "-", "..."]
+                        .iter()
+                        .any(|text| token.is(text))
+                    {
+                        return None;
+                    }
+                }
+                TokenKind::Regex => return None,
+                _ => {}
+            }
+            j += 1;
+        }
+        None
+    }
+
+    /// Returns the index after the type starting at `i`.
+    fn skip_type(&self, i: usize) -> usize {
+        let mut i = i;
+        if self.is(i, "|") || self.is(i, "&") {
+            i += 1;
+        }
+        loop {
+            i = self.skip_type_operand(i);
+            if self.is(i, "|") || self.is(i, "&") {
+                i += 1;
+                continue;
+            }
+            if self.is(i, "extends") {
+                // A conditional type
+                let mut check = i + 1;
+                loop {
+                    check = self.skip_type_operand(check);
+                    if self.is(check, "|") || self.is(check, "&") {
+                        check += 1;
+                    } else {
+                        break;
+                    }
+                }
+                if self.is(check, "?") {
+                    let consequent = self.skip_type(check + 1);
+                    if self.is(consequent, ":") {
+                        return self.skip_type(consequent + 1);
+                    }
+                }
+            }
+            return i;
+        }
+    }
+
+    fn skip_type_operand(&self, i: usize) -> usize {
+        let operand_follows = |i: usize| {
+            self.tokens.get(i + 1).map_or(false, |next| {
+                next.is_ident() || next.opens() || next.is("new")
+            })
+        };
+        let mut i = i;
+        while i < self.tokens.len()
+            && [
+                "keyof", "unique", "readonly", "infer", "asserts", "abstract",
+            ]
+            .iter()
+            .any(|text| self.is(i, text))
+            && operand_follows(i)
+        {
+            i += 1;
+        }
+        if self.is(i, "new") {
+            i += 1;
+        }
+        let token = match self.tokens.get(i) {
+            Some(token) => token,
+            None => return i,
+        };
+
+        i = match token.kind {
+            TokenKind::Punct if token.is("(") => {
+                let close = match self.matching[i] {
+                    Some(close) => close,
+                    None => return i,
+                };
+                if self.is(close + 1, "=>") {
+                    return self.skip_type(close + 2);
+                }
+                close + 1
+            }
+            TokenKind::Punct if token.is("<") => {
+                // A generic function type
+                match self.skip_angle(i) {
+                    Some(end) if self.is(end, "(") => {
+                        let close = match self.matching[end] {
+                            Some(close) => close,
+                            None => return i,
+                        };
+                        if self.is(close + 1, "=>") {
+                            return self.skip_type(close + 2);
+                        }
+                        close + 1
+                    }
+                    _ => return i,
+                }
+            }
+            TokenKind::Punct if token.is("{") || token.is("[") => {
+                self.matching[i].map_or(self.tokens.len(), |close| close + 1)
+            }
+            TokenKind::Punct if token.is("-") => i + 2,
+            TokenKind::Template => {
+                let mut j = i;
+                while self.tokens[j].opens() {
+                    j = match self.matching[j] {
+                        Some(close) => close,
+                        None => return self.tokens.len(),
+                    };
+                }
+                j + 1
+            }
+            TokenKind::String | TokenKind::Number => i + 1,
+            TokenKind::Ident if token.is("typeof") || token.is("import") => {
+                // `typeof a.b` and `import("a").B`
+                let mut j = if token.is("typeof") { i + 1 } else { i };
+                if self.is(j, "import") {
+                    j += 1;
+                }
+                if self.is(j, "(") {
+                    j = self.matching[j].map_or(self.tokens.len(), |close| close + 1);
+                } else if self.tokens.get(j).map_or(false, Token::is_ident) {
+                    j += 1;
+                }
+                while self.is(j, ".") && self.tokens.get(j + 1).map_or(false, Token::is_ident) {
+                    j += 2;
+                }
+                if self.is(j, "<") && !self.tokens[j].newline_before() {
+                    j = self.skip_angle(j).unwrap_or(j);
+                }
+                j
+            }
+            TokenKind::Ident => {
+                let mut j = i + 1;
+                while self.is(j, ".") && self.tokens.get(j + 1).map_or(false, Token::is_ident) {
+                    j += 2;
+                }
+                if self.is(j, "<") && !self.tokens[j].newline_before() {
+                    j = self.skip_angle(j).unwrap_or(j);
+                }
+                // A type predicate, `x is T`
+                if self.is(j, "is") && !self.tokens[j].newline_before() {
+                    return self.skip_type(j + 1);
+                }
+                j
+            }
+            _ => return i,
+        };
+
+        // Array types and indexed access types
+        while self.is(i, "[") && !self.tokens[i].newline_before() {
+            i = self.matching[i].map_or(self.tokens.len(), |close| close + 1);
+        }
+        i
+    }
+
+    /// Compiles an enum to the object TypeScript would build for it. `start` is where the
+    /// declaration starts, i.e. `const` for `const enum`, and `keyword` is the `enum` keyword.
+    fn compile_enum(&mut self, start: usize, keyword: usize) -> Result<()> {
+        let name = match self.tokens.get(keyword + 1) {
+            Some(name) if name.is_ident() => name.text.clone(),
+            _ => return Err(self.error(keyword, "expected the name of the enum")),
+        };
+        let open = keyword + 2;
+        let close = match (
+            self.is(open, "{"),
+            self.matching.get(open).copied().flatten(),
+        ) {
+            (true, Some(close)) => close,
+            _ => return Err(self.error(keyword, "expected the members of the enum")),
+        };
+
+        let mut code = format!("var {name}; (function ({name}) {{", name = name);
+        let mut next_value = Some(0.0);
+        let mut members = Vec::new();
+        let mut j = open + 1;
+        while j < close {
+            let member = &self.tokens[j];
+            let key = match member.kind {
+                TokenKind::Ident => member.text.clone(),
+                TokenKind::String => member.string_value().unwrap_or_default(),
+                _ => return Err(self.error(j, "expected the name of an enum member")),
+            };
+            members.push(key.clone());
+            let key = serde_json::to_string(&key)?;
+            j += 1;
+
+            let mut initializer = Vec::new();
+            if self.is(j, "=") {
+                j += 1;
+                while j < close && !self.is(j, ",") {
+                    initializer.push(j);
+                    j = if self.tokens[j].opens() {
+                        self.matching[j].map_or(close, |end| end + 1)
+                    } else {
+                        j + 1
+                    };
+                }
+            }
+            let last_initializer = initializer.last().map(|last| match self.matching[*last] {
+                Some(end) if self.tokens[*last].opens() => end,
+                _ => *last,
+            });
+            if self.is(j, ",") {
+                j += 1;
+            }
+
+            match initializer.as_slice() {
+                [] => {
+                    let value = next_value.ok_or_else(|| {
+                        self.error(keyword, "enum members after a computed member need a value")
+                    })?;
+                    code.push_str(&format!(
+                        " {name}[{name}[{key}] = {value}] = {key};",
+                        name = name,
+                        key = key,
+                        value = value
+                    ));
+                    next_value = Some(value + 1.0);
+                }
+                [literal] if self.tokens[*literal].kind == TokenKind::String => {
+                    code.push_str(&format!(
+                        " {}[{}] = {};",
+                        name, key, self.tokens[*literal].text
+                    ));
+                    next_value = None;
+                }
+                [literal] if self.tokens[*literal].kind == TokenKind::Number => {
+                    let text = &self.tokens[*literal].text;
+                    code.push_str(&format!(
+                        " {name}[{name}[{key}] = {value}] = {key};",
+                        name = name,
+                        key = key,
+                        value = text
+                    ));
+                    next_value = parse_number(text).map(|value| value + 1.0);
+                }
+                [minus, literal]
+                    if self.tokens[*minus].is("-")
+                        && self.tokens[*literal].kind == TokenKind::Number =>
+                {
+                    let text = &self.tokens[*literal].text;
+                    code.push_str(&format!(
+                        " {name}[{name}[{key}] = -{value}] = {key};",
+                        name = name,
+                        key = key,
+                        value = text
+                    ));
+                    next_value = parse_number(text).map(|value| 1.0 - value);
+                }
+                _ => {
+                    // Members are referred to by their name, like `B = A << 1`
+                    let first = initializer[0];
+                    let expression = (first..=last_initializer.unwrap())
+                        .map(|k| {
+                            let token = &self.tokens[k];
+                            let leading = if k == first {
+                                ""
+                            } else {
+                                token.leading.as_str()
+                            };
+                            if token.is_ident()
+                                && members.contains(&token.text)
+                                && !self.tokens[k - 1].is(".")
+                            {
+                                format!("{}{}.{}", leading, name, token.text)
+                            } else {
+                                format!("{}{}", leading, token.text)
+                            }
+                        })
+                        .collect::<String>();
+                    code.push_str(&format!(
+                        " {name}[{name}[{key}] = {expression}] = {key};",
+                        name = name,
+                        key = key,
+                        expression = expression
+                    ));
+                    next_value = None;
+                }
+            }
+        }
+        code.push_str(&format!(" }})({name} || ({name} = {{}}));", name = name));
+
+        let mut tokens = tokenize(&code, self.name)?;
+        for token in tokens.iter_mut() {
+            token.pos = None;
+        }
+        if let Some(first) = tokens.first_mut() {
+            first.leading = self.tokens[start].leading.clone();
+            first.pos = self.tokens[start].pos;
+        }
+        self.remove(start, close + 1);
+        self.replacements.insert(start, tokens);
+        Ok(())
+    }
+
+    /// Whether the token can end an expression, so that a following `!` is a non-null
+    /// assertion and `as` a type assertion.
+    fn ends_expression(&self, i: usize) -> bool {
+        let token = &self.tokens[i];
+        match token.kind {
+            TokenKind::Ident => !EXPRESSION_KEYWORDS.contains(&token.text.as_str()),
+            TokenKind::Punct => token.is(")") || token.is("]") || token.is("}"),
+            TokenKind::Template => !token.text.ends_with("${"),
+            _ => true,
+        }
+    }
+
+    fn after_dot(&self, last: Option<usize>) -> bool {
+        last.map_or(false, |last| {
+            self.tokens[last].is(".") || self.tokens[last].is("?.")
+        })
+    }
+
+    fn is(&self, i: usize, text: &str) -> bool {
+        self.tokens.get(i).map_or(false, |token| token.is(text))
+    }
+
+    fn next(&self, i: usize) -> Option<usize> {
+        self.next_kept(i + 1)
+    }
+
+    fn next_kept(&self, i: usize) -> Option<usize> {
+        (i..self.tokens.len()).find(|j| !self.removed[*j])
+    }
+
+    fn previous(&self, i: usize) -> Option<usize> {
+        (0..i).rev().find(|j| !self.removed[*j])
+    }
+
+    fn remove(&mut self, start: usize, end: usize) {
+        for removed in self.removed[start..end.min(self.tokens.len())].iter_mut() {
+            *removed = true;
+        }
+    }
+
+    fn error(&self, i: usize, message: &str) -> anyhow::Error {
+        match self.tokens[i].pos {
+            Some(pos) => anyhow::anyhow!(
+                "{}:{}:{}: {}",
+                self.name,
+                pos.line + 1,
+                pos.column + 1,
+                message
+            ),
+            None => anyhow::anyhow!("{}: {}", self.name, message),
+        }
+    }
+
+    /// Collects the kept tokens. Line breaks of removed code are kept, since a line break may
+    /// end a statement.
+    fn finish(self) -> Vec<Token> {
+        let mut tokens = Vec::with_capacity(self.tokens.len());
+        let mut replacements = self.replacements;
+        let mut pending_newline = false;
+        for (i, (mut token, removed)) in self
+            .tokens
+            .into_iter()
+            .zip(self.removed.into_iter())
+            .enumerate()
+        {
+            if let Some(replacement) = replacements.remove(&i) {
+                tokens.extend(replacement);
+                continue;
+            }
+            if removed {
+                pending_newline |= token.newline_before();
+                continue;
+            }
+            if pending_newline && !token.newline_before() {
+                token.leading.insert(0, '\n');
+            }
+            pending_newline = false;
+            tokens.push(token);
+        }
+        tokens
+    }
+}
+
+/// Parses a numeric literal, for the values of enum members.
+fn parse_number(text: &str) -> Option<f64> {
+    let text = text.replace('_', "");
+    let radix = match text.get(..2) {
+        Some("0x") | Some("0X") => 16,
+        Some("0o") | Some("0O") => 8,
+        Some("0b") | Some("0B") => 2,
+        _ => return text.parse().ok(),
+    };
+    u64::from_str_radix(&text[2..], radix)
+        .ok()
+        .map(|value| value as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip(source: &str) -> String {
+        let tokens = strip_types(tokenize(source, "test.ts").unwrap(), "test.ts").unwrap();
+        tokens
+            .iter()
+            .map(|token| token.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn it_strips_annotations() {
+        assert_eq!(
+            strip(
+                "const a: number = 1, b: Map<string, Array<number>> = new Map<string, number[]>();"
+            ),
+            "const a = 1 , b = new Map ( ) ;"
+        );
+        assert_eq!(
+            strip("function f<T>(x?: T, { y }: { y: string } = {}): x is T { return x as T; }"),
+            "function f ( x , { y } = { } ) { return x ; }"
+        );
+        assert_eq!(
+            strip("const g = async (a: string): Promise<void> => { a!.length; };"),
+            "const g = async ( a ) => { a . length ; } ;"
+        );
+        assert_eq!(
+            strip("const o = { a: 1, m(x: number): number { return c ? x : 1; } };"),
+            "const o = { a : 1 , m ( x ) { return c ? x : 1 ; } } ;"
+        );
+    }
+
+    #[test]
+    fn it_strips_declarations() {
+        assert_eq!(
+            strip("import type { A } from './a';\nimport { type B, c } from './b';\ntype C = A | B;\ninterface D extends A { d: C }\ndeclare const e: number;\nexport type { D };\nc();"),
+            "import { c } from './b' ; c ( ) ;"
+        );
+        assert_eq!(
+            strip("function f(a: string): void;\nfunction f(a: any) {}"),
+            "function f ( a ) { }"
+        );
+    }
+
+    #[test]
+    fn it_strips_classes() {
+        assert_eq!(
+            strip("abstract class A<T> extends B<T> implements C, D<T> {\n  private readonly x: number = 1;\n  y?: string;\n  declare z: number;\n  [key: string]: any;\n  abstract m(): void;\n  static n<U>(u: U): U { return u; }\n}"),
+            "class A extends B { x = 1 ; y ; static n ( u ) { return u ; } }"
+        );
+        assert!(strip_types(
+            tokenize("class A { constructor(private a: number) {} }", "test.ts").unwrap(),
+            "test.ts"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn it_compiles_enums() {
+        assert_eq!(
+            strip("enum E { A, B = 5, C, D = \"d\" }"),
+            "var E ; ( function ( E ) { E [ E [ \"A\" ] = 0 ] = \"A\" ; E [ E [ \"B\" ] = 5 ] = \"B\" ; E [ E [ \"C\" ] = 6 ] = \"C\" ; E [ \"D\" ] = \"d\" ; } ) ( E || ( E = { } ) ) ;"
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use super::Cli;
+use crate::commands;
+use crate::commands::middleware::NAMES;
+use crate::settings::toml::Manifest;
+
+use anyhow::Result;
+use structopt::StructOpt;
+
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(rename_all = "lower")]
+pub enum Add {
+    /// Generate a middleware that runs before your worker, import it in the entry point, and add
+    /// its settings to [vars]
+    Middleware {
+        /// The middleware to add
+        #[structopt(index = 1, possible_values = NAMES)]
+        name: String,
+    },
+}
+
+pub fn add(add: Add, cli_params: &Cli) -> Result<()> {
+    let manifest = Manifest::new(&cli_params.config)?;
+    let target = manifest.get_target(cli_params.environment.as_deref(), false)?;
+
+    match add {
+        Add::Middleware { name } => {
+            log::info!("Adding the {} middleware", name);
+            commands::middleware::add(&target, &cli_params.config, &name)
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! The `[alias]` table of a configuration file, whose commands are run by names of their own,
+//! like `wrangler deploy-prod` for `deploy-prod = "publish --env production"`. An alias is a
+//! command line, split like a shell does, or an array of arguments. The commands of wrangler
+//! can't be replaced by aliases, and an alias is never expanded into another alias.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use clap::ErrorKind;
+use serde::Deserialize;
+use structopt::StructOpt;
+
+use super::Cli;
+use crate::settings::toml::{find_config_file, read_value, CONFIG_FILE_NAMES};
+use crate::terminal::message::{Message, StdErr};
+
+/// The global options whose value is the next argument, which may come before the command.
+const VALUE_OPTIONS: &[&str] = &["--config", "-c", "--env", "-e", "--profile", "--account-id"];
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+enum Alias {
+    CommandLine(String),
+    Args(Vec<String>),
+}
+
+/// Replaces the name of an alias in the arguments of wrangler with the arguments of its command.
+/// The expansion is shown with `--verbose`.
+pub fn expand(args: Vec<OsString>) -> Result<Vec<OsString>> {
+    let index = match command_index(&args) {
+        Some(index) => index,
+        None => return Ok(args),
+    };
+    let name = match args[index].to_str() {
+        Some(name) if !is_command(name) => name,
+        _ => return Ok(args),
+    };
+    let config_path = config_path(&args[..index]);
+    let expansion = match find_alias(&config_path, name)? {
+        Some(Alias::CommandLine(command_line)) => split(&command_line)?,
+        Some(Alias::Args(args)) => args,
+        None => return Ok(args),
+    };
+    anyhow::ensure!(
+        !expansion.is_empty(),
+        "The alias {} in {} has no command",
+        name,
+        config_path.display()
+    );
+    if args.iter().any(|arg| arg == "--verbose") {
+        StdErr::info(&format!(
+            "Running `wrangler {}` for the alias {}",
+            expansion.join(" "),
+            name
+        ));
+    }
+
+    let mut expanded = args[..index].to_vec();
+    expanded.extend(expansion.into_iter().map(OsString::from));
+    expanded.extend(args[index + 1..].iter().cloned());
+    Ok(expanded)
+}
+
+/// The index of the command in the arguments of wrangler, after the global options.
+fn command_index(args: &[OsString]) -> Option<usize> {
+    let mut i = 1;
+    while i < args.len() {
+        let arg = args[i].to_string_lossy();
+        if arg == "--" {
+            return None;
+        } else if VALUE_OPTIONS.contains(&arg.as_ref()) {
+            i += 2;
+        } else if arg.starts_with('-') {
+            i += 1;
+        } else {
+            return Some(i);
+        }
+    }
+    None
+}
+
+fn is_command(name: &str) -> bool {
+    match Cli::clap().get_matches_from_safe(vec!["wrangler", name]) {
+        Err(e) => !matches!(
+            e.kind,
+            ErrorKind::UnrecognizedSubcommand | ErrorKind::InvalidSubcommand
+        ),
+        Ok(_) => true,
+    }
+}
+
+/// The configuration file that the global options before the command choose, like `main` does.
+fn config_path(options: &[OsString]) -> PathBuf {
+    let mut config = None;
+    for (i, option) in options.iter().enumerate() {
+        let option = option.to_string_lossy();
+        if option == "--config" || option == "-c" {
+            config = options.get(i + 1).map(PathBuf::from);
+        } else if let Some(path) = option.strip_prefix("--config=") {
+            config = Some(PathBuf::from(path));
+        }
+    }
+    config.unwrap_or_else(|| {
+        let default = PathBuf::from(CONFIG_FILE_NAMES[0]);
+        if default.exists() {
+            default
+        } else {
+            find_config_file(Path::new(".")).unwrap_or(default)
+        }
+    })
+}
+
+fn find_alias(config_path: &Path, name: &str) -> Result<Option<Alias>> {
+    if !config_path.exists() {
+        return Ok(None);
+    }
+    let config = read_value(config_path)?;
+    match config.get("alias").and_then(|aliases| aliases.get(name)) {
+        Some(alias) => match serde_json::from_value(alias.clone()) {
+            Ok(alias) => Ok(Some(alias)),
+            Err(_) => anyhow::bail!(
+                "The alias {} in {} must be a command line or an array of arguments",
+                name,
+                config_path.display()
+            ),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Splits a command line into arguments at whitespace, except in quotes. Like in a shell, a
+/// backslash escapes a character outside of single quotes.
+fn split(command_line: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut arg: Option<String> = None;
+    let mut quote = None;
+    let mut chars = command_line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\'') | (Some('"'), '"') => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                let escaped = chars
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("`{}` ends with a backslash", command_line))?;
+                arg.get_or_insert_with(String::new).push(escaped);
+            }
+            (Some(_), c) => arg.get_or_insert_with(String::new).push(c),
+            (None, '\'') | (None, '"') => {
+                quote = Some(c);
+                arg.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => args.extend(arg.take()),
+            (None, c) => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    anyhow::ensure!(quote.is_none(), "`{}` has an unclosed quote", command_line);
+    args.extend(arg);
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn os_args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn it_splits_command_lines() {
+        assert_eq!(
+            split("publish  --env production").unwrap(),
+            vec!["publish", "--env", "production"]
+        );
+        assert_eq!(
+            split(r#"kv:key put "a key" 'it''s' "say \"hi\"" a\ b ''"#).unwrap(),
+            vec!["kv:key", "put", "a key", "its", "say \"hi\"", "a b", ""]
+        );
+        assert!(split("publish 'production").is_err());
+    }
+
+    #[test]
+    fn it_finds_the_command_after_global_options() {
+        assert_eq!(
+            command_index(&os_args(&[
+                "wrangler",
+                "--verbose",
+                "-c",
+                "a.toml",
+                "deploy-prod"
+            ])),
+            Some(4)
+        );
+        assert_eq!(command_index(&os_args(&["wrangler", "--verbose"])), None);
+        assert_eq!(
+            config_path(&os_args(&["wrangler", "--config=b.toml"])),
+            PathBuf::from("b.toml")
+        );
+    }
+
+    #[test]
+    fn it_never_replaces_commands() {
+        assert!(is_command("publish"));
+        assert!(is_command("kv:key"));
+        assert!(!is_command("deploy-prod"));
+    }
+
+    #[test]
+    fn it_expands_aliases() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("wrangler.toml");
+        std::fs::write(
+            &config,
+            "name = \"worker\"\ntype = \"javascript\"\n\n[alias]\ndeploy-prod = \"publish --env production\"\nlogs = [\"tail\", \"--format\", \"pretty\"]\n",
+        )
+        .unwrap();
+        let config = config.to_string_lossy().into_owned();
+
+        assert_eq!(
+            expand(os_args(&[
+                "wrangler",
+                "-c",
+                &config,
+                "deploy-prod",
+                "--verbose"
+            ]))
+            .unwrap(),
+            os_args(&[
+                "wrangler",
+                "-c",
+                &config,
+                "publish",
+                "--env",
+                "production",
+                "--verbose"
+            ])
+        );
+        assert_eq!(
+            expand(os_args(&["wrangler", "-c", &config, "logs"])).unwrap(),
+            os_args(&["wrangler", "-c", &config, "tail", "--format", "pretty"])
+        );
+        assert_eq!(
+            expand(os_args(&["wrangler", "-c", &config, "unknown"])).unwrap(),
+            os_args(&["wrangler", "-c", &config, "unknown"])
+        );
+    }
+}
@@ -1,13 +1,104 @@
 use super::Cli;
+use crate::build::diagnostics::{BuildError, Diagnostic};
+use crate::build::{analyze, reproducible};
 use crate::build_target;
 use crate::settings::toml::Manifest;
-use crate::terminal::message::{Message, StdOut};
+use crate::terminal::message::{Message, Output, StdErr, StdOut};
+use crate::watch::build_on_changes;
+
+use std::fs;
+use std::path::PathBuf;
 
 use anyhow::Result;
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct BuildOutput<'a> {
+    success: bool,
+    message: String,
+    diagnostics: &'a [Diagnostic],
+    content_hash: Option<String>,
+}
 
-pub fn build(cli_params: &Cli) -> Result<()> {
+pub fn build(
+    watch: bool,
+    analyze: bool,
+    treemap: Option<PathBuf>,
+    reproducible: bool,
+    output: Option<String>,
+    cli_params: &Cli,
+) -> Result<()> {
+    if reproducible {
+        reproducible::set_reproducible();
+    }
+    let output = if output.as_deref() == Some("json") {
+        Output::Json
+    } else {
+        Output::PlainText
+    };
     log::info!("Getting project settings");
     let manifest = Manifest::new(&cli_params.config)?;
     let target = manifest.get_target(cli_params.environment.as_deref(), false)?;
-    build_target(&target).map(|msg| StdOut::success(&msg))
+    if watch {
+        build_on_changes(&target)
+    } else if analyze {
+        let modules = analyze::analyze(&target)?;
+        println!("{}", analyze::report(&modules));
+        if let Some(treemap) = treemap {
+            fs::write(&treemap, analyze::treemap(&modules))?;
+            StdOut::success(&format!("Drew the treemap in {}", treemap.display()));
+        }
+        Ok(())
+    } else {
+        let msg = match build_target(&target) {
+            Ok(msg) => msg,
+            Err(e) => {
+                report_failure(&e, output);
+                return Err(e);
+            }
+        };
+        let content_hash = if reproducible {
+            Some(reproducible::content_hash(&target)?)
+        } else {
+            None
+        };
+        match output {
+            Output::Json => {
+                StdErr::success(&msg);
+                StdOut::as_json(&BuildOutput {
+                    success: true,
+                    message: msg,
+                    diagnostics: &[],
+                    content_hash,
+                });
+            }
+            Output::PlainText => {
+                StdOut::success(&msg);
+                if let Some(content_hash) = content_hash {
+                    StdOut::info(&format!("Content hash: {}", content_hash));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reports the diagnostics of a failed build, after the output of the tool that emitted them.
+fn report_failure(e: &anyhow::Error, output: Output) {
+    let diagnostics = e
+        .downcast_ref::<BuildError>()
+        .map_or(&[][..], |e| e.diagnostics.as_slice());
+    match output {
+        Output::Json => StdOut::as_json(&BuildOutput {
+            success: false,
+            message: e.to_string(),
+            diagnostics,
+            content_hash: None,
+        }),
+        Output::PlainText => {
+            for diagnostic in diagnostics {
+                StdErr::message(&diagnostic.to_string());
+            }
+        }
+    }
 }
@@ -0,0 +1,9 @@
+use super::Cli;
+use crate::commands;
+
+use anyhow::Result;
+
+pub fn check(cli_params: &Cli) -> Result<()> {
+    log::info!("Checking project settings");
+    commands::check::check(&cli_params.config)
+}
@@ -1,12 +1,61 @@
+use super::Cli;
 use crate::commands;
 use crate::settings::global_user::GlobalUser;
 use crate::terminal::message::{Message, StdOut};
 use crate::terminal::{interactive, styles};
 
+use std::path::PathBuf;
+
 use anyhow::Result;
+use structopt::StructOpt;
+
+#[derive(Debug, Clone, StructOpt)]
+pub enum ConfigCommand {
+    /// List the authentication profiles that have been configured
+    #[structopt(name = "list-profiles")]
+    ListProfiles,
+
+    /// Move the credentials of the profile from its configuration file to the OS keychain. Needs a
+    /// wrangler built with `--features keychain`
+    #[structopt(name = "use-keychain")]
+    UseKeychain,
+
+    /// Rewrite deprecated settings of the configuration file into the current layout
+    #[structopt(name = "migrate")]
+    Migrate,
+
+    /// Write a JSON Schema of the configuration file, for editors to complete and validate it
+    #[structopt(name = "schema")]
+    Schema {
+        /// The file to write the schema to, instead of the standard output
+        #[structopt(long, short = "o")]
+        out: Option<PathBuf>,
+    },
+}
+
+pub fn configure(
+    api_key: bool,
+    service_token: bool,
+    no_verify: bool,
+    keychain: bool,
+    default_account_id: Option<String>,
+) -> Result<()> {
+    let user: GlobalUser = if service_token {
+        StdOut::billboard(&format!(
+            concat!(
+                "To create a Service Token, go to {}\n",
+                "and copy its Client ID and Client Secret, which are only shown once.\n",
+                "\n",
+                "Service Tokens belong to your account rather than to a user, for machines\n",
+                "where the API Tokens of users are not allowed."
+            ),
+            styles::url("https://dash.teams.cloudflare.com/")
+        ));
+        let access_client_id: String = interactive::get_user_input("Enter Client ID: ");
+        let access_client_secret: String = interactive::get_user_input("Enter Client Secret: ");
 
-pub fn configure(api_key: bool, no_verify: bool) -> Result<()> {
-    let user: GlobalUser = if !api_key {
+        GlobalUser::service_token(access_client_id, access_client_secret)?
+    } else if !api_key {
         // API Tokens are the default
         StdOut::billboard(&format!(
             concat!(
@@ -43,5 +92,26 @@ pub fn configure(api_key: bool, no_verify: bool) -> Result<()> {
         GlobalUser::GlobalKeyAuth { email, api_key }
     };
 
-    commands::global_config(&user, !no_verify)
+    commands::global_config(&user, !no_verify, keychain)?;
+    if let Some(account_id) = default_account_id {
+        commands::config::set_default_account_id(&account_id)?;
+    }
+    Ok(())
+}
+
+pub fn list_profiles() -> Result<()> {
+    commands::config::list_profiles()
+}
+
+pub fn use_keychain() -> Result<()> {
+    commands::config::migrate_to_keychain()
+}
+
+pub fn migrate(cli_params: &Cli) -> Result<()> {
+    log::info!("Migrating project settings");
+    commands::migrate::migrate(&cli_params.config)
+}
+
+pub fn schema(out: Option<PathBuf>) -> Result<()> {
+    commands::config::write_schema(out.as_deref())
 }
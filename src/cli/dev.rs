@@ -1,7 +1,10 @@
 use std::net::{IpAddr, Ipv4Addr};
 
 use super::Cli;
-use crate::commands::{self, dev::Protocol};
+use crate::commands::{
+    self,
+    dev::{Limits, Protocol},
+};
 use crate::settings::{global_user::GlobalUser, toml::Manifest};
 
 use anyhow::Result;
@@ -16,6 +19,11 @@ pub fn dev(
     cli_params: &Cli,
     inspect: bool,
     unauthenticated: bool,
+    upload_assets: bool,
+    mut kv_propagation_delay: Option<u64>,
+    mut simulate_limits: bool,
+    mut subrequest_limit: Option<u32>,
+    mut cpu_limit: Option<u64>,
 ) -> Result<()> {
     log::info!("Starting dev server");
     let manifest = Manifest::new(&cli_params.config)?;
@@ -27,6 +35,10 @@ pub fn dev(
         port = port.or(d.port);
         local_protocol = local_protocol.or(d.local_protocol);
         upstream_protocol = upstream_protocol.or(d.upstream_protocol);
+        kv_propagation_delay = kv_propagation_delay.or(d.kv_propagation_delay);
+        simulate_limits = simulate_limits || d.simulate_limits.unwrap_or_default();
+        subrequest_limit = subrequest_limit.or(d.subrequest_limit);
+        cpu_limit = cpu_limit.or(d.cpu_limit);
     }
 
     let ip = ip.unwrap_or_else(|| Ipv4Addr::new(127, 0, 0, 1).into());
@@ -37,6 +49,12 @@ pub fn dev(
     let deployments = manifest.get_deployments(cli_params.environment.as_deref())?;
     let target = manifest.get_target(cli_params.environment.as_deref(), true)?;
     let user = GlobalUser::new().ok();
+    let limits = Limits::new(
+        target.usage_model,
+        simulate_limits,
+        subrequest_limit,
+        cpu_limit,
+    );
 
     let server_config = commands::dev::ServerConfig::new(host, ip, port, upstream_protocol)?;
 
@@ -50,5 +68,8 @@ pub fn dev(
         cli_params.verbose,
         inspect,
         unauthenticated,
+        upload_assets,
+        kv_propagation_delay,
+        limits,
     )
 }
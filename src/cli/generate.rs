@@ -1,14 +1,53 @@
 use crate::commands;
+use crate::commands::generate::{GenerateType, GitOptions};
 use crate::settings::toml::TargetType;
+use crate::terminal::interactive;
 
 use anyhow::Result;
 
+/// Generates a workspace of workers in a new directory.
+pub fn generate_workspace(name: String, workers: Vec<String>, git: &GitOptions) -> Result<()> {
+    let name = commands::generate::project_name(&name);
+    commands::generate_workspace(&name, &workers, git)
+}
+
+/// Lists the templates of the registry, returning the one that the user picks.
+pub fn browse_templates(search: Option<&str>, yes: bool) -> Result<Option<String>> {
+    if yes {
+        interactive::set_accept_defaults();
+    }
+    commands::generate::registry::browse(search)
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn generate(
     name: String,
     site: bool,
     template: Option<String>,
-    target_type: Option<TargetType>,
+    branch: Option<String>,
+    defines: Vec<(String, String)>,
+    target_type: Option<GenerateType>,
+    yes: bool,
+    git: &GitOptions,
+    install: bool,
 ) -> Result<()> {
+    if yes {
+        interactive::set_accept_defaults();
+    }
+
+    let target_type = match target_type {
+        Some(GenerateType::Starter(starter)) => {
+            anyhow::ensure!(
+                template.is_none() && !site,
+                "The {} starter is built in, and can't be generated from a template or as a site",
+                starter
+            );
+            return commands::generate::generate_starter(&name, starter, git);
+        }
+        Some(GenerateType::Target(target_type)) => Some(target_type),
+        None => None,
+    };
+
     const DEFAULT_TEMPLATE: &str = "https://github.com/cloudflare/worker-template";
     const RUST_TEMPLATE: &str = "https://github.com/cloudflare/rustwasm-worker-template";
     const SITES_TEMPLATE: &str = "https://github.com/cloudflare/worker-sites-template";
@@ -29,5 +68,15 @@ pub fn generate(
         name
     );
 
-    commands::generate(&name, template, target_type, site)
+    let defines = defines.into_iter().collect();
+    commands::generate(
+        &name,
+        template,
+        branch.as_deref(),
+        &defines,
+        target_type,
+        site,
+        git,
+        install,
+    )
 }
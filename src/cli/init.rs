@@ -1,7 +1,24 @@
+use crate::settings::global_user::GlobalUser;
+use crate::terminal::interactive;
 use crate::{commands, settings::toml::TargetType};
 use anyhow::Result;
 
-pub fn init(name: Option<String>, site: bool, target_type: Option<TargetType>) -> Result<()> {
+pub fn init(
+    name: Option<String>,
+    site: bool,
+    target_type: Option<TargetType>,
+    force: bool,
+    yes: bool,
+    from_dash: Option<String>,
+) -> Result<()> {
+    if yes {
+        interactive::set_accept_defaults();
+    }
+    if let Some(worker) = from_dash {
+        let user = GlobalUser::new()?;
+        return commands::init::init_from_dash(&worker, &user, force);
+    }
+
     let target_type = if site {
         // Workers Sites projects are always webpack for now
         Some(TargetType::Webpack)
@@ -9,5 +26,5 @@ pub fn init(name: Option<String>, site: bool, target_type: Option<TargetType>) -
         target_type
     };
 
-    commands::init(name.as_deref(), target_type, site)
+    commands::init(name.as_deref(), target_type, site, force)
 }
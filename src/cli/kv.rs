@@ -1,8 +1,13 @@
 use std::path::PathBuf;
+use std::time::Duration;
 
 use super::Cli;
 use crate::commands;
-use crate::commands::kv::key::{parse_metadata, KVMetaData};
+use crate::commands::kv::bulk::transform::Transform;
+use crate::commands::kv::key::{parse_metadata, KVMetaData, STDIN_PATH};
+use crate::commands::kv::namespace::TitlePattern;
+use crate::commands::kv::ListFormat;
+use crate::kv::encryption::Recipient;
 use crate::settings::{global_user::GlobalUser, toml::Manifest};
 
 use anyhow::{anyhow, Result};
@@ -17,9 +22,14 @@ pub enum KvNamespace {
         /// The binding for your new namespace
         #[structopt(index = 1)]
         binding: String,
-        /// Applies the command to the preview namespace
+        /// Applies the command to the preview namespace, which is created along with the
+        /// production namespace when combined with --update-config
         #[structopt(name = "preview", long)]
         preview: bool,
+        /// Add the namespace to kv_namespaces of the environment in your configuration file,
+        /// instead of printing the TOML to add
+        #[structopt(name = "update-config", long)]
+        update_config: bool,
     },
     /// Delete namespace
     Delete {
@@ -31,7 +41,30 @@ pub enum KvNamespace {
         force: bool,
     },
     /// List all namespaces on your Cloudflare account
-    List,
+    List {
+        /// How to print the namespaces: a JSON array, a table, or an ID per line
+        #[structopt(long, default_value = "json", possible_values = &["json", "table", "plain"])]
+        format: ListFormat,
+
+        /// Only list the namespaces whose title matches a glob, like my-worker-*, or a regex
+        /// between slashes, like /^my-worker-/
+        #[structopt(long)]
+        pattern: Option<TitlePattern>,
+    },
+    /// Copy all the key-value pairs of a namespace, with their metadata and expiration, into
+    /// another namespace
+    Copy {
+        /// The ID of the namespace to copy the pairs from
+        #[structopt(long)]
+        from: String,
+        /// The ID of the namespace to copy the pairs into
+        #[structopt(long)]
+        to: String,
+        /// The account of the namespace to copy the pairs into, if it's not the account of the
+        /// namespace they are copied from
+        #[structopt(long = "to-account")]
+        to_account: Option<String>,
+    },
 }
 
 #[derive(Debug, Clone, StructOpt)]
@@ -68,8 +101,8 @@ pub enum KvKey {
         key: String,
 
         /// Value for key
-        #[structopt(name = "value", index = 2)]
-        value: String,
+        #[structopt(name = "value", index = 2, required_unless = "stdin")]
+        value: Option<String>,
 
         /// Number of seconds for which the entries should be visible before they expire.
         /// At least 60. Takes precedence over 'expiration' option.
@@ -84,9 +117,19 @@ pub enum KvKey {
         #[structopt(name = "metadata", long, short = "m")]
         metadata: Option<String>,
 
-        /// The value passed in is a path to a file; open and upload its contents
+        /// The value passed in is a path to a file; open and upload its contents. A path of `-`
+        /// reads the value from stdin
         #[structopt(name = "path", long, short = "p")]
         path: bool,
+
+        /// Read the value from stdin, e.g. to pipe it from another command
+        #[structopt(long, conflicts_with_all = &["value", "path"])]
+        stdin: bool,
+
+        /// Encrypt the value to an age public key before it is uploaded, in the form
+        /// age:<recipient>. Can be repeated to let any of several keys decrypt it
+        #[structopt(long = "encrypt", number_of_values = 1)]
+        recipients: Vec<Recipient>,
     },
     /// Get a key's value from a namespace
     Get {
@@ -96,6 +139,50 @@ pub enum KvKey {
         /// Key whose value to get
         #[structopt(name = "key", index = 1)]
         key: String,
+
+        /// Write the value to a file instead of stdout, byte for byte
+        #[structopt(long, short = "o")]
+        output: Option<PathBuf>,
+
+        /// Encode the value in base64, e.g. to print a binary value in a terminal
+        #[structopt(long)]
+        base64: bool,
+
+        /// Decrypt a value that was uploaded with --encrypt, with the age identities of this file
+        #[structopt(long, value_name = "identity-file")]
+        decrypt: Option<PathBuf>,
+
+        /// Print the value with its metadata and expiration as a JSON object, like the pairs of
+        /// kv:bulk put
+        #[structopt(long = "with-metadata")]
+        with_metadata: bool,
+    },
+    /// Get the values of several keys of a namespace at once, printed as a JSON object from the
+    /// keys to their values, or null for the keys that don't exist
+    #[structopt(name = "get-many")]
+    GetMany {
+        #[structopt(flatten)]
+        namespace: Namespace,
+
+        /// Keys whose values to get
+        #[structopt(name = "key", index = 1, required_unless = "keys-file")]
+        keys: Vec<String>,
+
+        /// A file of keys whose values to get, one per line
+        #[structopt(name = "keys-file", long)]
+        keys_file: Option<PathBuf>,
+
+        /// Encode the values in base64, which values that are not UTF-8 need
+        #[structopt(long)]
+        base64: bool,
+
+        /// Decrypt values that were uploaded with --encrypt, with the age identities of this file
+        #[structopt(long, value_name = "identity-file")]
+        decrypt: Option<PathBuf>,
+
+        /// How many values to get at a time
+        #[structopt(long, default_value = "8")]
+        concurrency: usize,
     },
     /// Delete a key and its value from a namespace
     Delete {
@@ -110,7 +197,7 @@ pub enum KvKey {
         #[structopt(name = "force", long, short = "f")]
         force: bool,
     },
-    /// List all keys in a namespace. Produces JSON output
+    /// List all keys in a namespace
     List {
         #[structopt(flatten)]
         namespace: Namespace,
@@ -118,6 +205,37 @@ pub enum KvKey {
         /// The prefix for filtering listed keys
         #[structopt(name = "prefix", long, short = "p")]
         prefix: Option<String>,
+
+        /// The most keys to list. The cursor to list the keys that follow is printed to stderr
+        #[structopt(long)]
+        limit: Option<usize>,
+
+        /// The cursor printed by an earlier listing, to list the keys that follow it
+        #[structopt(long)]
+        cursor: Option<String>,
+
+        /// How to print the keys: a JSON array of objects with their name, expiration and
+        /// metadata, a table, or a name per line
+        #[structopt(long, default_value = "json", possible_values = &["json", "table", "plain"])]
+        format: ListFormat,
+    },
+    /// Poll a key and print how its value changes, until interrupted
+    Watch {
+        #[structopt(flatten)]
+        namespace: Namespace,
+
+        /// Key whose value to watch
+        #[structopt(name = "key", index = 1)]
+        key: String,
+
+        /// How often to fetch the value, e.g. 30s or 1m
+        #[structopt(long, default_value = "5s", parse(try_from_str = super::parse_interval))]
+        interval: Duration,
+
+        /// A shell command to run whenever the value changes, with the key in $WRANGLER_KV_KEY
+        /// and the new value on its stdin
+        #[structopt(long)]
+        exec: Option<String>,
     },
 }
 
@@ -129,9 +247,27 @@ pub enum KvBulk {
         #[structopt(flatten)]
         namespace: Namespace,
 
-        /// The JSON file of key-value pairs to upload, in form [{\"key\":..., \"value\":...}\"...]
+        /// The JSON file of key-value pairs to upload, in form [{\"key\":..., \"value\":...}\"...].
+        /// Each pair may have arbitrary JSON of no more than 1024 bytes as its \"metadata\"
         #[structopt(index = 1)]
         path: PathBuf,
+
+        /// Rewrite the pairs before they are uploaded, can be repeated to apply several steps in
+        /// order: prefix:<text>, suffix:<text>, replace:<regex>=<replacement> rewrite the keys,
+        /// minify rewrites JSON values without whitespace, and csv converts CSV values with a
+        /// header row to JSON
+        #[structopt(long = "transform", number_of_values = 1)]
+        transforms: Vec<Transform>,
+
+        /// Encrypt the values to an age public key after they are transformed, in the form
+        /// age:<recipient>. Can be repeated to let any of several keys decrypt them. The
+        /// metadata is not encrypted
+        #[structopt(long = "encrypt", number_of_values = 1)]
+        recipients: Vec<Recipient>,
+
+        /// How many batches of pairs to upload at a time
+        #[structopt(long, default_value = "4")]
+        concurrency: usize,
     },
     /// Delete multiple keys and their values from a namespace
     Delete {
@@ -146,6 +282,40 @@ pub enum KvBulk {
         #[structopt(name = "force", long, short = "f")]
         force: bool,
     },
+    /// Download all key-value pairs of a namespace to a JSON file that `kv:bulk put` uploads
+    Export {
+        #[structopt(flatten)]
+        namespace: Namespace,
+
+        /// The JSON file to write the key-value pairs to, with their metadata and expiration
+        #[structopt(long)]
+        out: PathBuf,
+
+        /// Decrypt values that were uploaded with --encrypt, with the age identities of this file
+        #[structopt(long, value_name = "identity-file")]
+        decrypt: Option<PathBuf>,
+    },
+    /// Mirror a directory into a namespace, keyed by the paths of its files, uploading only the
+    /// files that changed and deleting the keys of files that don't exist anymore
+    Sync {
+        #[structopt(flatten)]
+        namespace: Namespace,
+
+        /// The directory to mirror
+        #[structopt(index = 1)]
+        path: PathBuf,
+
+        /// Deletes keys without user confirmation
+        #[structopt(name = "force", long, short = "f")]
+        force: bool,
+    },
+    /// Check a JSON file of key-value pairs against the limits of Workers KV before uploading it
+    /// with `kv:bulk put`, reporting the line of every problem
+    Validate {
+        /// The JSON file of key-value pairs to check
+        #[structopt(index = 1)]
+        path: PathBuf,
+    },
 }
 
 pub fn kv_namespace(namespace: KvNamespace, cli_params: &Cli) -> Result<()> {
@@ -154,8 +324,23 @@ pub fn kv_namespace(namespace: KvNamespace, cli_params: &Cli) -> Result<()> {
     let env = cli_params.environment.as_deref();
 
     match namespace {
-        KvNamespace::Create { binding, preview } => {
-            commands::kv::namespace::create(&manifest, preview, env, &user, &binding)
+        KvNamespace::Create {
+            binding,
+            preview,
+            update_config,
+        } => {
+            if update_config {
+                commands::kv::namespace::create_and_update_config(
+                    &manifest,
+                    &cli_params.config,
+                    preview,
+                    env,
+                    &user,
+                    &binding,
+                )
+            } else {
+                commands::kv::namespace::create(&manifest, preview, env, &user, &binding)
+            }
         }
         KvNamespace::Delete { namespace, force } => {
             let target = manifest.get_target(env, namespace.preview)?;
@@ -168,9 +353,17 @@ pub fn kv_namespace(namespace: KvNamespace, cli_params: &Cli) -> Result<()> {
             };
             commands::kv::namespace::delete(&target, &user, &id, force)
         }
-        KvNamespace::List => {
+        KvNamespace::List { format, pattern } => {
             let target = manifest.get_target(env, false)?;
-            commands::kv::namespace::list(&target, &user)
+            commands::kv::namespace::list(&target, &user, format, pattern.as_ref())
+        }
+        KvNamespace::Copy {
+            from,
+            to,
+            to_account,
+        } => {
+            let target = manifest.get_target(env, false)?;
+            commands::kv::namespace::copy(&target, &user, &from, &to, to_account.as_deref())
         }
     }
 }
@@ -193,9 +386,52 @@ pub fn kv_key(key: KvKey, cli_params: &Cli) -> Result<()> {
     };
 
     match key {
-        KvKey::Get { namespace, key } => {
+        KvKey::Get {
+            namespace,
+            key,
+            output,
+            base64,
+            decrypt,
+            with_metadata,
+        } => {
+            let (target, namespace_id) = target_and_namespace(namespace)?;
+            let get = if with_metadata {
+                commands::kv::key::get_with_metadata
+            } else {
+                commands::kv::key::get
+            };
+            get(
+                &target,
+                &user,
+                &namespace_id,
+                &key,
+                output.as_deref(),
+                base64,
+                decrypt.as_deref(),
+            )
+        }
+        KvKey::GetMany {
+            namespace,
+            keys,
+            keys_file,
+            base64,
+            decrypt,
+            concurrency,
+        } => {
+            if concurrency == 0 {
+                anyhow::bail!("--concurrency must be at least 1");
+            }
             let (target, namespace_id) = target_and_namespace(namespace)?;
-            commands::kv::key::get(&target, &user, &namespace_id, &key)
+            commands::kv::key::get_many(
+                &target,
+                &user,
+                &namespace_id,
+                keys,
+                keys_file.as_deref(),
+                base64,
+                decrypt.as_deref(),
+                concurrency,
+            )
         }
         KvKey::Put {
             namespace,
@@ -205,8 +441,16 @@ pub fn kv_key(key: KvKey, cli_params: &Cli) -> Result<()> {
             expiration_ttl,
             expiration,
             metadata,
+            recipients,
+            stdin,
         } => {
             let (target, namespace_id) = target_and_namespace(namespace)?;
+            // stdin is read like a file named `-`
+            let (value, is_file) = if stdin {
+                (STDIN_PATH.to_string(), true)
+            } else {
+                (value.expect("Value is required without --stdin"), is_file)
+            };
             let expiration = expiration.as_ref().map(ToString::to_string);
             let expiration_ttl = expiration_ttl.as_ref().map(ToString::to_string);
             let metadata = parse_metadata(metadata.as_deref())
@@ -223,6 +467,7 @@ pub fn kv_key(key: KvKey, cli_params: &Cli) -> Result<()> {
                     expiration,
                     expiration_ttl,
                     metadata,
+                    recipients,
                 },
             )
         }
@@ -234,14 +479,52 @@ pub fn kv_key(key: KvKey, cli_params: &Cli) -> Result<()> {
             let (target, namespace_id) = target_and_namespace(namespace)?;
             commands::kv::key::delete(&target, &user, &namespace_id, &key, force)
         }
-        KvKey::List { namespace, prefix } => {
+        KvKey::Watch {
+            namespace,
+            key,
+            interval,
+            exec,
+        } => {
             let (target, namespace_id) = target_and_namespace(namespace)?;
-            commands::kv::key::list(&target, &user, &namespace_id, prefix.as_deref())
+            commands::kv::key::watch(
+                &target,
+                &user,
+                &namespace_id,
+                &key,
+                interval,
+                exec.as_deref(),
+            )
+        }
+        KvKey::List {
+            namespace,
+            prefix,
+            limit,
+            cursor,
+            format,
+        } => {
+            if limit == Some(0) {
+                anyhow::bail!("--limit must be at least 1");
+            }
+            let (target, namespace_id) = target_and_namespace(namespace)?;
+            commands::kv::key::list(
+                &target,
+                &user,
+                &namespace_id,
+                prefix.as_deref(),
+                cursor,
+                limit,
+                format,
+            )
         }
     }
 }
 
 pub fn kv_bulk(bulk: KvBulk, cli_params: &Cli) -> Result<()> {
+    // validating a file needs neither a configuration nor a login
+    if let KvBulk::Validate { path } = &bulk {
+        return commands::kv::bulk::validate(path);
+    }
+
     // Get environment and bindings
     let manifest = Manifest::new(&cli_params.config)?;
     let user = GlobalUser::new()?;
@@ -260,9 +543,26 @@ pub fn kv_bulk(bulk: KvBulk, cli_params: &Cli) -> Result<()> {
     };
 
     match bulk {
-        KvBulk::Put { namespace, path } => {
+        KvBulk::Put {
+            namespace,
+            path,
+            transforms,
+            recipients,
+            concurrency,
+        } => {
+            if concurrency == 0 {
+                anyhow::bail!("--concurrency must be at least 1");
+            }
             let (target, namespace_id) = target_and_namespace(namespace)?;
-            commands::kv::bulk::put(&target, &user, &namespace_id, &path)
+            commands::kv::bulk::put(
+                &target,
+                &user,
+                &namespace_id,
+                &path,
+                &transforms,
+                &recipients,
+                concurrency,
+            )
         }
         KvBulk::Delete {
             namespace,
@@ -272,5 +572,22 @@ pub fn kv_bulk(bulk: KvBulk, cli_params: &Cli) -> Result<()> {
             let (target, namespace_id) = target_and_namespace(namespace)?;
             commands::kv::bulk::delete(&target, &user, &namespace_id, &path, force)
         }
+        KvBulk::Export {
+            namespace,
+            out,
+            decrypt,
+        } => {
+            let (target, namespace_id) = target_and_namespace(namespace)?;
+            commands::kv::bulk::export(&target, &user, &namespace_id, &out, decrypt.as_deref())
+        }
+        KvBulk::Sync {
+            namespace,
+            path,
+            force,
+        } => {
+            let (target, namespace_id) = target_and_namespace(namespace)?;
+            commands::kv::bulk::sync(&target, &user, &namespace_id, &path, force)
+        }
+        KvBulk::Validate { .. } => unreachable!(),
     }
 }
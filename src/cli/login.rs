@@ -40,7 +40,17 @@ fn format_scopes(scopes: &[&str], descriptions: &[&str]) -> Table {
     table
 }
 
-pub fn login(scopes: &[String], scopes_list: bool) -> Result<(), anyhow::Error> {
+pub fn login(
+    scopes: &[String],
+    scopes_list: bool,
+    keychain: bool,
+    default_account_id: Option<String>,
+    refresh: bool,
+) -> Result<(), anyhow::Error> {
+    if refresh {
+        return commands::login::refresh();
+    }
+
     if scopes_list {
         StdOut::info(&format!(
             "Available scopes \n\n{}",
@@ -49,11 +59,16 @@ pub fn login(scopes: &[String], scopes_list: bool) -> Result<(), anyhow::Error>
         return Ok(());
     }
 
-    // User provided scopes
     if !scopes.is_empty() {
-        return commands::login::run(Some(scopes));
+        // User provided scopes
+        commands::login::run(Some(scopes), keychain)?;
+    } else {
+        // No user input, default scopes
+        commands::login::run(None, keychain)?;
     }
 
-    // No user input, default scopes
-    commands::login::run(None)
+    if let Some(account_id) = default_account_id {
+        commands::config::set_default_account_id(&account_id)?;
+    }
+    Ok(())
 }
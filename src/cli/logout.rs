@@ -1,5 +1,5 @@
 use crate::commands;
 
-pub fn logout() -> Result<(), anyhow::Error> {
-    commands::logout::run()
+pub fn logout(keep_remote: bool) -> Result<(), anyhow::Error> {
+    commands::logout::run(keep_remote)
 }
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use super::tail::Pretty;
+use super::{parse_duration, Cli};
+use crate::commands;
+use crate::commands::logs::Query;
+use crate::commands::tail::sourcemap::SourceMaps;
+use crate::commands::tail::websocket::TailFormat;
+use crate::settings::toml::Manifest;
+
+use anyhow::Result;
+use structopt::StructOpt;
+
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(rename_all = "lower")]
+pub enum Logs {
+    /// Print the past events of a worker from the R2 bucket of its Logpush job, set by
+    /// `[logs] logpush_bucket`
+    Query {
+        /// Name of the worker, the one of the configuration by default
+        #[structopt(index = 1)]
+        name: Option<String>,
+
+        /// How far back to look (e.g. 30m, 1h, 24h)
+        #[structopt(long, default_value = "1h", parse(try_from_str = parse_duration))]
+        since: Duration,
+
+        /// Filter by invocation status
+        #[structopt(long, possible_values = &["ok", "error", "canceled"])]
+        status: Vec<String>,
+
+        /// Output format for log messages
+        #[structopt(long, short = "f", default_value = "json", possible_values = &["json", "ndjson", "pretty"])]
+        format: TailFormat,
+
+        #[structopt(flatten)]
+        pretty: Pretty,
+    },
+}
+
+pub fn logs(logs: Logs, cli_params: &Cli) -> Result<()> {
+    let manifest = Manifest::new(&cli_params.config)?;
+    let env = cli_params.environment.as_deref();
+
+    match logs {
+        Logs::Query {
+            name,
+            since,
+            status,
+            format,
+            pretty,
+        } => {
+            let config = manifest.logs.clone().ok_or_else(|| {
+                anyhow::anyhow!(
+                    "wrangler logs query reads the events that Logpush writes to R2, set the bucket with `[logs] logpush_bucket` in your configuration file"
+                )
+            })?;
+            let target = manifest.get_target(env, false)?;
+            let since = chrono::Duration::from_std(since)?;
+            let query = Query {
+                script_name: name.unwrap_or_else(|| target.name.clone()),
+                since: chrono::Utc::now() - since,
+                status,
+                format,
+                pretty: pretty.into_format(manifest.tail.as_ref(), SourceMaps::for_target(&target)),
+            };
+            commands::logs::query(&target, &config, query)
+        }
+    }
+}
@@ -1,4 +1,7 @@
+pub mod add;
+pub mod alias;
 pub mod build;
+pub mod check;
 pub mod config;
 pub mod dev;
 pub mod generate;
@@ -6,41 +9,56 @@ pub mod init;
 pub mod kv;
 pub mod login;
 pub mod logout;
+pub mod logs;
 pub mod preview;
+pub mod preview_env;
 pub mod publish;
 pub mod r2;
 pub mod route;
 pub mod secret;
+pub mod sites;
 pub mod subdomain;
 pub mod tail;
+pub mod types;
 pub mod whoami;
 
 pub mod exec {
+    pub use super::add::add;
     pub use super::build::build;
-    pub use super::config::configure;
+    pub use super::check::check;
+    pub use super::config::{configure, list_profiles, migrate, schema, use_keychain};
     pub use super::dev::dev;
-    pub use super::generate::generate;
+    pub use super::generate::{browse_templates, generate, generate_workspace};
     pub use super::init::init;
     pub use super::kv::kv_bulk;
     pub use super::kv::kv_key;
     pub use super::kv::kv_namespace;
     pub use super::login::login;
     pub use super::logout::logout;
+    pub use super::logs::logs;
     pub use super::preview::preview;
+    pub use super::preview_env::preview_env;
     pub use super::publish::publish;
     pub use super::r2::r2_bucket;
     pub use super::route::route;
     pub use super::secret::secret;
+    pub use super::sites::sites;
     pub use super::subdomain::subdomain;
     pub use super::tail::tail;
+    pub use super::types::types;
     pub use super::whoami::whoami;
 }
 
 use std::net::IpAddr;
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
 
 use crate::commands::dev::Protocol;
+use crate::commands::generate::GenerateType;
+use crate::commands::tail::event::EventType;
+use crate::commands::tail::expression::Expression;
+use crate::commands::tail::output::parse_size;
 use crate::commands::tail::websocket::TailFormat;
 use crate::preview::HttpMethod;
 use crate::settings::toml::migrations::{
@@ -65,7 +83,8 @@ pub struct Cli {
     #[structopt(long, global = true)]
     pub verbose: bool,
 
-    /// Path to configuration file.
+    /// Path to configuration file. It can be written in TOML, JSON or YAML, and wrangler.json or
+    /// wrangler.yaml are used when wrangler.toml does not exist.
     #[structopt(long, short = "c", default_value = "wrangler.toml", global = true)]
     pub config: PathBuf,
 
@@ -73,6 +92,18 @@ pub struct Cli {
     #[structopt(name = "env", long, short = "e", global = true)]
     pub environment: Option<String>,
 
+    /// Authentication profile to use, instead of $WRANGLER_PROFILE or "default"
+    #[structopt(long, global = true)]
+    pub profile: Option<String>,
+
+    /// Account to use for every environment, instead of the `account_id`s in your configuration file
+    #[structopt(name = "account-id", long, global = true)]
+    pub account_id: Option<String>,
+
+    /// Reject unknown keys in your configuration file, like `strict = true` does
+    #[structopt(long, global = true)]
+    pub strict: bool,
+
     #[structopt(subcommand)]
     pub command: Command,
 }
@@ -103,23 +134,71 @@ pub enum Command {
     #[structopt(name = "secret", setting = AppSettings::SubcommandRequiredElseHelp)]
     Secret(secret::Secret),
 
+    /// Inspect the assets of your Workers Site, and delete those it doesn't serve anymore
+    #[structopt(name = "sites", setting = AppSettings::SubcommandRequiredElseHelp)]
+    Sites(sites::Sites),
+
+    /// Add code maintained by wrangler to your project
+    #[structopt(name = "add", setting = AppSettings::SubcommandRequiredElseHelp)]
+    Add(add::Add),
+
     /// Generate a new worker project
     Generate {
         /// The name of your worker!
         #[structopt(index = 1, default_value = "worker")]
         name: String,
 
-        /// A link to a GitHub template! Defaults to https://github.com/cloudflare/worker-template
+        /// A link to a git repository of a template, or to a directory in one, or the path of a local template! Defaults to https://github.com/cloudflare/worker-template
         #[structopt(index = 2)]
         template: Option<String>,
 
-        /// The type of project you want generated
+        /// The branch or tag of the template's repository to generate from
+        #[structopt(long, short = "b")]
+        branch: Option<String>,
+
+        /// Sets a placeholder of the template, like `--define author=jane`, instead of prompting for it
+        #[structopt(long = "define", short = "d", parse(try_from_str = parse_define))]
+        defines: Vec<(String, String)>,
+
+        /// The type of project you want generated: javascript, rust or webpack, or durable-objects or scheduled for a starter with its bindings and triggers configured
         #[structopt(name = "type", long, short = "t")]
-        target_type: Option<TargetType>,
+        target_type: Option<GenerateType>,
 
         /// Initializes a Workers Sites project. Overrides 'type' and 'template'
         #[structopt(long, short = "s")]
         site: bool,
+
+        /// Never prompt, and use the defaults of the placeholders that are not set with --define
+        #[structopt(long, short = "y")]
+        yes: bool,
+
+        /// Lists the templates of the registry to pick one from, instead of giving a template
+        #[structopt(long, conflicts_with = "template")]
+        list: bool,
+
+        /// Lists the templates of the registry whose name, description or tags match, like `--search kv`
+        #[structopt(long, conflicts_with = "template")]
+        search: Option<String>,
+
+        /// Generates a workspace of workers, like `--workspace api,auth`, whose first worker is bound to the others
+        #[structopt(long, use_delimiter = true, conflicts_with_all = &["template", "site", "list", "search"])]
+        workspace: Vec<String>,
+
+        /// Doesn't initialize a git repository; `git = false` in your configuration makes this the default
+        #[structopt(long, conflicts_with = "git-branch")]
+        no_git: bool,
+
+        /// The branch that the git repository starts on, like `main`. Defaults to `git_branch` of your configuration
+        #[structopt(long, value_name = "name")]
+        git_branch: Option<String>,
+
+        /// Commits the generated files as the first commit of the git repository; `git_commit = true` in your configuration makes this the default
+        #[structopt(long, conflicts_with = "no-git")]
+        commit: bool,
+
+        /// Doesn't install the dependencies of the template with npm, yarn or pnpm, whichever its lockfile is for
+        #[structopt(long)]
+        no_install: bool,
     },
 
     /// Create a wrangler.toml for an existing project
@@ -135,10 +214,56 @@ pub enum Command {
         /// Initializes a Workers Sites project. Overrides `type` and `template`
         #[structopt(long, short = "s")]
         site: bool,
+
+        /// Replaces an existing wrangler.toml, and the files downloaded by --from-dash
+        #[structopt(long, short = "f")]
+        force: bool,
+
+        /// Never prompt; an existing wrangler.toml is only replaced with --force
+        #[structopt(long, short = "y")]
+        yes: bool,
+
+        /// Downloads the script, bindings, routes and Cron Triggers of a deployed worker
+        #[structopt(long, value_name = "worker-name", conflicts_with_all = &["name", "type", "site"])]
+        from_dash: Option<String>,
     },
 
     /// Build your worker
-    Build,
+    Build {
+        /// Watch your project for changes and rebuild it
+        #[structopt(long, conflicts_with = "analyze")]
+        watch: bool,
+
+        /// Report the size of each module of the bundle, and the largest dependencies
+        #[structopt(long)]
+        analyze: bool,
+
+        /// Also draw the modules of the bundle as a treemap in this HTML file
+        #[structopt(long, requires = "analyze")]
+        treemap: Option<PathBuf>,
+
+        /// Build the same bytes from the same sources anywhere, and print a hash of them
+        #[structopt(long)]
+        reproducible: bool,
+
+        /// Print the result of the build, with the errors of the build tool, as JSON
+        #[structopt(long, possible_value = "json")]
+        output: Option<String>,
+    },
+
+    /// Validate your configuration file and the files it references, without calling any API
+    Check,
+
+    /// Generate TypeScript declarations of the bindings of your worker
+    Types {
+        /// The file to write the declarations to
+        #[structopt(long, short = "o", default_value = "bindings.d.ts")]
+        output: PathBuf,
+
+        /// Also declare the secrets of your deployed worker, which are listed with the API
+        #[structopt(long)]
+        secrets: bool,
+    },
 
     /// Preview your code temporarily on cloudflareworkers.com
     Preview {
@@ -195,6 +320,26 @@ pub enum Command {
         /// Run wrangler dev unauthenticated
         #[structopt(long)]
         unauthenticated: bool,
+
+        /// Upload the assets of your Workers Site to its preview namespace, instead of serving them from its bucket
+        #[structopt(long)]
+        upload_assets: bool,
+
+        /// Delay the writes to KV namespaces by this many seconds, like they may be in production
+        #[structopt(long)]
+        kv_propagation_delay: Option<u64>,
+
+        /// Fail the requests going over the subrequest and CPU limits of production
+        #[structopt(long)]
+        simulate_limits: bool,
+
+        /// How many subrequests a request can make, overriding the limit of the usage model
+        #[structopt(long)]
+        subrequest_limit: Option<u32>,
+
+        /// How many seconds a request can take, overriding the CPU limit of production
+        #[structopt(long)]
+        cpu_limit: Option<u64>,
     },
 
     /// Publish your worker to the orange cloud
@@ -209,18 +354,48 @@ pub enum Command {
 
         #[structopt(flatten)]
         migration: AdhocMigration,
+
+        /// Build the same bytes from the same sources anywhere, and report a hash of them
+        #[structopt(long)]
+        reproducible: bool,
+
+        /// Publish the preview environment of the current git branch to workers.dev, as a
+        /// worker named after the branch with the preview namespaces of its bindings
+        #[structopt(name = "preview-branch", long)]
+        preview_branch: bool,
     },
 
-    /// Authenticate Wrangler with a Cloudflare API Token or Global API Key
+    /// Delete the preview environments of git branches published with `publish --preview-branch`
+    #[structopt(name = "preview-env", setting = AppSettings::SubcommandRequiredElseHelp)]
+    PreviewEnv(preview_env::PreviewEnv),
+
+    /// Query the past events of a worker, which a Logpush job stores
+    #[structopt(name = "logs", setting = AppSettings::SubcommandRequiredElseHelp)]
+    Logs(logs::Logs),
+
+    /// Authenticate Wrangler with a Cloudflare API Token, Global API Key or Service Token
     #[structopt(name = "config")]
     Config {
         /// Use an email and global API key for authentication.
         /// This is not recommended; use API tokens (the default) if possible
         #[structopt(name = "api-key", long)]
         api_key: bool,
+        /// Use the client ID and secret of a service token of your account for authentication,
+        /// e.g. on machines that may not use the API tokens of users
+        #[structopt(name = "service-token", long, conflicts_with = "api-key")]
+        service_token: bool,
         /// Do not verify provided credentials before writing out Wrangler config file
         #[structopt(name = "no-verify", long)]
         no_verify: bool,
+        /// Store credentials in the OS keychain instead of the configuration file. Needs a
+        /// wrangler built with `--features keychain`
+        #[structopt(long)]
+        keychain: bool,
+        /// Account ID to use by default with this profile
+        #[structopt(name = "default-account-id", long)]
+        default_account_id: Option<String>,
+        #[structopt(subcommand)]
+        command: Option<config::ConfigCommand>,
     },
 
     /// Configure your workers.dev subdomain
@@ -233,7 +408,16 @@ pub enum Command {
 
     /// Retrieve your user info and test your auth config
     #[structopt(name = "whoami")]
-    Whoami,
+    Whoami {
+        /// Verify the token and print its permissions, expiration, accounts and zones as JSON
+        #[structopt(long)]
+        json: bool,
+
+        /// Also report the limits of the plans of your accounts on scripts, their size, KV
+        /// namespaces and subrequests, and how much of them the accounts use
+        #[structopt(long)]
+        limits: bool,
+    },
 
     /// View a stream of logs from a published worker
     #[structopt(name = "tail")]
@@ -242,14 +426,48 @@ pub enum Command {
         #[structopt(index = 1)]
         name: Option<String>,
 
+        /// Name of an additional worker to tail, can be repeated
+        #[structopt(long = "name", number_of_values = 1)]
+        names: Vec<String>,
+
+        /// Tail every worker on the account
+        #[structopt(long, conflicts_with_all = &["name", "names"])]
+        all: bool,
+
         /// Output format for log messages
-        #[structopt(long, short = "f", default_value = "json", possible_values = &["json", "pretty"])]
+        #[structopt(long, short = "f", default_value = "json", possible_values = &["json", "ndjson", "pretty"])]
         format: TailFormat,
 
+        #[structopt(flatten)]
+        pretty: tail::Pretty,
+
+        /// Write log messages to a file instead of stdout
+        #[structopt(long, short = "o")]
+        output: Option<PathBuf>,
+
+        /// Rotate the output file once it exceeds this size (e.g. 10MB)
+        #[structopt(long = "rotate-size", requires = "output", parse(try_from_str = parse_size))]
+        rotate_size: Option<u64>,
+
+        /// Number of rotated output files to keep
+        #[structopt(long = "rotate-count", default_value = "5")]
+        rotate_count: usize,
+
+        #[structopt(flatten)]
+        forward: tail::Forward,
+
         /// Stops the tail after receiving the first log (useful for testing)
         #[structopt(long)]
         once: bool,
 
+        /// Print aggregated metrics per interval instead of every event
+        #[structopt(long)]
+        stats: bool,
+
+        /// Interval between two summaries of aggregated metrics (e.g. 10s, 1m)
+        #[structopt(long, default_value = "10s", parse(try_from_str = parse_interval))]
+        interval: Duration,
+
         /// Adds a sampling rate (0.01 for 1%)
         #[structopt(long = "sampling-rate", default_value = "1")]
         sampling_rate: f64,
@@ -258,6 +476,22 @@ pub enum Command {
         #[structopt(long, possible_values = &["ok", "error", "canceled"])]
         status: Vec<String>,
 
+        /// Only show events with exceptions or error outcomes
+        #[structopt(name = "errors-only", long)]
+        errors_only: bool,
+
+        /// Exit with an error as soon as an event with an exception or error outcome is received
+        #[structopt(name = "fail-on-error", long)]
+        fail_on_error: bool,
+
+        /// Stop tailing after this long (e.g. 60s, 5m)
+        #[structopt(long, parse(try_from_str = parse_duration))]
+        timeout: Option<Duration>,
+
+        /// Filter by event type
+        #[structopt(name = "event-type", long, possible_values = &["fetch", "scheduled", "alarm", "queue"])]
+        event_type: Vec<EventType>,
+
         /// Filter by HTTP method
         #[structopt(long)]
         method: Vec<String>,
@@ -274,6 +508,10 @@ pub enum Command {
         #[structopt(long)]
         search: Option<String>,
 
+        /// Filter by an expression evaluated by wrangler (e.g. 'status >= 500 && url ~ "/api/"')
+        #[structopt(long)]
+        filter: Option<Expression>,
+
         /// Set the URL to forward log messages
         #[structopt(hidden = true)]
         url: Option<Url>,
@@ -297,12 +535,30 @@ pub enum Command {
         /// List all scopes
         #[structopt(name = "scopes-list", long)]
         scopes_list: bool,
+
+        /// Store credentials in the OS keychain instead of the configuration file. Needs a
+        /// wrangler built with `--features keychain`
+        #[structopt(long)]
+        keychain: bool,
+
+        /// Account ID to use by default with this profile
+        #[structopt(name = "default-account-id", long)]
+        default_account_id: Option<String>,
+
+        /// Renew the access token of a previous login now, without logging in again
+        #[structopt(long, conflicts_with_all = &["scopes", "scopes-list"])]
+        refresh: bool,
     },
 
     /// Logout from your current authentication method and remove any configuration files.
+    /// OAuth tokens from `wrangler login` are revoked, unless `--keep-remote` is passed.
     /// It does not logout if you have authenticated wrangler through environment variables.
     #[structopt(name = "logout")]
-    Logout,
+    Logout {
+        /// Only remove the credentials from this machine, without revoking them
+        #[structopt(name = "keep-remote", long)]
+        keep_remote: bool,
+    },
 
     /// Report an error caught by wrangler to Cloudflare
     #[structopt(name = "report")]
@@ -403,6 +659,42 @@ impl AdhocMigration {
     }
 }
 
+/// Parses a duration such as `90`, `60s`, `5m` or `1h`, defaulting to seconds.
+pub fn parse_duration(input: &str) -> Result<Duration, anyhow::Error> {
+    let input = input.trim();
+    let (number, multiplier) = match input.char_indices().last() {
+        Some((i, 's')) => (&input[..i], 1),
+        Some((i, 'm')) => (&input[..i], 60),
+        Some((i, 'h')) => (&input[..i], 60 * 60),
+        _ => (input, 1),
+    };
+    let number = match number.parse::<u64>() {
+        Ok(number) => number,
+        Err(err) => anyhow::bail!("{}: {}", err, input),
+    };
+    match number.checked_mul(multiplier) {
+        Some(secs) => Ok(Duration::from_secs(secs)),
+        None => anyhow::bail!("Duration is too long: {}", input),
+    }
+}
+
+/// Parses the interval between two summaries of `wrangler tail --stats`, or two polls of
+/// `wrangler kv:key watch`.
+fn parse_interval(input: &str) -> Result<Duration, anyhow::Error> {
+    let interval = parse_duration(input)?;
+    if interval.as_secs() == 0 {
+        anyhow::bail!("The interval must be at least one second: {}", input);
+    }
+    Ok(interval)
+}
+
+fn parse_define(input: &str) -> Result<(String, String), anyhow::Error> {
+    match input.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => anyhow::bail!("Expected key=value: {}", input),
+    }
+}
+
 fn parse_ip_address(input: &str) -> Result<String, anyhow::Error> {
     match input {
         "self" => Ok(String::from("self")),
@@ -432,6 +724,17 @@ mod tests {
         }
     }
 
+    #[test]
+    fn duration_parsing() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("60s").unwrap(), Duration::from_secs(60));
+        assert_eq!(parse_duration("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse_duration("1h").unwrap(), Duration::from_secs(3600));
+        assert!(parse_duration("s").is_err());
+        assert!(parse_duration("1d").is_err());
+        assert!(parse_duration("18446744073709551615h").is_err());
+    }
+
     #[test]
     fn adhoc_migration_parsing() {
         let command = Cli::from_iter(&[
@@ -0,0 +1,33 @@
+use super::Cli;
+use crate::commands;
+use crate::settings::{global_user::GlobalUser, toml::Manifest};
+
+use anyhow::Result;
+use structopt::StructOpt;
+
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(rename_all = "lower")]
+pub enum PreviewEnv {
+    /// Delete the preview environments published from this project for the branches that the git remote origin doesn't have
+    Cleanup {
+        /// Only print the workers that would be deleted
+        #[structopt(long)]
+        dry_run: bool,
+
+        /// Forces delete without user confirmation
+        #[structopt(name = "force", long, short = "f")]
+        force: bool,
+    },
+}
+
+pub fn preview_env(preview_env: PreviewEnv, cli_params: &Cli) -> Result<()> {
+    let user = GlobalUser::new()?;
+    let manifest = Manifest::new(&cli_params.config)?;
+    let env = cli_params.environment.as_deref();
+
+    match preview_env {
+        PreviewEnv::Cleanup { dry_run, force } => {
+            commands::preview_env::cleanup(&manifest, env, &user, dry_run, force)
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use super::AdhocMigration;
 use super::Cli;
+use crate::build;
 use crate::commands;
+use crate::commands::notify::Event;
+use crate::login;
 use crate::settings::{global_user::GlobalUser, toml::Manifest};
 use crate::terminal::message::{Message, Output, StdOut};
 use crate::terminal::styles;
@@ -11,10 +14,16 @@ pub fn publish(
     release: bool,
     output: Option<String>,
     migration: AdhocMigration,
+    reproducible: bool,
+    preview_branch: bool,
     cli_params: &Cli,
 ) -> Result<()> {
+    if reproducible {
+        build::reproducible::set_reproducible();
+    }
     log::info!("Getting User settings");
     let user = GlobalUser::new()?;
+    login::warn_if_login_expires_soon(&user);
 
     if release {
         StdOut::warn(&format!(concat!(
@@ -30,7 +39,15 @@ pub fn publish(
     let manifest = Manifest::new(&cli_params.config)?;
     manifest.warn_about_compatibility_date();
 
-    let mut target = manifest.get_target(cli_params.environment.as_deref(), false)?;
+    let env = cli_params.environment.as_deref();
+    let (mut target, deploy_config) = if preview_branch {
+        commands::preview_env::branch_target(&manifest, env)?
+    } else {
+        (
+            manifest.get_target(env, false)?,
+            manifest.pick_deployments(env)?,
+        )
+    };
 
     if let Some(migration) = migration.into_migrations() {
         target.migrations = Some(migration);
@@ -41,6 +58,7 @@ pub fn publish(
     } else {
         Output::PlainText
     };
-    let deploy_config = manifest.get_deployments(cli_params.environment.as_deref())?;
-    commands::publish(&user, &mut target, deploy_config, output)
+    let result = commands::publish(&user, &mut target, deploy_config, output);
+    commands::notify::notify(&manifest, env, &target.name, Event::Publish, &result);
+    result
 }
@@ -9,7 +9,15 @@ use structopt::StructOpt;
 #[structopt(rename_all = "lower")]
 pub enum Route {
     /// List all routes associated with a zone (outputs json)
-    List,
+    List {
+        /// List the routes of every zone your token can access, with the zone of each route
+        #[structopt(name = "all-zones", long)]
+        all_zones: bool,
+
+        /// Only list the routes pointing at this worker
+        #[structopt(long)]
+        script: Option<String>,
+    },
     /// Delete a route by ID
     Delete {
         /// The ID associated with the route you want to delete (find using `wrangler route list`)
@@ -20,6 +28,15 @@ pub enum Route {
 
 pub fn route(route: Route, cli_params: &Cli) -> Result<()> {
     let user = GlobalUser::new()?;
+    // the zones of the account are listed without a configuration file
+    if let Route::List {
+        all_zones: true,
+        script,
+    } = &route
+    {
+        return commands::route::list_all_zones(&user, script.as_deref());
+    }
+
     let manifest = Manifest::new(&cli_params.config)?;
     let zone_id = manifest
         .get_environment(cli_params.environment.as_deref())?
@@ -33,7 +50,7 @@ pub fn route(route: Route, cli_params: &Cli) -> Result<()> {
     })?;
 
     match route {
-        Route::List => commands::route::list(zone_id, &user),
+        Route::List { script, .. } => commands::route::list(zone_id, &user, script.as_deref()),
         Route::Delete { route_id } => commands::route::delete(zone_id, &user, &route_id),
     }
 }
@@ -1,5 +1,6 @@
 use super::Cli;
 use crate::commands;
+use crate::commands::notify::Event;
 use crate::settings::{global_user::GlobalUser, toml::Manifest};
 
 use anyhow::Result;
@@ -27,10 +28,19 @@ pub fn secret(secret: Secret, cli_params: &Cli) -> Result<()> {
 
     log::info!("Getting project settings");
     let manifest = Manifest::new(&cli_params.config)?;
-    let target = manifest.get_target(cli_params.environment.as_deref(), false)?;
-    match secret {
-        Secret::Put { name } => commands::secret::create_secret(&name, &user, &target),
-        Secret::Delete { name } => commands::secret::delete_secret(&name, &user, &target),
-        Secret::List => commands::secret::list_secrets(&user, &target),
-    }
+    let env = cli_params.environment.as_deref();
+    let target = manifest.get_target(env, false)?;
+    let (result, event) = match secret {
+        Secret::Put { name } => (
+            commands::secret::create_secret(&name, &user, &target),
+            Event::SecretPut(name),
+        ),
+        Secret::Delete { name } => match commands::secret::delete_secret(&name, &user, &target) {
+            Ok(false) => return Ok(()),
+            result => (result.map(|_| ()), Event::SecretDelete(name)),
+        },
+        Secret::List => return commands::secret::list_secrets(&user, &target),
+    };
+    commands::notify::notify(&manifest, env, &target.name, event, &result);
+    result
 }
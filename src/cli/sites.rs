@@ -0,0 +1,37 @@
+use super::Cli;
+use crate::commands;
+use crate::settings::{global_user::GlobalUser, toml::Manifest};
+
+use anyhow::Result;
+use structopt::StructOpt;
+
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(rename_all = "lower")]
+pub enum Sites {
+    /// Print the assets of the last publish, with their key, size and hash
+    Manifest,
+    /// Delete the assets that the last publish doesn't serve anymore
+    Prune {
+        /// Only print the keys that would be deleted
+        #[structopt(long)]
+        dry_run: bool,
+
+        /// Forces delete without user confirmation
+        #[structopt(name = "force", long, short = "f")]
+        force: bool,
+    },
+}
+
+pub fn sites(sites: Sites, cli_params: &Cli) -> Result<()> {
+    let user = GlobalUser::new()?;
+    let manifest = Manifest::new(&cli_params.config)?;
+    let target = manifest.get_target(cli_params.environment.as_deref(), false)?;
+    if target.site.is_none() {
+        anyhow::bail!("`wrangler sites` commands need a [site] in your configuration file");
+    }
+
+    match sites {
+        Sites::Manifest => commands::sites::manifest(&target, &user),
+        Sites::Prune { dry_run, force } => commands::sites::prune(&target, &user, dry_run, force),
+    }
+}
@@ -1,24 +1,144 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
 use super::Cli;
 use crate::commands;
+use crate::commands::tail::event::EventType;
+use crate::commands::tail::expression::Expression;
 use crate::commands::tail::filter::*;
+use crate::commands::tail::forward::ForwardOptions;
+use crate::commands::tail::output::TailOutput;
+use crate::commands::tail::pretty::PrettyFormat;
+use crate::commands::tail::sourcemap::SourceMaps;
 use crate::commands::tail::websocket::{TailFormat, TailOptions};
-use crate::settings::{global_user::GlobalUser, toml::Manifest};
+use crate::settings::global_user::GlobalUser;
+use crate::settings::toml::{Manifest, TailConfig};
 
 use anyhow::Result;
+use structopt::StructOpt;
 use url::Url;
 
+#[derive(Debug, Clone, StructOpt)]
+pub struct Pretty {
+    /// Layout of pretty log messages, e.g. "{timestamp} {method} {url} {status} {logs}"
+    #[structopt(name = "format-template", long)]
+    pub template: Option<String>,
+
+    /// Show request headers in pretty log messages
+    #[structopt(name = "show-headers", long)]
+    pub show_headers: bool,
+
+    /// Truncate console messages longer than this many characters in pretty log messages
+    #[structopt(name = "truncate", long)]
+    pub truncate: Option<usize>,
+
+    /// Color each pretty log message by the outcome of its event
+    #[structopt(name = "colorize-outcome", long)]
+    pub colorize_outcome: bool,
+
+    /// Print each line of a pretty log message on its own, instead of grouped by request
+    #[structopt(name = "no-group", long)]
+    pub no_group: bool,
+}
+
+impl Pretty {
+    pub(super) fn into_format(
+        self,
+        config: Option<&TailConfig>,
+        source_maps: Option<SourceMaps>,
+    ) -> PrettyFormat {
+        let config = config.cloned().unwrap_or_default();
+        PrettyFormat {
+            template: self.template.or(config.format_template),
+            show_headers: self.show_headers || config.show_headers.unwrap_or_default(),
+            truncate: self.truncate.or(config.truncate),
+            colorize_outcome: self.colorize_outcome || config.colorize_outcome.unwrap_or_default(),
+            group: !self.no_group && config.group.unwrap_or(true),
+            source_maps: source_maps.map(Arc::new),
+        }
+    }
+}
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct Forward {
+    /// Forward log messages to an HTTP endpoint, POSTed in batches as JSON arrays
+    #[structopt(name = "forward-url", long)]
+    pub url: Option<Url>,
+
+    /// Forward log messages to a syslog server over UDP (host:port)
+    #[structopt(name = "forward-syslog", long)]
+    pub syslog: Option<String>,
+
+    /// Add a header to forwarded HTTP requests (e.g. "Authorization: Bearer <token>")
+    #[structopt(
+        name = "forward-header",
+        long,
+        number_of_values = 1,
+        requires = "forward-url"
+    )]
+    pub headers: Vec<String>,
+
+    /// Maximum number of log messages to forward at once
+    #[structopt(name = "forward-batch-size", long, default_value = "10")]
+    pub batch_size: usize,
+
+    /// Maximum number of seconds to hold log messages before forwarding them
+    #[structopt(name = "forward-batch-interval", long, default_value = "5")]
+    pub batch_interval: u64,
+}
+
+impl Forward {
+    fn into_options(self) -> Result<Option<ForwardOptions>> {
+        if self.url.is_none() && self.syslog.is_none() {
+            return Ok(None);
+        }
+        let mut headers = Vec::with_capacity(self.headers.len());
+        for header in self.headers {
+            match header.split_once(":") {
+                Some((name, value)) => {
+                    headers.push((name.trim().to_owned(), value.trim().to_owned()))
+                }
+                None => anyhow::bail!("Invalid header \"{}\", expected \"Name: value\"", header),
+            }
+        }
+        Ok(Some(ForwardOptions {
+            url: self.url,
+            syslog: self.syslog,
+            headers,
+            batch_size: self.batch_size.max(1),
+            batch_interval: Duration::from_secs(self.batch_interval.max(1)),
+        }))
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn tail(
     name: Option<String>,
+    names: Vec<String>,
+    all: bool,
     url: Option<Url>,
     format: TailFormat,
+    pretty: Pretty,
+    output: Option<PathBuf>,
+    rotate_size: Option<u64>,
+    rotate_count: usize,
+    forward: Forward,
     once: bool,
+    stats: bool,
+    interval: Duration,
     sampling_rate: f64,
+    errors_only: bool,
+    fail_on_error: bool,
+    timeout: Option<Duration>,
     outcomes: Vec<String>,
+    event_types: Vec<EventType>,
     methods: Vec<String>,
     headers: Vec<String>,
     client_ips: Vec<String>,
     search: Option<String>,
+    expression: Option<Expression>,
     cli_params: &Cli,
 ) -> Result<()> {
     let user = GlobalUser::new()?;
@@ -27,7 +147,24 @@ pub fn tail(
     let manifest = Manifest::new(&cli_params.config)?;
     let target = manifest.get_target(cli_params.environment.as_deref(), false)?;
     let account_id = target.account_id.load()?.to_string();
-    let script_name = name.unwrap_or(target.name);
+    let source_maps = match format {
+        TailFormat::Pretty => SourceMaps::for_target(&target),
+        _ => None,
+    };
+    let script_names = if all {
+        commands::tail::api::list_scripts(&user, &account_id)?
+    } else {
+        let mut script_names: Vec<String> = name.into_iter().chain(names).collect();
+        if script_names.is_empty() {
+            script_names.push(target.name);
+        }
+        let mut seen = HashSet::new();
+        script_names.retain(|name| seen.insert(name.clone()));
+        script_names
+    };
+    if script_names.is_empty() {
+        anyhow::bail!("There are no workers on this account to tail");
+    }
 
     let mut filters: Vec<Box<dyn TraceFilter>> = vec![];
     if !outcomes.is_empty() {
@@ -49,15 +186,40 @@ pub fn tail(
         filters.push(Box::new(SamplingRateFilter::from(sampling_rate))); // Should always be last
     };
 
+    let output = match output {
+        Some(path) => {
+            // Escape codes are only useful on a terminal.
+            console::set_colors_enabled(false);
+            TailOutput::file(path, rotate_size, rotate_count)?
+        }
+        None => TailOutput::Stdout,
+    };
+
+    let forward = forward.into_options()?;
+    let pretty = pretty.into_format(manifest.tail.as_ref(), source_maps);
+
     let tail = commands::tail::run(
         user,
         account_id,
-        script_name,
+        script_names,
         url,
+        forward,
+        if stats { Some(interval) } else { None },
         TailOptions {
             once,
             format,
+            pretty,
+            event_types,
+            errors_only,
+            fail_on_error,
+            timeout,
+            expression,
             filters,
+            prefixed: false,
+            output,
+            forwarder: None,
+            stats: None,
+            stop: Default::default(),
         },
     );
 
@@ -0,0 +1,22 @@
+use super::Cli;
+use crate::commands;
+use crate::settings::{global_user::GlobalUser, toml::Manifest};
+
+use std::path::Path;
+
+use anyhow::Result;
+
+pub fn types(output: &Path, secrets: bool, cli_params: &Cli) -> Result<()> {
+    log::info!("Getting project settings");
+    let manifest = Manifest::new(&cli_params.config)?;
+    let target = manifest.get_target(cli_params.environment.as_deref(), false)?;
+
+    let secrets = if secrets {
+        log::info!("Getting User settings");
+        let user = GlobalUser::new()?;
+        commands::secret::secret_names(&user, &target)?
+    } else {
+        Vec::new()
+    };
+    commands::types::types(&target, &secrets, output)
+}
@@ -1,10 +1,21 @@
 use crate::commands;
+use crate::login;
 use crate::settings::global_user::GlobalUser;
 
 use anyhow::Result;
 
-pub fn whoami() -> Result<()> {
+pub fn whoami(json: bool, limits: bool) -> Result<()> {
     log::info!("Getting User settings");
 
-    commands::whoami(&GlobalUser::new()?)
+    let user = GlobalUser::new()?;
+    login::warn_if_login_expires_soon(&user);
+    if json {
+        commands::whoami::whoami_json(&user, limits)
+    } else {
+        commands::whoami(&user)?;
+        if limits {
+            commands::limits::print_limits(&user)?;
+        }
+        Ok(())
+    }
 }
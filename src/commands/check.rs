@@ -0,0 +1,462 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use chrono::NaiveDate;
+
+use crate::settings::toml::migrations::MigrationConfig;
+use crate::settings::toml::schema;
+use crate::settings::toml::triggers::validate_cron;
+use crate::settings::toml::{
+    read_value, Bundler, DurableObjects, Manifest, Site, TargetType, UploadFormat, WasmOpt,
+};
+use crate::terminal::message::{Message, StdOut};
+use crate::terminal::styles;
+
+#[derive(Debug, PartialEq)]
+enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found in a project, along with where it was found.
+#[derive(Debug)]
+struct Diagnostic {
+    severity: Severity,
+    location: String,
+    message: String,
+}
+
+#[derive(Default)]
+struct Diagnostics(Vec<Diagnostic>);
+
+impl Diagnostics {
+    fn error(&mut self, location: &str, message: String) {
+        self.0.push(Diagnostic {
+            severity: Severity::Error,
+            location: location.to_string(),
+            message,
+        });
+    }
+
+    fn warn(&mut self, location: &str, message: String) {
+        self.0.push(Diagnostic {
+            severity: Severity::Warning,
+            location: location.to_string(),
+            message,
+        });
+    }
+}
+
+/// Validates a configuration file and the files it references without calling any API, so
+/// that it can run in pre-commit hooks. Fails if any error is found.
+pub fn check(config_path: &Path) -> Result<()> {
+    let diagnostics = diagnose(config_path);
+    let file = config_path.display().to_string();
+
+    let mut errors = 0;
+    for diagnostic in diagnostics.0.iter() {
+        let location = if diagnostic.location.is_empty() {
+            file.clone()
+        } else {
+            format!("{} [{}]", file, diagnostic.location)
+        };
+        let msg = format!("{}: {}", styles::bold(&location), diagnostic.message);
+        match diagnostic.severity {
+            Severity::Error => {
+                errors += 1;
+                StdOut::user_error(&msg)
+            }
+            Severity::Warning => StdOut::warn(&msg),
+        }
+    }
+
+    let warnings = diagnostics.0.len() - errors;
+    if errors > 0 {
+        anyhow::bail!(
+            "Found {} error(s) and {} warning(s) in {}",
+            errors,
+            warnings,
+            file
+        )
+    }
+    if warnings > 0 {
+        StdOut::success(&format!("{} is valid, with {} warning(s)", file, warnings));
+    } else {
+        StdOut::success(&format!("{} is valid", file));
+    }
+    Ok(())
+}
+
+fn diagnose(config_path: &Path) -> Diagnostics {
+    let mut diagnostics = Diagnostics::default();
+    if !config_path.exists() {
+        diagnostics.error("", "file not found".to_string());
+        return diagnostics;
+    }
+
+    let value = match read_value(config_path) {
+        Ok(value) => value,
+        Err(e) => {
+            diagnostics.error("", format!("could not be parsed: {}", e));
+            return diagnostics;
+        }
+    };
+    for unknown in schema::unknown_keys(&value) {
+        diagnostics.error("", unknown.to_string());
+    }
+
+    let manifest = match Manifest::new(config_path) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            diagnostics.error("", e.to_string());
+            return diagnostics;
+        }
+    };
+    // Paths in the configuration are relative to the project
+    let project_dir = config_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."))
+        .to_path_buf();
+
+    if let Some(date) = &manifest.compatibility_date {
+        if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+            diagnostics.error(
+                "",
+                format!("compatibility_date \"{}\" is not a YYYY-MM-DD date", date),
+            );
+        }
+    }
+
+    if let Some(main) = &manifest.main {
+        let built = manifest.build.is_some() && manifest.target_type == TargetType::JavaScript;
+        if let Err(e) = manifest.get_target(None, false) {
+            diagnostics.error("", e.to_string());
+        } else if project_dir.join(main).exists() {
+            if manifest.target_type == TargetType::Webpack && manifest.webpack_config.is_some() {
+                diagnostics.warn(
+                    "",
+                    "main is ignored because webpack_config sets the entry of the build"
+                        .to_string(),
+                );
+            }
+        } else if built {
+            // The script may not exist until the project is built
+            diagnostics.warn(
+                "",
+                format!(
+                    "main {} was not found; has the project been built?",
+                    main.display()
+                ),
+            );
+        } else {
+            diagnostics.error("", format!("main {} does not exist", main.display()));
+        }
+    }
+
+    let node_compat = manifest.node_compat.unwrap_or_default()
+        || manifest
+            .env
+            .iter()
+            .flat_map(|envs| envs.values())
+            .any(|env| env.node_compat.unwrap_or_default());
+    if node_compat && manifest.target_type != TargetType::Webpack {
+        diagnostics.warn(
+            "",
+            format!(
+                "node_compat only applies to webpack projects, and has no effect on {} projects",
+                manifest.target_type
+            ),
+        );
+    }
+
+    if let (TargetType::Rust, Some(build)) = (&manifest.target_type, &manifest.build) {
+        if let Some(Err(e)) = build.wasm.as_ref().map(WasmOpt::flag) {
+            diagnostics.error("", e.to_string());
+        }
+    }
+    if let Some(build) = &manifest.build {
+        if build.wasm.is_some() && manifest.target_type != TargetType::Rust {
+            diagnostics.warn("", "[build.wasm] only applies to rust projects".to_string());
+        }
+        if build.define.is_some() && manifest.target_type != TargetType::Webpack {
+            diagnostics.warn(
+                "",
+                "[build.define] only applies to webpack projects".to_string(),
+            );
+        }
+    }
+    if manifest.bundler.is_some() && manifest.target_type != TargetType::Webpack {
+        diagnostics.warn(
+            "",
+            format!(
+                "bundler only applies to webpack projects, and has no effect on {} projects",
+                manifest.target_type
+            ),
+        );
+    }
+    if manifest.target_type == TargetType::Webpack {
+        // Environments are only diagnosed when they override one of the settings
+        let mut bundles = vec![(
+            String::new(),
+            manifest.bundler,
+            manifest.node_compat,
+            manifest.webpack_config.is_some(),
+        )];
+        let mut envs = manifest.env.iter().flatten().collect::<Vec<_>>();
+        envs.sort_by_key(|(name, _)| *name);
+        for (name, env) in envs {
+            if env.bundler.is_some() || env.node_compat.is_some() || env.webpack_config.is_some() {
+                bundles.push((
+                    format!("env.{}", name),
+                    env.bundler.or(manifest.bundler),
+                    env.node_compat.or(manifest.node_compat),
+                    env.webpack_config.is_some() || manifest.webpack_config.is_some(),
+                ));
+            }
+        }
+        for (location, bundler, node_compat, webpack_config) in bundles {
+            if bundler != Some(Bundler::Builtin) {
+                continue;
+            }
+            if node_compat.unwrap_or_default() {
+                diagnostics.error(
+                    &location,
+                    "node_compat is not supported by the builtin bundler".to_string(),
+                );
+            }
+            if webpack_config {
+                diagnostics.warn(
+                    &location,
+                    "webpack_config is ignored by the builtin bundler".to_string(),
+                );
+            }
+        }
+    }
+
+    let top_level = Scope {
+        location: String::new(),
+        route: manifest.route.is_some(),
+        routes: manifest.routes.is_some(),
+        webpack_config: manifest.webpack_config.as_deref(),
+        build: manifest
+            .build
+            .as_ref()
+            .map(|build| (&build.cwd, &build.upload)),
+        site: manifest.site.as_ref(),
+        crons: manifest.triggers.as_ref().map(|t| t.crons.as_slice()),
+        durable_objects: manifest.durable_objects.as_ref(),
+    };
+    top_level.diagnose(&project_dir, &manifest.migrations, &mut diagnostics);
+
+    if let Some(envs) = &manifest.env {
+        let mut names = envs.keys().collect::<Vec<_>>();
+        names.sort();
+        for name in names {
+            let env = &envs[name];
+            let location = format!("env.{}", name);
+            let build = match env.build.as_ref().map(|b| b.apply(manifest.build.as_ref())) {
+                Some(Ok(build)) => Some(build),
+                Some(Err(e)) => {
+                    diagnostics.error(&location, e.to_string());
+                    None
+                }
+                None => None,
+            };
+            let scope = Scope {
+                location,
+                route: env.route.is_some(),
+                routes: env.routes.is_some(),
+                webpack_config: env.webpack_config.as_deref(),
+                build: build.as_ref().map(|build| (&build.cwd, &build.upload)),
+                site: env.site.as_ref(),
+                crons: env.triggers.as_ref().map(|t| t.crons.as_slice()),
+                durable_objects: env.durable_objects.as_ref(),
+            };
+            scope.diagnose(&project_dir, &manifest.migrations, &mut diagnostics);
+        }
+    }
+
+    let files = manifest
+        .text_blobs
+        .iter()
+        .flatten()
+        .chain(manifest.wasm_modules.iter().flatten());
+    for (binding, path) in files {
+        if !project_dir.join(path).exists() {
+            diagnostics.error(
+                "",
+                format!("{} of binding {} does not exist", path.display(), binding),
+            );
+        }
+    }
+
+    diagnostics
+}
+
+/// The settings that can be defined both at the top level and in an environment.
+struct Scope<'a> {
+    location: String,
+    route: bool,
+    routes: bool,
+    webpack_config: Option<&'a str>,
+    build: Option<(&'a PathBuf, &'a UploadFormat)>,
+    site: Option<&'a Site>,
+    crons: Option<&'a [String]>,
+    durable_objects: Option<&'a DurableObjects>,
+}
+
+impl<'a> Scope<'a> {
+    fn diagnose(
+        &self,
+        project_dir: &Path,
+        migrations: &Option<Vec<MigrationConfig>>,
+        diagnostics: &mut Diagnostics,
+    ) {
+        let location = self.location.as_str();
+
+        if self.route && self.routes {
+            diagnostics.error(
+                location,
+                "`route` and `routes` cannot be used together; move the route into `routes`"
+                    .to_string(),
+            );
+        }
+
+        if let Some(webpack_config) = self.webpack_config {
+            if !project_dir.join(webpack_config).exists() {
+                diagnostics.error(
+                    location,
+                    format!("webpack_config {} does not exist", webpack_config),
+                );
+            }
+        }
+
+        if let Some((cwd, upload)) = self.build {
+            if !project_dir.join(cwd).is_dir() {
+                diagnostics.error(
+                    location,
+                    format!("build.cwd {} is not a directory", cwd.display()),
+                );
+            }
+            if let UploadFormat::Modules { main, dir, .. } = upload {
+                // The output of the build may not exist until the project is built
+                if !project_dir.join(dir).join(main).exists() {
+                    diagnostics.warn(
+                        location,
+                        format!(
+                            "build.upload.main {} was not found in {}; has the project been built?",
+                            main,
+                            dir.display()
+                        ),
+                    );
+                }
+            }
+        }
+
+        if let Some(site) = self.site {
+            if !project_dir.join(&site.bucket).is_dir() {
+                diagnostics.warn(
+                    location,
+                    format!(
+                        "site.bucket {} is not a directory; has the site been built?",
+                        site.bucket.display()
+                    ),
+                );
+            }
+            if let Some(entry_point) = &site.entry_point {
+                if !project_dir.join(entry_point).is_dir() {
+                    diagnostics.error(
+                        location,
+                        format!("site.entry-point {} does not exist", entry_point.display()),
+                    );
+                }
+            }
+        }
+
+        for cron in self.crons.unwrap_or_default() {
+            if let Err(e) = validate_cron(cron) {
+                diagnostics.error(location, e.to_string());
+            }
+        }
+
+        let classes = self
+            .durable_objects
+            .and_then(|durable_objects| durable_objects.classes.as_ref());
+        for class in classes.into_iter().flatten() {
+            // Classes of other scripts are migrated by those scripts
+            if class.script_name.is_some() {
+                continue;
+            }
+            if !migrated_classes(migrations).contains(&class.class_name.as_str()) {
+                diagnostics.warn(
+                    location,
+                    format!(
+                        "Durable Object class {} of binding {} is not created by any [[migrations]]; add it to the `new_classes` of a migration",
+                        class.class_name, class.binding
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// The Durable Object classes that exist once every migration is applied.
+fn migrated_classes(migrations: &Option<Vec<MigrationConfig>>) -> Vec<&str> {
+    let mut classes = Vec::new();
+    for migration in migrations.iter().flatten() {
+        let migration = &migration.migration.durable_objects;
+        classes.extend(migration.new_classes.iter().map(String::as_str));
+        classes.extend(migration.renamed_classes.iter().map(|c| c.to.as_str()));
+        classes.extend(migration.transferred_classes.iter().map(|c| c.to.as_str()));
+        classes.retain(|class| !migration.deleted_classes.iter().any(|d| d == class));
+    }
+    classes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_reports_problems_in_every_environment() {
+        let tmp_dir = tempdir().unwrap();
+        let config_path = tmp_dir.path().join("wrangler.toml");
+        fs::write(
+            &config_path,
+            r#"
+name = "worker"
+type = "javascript"
+route = "example.com/*"
+routes = ["example.com/api/*"]
+compatibility_date = "2021-13-01"
+kv_namespace = []
+
+[triggers]
+crons = ["* * * *"]
+
+[durable_objects]
+classes = [{ binding = "COUNTER", class_name = "Counter" }]
+
+[env.production]
+webpack_config = "webpack.prod.js"
+"#,
+        )
+        .unwrap();
+
+        let diagnostics = diagnose(&config_path).0;
+        let messages = diagnostics
+            .iter()
+            .map(|d| format!("{:?} {} {}", d.severity, d.location, d.message))
+            .collect::<Vec<_>>();
+        assert_eq!(messages.len(), 6, "{:#?}", messages);
+        assert!(messages[0].contains("did you mean `kv_namespaces`?"));
+        assert!(messages[1].contains("compatibility_date"));
+        assert!(messages[2].starts_with("Error  `route` and `routes`"));
+        assert!(messages[3].contains("needs 5"));
+        assert!(messages[4].starts_with("Warning  Durable Object class Counter"));
+        assert!(messages[5].starts_with("Error env.production webpack_config"));
+    }
+}
@@ -1,17 +1,19 @@
+use std::fs;
 #[cfg(not(target_os = "windows"))]
 use std::fs::File;
 #[cfg(not(target_os = "windows"))]
 use std::os::unix::fs::PermissionsExt;
-#[cfg(not(target_os = "windows"))]
 use std::path::Path;
 
 use anyhow::Result;
+use cloudflare::endpoints::account::ListAccounts;
 use cloudflare::endpoints::user::{GetUserDetails, GetUserTokenStatus};
 use cloudflare::framework::apiclient::ApiClient;
 
 use crate::commands::logout::invalidate_oauth_token;
 use crate::http;
-use crate::settings::{get_global_config_path, global_user::GlobalUser};
+use crate::settings::toml::schema;
+use crate::settings::{cache, get_global_config_path, global_user::GlobalUser, keychain, profile};
 use crate::terminal::message::{Message, StdOut};
 use crate::terminal::styles;
 
@@ -24,7 +26,7 @@ pub fn set_file_mode(file: &Path) {
         .expect("could not set permissions on file");
 }
 
-pub fn global_config(user: &GlobalUser, verify: bool) -> Result<()> {
+pub fn global_config(user: &GlobalUser, verify: bool, keychain: bool) -> Result<()> {
     if verify {
         StdOut::info("Validating credentials...");
         validate_credentials(user)?;
@@ -33,23 +35,112 @@ pub fn global_config(user: &GlobalUser, verify: bool) -> Result<()> {
     // Invalidate previous oauth token if present
     invalidate_oauth_token("`wrangler config`".to_string());
 
+    // Accounts and zones cached for the previous credentials may not be accessible anymore
+    if let Err(e) = cache::clear() {
+        log::info!("Failed to clear the cache: {}", e);
+    }
+
     let config_file = get_global_config_path();
-    user.to_file(&config_file)?;
+    let in_keychain = keychain && store_in_keychain(user, &config_file);
+    if !in_keychain {
+        user.to_file(&config_file)?;
+    }
 
     // set permissions on the file
     #[cfg(not(target_os = "windows"))]
     set_file_mode(&config_file);
 
+    if in_keychain || keychain::uses_keychain(&config_file) {
+        StdOut::success("Successfully configured. Your credentials are stored in the keychain.");
+    } else {
+        StdOut::success(&format!(
+            "Successfully configured. You can find your configuration file at: {}",
+            &config_file.to_string_lossy()
+        ));
+    }
+
+    Ok(())
+}
+
+// store_in_keychain() tries the keychain, so callers can fall back to the configuration file.
+fn store_in_keychain(user: &GlobalUser, config_file: &Path) -> bool {
+    match user.to_keychain(config_file) {
+        Ok(()) => true,
+        Err(e) => {
+            StdOut::warn(&format!(
+                "{}; storing credentials in the configuration file instead",
+                e
+            ));
+            false
+        }
+    }
+}
+
+// migrate_to_keychain() moves the credentials of the selected profile from its configuration
+// file to the keychain.
+pub fn migrate_to_keychain() -> Result<()> {
+    let config_file = get_global_config_path();
+    if keychain::uses_keychain(&config_file) {
+        StdOut::info("Your credentials are already stored in the keychain.");
+        return Ok(());
+    }
+    if !config_file.exists() {
+        anyhow::bail!(
+            "No configuration file was found at {}. Run `wrangler login --keychain` or `wrangler config --keychain` instead",
+            config_file.display()
+        );
+    }
+
+    let user = GlobalUser::from_file(config_file.clone())?;
+    user.to_keychain(&config_file)?;
+
     StdOut::success(&format!(
-        "Successfully configured. You can find your configuration file at: {}",
-        &config_file.to_string_lossy()
+        "Moved your credentials from {} to the keychain.",
+        config_file.display()
     ));
 
     Ok(())
 }
 
-// validate_credentials() checks the /user/tokens/verify endpoint (for API token)
-// or /user endpoint (for global API key) to ensure provided credentials actually work.
+// set_default_account_id() stores the account ID used by the selected profile when
+// wrangler.toml does not have one.
+pub fn set_default_account_id(account_id: &str) -> Result<()> {
+    let config_file = get_global_config_path();
+    profile::set_default_account_id(&config_file, account_id)?;
+
+    StdOut::success(&format!(
+        "Set the default account ID of profile \"{}\" to {}",
+        profile::active_profile(),
+        account_id
+    ));
+
+    Ok(())
+}
+
+// list_profiles() prints every configured profile, marking the selected one.
+pub fn list_profiles() -> Result<()> {
+    let profiles = profile::list_profiles()?;
+    if profiles.is_empty() {
+        StdOut::info("No profiles have been configured. Run `wrangler login` or `wrangler config` to create one.");
+        return Ok(());
+    }
+
+    let active = profile::active_profile();
+    for name in profiles {
+        let config_file = profile::profile_config_path(&name);
+        let marker = if name == active { "*" } else { " " };
+        let account_id = profile::default_account_id(&config_file)
+            .map(|id| format!(" (account {})", id))
+            .unwrap_or_default();
+        println!("{} {}{}", marker, styles::bold(&name), account_id);
+    }
+
+    Ok(())
+}
+
+// validate_credentials() checks the /user/tokens/verify endpoint (for API token),
+// /user endpoint (for global API key) or /accounts endpoint (for service token)
+// to ensure provided credentials actually work.
 pub fn validate_credentials(user: &GlobalUser) -> Result<()> {
     let client = http::cf_v4_client(user)?;
 
@@ -77,5 +168,28 @@ pub fn validate_credentials(user: &GlobalUser) -> Result<()> {
             }
         },
         GlobalUser::OAuthTokenAuth { .. } => anyhow::bail!("OAuth token cannot be verified."),
+        // a service token has no user, but it can list the accounts that it belongs to
+        GlobalUser::ServiceTokenAuth { .. } => {
+            match client.request(&ListAccounts { params: None }) {
+                Ok(_) => Ok(()),
+                Err(e) => anyhow::bail!(
+                    "Authentication check failed. Please make sure your service token's client ID and secret are correct.\n{}",
+                    http::format_error(e, None)
+                ),
+            }
+        }
     }
 }
+
+/// Prints the JSON Schema of wrangler.toml, or writes it to `out`.
+pub fn write_schema(out: Option<&Path>) -> Result<()> {
+    let schema = serde_json::to_string_pretty(&schema::json_schema())?;
+    match out {
+        Some(out) => {
+            fs::write(out, schema + "\n")?;
+            StdOut::success(&format!("Wrote the JSON Schema to {}", out.display()));
+        }
+        None => println!("{}", schema),
+    }
+    Ok(())
+}
@@ -0,0 +1,125 @@
+//! The assets of a Workers Site, which `wrangler dev` serves from the bucket on disk instead of
+//! uploading them to the preview namespace of the site whenever they change.
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::Result;
+use hyper::header::{HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE};
+use hyper::{Body, HeaderMap, Method, Response, StatusCode};
+use percent_encoding::percent_decode_str;
+
+use crate::settings::toml::{NotFoundHandling, Target};
+use crate::sites::{self, SiteSettings};
+use crate::terminal::message::{Message, StdOut};
+
+pub struct LocalAssets {
+    target: Target,
+    bucket: PathBuf,
+    settings: SiteSettings,
+    not_found_handling: Option<NotFoundHandling>,
+}
+
+impl LocalAssets {
+    /// The assets of the site of `target`, if it has one.
+    pub fn new(target: &Target) -> Result<Option<LocalAssets>> {
+        let site = match &target.site {
+            Some(site) => site,
+            None => return Ok(None),
+        };
+        StdOut::info(&format!(
+            "Serving the assets of your site from {}",
+            site.bucket.display()
+        ));
+        Ok(Some(LocalAssets {
+            target: target.clone(),
+            bucket: site.bucket.clone(),
+            settings: SiteSettings::new(site)?,
+            not_found_handling: site.not_found_handling()?,
+        }))
+    }
+
+    /// The response with the asset of a request, when the bucket has one.
+    pub fn serve(&self, method: &Method, path: &str) -> Option<Response<Body>> {
+        if method != Method::GET && method != Method::HEAD {
+            return None;
+        }
+        let file = self.asset(path)?;
+        self.response(StatusCode::OK, path, &file)
+    }
+
+    /// The page of `not_found_handling` of `[site]`, for a request that the worker found nothing
+    /// for.
+    pub fn fallback(&self, method: &Method, headers: &HeaderMap) -> Option<Response<Body>> {
+        if method != Method::GET && method != Method::HEAD {
+            return None;
+        }
+        let not_found_handling = self.not_found_handling?;
+        let status = match not_found_handling {
+            // the app routes pages, not the scripts or images that are missing
+            NotFoundHandling::SinglePageApp => {
+                let accept = headers.get(ACCEPT).and_then(|accept| accept.to_str().ok());
+                if !accept.map_or(false, |accept| accept.contains("text/html")) {
+                    return None;
+                }
+                StatusCode::OK
+            }
+            NotFoundHandling::NotFoundPage => StatusCode::NOT_FOUND,
+        };
+        let page = not_found_handling.page();
+        self.response(status, &format!("/{}", page), &self.bucket.join(page))
+    }
+
+    /// The file of the asset of a path, which is mapped like the worker of the site does:
+    /// `/about` and `/about/` to `about/index.html`.
+    fn asset(&self, path: &str) -> Option<PathBuf> {
+        let path = percent_decode_str(path).decode_utf8().ok()?;
+        let mut relative = path.trim_start_matches('/').to_string();
+        if relative.is_empty() || relative.ends_with('/') {
+            relative.push_str("index.html");
+        } else if mime_guess::from_path(&relative).first().is_none() {
+            relative.push_str("/index.html");
+        }
+        // only the files in the bucket are served
+        let relative = Path::new(&relative);
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return None;
+        }
+        let file = self.bucket.join(relative);
+        match sites::is_asset(&self.target, &self.bucket, &file) {
+            Ok(true) => Some(file),
+            Ok(false) => None,
+            Err(e) => {
+                log::warn!(
+                    "Could not tell whether {} is an asset: {}",
+                    file.display(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    fn response(&self, status: StatusCode, path: &str, file: &Path) -> Option<Response<Body>> {
+        let body = fs::read(file).ok()?;
+        let content_type = mime_guess::from_path(file).first_or_octet_stream();
+        let mut response = Response::builder()
+            .status(status)
+            .header(CONTENT_TYPE, content_type.as_ref())
+            .body(Body::from(body))
+            .ok()?;
+        // the headers of longer patterns replace those of shorter ones
+        for (name, value) in self.settings.headers(path) {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                response.headers_mut().insert(name, value);
+            }
+        }
+        Some(response)
+    }
+}
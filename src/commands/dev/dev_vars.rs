@@ -0,0 +1,113 @@
+//! The `.dev.vars` file of a project, whose `KEY=VALUE` lines override the `vars` of the
+//! configuration file in `wrangler dev`, so that secrets can be tried out without publishing them.
+//! The worker is uploaded again with the new bindings whenever the file changes.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Result;
+use notify::{DebouncedEvent, RecursiveMode, Watcher};
+
+use crate::settings::toml::Target;
+use crate::terminal::message::{Message, StdOut};
+
+pub const DEV_VARS: &str = ".dev.vars";
+
+/// Adds the variables of `.dev.vars` to the `vars` of `target`, replacing those with the same
+/// name.
+pub fn apply(target: &mut Target) -> Result<()> {
+    let content = match fs::read_to_string(DEV_VARS) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => anyhow::bail!("Failed to read {}: {}", DEV_VARS, e),
+    };
+    let dev_vars = parse(&content)?;
+    log::info!("Using {} variables of {}", dev_vars.len(), DEV_VARS);
+    let vars = target.vars.get_or_insert_with(HashMap::new);
+    for (name, value) in dev_vars {
+        vars.insert(name, serde_json::Value::String(value));
+    }
+    Ok(())
+}
+
+/// Sends to `tx` whenever `.dev.vars` is written, created or removed.
+pub fn watch(tx: Sender<()>) -> Result<()> {
+    let (watcher_tx, watcher_rx) = mpsc::channel();
+    let mut watcher = notify::watcher(watcher_tx, Duration::from_secs(1))?;
+    // the file may not exist yet
+    watcher.watch("./", RecursiveMode::NonRecursive)?;
+    thread::spawn::<_, Result<()>>(move || {
+        // the watcher stops when it is dropped
+        let _watcher = watcher;
+        for event in watcher_rx {
+            let path = match event {
+                DebouncedEvent::Write(path)
+                | DebouncedEvent::Create(path)
+                | DebouncedEvent::Remove(path) => path,
+                DebouncedEvent::Rename(_, path) => path,
+                _ => continue,
+            };
+            if is_dev_vars(&path) {
+                StdOut::working(&format!("Detected changes to {}...", DEV_VARS));
+                tx.send(())?;
+            }
+        }
+        Ok(())
+    });
+    Ok(())
+}
+
+/// Whether a changed file is `.dev.vars`, which `watch` reports.
+pub fn is_dev_vars(path: &Path) -> bool {
+    path.file_name() == Some(OsStr::new(DEV_VARS))
+}
+
+fn parse(content: &str) -> Result<Vec<(String, String)>> {
+    let mut vars = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, value) = line.split_once('=').ok_or_else(|| {
+            anyhow::anyhow!(
+                "Line {} of {} is not like KEY=VALUE: {}",
+                i + 1,
+                DEV_VARS,
+                line
+            )
+        })?;
+        let value = value.trim();
+        let value = ['"', '\'']
+            .iter()
+            .find_map(|&quote| value.strip_prefix(quote)?.strip_suffix(quote))
+            .unwrap_or(value);
+        vars.push((name.trim().to_string(), value.to_string()));
+    }
+    Ok(vars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_dev_vars() {
+        let vars =
+            parse("# secrets\nAPI_TOKEN = \"abc=def\"\n\nNAME='wrangler'\nEMPTY=\n").unwrap();
+        assert_eq!(
+            vars,
+            vec![
+                ("API_TOKEN".to_string(), "abc=def".to_string()),
+                ("NAME".to_string(), "wrangler".to_string()),
+                ("EMPTY".to_string(), "".to_string()),
+            ]
+        );
+        assert!(parse("API_TOKEN").is_err());
+    }
+}
@@ -7,7 +7,8 @@ use tokio::sync::oneshot;
 use tokio::task::JoinHandle;
 use watch::watch_for_changes;
 
-use crate::commands::dev::{socket, Protocol, ServerConfig};
+use crate::commands::dev::assets::LocalAssets;
+use crate::commands::dev::{socket, Limits, Protocol, ServerConfig};
 use crate::deploy::DeployTarget;
 use crate::login::check_update_oauth_token;
 use crate::settings::global_user::GlobalUser;
@@ -33,6 +34,9 @@ pub fn dev(
     upstream_protocol: Protocol,
     verbose: bool,
     inspect: bool,
+    upload_assets: bool,
+    kv_propagation_delay: Option<u64>,
+    limits: Limits,
 ) -> Result<()> {
     let runtime = TokioRuntime::new()?;
     loop {
@@ -57,6 +61,9 @@ pub fn dev(
             upstream_protocol,
             verbose,
             inspect,
+            upload_assets,
+            kv_propagation_delay,
+            limits,
             &runtime,
             sender,
             (rx_init_shutdown, tx_ack_shutdown),
@@ -88,11 +95,20 @@ fn dev_once(
     upstream_protocol: Protocol,
     verbose: bool,
     inspect: bool,
+    upload_assets: bool,
+    kv_propagation_delay: Option<u64>,
+    limits: Limits,
     runtime: &TokioRuntime,
     refresh_session_sender: Sender<Option<()>>,
     shutdown_channel: (oneshot::Receiver<()>, oneshot::Sender<()>),
 ) -> Result<Vec<JoinHandle<Result<()>>>> {
     let session = Session::new(&target, &user, &deploy_target)?;
+    let assets = if upload_assets {
+        None
+    } else {
+        LocalAssets::new(&target)?.map(Arc::new)
+    };
+    let local_assets = assets.is_some();
 
     let preview_token = upload(
         &mut target,
@@ -100,6 +116,9 @@ fn dev_once(
         &user,
         session.preview_token.clone(),
         verbose,
+        local_assets,
+        kv_propagation_delay,
+        limits,
     )?;
 
     let inspect = if inspect {
@@ -129,6 +148,9 @@ fn dev_once(
                 Arc::clone(&preview_token),
                 session_token,
                 verbose,
+                local_assets,
+                kv_propagation_delay,
+                limits,
                 refresh_session_sender,
             )
         });
@@ -155,13 +177,17 @@ fn dev_once(
             server_config,
             Arc::clone(&preview_token),
             host,
+            assets,
+            limits,
             shutdown_channel,
         )),
         Protocol::Http => runtime.spawn(server::http(
             server_config,
             Arc::clone(&preview_token),
             host,
+            assets,
             upstream_protocol,
+            limits,
             shutdown_channel,
         )),
     };
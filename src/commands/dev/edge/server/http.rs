@@ -1,6 +1,7 @@
 use super::preview_request;
+use crate::commands::dev::assets::LocalAssets;
 use crate::commands::dev::utils::{get_path_as_str, rewrite_redirect};
-use crate::commands::dev::{self, Protocol, ServerConfig};
+use crate::commands::dev::{self, Limits, Protocol, ServerConfig};
 use crate::terminal::emoji;
 
 use std::sync::{Arc, Mutex};
@@ -9,14 +10,16 @@ use anyhow::Result;
 use chrono::prelude::*;
 use hyper::service::{make_service_fn, service_fn};
 use hyper::upgrade::OnUpgrade;
-use hyper::Server;
+use hyper::{Server, StatusCode};
 use tokio::sync::oneshot::{Receiver, Sender};
 
 pub async fn http(
     server_config: ServerConfig,
     preview_token: Arc<Mutex<String>>,
     host: String,
+    assets: Option<Arc<LocalAssets>>,
     upstream_protocol: Protocol,
+    limits: Limits,
     shutdown_channel: (Receiver<()>, Sender<()>),
 ) -> Result<()> {
     // set up https client to connect to the preview service
@@ -29,6 +32,7 @@ pub async fn http(
         let client = client.to_owned();
         let preview_token = preview_token.to_owned();
         let host = host.to_owned();
+        let assets = assets.to_owned();
         let server_config = server_config.to_owned();
 
         async move {
@@ -41,8 +45,16 @@ pub async fn http(
                 let client = client.to_owned();
                 let preview_token = preview_token.lock().unwrap().to_owned();
                 let host = host.to_owned();
+                let assets = assets.to_owned();
                 let version = req.version();
                 let (parts, body) = req.into_parts();
+                // the worker accepts the websockets, whatever their path
+                let asset = assets
+                    .as_ref()
+                    .filter(|_| !is_websocket)
+                    .and_then(|assets| assets.serve(&parts.method, parts.uri.path()));
+                let method = parts.method.clone();
+                let headers = parts.headers.clone();
                 let local_host = format!(
                     "{}:{}",
                     server_config.listening_address.ip().to_string(),
@@ -52,18 +64,37 @@ pub async fn http(
                 let now: DateTime<Local> = Local::now();
                 let path = get_path_as_str(&parts.uri);
                 async move {
-                    let mut req = preview_request(
-                        parts,
-                        body,
-                        preview_token.to_owned(),
-                        host.clone(),
-                        upstream_protocol,
-                    );
-                    let client_on_upgrade = req.extensions_mut().remove::<OnUpgrade>();
+                    let resp = match asset {
+                        Some(resp) => resp,
+                        None => {
+                            let mut req = preview_request(
+                                parts,
+                                body,
+                                preview_token.to_owned(),
+                                host.clone(),
+                                upstream_protocol,
+                            );
+                            let client_on_upgrade = req.extensions_mut().remove::<OnUpgrade>();
+
+                            let mut resp = limits.guard(client.request(req)).await?;
+                            super::maybe_proxy_websocket(
+                                is_websocket,
+                                client_on_upgrade,
+                                &mut resp,
+                            );
+                            rewrite_redirect(&mut resp, &host, &local_host, false);
 
-                    let mut resp = client.request(req).await?;
-                    super::maybe_proxy_websocket(is_websocket, client_on_upgrade, &mut resp);
-                    rewrite_redirect(&mut resp, &host, &local_host, false);
+                            // the worker has none of the assets, so neither the fallback page
+                            if resp.status() == StatusCode::NOT_FOUND {
+                                if let Some(page) =
+                                    assets.and_then(|assets| assets.fallback(&method, &headers))
+                                {
+                                    resp = page;
+                                }
+                            }
+                            resp
+                        }
+                    };
 
                     println!(
                         "[{}] {} {}{} {:?} {}",
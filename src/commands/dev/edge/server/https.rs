@@ -1,6 +1,7 @@
 use super::preview_request;
+use crate::commands::dev::assets::LocalAssets;
 use crate::commands::dev::utils::{get_path_as_str, rewrite_redirect};
-use crate::commands::dev::{self, tls, Protocol, ServerConfig};
+use crate::commands::dev::{self, tls, Limits, Protocol, ServerConfig};
 use crate::terminal::emoji;
 use crate::terminal::message::{Message, StdOut};
 use std::sync::{Arc, Mutex};
@@ -11,7 +12,7 @@ use futures_util::{stream::StreamExt, FutureExt};
 
 use hyper::service::{make_service_fn, service_fn};
 use hyper::upgrade::OnUpgrade;
-use hyper::Server;
+use hyper::{Server, StatusCode};
 use tokio::net::TcpListener;
 use tokio::sync::oneshot::{Receiver, Sender};
 
@@ -19,6 +20,8 @@ pub async fn https(
     server_config: ServerConfig,
     preview_token: Arc<Mutex<String>>,
     host: String,
+    assets: Option<Arc<LocalAssets>>,
+    limits: Limits,
     shutdown_channel: (Receiver<()>, Sender<()>),
 ) -> Result<()> {
     tls::generate_cert()?;
@@ -33,6 +36,7 @@ pub async fn https(
         let client = client.to_owned();
         let preview_token = preview_token.to_owned();
         let host = host.to_owned();
+        let assets = assets.to_owned();
         let server_config = server_config.to_owned();
 
         async move {
@@ -44,8 +48,16 @@ pub async fn https(
                 let client = client.to_owned();
                 let preview_token = preview_token.lock().unwrap().to_owned();
                 let host = host.to_owned();
+                let assets = assets.to_owned();
                 let version = req.version();
                 let (parts, body) = req.into_parts();
+                // the worker accepts the websockets, whatever their path
+                let asset = assets
+                    .as_ref()
+                    .filter(|_| !is_websocket)
+                    .and_then(|assets| assets.serve(&parts.method, parts.uri.path()));
+                let method = parts.method.clone();
+                let headers = parts.headers.clone();
                 let local_host = format!(
                     "{}:{}",
                     server_config.listening_address.ip().to_string(),
@@ -55,19 +67,38 @@ pub async fn https(
                 let now: DateTime<Local> = Local::now();
                 let path = get_path_as_str(&parts.uri);
                 async move {
-                    let mut req = preview_request(
-                        parts,
-                        body,
-                        preview_token.to_owned(),
-                        host.clone(),
-                        Protocol::Http,
-                    );
-
-                    let client_on_upgrade = req.extensions_mut().remove::<OnUpgrade>();
-                    let mut resp = client.request(req).await?;
-                    super::maybe_proxy_websocket(is_websocket, client_on_upgrade, &mut resp);
-
-                    rewrite_redirect(&mut resp, &host, &local_host, true);
+                    let resp = match asset {
+                        Some(resp) => resp,
+                        None => {
+                            let mut req = preview_request(
+                                parts,
+                                body,
+                                preview_token.to_owned(),
+                                host.clone(),
+                                Protocol::Http,
+                            );
+
+                            let client_on_upgrade = req.extensions_mut().remove::<OnUpgrade>();
+                            let mut resp = limits.guard(client.request(req)).await?;
+                            super::maybe_proxy_websocket(
+                                is_websocket,
+                                client_on_upgrade,
+                                &mut resp,
+                            );
+
+                            rewrite_redirect(&mut resp, &host, &local_host, true);
+
+                            // the worker has none of the assets, so neither the fallback page
+                            if resp.status() == StatusCode::NOT_FOUND {
+                                if let Some(page) =
+                                    assets.and_then(|assets| assets.fallback(&method, &headers))
+                                {
+                                    resp = page;
+                                }
+                            }
+                            resp
+                        }
+                    };
 
                     println!(
                         "[{}] {} {}{} {:?} {}",
@@ -2,11 +2,12 @@ use std::error::Error;
 use std::fmt;
 use std::path::Path;
 
+use crate::commands::dev::{dev_vars, kv_consistency, Limits};
 use crate::deploy::DeployTarget;
 use crate::kv::bulk;
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
-use crate::sites::{add_namespace, sync};
+use crate::sites::{self, add_namespace, sync, AssetManifest};
 use crate::terminal::message::{Message, StdOut};
 use crate::upload;
 
@@ -21,31 +22,62 @@ pub(super) fn upload(
     user: &GlobalUser,
     session_token: String,
     verbose: bool,
+    local_assets: bool,
+    kv_propagation_delay: Option<u64>,
+    limits: Limits,
 ) -> Result<String> {
     let client = crate::http::legacy_auth_client(user);
+    dev_vars::apply(target)?;
+
+    let (to_delete, asset_manifest, site_namespace_id) =
+        if let Some(site_config) = target.site.clone() {
+            let site_namespace = add_namespace(user, target, true)?;
+            if local_assets {
+                // the worker finds none of the assets, which the dev server serves from the bucket
+                (
+                    Vec::new(),
+                    Some(AssetManifest::new()),
+                    Some(site_namespace.id),
+                )
+            } else {
+                let path = Path::new(&site_config.bucket);
+                let (to_upload, to_delete, asset_manifest) =
+                    sync(target, user, &site_namespace.id, path)?;
+
+                // First, upload all existing files in given directory
+                if verbose {
+                    StdOut::info("Uploading updated files...");
+                }
+
+                sites::upload(target, user, &site_namespace.id, to_upload, &None)?;
+                (to_delete, Some(asset_manifest), Some(site_namespace.id))
+            }
+        } else {
+            (Vec::new(), None, None)
+        };
 
-    let (to_delete, asset_manifest, site_namespace_id) = if let Some(site_config) =
-        target.site.clone()
-    {
-        let site_namespace = add_namespace(user, target, true)?;
-        let path = Path::new(&site_config.bucket);
-        let (to_upload, to_delete, asset_manifest) = sync(target, user, &site_namespace.id, path)?;
-
-        // First, upload all existing files in given directory
-        if verbose {
-            StdOut::info("Uploading updated files...");
-        }
+    let session_config = get_session_config(deploy_target);
+    let address = get_upload_address(target)?;
 
-        bulk::put(target, user, &site_namespace.id, to_upload, &None)?;
-        (to_delete, Some(asset_manifest), Some(site_namespace.id))
+    // the preludes are joined into a single line, so the lines of the worker are only off by one
+    let preludes: Vec<String> = kv_propagation_delay
+        .map(|delay| kv_consistency::prelude(&target.kv_namespaces, delay))
+        .into_iter()
+        .chain(limits.prelude())
+        .map(|prelude| prelude.trim_end().to_string())
+        .collect();
+    let prelude = if preludes.is_empty() {
+        None
     } else {
-        (Vec::new(), None, None)
+        Some(format!("{}\n", preludes.join(" ")))
     };
 
-    let session_config = get_session_config(deploy_target);
-    let address = get_upload_address(target)?;
-
-    let script_upload_form = upload::form::build(target, asset_manifest, Some(session_config))?;
+    let script_upload_form = upload::form::build(
+        target,
+        asset_manifest,
+        Some(session_config),
+        prelude.as_deref(),
+    )?;
 
     let response = client
         .post(&address)
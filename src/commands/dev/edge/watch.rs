@@ -2,6 +2,7 @@ use std::sync::mpsc::Sender;
 use std::sync::{mpsc, Arc, Mutex};
 
 use crate::commands::dev::edge::setup;
+use crate::commands::dev::{dev_vars, Limits};
 use crate::deploy::DeployTarget;
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
@@ -10,6 +11,7 @@ use crate::watch::watch_and_build;
 
 use anyhow::Result;
 
+#[allow(clippy::too_many_arguments)]
 pub fn watch_for_changes(
     target: &Target,
     deploy_target: &DeployTarget,
@@ -17,10 +19,18 @@ pub fn watch_for_changes(
     preview_token: Arc<Mutex<String>>,
     session_token: String,
     verbose: bool,
+    local_assets: bool,
+    kv_propagation_delay: Option<u64>,
+    limits: Limits,
     refresh_session_channel: Sender<Option<()>>,
 ) -> Result<()> {
     let (sender, receiver) = mpsc::channel();
-    watch_and_build(target, Some(sender), Some(refresh_session_channel.clone()))?;
+    watch_and_build(
+        target,
+        Some(sender.clone()),
+        Some(refresh_session_channel.clone()),
+    )?;
+    dev_vars::watch(sender)?;
 
     while receiver.recv().is_ok() {
         let user = user.clone();
@@ -37,7 +47,16 @@ pub fn watch_for_changes(
         //
         // this allows the server to route subsequent requests
         // to the proper script
-        let uploaded = setup::upload(&mut target, &deploy_target, &user, session_token, verbose);
+        let uploaded = setup::upload(
+            &mut target,
+            &deploy_target,
+            &user,
+            session_token,
+            verbose,
+            local_assets,
+            kv_propagation_delay,
+            limits,
+        );
 
         match uploaded {
             Ok(token) => {
@@ -1,4 +1,4 @@
-use crate::commands::dev::ServerConfig;
+use crate::commands::dev::{dev_vars, ServerConfig};
 use crate::preview::upload;
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
@@ -28,6 +28,7 @@ pub fn get_preview_id(
     // directing the user to open the browser to view the output
     // this message makes sense for `wrangler preview` but not `wrangler dev`
     let sites_preview = false;
+    dev_vars::apply(&mut target)?;
     let script_id = upload(&mut target, user.as_ref(), sites_preview, verbose)?;
     Ok(format!(
         "{}{}{}{}",
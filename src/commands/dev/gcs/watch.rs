@@ -1,5 +1,6 @@
 use std::sync::{mpsc, Arc, Mutex};
 
+use crate::commands::dev::dev_vars;
 use crate::commands::dev::gcs::setup::get_preview_id;
 use crate::commands::dev::server_config::ServerConfig;
 
@@ -16,7 +17,8 @@ pub fn watch_for_changes(
     verbose: bool,
 ) -> Result<()> {
     let (sender, receiver) = mpsc::channel();
-    watch_and_build(&target, Some(sender), None)?;
+    watch_and_build(&target, Some(sender.clone()), None)?;
+    dev_vars::watch(sender)?;
 
     while receiver.recv().is_ok() {
         let target = target.clone();
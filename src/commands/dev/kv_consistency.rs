@@ -0,0 +1,103 @@
+//! The eventual consistency of Workers KV, which `wrangler dev` simulates with `[dev]
+//! kv_propagation_delay` so that a worker reading a key right after writing it fails in dev like it
+//! may fail in production. A write is only seen by the reads of the worker once the delay has
+//! passed, and a value read with `cacheTtl` is kept for that long even if the key is written.
+
+use crate::settings::toml::KvNamespace;
+
+/// Replaces the KV namespaces of a service worker with wrappers that delay writes. It has no
+/// comments and is joined into a single line, so the lines of the worker are only off by one.
+const PRELUDE: &str = r#"
+(() => {
+  const DELAY = __DELAY__ * 1000;
+  const BINDINGS = __BINDINGS__;
+  const decode = (buffer, options) => {
+    const type = (typeof options === "string" ? options : options && options.type) || "text";
+    if (buffer === null) {
+      return null;
+    }
+    switch (type) {
+      case "arrayBuffer":
+        return buffer.slice(0);
+      case "json":
+        return JSON.parse(new TextDecoder().decode(buffer));
+      case "stream":
+        return new Response(buffer.slice(0)).body;
+      default:
+        return new TextDecoder().decode(buffer);
+    }
+  };
+  const wrap = (namespace) => {
+    const cached = new Map();
+    const written = new Map();
+    const read = async (key, options) => {
+      const now = Date.now();
+      for (const entries of [cached, written]) {
+        const entry = entries.get(key);
+        if (entry && now < entry.until) {
+          return entry;
+        }
+        entries.delete(key);
+      }
+      const { value, metadata } = await namespace.getWithMetadata(key, "arrayBuffer");
+      const cacheTtl = options && typeof options === "object" ? options.cacheTtl : undefined;
+      const entry = { value, metadata, until: now + (cacheTtl || 0) * 1000 };
+      if (cacheTtl) {
+        cached.set(key, entry);
+      }
+      return entry;
+    };
+    const write = (method) => async (key, ...args) => {
+      const previous = await read(key);
+      await namespace[method](key, ...args);
+      written.set(key, { ...previous, until: Date.now() + DELAY });
+    };
+    return new Proxy(namespace, {
+      get(target, property) {
+        switch (property) {
+          case "get":
+            return async (key, options) => decode((await read(key, options)).value, options);
+          case "getWithMetadata":
+            return async (key, options) => {
+              const { value, metadata } = await read(key, options);
+              return { value: decode(value, options), metadata: metadata === undefined ? null : metadata };
+            };
+          case "put":
+          case "delete":
+            return write(property);
+          default: {
+            const value = Reflect.get(target, property);
+            return typeof value === "function" ? value.bind(target) : value;
+          }
+        }
+      },
+    });
+  };
+  for (const binding of BINDINGS) {
+    if (globalThis[binding]) {
+      globalThis[binding] = wrap(globalThis[binding]);
+    }
+  }
+})();
+"#;
+
+/// The script put before a service worker to delay the writes of its KV namespaces by `delay`
+/// seconds.
+pub fn prelude(kv_namespaces: &[KvNamespace], delay: u64) -> String {
+    // the assets of a site are only read
+    let bindings: Vec<&str> = kv_namespaces
+        .iter()
+        .map(|namespace| namespace.binding.as_str())
+        .filter(|&binding| binding != "__STATIC_CONTENT")
+        .collect();
+    let script = PRELUDE
+        .replace("__DELAY__", &delay.to_string())
+        .replace("__BINDINGS__", &serde_json::json!(bindings).to_string());
+    let line = script
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!("{}\n", line)
+}
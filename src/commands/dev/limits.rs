@@ -0,0 +1,147 @@
+//! The limits of a worker in production, which `wrangler dev` simulates with `[dev]
+//! simulate_limits` so that a worker going over them fails in dev like it would in production. The
+//! subrequests are counted by the worker itself, since they are made from the edge, and the time
+//! of a request is measured by the dev server, which can't tell its CPU time from its wall time.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use hyper::{Body, Response, StatusCode};
+
+use crate::settings::toml::UsageModel;
+
+/// How many subrequests a worker of the bundled usage model can make in a request.
+const BUNDLED_SUBREQUESTS: u32 = 50;
+/// How many subrequests a worker of the unbound usage model can make in a request.
+const UNBOUND_SUBREQUESTS: u32 = 1000;
+/// How many seconds a request can take, which is the CPU limit of the unbound usage model.
+const CPU_LIMIT: u64 = 30;
+
+/// Wraps `fetch` so that it fails like in production once a request has made too many
+/// subrequests. The count is reset when an event is dispatched to the worker, so the
+/// subrequests of requests handled at the same time are counted together.
+const PRELUDE: &str = r#"
+(() => {
+  const LIMIT = __LIMIT__;
+  const addEventListener = globalThis.addEventListener;
+  const fetch = globalThis.fetch;
+  let count = 0;
+  globalThis.addEventListener = (type, listener, ...args) => {
+    const counted = typeof listener === "function"
+      ? (event) => { count = 0; return listener(event); }
+      : listener;
+    return addEventListener(type, counted, ...args);
+  };
+  globalThis.fetch = (...args) => {
+    count += 1;
+    if (count > LIMIT) {
+      return Promise.reject(new Error("Too many subrequests."));
+    }
+    return fetch(...args);
+  };
+})();
+"#;
+
+/// The limits `wrangler dev` puts on the requests to a worker.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Limits {
+    pub subrequests: Option<u32>,
+    pub cpu_time: Option<Duration>,
+}
+
+impl Limits {
+    /// The limits of a worker, which are those of its usage model when `simulate` is set and
+    /// none otherwise. `subrequests` and `cpu_limit` override them either way.
+    pub fn new(
+        usage_model: Option<UsageModel>,
+        simulate: bool,
+        subrequests: Option<u32>,
+        cpu_limit: Option<u64>,
+    ) -> Limits {
+        let (default_subrequests, default_cpu_limit) = if simulate {
+            let subrequests = match usage_model {
+                Some(UsageModel::Unbound) => UNBOUND_SUBREQUESTS,
+                Some(UsageModel::Bundled) | None => BUNDLED_SUBREQUESTS,
+            };
+            (Some(subrequests), Some(CPU_LIMIT))
+        } else {
+            (None, None)
+        };
+        Limits {
+            subrequests: subrequests.or(default_subrequests),
+            cpu_time: cpu_limit.or(default_cpu_limit).map(Duration::from_secs),
+        }
+    }
+
+    /// The script put before a service worker to limit its subrequests, if they are.
+    pub fn prelude(&self) -> Option<String> {
+        let limit = self.subrequests?;
+        let script = PRELUDE.replace("__LIMIT__", &limit.to_string());
+        let line = script
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Some(format!("{}\n", line))
+    }
+
+    /// Waits for the response of the worker, failing the request like in production if it takes
+    /// longer than the CPU limit.
+    pub async fn guard<F>(&self, response: F) -> Result<Response<Body>>
+    where
+        F: std::future::Future<Output = hyper::Result<Response<Body>>>,
+    {
+        let cpu_time = match self.cpu_time {
+            Some(cpu_time) => cpu_time,
+            None => return Ok(response.await?),
+        };
+        match tokio::time::timeout(cpu_time, response).await {
+            Ok(response) => Ok(response?),
+            Err(_) => {
+                log::warn!(
+                    "the worker took longer than {} seconds to respond",
+                    cpu_time.as_secs()
+                );
+                Ok(Response::builder()
+                    .status(StatusCode::SERVICE_UNAVAILABLE)
+                    .header("content-type", "text/plain")
+                    .body(Body::from(
+                        "error code: 1102\nWorker exceeded resource limits",
+                    ))?)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_has_no_limits_unless_simulated() {
+        assert_eq!(Limits::new(None, false, None, None), Limits::default());
+        assert_eq!(Limits::default().prelude(), None);
+    }
+
+    #[test]
+    fn it_uses_the_limits_of_the_usage_model() {
+        let bundled = Limits::new(Some(UsageModel::Bundled), true, None, None);
+        assert_eq!(bundled.subrequests, Some(50));
+        assert_eq!(bundled.cpu_time, Some(Duration::from_secs(30)));
+
+        let unbound = Limits::new(Some(UsageModel::Unbound), true, None, None);
+        assert_eq!(unbound.subrequests, Some(1000));
+    }
+
+    #[test]
+    fn it_overrides_the_limits() {
+        let limits = Limits::new(Some(UsageModel::Unbound), true, Some(3), Some(5));
+        assert_eq!(limits.subrequests, Some(3));
+        assert_eq!(limits.cpu_time, Some(Duration::from_secs(5)));
+
+        let prelude = limits.prelude().unwrap();
+        assert!(prelude.contains("const LIMIT = 3;"));
+        assert_eq!(prelude.lines().count(), 1);
+    }
+}
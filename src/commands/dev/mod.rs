@@ -1,5 +1,9 @@
+mod assets;
+pub mod dev_vars;
 mod edge;
 mod gcs;
+mod kv_consistency;
+mod limits;
 mod server_config;
 mod socket;
 mod tls;
@@ -8,6 +12,7 @@ mod utils;
 use hyper::client::HttpConnector;
 use hyper::Body;
 use hyper_rustls::HttpsConnector;
+pub use limits::Limits;
 pub use server_config::Protocol;
 pub use server_config::ServerConfig;
 
@@ -43,6 +48,9 @@ pub fn dev(
     verbose: bool,
     inspect: bool,
     unauthenticated: bool,
+    upload_assets: bool,
+    kv_propagation_delay: Option<u64>,
+    limits: Limits,
 ) -> Result<()> {
     // before serving requests we must first build the Worker
     build_target(&target)?;
@@ -94,6 +102,9 @@ pub fn dev(
                 upstream_protocol,
                 verbose,
                 inspect,
+                upload_assets,
+                kv_propagation_delay,
+                limits,
             );
         }
     } else {
@@ -109,5 +120,9 @@ pub fn dev(
         anyhow::bail!("wrangler dev does not yet support unauthenticated sessions when using Durable Objects. Please run wrangler login or wrangler config first.")
     }
 
+    if limits != Limits::default() {
+        StdOut::warn("The limits of production are only simulated by authenticated sessions of `wrangler dev`");
+    }
+
     gcs::dev(target, server_config, local_protocol, verbose, inspect)
 }
@@ -0,0 +1,157 @@
+//! The git repository and the `.gitignore` of new projects, which `--no-git`, `--git-branch`,
+//! `--commit` and the `git`, `git_branch`, `git_commit` and `gitignore` settings of the user's
+//! configuration change.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::Result;
+
+use crate::commands;
+use crate::settings::{get_global_config_path, profile};
+
+/// What is ignored when the user doesn't configure `gitignore`: dependencies, the local state of
+/// wrangler, and the secrets of `wrangler dev`.
+const DEFAULT_GITIGNORE: &[&str] = &["node_modules", WRANGLER_DIR, ".dev.vars"];
+/// The local state of wrangler, like the caches of builds, which is ignored even when the user
+/// configures `gitignore`.
+const WRANGLER_DIR: &str = ".wrangler/";
+
+/// How the repository of a new project is set up.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GitOptions {
+    /// Whether a repository is initialized.
+    pub init: bool,
+    /// The branch of the first commit, or the default of git.
+    pub branch: Option<String>,
+    /// Whether the files of the project are committed as the first commit.
+    pub commit: bool,
+    /// The patterns that the `.gitignore` of the project has to have.
+    pub gitignore: Vec<String>,
+}
+
+impl GitOptions {
+    /// The options given on the command line, with the user's defaults for the others.
+    pub fn new(no_git: bool, branch: Option<String>, commit: bool) -> GitOptions {
+        let config_path = get_global_config_path();
+        GitOptions {
+            init: !no_git && profile::git_enabled(&config_path),
+            branch: branch.or_else(|| profile::default_git_branch(&config_path)),
+            commit: commit || profile::git_commit_enabled(&config_path),
+            gitignore: gitignore_patterns(),
+        }
+    }
+
+    /// Writes the `.gitignore` of `dir`, initializes its repository and commits its files if
+    /// `commit` is set.
+    pub fn apply(&self, dir: &Path) -> Result<()> {
+        write_gitignore(dir, &self.gitignore)?;
+        if !self.init {
+            return Ok(());
+        }
+        // starters can be generated without git
+        if which::which("git").is_err() {
+            log::info!("Not initializing a git repository, since git is not installed");
+            return Ok(());
+        }
+        let mut init = Command::new("git");
+        init.args(&["init", "--quiet"]).current_dir(dir);
+        let command_name = format!("{:?}", init);
+        commands::run(init, &command_name)?;
+
+        if let Some(branch) = &self.branch {
+            // unlike `git init --initial-branch`, this works with any version of git
+            let mut symbolic_ref = Command::new("git");
+            symbolic_ref
+                .args(&["symbolic-ref", "HEAD"])
+                .arg(format!("refs/heads/{}", branch))
+                .current_dir(dir);
+            let command_name = format!("{:?}", symbolic_ref);
+            commands::run(symbolic_ref, &command_name)?;
+        }
+
+        if self.commit {
+            let mut add = Command::new("git");
+            add.args(&["add", "--all"]).current_dir(dir);
+            let command_name = format!("{:?}", add);
+            commands::run(add, &command_name)?;
+
+            let mut commit = Command::new("git");
+            commit
+                .args(&["commit", "--quiet", "--message", "Initial commit"])
+                .current_dir(dir);
+            let command_name = format!("{:?}", commit);
+            commands::run(commit, &command_name)?;
+        }
+        Ok(())
+    }
+}
+
+/// The patterns that the `.gitignore` of a project has to have, `gitignore` of the user's
+/// configuration or the default ones.
+pub fn gitignore_patterns() -> Vec<String> {
+    let mut patterns = profile::gitignore_entries(&get_global_config_path())
+        .unwrap_or_else(|| DEFAULT_GITIGNORE.iter().map(|s| s.to_string()).collect());
+    if !patterns
+        .iter()
+        .any(|pattern| ignores(pattern, WRANGLER_DIR))
+    {
+        patterns.push(WRANGLER_DIR.to_string());
+    }
+    patterns
+}
+
+/// Adds the `patterns` that the `.gitignore` of `dir` doesn't have yet, creating it if needed.
+pub fn write_gitignore(dir: &Path, patterns: &[String]) -> Result<()> {
+    let path = dir.join(".gitignore");
+    let mut gitignore = fs::read_to_string(&path).unwrap_or_default();
+    let missing: Vec<&String> = patterns
+        .iter()
+        .filter(|pattern| !ignores(&gitignore, pattern))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    if !gitignore.is_empty() && !gitignore.ends_with('\n') {
+        gitignore.push('\n');
+    }
+    for pattern in missing {
+        gitignore.push_str(pattern);
+        gitignore.push('\n');
+    }
+    log::info!("Writing {}", path.display());
+    fs::write(path, gitignore)?;
+    Ok(())
+}
+
+/// Whether a line of `gitignore` is `pattern`, ignoring slashes around it.
+fn ignores(gitignore: &str, pattern: &str) -> bool {
+    let pattern = pattern.trim_matches('/');
+    gitignore
+        .lines()
+        .any(|line| line.trim().trim_matches('/') == pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_adds_missing_patterns_to_gitignore() {
+        let dir = tempfile::tempdir().unwrap();
+        let patterns: Vec<String> = DEFAULT_GITIGNORE.iter().map(|s| s.to_string()).collect();
+        write_gitignore(dir.path(), &patterns).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join(".gitignore")).unwrap(),
+            "node_modules\n.wrangler/\n.dev.vars\n"
+        );
+
+        fs::write(dir.path().join(".gitignore"), "/node_modules/\ntarget").unwrap();
+        write_gitignore(dir.path(), &patterns).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join(".gitignore")).unwrap(),
+            "/node_modules/\ntarget\n.wrangler/\n.dev.vars\n"
+        );
+    }
+}
@@ -0,0 +1,172 @@
+//! The installation of the dependencies of new projects with the package manager that their
+//! lockfile is for, which `--no-install` skips. Packages are cached in the home directory of
+//! wrangler and preferred to the registry, so that projects are generated quickly even where the
+//! network is slow, and the packages of every directory are installed at the same time.
+
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread::{self, JoinHandle};
+
+use anyhow::Result;
+
+use crate::settings::get_wrangler_home_dir;
+use crate::terminal::message::{Message, StdOut};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PackageManager {
+    Npm,
+    Yarn,
+    Pnpm,
+}
+
+impl PackageManager {
+    /// The package manager of the project in `dir`, or `None` if it has no `package.json`.
+    pub fn detect(dir: &Path) -> Option<PackageManager> {
+        let package_json = fs::read_to_string(dir.join("package.json")).ok()?;
+        let manager = if dir.join("pnpm-lock.yaml").exists() {
+            PackageManager::Pnpm
+        } else if dir.join("yarn.lock").exists() {
+            PackageManager::Yarn
+        } else if dir.join("package-lock.json").exists() {
+            PackageManager::Npm
+        } else {
+            // e.g. "packageManager": "pnpm@6.32.2"
+            let package: serde_json::Value = serde_json::from_str(&package_json).ok()?;
+            match package["packageManager"].as_str() {
+                Some(name) if name.starts_with("pnpm@") => PackageManager::Pnpm,
+                Some(name) if name.starts_with("yarn@") => PackageManager::Yarn,
+                _ => PackageManager::Npm,
+            }
+        };
+        Some(manager)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            PackageManager::Npm => "npm",
+            PackageManager::Yarn => "yarn",
+            PackageManager::Pnpm => "pnpm",
+        }
+    }
+
+    /// The command installing the dependencies in `dir` with packages cached in `cache`.
+    fn install_command(self, dir: &Path, cache: &Path) -> Command {
+        let mut command = Command::new(self.name());
+        command
+            .current_dir(dir)
+            .args(&["install", "--prefer-offline"]);
+        match self {
+            PackageManager::Npm => command.env("npm_config_cache", cache),
+            PackageManager::Yarn => command.env("YARN_CACHE_FOLDER", cache),
+            PackageManager::Pnpm => command.arg("--store-dir").arg(cache),
+        };
+        command
+    }
+}
+
+/// The directories of a new project whose dependencies are installed: the project itself and
+/// the packages right under it, like the workers of a monorepo.
+pub fn package_dirs(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![dir.to_path_buf()];
+    if let Ok(entries) = fs::read_dir(dir) {
+        let mut children: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir() && path.file_name() != Some(OsStr::new("node_modules")))
+            .collect();
+        children.sort();
+        dirs.extend(children);
+    }
+    dirs.retain(|dir| dir.join("package.json").is_file());
+    dirs
+}
+
+/// Starts installing the dependencies of every package of the project in `dir`, each in a thread
+/// of its own, for the caller to do something else meanwhile.
+pub fn spawn(dir: &Path) -> Vec<JoinHandle<Result<()>>> {
+    package_dirs(dir)
+        .into_iter()
+        .filter_map(|dir| Some((PackageManager::detect(&dir)?, dir)))
+        .map(|(manager, dir)| thread::spawn(move || install(manager, &dir)))
+        .collect()
+}
+
+/// Waits for the installations that `spawn` started. A project that failed to install is still
+/// generated, so the failures are only warned about.
+pub fn wait(installs: Vec<JoinHandle<Result<()>>>) {
+    for install in installs {
+        let result = install
+            .join()
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("The installation panicked")));
+        if let Err(e) = result {
+            StdOut::warn(&format!("{}", e));
+        }
+    }
+}
+
+fn install(manager: PackageManager, dir: &Path) -> Result<()> {
+    if which::which(manager.name()).is_err() {
+        anyhow::bail!(
+            "Not installing the dependencies of {}, since {} is not installed",
+            dir.display(),
+            manager.name()
+        );
+    }
+    let cache = get_wrangler_home_dir()
+        .join("packages")
+        .join(manager.name());
+    fs::create_dir_all(&cache)?;
+
+    StdOut::working(&format!(
+        "Installing the dependencies of {} with {}...",
+        dir.display(),
+        manager.name()
+    ));
+    // the output of installations running at the same time would be interleaved
+    let output = manager.install_command(dir, &cache).output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to install the dependencies of {} with {}. Run `{} install` there to try again.\n{}",
+            dir.display(),
+            manager.name(),
+            manager.name(),
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_detects_package_managers() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(PackageManager::detect(dir.path()), None);
+
+        fs::write(dir.path().join("package.json"), "{}").unwrap();
+        assert_eq!(
+            PackageManager::detect(dir.path()),
+            Some(PackageManager::Npm)
+        );
+
+        fs::write(
+            dir.path().join("package.json"),
+            r#"{"packageManager": "yarn@1.22.17"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            PackageManager::detect(dir.path()),
+            Some(PackageManager::Yarn)
+        );
+
+        fs::write(dir.path().join("pnpm-lock.yaml"), "").unwrap();
+        assert_eq!(
+            PackageManager::detect(dir.path()),
+            Some(PackageManager::Pnpm)
+        );
+    }
+}
@@ -0,0 +1,221 @@
+mod git;
+mod install;
+mod placeholders;
+pub mod registry;
+mod starter;
+mod template;
+mod workspace;
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{env, fs};
+
+use anyhow::{anyhow, Result};
+
+use crate::commands::validate_worker_name;
+use crate::settings::toml::{Manifest, Site, TargetType};
+use crate::terminal::interactive;
+use crate::{build, commands};
+use placeholders::{TemplateManifest, TEMPLATE_MANIFEST};
+
+pub use git::{gitignore_patterns, write_gitignore, GitOptions};
+pub use starter::{GenerateType, Starter};
+pub use template::TemplateSource;
+pub use workspace::generate_workspace;
+
+pub fn generate(
+    name: &str,
+    template: &str,
+    branch: Option<&str>,
+    defines: &BTreeMap<String, String>,
+    target_type: Option<TargetType>,
+    site: bool,
+    git: &GitOptions,
+    install: bool,
+) -> Result<()> {
+    validate_worker_name(name)?;
+
+    let new_name = project_name(name);
+    log::info!("Generating a new worker project with name '{}'", new_name);
+    run_generate(
+        &new_name,
+        &TemplateSource::parse(template, branch)?,
+        defines,
+        git,
+        install,
+    )?;
+
+    let config_path = PathBuf::from("./").join(&new_name);
+    // TODO: this is tightly coupled to our site template. Need to remove once
+    // we refine our generate logic.
+    let generated_site = if site {
+        Some(Site::new("./public"))
+    } else {
+        None
+    };
+    Manifest::generate(new_name, target_type, &config_path, generated_site)?;
+
+    Ok(())
+}
+
+/// Generates the project `name` from one of the starters, which are built in.
+pub fn generate_starter(name: &str, starter: Starter, git: &GitOptions) -> Result<()> {
+    validate_worker_name(name)?;
+
+    let new_name = project_name(name);
+    log::info!(
+        "Generating a new {} worker project with name '{}'",
+        starter,
+        new_name
+    );
+    let dir = PathBuf::from("./").join(&new_name);
+    starter.scaffold(&dir)?;
+    Manifest::generate(new_name, Some(TargetType::JavaScript), &dir, None)?;
+    git.apply(&dir)
+}
+
+/// Generates the project `name` from a template with cargo-generate, which fills in the name of
+/// the project, its authors, and the placeholders declared by the template with the values of
+/// `defines` or those that the user is prompted for, then sets up its repository as `git` says
+/// while the dependencies are installed, unless `install` is unset.
+pub fn run_generate(
+    name: &str,
+    source: &TemplateSource,
+    defines: &BTreeMap<String, String>,
+    git: &GitOptions,
+    install: bool,
+) -> Result<()> {
+    source.ensure_outside(Path::new(name))?;
+    let template = tempfile::tempdir()?;
+    source.fetch(template.path())?;
+    declare_placeholders(template.path(), name, defines)?;
+    commit_template(template.path())?;
+
+    let binary_path = crate::install::install_cargo_generate()?;
+    let template_path = template.path().display().to_string();
+    let args = [
+        "generate",
+        "--git",
+        &template_path,
+        "--name",
+        name,
+        "--force",
+    ];
+    let command = build::command(&args, &binary_path);
+    let command_name = format!("{:?}", command);
+    commands::run(command, &command_name)?;
+
+    let dir = Path::new(name);
+    // cargo-generate initializes a repository of its own
+    let repository = dir.join(".git");
+    if repository.exists() {
+        fs::remove_dir_all(repository)?;
+    }
+
+    let installs = if install {
+        install::spawn(dir)
+    } else {
+        Vec::new()
+    };
+    let applied = git.apply(dir);
+    install::wait(installs);
+    applied
+}
+
+/// The name of the directory of a new project, `name` unless a directory already has it.
+pub fn project_name(name: &str) -> String {
+    if !directory_exists(name).unwrap_or(true) {
+        return String::from(name);
+    }
+    match generate_name(name) {
+        Ok(val) => val,
+        Err(_) => {
+            log::debug!(
+                "Failed to auto-increment name for a new worker project, using '{}'",
+                name
+            );
+            String::from(name)
+        }
+    }
+}
+
+/// Declares the values of the placeholders of the template in `dir` for cargo-generate.
+fn declare_placeholders(dir: &Path, name: &str, defines: &BTreeMap<String, String>) -> Result<()> {
+    let mut values = BTreeMap::new();
+    values.insert("project-name".to_string(), name.to_string());
+    values.insert("crate_name".to_string(), name.replace("-", "_"));
+
+    let manifest_path = dir.join(TEMPLATE_MANIFEST);
+    if manifest_path.is_file() {
+        let manifest = TemplateManifest::read(&manifest_path)?;
+        fs::remove_file(&manifest_path)?;
+        values.extend(manifest.values(defines, interactive::is_interactive())?);
+    }
+    values.extend(defines.clone());
+    template::declare(dir, &values)
+}
+
+/// Commits the template in `dir` to a new repository, since cargo-generate clones the projects
+/// it generates from one.
+fn commit_template(dir: &Path) -> Result<()> {
+    which::which("git").map_err(|e| anyhow!("git is required to generate projects: {}", e))?;
+    let git = |args: &[&str]| {
+        let mut command = Command::new("git");
+        command.args(args).current_dir(dir);
+        let command_name = format!("{:?}", command);
+        commands::run(command, &command_name)
+    };
+    git(&["init", "--quiet"])?;
+    git(&["add", "--all"])?;
+    git(&[
+        "-c",
+        "user.name=wrangler",
+        "-c",
+        "user.email=wrangler@localhost",
+        "-c",
+        "commit.gpgsign=false",
+        "commit",
+        "--quiet",
+        "--no-verify",
+        "--allow-empty",
+        "--message",
+        "Template",
+    ])
+}
+
+fn generate_name(name: &str) -> Result<String> {
+    let mut num = 1;
+    let entry_names = read_current_dir()?;
+    let mut new_name = construct_name(name, num);
+
+    while entry_names.contains(&OsString::from(&new_name)) {
+        num += 1;
+        new_name = construct_name(name, num);
+    }
+    Ok(new_name)
+}
+
+fn read_current_dir() -> Result<Vec<OsString>> {
+    let current_dir = env::current_dir()?;
+    let mut entry_names = Vec::new();
+
+    for entry in fs::read_dir(current_dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.is_dir() {
+            entry_names.push(entry.file_name());
+        }
+    }
+    Ok(entry_names)
+}
+
+fn directory_exists(dirname: &str) -> Result<bool> {
+    let entry_names = read_current_dir()?;
+    Ok(entry_names.contains(&OsString::from(dirname)))
+}
+
+fn construct_name(name: &str, num: i32) -> String {
+    format!("{}-{}", name, num)
+}
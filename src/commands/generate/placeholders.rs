@@ -0,0 +1,196 @@
+//! The placeholders that a template declares in its `template.toml`, whose values are prompted
+//! for or passed with `--define`, like the placeholders of cargo-generate:
+//!
+//! ```toml
+//! [placeholders]
+//! description = { prompt = "What does the Worker do?", default = "A Worker" }
+//! storage = { prompt = "Where is data stored?", choices = ["kv", "r2"], default = "kv" }
+//! analytics = { type = "bool", prompt = "Send analytics?", default = false }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::terminal::interactive;
+use crate::terminal::message::{Message, StdErr};
+
+/// The manifest of a template, which is not copied to the generated project.
+pub const TEMPLATE_MANIFEST: &str = "template.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateManifest {
+    #[serde(default)]
+    pub placeholders: BTreeMap<String, Placeholder>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Placeholder {
+    #[serde(default, rename = "type")]
+    pub kind: PlaceholderType,
+    pub prompt: Option<String>,
+    pub default: Option<DefaultValue>,
+    /// The values that are allowed.
+    pub choices: Option<Vec<String>>,
+    /// A pattern that the whole value must match.
+    pub regex: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaceholderType {
+    String,
+    Bool,
+}
+
+impl Default for PlaceholderType {
+    fn default() -> Self {
+        PlaceholderType::String
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum DefaultValue {
+    Bool(bool),
+    String(String),
+}
+
+impl DefaultValue {
+    fn to_value(&self) -> String {
+        match self {
+            DefaultValue::Bool(value) => value.to_string(),
+            DefaultValue::String(value) => value.clone(),
+        }
+    }
+}
+
+impl TemplateManifest {
+    pub fn read(path: &Path) -> Result<TemplateManifest> {
+        let content = fs::read_to_string(path)?;
+        toml::from_str(&content).with_context(|| format!("Invalid {}", path.display()))
+    }
+
+    /// The value of each placeholder: the one passed with `--define`, the answer to its prompt
+    /// if wrangler can prompt, or else its default.
+    pub fn values(
+        &self,
+        defines: &BTreeMap<String, String>,
+        interactive: bool,
+    ) -> Result<BTreeMap<String, String>> {
+        let mut values = BTreeMap::new();
+        for (name, placeholder) in &self.placeholders {
+            let value = match defines.get(name) {
+                Some(value) => {
+                    placeholder.validate(name, value)?;
+                    value.clone()
+                }
+                None if interactive => placeholder.ask(name)?,
+                None => match &placeholder.default {
+                    Some(default) => default.to_value(),
+                    None => anyhow::bail!(
+                        "The template needs a value for `{}`; pass it with --define {}=<value>",
+                        name,
+                        name
+                    ),
+                },
+            };
+            values.insert(name.clone(), value);
+        }
+        Ok(values)
+    }
+}
+
+impl Placeholder {
+    fn validate(&self, name: &str, value: &str) -> Result<()> {
+        if self.kind == PlaceholderType::Bool {
+            anyhow::ensure!(
+                value == "true" || value == "false",
+                "`{}` must be true or false, not \"{}\"",
+                name,
+                value
+            );
+        }
+        if let Some(choices) = &self.choices {
+            anyhow::ensure!(
+                choices.iter().any(|choice| choice == value),
+                "`{}` must be one of {}, not \"{}\"",
+                name,
+                choices.join(", "),
+                value
+            );
+        }
+        if let Some(regex) = &self.regex {
+            let pattern = Regex::new(&format!("^(?:{})$", regex))
+                .with_context(|| format!("Invalid regex of `{}` in {}", name, TEMPLATE_MANIFEST))?;
+            anyhow::ensure!(
+                pattern.is_match(value),
+                "`{}` must match {}, not \"{}\"",
+                name,
+                regex,
+                value
+            );
+        }
+        Ok(())
+    }
+
+    /// Prompts for the value until a valid one is given.
+    fn ask(&self, name: &str) -> Result<String> {
+        let prompt = self.prompt.as_deref().unwrap_or(name);
+        let default = self.default.as_ref().map(DefaultValue::to_value);
+        loop {
+            let value = if let Some(choices) = &self.choices {
+                choices[interactive::select(prompt, choices)?].clone()
+            } else if self.kind == PlaceholderType::Bool {
+                interactive::confirm(prompt)?.to_string()
+            } else {
+                let prompt = match &default {
+                    Some(default) => format!("{} [{}]", prompt, default),
+                    None => prompt.to_string(),
+                };
+                match interactive::get_user_input(&prompt) {
+                    value if value.is_empty() => default.clone().unwrap_or_default(),
+                    value => value,
+                }
+            };
+            match self.validate(name, &value) {
+                Ok(()) => return Ok(value),
+                Err(e) => StdErr::user_error(&e.to_string()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_takes_defined_values_and_defaults() {
+        let manifest: TemplateManifest = toml::from_str(
+            "[placeholders]\nstorage = { choices = [\"kv\", \"r2\"], default = \"kv\" }\nanalytics = { type = \"bool\", default = false }\nversion = { regex = \"[0-9]+\" }\n",
+        )
+        .unwrap();
+
+        let mut defines = BTreeMap::new();
+        defines.insert("version".to_string(), "2".to_string());
+        let values = manifest.values(&defines, false).unwrap();
+        assert_eq!(values["storage"], "kv");
+        assert_eq!(values["analytics"], "false");
+        assert_eq!(values["version"], "2");
+
+        defines.insert("version".to_string(), "v2".to_string());
+        assert!(manifest.values(&defines, false).is_err());
+        defines.clear();
+        assert!(manifest.values(&defines, false).is_err());
+        defines.insert("version".to_string(), "2".to_string());
+        defines.insert("storage".to_string(), "d1".to_string());
+        assert!(manifest.values(&defines, false).is_err());
+    }
+}
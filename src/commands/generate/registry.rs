@@ -0,0 +1,143 @@
+//! The registry of templates, an index of the official templates and of those of the community
+//! that `wrangler generate --list` lets the user pick from. The index is built into wrangler,
+//! unless another one is set.
+
+use std::env;
+use std::fs;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::http;
+use crate::terminal::interactive;
+
+const REGISTRY: &str = include_str!("registry.json");
+/// Overrides the registry, with a URL or the path of a JSON file, e.g. for an internal registry.
+const REGISTRY_VAR: &str = "WRANGLER_TEMPLATE_REGISTRY";
+
+#[derive(Debug, Deserialize)]
+struct Registry {
+    templates: Vec<RegistryEntry>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct RegistryEntry {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// What `wrangler generate` is given to generate from the template.
+    pub url: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Whether the template is maintained by Cloudflare.
+    #[serde(default)]
+    pub official: bool,
+}
+
+impl RegistryEntry {
+    fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        self.name.to_lowercase().contains(&query)
+            || self.description.to_lowercase().contains(&query)
+            || self.tags.iter().any(|tag| tag.to_lowercase() == query)
+    }
+
+    fn label(&self) -> String {
+        let kind = if self.official {
+            "official"
+        } else {
+            "community"
+        };
+        format!("{} ({}): {}", self.name, kind, self.description)
+    }
+}
+
+/// Lists the templates of the registry that match `query`, official ones first. The user picks
+/// one when wrangler can prompt, whose URL is returned.
+pub fn browse(query: Option<&str>) -> Result<Option<String>> {
+    let mut templates = fetch()?;
+    if let Some(query) = query {
+        templates.retain(|template| template.matches(query));
+    }
+    anyhow::ensure!(
+        !templates.is_empty(),
+        "No template in the registry matches \"{}\"",
+        query.unwrap_or_default()
+    );
+    templates.sort_by(|a, b| b.official.cmp(&a.official).then(a.name.cmp(&b.name)));
+
+    if !interactive::is_interactive() {
+        for template in &templates {
+            println!("{}\n    {}", template.label(), template.url);
+        }
+        return Ok(None);
+    }
+    let labels: Vec<String> = templates.iter().map(RegistryEntry::label).collect();
+    let index = interactive::select("Which template do you want to generate from?", &labels)?;
+    Ok(Some(templates.swap_remove(index).url))
+}
+
+fn fetch() -> Result<Vec<RegistryEntry>> {
+    let location = match env::var(REGISTRY_VAR) {
+        Ok(location) => location,
+        Err(_) => return parse(REGISTRY, "built into wrangler"),
+    };
+    let index = if location.starts_with("https://") || location.starts_with("http://") {
+        log::info!("Fetching the template registry from {}", location);
+        let res = http::client().get(&location).send()?;
+        anyhow::ensure!(
+            res.status().is_success(),
+            "Could not fetch the template registry from {}: {}",
+            location,
+            res.status()
+        );
+        res.text()?
+    } else {
+        fs::read_to_string(&location)
+            .with_context(|| format!("Could not read the template registry {}", location))?
+    };
+    parse(&index, &location)
+}
+
+fn parse(index: &str, location: &str) -> Result<Vec<RegistryEntry>> {
+    let registry: Registry = serde_json::from_str(index)
+        .with_context(|| format!("Invalid template registry {}", location))?;
+    Ok(registry.templates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::generate::TemplateSource;
+
+    #[test]
+    fn it_searches_names_descriptions_and_tags() {
+        let registry: Registry = serde_json::from_str(
+            r#"{"templates": [
+                {"name": "router", "description": "Routes requests", "url": "https://example.com/router", "official": true},
+                {"name": "counter", "description": "Counts visits in Workers KV", "url": "https://example.com/counter"},
+                {"name": "cache", "url": "https://example.com/cache", "tags": ["kv"]}
+            ]}"#,
+        )
+        .unwrap();
+        let names = |query| {
+            registry
+                .templates
+                .iter()
+                .filter(|template| template.matches(query))
+                .map(|template| template.name.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("kv"), vec!["counter", "cache"]);
+        assert_eq!(names("ROUTE"), vec!["router"]);
+        assert!(names("durable").is_empty());
+    }
+
+    #[test]
+    fn it_parses_the_builtin_registry() {
+        let templates = parse(REGISTRY, "built into wrangler").unwrap();
+        assert!(templates
+            .iter()
+            .all(|template| TemplateSource::parse(&template.url, None).is_ok()));
+    }
+}
@@ -0,0 +1,157 @@
+//! The starter projects of `wrangler generate --type`, which need no template: a Durable Object
+//! with its binding and first migration, and a Worker with a Cron Trigger.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+
+use crate::settings::toml::TargetType;
+
+/// What `--type` of `wrangler generate` asks for.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GenerateType {
+    /// The type of a project generated from a template.
+    Target(TargetType),
+    Starter(Starter),
+}
+
+impl FromStr for GenerateType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "durable-objects" => Ok(GenerateType::Starter(Starter::DurableObjects)),
+            "scheduled" => Ok(GenerateType::Starter(Starter::Scheduled)),
+            _ => s.parse().map(GenerateType::Target).map_err(|_| {
+                anyhow!(
+                    "{} is not a valid type! Use javascript, rust, webpack, durable-objects or scheduled.",
+                    s
+                )
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Starter {
+    DurableObjects,
+    Scheduled,
+}
+
+impl fmt::Display for Starter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Starter::DurableObjects => write!(f, "durable-objects"),
+            Starter::Scheduled => write!(f, "scheduled"),
+        }
+    }
+}
+
+const DURABLE_OBJECTS_CONFIG: &str = r#"type = "javascript"
+
+[build.upload]
+format = "modules"
+dir = "src"
+main = "./index.mjs"
+
+[durable_objects]
+classes = [
+  { binding = "COUNTER", class_name = "Counter" },
+]
+
+# Each class has to be created by a migration before it is bound
+[[migrations]]
+tag = "v1"
+new_classes = ["Counter"]
+"#;
+
+const DURABLE_OBJECTS_SCRIPT: &str = r#"// A Durable Object, whose storage is shared by every request for the same ID
+export class Counter {
+  constructor(state) {
+    this.state = state;
+  }
+
+  async fetch(request) {
+    const value = ((await this.state.storage.get("value")) || 0) + 1;
+    await this.state.storage.put("value", value);
+    return new Response(String(value));
+  }
+}
+
+export default {
+  async fetch(request, env) {
+    const id = env.COUNTER.idFromName(new URL(request.url).pathname);
+    return env.COUNTER.get(id).fetch(request);
+  },
+};
+"#;
+
+const SCHEDULED_CONFIG: &str = r#"type = "javascript"
+main = "src/index.js"
+
+[triggers]
+crons = ["*/30 * * * *"]
+"#;
+
+const SCHEDULED_SCRIPT: &str = r#"// Runs on the schedule of the Cron Triggers in wrangler.toml
+addEventListener("scheduled", (event) => {
+  event.waitUntil(handleScheduled(event.scheduledTime));
+});
+
+async function handleScheduled(scheduledTime) {
+  console.log(`Triggered at ${new Date(scheduledTime).toISOString()}`);
+}
+
+addEventListener("fetch", (event) => {
+  event.respondWith(new Response("This worker runs on a schedule."));
+});
+"#;
+
+impl Starter {
+    /// Writes the script of the starter and the template of its `wrangler.toml` to `dir`.
+    pub fn scaffold(self, dir: &Path) -> Result<()> {
+        let (config, script, script_path) = match self {
+            Starter::DurableObjects => (
+                DURABLE_OBJECTS_CONFIG,
+                DURABLE_OBJECTS_SCRIPT,
+                "src/index.mjs",
+            ),
+            Starter::Scheduled => (SCHEDULED_CONFIG, SCHEDULED_SCRIPT, "src/index.js"),
+        };
+        fs::create_dir_all(dir.join("src"))?;
+        fs::write(dir.join("wrangler.toml"), config)?;
+        fs::write(dir.join(script_path), script)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::settings::toml::Manifest;
+
+    #[test]
+    fn it_configures_starters_completely() {
+        let manifest: Manifest = toml::from_str(DURABLE_OBJECTS_CONFIG).unwrap();
+        let classes = manifest.durable_objects.unwrap().classes.unwrap();
+        assert_eq!(classes[0].class_name, "Counter");
+        assert_eq!(manifest.migrations.unwrap()[0].tag, "v1");
+
+        let manifest: Manifest = toml::from_str(SCHEDULED_CONFIG).unwrap();
+        assert_eq!(manifest.triggers.unwrap().crons, vec!["*/30 * * * *"]);
+
+        assert_eq!(
+            "durable-objects".parse::<GenerateType>().unwrap(),
+            GenerateType::Starter(Starter::DurableObjects)
+        );
+        assert_eq!(
+            "rust".parse::<GenerateType>().unwrap(),
+            GenerateType::Target(TargetType::Rust)
+        );
+        assert!("cron".parse::<GenerateType>().is_err());
+    }
+}
@@ -0,0 +1,499 @@
+//! Fetches the templates of `wrangler generate` from git repositories on any host, and declares
+//! the values of their placeholders for cargo-generate, which renders them.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::{env, fs};
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use url::Url;
+
+use crate::commands;
+
+static PLACEHOLDER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{\s*([\w-]+)\s*\}\}").unwrap());
+static LIQUID_VARIABLE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z_][\w-]*$").unwrap());
+
+/// What is not copied from a local template.
+const SKIPPED_DIRS: &[&str] = &[".git", "node_modules", "target"];
+
+/// The version of git that `git sparse-checkout` came with.
+const SPARSE_CHECKOUT_VERSION: (u32, u32) = (2, 25);
+
+/// Where a template is.
+#[derive(Debug, PartialEq)]
+pub enum TemplateSource {
+    /// A directory on this machine, which needs no network.
+    Local(PathBuf),
+    /// A git repository, a branch of it, and a directory in it.
+    Git {
+        url: String,
+        /// The default branch of the repository when `None`.
+        branch: Option<String>,
+        /// The root of the repository when `None`.
+        subdir: Option<PathBuf>,
+    },
+}
+
+impl TemplateSource {
+    /// Parses the template argument of `wrangler generate`. A path, like `./templates/router`,
+    /// or a `file://` URL is a local template, unless a branch of it is asked for. Anything else
+    /// is the URL of a repository that git can clone, optionally followed by a directory in the
+    /// repository, like `https://git.example.com/templates.git/workers/router` or
+    /// `https://git.example.com/templates//workers/router`. The URLs of directories on GitHub,
+    /// GitLab, Bitbucket and Gitea, such as `https://github.com/owner/repo/tree/main/router`,
+    /// also set the branch.
+    pub fn parse(template: &str, branch: Option<&str>) -> Result<TemplateSource> {
+        if let Some(path) = local_path(template)? {
+            return Ok(match branch {
+                // git clones the branch of a local repository as well
+                Some(branch) => TemplateSource::Git {
+                    url: path.display().to_string(),
+                    branch: Some(branch.to_string()),
+                    subdir: None,
+                },
+                None => TemplateSource::Local(path),
+            });
+        }
+
+        let template = template.trim_end_matches('/');
+        let (mut url, mut subdir) = split_subdir(template);
+        let mut url_branch = None;
+        if subdir.is_none() {
+            if let Some((repo, branch, dir)) = split_web_url(&url) {
+                url = repo;
+                url_branch = Some(branch);
+                subdir = dir;
+            }
+        }
+
+        let branch = match (branch, url_branch) {
+            (Some(branch), Some(url_branch)) if branch != url_branch => anyhow::bail!(
+                "--branch {} differs from the branch {} in the URL of the template",
+                branch,
+                url_branch
+            ),
+            (branch, url_branch) => branch.map(str::to_string).or(url_branch),
+        };
+        let subdir = subdir.map(PathBuf::from);
+        if let Some(subdir) = &subdir {
+            anyhow::ensure!(
+                subdir
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_))),
+                "The directory of a template must be a relative path in its repository, not {}",
+                subdir.display()
+            );
+        }
+        Ok(TemplateSource::Git {
+            url,
+            branch,
+            subdir,
+        })
+    }
+
+    /// Copies the template to `dir`, cloning it with git first unless it is local.
+    pub fn fetch(&self, dir: &Path) -> Result<()> {
+        match self {
+            TemplateSource::Local(path) => copy_dir(path, dir),
+            TemplateSource::Git {
+                url,
+                branch,
+                subdir,
+            } => fetch_git(url, branch.as_deref(), subdir.as_deref(), dir),
+        }
+    }
+
+    /// Fails if the project `dir` would be inside the template, which it is generated from.
+    pub fn ensure_outside(&self, dir: &Path) -> Result<()> {
+        if let TemplateSource::Local(path) = self {
+            let template = path.canonicalize()?;
+            // the project doesn't exist yet
+            let dir = env::current_dir()?.canonicalize()?.join(dir);
+            anyhow::ensure!(
+                !dir.starts_with(&template),
+                "Can't generate {} inside its template {}",
+                dir.display(),
+                template.display()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// The directory of a local template, if `template` is a path or a `file://` URL.
+fn local_path(template: &str) -> Result<Option<PathBuf>> {
+    let path = if template.starts_with("file://") {
+        Url::parse(template)?
+            .to_file_path()
+            .map_err(|_| anyhow!("{} is not the URL of a local directory", template))?
+    } else {
+        let path = PathBuf::from(template);
+        let is_path = template.starts_with('.') || path.is_absolute() || path.is_dir();
+        if !is_path {
+            return Ok(None);
+        }
+        path
+    };
+    anyhow::ensure!(
+        path.is_dir(),
+        "There is no template directory at {}",
+        path.display()
+    );
+    Ok(Some(path))
+}
+
+/// Clones a template with git, only checking out its directory, and copies it to `dir`.
+fn fetch_git(url: &str, branch: Option<&str>, subdir: Option<&Path>, dir: &Path) -> Result<()> {
+    which::which("git").map_err(|e| anyhow!("git is required to fetch templates: {}", e))?;
+    let checkout = tempfile::tempdir()?;
+
+    // older versions of git check out the whole repository
+    let sparse = subdir.is_some() && supports_sparse_checkout();
+    let mut clone = Command::new("git");
+    clone.args(&["clone", "--quiet", "--depth", "1"]);
+    if let Some(branch) = branch {
+        clone.args(&["--branch", branch]);
+    }
+    if sparse {
+        clone.args(&["--filter=blob:none", "--sparse"]);
+    }
+    clone.arg(url).arg(checkout.path());
+    let command_name = format!("{:?}", clone);
+    commands::run(clone, &command_name)?;
+
+    let root = match subdir {
+        Some(subdir) => {
+            if sparse {
+                let mut sparse = Command::new("git");
+                sparse.arg("-C").arg(checkout.path());
+                sparse.args(&["sparse-checkout", "set"]).arg(subdir);
+                let command_name = format!("{:?}", sparse);
+                commands::run(sparse, &command_name)?;
+            }
+            checkout.path().join(subdir)
+        }
+        None => checkout.path().to_path_buf(),
+    };
+    anyhow::ensure!(
+        root.is_dir(),
+        "{} has no directory {}",
+        url,
+        root.strip_prefix(checkout.path())?.display()
+    );
+    copy_dir(&root, dir)
+}
+
+/// Whether the installed git has `git sparse-checkout`.
+fn supports_sparse_checkout() -> bool {
+    let version = Command::new("git")
+        .arg("--version")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| git_version(&String::from_utf8_lossy(&output.stdout)));
+    match version {
+        Some(version) => version >= SPARSE_CHECKOUT_VERSION,
+        None => false,
+    }
+}
+
+/// The major and minor version in the output of `git --version`, like `git version 2.37.1` or
+/// `git version 2.35.1.windows.2`.
+fn git_version(output: &str) -> Option<(u32, u32)> {
+    let version = output.trim().strip_prefix("git version ")?;
+    let mut numbers = version.split(|c: char| !c.is_ascii_digit());
+    let major = numbers.next()?.parse().ok()?;
+    let minor = numbers.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+/// Splits the directory off a template whose URL has one after `.git/` or `//`.
+fn split_subdir(template: &str) -> (String, Option<String>) {
+    let start = template.find("://").map_or(0, |i| i + "://".len());
+    let split = match template[start..].find(".git/") {
+        Some(i) => Some((start + i + ".git".len(), start + i + ".git/".len())),
+        None => template[start..]
+            .find("//")
+            .map(|i| (start + i, start + i + "//".len())),
+    };
+    match split {
+        Some((end, subdir)) => (
+            template[..end].to_string(),
+            Some(template[subdir..].to_string()).filter(|subdir| !subdir.is_empty()),
+        ),
+        None => (template.to_string(), None),
+    }
+}
+
+/// Splits the URL of a directory of a repository on the web into the URL of the repository, the
+/// branch and the directory.
+fn split_web_url(url: &str) -> Option<(String, String, Option<String>)> {
+    let parsed = Url::parse(url)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))?;
+    let segments: Vec<&str> = parsed.path_segments()?.filter(|s| !s.is_empty()).collect();
+    let (repo, rest) = match segments.iter().position(|segment| *segment == "-") {
+        // GitLab, whose groups nest: group/subgroup/repo/-/tree/branch/dir
+        Some(i) if segments.get(i + 1) == Some(&"tree") => (&segments[..i], &segments[i + 2..]),
+        Some(_) => return None,
+        // GitHub: owner/repo/tree/branch/dir, Bitbucket: owner/repo/src/branch/dir, and Gitea:
+        // owner/repo/src/branch/branch/dir
+        None if segments.len() > 3 && matches!(segments[2], "tree" | "src") => {
+            let rest = &segments[3..];
+            match rest {
+                ["branch", _, ..] if segments[2] == "src" => (&segments[..2], &rest[1..]),
+                _ => (&segments[..2], rest),
+            }
+        }
+        None => return None,
+    };
+    let (branch, dir) = rest.split_first()?;
+    let mut repo_url = parsed.clone();
+    repo_url.set_path(&repo.join("/"));
+    let dir = Some(dir.join("/")).filter(|dir| !dir.is_empty());
+    Some((repo_url.to_string(), branch.to_string(), dir))
+}
+
+/// Copies a template to `to`, leaving out its git repository, its dependencies and what it
+/// builds.
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        if SKIPPED_DIRS.iter().any(|dir| entry.file_name() == *dir) {
+            continue;
+        }
+        let path = entry.path();
+        let dest = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&path, &dest)?;
+        } else {
+            fs::copy(&path, &dest)?;
+        }
+    }
+    Ok(())
+}
+
+/// Fills in `values`, like `{{ project-name }}`, in the names of the files under `dir`, and
+/// declares them as variables of the files that are Liquid templates. cargo-generate then renders
+/// the placeholders, filters and conditionals of those files with them as well as with its own.
+pub fn declare(dir: &Path, values: &BTreeMap<String, String>) -> Result<()> {
+    let assigns = assigns(values)?;
+    declare_in(dir, values, &assigns)
+}
+
+fn declare_in(dir: &Path, values: &BTreeMap<String, String>, assigns: &str) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let mut path = entry?.path();
+        if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+            let rendered = fill(name, values);
+            if rendered != name {
+                let renamed = path.with_file_name(&*rendered);
+                fs::rename(&path, &renamed)?;
+                path = renamed;
+            }
+        }
+        if path.is_dir() {
+            declare_in(&path, values, assigns)?;
+            continue;
+        }
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            // binary files
+            Err(_) => continue,
+        };
+        if text.contains("{{") || text.contains("{%") {
+            fs::write(&path, format!("{}{}", assigns, text))?;
+        }
+    }
+    Ok(())
+}
+
+fn fill<'a>(text: &'a str, values: &BTreeMap<String, String>) -> Cow<'a, str> {
+    PLACEHOLDER.replace_all(text, |caps: &Captures| {
+        values
+            .get(&caps[1])
+            .cloned()
+            .unwrap_or_else(|| caps[0].to_string())
+    })
+}
+
+/// The Liquid tags that assign `values`, which render to nothing. `true` and `false` are
+/// booleans, for the conditionals of the placeholders of that type.
+fn assigns(values: &BTreeMap<String, String>) -> Result<String> {
+    let mut assigns = String::new();
+    for (name, value) in values {
+        anyhow::ensure!(
+            LIQUID_VARIABLE.is_match(name),
+            "`{}` can't be the name of a placeholder, which must be a Liquid variable",
+            name
+        );
+        let literal = match value.as_str() {
+            "true" | "false" => value.clone(),
+            _ if !value.contains('"') => format!("\"{}\"", value),
+            _ if !value.contains('\'') => format!("'{}'", value),
+            _ => anyhow::bail!(
+                "The value of `{}` can't have both single and double quotes",
+                name
+            ),
+        };
+        assigns.push_str(&format!("{{% assign {} = {} %}}", name, literal));
+    }
+    Ok(assigns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(url: &str, branch: Option<&str>, subdir: Option<&str>) -> TemplateSource {
+        TemplateSource::Git {
+            url: url.to_string(),
+            branch: branch.map(str::to_string),
+            subdir: subdir.map(PathBuf::from),
+        }
+    }
+
+    #[test]
+    fn it_parses_templates_on_any_host() {
+        let parse = |template, branch| TemplateSource::parse(template, branch).unwrap();
+        assert_eq!(
+            parse("https://github.com/cloudflare/worker-template", None),
+            source("https://github.com/cloudflare/worker-template", None, None)
+        );
+        assert_eq!(
+            parse(
+                "git@git.example.com:infra/templates.git/workers/router",
+                Some("v2")
+            ),
+            source(
+                "git@git.example.com:infra/templates.git",
+                Some("v2"),
+                Some("workers/router")
+            )
+        );
+        assert_eq!(
+            parse("https://git.example.com/templates//router/", None),
+            source("https://git.example.com/templates", None, Some("router"))
+        );
+        assert_eq!(
+            parse(
+                "https://github.com/owner/repo/tree/main/workers/router",
+                None
+            ),
+            source(
+                "https://github.com/owner/repo",
+                Some("main"),
+                Some("workers/router")
+            )
+        );
+        assert_eq!(
+            parse("https://gitlab.com/group/sub/repo/-/tree/dev/router", None),
+            source(
+                "https://gitlab.com/group/sub/repo",
+                Some("dev"),
+                Some("router")
+            )
+        );
+        assert_eq!(
+            parse("https://bitbucket.org/owner/repo/src/main", Some("main")),
+            source("https://bitbucket.org/owner/repo", Some("main"), None)
+        );
+        assert!(TemplateSource::parse("https://github.com/o/r/tree/main", Some("dev")).is_err());
+        assert!(TemplateSource::parse("https://example.com/repo.git/../etc", None).is_err());
+    }
+
+    #[test]
+    fn it_parses_local_templates() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let dir = tmp_dir.path().to_path_buf();
+        let url = Url::from_file_path(&dir).unwrap().to_string();
+        assert_eq!(
+            TemplateSource::parse(&url, None).unwrap(),
+            TemplateSource::Local(dir.clone())
+        );
+        let path = dir.display().to_string();
+        assert_eq!(
+            TemplateSource::parse(&path, None).unwrap(),
+            TemplateSource::Local(dir.clone())
+        );
+        assert_eq!(
+            TemplateSource::parse(&path, Some("main")).unwrap(),
+            source(&path, Some("main"), None)
+        );
+        assert!(TemplateSource::parse("./no/such/template", None).is_err());
+    }
+
+    #[test]
+    fn it_copies_local_templates_without_their_dependencies() {
+        let template = tempfile::tempdir().unwrap();
+        for dir in &[".git", "node_modules/a", "target/debug", "src"] {
+            fs::create_dir_all(template.path().join(dir)).unwrap();
+        }
+        fs::write(template.path().join("src/index.js"), "export {}").unwrap();
+        fs::write(template.path().join("node_modules/a/index.js"), "").unwrap();
+
+        let project = tempfile::tempdir().unwrap();
+        TemplateSource::Local(template.path().to_path_buf())
+            .fetch(project.path())
+            .unwrap();
+        let mut names: Vec<_> = fs::read_dir(project.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["src"]);
+        assert!(project.path().join("src/index.js").is_file());
+    }
+
+    #[test]
+    fn it_rejects_projects_inside_their_template() {
+        let cwd = env::current_dir().unwrap();
+        let inside = TemplateSource::Local(cwd.join("."));
+        assert!(inside.ensure_outside(Path::new("my-worker")).is_err());
+        let outside = TemplateSource::Local(cwd.join("src"));
+        assert!(outside.ensure_outside(Path::new("my-worker")).is_ok());
+    }
+
+    #[test]
+    fn it_reads_the_version_of_git() {
+        assert_eq!(git_version("git version 2.25.0\n"), Some((2, 25)));
+        assert_eq!(git_version("git version 2.35.1.windows.2"), Some((2, 35)));
+        assert_eq!(
+            git_version("git version 2.24.3 (Apple Git-128)"),
+            Some((2, 24))
+        );
+        assert_eq!(git_version("hub version 2.14.2"), None);
+    }
+
+    #[test]
+    fn it_declares_values_in_liquid_templates() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("{{ storage }}.js"), "export {}").unwrap();
+        fs::write(
+            dir.path().join("README.md"),
+            "{% if analytics %}{{ storage | upcase }}{% endif %}",
+        )
+        .unwrap();
+
+        let mut values = BTreeMap::new();
+        values.insert("storage".to_string(), "kv".to_string());
+        values.insert("analytics".to_string(), "true".to_string());
+        declare(dir.path(), &values).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("kv.js")).unwrap(),
+            "export {}"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("README.md")).unwrap(),
+            "{% assign analytics = true %}{% assign storage = \"kv\" %}{% if analytics %}{{ storage | upcase }}{% endif %}"
+        );
+
+        values.insert("quote".to_string(), "it's \"kv\"".to_string());
+        assert!(declare(dir.path(), &values).is_err());
+    }
+}
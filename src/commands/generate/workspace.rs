@@ -0,0 +1,164 @@
+//! `wrangler generate --workspace`, which lays out a repository of several Workers: one
+//! directory with a `wrangler.toml` for each Worker, all extending a shared base configuration,
+//! and a first Worker that forwards requests to the others through service bindings.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use chrono::Utc;
+
+use super::GitOptions;
+use crate::commands::validate_worker_name;
+use crate::settings::{get_global_config_path, profile};
+use crate::terminal::message::{Message, StdOut};
+
+const BASE_CONFIG: &str = "wrangler.base.toml";
+
+/// Generates the workspace `name` with a Worker for each of `workers`. The first Worker is the
+/// one that receives requests, and is bound to every other Worker.
+pub fn generate_workspace(name: &str, workers: &[String], git: &GitOptions) -> Result<()> {
+    anyhow::ensure!(!workers.is_empty(), "A workspace needs at least one worker");
+    for (i, worker) in workers.iter().enumerate() {
+        anyhow::ensure!(
+            !workers[..i].contains(worker),
+            "The worker {} is listed twice",
+            worker
+        );
+        validate_worker_name(&script_name(name, worker))?;
+    }
+
+    let root = Path::new(name);
+    fs::create_dir_all(root)?;
+    fs::write(root.join(BASE_CONFIG), base_config())?;
+    fs::write(root.join("README.md"), readme(name, workers))?;
+
+    let (gateway, others) = workers.split_first().expect("there is a worker");
+    for worker in workers {
+        let dir = root.join(worker);
+        fs::create_dir_all(dir.join("src"))?;
+        let bound = if worker == gateway { others } else { &[] };
+        fs::write(
+            dir.join("wrangler.toml"),
+            worker_config(name, worker, bound),
+        )?;
+        let script = if worker == gateway {
+            gateway_script(others)
+        } else {
+            worker_script(worker)
+        };
+        fs::write(dir.join("src").join("index.js"), script)?;
+    }
+
+    git.apply(root)?;
+    StdOut::success(&format!(
+        "Generated the workspace {} with {} workers",
+        name,
+        workers.len()
+    ));
+    Ok(())
+}
+
+/// The name of a Worker of the workspace once published, like `shop-auth`.
+fn script_name(workspace: &str, worker: &str) -> String {
+    format!("{}-{}", workspace, worker)
+}
+
+/// The name of the binding of a Worker, like `AUTH` for `auth`.
+fn binding_name(worker: &str) -> String {
+    worker.to_uppercase().replace('-', "_")
+}
+
+fn base_config() -> String {
+    let compatibility_date = profile::default_compatibility_date(&get_global_config_path())
+        .unwrap_or_else(|| Utc::now().format("%F").to_string());
+    format!(
+        "# The settings shared by every worker of the workspace, which each wrangler.toml extends.\n\
+         type = \"javascript\"\n\
+         workers_dev = true\n\
+         compatibility_date = \"{}\"\n",
+        compatibility_date
+    )
+}
+
+fn worker_config(workspace: &str, worker: &str, bound: &[String]) -> String {
+    let mut config = format!(
+        "extends = \"../{}\"\nname = \"{}\"\nmain = \"src/index.js\"\n",
+        BASE_CONFIG,
+        script_name(workspace, worker)
+    );
+    for other in bound {
+        config.push_str(&format!(
+            "\n[[services]]\nbinding = \"{}\"\nservice = \"{}\"\n",
+            binding_name(other),
+            script_name(workspace, other)
+        ));
+    }
+    config
+}
+
+fn gateway_script(others: &[String]) -> String {
+    let mut routes = String::new();
+    for other in others {
+        routes.push_str(&format!(
+            "  if (url.pathname.startsWith(\"/{}/\")) {{\n    return {}.fetch(request);\n  }}\n",
+            other,
+            binding_name(other)
+        ));
+    }
+    format!(
+        "addEventListener(\"fetch\", (event) => {{\n  event.respondWith(handleRequest(event.request));\n}});\n\n\
+         async function handleRequest(request) {{\n  const url = new URL(request.url);\n{}  return new Response(\"Hello from the gateway!\");\n}}\n",
+        routes
+    )
+}
+
+fn worker_script(worker: &str) -> String {
+    format!(
+        "addEventListener(\"fetch\", (event) => {{\n  event.respondWith(new Response(\"Hello from {}!\"));\n}});\n",
+        worker
+    )
+}
+
+fn readme(name: &str, workers: &[String]) -> String {
+    let (gateway, others) = workers.split_first().expect("there is a worker");
+    let mut readme = format!(
+        "# {}\n\nEach directory is a worker with its own `wrangler.toml`, which extends the settings of `{}`.\n\n",
+        name, BASE_CONFIG
+    );
+    if !others.is_empty() {
+        readme.push_str(&format!(
+            "`{}` receives the requests, and forwards `/<worker>/...` to the other workers through service bindings. \
+             Since a worker can only be bound to workers that exist, publish them first:\n\n```sh\n",
+            gateway
+        ));
+        for worker in others.iter().chain(Some(gateway)) {
+            readme.push_str(&format!("(cd {} && wrangler publish)\n", worker));
+        }
+        readme.push_str("```\n\n");
+    }
+    readme.push_str(&format!(
+        "Run `wrangler dev` in the directory of a worker to develop it; `{}` calls the published versions of the others.\n",
+        gateway
+    ));
+    readme
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_binds_the_gateway_to_the_other_workers() {
+        let others = vec!["auth".to_string(), "user-data".to_string()];
+        let config = worker_config("shop", "api", &others);
+        let value: toml::Value = toml::from_str(&config).unwrap();
+        assert_eq!(value["name"].as_str(), Some("shop-api"));
+        assert_eq!(value["services"][1]["binding"].as_str(), Some("USER_DATA"));
+        assert_eq!(
+            value["services"][1]["service"].as_str(),
+            Some("shop-user-data")
+        );
+        assert!(gateway_script(&others).contains("return USER_DATA.fetch(request);"));
+    }
+}
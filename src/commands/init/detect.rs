@@ -0,0 +1,167 @@
+//! Finds out what kind of project `wrangler init` is run in, so that the `wrangler.toml` it
+//! creates can build the project as it is.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+use crate::settings::toml::TargetType;
+
+const WEBPACK_CONFIGS: &[&str] = &[
+    "webpack.config.js",
+    "webpack.config.cjs",
+    "webpack.config.mjs",
+    "webpack.config.ts",
+];
+const ENTRY_POINTS: &[&str] = &["index.js", "worker.js", "src/index.js", "src/worker.js"];
+const WORKER_BUILD_COMMAND: &str = "cargo install -q worker-build && worker-build --release";
+
+/// The settings of a project that was detected.
+#[derive(Debug, PartialEq)]
+pub struct Detected {
+    /// What was found, like "a Rust project using workers-rs".
+    pub description: &'static str,
+    pub target_type: TargetType,
+    pub main: Option<String>,
+    pub webpack_config: Option<String>,
+    pub build_command: Option<String>,
+    /// The directory and the main module of Workers uploaded in the modules format.
+    pub modules: Option<(String, String)>,
+}
+
+impl Detected {
+    fn new(description: &'static str, target_type: TargetType) -> Detected {
+        Detected {
+            description,
+            target_type,
+            main: None,
+            webpack_config: None,
+            build_command: None,
+            modules: None,
+        }
+    }
+
+    /// Adds the settings to the `wrangler.toml` created by `Manifest::generate`.
+    pub fn apply(&self, config_file: &Path) -> Result<()> {
+        let mut doc = fs::read_to_string(config_file)?
+            .parse::<toml_edit::Document>()
+            .map_err(|err| {
+                anyhow!(
+                    "toml_edit failed to parse {}. {}",
+                    config_file.display(),
+                    err
+                )
+            })?;
+        // Manifest::generate writes the type of Rust projects as javascript
+        doc["type"] = toml_edit::value(self.target_type.to_string());
+        if let Some(main) = &self.main {
+            doc["main"] = toml_edit::value(main.as_str());
+        }
+        if let Some(webpack_config) = &self.webpack_config {
+            doc["webpack_config"] = toml_edit::value(webpack_config.as_str());
+        }
+        if let Some(command) = &self.build_command {
+            doc["build"]["command"] = toml_edit::value(command.as_str());
+        }
+        if let Some((dir, main)) = &self.modules {
+            doc["build"]["upload"]["format"] = toml_edit::value("modules");
+            doc["build"]["upload"]["dir"] = toml_edit::value(dir.as_str());
+            doc["build"]["upload"]["main"] = toml_edit::value(main.as_str());
+        }
+        fs::write(config_file, doc.to_string_in_original_order())?;
+        Ok(())
+    }
+}
+
+/// Detects the project in `dir`: a Rust crate, a package for webpack, or a plain script. Returns
+/// `None` when none of them are found.
+pub fn detect(dir: &Path) -> Result<Option<Detected>> {
+    let cargo_toml = dir.join("Cargo.toml");
+    if cargo_toml.is_file() {
+        let manifest: toml::Value = toml::from_str(&fs::read_to_string(&cargo_toml)?)?;
+        let uses_workers_rs = manifest
+            .get("dependencies")
+            .and_then(|dependencies| dependencies.get("worker"))
+            .is_some();
+        if !uses_workers_rs {
+            return Ok(Some(Detected::new(
+                "a Rust project built by wasm-pack",
+                TargetType::Rust,
+            )));
+        }
+        let mut detected = Detected::new("a Rust project using workers-rs", TargetType::JavaScript);
+        detected.build_command = Some(WORKER_BUILD_COMMAND.to_string());
+        detected.modules = Some(("build/worker".to_string(), "./shim.mjs".to_string()));
+        return Ok(Some(detected));
+    }
+
+    let entry_point = ENTRY_POINTS
+        .iter()
+        .find(|path| dir.join(path).is_file())
+        .map(|path| path.to_string());
+    let package_json = dir.join("package.json");
+    if package_json.is_file() {
+        let package: Value = serde_json::from_str(&fs::read_to_string(&package_json)?)?;
+        let mut detected = Detected::new("a package bundled by webpack", TargetType::Webpack);
+        detected.webpack_config = WEBPACK_CONFIGS
+            .iter()
+            .find(|path| dir.join(path).is_file())
+            .map(|path| path.to_string());
+        if package.get("main").is_none() {
+            detected.main = entry_point;
+        }
+        return Ok(Some(detected));
+    }
+
+    Ok(entry_point.map(|main| {
+        let mut detected = Detected::new("a plain script", TargetType::JavaScript);
+        detected.main = Some(main);
+        detected
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_detects_projects() {
+        let project = tempfile::tempdir().unwrap();
+        let dir = project.path();
+        fs::create_dir_all(dir.join("src")).unwrap();
+        assert_eq!(detect(dir).unwrap(), None);
+
+        fs::write(
+            dir.join("src/index.js"),
+            "addEventListener('fetch', () => {});",
+        )
+        .unwrap();
+        let detected = detect(dir).unwrap().unwrap();
+        assert_eq!(detected.target_type, TargetType::JavaScript);
+        assert_eq!(detected.main.as_deref(), Some("src/index.js"));
+
+        fs::write(dir.join("package.json"), "{\"name\": \"worker\"}").unwrap();
+        fs::write(dir.join("webpack.config.js"), "module.exports = {};").unwrap();
+        let detected = detect(dir).unwrap().unwrap();
+        assert_eq!(detected.target_type, TargetType::Webpack);
+        assert_eq!(detected.main.as_deref(), Some("src/index.js"));
+        assert_eq!(
+            detected.webpack_config.as_deref(),
+            Some("webpack.config.js")
+        );
+
+        fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"worker\"\n\n[dependencies]\nworker = \"0.0.9\"\n",
+        )
+        .unwrap();
+        let detected = detect(dir).unwrap().unwrap();
+        assert_eq!(detected.target_type, TargetType::JavaScript);
+        assert_eq!(
+            detected.build_command.as_deref(),
+            Some(WORKER_BUILD_COMMAND)
+        );
+    }
+}
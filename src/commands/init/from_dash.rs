@@ -0,0 +1,589 @@
+//! `wrangler init --from-dash`, which brings a Worker that was created in the dashboard under
+//! version control: its script is downloaded to `src/`, and its bindings, routes and Cron
+//! Triggers are written to a new `wrangler.toml`.
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use cloudflare::endpoints::workers::ListRoutes;
+use cloudflare::framework::apiclient::ApiClient;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::commands::generate::{gitignore_patterns, write_gitignore};
+use crate::commands::validate_worker_name;
+use crate::commands::whoami::fetch_zones_cached;
+use crate::http;
+use crate::settings::global_user::GlobalUser;
+use crate::settings::toml::target::LazyAccountId;
+use crate::terminal::message::{Message, StdOut};
+use crate::upload::form::ModuleType;
+
+const SOURCE_DIR: &str = "src";
+
+/// The settings of a deployed Worker.
+#[derive(Debug, Default, Deserialize)]
+struct ScriptSettings {
+    #[serde(default)]
+    bindings: Vec<DashBinding>,
+    compatibility_date: Option<String>,
+    #[serde(default)]
+    compatibility_flags: Vec<String>,
+    usage_model: Option<String>,
+}
+
+/// A binding of a deployed Worker, as the API returns it.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum DashBinding {
+    KvNamespace {
+        name: String,
+        namespace_id: String,
+    },
+    R2Bucket {
+        name: String,
+        bucket_name: String,
+    },
+    DurableObjectNamespace {
+        name: String,
+        class_name: String,
+        script_name: Option<String>,
+    },
+    PlainText {
+        name: String,
+        text: String,
+    },
+    Json {
+        name: String,
+        json: Value,
+    },
+    SecretText {
+        name: String,
+    },
+    WasmModule {
+        name: String,
+        part: String,
+    },
+    TextBlob {
+        name: String,
+        part: String,
+    },
+    Service {
+        name: String,
+        service: String,
+        environment: Option<String>,
+    },
+    #[serde(other)]
+    Unsupported,
+}
+
+#[derive(Debug, Deserialize)]
+struct Schedules {
+    schedules: Vec<Schedule>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Schedule {
+    cron: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Subdomain {
+    enabled: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct V4Response<T> {
+    result: T,
+}
+
+/// A part of a script downloaded as `multipart/form-data`.
+#[derive(Debug, PartialEq)]
+struct Part {
+    name: String,
+    content_type: Option<String>,
+    body: Vec<u8>,
+}
+
+/// The script of a Worker, as it was uploaded.
+enum Script {
+    ServiceWorker { script: Vec<u8>, parts: Vec<Part> },
+    Modules { main: String, modules: Vec<Part> },
+}
+
+/// Creates a project in the current directory from the Worker `name` deployed to the account,
+/// replacing the files that exist if `force` is set.
+pub fn init_from_dash(name: &str, user: &GlobalUser, force: bool) -> Result<()> {
+    anyhow::ensure!(
+        force || !Path::new("./wrangler.toml").exists(),
+        "A wrangler.toml file already exists! Please remove it, or pass --force to replace it."
+    );
+    validate_worker_name(name)?;
+    let account_id = LazyAccountId::default().load()?.clone();
+
+    StdOut::working(&format!("Downloading {}...", name));
+    let script = download_script(user, &account_id, name)?;
+    let settings = fetch_settings(user, &account_id, name)?;
+    let crons = fetch_schedules(user, &account_id, name)?;
+    let routes = fetch_routes(user, &account_id, name);
+    let workers_dev = fetch_workers_dev(user, &account_id, name).unwrap_or_else(|e| {
+        log::info!(
+            "Could not fetch the workers.dev subdomain of {}: {}",
+            name,
+            e
+        );
+        routes.is_empty()
+    });
+
+    let mut config = toml::value::Table::new();
+    config.insert("name".into(), name.into());
+    config.insert("type".into(), "javascript".into());
+    config.insert("account_id".into(), account_id.into());
+    config.insert("workers_dev".into(), workers_dev.into());
+    if !routes.is_empty() {
+        config.insert("routes".into(), routes.into());
+    }
+    if let Some(date) = settings.compatibility_date {
+        config.insert("compatibility_date".into(), date.into());
+    }
+    if !settings.compatibility_flags.is_empty() {
+        config.insert(
+            "compatibility_flags".into(),
+            settings.compatibility_flags.into(),
+        );
+    }
+    if let Some(usage_model) = settings.usage_model {
+        config.insert("usage_model".into(), usage_model.into());
+    }
+    if !crons.is_empty() {
+        let mut triggers = toml::value::Table::new();
+        triggers.insert("crons".into(), crons.into());
+        config.insert("triggers".into(), triggers.into());
+    }
+
+    let src = Path::new(SOURCE_DIR);
+    let mut files = Vec::new();
+    match script {
+        Script::ServiceWorker { script, parts } => {
+            let main = src.join("index.js");
+            config.insert("main".into(), path_value(&main));
+            files.push((main, script));
+            let mut wasm_modules = toml::value::Table::new();
+            let mut text_blobs = toml::value::Table::new();
+            for binding in &settings.bindings {
+                let (name, part, extension, table) = match binding {
+                    DashBinding::WasmModule { name, part } => {
+                        (name, part, "wasm", &mut wasm_modules)
+                    }
+                    DashBinding::TextBlob { name, part } => (name, part, "txt", &mut text_blobs),
+                    _ => continue,
+                };
+                let body = match parts.iter().find(|p| &p.name == part) {
+                    Some(p) => p.body.clone(),
+                    None => anyhow::bail!("The script has no part {} for {}", part, name),
+                };
+                let path = src.join(format!("{}.{}", name, extension));
+                table.insert(name.clone(), path_value(&path));
+                files.push((path, body));
+            }
+            if !wasm_modules.is_empty() {
+                config.insert("wasm_modules".into(), wasm_modules.into());
+            }
+            if !text_blobs.is_empty() {
+                config.insert("text_blobs".into(), text_blobs.into());
+            }
+        }
+        Script::Modules { main, modules } => {
+            let mut rules = Vec::new();
+            for module in modules {
+                let path = module_path(&module.name)?;
+                let module_type = module.content_type.as_deref().and_then(|content_type| {
+                    ModuleType::iter()
+                        .find(|t| t.content_type() == content_type)
+                        .copied()
+                });
+                if let Some(module_type) = module_type {
+                    if !has_default_glob(&module.name, module_type) {
+                        let mut rule = toml::value::Table::new();
+                        rule.insert("type".into(), module_type.name().into());
+                        rule.insert("globs".into(), vec![module.name.clone()].into());
+                        rules.push(toml::Value::from(rule));
+                    }
+                }
+                files.push((src.join(path), module.body));
+            }
+            let mut upload = toml::value::Table::new();
+            upload.insert("format".into(), "modules".into());
+            upload.insert("dir".into(), SOURCE_DIR.into());
+            upload.insert("main".into(), format!("./{}", main).into());
+            if !rules.is_empty() {
+                upload.insert("rules".into(), rules.into());
+            }
+            let mut build = toml::value::Table::new();
+            build.insert("upload".into(), upload.into());
+            config.insert("build".into(), build.into());
+        }
+    }
+
+    let mut vars = toml::value::Table::new();
+    let mut kv_namespaces = Vec::new();
+    let mut r2_buckets = Vec::new();
+    let mut durable_objects = Vec::new();
+    let mut services = Vec::new();
+    let mut secrets = Vec::new();
+    for binding in settings.bindings {
+        match binding {
+            DashBinding::KvNamespace { name, namespace_id } => {
+                let mut namespace = toml::value::Table::new();
+                namespace.insert("binding".into(), name.into());
+                namespace.insert("id".into(), namespace_id.into());
+                kv_namespaces.push(toml::Value::from(namespace));
+            }
+            DashBinding::R2Bucket { name, bucket_name } => {
+                let mut bucket = toml::value::Table::new();
+                bucket.insert("binding".into(), name.into());
+                bucket.insert("bucket_name".into(), bucket_name.into());
+                r2_buckets.push(toml::Value::from(bucket));
+            }
+            DashBinding::DurableObjectNamespace {
+                name,
+                class_name,
+                script_name,
+            } => {
+                let mut class = toml::value::Table::new();
+                class.insert("binding".into(), name.into());
+                class.insert("class_name".into(), class_name.into());
+                if let Some(script_name) = script_name {
+                    class.insert("script_name".into(), script_name.into());
+                }
+                durable_objects.push(toml::Value::from(class));
+            }
+            DashBinding::Service {
+                name,
+                service,
+                environment,
+            } => {
+                let mut binding = toml::value::Table::new();
+                binding.insert("binding".into(), name.into());
+                binding.insert("service".into(), service.into());
+                if let Some(environment) = environment {
+                    binding.insert("environment".into(), environment.into());
+                }
+                services.push(toml::Value::from(binding));
+            }
+            DashBinding::PlainText { name, text } => {
+                vars.insert(name, text.into());
+            }
+            DashBinding::Json { name, json } => {
+                vars.insert(name, toml::Value::try_from(json)?);
+            }
+            DashBinding::SecretText { name } => secrets.push(name),
+            DashBinding::WasmModule { .. } | DashBinding::TextBlob { .. } => {}
+            DashBinding::Unsupported => {
+                StdOut::warn("The Worker has a binding that wrangler.toml cannot describe; add it in the dashboard after publishing.");
+            }
+        }
+    }
+    if !vars.is_empty() {
+        config.insert("vars".into(), vars.into());
+    }
+    if !kv_namespaces.is_empty() {
+        config.insert("kv_namespaces".into(), kv_namespaces.into());
+    }
+    if !r2_buckets.is_empty() {
+        config.insert("r2_buckets".into(), r2_buckets.into());
+    }
+    if !services.is_empty() {
+        config.insert("services".into(), services.into());
+    }
+    if !durable_objects.is_empty() {
+        let mut classes = toml::value::Table::new();
+        classes.insert("classes".into(), durable_objects.into());
+        config.insert("durable_objects".into(), classes.into());
+    }
+
+    for (path, _) in &files {
+        anyhow::ensure!(
+            force || !path.exists(),
+            "{} already exists! Please remove it, or pass --force to replace it.",
+            path.display()
+        );
+    }
+    for (path, body) in files {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, body)?;
+    }
+    // toml-rs writes the values of a `Value` before its tables, as TOML requires
+    fs::write(
+        "wrangler.toml",
+        toml::to_string_pretty(&toml::Value::Table(config))?,
+    )?;
+    write_gitignore(Path::new("."), &gitignore_patterns())?;
+
+    StdOut::success(&format!(
+        "Successfully created a project for {} in {}",
+        name, SOURCE_DIR
+    ));
+    if !secrets.is_empty() {
+        StdOut::info(&format!(
+            "The values of secrets cannot be downloaded; set {} with `wrangler secret put` if you publish to another Worker.",
+            secrets.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+fn script_url(account_id: &str, name: &str) -> String {
+    format!(
+        "https://api.cloudflare.com/client/v4/accounts/{}/workers/scripts/{}",
+        account_id, name
+    )
+}
+
+fn get_json<T: serde::de::DeserializeOwned>(user: &GlobalUser, url: &str) -> Result<T> {
+    let res = http::legacy_auth_client(user).get(url).send()?;
+    let status = res.status();
+    let text = res.text()?;
+    if !status.is_success() {
+        anyhow::bail!(crate::format_api_errors(text))
+    }
+    let response: V4Response<T> = serde_json::from_str(&text)?;
+    Ok(response.result)
+}
+
+fn download_script(user: &GlobalUser, account_id: &str, name: &str) -> Result<Script> {
+    let res = http::legacy_auth_client(user)
+        .get(&script_url(account_id, name))
+        .send()?;
+    let status = res.status();
+    if !status.is_success() {
+        anyhow::bail!(crate::format_api_errors(res.text()?))
+    }
+    let header = |name: &str| {
+        res.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let content_type = header("content-type").unwrap_or_default();
+    let entrypoint = header("cf-entrypoint");
+    let body = res.bytes()?.to_vec();
+
+    let boundary = match content_type
+        .split(';')
+        .filter_map(|param| param.trim().strip_prefix("boundary="))
+        .next()
+    {
+        Some(boundary) if content_type.starts_with("multipart/form-data") => {
+            boundary.trim_matches('"').to_string()
+        }
+        _ => {
+            return Ok(Script::ServiceWorker {
+                script: body,
+                parts: Vec::new(),
+            })
+        }
+    };
+    let mut parts = parse_multipart(&body, &boundary)?;
+    let is_modules = parts
+        .iter()
+        .any(|part| part.content_type.as_deref() == Some(ModuleType::ESModule.content_type()));
+    if is_modules {
+        let main = match entrypoint {
+            Some(main) => main,
+            None => parts
+                .first()
+                .map(|part| part.name.clone())
+                .ok_or_else(|| anyhow!("The script of {} has no modules", name))?,
+        };
+        Ok(Script::Modules {
+            main,
+            modules: parts,
+        })
+    } else {
+        let main = parts
+            .iter()
+            .position(|part| {
+                part.content_type.as_deref() == Some(ModuleType::CommonJS.content_type())
+            })
+            .ok_or_else(|| anyhow!("The script of {} has no JavaScript", name))?;
+        let script = parts.remove(main).body;
+        Ok(Script::ServiceWorker { script, parts })
+    }
+}
+
+fn fetch_settings(user: &GlobalUser, account_id: &str, name: &str) -> Result<ScriptSettings> {
+    get_json(user, &format!("{}/settings", script_url(account_id, name)))
+}
+
+fn fetch_schedules(user: &GlobalUser, account_id: &str, name: &str) -> Result<Vec<String>> {
+    let schedules: Schedules =
+        get_json(user, &format!("{}/schedules", script_url(account_id, name)))?;
+    Ok(schedules
+        .schedules
+        .into_iter()
+        .map(|schedule| schedule.cron)
+        .collect())
+}
+
+fn fetch_workers_dev(user: &GlobalUser, account_id: &str, name: &str) -> Result<bool> {
+    let subdomain: Subdomain =
+        get_json(user, &format!("{}/subdomain", script_url(account_id, name)))?;
+    Ok(subdomain.enabled)
+}
+
+/// The patterns of the routes of the Worker in the zones of the account. Routes are left out,
+/// with a warning, when the zones cannot be listed.
+fn fetch_routes(user: &GlobalUser, account_id: &str, name: &str) -> Vec<String> {
+    let zones = match fetch_zones_cached(user) {
+        Ok(zones) => zones,
+        Err(e) => {
+            StdOut::warn(&format!("Could not find the routes of {}: {}", name, e));
+            return Vec::new();
+        }
+    };
+    let client = match http::cf_v4_client(user) {
+        Ok(client) => client,
+        Err(e) => {
+            StdOut::warn(&format!("Could not find the routes of {}: {}", name, e));
+            return Vec::new();
+        }
+    };
+    let mut patterns = Vec::new();
+    for zone in zones {
+        if zone
+            .account_id
+            .as_deref()
+            .map_or(false, |id| id != account_id)
+        {
+            continue;
+        }
+        match client.request(&ListRoutes {
+            zone_identifier: &zone.id,
+        }) {
+            Ok(success) => patterns.extend(
+                success
+                    .result
+                    .into_iter()
+                    .filter(|route| route.script.as_deref() == Some(name))
+                    .map(|route| route.pattern),
+            ),
+            Err(e) => StdOut::warn(&format!(
+                "Could not list the routes of {}: {}",
+                zone.name,
+                http::format_error(e, None)
+            )),
+        }
+    }
+    patterns
+}
+
+/// Parses a `multipart/form-data` body into its parts.
+fn parse_multipart(body: &[u8], boundary: &str) -> Result<Vec<Part>> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let mut parts = Vec::new();
+    let mut rest = match find(body, &delimiter) {
+        Some(start) => &body[start + delimiter.len()..],
+        None => anyhow::bail!("The script has no parts"),
+    };
+    loop {
+        if rest.starts_with(b"--") {
+            return Ok(parts);
+        }
+        let rest_of_line = find(rest, b"\r\n").ok_or_else(|| anyhow!("Malformed multipart"))?;
+        rest = &rest[rest_of_line + 2..];
+        let end = find(rest, &delimiter).ok_or_else(|| anyhow!("Malformed multipart"))?;
+        let part = &rest[..end];
+        let part = part.strip_suffix(b"\r\n").unwrap_or(part);
+        let headers_end =
+            find(part, b"\r\n\r\n").ok_or_else(|| anyhow!("Malformed multipart part"))?;
+        let headers = String::from_utf8_lossy(&part[..headers_end]);
+        let mut name = None;
+        let mut content_type = None;
+        for header in headers.split("\r\n") {
+            let (key, value) = match header.split_once(':') {
+                Some((key, value)) => (key.trim().to_ascii_lowercase(), value.trim()),
+                None => continue,
+            };
+            if key == "content-disposition" {
+                name = value
+                    .split(';')
+                    .filter_map(|param| param.trim().strip_prefix("name="))
+                    .map(|value| value.trim_matches('"').to_string())
+                    .next();
+            } else if key == "content-type" {
+                content_type = Some(value.split(';').next().unwrap_or(value).trim().to_string());
+            }
+        }
+        parts.push(Part {
+            name: name.ok_or_else(|| anyhow!("A part of the script has no name"))?,
+            content_type,
+            body: part[headers_end + 4..].to_vec(),
+        });
+        rest = &rest[end + delimiter.len()..];
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// The path of a module under `src/`, which must not leave it.
+fn module_path(name: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(name.trim_start_matches("./"));
+    anyhow::ensure!(
+        path.components()
+            .all(|component| matches!(component, Component::Normal(_))),
+        "The module {} is not a relative path",
+        name
+    );
+    Ok(path)
+}
+
+/// Whether the upload rules that wrangler uses by default give the module its type.
+fn has_default_glob(name: &str, module_type: ModuleType) -> bool {
+    module_type
+        .default_globs()
+        .iter()
+        .any(|glob| name.ends_with(glob.trim_start_matches("**/*")))
+}
+
+fn path_value(path: &Path) -> toml::Value {
+    path.to_string_lossy().replace('\\', "/").into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_downloaded_scripts() {
+        let body = b"--xyz\r\nContent-Disposition: form-data; name=\"index.mjs\"; filename=\"index.mjs\"\r\nContent-Type: application/javascript+module\r\n\r\nexport default {};\r\n--xyz\r\nContent-Disposition: form-data; name=\"lib/add.wasm\"\r\nContent-Type: application/wasm\r\n\r\n\x00asm\r\n--xyz--\r\n";
+        let parts = parse_multipart(body, "xyz").unwrap();
+        assert_eq!(
+            parts,
+            vec![
+                Part {
+                    name: "index.mjs".to_string(),
+                    content_type: Some("application/javascript+module".to_string()),
+                    body: b"export default {};".to_vec(),
+                },
+                Part {
+                    name: "lib/add.wasm".to_string(),
+                    content_type: Some("application/wasm".to_string()),
+                    body: b"\x00asm".to_vec(),
+                },
+            ]
+        );
+
+        assert!(has_default_glob("index.mjs", ModuleType::ESModule));
+        assert!(!has_default_glob("index.js", ModuleType::ESModule));
+        assert!(!has_default_glob("lib/add.wasm", ModuleType::CompiledWasm));
+        assert!(module_path("../secrets.js").is_err());
+    }
+}
@@ -0,0 +1,106 @@
+mod detect;
+mod from_dash;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::commands::generate::{gitignore_patterns, write_gitignore};
+use crate::commands::validate_worker_name;
+use crate::settings::toml::{Manifest, Site, TargetType};
+use crate::terminal::interactive;
+use crate::terminal::message::{Message, StdOut};
+
+pub use from_dash::init_from_dash;
+
+/// Creates a `wrangler.toml` in the current directory. An existing one is replaced when `force`
+/// is set, or when the user agrees to it.
+pub fn init(
+    name: Option<&str>,
+    target_type: Option<TargetType>,
+    site_flag: bool,
+    force: bool,
+) -> Result<()> {
+    let config_file = Path::new("./wrangler.toml");
+    if config_file.exists() {
+        if !force && !confirm_replace()? {
+            if site_flag {
+                let msg = r#"A wrangler.toml file already exists!
+
+To add Workers Sites to your existing wrangler.toml, please add this section:
+
+[site]
+bucket = "" # this should point to the directory with static assets
+entry-point = "workers-site"
+
+Or pass --force to replace it.
+"#;
+                anyhow::bail!(msg);
+            } else {
+                anyhow::bail!("A wrangler.toml file already exists! Please remove it, or pass --force to replace it.");
+            }
+        }
+        // Manifest::generate would otherwise keep what the file has
+        fs::remove_file(config_file)?;
+    }
+    let dirname = get_current_dirname()?;
+    let name = name.unwrap_or(&dirname);
+    validate_worker_name(name)?;
+
+    let config_path = PathBuf::from("./");
+    // the type of a Workers Sites project is decided by the caller
+    let detected = match target_type {
+        None if !site_flag => detect::detect(&config_path)?,
+        _ => None,
+    };
+    let target_type = match &detected {
+        Some(detected) => {
+            StdOut::info(&format!(
+                "Detected {}, which is built as a {} project",
+                detected.description, detected.target_type
+            ));
+            detected.target_type.clone()
+        }
+        None => target_type.unwrap_or_default(),
+    };
+
+    if site_flag {
+        let site = Site::default();
+        Manifest::generate(
+            name.to_string(),
+            Some(target_type),
+            &config_path,
+            Some(site.clone()),
+        )?;
+
+        site.scaffold_worker()?;
+        StdOut::success("Successfully scaffolded workers site");
+    } else {
+        Manifest::generate(name.to_string(), Some(target_type), &config_path, None)?;
+    }
+    if let Some(detected) = detected {
+        detected.apply(config_file)?;
+    }
+    write_gitignore(&config_path, &gitignore_patterns())?;
+
+    StdOut::success("Succesfully created a `wrangler.toml`");
+    Ok(())
+}
+
+fn confirm_replace() -> Result<bool> {
+    if !interactive::is_interactive() {
+        return Ok(false);
+    }
+    interactive::confirm("A wrangler.toml file already exists! Do you want to replace it?")
+}
+
+fn get_current_dirname() -> Result<String> {
+    let current_path = std::env::current_dir()?;
+    let parent = current_path.parent();
+    let dirname = match parent {
+        Some(parent) => current_path.strip_prefix(parent)?.display().to_string(),
+        None => "worker".to_string(),
+    };
+    Ok(dirname)
+}
@@ -0,0 +1,109 @@
+// The values are fetched with http::legacy_auth_client since they are not JSON, like in
+// `kv:key get`.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use cloudflare::endpoints::workerskv::write_bulk::KeyValuePair;
+use cloudflare::framework::response::ApiFailure;
+use indicatif::{ProgressBar, ProgressStyle};
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+
+use crate::commands::kv;
+use crate::http;
+use crate::kv::bulk::PairWithMetadata;
+use crate::kv::encryption::Identities;
+use crate::kv::key::{list_with_metadata, KeyWithMetadata};
+use crate::settings::global_user::GlobalUser;
+use crate::settings::toml::Target;
+use crate::terminal::message::{Message, StdErr};
+
+/// Writes all the key-value pairs of a namespace to a JSON file that `kv:bulk put` uploads, with
+/// their metadata and expiration. Values that are not UTF-8 are written in base64, and encrypted
+/// values are decrypted with the identities of the file `decrypt`.
+pub fn run(
+    target: &Target,
+    user: &GlobalUser,
+    namespace_id: &str,
+    filename: &Path,
+    decrypt: Option<&Path>,
+) -> Result<()> {
+    let identities = decrypt.map(Identities::from_file).transpose()?;
+    let client = http::legacy_auth_client(user);
+    let namespace_endpoint = format!(
+        "https://api.cloudflare.com/client/v4/accounts/{}/storage/kv/namespaces/{}",
+        target.account_id.load()?,
+        namespace_id
+    );
+
+    StdErr::working(&format!("exporting the keys of {}", namespace_id));
+    let style = ProgressStyle::default_spinner().template("{spinner}   {msg}");
+    let spinner = ProgressBar::new_spinner().with_style(style);
+
+    let mut pairs = Vec::new();
+    for key in list_with_metadata(&client, target, namespace_id)? {
+        spinner.set_message(&format!("{} key value pairs, {}", pairs.len(), key.name));
+        // a key that expired after it was listed has no value
+        if let Some(mut pair) = get_pair(&client, &namespace_endpoint, key)? {
+            if let Some(identities) = &identities {
+                pair = identities.decrypt_pair(pair)?;
+            }
+            pairs.push(pair);
+        }
+        spinner.tick();
+    }
+    spinner.finish_and_clear();
+
+    fs::write(filename, serde_json::to_string_pretty(&pairs)?)?;
+    StdErr::success(&format!(
+        "exported {} key value pairs to {}",
+        pairs.len(),
+        filename.display()
+    ));
+    Ok(())
+}
+
+/// Fetches the value of a listed key, or `None` if the key doesn't exist anymore.
+pub(crate) fn get_pair(
+    client: &Client,
+    namespace_endpoint: &str,
+    key: KeyWithMetadata,
+) -> Result<Option<PairWithMetadata>> {
+    let res = client
+        .get(&format!(
+            "{}/values/{}",
+            namespace_endpoint,
+            kv::url_encode_key(&key.name)
+        ))
+        .send()?;
+    let status = res.status();
+    if status == StatusCode::NOT_FOUND {
+        log::info!("{} was deleted while it was exported", key.name);
+        return Ok(None);
+    }
+    if !status.is_success() {
+        let errors = res.json().unwrap_or_default();
+        anyhow::bail!(
+            "Failed to get the value of {}: {}",
+            key.name,
+            kv::format_error(ApiFailure::Error(status, errors))
+        );
+    }
+
+    let (value, base64) = match String::from_utf8(res.bytes()?.to_vec()) {
+        Ok(value) => (value, None),
+        Err(e) => (base64::encode(e.as_bytes()), Some(true)),
+    };
+    Ok(Some(PairWithMetadata {
+        pair: KeyValuePair {
+            key: key.name,
+            value,
+            expiration: key.expiration,
+            expiration_ttl: None,
+            base64,
+        },
+        metadata: key.metadata,
+    }))
+}
@@ -1,5 +1,12 @@
 pub mod delete;
+pub mod export;
 pub mod put;
+pub mod sync;
+pub mod transform;
+pub mod validate;
 
 pub use delete::run as delete;
+pub use export::run as export;
 pub use put::run as put;
+pub use sync::run as sync;
+pub use validate::run as validate;
@@ -1,57 +1,182 @@
-extern crate base64;
-
-use std::fs;
-use std::fs::metadata;
+use std::fmt;
+use std::fs::{metadata, File};
+use std::io::BufReader;
 use std::path::Path;
 
-use cloudflare::endpoints::workerskv::write_bulk::KeyValuePair;
-
 use anyhow::{anyhow, Result};
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+
+use super::transform::{self, Transform};
 
-use crate::kv::bulk::put;
-use crate::kv::bulk::BATCH_KEY_MAX;
+use crate::kv::bulk::{PairWithMetadata, StreamingPut};
+use crate::kv::encryption::{self, Recipient};
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
 use crate::terminal::message::{Message, StdErr};
-pub fn run(target: &Target, user: &GlobalUser, namespace_id: &str, filename: &Path) -> Result<()> {
-    let pairs: Vec<KeyValuePair> = match &metadata(filename) {
-        Ok(file_type) if file_type.is_file() => {
-            let data = fs::read_to_string(filename)?;
-            let data_vec = serde_json::from_str(&data);
-            match data_vec {
-                Ok(data_vec) => Ok(data_vec),
-                Err(_) => {
-                    // Hide '{' in this error message from the formatting machinery in anyhow macro
-                    let msg = "Failed to decode JSON. Please make sure to follow the format, [{\"key\": \"test_key\", \"value\": \"test_value\"}, ...]";
-                    Err(anyhow!(msg))
-                }
+
+pub(crate) const METADATA_MAX_SIZE: usize = 1024;
+
+// The file is read twice rather than kept in memory, since it may be larger than the memory of a
+// CI machine: once to check all of it before anything is uploaded, and once to upload it. The
+// pairs are transformed in both passes, so that a value a transform fails on is found first, and
+// only encrypted to the recipients when they are uploaded.
+pub fn run(
+    target: &Target,
+    user: &GlobalUser,
+    namespace_id: &str,
+    filename: &Path,
+    transforms: &[Transform],
+    recipients: &[Recipient],
+    concurrency: usize,
+) -> Result<()> {
+    match &metadata(filename) {
+        Ok(file_type) if file_type.is_file() => (),
+        Ok(_) => anyhow::bail!("{} should be a JSON file, but is not", filename.display()),
+        Err(e) => anyhow::bail!("{}", e),
+    }
+
+    let mut len = 0;
+    let mut with_metadata = false;
+    for_each_pair(filename, |pair| {
+        let pair = transform::apply(transforms, pair)?;
+        if let Some(metadata) = &pair.metadata {
+            let size = metadata.to_string().len();
+            if size > METADATA_MAX_SIZE {
+                anyhow::bail!(
+                    "The metadata of key `{}` is {} bytes, more than the limit of {} bytes",
+                    pair.pair.key,
+                    size,
+                    METADATA_MAX_SIZE
+                );
             }
+            with_metadata = true;
         }
-        Ok(_) => Err(anyhow!(
-            "{} should be a JSON file, but is not",
-            filename.display()
-        )),
-        Err(e) => Err(anyhow!("{}", e)),
-    }?;
-
-    let len = pairs.len();
-
-    StdErr::working(&format!("uploading {} key value pairs", len));
-    let progress_bar = if len > BATCH_KEY_MAX {
-        let pb = ProgressBar::new(len as u64);
-        pb.set_style(ProgressStyle::default_bar().template("{wide_bar} {pos}/{len}\n{msg}"));
-        Some(pb)
-    } else {
-        None
-    };
-
-    put(target, user, namespace_id, pairs, &progress_bar)?;
-
-    if let Some(pb) = &progress_bar {
-        pb.finish_with_message(&format!("uploaded {} key value pairs", len));
-    }
+        len += 1;
+        Ok(())
+    })?;
+
+    StdErr::working(&format!(
+        "uploading {} key value pairs, {} batches at a time",
+        len, concurrency
+    ));
+    let progress_bar = ProgressBar::new(len as u64);
+    progress_bar.set_style(
+        ProgressStyle::default_bar()
+            .template("{wide_bar} {pos}/{len} pairs, {per_sec}, {eta} left\n{msg}"),
+    );
+
+    let mut upload = StreamingPut::new(
+        target,
+        user,
+        namespace_id,
+        with_metadata,
+        Some(progress_bar.clone()),
+    )?
+    .concurrency(concurrency);
+    for_each_pair(filename, |pair| {
+        let mut pair = transform::apply(transforms, pair)?;
+        if !recipients.is_empty() {
+            pair = encryption::encrypt_pair(recipients, pair)?;
+        }
+        upload.push(pair)
+    })?;
+    upload.finish()?;
+
+    progress_bar.finish_with_message(&format!("uploaded {} key value pairs", len));
 
     StdErr::success("Success");
     Ok(())
 }
+
+/// Calls `f` with each pair of the JSON array in `filename` as it is parsed, stopping at the first
+/// error.
+fn for_each_pair<F>(filename: &Path, f: F) -> Result<()>
+where
+    F: FnMut(PairWithMetadata) -> Result<()>,
+{
+    let reader = BufReader::new(File::open(filename)?);
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let mut visitor = PairVisitor { f, error: None };
+    let parsed = (&mut deserializer)
+        .deserialize_seq(&mut visitor)
+        .and_then(|()| deserializer.end());
+    // an error of `f` is only passed through the parser as a message
+    if let Some(e) = visitor.error {
+        return Err(e);
+    }
+    parsed.map_err(|e| {
+        // Hide '{' in this error message from the formatting machinery in anyhow macro
+        let msg = "Failed to decode JSON. Please make sure to follow the format, [{\"key\": \"test_key\", \"value\": \"test_value\"}, ...]";
+        anyhow!("{}\n{}", msg, e)
+    })
+}
+
+struct PairVisitor<F> {
+    f: F,
+    error: Option<anyhow::Error>,
+}
+
+impl<'de, 'a, F> Visitor<'de> for &'a mut PairVisitor<F>
+where
+    F: FnMut(PairWithMetadata) -> Result<()>,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of key-value pairs")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<(), A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        while let Some(pair) = seq.next_element()? {
+            if let Err(e) = (self.f)(pair) {
+                self.error = Some(e);
+                return Err(de::Error::custom("stopped reading the pairs"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn it_reads_pairs_one_by_one() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"[{{"key": "a", "value": "1"}}, {{"key": "b", "value": "2", "metadata": {{"n": 2}}}}]"#
+        )
+        .unwrap();
+
+        let mut keys = Vec::new();
+        for_each_pair(file.path(), |pair| {
+            keys.push(pair.pair.key);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(keys, vec!["a", "b"]);
+
+        let mut count = 0;
+        let result = for_each_pair(file.path(), |_| {
+            count += 1;
+            anyhow::bail!("upload failed")
+        });
+        assert_eq!(result.unwrap_err().to_string(), "upload failed");
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn it_fails_on_malformed_json() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, r#"[{{"key": "a", "value": "1"}},"#).unwrap();
+
+        assert!(for_each_pair(file.path(), |_| Ok(())).is_err());
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use cloudflare::endpoints::workerskv::write_bulk::KeyValuePair;
+use ignore::WalkBuilder;
+use indicatif::{ProgressBar, ProgressStyle};
+use sha2::{Digest, Sha256};
+
+use crate::http;
+use crate::kv::bulk::{delete, PairWithMetadata, StreamingPut};
+use crate::kv::key::list_with_metadata;
+use crate::settings::global_user::GlobalUser;
+use crate::settings::toml::Target;
+use crate::sites::VALUE_MAX_SIZE;
+use crate::terminal::interactive;
+use crate::terminal::message::{Message, StdErr};
+
+/// The key of the metadata of a synced value that has the SHA-256 hash of the file.
+const HASH_KEY: &str = "sha256";
+
+/// Mirrors the files of `directory` into a namespace, keyed by their path relative to it with `/`
+/// separators, like Sites does for the assets of a site. A file is only uploaded if the hash in
+/// the metadata of its key differs, and the keys of files that don't exist anymore are deleted.
+pub fn run(
+    target: &Target,
+    user: &GlobalUser,
+    namespace_id: &str,
+    directory: &Path,
+    force: bool,
+) -> Result<()> {
+    anyhow::ensure!(
+        directory.is_dir(),
+        "{} should be a directory, but is not",
+        directory.display()
+    );
+
+    StdErr::working(&format!(
+        "comparing {} with the namespace {}",
+        directory.display(),
+        namespace_id
+    ));
+    let remote_hashes: HashMap<String, Option<String>> =
+        list_with_metadata(&http::legacy_auth_client(user), target, namespace_id)?
+            .into_iter()
+            .map(|key| {
+                let hash = key
+                    .metadata
+                    .as_ref()
+                    .and_then(|metadata| metadata[HASH_KEY].as_str())
+                    .map(str::to_string);
+                (key.name, hash)
+            })
+            .collect();
+
+    let mut to_upload = Vec::new();
+    let mut local_keys = HashSet::new();
+    for (path, key) in files(directory)? {
+        let value = fs::read(&path)?;
+        anyhow::ensure!(
+            value.len() as u64 <= VALUE_MAX_SIZE,
+            "{} is {} bytes, more than the limit of {} bytes of a value",
+            path.display(),
+            value.len(),
+            VALUE_MAX_SIZE
+        );
+        let hash = format!("{:x}", Sha256::digest(&value));
+        if remote_hashes.get(&key) != Some(&Some(hash)) {
+            to_upload.push((path, key.clone()));
+        }
+        local_keys.insert(key);
+    }
+    let mut to_delete: Vec<String> = remote_hashes
+        .keys()
+        .filter(|key| !local_keys.contains(*key))
+        .cloned()
+        .collect();
+    to_delete.sort();
+
+    StdErr::info(&format!(
+        "{} files to upload, {} unchanged, {} keys to delete",
+        to_upload.len(),
+        local_keys.len() - to_upload.len(),
+        to_delete.len()
+    ));
+
+    if !to_upload.is_empty() {
+        let progress_bar = ProgressBar::new(to_upload.len() as u64);
+        progress_bar
+            .set_style(ProgressStyle::default_bar().template("{wide_bar} {pos}/{len}\n{msg}"));
+        let mut upload =
+            StreamingPut::new(target, user, namespace_id, true, Some(progress_bar.clone()))?;
+        // the files are read again, so that they aren't all in memory at once
+        for (path, key) in to_upload {
+            upload.push(pair(&path, key)?)?;
+        }
+        upload.finish()?;
+        progress_bar.finish_and_clear();
+    }
+
+    if !to_delete.is_empty() {
+        if !force
+            && !interactive::confirm(&format!(
+                "Are you sure you want to delete {} keys that are not in {}?",
+                to_delete.len(),
+                directory.display()
+            ))?
+        {
+            StdErr::info("Not deleting keys");
+            return Ok(());
+        }
+        delete(target, user, namespace_id, to_delete, &None)?;
+    }
+
+    StdErr::success("Success");
+    Ok(())
+}
+
+/// The files of a directory and their keys. Every file is synced, even if it is hidden or
+/// ignored by git.
+fn files(directory: &Path) -> Result<Vec<(PathBuf, String)>> {
+    let mut files = Vec::new();
+    for entry in WalkBuilder::new(directory).standard_filters(false).build() {
+        let entry = entry?;
+        if !entry.file_type().map_or(false, |t| t.is_file()) {
+            continue;
+        }
+        let path = entry.into_path();
+        let relative = path.strip_prefix(directory)?;
+        let key = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        files.push((path, key));
+    }
+    Ok(files)
+}
+
+fn pair(path: &Path, key: String) -> Result<PairWithMetadata> {
+    let value = fs::read(path)?;
+    let hash = format!("{:x}", Sha256::digest(&value));
+    Ok(PairWithMetadata {
+        pair: KeyValuePair {
+            key,
+            value: base64::encode(&value),
+            expiration: None,
+            expiration_ttl: None,
+            base64: Some(true),
+        },
+        metadata: Some(serde_json::json!({ HASH_KEY: hash })),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_keys_files_by_their_relative_path() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("images/icons")).unwrap();
+        fs::write(dir.path().join("index.json"), "{}").unwrap();
+        fs::write(dir.path().join(".hidden"), "").unwrap();
+        fs::write(dir.path().join("images/icons/a.png"), "").unwrap();
+
+        let mut keys: Vec<String> = files(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|(_, key)| key)
+            .collect();
+        keys.sort();
+        assert_eq!(keys, vec![".hidden", "images/icons/a.png", "index.json"]);
+    }
+}
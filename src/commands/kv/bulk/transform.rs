@@ -0,0 +1,241 @@
+//! The steps of `kv:bulk put --transform`, which rewrite the pairs of a file while it is uploaded
+//! for the common migrations that would otherwise need a script to preprocess the file. Steps are
+//! applied in the order they are given:
+//!
+//! - `prefix:<text>` and `suffix:<text>` add text before or after every key
+//! - `replace:<regex>=<replacement>` replaces the matches of a regex in every key, where the
+//!   replacement may refer to the groups of the regex like `$1`
+//! - `minify` writes values that are JSON without whitespace
+//! - `csv` converts values that are CSV with a header row to JSON arrays of objects
+//!
+//! Values in base64 are binary, so they are left as they are by the steps that rewrite values.
+
+use std::str::FromStr;
+
+use anyhow::Result;
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::kv::bulk::PairWithMetadata;
+
+#[derive(Debug, Clone)]
+pub enum Transform {
+    Prefix(String),
+    Suffix(String),
+    Replace(Regex, String),
+    Minify,
+    Csv,
+}
+
+impl FromStr for Transform {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, argument) = match s.split_once(':') {
+            Some((name, argument)) => (name, Some(argument)),
+            None => (s, None),
+        };
+        match (name, argument) {
+            ("prefix", Some(prefix)) => Ok(Transform::Prefix(prefix.to_string())),
+            ("suffix", Some(suffix)) => Ok(Transform::Suffix(suffix.to_string())),
+            ("replace", Some(argument)) => {
+                let (pattern, replacement) = argument.split_once('=').ok_or_else(|| {
+                    anyhow::anyhow!("`replace` should be in the form replace:<regex>=<replacement>")
+                })?;
+                let regex = Regex::new(pattern)
+                    .map_err(|e| anyhow::anyhow!("`{}` is not a valid regex: {}", pattern, e))?;
+                Ok(Transform::Replace(regex, replacement.to_string()))
+            }
+            ("minify", None) => Ok(Transform::Minify),
+            ("csv", None) => Ok(Transform::Csv),
+            ("prefix", None) | ("suffix", None) | ("replace", None) => {
+                anyhow::bail!("`{}` should be followed by `:` and its argument", name)
+            }
+            ("minify", Some(_)) | ("csv", Some(_)) => {
+                anyhow::bail!("`{}` doesn't take an argument", name)
+            }
+            _ => anyhow::bail!(
+                "Unknown transform `{}`, expected one of prefix, suffix, replace, minify or csv",
+                name
+            ),
+        }
+    }
+}
+
+impl Transform {
+    fn apply(&self, mut pair: PairWithMetadata) -> Result<PairWithMetadata> {
+        let binary = pair.pair.base64.unwrap_or(false);
+        let key = &mut pair.pair.key;
+        let value = &mut pair.pair.value;
+        match self {
+            Transform::Prefix(prefix) => key.insert_str(0, prefix),
+            Transform::Suffix(suffix) => key.push_str(suffix),
+            Transform::Replace(regex, replacement) => {
+                *key = regex.replace_all(key, replacement.as_str()).into_owned()
+            }
+            Transform::Minify if !binary => {
+                // values that are not JSON are uploaded as they are
+                if let Ok(json) = serde_json::from_str::<Value>(value) {
+                    *value = json.to_string();
+                }
+            }
+            Transform::Csv if !binary => {
+                let json = csv_to_json(value)
+                    .map_err(|e| anyhow::anyhow!("The value of key `{}` {}", key, e))?;
+                *value = json.to_string();
+            }
+            Transform::Minify | Transform::Csv => (),
+        }
+        Ok(pair)
+    }
+}
+
+/// Applies the steps to a pair in order.
+pub fn apply(transforms: &[Transform], pair: PairWithMetadata) -> Result<PairWithMetadata> {
+    transforms
+        .iter()
+        .try_fold(pair, |pair, transform| transform.apply(pair))
+}
+
+/// Converts CSV with a header row to an array with an object for each row, whose keys are the
+/// names of the columns.
+fn csv_to_json(csv: &str) -> Result<Value> {
+    let mut rows = parse_csv(csv)?.into_iter();
+    let header = match rows.next() {
+        Some(header) => header,
+        None => return Ok(Value::Array(Vec::new())),
+    };
+    let objects = rows
+        .enumerate()
+        .map(|(i, row)| {
+            if row.len() != header.len() {
+                anyhow::bail!(
+                    "has {} columns in row {} of its CSV, but {} in its header",
+                    row.len(),
+                    i + 1,
+                    header.len()
+                );
+            }
+            let object = header
+                .iter()
+                .cloned()
+                .zip(row.into_iter().map(Value::String))
+                .collect::<Map<String, Value>>();
+            Ok(Value::Object(object))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Value::Array(objects))
+}
+
+/// Splits CSV into the fields of its rows, where fields may be quoted with `"` to contain commas,
+/// line breaks and quotes written as `""`.
+fn parse_csv(csv: &str) -> Result<Vec<Vec<String>>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = csv.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            _ if quoted => field.push(c),
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => (),
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        anyhow::bail!("has an unterminated quoted field in its CSV");
+    }
+    // the last row may not end with a line break
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cloudflare::endpoints::workerskv::write_bulk::KeyValuePair;
+    use serde_json::json;
+
+    fn pair(key: &str, value: &str) -> PairWithMetadata {
+        PairWithMetadata {
+            pair: KeyValuePair {
+                key: key.to_string(),
+                value: value.to_string(),
+                expiration: None,
+                expiration_ttl: None,
+                base64: None,
+            },
+            metadata: None,
+        }
+    }
+
+    fn transforms(steps: &[&str]) -> Vec<Transform> {
+        steps.iter().map(|step| step.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn it_rewrites_keys_in_order() {
+        let steps = transforms(&[
+            "replace:^user-(\\d+)$=users/$1",
+            "prefix:v2:",
+            "suffix:.json",
+        ]);
+        let pair = apply(&steps, pair("user-42", "{}")).unwrap();
+        assert_eq!(pair.pair.key, "v2:users/42.json");
+    }
+
+    #[test]
+    fn it_minifies_json_values() {
+        let steps = transforms(&["minify"]);
+        let minified = apply(&steps, pair("a", "{ \"b\": [1, 2] }\n")).unwrap();
+        assert_eq!(minified.pair.value, r#"{"b":[1,2]}"#);
+        let text = apply(&steps, pair("a", "not json ")).unwrap();
+        assert_eq!(text.pair.value, "not json ");
+    }
+
+    #[test]
+    fn it_converts_csv_values_to_json() {
+        let csv = "name,note\r\nada,\"first, \"\"programmer\"\"\"\nalan,\"multi\nline\"";
+        let pair = apply(&transforms(&["csv"]), pair("people", csv)).unwrap();
+        let value: Value = serde_json::from_str(&pair.pair.value).unwrap();
+        assert_eq!(
+            value,
+            json!([
+                { "name": "ada", "note": "first, \"programmer\"" },
+                { "name": "alan", "note": "multi\nline" },
+            ])
+        );
+
+        let error = csv_to_json("a,b\n1\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "has 1 columns in row 1 of its CSV, but 2 in its header"
+        );
+    }
+
+    #[test]
+    fn it_rejects_malformed_transforms() {
+        assert!("prefix".parse::<Transform>().is_err());
+        assert!("minify:all".parse::<Transform>().is_err());
+        assert!("replace:(=x".parse::<Transform>().is_err());
+        assert!("replace:abc".parse::<Transform>().is_err());
+        assert!("upcase".parse::<Transform>().is_err());
+    }
+}
@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use serde_json::Value;
+
+use super::put::METADATA_MAX_SIZE;
+use crate::sites::{KEY_MAX_SIZE, VALUE_MAX_SIZE};
+use crate::terminal::message::{Message, StdOut};
+
+/// The shortest time a key can live, in seconds, whether by its expiration or its TTL.
+const MIN_EXPIRATION_TTL: i64 = 60;
+
+/// A pair of the file that `kv:bulk put` would fail on, at the line its object starts on.
+#[derive(Debug, PartialEq)]
+pub struct Problem {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Checks every pair of a JSON file for `kv:bulk put` against the limits of Workers KV, and prints
+/// all the problems found with their line instead of stopping at the first.
+pub fn run(filename: &Path) -> Result<()> {
+    let reader = BufReader::new(File::open(filename)?);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    let (len, problems) = validate(reader, now)?;
+
+    if problems.is_empty() {
+        StdOut::success(&format!("{} key value pairs are valid", len));
+        return Ok(());
+    }
+    for problem in &problems {
+        println!(
+            "{}:{}: {}",
+            filename.display(),
+            problem.line,
+            problem.message
+        );
+    }
+    anyhow::bail!(
+        "Found {} problems in {} key value pairs",
+        problems.len(),
+        len
+    )
+}
+
+/// Validates the pairs of `reader` one at a time, so that a file larger than memory can be
+/// checked, and returns how many there are with their problems. `now` is the unix time the
+/// expirations must be after.
+fn validate<R: Read>(reader: R, now: i64) -> Result<(usize, Vec<Problem>)> {
+    let mut problems = Vec::new();
+    let mut keys: HashMap<String, usize> = HashMap::new();
+    let mut len = 0;
+
+    for element in Elements::new(reader) {
+        let (line, bytes) = element?;
+        len += 1;
+        let mut problem = |message: String| problems.push(Problem { line, message });
+
+        let pair = match serde_json::from_slice::<Value>(&bytes) {
+            Ok(Value::Object(pair)) => pair,
+            Ok(_) => {
+                problem("the pair is not an object".to_string());
+                continue;
+            }
+            Err(e) => anyhow::bail!("Failed to decode JSON at line {}: {}", line, e),
+        };
+
+        match pair.get("key") {
+            Some(Value::String(key)) => {
+                if key.is_empty() {
+                    problem("the key is empty".to_string());
+                } else if key == "." || key == ".." {
+                    problem(format!("the key cannot be `{}`", key));
+                } else if key.len() > KEY_MAX_SIZE {
+                    problem(format!(
+                        "the key is {} bytes, more than the limit of {} bytes",
+                        key.len(),
+                        KEY_MAX_SIZE
+                    ));
+                }
+                if let Some(first) = keys.insert(key.clone(), line) {
+                    problem(format!(
+                        "the key `{}` was already set at line {}",
+                        key, first
+                    ));
+                }
+            }
+            Some(_) => problem("the key is not a string".to_string()),
+            None => problem("the pair has no key".to_string()),
+        }
+
+        let is_base64 = match pair.get("base64") {
+            None | Some(Value::Null) => false,
+            Some(Value::Bool(is_base64)) => *is_base64,
+            Some(_) => {
+                problem("base64 is not true or false".to_string());
+                false
+            }
+        };
+        match pair.get("value") {
+            Some(Value::String(value)) => {
+                let size = if is_base64 {
+                    match base64::decode(value) {
+                        Ok(decoded) => Some(decoded.len()),
+                        Err(e) => {
+                            problem(format!("the value is not valid base64: {}", e));
+                            None
+                        }
+                    }
+                } else {
+                    Some(value.len())
+                };
+                if let Some(size) = size {
+                    if size as u64 > VALUE_MAX_SIZE {
+                        problem(format!(
+                            "the value is {} bytes, more than the limit of {} bytes",
+                            size, VALUE_MAX_SIZE
+                        ));
+                    }
+                }
+            }
+            Some(_) => problem("the value is not a string".to_string()),
+            None => problem("the pair has no value".to_string()),
+        }
+
+        if let Some(metadata) = pair.get("metadata") {
+            let size = metadata.to_string().len();
+            if size > METADATA_MAX_SIZE {
+                problem(format!(
+                    "the metadata is {} bytes, more than the limit of {} bytes",
+                    size, METADATA_MAX_SIZE
+                ));
+            }
+        }
+
+        match pair.get("expiration") {
+            None | Some(Value::Null) => (),
+            Some(expiration) => match expiration.as_i64() {
+                Some(expiration) if expiration < now + MIN_EXPIRATION_TTL => problem(format!(
+                    "the expiration {} is not at least {} seconds in the future",
+                    expiration, MIN_EXPIRATION_TTL
+                )),
+                Some(_) => (),
+                None => problem("the expiration is not a unix time in seconds".to_string()),
+            },
+        }
+        match pair.get("expiration_ttl") {
+            None | Some(Value::Null) => (),
+            Some(ttl) => match ttl.as_i64() {
+                Some(ttl) if ttl < MIN_EXPIRATION_TTL => problem(format!(
+                    "the expiration_ttl {} is less than {} seconds",
+                    ttl, MIN_EXPIRATION_TTL
+                )),
+                Some(_) => (),
+                None => problem("the expiration_ttl is not a number of seconds".to_string()),
+            },
+        }
+    }
+
+    Ok((len, problems))
+}
+
+/// Splits the top-level JSON array of a reader into the bytes of its elements, each with the line
+/// it starts on, which the parser of serde_json can't tell.
+struct Elements<R: Read> {
+    bytes: std::io::Bytes<R>,
+    line: usize,
+    started: bool,
+    done: bool,
+}
+
+impl<R: Read> Elements<R> {
+    fn new(reader: R) -> Elements<R> {
+        Elements {
+            bytes: reader.bytes(),
+            line: 1,
+            started: false,
+            done: false,
+        }
+    }
+
+    fn next_byte(&mut self) -> Result<Option<u8>> {
+        match self.bytes.next() {
+            Some(byte) => {
+                let byte = byte?;
+                if byte == b'\n' {
+                    self.line += 1;
+                }
+                Ok(Some(byte))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_element(&mut self) -> Result<Option<(usize, Vec<u8>)>> {
+        if !self.started {
+            loop {
+                match self.next_byte()? {
+                    Some(b'[') => break,
+                    Some(byte) if byte.is_ascii_whitespace() => (),
+                    _ => anyhow::bail!(
+                        "Failed to decode JSON at line {}: expected an array of key-value pairs",
+                        self.line
+                    ),
+                }
+            }
+            self.started = true;
+        }
+
+        let mut element = Vec::new();
+        let mut start = self.line;
+        let mut depth = 0;
+        let mut in_string = false;
+        let mut escaped = false;
+        loop {
+            let byte = match self.next_byte()? {
+                Some(byte) => byte,
+                None => anyhow::bail!("Failed to decode JSON: the array is not closed"),
+            };
+            if in_string {
+                if escaped {
+                    escaped = false;
+                } else if byte == b'\\' {
+                    escaped = true;
+                } else if byte == b'"' {
+                    in_string = false;
+                }
+                element.push(byte);
+                continue;
+            }
+            match byte {
+                b',' | b']' if depth == 0 => {
+                    if element.is_empty() {
+                        if byte == b']' {
+                            return Ok(None);
+                        }
+                        anyhow::bail!(
+                            "Failed to decode JSON at line {}: missing a pair",
+                            self.line
+                        );
+                    }
+                    if byte == b']' {
+                        self.done = true;
+                    }
+                    return Ok(Some((start, element)));
+                }
+                byte if byte.is_ascii_whitespace() && element.is_empty() => {
+                    start = self.line;
+                    continue;
+                }
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' => depth -= 1,
+                b'"' => in_string = true,
+                _ => (),
+            }
+            element.push(byte);
+        }
+    }
+}
+
+impl<R: Read> Iterator for Elements<R> {
+    type Item = Result<(usize, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_element() {
+            Ok(Some(element)) => Some(Ok(element)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_600_000_000;
+
+    fn problems(json: &str) -> Vec<(usize, String)> {
+        let (_, problems) = validate(json.as_bytes(), NOW).unwrap();
+        problems
+            .into_iter()
+            .map(|problem| (problem.line, problem.message))
+            .collect()
+    }
+
+    #[test]
+    fn it_accepts_valid_pairs() {
+        let json = r#"[
+  {"key": "a", "value": "1"},
+  {"key": "b", "value": "aGk=", "base64": true, "metadata": {"n": 2}},
+  {"key": "c", "value": "3", "expiration": 1600000100, "expiration_ttl": 60}
+]"#;
+        let (len, problems) = validate(json.as_bytes(), NOW).unwrap();
+        assert_eq!(len, 3);
+        assert!(problems.is_empty());
+        assert_eq!(validate("[]".as_bytes(), NOW).unwrap().0, 0);
+    }
+
+    #[test]
+    fn it_reports_the_line_of_each_problem() {
+        let json = format!(
+            r#"[
+  {{"key": "a", "value": "1"}},
+  {{"key": "a", "value": "{{\"x\": \"]\"}}"}},
+  {{
+    "key": "b",
+    "value": "not base64!",
+    "base64": true
+  }},
+  {{"key": "{}", "value": "1", "base64": "yes"}},
+  {{"key": "c", "value": "1", "metadata": "{}"}},
+  {{"key": "d", "value": "1", "expiration": 1600000000, "expiration_ttl": 10}},
+  {{"value": 1}}
+]"#,
+            "k".repeat(513),
+            "m".repeat(1024)
+        );
+        let problems = problems(&json);
+        let lines: Vec<usize> = problems.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, vec![3, 4, 9, 9, 10, 11, 11, 12, 12]);
+        assert_eq!(problems[0].1, "the key `a` was already set at line 2");
+        assert!(problems[1].1.starts_with("the value is not valid base64"));
+        assert_eq!(problems[3].1, "base64 is not true or false");
+        assert_eq!(problems[8].1, "the value is not a string");
+    }
+
+    #[test]
+    fn it_fails_on_malformed_json() {
+        assert!(validate(r#"{"key": "a"}"#.as_bytes(), NOW).is_err());
+        assert!(validate(r#"[{"key": "a", "value": "1"},"#.as_bytes(), NOW).is_err());
+        assert!(validate(r#"[{"key": "a", "value": }]"#.as_bytes(), NOW).is_err());
+    }
+}
@@ -5,15 +5,33 @@
 
 use cloudflare::framework::response::ApiFailure;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 
 use crate::commands::kv;
+use crate::commands::kv::bulk::export::get_pair;
 use crate::http;
+use crate::kv::encryption::Identities;
+use crate::kv::key::list_page;
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
+use crate::terminal::message::{Message, StdErr};
+use std::fs;
 use std::io::{self, Write};
+use std::path::Path;
 
-pub fn get(target: &Target, user: &GlobalUser, id: &str, key: &str) -> Result<()> {
+/// Writes the value of `key` to `output`, or to stdout, as it is or in base64 if `base64` is set.
+/// An encrypted value is decrypted first with the identities of the file `decrypt`.
+pub fn get(
+    target: &Target,
+    user: &GlobalUser,
+    id: &str,
+    key: &str,
+    output: Option<&Path>,
+    base64: bool,
+    decrypt: Option<&Path>,
+) -> Result<()> {
+    // the identities are read first, so that a wrong path fails before the request
+    let identities = decrypt.map(Identities::from_file).transpose()?;
     let api_endpoint = format!(
         "https://api.cloudflare.com/client/v4/accounts/{}/storage/kv/namespaces/{}/values/{}",
         target.account_id.load()?,
@@ -27,12 +45,31 @@ pub fn get(target: &Target, user: &GlobalUser, id: &str, key: &str) -> Result<()
 
     let response_status = res.status();
     if response_status.is_success() {
-        let body = res.bytes()?;
-        // We don't use message::success because we don't want to include the emoji/formatting
-        // in case someone is piping this to stdin.
-        // This will probably fail for non-UTF8 on Windows, but should at least work for people
-        // getting binary data from KV on Unix-y systems.
-        io::stdout().write_all(&*body)?;
+        let mut body = res.bytes()?.to_vec();
+        if let Some(identities) = &identities {
+            body = identities.decrypt(&body)?;
+        }
+        let value = if base64 {
+            base64::encode(&body).into_bytes()
+        } else {
+            body
+        };
+        match output {
+            Some(output) => {
+                fs::write(output, &value)?;
+                StdErr::success(&format!(
+                    "Wrote the value of {} to {} ({} bytes)",
+                    key,
+                    output.display(),
+                    value.len()
+                ));
+            }
+            // We don't use message::success because we don't want to include the emoji/formatting
+            // in case someone is piping this to stdin.
+            // This will probably fail for non-UTF8 on Windows, but should at least work for people
+            // getting binary data from KV on Unix-y systems.
+            None => io::stdout().write_all(&value)?,
+        }
     } else {
         // This is logic pulled from cloudflare-rs for pretty error formatting right now;
         // it will be redundant when we switch to using cloudflare-rs for all API requests.
@@ -46,3 +83,54 @@ pub fn get(target: &Target, user: &GlobalUser, id: &str, key: &str) -> Result<()
 
     Ok(())
 }
+
+/// Writes the value of `key` as a JSON object with its metadata and expiration, in the format of
+/// the files of `kv:bulk put`, to `output` or to stdout. Values that are not UTF-8 are written in
+/// base64, and all of them are with `base64`.
+pub fn get_with_metadata(
+    target: &Target,
+    user: &GlobalUser,
+    id: &str,
+    key: &str,
+    output: Option<&Path>,
+    base64: bool,
+    decrypt: Option<&Path>,
+) -> Result<()> {
+    let identities = decrypt.map(Identities::from_file).transpose()?;
+    let client = http::legacy_auth_client(user);
+    let namespace_endpoint = format!(
+        "https://api.cloudflare.com/client/v4/accounts/{}/storage/kv/namespaces/{}",
+        target.account_id.load()?,
+        id
+    );
+
+    // the key comes first of the keys it prefixes, so listing one key finds its metadata
+    let (keys, _) = list_page(&client, target, id, Some(key), None, Some(1))?;
+    let listed = keys
+        .into_iter()
+        .find(|listed| listed.name == key)
+        .ok_or_else(|| anyhow!("{} was not found in namespace {}", key, id))?;
+    let mut pair = get_pair(&client, &namespace_endpoint, listed)?
+        .ok_or_else(|| anyhow!("{} was deleted while it was read", key))?;
+    if let Some(identities) = &identities {
+        pair = identities.decrypt_pair(pair)?;
+    }
+    if base64 && !pair.pair.base64.unwrap_or(false) {
+        pair.pair.value = base64::encode(&pair.pair.value);
+        pair.pair.base64 = Some(true);
+    }
+
+    let json = serde_json::to_string_pretty(&pair)?;
+    match output {
+        Some(output) => {
+            fs::write(output, &json)?;
+            StdErr::success(&format!(
+                "Wrote the value and metadata of {} to {}",
+                key,
+                output.display()
+            ));
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use anyhow::{anyhow, Result};
+use cloudflare::framework::response::ApiFailure;
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use serde_json::Value;
+
+use crate::commands::kv;
+use crate::http;
+use crate::kv::encryption::Identities;
+use crate::settings::global_user::GlobalUser;
+use crate::settings::toml::Target;
+
+/// Prints the values of `keys` as a JSON object from the keys to their values, sorted by key, with
+/// `null` for the keys that don't exist. The values are fetched `concurrency` at a time, and
+/// decrypted with the identities of the file `decrypt` if they were encrypted.
+#[allow(clippy::too_many_arguments)]
+pub fn get_many(
+    target: &Target,
+    user: &GlobalUser,
+    id: &str,
+    keys: Vec<String>,
+    keys_file: Option<&Path>,
+    base64: bool,
+    decrypt: Option<&Path>,
+    concurrency: usize,
+) -> Result<()> {
+    let identities = decrypt.map(Identities::from_file).transpose()?;
+    let keys = all_keys(keys, keys_file)?;
+    if keys.is_empty() {
+        anyhow::bail!("No keys to get, pass them as arguments or with --keys-file");
+    }
+    let namespace_endpoint = format!(
+        "https://api.cloudflare.com/client/v4/accounts/{}/storage/kv/namespaces/{}",
+        target.account_id.load()?,
+        id
+    );
+
+    let queue = Arc::new(Mutex::new(keys));
+    let workers: Vec<_> = (0..concurrency.max(1))
+        .map(|_| {
+            let queue = Arc::clone(&queue);
+            let client = http::legacy_auth_client(user);
+            let namespace_endpoint = namespace_endpoint.clone();
+            thread::spawn(move || -> Result<Vec<(String, Option<Vec<u8>>)>> {
+                let mut values = Vec::new();
+                loop {
+                    let key = match queue.lock().unwrap().pop() {
+                        Some(key) => key,
+                        None => return Ok(values),
+                    };
+                    match fetch(&client, &namespace_endpoint, &key) {
+                        Ok(value) => values.push((key, value)),
+                        Err(e) => {
+                            queue.lock().unwrap().clear();
+                            return Err(e);
+                        }
+                    }
+                }
+            })
+        })
+        .collect();
+
+    let mut values = BTreeMap::new();
+    for worker in workers {
+        let fetched = worker
+            .join()
+            .unwrap_or_else(|_| Err(anyhow!("A thread getting values from Workers KV panicked")))?;
+        for (key, value) in fetched {
+            let value = match value {
+                Some(mut value) => {
+                    if let Some(identities) = &identities {
+                        value = identities.decrypt(&value)?;
+                    }
+                    Value::String(encode(&key, value, base64)?)
+                }
+                None => Value::Null,
+            };
+            values.insert(key, value);
+        }
+    }
+
+    println!("{}", serde_json::to_string_pretty(&values)?);
+    Ok(())
+}
+
+/// The keys passed as arguments followed by those of `keys_file`, one per line, without
+/// duplicates.
+fn all_keys(mut keys: Vec<String>, keys_file: Option<&Path>) -> Result<Vec<String>> {
+    if let Some(keys_file) = keys_file {
+        let contents = fs::read_to_string(keys_file)
+            .map_err(|e| anyhow!("Could not read {}: {}", keys_file.display(), e))?;
+        keys.extend(
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(str::to_string),
+        );
+    }
+    keys.sort();
+    keys.dedup();
+    Ok(keys)
+}
+
+/// Fetches the value of a key, or `None` if it doesn't exist.
+fn fetch(client: &Client, namespace_endpoint: &str, key: &str) -> Result<Option<Vec<u8>>> {
+    let res = client
+        .get(&format!(
+            "{}/values/{}",
+            namespace_endpoint,
+            kv::url_encode_key(key)
+        ))
+        .send()?;
+    let status = res.status();
+    if status == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        let errors = res.json().unwrap_or_default();
+        anyhow::bail!(
+            "Failed to get the value of {}: {}",
+            key,
+            kv::format_error(ApiFailure::Error(status, errors))
+        );
+    }
+    Ok(Some(res.bytes()?.to_vec()))
+}
+
+/// The value as a JSON string, which it can only be as it is if it's UTF-8.
+fn encode(key: &str, value: Vec<u8>, base64: bool) -> Result<String> {
+    if base64 {
+        return Ok(base64::encode(&value));
+    }
+    String::from_utf8(value)
+        .map_err(|_| anyhow!("The value of {} is not UTF-8, pass --base64 to get it", key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn it_reads_keys_from_arguments_and_a_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, "user:2\n\n  user:3  \nuser:1\n").unwrap();
+
+        let keys = all_keys(vec!["user:1".to_string()], Some(file.path())).unwrap();
+        assert_eq!(keys, vec!["user:1", "user:2", "user:3"]);
+    }
+
+    #[test]
+    fn it_encodes_values_as_json_strings() {
+        assert_eq!(encode("a", b"hello".to_vec(), false).unwrap(), "hello");
+        assert_eq!(encode("a", b"hello".to_vec(), true).unwrap(), "aGVsbG8=");
+        assert!(encode("a", vec![0xff, 0xfe], false).is_err());
+    }
+}
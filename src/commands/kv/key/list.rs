@@ -1,45 +1,108 @@
 extern crate serde_json;
 
-use crate::commands::kv;
+use crate::commands::kv::ListFormat;
 use crate::http;
-use crate::kv::key::KeyList;
+use crate::kv::key::{list_page, KeyWithMetadata};
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
+use crate::terminal::message::{Message, StdErr};
 
 use anyhow::Result;
+use chrono::{TimeZone, Utc};
+use prettytable::{Cell, Row, Table};
 
-// Note: this function only prints keys in json form, given that
-// the number of entries in each json blob is variable (so csv and tsv
-// representation won't make sense)
+// Note: JSON and plain keys are printed as their pages are listed, while a table needs all of
+// them first.
 pub fn list(
     target: &Target,
     user: &GlobalUser,
     namespace_id: &str,
     prefix: Option<&str>,
+    cursor: Option<String>,
+    limit: Option<usize>,
+    format: ListFormat,
 ) -> Result<()> {
-    let client = http::cf_v4_client(user)?;
-    let key_list = KeyList::new(target, client, namespace_id, prefix)?;
+    let client = http::legacy_auth_client(user);
+    let mut cursor = cursor;
+    let mut remaining = limit;
 
-    print!("["); // Open json list bracket
+    let mut table = Table::new();
+    table.add_row(Row::new(vec![
+        Cell::new("Name"),
+        Cell::new("Expiration"),
+        Cell::new("Metadata"),
+    ]));
+    if format == ListFormat::Json {
+        print!("["); // Open json list bracket
+    }
 
     let mut first_key = true;
+    loop {
+        let (keys, next) = list_page(
+            &client,
+            target,
+            namespace_id,
+            prefix,
+            cursor.as_deref(),
+            remaining,
+        )?;
+        cursor = next;
+        if let Some(remaining) = remaining.as_mut() {
+            *remaining -= keys.len().min(*remaining);
+        }
 
-    for key_result in key_list {
-        match key_result {
-            Ok(key) => {
-                if first_key {
-                    first_key = false;
-                } else {
-                    print!(",");
+        for key in keys {
+            match format {
+                ListFormat::Json => {
+                    if !first_key {
+                        print!(",");
+                    }
+                    print!("{}", serde_json::to_string(&key)?);
+                }
+                ListFormat::Plain => println!("{}", key.name),
+                ListFormat::Table => {
+                    table.add_row(key_row(&key));
                 }
-
-                print!("{}", serde_json::to_string(&key)?);
             }
-            Err(e) => print!("{}", kv::format_error(e)),
+            first_key = false;
+        }
+
+        // a page may have no keys even if more follow
+        if cursor.is_none() || remaining == Some(0) {
+            break;
         }
     }
 
-    print!("]"); // Close json list bracket
+    match format {
+        ListFormat::Json => println!("]"), // Close json list bracket
+        ListFormat::Table => print!("{}", table),
+        ListFormat::Plain => (),
+    }
+
+    // the keys on stdout stay valid JSON
+    if let Some(cursor) = cursor {
+        StdErr::info(&format!(
+            "More keys follow. List them with --cursor {}",
+            cursor
+        ));
+    }
 
     Ok(())
 }
+
+fn key_row(key: &KeyWithMetadata) -> Row {
+    let expiration = key
+        .expiration
+        .map(|expiration| Utc.timestamp(expiration, 0).to_rfc3339())
+        .unwrap_or_default();
+    let metadata = key
+        .metadata
+        .as_ref()
+        .map(ToString::to_string)
+        .unwrap_or_default();
+    Row::new(vec![
+        Cell::new(&key.name),
+        Cell::new(&expiration),
+        Cell::new(&metadata),
+    ])
+}
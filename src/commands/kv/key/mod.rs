@@ -1,9 +1,13 @@
 mod delete;
 mod get;
+mod get_many;
 mod list;
 mod put;
+mod watch;
 
 pub use delete::delete;
-pub use get::get;
+pub use get::{get, get_with_metadata};
+pub use get_many::get_many;
 pub use list::list;
-pub use put::{parse_metadata, put, KVMetaData};
+pub use put::{parse_metadata, put, KVMetaData, STDIN_PATH};
+pub use watch::watch;
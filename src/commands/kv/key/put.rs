@@ -4,6 +4,7 @@
 
 use std::fs;
 use std::fs::metadata;
+use std::io::{self, Read};
 
 use anyhow::Result;
 use cloudflare::framework::response::ApiFailure;
@@ -11,12 +12,16 @@ use url::Url;
 
 use crate::commands::kv;
 use crate::http;
+use crate::kv::encryption::{self, Recipient};
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
 use crate::terminal::message::{Message, StdOut};
 use regex::Regex;
 use reqwest::blocking::multipart;
 
+/// The path of a file value that is read from stdin instead.
+pub const STDIN_PATH: &str = "-";
+
 pub struct KVMetaData {
     pub namespace_id: String,
     pub key: String,
@@ -25,6 +30,7 @@ pub struct KVMetaData {
     pub expiration: Option<String>,
     pub expiration_ttl: Option<String>,
     pub metadata: Option<serde_json::Value>,
+    pub recipients: Vec<Recipient>,
 }
 
 pub fn parse_metadata(arg: Option<&str>) -> Result<Option<serde_json::Value>> {
@@ -108,9 +114,22 @@ fn get_response(
 }
 
 // If is_file is true, overwrite value to be the contents of the given
-// filename in the 'value' arg.
+// filename in the 'value' arg. The value is encrypted if there are recipients.
 fn get_request_body(data: &KVMetaData) -> Result<Vec<u8>> {
-    if data.is_file {
+    let value = read_value(data)?;
+    if data.recipients.is_empty() {
+        Ok(value)
+    } else {
+        encryption::encrypt(&data.recipients, &value)
+    }
+}
+
+fn read_value(data: &KVMetaData) -> Result<Vec<u8>> {
+    if data.is_file && data.value == STDIN_PATH {
+        let mut value = Vec::new();
+        io::stdin().read_to_end(&mut value)?;
+        Ok(value)
+    } else if data.is_file {
         match &metadata(&data.value) {
             Ok(file_type) if file_type.is_file() => Ok(fs::read(&data.value)?),
             Ok(file_type) if file_type.is_dir() => anyhow::bail!(
@@ -0,0 +1,143 @@
+// The value is fetched with http::legacy_auth_client since it is not JSON, like in `kv:key get`.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::prelude::*;
+use cloudflare::framework::response::ApiFailure;
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+
+use crate::commands::kv;
+use crate::commands::migrate::{diff_lines, DiffLine};
+use crate::http;
+use crate::settings::global_user::GlobalUser;
+use crate::settings::toml::Target;
+use crate::terminal::message::{Message, StdErr};
+use crate::terminal::styles;
+
+/// Polls the value of `key` every `interval` and prints how it changed, as a diff of its lines if
+/// it is text. `exec` is run in a shell on every change, with the key in `$WRANGLER_KV_KEY` and
+/// the new value on its stdin.
+pub fn watch(
+    target: &Target,
+    user: &GlobalUser,
+    id: &str,
+    key: &str,
+    interval: Duration,
+    exec: Option<&str>,
+) -> Result<()> {
+    let client = http::legacy_auth_client(user);
+    let api_endpoint = format!(
+        "https://api.cloudflare.com/client/v4/accounts/{}/storage/kv/namespaces/{}/values/{}",
+        target.account_id.load()?,
+        id,
+        kv::url_encode_key(key)
+    );
+
+    StdErr::working(&format!(
+        "Watching {} every {} seconds, press Ctrl-C to stop",
+        key,
+        interval.as_secs()
+    ));
+    let mut value = fetch(&client, &api_endpoint)?;
+    match &value {
+        Some(value) => print_value(value),
+        None => StdErr::info(&format!("{} does not exist yet", key)),
+    }
+
+    loop {
+        thread::sleep(interval);
+        let new_value = fetch(&client, &api_endpoint)?;
+        if new_value == value {
+            continue;
+        }
+
+        let now: DateTime<Local> = Local::now();
+        println!(
+            "[{}] {}",
+            now.format("%Y-%m-%d %H:%M:%S"),
+            styles::highlight(key)
+        );
+        match (&value, &new_value) {
+            (Some(old), Some(new)) => print_change(old, new),
+            (None, Some(new)) => print_value(new),
+            (_, None) => println!("{}", styles::removed("deleted")),
+        }
+        if let Some(exec) = exec {
+            run(exec, key, new_value.as_deref())?;
+        }
+        value = new_value;
+    }
+}
+
+/// Fetches the value of a key, or `None` if it doesn't exist.
+fn fetch(client: &Client, api_endpoint: &str) -> Result<Option<Vec<u8>>> {
+    let res = client.get(api_endpoint).send()?;
+    let status = res.status();
+    if status == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        let errors = res.json().unwrap_or_default();
+        anyhow::bail!("{}", kv::format_error(ApiFailure::Error(status, errors)));
+    }
+    Ok(Some(res.bytes()?.to_vec()))
+}
+
+fn print_value(value: &[u8]) {
+    match std::str::from_utf8(value) {
+        Ok(value) => println!("{}", value),
+        Err(_) => println!("<{} bytes of binary data>", value.len()),
+    }
+}
+
+fn print_change(old: &[u8], new: &[u8]) {
+    match (std::str::from_utf8(old), std::str::from_utf8(new)) {
+        (Ok(old), Ok(new)) => {
+            for line in diff_lines(old, new) {
+                match line {
+                    DiffLine::Removed(line) => {
+                        println!("{}", styles::removed(format!("-{}", line)))
+                    }
+                    DiffLine::Added(line) => println!("{}", styles::added(format!("+{}", line))),
+                    DiffLine::Same(line) => println!(" {}", line),
+                }
+            }
+        }
+        _ => print_value(new),
+    }
+}
+
+/// Runs the command of `--exec` in a shell, and warns if it fails without stopping the watch.
+fn run(exec: &str, key: &str, value: Option<&[u8]>) -> Result<()> {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut c = Command::new("cmd");
+        c.arg("/C");
+        c.arg(exec);
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.arg("-c");
+        c.arg(exec);
+        c
+    };
+    let mut child = command
+        .env("WRANGLER_KV_KEY", key)
+        .stdin(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // the command may exit without reading its stdin
+        if let Err(e) = stdin.write_all(value.unwrap_or_default()) {
+            log::info!("Could not write the value to `{}`: {}", exec, e);
+        }
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        StdErr::warn(&format!("`{}` failed: {}", exec, status));
+    }
+    Ok(())
+}
@@ -1,4 +1,5 @@
 use std::collections::HashSet;
+use std::str::FromStr;
 
 use cloudflare::framework::response::ApiFailure;
 
@@ -12,6 +13,30 @@ pub mod bulk;
 pub mod key;
 pub mod namespace;
 
+/// How `kv:key list` and `kv:namespace list` print what they list.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ListFormat {
+    /// A JSON array of objects, for programs to parse.
+    Json,
+    /// A table, for people to read.
+    Table,
+    /// A name per line, e.g. to pipe to xargs.
+    Plain,
+}
+
+impl FromStr for ListFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(ListFormat::Json),
+            "table" => Ok(ListFormat::Table),
+            "plain" => Ok(ListFormat::Plain),
+            _ => anyhow::bail!("Unknown format `{}`, expected json, table or plain", s),
+        }
+    }
+}
+
 // TODO: callers outside this module should write their own error handling (lookin at you sites)
 pub fn format_error(e: ApiFailure) -> String {
     http::format_error(e, Some(&kv_help))
@@ -87,7 +112,7 @@ fn url_encode_key(key: &str) -> String {
 #[cfg(test)]
 mod tests {
     use crate::commands::kv;
-    use crate::settings::toml::{KvNamespace, Target, TargetType};
+    use crate::settings::toml::{Bundler, KvNamespace, Target, TargetType};
 
     #[test]
     fn it_can_detect_duplicate_bindings() {
@@ -104,11 +129,17 @@ mod tests {
                 },
             ],
             r2_buckets: Vec::new(),
+            services: Vec::new(),
             durable_objects: None,
             migrations: None,
             name: "test-target".to_string(),
             target_type: TargetType::Webpack,
+            main: None,
+            entries: None,
             webpack_config: None,
+            node_compat: false,
+            bundler: Bundler::default(),
+            minify: false,
             site: None,
             vars: None,
             text_blobs: None,
@@ -0,0 +1,60 @@
+// Copies the pairs of a namespace into another one, which may belong to another account. Values
+// are fetched one at a time and uploaded in batches, so they are never all in memory at once.
+
+use anyhow::Result;
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::commands::kv::bulk::export::get_pair;
+use crate::http;
+use crate::kv::bulk::StreamingPut;
+use crate::kv::key::list_with_metadata;
+use crate::settings::global_user::GlobalUser;
+use crate::settings::toml::Target;
+use crate::terminal::message::{Message, StdErr};
+
+pub fn run(
+    target: &Target,
+    user: &GlobalUser,
+    from: &str,
+    to: &str,
+    to_account: Option<&str>,
+) -> Result<()> {
+    let account_id = target.account_id.load()?;
+    let to_account = to_account.unwrap_or(account_id);
+    if from == to && to_account == account_id {
+        anyhow::bail!("The namespace {} can't be copied into itself", from);
+    }
+
+    let client = http::legacy_auth_client(user);
+    let namespace_endpoint = format!(
+        "https://api.cloudflare.com/client/v4/accounts/{}/storage/kv/namespaces/{}",
+        account_id, from
+    );
+
+    let keys = list_with_metadata(&client, target, from)?;
+    StdErr::working(&format!(
+        "copying {} key value pairs from {} to {}",
+        keys.len(),
+        from,
+        to
+    ));
+    let progress_bar = ProgressBar::new(keys.len() as u64);
+    progress_bar.set_style(ProgressStyle::default_bar().template("{wide_bar} {pos}/{len}\n{msg}"));
+
+    // the metadata is always written, so that the metadata of existing keys is replaced too
+    let mut upload =
+        StreamingPut::for_account(user, to_account, to, true, Some(progress_bar.clone()))?;
+    let mut copied = 0;
+    for key in keys {
+        // a key that expired after it was listed has no value
+        if let Some(pair) = get_pair(&client, &namespace_endpoint, key)? {
+            upload.push(pair)?;
+            copied += 1;
+        }
+    }
+    upload.finish()?;
+    progress_bar.finish_and_clear();
+
+    StdErr::success(&format!("copied {} key value pairs", copied));
+    Ok(())
+}
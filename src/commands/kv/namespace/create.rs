@@ -1,11 +1,16 @@
-use anyhow::Result;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use cloudflare::framework::HttpApiClient;
 use regex::Regex;
+use toml_edit::{Array, Document, Value};
 
 use crate::commands::kv;
 use crate::http;
 use crate::kv::namespace::create;
 use crate::settings::global_user::GlobalUser;
-use crate::settings::toml::{ConfigKvNamespace, KvNamespace, Manifest};
+use crate::settings::toml::{ConfigFormat, ConfigKvNamespace, KvNamespace, Manifest};
 use crate::terminal::message::{Message, StdOut};
 pub fn run(
     manifest: &Manifest,
@@ -18,10 +23,7 @@ pub fn run(
     let worker_name = manifest.worker_name(env);
     validate_binding(binding)?;
 
-    let mut title = format!("{}-{}", worker_name, binding);
-    if is_preview {
-        title.push_str("_preview");
-    }
+    let title = namespace_title(&worker_name, binding, is_preview);
     let msg = format!("Creating namespace with title \"{}\"", title);
     StdOut::working(&msg);
 
@@ -51,6 +53,160 @@ pub fn run(
     Ok(())
 }
 
+/// Creates a namespace for a binding and adds it to `kv_namespaces` of the environment in the
+/// configuration file. With `is_preview`, its preview namespace is created as well, and the
+/// production namespace the binding already has is kept.
+pub fn run_and_update_config(
+    manifest: &Manifest,
+    config_path: &Path,
+    is_preview: bool,
+    env: Option<&str>,
+    user: &GlobalUser,
+    binding: &str,
+) -> Result<()> {
+    let account_id = manifest.get_account_id(env)?;
+    let worker_name = manifest.worker_name(env);
+    validate_binding(binding)?;
+    if ConfigFormat::from_path(config_path)? != ConfigFormat::Toml {
+        anyhow::bail!("--update-config can only edit configuration files written in TOML");
+    }
+    let config = fs::read_to_string(config_path)?;
+
+    // environments don't inherit the namespaces of the top level
+    let namespaces = match env {
+        Some(_) => manifest
+            .get_environment(env)?
+            .and_then(|env| env.kv_namespaces.as_ref()),
+        None => manifest.kv_namespaces.as_ref(),
+    };
+    let existing = namespaces.and_then(|namespaces| {
+        namespaces
+            .iter()
+            .find(|namespace| namespace.binding == binding)
+    });
+
+    let client = http::cf_v4_client(user)?;
+    let id = match existing.and_then(|namespace| namespace.id.clone()) {
+        Some(id) if is_preview => id,
+        _ => create_namespace(&client, &account_id, &worker_name, binding, false)?,
+    };
+    let preview_id = if is_preview {
+        Some(create_namespace(
+            &client,
+            &account_id,
+            &worker_name,
+            binding,
+            true,
+        )?)
+    } else {
+        existing.and_then(|namespace| namespace.preview_id.clone())
+    };
+
+    let config = add_namespace(&config, env, binding, &id, preview_id.as_deref())?;
+    fs::write(config_path, config)?;
+    let table = match env {
+        Some(env) => format!("[env.{}]", env),
+        None => "the top level".to_string(),
+    };
+    StdOut::success(&format!(
+        "Added {} to kv_namespaces of {} in {}",
+        binding,
+        table,
+        config_path.display()
+    ));
+    Ok(())
+}
+
+fn namespace_title(worker_name: &str, binding: &str, is_preview: bool) -> String {
+    let mut title = format!("{}-{}", worker_name, binding);
+    if is_preview {
+        title.push_str("_preview");
+    }
+    title
+}
+
+fn create_namespace(
+    client: &HttpApiClient,
+    account_id: &str,
+    worker_name: &str,
+    binding: &str,
+    is_preview: bool,
+) -> Result<String> {
+    let title = namespace_title(worker_name, binding, is_preview);
+    StdOut::working(&format!("Creating namespace with title \"{}\"", title));
+    match create(client, account_id, &title) {
+        Ok(success) => Ok(success.result.id),
+        Err(e) => Err(anyhow!("{}", kv::format_error(e))),
+    }
+}
+
+/// Adds the namespace of a binding to `kv_namespaces` of an environment, or of the top level,
+/// replacing the namespace the binding already had. toml_edit is used so that comments and the
+/// order of the keys are preserved.
+fn add_namespace(
+    config: &str,
+    env: Option<&str>,
+    binding: &str,
+    id: &str,
+    preview_id: Option<&str>,
+) -> Result<String> {
+    let mut doc = config
+        .parse::<Document>()
+        .map_err(|e| anyhow!("Could not parse the configuration: {}", e))?;
+
+    let mut entry = format!("{{ binding = \"{}\", id = \"{}\"", binding, id);
+    if let Some(preview_id) = preview_id {
+        entry.push_str(&format!(", preview_id = \"{}\"", preview_id));
+    }
+    entry.push_str(" }");
+    let entry = entry
+        .parse::<Value>()
+        .map_err(|e| anyhow!("Could not build the namespace of {}: {}", binding, e))?;
+
+    let namespaces = match env {
+        Some(env) => &mut doc["env"][env]["kv_namespaces"],
+        None => &mut doc["kv_namespaces"],
+    };
+    let existing: Vec<Value> = if namespaces.is_none() {
+        Vec::new()
+    } else {
+        namespaces
+            .as_array()
+            .ok_or_else(|| {
+                anyhow!("kv_namespaces can only be updated when it is an array of inline tables")
+            })?
+            .iter()
+            .cloned()
+            .collect()
+    };
+
+    let mut array = Array::default();
+    let mut replaced = false;
+    for namespace in existing {
+        let is_binding = namespace
+            .as_inline_table()
+            .and_then(|table| table.get("binding"))
+            .and_then(Value::as_str)
+            == Some(binding);
+        let namespace = if is_binding {
+            replaced = true;
+            entry.clone()
+        } else {
+            namespace
+        };
+        array
+            .push(namespace)
+            .map_err(|_| anyhow!("kv_namespaces can only hold inline tables"))?;
+    }
+    if !replaced {
+        array
+            .push(entry)
+            .map_err(|_| anyhow!("kv_namespaces can only hold inline tables"))?;
+    }
+    *namespaces = toml_edit::value(array);
+    Ok(doc.to_string_in_original_order())
+}
+
 fn validate_binding(binding: &str) -> Result<()> {
     let re = Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*$").unwrap();
     if !re.is_match(binding) {
@@ -271,6 +427,42 @@ mod tests {
         assert!(!msg.contains("kv_namespaces = ["));
     }
 
+    #[test]
+    fn it_adds_namespaces_to_the_config() {
+        let config = "name = \"worker\" # the name\n\n[env.staging]\nkv_namespaces = [{ binding = \"BAR\", id = \"bar_id\" }]\n";
+
+        // the value of a key of the first namespace of a table
+        fn first(table: &toml_edit::Item, key: &str) -> Option<String> {
+            let namespaces = table["kv_namespaces"].as_array()?;
+            let value = namespaces.get(0)?.as_inline_table()?.get(key)?;
+            value.as_str().map(str::to_string)
+        }
+
+        let config = add_namespace(config, None, "FOO", "foo_id", None).unwrap();
+        assert!(config.contains("# the name"));
+        let doc = config.parse::<Document>().unwrap();
+        assert_eq!(first(&doc.root, "id").as_deref(), Some("foo_id"));
+
+        let config = add_namespace(
+            &config,
+            Some("staging"),
+            "BAR",
+            "new_bar_id",
+            Some("bar_preview_id"),
+        )
+        .unwrap();
+        let doc = config.parse::<Document>().unwrap();
+        let namespaces = doc["env"]["staging"]["kv_namespaces"].as_array().unwrap();
+        assert_eq!(namespaces.len(), 1);
+        let staging = &doc["env"]["staging"];
+        assert_eq!(first(staging, "id").as_deref(), Some("new_bar_id"));
+        assert_eq!(
+            first(staging, "preview_id").as_deref(),
+            Some("bar_preview_id")
+        );
+        assert_eq!(first(&doc.root, "binding").as_deref(), Some("FOO"));
+    }
+
     #[test]
     fn it_can_detect_invalid_binding() {
         let invalid_bindings = vec!["hi there", "1234"];
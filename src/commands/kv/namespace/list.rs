@@ -1,18 +1,98 @@
+use std::str::FromStr;
+
+use crate::commands::kv::ListFormat;
 use crate::http;
 use crate::kv::namespace::list;
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
 
 use anyhow::Result;
+use globset::{GlobBuilder, GlobMatcher};
+use prettytable::{Cell, Row, Table};
+use regex::Regex;
+
+/// A pattern that the titles of listed namespaces must match: a regex between slashes like
+/// `/^my-worker-/`, or a glob like `my-worker-*` that must match the whole title.
+#[derive(Clone, Debug)]
+pub enum TitlePattern {
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+impl TitlePattern {
+    pub fn is_match(&self, title: &str) -> bool {
+        match self {
+            TitlePattern::Glob(glob) => glob.is_match(title),
+            TitlePattern::Regex(regex) => regex.is_match(title),
+        }
+    }
+}
+
+impl FromStr for TitlePattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.len() > 1 && s.starts_with('/') && s.ends_with('/') {
+            let regex = Regex::new(&s[1..s.len() - 1])
+                .map_err(|e| anyhow::anyhow!("`{}` is not a valid regex: {}", s, e))?;
+            return Ok(TitlePattern::Regex(regex));
+        }
+        let glob = GlobBuilder::new(s)
+            .build()
+            .map_err(|e| anyhow::anyhow!("`{}` is not a valid glob: {}", s, e))?;
+        Ok(TitlePattern::Glob(glob.compile_matcher()))
+    }
+}
 
-pub fn run(target: &Target, user: &GlobalUser) -> Result<()> {
+pub fn run(
+    target: &Target,
+    user: &GlobalUser,
+    format: ListFormat,
+    pattern: Option<&TitlePattern>,
+) -> Result<()> {
     let client = http::cf_v4_client(user)?;
-    let result = list(&client, target);
-    match result {
-        Ok(namespaces) => {
-            println!("{}", serde_json::to_string(&namespaces)?);
+    let mut namespaces = list(&client, target)?;
+    if let Some(pattern) = pattern {
+        namespaces.retain(|namespace| pattern.is_match(&namespace.title));
+    }
+    match format {
+        ListFormat::Json => println!("{}", serde_json::to_string(&namespaces)?),
+        ListFormat::Table => {
+            let mut table = Table::new();
+            table.add_row(Row::new(vec![Cell::new("ID"), Cell::new("Title")]));
+            for namespace in &namespaces {
+                table.add_row(Row::new(vec![
+                    Cell::new(&namespace.id),
+                    Cell::new(&namespace.title),
+                ]));
+            }
+            print!("{}", table);
+        }
+        // the IDs are what other commands take
+        ListFormat::Plain => {
+            for namespace in &namespaces {
+                println!("{}", namespace.id);
+            }
         }
-        Err(e) => anyhow::bail!(e),
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_matches_titles_by_glob_or_regex() {
+        let glob: TitlePattern = "my-worker-*".parse().unwrap();
+        assert!(glob.is_match("my-worker-CACHE"));
+        assert!(!glob.is_match("other-my-worker-CACHE"));
+
+        let regex: TitlePattern = "/_preview$/".parse().unwrap();
+        assert!(regex.is_match("my-worker-CACHE_preview"));
+        assert!(!regex.is_match("my-worker-CACHE"));
+
+        assert!("/(/".parse::<TitlePattern>().is_err());
+        assert!("[".parse::<TitlePattern>().is_err());
+    }
+}
@@ -1,7 +1,11 @@
+mod copy;
 mod create;
 mod delete;
 mod list;
 
+pub use copy::run as copy;
 pub use create::run as create;
+pub use create::run_and_update_config as create_and_update_config;
 pub use delete::run as delete;
 pub use list::run as list;
+pub use list::TitlePattern;
@@ -0,0 +1,202 @@
+use anyhow::Result;
+use prettytable::{Cell, Row, Table};
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::commands::whoami::{fetch_accounts_cached, AccountOutput};
+use crate::http;
+use crate::settings::global_user::GlobalUser;
+use crate::terminal::message::{Message, StdOut};
+use crate::terminal::styles;
+use crate::wranglerjs::output::human_size;
+
+const API_BASE: &str = "https://api.cloudflare.com/client/v4";
+
+// The limits of the Workers Free and Paid plans that commands of wrangler run into, from
+// https://developers.cloudflare.com/workers/platform/limits
+const SCRIPTS_FREE: u64 = 30;
+const SCRIPTS_PAID: u64 = 100;
+const SCRIPT_SIZE: u64 = 1024 * 1024;
+const KV_NAMESPACES: u64 = 100;
+const SUBREQUESTS_BUNDLED: u64 = 50;
+const SUBREQUESTS_UNBOUND: u64 = 1000;
+
+/// The limits of an account and how much of them it uses, printed by `whoami --limits`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountLimits {
+    pub id: String,
+    pub name: String,
+    /// The usage model of new workers, which decides the limit of subrequests on the Paid plan.
+    pub default_usage_model: Option<String>,
+    pub limits: Vec<LimitOutput>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LimitOutput {
+    pub name: &'static str,
+    /// How much the account uses, if it can be queried and the credentials are allowed to.
+    pub used: Option<u64>,
+    pub free: u64,
+    pub paid: u64,
+    #[serde(skip)]
+    bytes: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct V4Response<T> {
+    success: bool,
+    result: Option<T>,
+    result_info: Option<ResultInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResultInfo {
+    total_count: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountSettings {
+    default_usage_model: Option<String>,
+}
+
+/// Fetches the limits and usage of every account of the user.
+pub fn fetch_limits(user: &GlobalUser) -> Result<Vec<AccountLimits>> {
+    let client = http::legacy_auth_client(user);
+    fetch_accounts_cached(user)?
+        .into_iter()
+        .map(|account| account_limits(&client, account))
+        .collect()
+}
+
+/// Prints a table of the limits of every account of the user.
+pub fn print_limits(user: &GlobalUser) -> Result<()> {
+    let accounts = fetch_limits(user)?;
+    if accounts.is_empty() {
+        StdOut::info("No accounts were found to report the limits of.");
+        return Ok(());
+    }
+
+    for account in accounts {
+        let mut table = Table::new();
+        table.add_row(Row::new(vec![
+            Cell::new("Limit"),
+            Cell::new("Used"),
+            Cell::new("Free plan"),
+            Cell::new("Paid plan"),
+        ]));
+        for limit in &account.limits {
+            let format = |n: u64| {
+                if limit.bytes {
+                    human_size(n)
+                } else {
+                    n.to_string()
+                }
+            };
+            let used = limit.used.map_or_else(|| "-".to_string(), format);
+            table.add_row(Row::new(vec![
+                Cell::new(limit.name),
+                Cell::new(&used),
+                Cell::new(&format(limit.free)),
+                Cell::new(&format(limit.paid)),
+            ]));
+        }
+
+        println!("\n{} ({})", styles::bold(&account.name), account.id);
+        if let Some(usage_model) = &account.default_usage_model {
+            println!("Default usage model: {}", usage_model);
+        }
+        println!("{}", table);
+    }
+    StdOut::info("Limits are from https://developers.cloudflare.com/workers/platform/limits and may have been raised for your account.");
+    Ok(())
+}
+
+fn account_limits(client: &Client, account: AccountOutput) -> Result<AccountLimits> {
+    // a count is left out when the credentials are not allowed to read it
+    let scripts = fetch(
+        client,
+        &format!("{}/accounts/{}/workers/scripts", API_BASE, account.id),
+    )?
+    .and_then(|response: V4Response<Vec<serde_json::Value>>| response.result)
+    .map(|scripts| scripts.len() as u64);
+    let kv_namespaces = fetch(
+        client,
+        &format!(
+            "{}/accounts/{}/storage/kv/namespaces?per_page=5",
+            API_BASE, account.id
+        ),
+    )?
+    .and_then(|response: V4Response<Vec<serde_json::Value>>| response.result_info)
+    .and_then(|info| info.total_count);
+    let default_usage_model = fetch(
+        client,
+        &format!(
+            "{}/accounts/{}/workers/account-settings",
+            API_BASE, account.id
+        ),
+    )?
+    .and_then(|response: V4Response<AccountSettings>| response.result)
+    .and_then(|settings| settings.default_usage_model);
+
+    let subrequests = match default_usage_model.as_deref() {
+        Some("unbound") => SUBREQUESTS_UNBOUND,
+        _ => SUBREQUESTS_BUNDLED,
+    };
+    let limit = |name, used, free, paid, bytes| LimitOutput {
+        name,
+        used,
+        free,
+        paid,
+        bytes,
+    };
+    Ok(AccountLimits {
+        id: account.id,
+        name: account.name,
+        default_usage_model,
+        limits: vec![
+            limit(
+                "Workers scripts",
+                scripts,
+                SCRIPTS_FREE,
+                SCRIPTS_PAID,
+                false,
+            ),
+            limit(
+                "Script size (compressed)",
+                None,
+                SCRIPT_SIZE,
+                SCRIPT_SIZE,
+                true,
+            ),
+            limit(
+                "KV namespaces",
+                kv_namespaces,
+                KV_NAMESPACES,
+                KV_NAMESPACES,
+                false,
+            ),
+            limit(
+                "Subrequests per request",
+                None,
+                SUBREQUESTS_BUNDLED,
+                subrequests,
+                false,
+            ),
+        ],
+    })
+}
+
+fn fetch<T: serde::de::DeserializeOwned>(
+    client: &Client,
+    addr: &str,
+) -> Result<Option<V4Response<T>>> {
+    let response: V4Response<T> = client.get(addr).send()?.json()?;
+    if response.success {
+        Ok(Some(response))
+    } else {
+        log::info!("Failed to fetch {}", addr);
+        Ok(None)
+    }
+}
@@ -1,6 +1,10 @@
 use crate::login;
 use anyhow::Result;
 
-pub fn run(scopes_list: Option<&[String]>) -> Result<()> {
-    login::run(scopes_list)
+pub fn run(scopes_list: Option<&[String]>, keychain: bool) -> Result<()> {
+    login::run(scopes_list, keychain)
+}
+
+pub fn refresh() -> Result<()> {
+    login::refresh()
 }
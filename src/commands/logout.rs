@@ -1,6 +1,6 @@
 use crate::login::{AUTH_URL, CLIENT_ID};
-use crate::settings::get_global_config_path;
 use crate::settings::global_user::GlobalUser;
+use crate::settings::{cache, get_global_config_path, keychain};
 use crate::terminal::message::{Message, StdOut};
 
 use anyhow::Result;
@@ -13,15 +13,21 @@ use std::fs;
 
 static REVOKE_URL: &str = "https://dash.cloudflare.com/oauth2/revoke";
 
-pub fn run() -> Result<()> {
+pub fn run(keep_remote: bool) -> Result<()> {
     let mut has_auth = true;
     if let Ok(user) = GlobalUser::new() {
         match user {
+            GlobalUser::OAuthTokenAuth { .. } if keep_remote => {
+                StdOut::info("Wrangler is configured with an OAuth token. The token has not been revoked and remains valid until it expires.");
+            }
             GlobalUser::OAuthTokenAuth { .. } => {
                 // Set up OAuth client
                 match revoke_token(&user) {
                     Ok(_) => StdOut::info("Wrangler is configured with an OAuth token. The token has been successfully revoked."),
-                    Err(e) => anyhow::bail!(e),
+                    Err(e) => anyhow::bail!(
+                        "Failed to revoke the OAuth token: {}\nRun `wrangler logout --keep-remote` to only remove it from this machine.",
+                        e
+                    ),
                 }
             }
             GlobalUser::ApiTokenAuth { .. } => {
@@ -32,6 +38,10 @@ pub fn run() -> Result<()> {
                 // Global API key cannot be modified
                 StdOut::info("Wrangler is configured with a Global API key.");
             }
+            GlobalUser::ServiceTokenAuth { .. } => {
+                // Service tokens belong to accounts, which revoke them in the dashboard
+                StdOut::info("Wrangler is configured with a service token. Please go to your dashboard if you would like to revoke the service token.");
+            }
         }
     } else {
         StdOut::info("Improperly configured or missing authentication method. Please see the documentation regarding `wrangler login` or `wrangler config` for authentication methods.");
@@ -48,9 +58,15 @@ pub fn run() -> Result<()> {
                 "configuration file"
             }
         };
+        if keychain::uses_keychain(&config_path) {
+            keychain::delete(&config_path)?;
+        }
         print!("Removing {}..", config_path_str);
         fs::remove_file(config_path)?;
         println!(" success!");
+        if let Err(e) = cache::clear() {
+            log::info!("Failed to clear the cache: {}", e);
+        }
     } else if has_auth {
         // (in)correct environment variables are set
         println!("No config file has been found. If you wish to unauntheticate `wrangler`, please unset your environment variables (e.g. \"CF_API_TOKEN\", \"CF_API_KEY\", or \"CF_EMAIL\").");
@@ -0,0 +1,362 @@
+//! `wrangler logs query`, which reads the past events of a worker from the R2 bucket that a
+//! Logpush job of the `workers_trace_events` dataset writes to, set by `[logs] logpush_bucket`.
+//! Logpush writes a gzipped file of JSON lines every few minutes, named after the day and the
+//! times of its first and last event, like `20220101/20220101T100000Z_20220101T100500Z_abc.log.gz`,
+//! so only the files of the queried period are downloaded, one at a time in the order of their
+//! first event. The bucket is read with the S3-compatible API of R2, whose access key is in
+//! `R2_ACCESS_KEY_ID` and `R2_SECRET_ACCESS_KEY`.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+use std::io::{BufRead, BufReader};
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use flate2::read::MultiGzDecoder;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::commands::tail::event::{
+    EventItem, ExceptionItem, LogItem, RequestItem, ResponseItem, TraceEvent,
+};
+use crate::commands::tail::filter::OutcomeFilter;
+use crate::commands::tail::pretty::PrettyFormat;
+use crate::commands::tail::websocket::TailFormat;
+use crate::http::r2::{self, R2Client};
+use crate::settings::toml::{LogsConfig, Target};
+use crate::terminal::message::{Message, StdErr};
+
+// 20220101T100000Z_20220101T100500Z_
+static FILE_NAME: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(\d{8}T\d{6}Z)_(\d{8}T\d{6}Z)_[^/]*$").unwrap());
+
+/// What `wrangler logs query` looks for.
+pub struct Query {
+    pub script_name: String,
+    pub since: DateTime<Utc>,
+    /// The outcomes of the events to print, as `tail --status` takes them, or all if empty.
+    pub status: Vec<String>,
+    pub format: TailFormat,
+    pub pretty: PrettyFormat,
+}
+
+pub fn query(target: &Target, config: &LogsConfig, query: Query) -> Result<()> {
+    let client = R2Client::new(&target.account_id.load()?, &config.logpush_bucket)?;
+    let outcomes: HashSet<String> = OutcomeFilter::from(query.status.clone())
+        .outcome
+        .into_iter()
+        .collect();
+
+    let now = Utc::now();
+    let mut files = Vec::new();
+    for day in days(query.since, now) {
+        let prefix = match &config.logpush_prefix {
+            Some(prefix) => format!("{}/{}/", prefix.trim_end_matches('/'), day),
+            None => format!("{}/", day),
+        };
+        for key in client.list_objects(&prefix)? {
+            // the files of other names are read anyway
+            if period(&key).map_or(true, |(_, end)| end >= query.since) {
+                files.push(key);
+            }
+        }
+    }
+    // the files of other names first, since when their events happened is unknown
+    files.sort_by_key(|key| period(key).map(|(start, _)| start));
+    StdErr::working(&format!(
+        "Reading {} Logpush files of {} since {}",
+        files.len(),
+        query.script_name,
+        query.since.to_rfc3339()
+    ));
+
+    let since = query.since.timestamp_millis();
+    let mut pending = EventQueue::default();
+    let mut count = 0;
+    for key in &files {
+        // this file and the next ones only have events after its first one
+        if let Some((start, _)) = period(key) {
+            while let Some(event) = pending.pop_before(start.timestamp_millis()) {
+                print(&event, &query)?;
+                count += 1;
+            }
+        }
+
+        let res = client.get_object(key).send()?;
+        if !res.status().is_success() {
+            anyhow::bail!(
+                "Failed to download {} from {}: {}",
+                key,
+                config.logpush_bucket,
+                r2::error_message(res)?
+            );
+        }
+        for line in BufReader::new(MultiGzDecoder::new(res)).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event = match serde_json::from_str::<LogpushEvent>(&line) {
+                Ok(event) => event,
+                Err(e) => {
+                    log::debug!("Skipping a line of {} that is not an event: {}", key, e);
+                    continue;
+                }
+            };
+            if event.script_name.as_deref() != Some(query.script_name.as_str())
+                || event.event_timestamp_ms < since
+                || !(outcomes.is_empty() || outcomes.contains(&event.outcome))
+            {
+                continue;
+            }
+            pending.push(event.into_trace_event());
+        }
+    }
+    while let Some(event) = pending.pop_before(i64::MAX) {
+        print(&event, &query)?;
+        count += 1;
+    }
+    StdErr::info(&format!("Found {} events", count));
+    Ok(())
+}
+
+fn print(event: &TraceEvent, query: &Query) -> Result<()> {
+    match query.format {
+        TailFormat::Pretty => println!("{}", query.pretty.render(event)),
+        TailFormat::Json | TailFormat::Ndjson => println!("{}", serde_json::to_string(event)?),
+    }
+    Ok(())
+}
+
+/// The events that are read but not printed yet, which come out in time order.
+#[derive(Default)]
+struct EventQueue(BinaryHeap<Pending>);
+
+impl EventQueue {
+    fn push(&mut self, event: TraceEvent) {
+        self.0.push(Pending(event));
+    }
+
+    /// The earliest event, if it happened before `time`.
+    fn pop_before(&mut self, time: i64) -> Option<TraceEvent> {
+        if self.0.peek()?.0.timestamp >= time {
+            return None;
+        }
+        self.0.pop().map(|pending| pending.0)
+    }
+}
+
+/// An event of the queue, which is a max-heap, so the earliest event is the greatest.
+struct Pending(TraceEvent);
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.timestamp == other.0.timestamp
+    }
+}
+
+impl Eq for Pending {}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.timestamp.cmp(&self.0.timestamp)
+    }
+}
+
+/// The days from `since` to `until`, in the `YYYYMMDD` form of the directories of Logpush.
+fn days(since: DateTime<Utc>, until: DateTime<Utc>) -> Vec<String> {
+    let mut days = Vec::new();
+    let mut day = since.date();
+    while day <= until.date() {
+        days.push(day.format("%Y%m%d").to_string());
+        day = day + Duration::days(1);
+    }
+    days
+}
+
+/// The times of the first and last events of a Logpush file, by its name.
+fn period(key: &str) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let caps = FILE_NAME.captures(key)?;
+    let time = |i| {
+        NaiveDateTime::parse_from_str(&caps[i], "%Y%m%dT%H%M%SZ")
+            .ok()
+            .map(|time| DateTime::<Utc>::from_utc(time, Utc))
+    };
+    Some((time(1)?, time(2)?))
+}
+
+/// An event of the `workers_trace_events` dataset of Logpush, whose fields are named like those of
+/// a tail in PascalCase.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LogpushEvent {
+    script_name: Option<String>,
+    outcome: String,
+    event_timestamp_ms: i64,
+    #[serde(default)]
+    event: Option<LogpushEventItem>,
+    #[serde(default)]
+    logs: Vec<LogpushLog>,
+    #[serde(default)]
+    exceptions: Vec<LogpushException>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LogpushEventItem {
+    request: Option<LogpushRequest>,
+    response: Option<LogpushResponse>,
+    cron: Option<String>,
+    queue: Option<String>,
+    scheduled_time: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LogpushRequest {
+    #[serde(rename = "URL")]
+    url: String,
+    method: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LogpushResponse {
+    status: u16,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LogpushLog {
+    level: String,
+    message: Value,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LogpushException {
+    name: String,
+    message: String,
+}
+
+impl LogpushEvent {
+    /// The event as a tail would have sent it, so that it's printed the same way.
+    fn into_trace_event(self) -> TraceEvent {
+        let event = self.event.unwrap_or(LogpushEventItem {
+            request: None,
+            response: None,
+            cron: None,
+            queue: None,
+            scheduled_time: None,
+        });
+        TraceEvent {
+            script_name: self.script_name,
+            timestamp: self.event_timestamp_ms,
+            outcome: self.outcome,
+            wall_time: None,
+            logs: self
+                .logs
+                .into_iter()
+                .map(|log| LogItem {
+                    level: log.level,
+                    message: log.message,
+                })
+                .collect(),
+            exceptions: self
+                .exceptions
+                .into_iter()
+                .map(|exception| ExceptionItem {
+                    name: exception.name,
+                    message: exception.message,
+                    stack: None,
+                })
+                .collect(),
+            event: EventItem {
+                request: event.request.map(|request| RequestItem {
+                    url: request.url,
+                    method: request.method,
+                    headers: Default::default(),
+                    cf: None,
+                }),
+                response: event.response.map(|response| ResponseItem {
+                    status: response.status,
+                }),
+                cron: event.cron,
+                queue: event.queue,
+                scheduled_time: event.scheduled_time,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn it_lists_the_days_of_the_period() {
+        let since = Utc.ymd(2022, 1, 30).and_hms(23, 0, 0);
+        let until = Utc.ymd(2022, 2, 1).and_hms(1, 0, 0);
+        assert_eq!(days(since, until), vec!["20220130", "20220131", "20220201"]);
+    }
+
+    #[test]
+    fn it_reads_the_period_of_files() {
+        let (start, end) =
+            period("logs/20220101/20220101T095500Z_20220101T100000Z_0a1b2c.log.gz").unwrap();
+        assert_eq!(start, Utc.ymd(2022, 1, 1).and_hms(9, 55, 0));
+        assert_eq!(end, Utc.ymd(2022, 1, 1).and_hms(10, 0, 0));
+        assert_eq!(period("logs/20220101/manual.log.gz"), None);
+    }
+
+    #[test]
+    fn it_queues_events_in_time_order() {
+        let event = |timestamp| {
+            let line = format!(
+                r#"{{"ScriptName":"my-worker","Outcome":"ok","EventTimestampMs":{}}}"#,
+                timestamp
+            );
+            serde_json::from_str::<LogpushEvent>(&line)
+                .unwrap()
+                .into_trace_event()
+        };
+        let mut queue = EventQueue::default();
+        for timestamp in &[30, 10, 20, 40] {
+            queue.push(event(*timestamp));
+        }
+        let mut popped = Vec::new();
+        while let Some(event) = queue.pop_before(30) {
+            popped.push(event.timestamp);
+        }
+        assert_eq!(popped, vec![10, 20]);
+        queue.push(event(25));
+        while let Some(event) = queue.pop_before(i64::MAX) {
+            popped.push(event.timestamp);
+        }
+        assert_eq!(popped, vec![10, 20, 25, 30, 40]);
+    }
+
+    #[test]
+    fn it_reads_logpush_events() {
+        let line = r#"{"ScriptName":"my-worker","Outcome":"exception","EventTimestampMs":1641031200000,"EventType":"fetch","Event":{"RayID":"6c7d","Request":{"URL":"https://example.com/","Method":"GET"},"Response":{"Status":500}},"Logs":[{"Level":"log","Message":["hello"],"TimestampMs":1641031200001}],"Exceptions":[{"Name":"Error","Message":"oops","TimestampMs":1641031200002}]}"#;
+        let event = serde_json::from_str::<LogpushEvent>(line)
+            .unwrap()
+            .into_trace_event();
+        assert_eq!(event.script_name.as_deref(), Some("my-worker"));
+        assert_eq!(event.timestamp, 1641031200000);
+        assert!(event.is_error());
+        assert_eq!(event.event.request.unwrap().url, "https://example.com/");
+        assert_eq!(event.event.response.unwrap().status, 500);
+        assert_eq!(event.logs[0].message, serde_json::json!(["hello"]));
+        assert_eq!(event.exceptions[0].message, "oops");
+    }
+}
@@ -0,0 +1,449 @@
+//! Middleware that `wrangler add middleware` generates into a project: modules that run before
+//! the fetch listener of the worker, maintained here so that projects start from snippets that
+//! handle the edge cases. A middleware is written next to the entry point in `middleware/`,
+//! imported at the top of the entry point, and configured with `[vars]`.
+//!
+//! The middleware of a project run in the order they were added. `middleware/chain.js` wraps the
+//! fetch listeners that the worker adds, which is why the entry point has to import the
+//! middleware before it adds them and has to be bundled, by webpack.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use toml_edit::Document;
+
+use crate::settings::toml::{ConfigFormat, Target, TargetType, UploadFormat};
+use crate::terminal::message::{Message, StdOut};
+use crate::terminal::styles;
+
+const MIDDLEWARE_DIR: &str = "middleware";
+
+/// Runs the middleware, then the fetch listeners of the worker. It is written again whenever a
+/// middleware is added.
+const CHAIN: &str = r#"// Generated by wrangler. Runs the middleware added with `wrangler add middleware` before the
+// fetch listeners of the worker, by wrapping the listeners when they are added.
+const middleware = [];
+const register = globalThis.addEventListener;
+const handled = new WeakSet();
+
+// Adds a middleware: an async function of a request and of `next`, which passes a request to the
+// middleware that follow and to the worker, and resolves to their response.
+export function use(handler) {
+  middleware.push(handler);
+}
+
+// A variable of `[vars]` or a secret, or `fallback` if it isn't set.
+export function variable(name, fallback) {
+  return typeof globalThis[name] === "undefined" ? fallback : globalThis[name];
+}
+
+globalThis.addEventListener = (type, listener, options) => {
+  if (type !== "fetch") {
+    return register(type, listener, options);
+  }
+  const respond = (event, request) =>
+    new Promise((resolve) => {
+      let responded = false;
+      const proxy = new Proxy(event, {
+        get(target, property) {
+          if (property === "request") {
+            return request;
+          }
+          if (property === "respondWith") {
+            return (response) => {
+              responded = true;
+              resolve(response);
+            };
+          }
+          const value = Reflect.get(target, property);
+          return typeof value === "function" ? value.bind(target) : value;
+        },
+      });
+      listener(proxy);
+      // like without middleware, a request the worker doesn't respond to goes to the origin
+      if (!responded) {
+        resolve(fetch(request));
+      }
+    });
+  return register(
+    "fetch",
+    (event) => {
+      // the first listener responds, like the first listener that calls respondWith
+      if (handled.has(event)) {
+        return;
+      }
+      handled.add(event);
+      const run = (index, request) =>
+        index < middleware.length
+          ? middleware[index](request, (next) => run(index + 1, next || request))
+          : respond(event, request);
+      event.respondWith(run(0, event.request));
+    },
+    options
+  );
+};
+"#;
+
+const BASIC_AUTH: &str = r#"// Generated by `wrangler add middleware basic-auth`. Asks for a user name and a password with
+// HTTP Basic authentication before the worker responds.
+import { use, variable } from "./chain.js";
+
+const encoder = new TextEncoder();
+
+// Compares the digests of the strings, so that the time it takes doesn't tell how much of them
+// matched.
+async function equal(a, b) {
+  const [x, y] = await Promise.all(
+    [a, b].map(async (s) => new Uint8Array(await crypto.subtle.digest("SHA-256", encoder.encode(s))))
+  );
+  let difference = 0;
+  for (let i = 0; i < x.length; i++) {
+    difference |= x[i] ^ y[i];
+  }
+  return difference === 0;
+}
+
+function credentials(request) {
+  const [scheme, encoded] = (request.headers.get("Authorization") || "").split(" ");
+  if (scheme !== "Basic" || !encoded) {
+    return null;
+  }
+  let decoded;
+  try {
+    decoded = atob(encoded);
+  } catch (e) {
+    return null;
+  }
+  const separator = decoded.indexOf(":");
+  return separator === -1 ? null : [decoded.slice(0, separator), decoded.slice(separator + 1)];
+}
+
+use(async (request, next) => {
+  const password = variable("BASIC_AUTH_PASSWORD", "");
+  if (!password) {
+    return new Response("Set the password with `wrangler secret put BASIC_AUTH_PASSWORD`", {
+      status: 500,
+    });
+  }
+  const given = credentials(request);
+  if (given) {
+    const [userMatches, passwordMatches] = await Promise.all([
+      equal(given[0], variable("BASIC_AUTH_USER", "")),
+      equal(given[1], password),
+    ]);
+    if (userMatches && passwordMatches) {
+      return next();
+    }
+  }
+  const realm = variable("BASIC_AUTH_REALM", "Restricted").replace(/"/g, "");
+  return new Response("Unauthorized", {
+    status: 401,
+    headers: { "WWW-Authenticate": `Basic realm="${realm}", charset="UTF-8"` },
+  });
+});
+"#;
+
+const MAINTENANCE_PAGE: &str = r#"// Generated by `wrangler add middleware maintenance-page`. Responds with a maintenance page
+// while MAINTENANCE_MODE is "on", except to the IP addresses of MAINTENANCE_ALLOWED_IPS.
+import { use, variable } from "./chain.js";
+
+const escape = (text) =>
+  text.replace(/[&<>"']/g, (c) => `&#${c.charCodeAt(0)};`);
+
+const page = (message) => `<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+    <title>Down for maintenance</title>
+  </head>
+  <body style="font-family: sans-serif; max-width: 40rem; margin: 4rem auto; padding: 0 1rem">
+    <h1>Down for maintenance</h1>
+    <p>${escape(message)}</p>
+  </body>
+</html>
+`;
+
+use(async (request, next) => {
+  if (variable("MAINTENANCE_MODE", "off") !== "on") {
+    return next();
+  }
+  const allowed = variable("MAINTENANCE_ALLOWED_IPS", "")
+    .split(",")
+    .map((ip) => ip.trim())
+    .filter((ip) => ip);
+  if (allowed.includes(request.headers.get("CF-Connecting-IP"))) {
+    return next();
+  }
+  const message = variable("MAINTENANCE_MESSAGE", "We'll be back soon.");
+  return new Response(page(message), {
+    status: 503,
+    headers: {
+      "Content-Type": "text/html; charset=utf-8",
+      "Cache-Control": "no-store",
+      "Retry-After": String(variable("MAINTENANCE_RETRY_AFTER", "3600")),
+    },
+  });
+});
+"#;
+
+const AB_TEST: &str = r#"// Generated by `wrangler add middleware ab-test`. Splits visitors into the groups "a" and "b",
+// which keep their group with a cookie. The requests of group "b" have AB_TEST_VARIANT_PREFIX put
+// before their path, e.g. /b/pricing instead of /pricing, for the worker or the origin to serve
+// the variant.
+import { use, variable } from "./chain.js";
+
+const MAX_AGE = 30 * 24 * 60 * 60;
+
+function group(request, cookieName) {
+  const prefix = `${cookieName}=`;
+  const cookie = (request.headers.get("Cookie") || "")
+    .split(";")
+    .map((c) => c.trim())
+    .find((c) => c.startsWith(prefix));
+  const value = cookie && cookie.slice(prefix.length);
+  return value === "a" || value === "b" ? value : null;
+}
+
+use(async (request, next) => {
+  const cookieName = variable("AB_TEST_COOKIE", "ab-group");
+  const share = Number(variable("AB_TEST_VARIANT_SHARE", "50"));
+  const known = group(request, cookieName);
+  const current = known || (Math.random() * 100 < share ? "b" : "a");
+
+  if (current === "b") {
+    const url = new URL(request.url);
+    url.pathname = variable("AB_TEST_VARIANT_PREFIX", "/b") + url.pathname;
+    request = new Request(url.toString(), request);
+  }
+  const response = await next(request);
+  if (known) {
+    return response;
+  }
+  const assigned = new Response(response.body, response);
+  assigned.headers.append(
+    "Set-Cookie",
+    `${cookieName}=${current}; Path=/; Max-Age=${MAX_AGE}; SameSite=Lax; Secure`
+  );
+  return assigned;
+});
+"#;
+
+/// A middleware that can be added to a project.
+pub struct Middleware {
+    pub name: &'static str,
+    source: &'static str,
+    /// The variables of `[vars]` that configure it, with their defaults.
+    vars: &'static [(&'static str, &'static str)],
+    /// The variables it reads that should be set as secrets rather than in `[vars]`.
+    secrets: &'static [&'static str],
+}
+
+pub const MIDDLEWARE: &[Middleware] = &[
+    Middleware {
+        name: "basic-auth",
+        source: BASIC_AUTH,
+        vars: &[
+            ("BASIC_AUTH_USER", "admin"),
+            ("BASIC_AUTH_REALM", "Restricted"),
+        ],
+        secrets: &["BASIC_AUTH_PASSWORD"],
+    },
+    Middleware {
+        name: "maintenance-page",
+        source: MAINTENANCE_PAGE,
+        vars: &[
+            ("MAINTENANCE_MODE", "off"),
+            ("MAINTENANCE_ALLOWED_IPS", ""),
+            ("MAINTENANCE_MESSAGE", "We'll be back soon."),
+            ("MAINTENANCE_RETRY_AFTER", "3600"),
+        ],
+        secrets: &[],
+    },
+    Middleware {
+        name: "ab-test",
+        source: AB_TEST,
+        vars: &[
+            ("AB_TEST_COOKIE", "ab-group"),
+            ("AB_TEST_VARIANT_SHARE", "50"),
+            ("AB_TEST_VARIANT_PREFIX", "/b"),
+        ],
+        secrets: &[],
+    },
+];
+
+/// The names of the middleware, for the command line.
+pub const NAMES: &[&str] = &["basic-auth", "maintenance-page", "ab-test"];
+
+/// Generates a middleware into the project of a target, imports it in the entry point, and adds
+/// its variables to the configuration file.
+pub fn add(target: &Target, config_path: &Path, name: &str) -> Result<()> {
+    let middleware = MIDDLEWARE
+        .iter()
+        .find(|middleware| middleware.name == name)
+        .ok_or_else(|| anyhow!("Unknown middleware `{}`", name))?;
+
+    if target.target_type != TargetType::Webpack {
+        anyhow::bail!(
+            "Middleware can only be added to webpack projects, which bundle the middleware that their entry point imports"
+        );
+    }
+    if target.entries.is_some()
+        || matches!(
+            target.build.as_ref().map(|build| &build.upload),
+            Some(UploadFormat::Modules { .. })
+        )
+    {
+        anyhow::bail!("Middleware can only be added to workers in the service worker format");
+    }
+
+    let entry_point = target.entry_point()?;
+    let dir = entry_point
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(MIDDLEWARE_DIR);
+    let file = dir.join(format!("{}.js", middleware.name));
+    if file.exists() {
+        anyhow::bail!("{} has already been added to {}", name, file.display());
+    }
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("chain.js"), CHAIN)?;
+    fs::write(&file, middleware.source)?;
+    StdOut::info(&format!("Wrote {}", file.display()));
+
+    let source = fs::read_to_string(&entry_point)?;
+    let import = format!("import \"./{}/{}.js\";", MIDDLEWARE_DIR, middleware.name);
+    if let Some(source) = add_import(&source, &import) {
+        fs::write(&entry_point, source)?;
+        StdOut::info(&format!("Imported it in {}", entry_point.display()));
+    }
+
+    // the variables of other formats are left to be added by hand
+    if ConfigFormat::from_path(config_path)? == ConfigFormat::Toml {
+        let config = fs::read_to_string(config_path)?;
+        let (config, added) = add_vars(&config, middleware.vars)?;
+        fs::write(config_path, config)?;
+        if !added.is_empty() {
+            StdOut::info(&format!(
+                "Added {} to [vars] of {}",
+                added.join(", "),
+                config_path.display()
+            ));
+        }
+    } else if !middleware.vars.is_empty() {
+        let vars = middleware
+            .vars
+            .iter()
+            .map(|(var, value)| format!("{} = {:?}", var, value))
+            .collect::<Vec<_>>();
+        StdOut::info(&format!(
+            "Add its variables to your configuration file:\n{}",
+            vars.join("\n")
+        ));
+    }
+
+    for secret in middleware.secrets {
+        StdOut::info(&format!(
+            "Set {} with {}",
+            secret,
+            styles::highlight(format!("`wrangler secret put {}`", secret))
+        ));
+    }
+    StdOut::success(&format!("Added the {} middleware", name));
+    Ok(())
+}
+
+/// Imports a middleware after the middleware already imported by an entry point, or at its top,
+/// so that they run in the order they were added. `None` if it is already imported.
+fn add_import(source: &str, import: &str) -> Option<String> {
+    let mut lines: Vec<&str> = source.lines().collect();
+    if lines.iter().any(|line| line.trim() == import) {
+        return None;
+    }
+    let prefix = format!("import \"./{}/", MIDDLEWARE_DIR);
+    let position = lines
+        .iter()
+        .rposition(|line| line.starts_with(&prefix))
+        .map_or(0, |last| last + 1);
+
+    lines.insert(position, import);
+    // the first middleware is set apart from the code of the entry point
+    if position == 0 && lines.get(1).map_or(false, |line| !line.trim().is_empty()) {
+        lines.insert(1, "");
+    }
+    let mut imported = lines.join("\n");
+    if source.is_empty() || source.ends_with('\n') {
+        imported.push('\n');
+    }
+    Some(imported)
+}
+
+/// Adds the variables that `[vars]` doesn't have yet, and returns the names of those it added.
+/// toml_edit is used so that comments and the order of the keys are preserved.
+fn add_vars(
+    config: &str,
+    vars: &[(&'static str, &'static str)],
+) -> Result<(String, Vec<&'static str>)> {
+    let mut doc = config
+        .parse::<Document>()
+        .map_err(|e| anyhow!("Could not parse the configuration: {}", e))?;
+    let mut added = Vec::new();
+    for &(var, value) in vars {
+        if doc["vars"][var].is_none() {
+            doc["vars"][var] = toml_edit::value(value);
+            added.push(var);
+        }
+    }
+    Ok((doc.to_string_in_original_order(), added))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_imports_middleware_in_the_order_they_are_added() {
+        let source = "addEventListener(\"fetch\", handle);\n";
+        let first = add_import(source, "import \"./middleware/basic-auth.js\";").unwrap();
+        assert_eq!(
+            first,
+            "import \"./middleware/basic-auth.js\";\n\naddEventListener(\"fetch\", handle);\n"
+        );
+        let second = add_import(&first, "import \"./middleware/ab-test.js\";").unwrap();
+        assert_eq!(
+            second,
+            "import \"./middleware/basic-auth.js\";\nimport \"./middleware/ab-test.js\";\n\naddEventListener(\"fetch\", handle);\n"
+        );
+        assert_eq!(
+            add_import(&second, "import \"./middleware/ab-test.js\";"),
+            None
+        );
+    }
+
+    #[test]
+    fn it_keeps_the_vars_that_are_set() {
+        let config = "name = \"worker\" # the name\n\n[vars]\nBASIC_AUTH_USER = \"me\"\n";
+        let (config, added) = add_vars(
+            config,
+            &[
+                ("BASIC_AUTH_USER", "admin"),
+                ("BASIC_AUTH_REALM", "Restricted"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(added, vec!["BASIC_AUTH_REALM"]);
+        assert!(config.contains("# the name"));
+        assert!(config.contains("BASIC_AUTH_USER = \"me\""));
+        assert!(config.contains("BASIC_AUTH_REALM = \"Restricted\""));
+    }
+
+    #[test]
+    fn it_has_a_name_for_every_middleware() {
+        let names: Vec<&str> = MIDDLEWARE
+            .iter()
+            .map(|middleware| middleware.name)
+            .collect();
+        assert_eq!(names, NAMES);
+    }
+}
@@ -0,0 +1,297 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use toml_edit::{Array, Document, Item};
+
+use crate::settings::toml::ConfigFormat;
+use crate::terminal::message::{Message, StdOut};
+use crate::terminal::styles;
+
+/// Rewrites the deprecated settings of a configuration file into the current layout. The
+/// original file is kept next to it with a `.bak` extension.
+pub fn migrate(config_path: &Path) -> Result<()> {
+    if ConfigFormat::from_path(config_path)? != ConfigFormat::Toml {
+        anyhow::bail!(
+            "Only TOML configuration files can be migrated, but {} was given",
+            config_path.display()
+        );
+    }
+    let original = fs::read_to_string(config_path)
+        .map_err(|e| anyhow!("Could not read {}: {}", config_path.display(), e))?;
+
+    let (migrated, changes) = migrate_toml(&original)?;
+    if changes.is_empty() {
+        StdOut::success(&format!("{} is already up to date", config_path.display()));
+        return Ok(());
+    }
+
+    for line in diff_lines(&original, &migrated) {
+        match line {
+            DiffLine::Removed(line) => println!("{}", styles::removed(format!("-{}", line))),
+            DiffLine::Added(line) => println!("{}", styles::added(format!("+{}", line))),
+            DiffLine::Same(line) => println!(" {}", line),
+        }
+    }
+    println!();
+    for change in changes.iter() {
+        StdOut::info(change);
+    }
+
+    let backup_path = backup_path(config_path);
+    fs::copy(config_path, &backup_path)?;
+    fs::write(config_path, migrated)?;
+    StdOut::success(&format!(
+        "Migrated {}. The original file was saved as {}",
+        config_path.display(),
+        backup_path.display()
+    ));
+    Ok(())
+}
+
+fn backup_path(config_path: &Path) -> PathBuf {
+    let mut file_name = config_path
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_default();
+    file_name.push(".bak");
+    config_path.with_file_name(file_name)
+}
+
+/// Applies every migration to the content of a wrangler.toml and describes what was changed.
+/// toml_edit is used so that comments and the order of the keys are preserved.
+fn migrate_toml(content: &str) -> Result<(String, Vec<String>)> {
+    let mut doc = content
+        .parse::<Document>()
+        .map_err(|e| anyhow!("Could not parse the configuration: {}", e))?;
+    let mut changes = Vec::new();
+
+    // `webpack` is the default type, so writing it down is redundant
+    let target_type = doc["type"].as_str().unwrap_or("webpack").to_string();
+    if doc["type"].as_str() == Some("webpack") {
+        doc["type"] = Item::None;
+        changes.push("Removed `type = \"webpack\"`, which is the default type".to_string());
+    }
+
+    let env_names = doc["env"]
+        .as_table()
+        .map(|envs| {
+            envs.iter()
+                .filter(|(_, env)| env.is_table())
+                .map(|(name, _)| name.to_string())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    // webpack_config is only read when building webpack projects
+    if target_type != "webpack" {
+        if !doc["webpack_config"].is_none() {
+            doc["webpack_config"] = Item::None;
+            changes.push(format!(
+                "Removed `webpack_config`, which is not used by {} projects",
+                target_type
+            ));
+        }
+        for name in env_names.iter() {
+            let env = &mut doc["env"][name.as_str()];
+            if !env["webpack_config"].is_none() {
+                env["webpack_config"] = Item::None;
+                changes.push(format!(
+                    "Removed `webpack_config` of env.{}, which is not used by {} projects",
+                    name, target_type
+                ));
+            }
+        }
+    }
+
+    let top_level_webpack_config = doc["webpack_config"].as_str().map(str::to_string);
+    let top_level_zone_id = doc["zone_id"].as_str().map(str::to_string);
+
+    if let Some(change) = migrate_route(doc.as_table_mut(), "") {
+        changes.push(change);
+    }
+
+    for name in env_names.iter() {
+        let location = format!("env.{}", name);
+        let env = &mut doc["env"][name.as_str()];
+
+        // Environments inherit these from the top level
+        if top_level_webpack_config.is_some()
+            && env["webpack_config"].as_str() == top_level_webpack_config.as_deref()
+        {
+            env["webpack_config"] = Item::None;
+            changes.push(format!(
+                "Removed `webpack_config` of {}, which is inherited from the top level",
+                location
+            ));
+        }
+        let has_routes = !env["route"].is_none() || !env["routes"].is_none();
+        if has_routes
+            && top_level_zone_id.is_some()
+            && env["zone_id"].as_str() == top_level_zone_id.as_deref()
+        {
+            env["zone_id"] = Item::None;
+            changes.push(format!(
+                "Removed `zone_id` of {}, which is inherited from the top level",
+                location
+            ));
+        }
+
+        if let Some(table) = env.as_table_mut() {
+            if let Some(change) = migrate_route(table, &location) {
+                changes.push(change);
+            }
+        }
+    }
+
+    Ok((doc.to_string(), changes))
+}
+
+/// Replaces the singular `route` with the `routes` list. An empty `route` was the legacy way
+/// of deploying to workers.dev.
+fn migrate_route(table: &mut toml_edit::Table, location: &str) -> Option<String> {
+    let route = table["route"].as_str()?.to_string();
+    // Arrays cannot mix bare patterns with the tables of routes naming their zone
+    let mixed = table["routes"].as_array().map_or(false, |routes| {
+        routes.iter().any(|route| route.as_str().is_none())
+    });
+    if mixed {
+        return None;
+    }
+    let of = if location.is_empty() {
+        String::new()
+    } else {
+        format!(" of {}", location)
+    };
+    table["route"] = Item::None;
+
+    if route.is_empty() {
+        if table["workers_dev"].is_none() {
+            table["workers_dev"] = toml_edit::value(true);
+            return Some(format!(
+                "Replaced the empty `route`{} with `workers_dev = true`",
+                of
+            ));
+        }
+        return Some(format!("Removed the empty `route`{}", of));
+    }
+
+    let mut routes = Array::default();
+    routes.push(route.as_str()).ok()?;
+    if let Some(existing) = table["routes"].as_array() {
+        for pattern in existing.iter().filter_map(|value| value.as_str()) {
+            if pattern != route {
+                routes.push(pattern).ok()?;
+            }
+        }
+    }
+    table["routes"] = toml_edit::value(routes);
+    Some(format!("Moved `route`{} into `routes`", of))
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// A line based diff built from the longest common subsequence of the two texts.
+pub(crate) fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+
+    // lcs[i][j] is the length of the common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                std::cmp::max(lcs[i + 1][j], lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(DiffLine::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            lines.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().copied().map(DiffLine::Removed));
+    lines.extend(new[j..].iter().copied().map(DiffLine::Added));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_migrates_legacy_settings() {
+        let legacy = r#"# my worker
+name = "worker"
+type = "javascript"
+route = "example.com/*"
+zone_id = "zone"
+webpack_config = "webpack.config.js"
+
+[env.staging]
+route = ""
+
+[env.production]
+# production routes
+route = "example.com/api/*"
+routes = ["example.com/admin/*"]
+zone_id = "zone"
+"#;
+        let (migrated, changes) = migrate_toml(legacy).unwrap();
+        assert_eq!(changes.len(), 5, "{:#?}", changes);
+
+        let doc = migrated.parse::<Document>().unwrap();
+        assert!(migrated.contains("# my worker"));
+        assert!(migrated.contains("# production routes"));
+        assert_eq!(doc["type"].as_str(), Some("javascript"));
+        assert!(doc["route"].is_none());
+        assert!(doc["webpack_config"].is_none());
+        assert_eq!(doc["zone_id"].as_str(), Some("zone"));
+        assert_eq!(doc["env"]["staging"]["workers_dev"].as_bool(), Some(true));
+
+        let production = &doc["env"]["production"];
+        assert!(production["zone_id"].is_none());
+        let routes = production["routes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|route| route.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(routes, vec!["example.com/api/*", "example.com/admin/*"]);
+
+        let (_, changes) = migrate_toml(&migrated).unwrap();
+        assert!(changes.is_empty(), "{:#?}", changes);
+    }
+
+    #[test]
+    fn it_diffs_lines() {
+        let diff = diff_lines("a\nb\nc\n", "a\nc\nd\n");
+        assert_eq!(
+            diff,
+            vec![
+                DiffLine::Same("a"),
+                DiffLine::Removed("b"),
+                DiffLine::Same("c"),
+                DiffLine::Added("d"),
+            ]
+        );
+    }
+}
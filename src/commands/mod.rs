@@ -1,25 +1,34 @@
 use std::process::Command;
 
+pub mod check;
 pub mod config;
 pub mod dev;
 pub mod generate;
 pub mod init;
 pub mod kv;
+pub mod limits;
 pub mod login;
 pub mod logout;
+pub mod logs;
+pub mod middleware;
+pub mod migrate;
+pub mod notify;
 mod preview;
+pub mod preview_env;
 pub mod publish;
 pub mod r2;
 pub mod report;
 pub mod route;
 pub mod secret;
+pub mod sites;
 pub mod subdomain;
 pub mod tail;
+pub mod types;
 pub mod whoami;
 
 pub use self::config::global_config;
 pub use self::preview::run as preview;
-pub use generate::generate;
+pub use generate::{generate, generate_workspace};
 pub use init::init;
 pub use publish::publish;
 pub use whoami::whoami;
@@ -0,0 +1,192 @@
+//! The webhook of `[notifications]`, which is posted a JSON payload when a worker is published or
+//! its secrets change, so that a team hears about deployments without wrapping wrangler in a
+//! script. The payload has a `text` field for chat webhooks like Slack's, which show only that.
+
+use std::env;
+use std::process::Command;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::http;
+use crate::settings::toml::{Manifest, NotificationEvent};
+use crate::terminal::message::{Message, StdErr};
+
+/// The variables CI services set to the commit being built.
+const SHA_VARS: [&str; 2] = ["GITHUB_SHA", "CI_COMMIT_SHA"];
+/// The variables CI services set to the user who started the build.
+const ACTOR_VARS: [&str; 2] = ["GITHUB_ACTOR", "GITLAB_USER_LOGIN"];
+
+/// What happened to a worker.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    Publish,
+    SecretPut(String),
+    SecretDelete(String),
+}
+
+impl Event {
+    fn kind(&self) -> NotificationEvent {
+        match self {
+            Event::Publish => NotificationEvent::Publish,
+            Event::SecretPut(_) | Event::SecretDelete(_) => NotificationEvent::Secret,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Event::Publish => "publish",
+            Event::SecretPut(_) => "secret_put",
+            Event::SecretDelete(_) => "secret_delete",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Payload {
+    text: String,
+    event: &'static str,
+    success: bool,
+    worker: String,
+    environment: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secret: Option<String>,
+    version: Option<String>,
+    git_sha: Option<String>,
+    actor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Posts the outcome of a command to the webhook of `[notifications]`, if the configuration has
+/// one that wants the event. A webhook that can't be posted to is only warned about, since the
+/// command has already succeeded or failed by then.
+pub fn notify(
+    manifest: &Manifest,
+    env: Option<&str>,
+    worker: &str,
+    event: Event,
+    result: &Result<()>,
+) {
+    let notifications = match &manifest.notifications {
+        Some(notifications) if notifications.wants(event.kind()) => notifications,
+        _ => return,
+    };
+    let payload = payload(env, worker, event, result);
+    log::info!("Posting {:?} to {}", payload, notifications.webhook);
+
+    let posted = http::client()
+        .post(&notifications.webhook)
+        .json(&payload)
+        .send()
+        .map_err(anyhow::Error::from)
+        .and_then(|res| {
+            let status = res.status();
+            if status.is_success() {
+                Ok(())
+            } else {
+                Err(anyhow::anyhow!("{}", status))
+            }
+        });
+    if let Err(e) = posted {
+        StdErr::warn(&format!(
+            "Failed to post to the webhook of [notifications]: {}",
+            e
+        ));
+    }
+}
+
+fn payload(env: Option<&str>, worker: &str, event: Event, result: &Result<()>) -> Payload {
+    let success = result.is_ok();
+    let (action, done) = match &event {
+        Event::Publish => ("publish".to_string(), "published".to_string()),
+        Event::SecretPut(name) => (
+            format!("set the secret {} of", name),
+            format!("set the secret {} of", name),
+        ),
+        Event::SecretDelete(name) => (
+            format!("delete the secret {} of", name),
+            format!("deleted the secret {} of", name),
+        ),
+    };
+    let actor = actor();
+    let actor_name = actor.as_deref().unwrap_or("wrangler");
+    let mut text = if success {
+        format!("{} {} {}", actor_name, done, worker)
+    } else {
+        format!("{} failed to {} {}", actor_name, action, worker)
+    };
+    if let Some(env) = env {
+        text.push_str(&format!(" ({})", env));
+    }
+
+    Payload {
+        text,
+        event: event.name(),
+        success,
+        worker: worker.to_string(),
+        environment: env.map(str::to_string),
+        secret: match event {
+            Event::Publish => None,
+            Event::SecretPut(name) | Event::SecretDelete(name) => Some(name),
+        },
+        version: git(&["describe", "--tags", "--always", "--dirty"]),
+        git_sha: first_var(&SHA_VARS).or_else(|| git(&["rev-parse", "HEAD"])),
+        actor,
+        error: result.as_ref().err().map(|e| e.to_string()),
+    }
+}
+
+/// Who ran the command, by the CI job or the git configuration.
+fn actor() -> Option<String> {
+    first_var(&ACTOR_VARS)
+        .or_else(|| git(&["config", "user.name"]))
+        .or_else(|| env::var("USER").ok())
+}
+
+fn first_var(vars: &[&str]) -> Option<String> {
+    vars.iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// The trimmed output of a git command, if it succeeds.
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let output = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if output.is_empty() {
+        None
+    } else {
+        Some(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_describes_the_event() {
+        let deleted = payload(
+            Some("production"),
+            "my-worker",
+            Event::SecretDelete("TOKEN".to_string()),
+            &Err(anyhow::anyhow!("not found")),
+        );
+        assert!(!deleted.success);
+        assert!(deleted
+            .text
+            .ends_with("failed to delete the secret TOKEN of my-worker (production)"));
+        assert_eq!(deleted.event, "secret_delete");
+        assert_eq!(deleted.secret.as_deref(), Some("TOKEN"));
+        assert_eq!(deleted.error.as_deref(), Some("not found"));
+
+        let published = payload(None, "my-worker", Event::Publish, &Ok(()));
+        assert!(published.success);
+        assert!(published.text.ends_with("published my-worker"));
+        assert!(published.secret.is_none() && published.error.is_none());
+    }
+}
@@ -0,0 +1,236 @@
+//! Preview environments of git branches, which `wrangler publish --preview-branch` deploys to
+//! workers.dev under the name of the worker suffixed with the branch, e.g. `my-worker-feature-x`,
+//! with the preview namespaces of its bindings. The deployments are remembered in
+//! `.wrangler/config`, and `wrangler preview-env cleanup` deletes those of the branches that the
+//! git remote doesn't have anymore.
+
+use std::collections::HashSet;
+use std::process::Command;
+
+use anyhow::Result;
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::commands::validate_worker_name;
+use crate::deploy::{DeployTarget, DeploymentSet, ZonelessTarget};
+use crate::http;
+use crate::settings::global_user::GlobalUser;
+use crate::settings::project;
+use crate::settings::toml::env_mapping::current_branch;
+use crate::settings::toml::{Manifest, Target};
+use crate::terminal::interactive;
+use crate::terminal::message::{Message, StdErr, StdOut};
+
+/// The longest name of a worker on workers.dev, whose subdomains are DNS labels.
+const MAX_NAME_LEN: usize = 63;
+/// The length of the hash of the branch that ends the names that are too long.
+const HASH_LEN: usize = 6;
+
+/// The target and deployment of the preview environment of the current git branch, which only
+/// goes to workers.dev, without the routes and schedules of the worker.
+pub fn branch_target(manifest: &Manifest, env: Option<&str>) -> Result<(Target, DeploymentSet)> {
+    let branch = current_branch().ok_or_else(|| {
+        anyhow::anyhow!("--preview-branch needs a git branch, but none is checked out")
+    })?;
+    let mut target = manifest.get_target(env, true)?;
+    target.name = branch_worker_name(&target.name, &branch)?;
+    project::add_preview_branch(env, &target.name, &branch)?;
+    StdErr::info(&format!(
+        "Publishing the preview environment of {} as {}",
+        branch, target.name
+    ));
+
+    let deployment = DeployTarget::Zoneless(ZonelessTarget {
+        account_id: target.account_id.clone(),
+        script_name: target.name.clone(),
+    });
+    Ok((target, vec![deployment]))
+}
+
+/// Deletes the preview environments that `--preview-branch` published for the branches that the
+/// git remote `origin` doesn't have.
+pub fn cleanup(
+    manifest: &Manifest,
+    env: Option<&str>,
+    user: &GlobalUser,
+    dry_run: bool,
+    force: bool,
+) -> Result<()> {
+    let target = manifest.get_target(env, false)?;
+    let published = project::preview_branches(env);
+    let branches = remote_branches()?;
+    let live: HashSet<String> = branches
+        .iter()
+        .filter_map(|branch| branch_worker_name(&target.name, branch).ok())
+        .collect();
+    // the workers of environments are named like those of branches
+    let environments: HashSet<String> = manifest
+        .env
+        .iter()
+        .flat_map(|envs| envs.keys())
+        .map(|env| manifest.worker_name(Some(env.as_str())))
+        .collect();
+
+    let client = http::legacy_auth_client(user);
+    let scripts_endpoint = format!(
+        "https://api.cloudflare.com/client/v4/accounts/{}/workers/scripts",
+        target.account_id.load()?
+    );
+    let scripts: HashSet<String> = list_scripts(&client, &scripts_endpoint)?
+        .into_iter()
+        .collect();
+    if !dry_run {
+        // the workers that were deleted some other way
+        for script in published.keys().filter(|script| !scripts.contains(*script)) {
+            project::remove_preview_branch(env, script)?;
+        }
+    }
+    let stale: Vec<String> = published
+        .into_keys()
+        .filter(|script| scripts.contains(script))
+        .filter(|script| !live.contains(script) && !environments.contains(script))
+        .collect();
+
+    if stale.is_empty() {
+        StdOut::success("No stale preview environments");
+        return Ok(());
+    }
+    if dry_run {
+        for script in &stale {
+            println!("{}", script);
+        }
+        StdOut::info(&format!(
+            "{} stale preview environments would be deleted",
+            stale.len()
+        ));
+        return Ok(());
+    }
+    if !force {
+        match interactive::confirm(&format!(
+            "Are you sure you want to delete {} stale preview environments?",
+            stale.len()
+        )) {
+            Ok(true) => (),
+            Ok(false) => {
+                StdOut::info("Not deleting stale preview environments");
+                return Ok(());
+            }
+            Err(e) => anyhow::bail!(e),
+        }
+    }
+
+    for script in &stale {
+        let res = client
+            .delete(&format!("{}/{}", scripts_endpoint, script))
+            .send()?;
+        let status = res.status();
+        if !status.is_success() {
+            anyhow::bail!(
+                "Failed to delete {}: {}",
+                script,
+                crate::format_api_errors(res.text()?)
+            );
+        }
+        project::remove_preview_branch(env, script)?;
+        StdOut::info(&format!("Deleted {}", script));
+    }
+    StdOut::success(&format!(
+        "Deleted {} stale preview environments",
+        stale.len()
+    ));
+    Ok(())
+}
+
+/// The name of the worker of a branch, made of the characters a worker name can have.
+fn branch_worker_name(name: &str, branch: &str) -> Result<String> {
+    let mut slug = String::new();
+    for c in branch.to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        anyhow::bail!(
+            "The branch {} has no character a worker name can have",
+            branch
+        );
+    }
+
+    let mut worker_name = format!("{}-{}", name, slug);
+    if worker_name.len() > MAX_NAME_LEN {
+        // the branches whose names only differ after the cut get workers of their own
+        let hash = format!("{:x}", Sha256::digest(branch.as_bytes()));
+        worker_name.truncate(MAX_NAME_LEN - HASH_LEN - 1);
+        worker_name = format!(
+            "{}-{}",
+            worker_name.trim_end_matches('-'),
+            &hash[..HASH_LEN]
+        );
+    }
+    validate_worker_name(&worker_name)?;
+    Ok(worker_name)
+}
+
+/// The branches of the git remote `origin`.
+fn remote_branches() -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .args(&["ls-remote", "--heads", "origin"])
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Could not list the branches of the git remote origin: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split('\t').nth(1))
+        .filter_map(|reference| reference.strip_prefix("refs/heads/"))
+        .map(str::to_string)
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct ListScriptsResponse {
+    result: Vec<Script>,
+}
+
+#[derive(Deserialize)]
+struct Script {
+    id: String,
+}
+
+fn list_scripts(client: &Client, scripts_endpoint: &str) -> Result<Vec<String>> {
+    let res = client.get(scripts_endpoint).send()?;
+    let status = res.status();
+    if !status.is_success() {
+        anyhow::bail!(
+            "Failed to list the workers of the account: {}",
+            crate::format_api_errors(res.text()?)
+        );
+    }
+    let scripts: ListScriptsResponse = res.json()?;
+    Ok(scripts.result.into_iter().map(|script| script.id).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_names_the_workers_of_branches() {
+        let name = |branch| branch_worker_name("my-worker", branch).unwrap();
+        assert_eq!(name("feature-x"), "my-worker-feature-x");
+        assert_eq!(name("Feature/Login_Page"), "my-worker-feature-login-page");
+        assert_eq!(name("fix--double//"), "my-worker-fix-double");
+        let long = "a".repeat(80);
+        assert_eq!(name(&long).len(), MAX_NAME_LEN);
+        assert_ne!(name(&format!("{}-1", long)), name(&format!("{}-2", long)));
+        assert_eq!(name(&long), name(&long));
+        assert!(branch_worker_name("my-worker", "/").is_err());
+    }
+}
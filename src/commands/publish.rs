@@ -6,7 +6,7 @@ use indicatif::{ProgressBar, ProgressStyle};
 use reqwest::blocking::Client;
 use serde::{Deserialize, Serialize};
 
-use crate::build::build_target;
+use crate::build::{build_target, reproducible};
 use crate::deploy::{self, DeploymentSet};
 use crate::http::{self, Feature};
 use crate::kv::bulk;
@@ -24,6 +24,9 @@ pub struct PublishOutput {
     pub name: String,
     pub urls: Vec<String>,
     pub schedules: Vec<String>,
+    /// The hash of what was uploaded, with `--reproducible`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
 }
 
 pub fn publish(
@@ -34,13 +37,14 @@ pub fn publish(
 ) -> Result<()> {
     validate_target_required_fields_present(target)?;
 
-    let run_deploy = |target: &Target| match deploy::deploy(user, &deployments) {
-        Ok(results) => {
-            build_output_message(results, target.name.clone(), out);
-            Ok(())
-        }
-        Err(e) => Err(e),
-    };
+    let run_deploy =
+        |target: &Target, content_hash: Option<String>| match deploy::deploy(user, &deployments) {
+            Ok(results) => {
+                build_output_message(results, target.name.clone(), content_hash, out);
+                Ok(())
+            }
+            Err(e) => Err(e),
+        };
 
     // Build the script before uploading and log build result
     let build_result = build_target(target);
@@ -56,6 +60,14 @@ pub fn publish(
         build_config.verify_upload_dir()?;
     }
 
+    let content_hash = if reproducible::is_reproducible() {
+        let hash = reproducible::content_hash(target)?;
+        StdErr::info(&format!("Content hash: {}", hash));
+        Some(hash)
+    } else {
+        None
+    };
+
     if target.migrations.is_some() {
         // Can't do this in the if below, since that one takes a mutable borrow on target
         let client = http::legacy_auth_client(user);
@@ -70,15 +82,27 @@ pub fn publish(
     if let Some(site_config) = &target.site {
         let path = &site_config.bucket.clone();
         validate_bucket_location(path)?;
+        sites::worker::warn_if_settings_are_ignored(site_config)?;
+        let fallback_page = sites::SiteSettings::new(site_config)?.fallback_page();
+        let asset_map = site_config.asset_map.clone();
 
         let site_namespace = sites::add_namespace(user, target, false)?;
 
         let (to_upload, to_delete, asset_manifest) =
             sites::sync(target, user, &site_namespace.id, path)?;
+        if let Some(page) = fallback_page {
+            if !asset_manifest.contains_key(page) {
+                StdErr::warn(&format!(
+                    "The not_found_handling of [site] serves {}, which isn't in {}",
+                    page,
+                    path.display()
+                ));
+            }
+        }
 
-        // First, upload all existing files in bucket directory
-        StdErr::working("Uploading site files");
-        let upload_progress_bar = if to_upload.len() > bulk::BATCH_KEY_MAX {
+        // First, upload the files of the bucket directory that are new or changed
+        let upload_progress_bar = if !to_upload.is_empty() {
+            StdErr::working("Uploading site files");
             let upload_progress_bar = ProgressBar::new(to_upload.len() as u64);
             upload_progress_bar
                 .set_style(ProgressStyle::default_bar().template("{wide_bar} {pos}/{len}\n{msg}"));
@@ -87,7 +111,7 @@ pub fn publish(
             None
         };
 
-        bulk::put(
+        sites::upload(
             target,
             user,
             &site_namespace.id,
@@ -100,11 +124,25 @@ pub fn publish(
         }
 
         let upload_client = http::featured_legacy_auth_client(user, Feature::Sites);
+        let deployed_manifest = sites::deployed::new(path, &asset_manifest)?;
 
         // Next, upload and deploy the worker with the updated asset_manifest
         upload::script(&upload_client, target, Some(asset_manifest))?;
 
-        run_deploy(target)?;
+        run_deploy(target, content_hash)?;
+
+        if let Some(asset_map) = &asset_map {
+            sites::deployed::write_asset_map(asset_map, &deployed_manifest)?;
+            StdErr::info(&format!("Wrote the asset map to {}", asset_map.display()));
+        }
+
+        // the site is deployed even if it isn't recorded, which only `wrangler sites` needs
+        if let Err(e) = sites::deployed::put(target, user, &site_namespace.id, &deployed_manifest) {
+            StdErr::warn(&format!(
+                "Failed to record the deployed assets of the site: {}",
+                e
+            ));
+        }
 
         // Finally, remove any stale files
         if !to_delete.is_empty() {
@@ -136,13 +174,18 @@ pub fn publish(
         let upload_client = http::legacy_auth_client(user);
 
         upload::script(&upload_client, target, None)?;
-        run_deploy(target)?;
+        run_deploy(target, content_hash)?;
     }
 
     Ok(())
 }
 
-fn build_output_message(deploy_results: deploy::DeployResults, target_name: String, out: Output) {
+fn build_output_message(
+    deploy_results: deploy::DeployResults,
+    target_name: String,
+    content_hash: Option<String>,
+    out: Output,
+) {
     let deploy::DeployResults { urls, schedules } = deploy_results;
 
     let mut msg = "Successfully published your script ".to_owned();
@@ -160,6 +203,7 @@ fn build_output_message(deploy_results: deploy::DeployResults, target_name: Stri
             name: target_name,
             urls,
             schedules,
+            content_hash,
         });
     }
 }
@@ -220,6 +264,16 @@ fn validate_target_required_fields_present(target: &Target) -> Result<()> {
         }
     }
 
+    for service in &target.services {
+        if service.binding.is_empty() {
+            missing_fields.push("service binding")
+        }
+
+        if service.service.is_empty() {
+            missing_fields.push("service name")
+        }
+    }
+
     let (field_pluralization, is_are) = match missing_fields.len() {
         n if n >= 2 => ("fields", "are"),
         1 => ("field", "is"),
@@ -1,19 +1,34 @@
 use anyhow::Result;
-use cloudflare::endpoints::workers::{DeleteRoute, ListRoutes};
+use cloudflare::endpoints::workers::{DeleteRoute, ListRoutes, WorkersRoute};
 use cloudflare::framework::apiclient::ApiClient;
+use serde::Serialize;
 
+use crate::commands::whoami::fetch_zones_cached;
 use crate::http;
 use crate::settings::global_user::GlobalUser;
-use crate::terminal::message::{Message, StdOut};
+use crate::terminal::message::{Message, StdErr, StdOut};
 
-pub fn list(zone_identifier: &str, user: &GlobalUser) -> Result<()> {
+/// A route of `route list --all-zones`, with the zone it belongs to.
+#[derive(Serialize)]
+struct ZoneRoute {
+    zone_id: String,
+    zone_name: String,
+    #[serde(flatten)]
+    route: WorkersRoute,
+}
+
+pub fn list(zone_identifier: &str, user: &GlobalUser, script: Option<&str>) -> Result<()> {
     let client = http::cf_v4_client(user)?;
 
     let result = client.request(&ListRoutes { zone_identifier });
 
     match result {
         Ok(success) => {
-            let routes = success.result;
+            let routes: Vec<WorkersRoute> = success
+                .result
+                .into_iter()
+                .filter(|route| script.map_or(true, |script| points_at(route, script)))
+                .collect();
             println!("{}", serde_json::to_string(&routes)?);
         }
 
@@ -22,6 +37,48 @@ pub fn list(zone_identifier: &str, user: &GlobalUser) -> Result<()> {
     Ok(())
 }
 
+/// Lists the routes pointing at a worker in every zone the user can access, or at `script` only.
+/// The zones whose routes can't be listed are skipped with a warning.
+pub fn list_all_zones(user: &GlobalUser, script: Option<&str>) -> Result<()> {
+    let client = http::cf_v4_client(user)?;
+    let zones = fetch_zones_cached(user)?;
+    StdErr::working(&format!("Listing the routes of {} zones", zones.len()));
+
+    let mut routes = Vec::new();
+    for zone in zones {
+        let result = client.request(&ListRoutes {
+            zone_identifier: &zone.id,
+        });
+        match result {
+            Ok(success) => routes.extend(
+                success
+                    .result
+                    .into_iter()
+                    .filter(|route| match script {
+                        Some(script) => points_at(route, script),
+                        None => route.script.is_some(),
+                    })
+                    .map(|route| ZoneRoute {
+                        zone_id: zone.id.clone(),
+                        zone_name: zone.name.clone(),
+                        route,
+                    }),
+            ),
+            Err(e) => StdErr::warn(&format!(
+                "Could not list the routes of {}: {}",
+                zone.name,
+                http::format_error(e, None)
+            )),
+        }
+    }
+    println!("{}", serde_json::to_string(&routes)?);
+    Ok(())
+}
+
+fn points_at(route: &WorkersRoute, script: &str) -> bool {
+    route.script.as_deref() == Some(script)
+}
+
 pub fn delete(zone_identifier: &str, user: &GlobalUser, route_id: &str) -> Result<()> {
     let client = http::cf_v4_client(user)?;
 
@@ -106,7 +106,8 @@ pub fn create_secret(name: &str, user: &GlobalUser, target: &Target) -> Result<(
     Ok(())
 }
 
-pub fn delete_secret(name: &str, user: &GlobalUser, target: &Target) -> Result<()> {
+/// Deletes a secret once the user confirms it, returning whether it was deleted.
+pub fn delete_secret(name: &str, user: &GlobalUser, target: &Target) -> Result<bool> {
     match interactive::confirm(&format!(
         "Are you sure you want to permanently delete the variable {} on the script named {}?",
         name, target.name
@@ -114,7 +115,7 @@ pub fn delete_secret(name: &str, user: &GlobalUser, target: &Target) -> Result<(
         Ok(true) => (),
         Ok(false) => {
             StdOut::info(&format!("Not deleting secret {}.", name));
-            return Ok(());
+            return Ok(false);
         }
         Err(e) => anyhow::bail!(e),
     }
@@ -137,7 +138,26 @@ pub fn delete_secret(name: &str, user: &GlobalUser, target: &Target) -> Result<(
         Err(e) => anyhow::bail!(format_error(e)),
     }
 
-    Ok(())
+    Ok(true)
+}
+
+/// The names of the secrets of a script.
+pub fn secret_names(user: &GlobalUser, target: &Target) -> Result<Vec<String>> {
+    let client = http::cf_v4_client(user)?;
+
+    let response = client.request(&ListSecrets {
+        account_identifier: target.account_id.load()?,
+        script_name: &target.name,
+    });
+
+    match response {
+        Ok(success) => Ok(success
+            .result
+            .into_iter()
+            .map(|secret| secret.name)
+            .collect()),
+        Err(e) => anyhow::bail!(format_error(e)),
+    }
 }
 
 pub fn list_secrets(user: &GlobalUser, target: &Target) -> Result<()> {
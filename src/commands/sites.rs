@@ -0,0 +1,101 @@
+use anyhow::Result;
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::kv::bulk;
+use crate::settings::global_user::GlobalUser;
+use crate::settings::toml::Target;
+use crate::sites::{self, deployed};
+use crate::terminal::interactive;
+use crate::terminal::message::{Message, StdOut};
+
+/// Prints the assets of the last publish of the site, with their key, size and hash.
+pub fn manifest(target: &Target, user: &GlobalUser) -> Result<()> {
+    let namespace_id = sites::namespace_id(user, target)?;
+    let deployed = read_deployed(target, user, &namespace_id)?;
+
+    for (path, asset) in &deployed {
+        println!(
+            "{} -> {} ({} bytes, hash {})",
+            path,
+            asset.key,
+            asset.size,
+            asset.hash.as_deref().unwrap_or("unknown")
+        );
+        for variant in &asset.variants {
+            println!("  {}", variant);
+        }
+    }
+    let total: u64 = deployed.values().map(|asset| asset.size).sum();
+    StdOut::info(&format!("{} assets, {} bytes", deployed.len(), total));
+    Ok(())
+}
+
+/// Deletes the keys of the namespace of the site that the last publish doesn't serve.
+pub fn prune(target: &Target, user: &GlobalUser, dry_run: bool, force: bool) -> Result<()> {
+    let namespace_id = sites::namespace_id(user, target)?;
+    let deployed = read_deployed(target, user, &namespace_id)?;
+    let served = deployed::keys(&deployed);
+
+    let mut orphans: Vec<String> = sites::remote_keys(target, user, &namespace_id)?
+        .into_iter()
+        .filter(|key| !served.contains(key))
+        .collect();
+    orphans.sort();
+
+    if orphans.is_empty() {
+        StdOut::success("No orphaned site assets");
+        return Ok(());
+    }
+    if dry_run {
+        for key in &orphans {
+            println!("{}", key);
+        }
+        StdOut::info(&format!(
+            "{} orphaned site assets would be deleted",
+            orphans.len()
+        ));
+        return Ok(());
+    }
+    if !force {
+        match interactive::confirm(&format!(
+            "Are you sure you want to delete {} orphaned site assets?",
+            orphans.len()
+        )) {
+            Ok(true) => (),
+            Ok(false) => {
+                StdOut::info("Not deleting orphaned site assets");
+                return Ok(());
+            }
+            Err(e) => anyhow::bail!(e),
+        }
+    }
+
+    let len = orphans.len();
+    StdOut::working(&format!("Deleting {} orphaned site assets", len));
+    let progress_bar = if len > bulk::BATCH_KEY_MAX {
+        let pb = ProgressBar::new(len as u64);
+        pb.set_style(ProgressStyle::default_bar().template("{wide_bar} {pos}/{len}\n{msg}"));
+        Some(pb)
+    } else {
+        None
+    };
+    bulk::delete(target, user, &namespace_id, orphans, &progress_bar)?;
+    if let Some(pb) = &progress_bar {
+        pb.finish_with_message("Done deleting");
+    }
+    StdOut::success("Success");
+    Ok(())
+}
+
+fn read_deployed(
+    target: &Target,
+    user: &GlobalUser,
+    namespace_id: &str,
+) -> Result<deployed::DeployedManifest> {
+    deployed::get(target, user, namespace_id)?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "The assets of {} were published without being recorded. Run `wrangler publish` to record them",
+            target.name
+        )
+    })
+}
@@ -7,6 +7,7 @@ use cloudflare::{
     framework::{async_api::ApiClient, response::ApiFailure},
 };
 use reqwest::StatusCode;
+use serde::Deserialize;
 use url::Url;
 
 /// A tail captures `TraceEvent`s from a published Worker.
@@ -101,3 +102,30 @@ impl Tail {
         }
     }
 }
+
+#[derive(Debug, Deserialize)]
+struct ListScriptsResponse {
+    result: Vec<ScriptItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScriptItem {
+    id: String,
+}
+
+/// Lists the names of every Worker on an account, so they can all be tailed at once.
+pub fn list_scripts(user: &GlobalUser, account_id: &str) -> Result<Vec<String>> {
+    let addr = format!(
+        "https://api.cloudflare.com/client/v4/accounts/{}/workers/scripts",
+        account_id
+    );
+    let response = http::legacy_auth_client(user).get(&addr).send()?;
+    if !response.status().is_success() {
+        anyhow::bail!(
+            "Failed to list workers: {}",
+            crate::format_api_errors(response.text()?)
+        )
+    }
+    let scripts: ListScriptsResponse = response.json()?;
+    Ok(scripts.result.into_iter().map(|script| script.id).collect())
+}
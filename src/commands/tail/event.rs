@@ -1,8 +1,11 @@
-use chrono::{Local, TimeZone};
-use console::style;
+use super::pretty::PrettyFormat;
+
+use console::{style, Style, StyledObject};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter, Result};
+use std::str::FromStr;
 
 /// A unique protocol ID that is passed by the `Sec-WebSocket-Protocol` header.
 ///
@@ -16,9 +19,14 @@ pub const PROTOCOL_ID: &str = "trace-v1";
 /// Not every field is shown here, only the ones necessary for Display.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TraceEvent {
+    #[serde(rename = "scriptName")]
+    pub script_name: Option<String>,
     #[serde(alias = "eventTimestamp")]
     pub timestamp: i64,
     pub outcome: String,
+    /// How long the event took, in milliseconds, if reported by the runtime.
+    #[serde(rename = "wallTime")]
+    pub wall_time: Option<f64>,
     pub logs: Vec<LogItem>,
     pub exceptions: Vec<ExceptionItem>,
     pub event: EventItem,
@@ -28,11 +36,70 @@ pub struct TraceEvent {
 ///
 /// * If `request` is present, it's an fetch event.
 /// * If `cron` is present, it's a scheduled event.
+/// * If `queue` is present, it's a queue event.
+/// * If only `scheduled_time` is present, it's an alarm event.
 /// * Otherwise, the event type is unknown.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EventItem {
     pub request: Option<RequestItem>,
+    pub response: Option<ResponseItem>,
     pub cron: Option<String>,
+    pub queue: Option<String>,
+    #[serde(rename = "scheduledTime")]
+    pub scheduled_time: Option<f64>,
+}
+
+/// The type of event that invoked a Worker.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventType {
+    Fetch,
+    Scheduled,
+    Alarm,
+    Queue,
+}
+
+impl FromStr for EventType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "fetch" => Ok(EventType::Fetch),
+            "scheduled" => Ok(EventType::Scheduled),
+            "alarm" => Ok(EventType::Alarm),
+            "queue" => Ok(EventType::Queue),
+            _ => anyhow::bail!("{} is not a valid event type", s),
+        }
+    }
+}
+
+impl Display for EventType {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        let name = match self {
+            EventType::Fetch => "fetch",
+            EventType::Scheduled => "scheduled",
+            EventType::Alarm => "alarm",
+            EventType::Queue => "queue",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl EventItem {
+    /// Infers the type of event, if known.
+    pub fn event_type(&self) -> Option<EventType> {
+        if self.request.is_some() {
+            Some(EventType::Fetch)
+        } else if self.cron.is_some() {
+            Some(EventType::Scheduled)
+        } else if self.queue.is_some() {
+            Some(EventType::Queue)
+        } else if self.scheduled_time.is_some() {
+            Some(EventType::Alarm)
+        } else {
+            None
+        }
+    }
 }
 
 /// A request item.
@@ -40,9 +107,17 @@ pub struct EventItem {
 pub struct RequestItem {
     pub url: String,
     pub method: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
     pub cf: Option<CfMetadata>,
 }
 
+/// A response item, only present if the Worker responded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseItem {
+    pub status: u16,
+}
+
 /// Cloudflare metadata about an event.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CfMetadata {
@@ -61,68 +136,67 @@ pub struct LogItem {
 pub struct ExceptionItem {
     pub name: String,
     pub message: String,
-    // TODO(soon): we really need to implement stacktraces.
+    pub stack: Option<String>,
 }
 
 impl Display for TraceEvent {
     fn fmt(&self, f: &mut Formatter) -> Result {
-        let timestamp = style(
-            Local
-                .timestamp_millis(self.timestamp)
-                .format("%Y-%m-%d %H:%M:%S"),
-        )
-        .dim();
-        let outcome = match self.outcome.as_ref() {
-            "ok" => style("Ok").green(),
-            "canceled" => style("Canceled").yellow(),
-            "exception" => style("Error").red(),
-            "exceededCpu" => style("Exceeded Limit").red(),
-            _ => style("System Error").red(),
-        };
-        match self.event.request.clone() {
-            Some(request) => {
-                let colo = style(
-                    request
-                        .cf
-                        .map(|cf| cf.colo)
-                        .unwrap_or_else(|| "?".to_owned()),
-                )
-                .dim();
-                let method = style(request.method);
-                let url = style(request.url).bold();
-                write!(
-                    f,
-                    "[{}] [{}] [{}] {} {}",
-                    timestamp, colo, outcome, method, url
-                )
-            }
-            _ => match self.event.cron.clone() {
-                // TODO(soon): add colo to scheduled event.
-                Some(cron) => write!(f, "[{}] [?] [{}] {}", timestamp, outcome, cron),
-                _ => write!(f, "[{}] [?] [{}] <unknown event>", timestamp, outcome),
-            },
-        }?;
-        for log in self.logs.iter() {
-            let prefix = style("|").dim();
-            write!(f, "\n {} {}", prefix, log)?;
-        }
-        for err in self.exceptions.iter() {
-            let prefix = style("!").dim();
-            write!(f, "\n {} {}", prefix, err)?;
+        write!(f, "{}", PrettyFormat::default().render(self))
+    }
+}
+
+impl TraceEvent {
+    /// Tests if the event had an exception or an error outcome.
+    pub fn is_error(&self) -> bool {
+        !self.exceptions.is_empty() || !matches!(self.outcome.as_ref(), "ok" | "canceled")
+    }
+
+    /// The ray ID of the request that invoked the Worker, if any.
+    pub fn ray_id(&self) -> Option<&str> {
+        self.event.request.as_ref().and_then(|request| {
+            request
+                .headers
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("cf-ray"))
+                .map(|(_, value)| value.as_str())
+        })
+    }
+
+    /// The color of the event's outcome.
+    pub fn outcome_style(&self) -> Style {
+        match self.outcome.as_ref() {
+            "ok" => Style::new().green(),
+            "canceled" => Style::new().yellow(),
+            _ => Style::new().red(),
         }
-        Ok(())
+    }
+
+    /// The styled label of the event's outcome.
+    pub fn outcome_label(&self) -> StyledObject<&'static str> {
+        let label = match self.outcome.as_ref() {
+            "ok" => "Ok",
+            "canceled" => "Canceled",
+            "exception" => "Error",
+            "exceededCpu" => "Exceeded Limit",
+            _ => "System Error",
+        };
+        self.outcome_style().apply_to(label)
     }
 }
 
-impl Display for LogItem {
-    fn fmt(&self, f: &mut Formatter) -> Result {
-        let level = match self.level.as_ref() {
+impl LogItem {
+    /// The styled label of the log's level.
+    pub fn level_label(&self) -> StyledObject<&'static str> {
+        match self.level.as_ref() {
             "debug" => style("Debug").blue(),
             "warn" => style("Warn").yellow(),
             "error" => style("Error").red(),
             _ => style("Info").dim(),
-        };
-        write!(f, "[{}] ", level)?;
+        }
+    }
+
+    /// The log's message, formatted similar to how it's done in DevTools.
+    pub fn text(&self) -> String {
         match &self.message {
             // Most console.log() messages are formatted as an array.
             // e.g.
@@ -137,19 +211,25 @@ impl Display for LogItem {
             // suprisingly unreadable. Instead, we only handle the simple case where the
             // top-level is an array and its values are strings.
             Value::Array(values) => {
+                let mut text = String::new();
                 for value in values.iter() {
                     match value {
-                        Value::String(s) => write!(f, "{}", s),
-                        v => write!(f, "{}", v),
-                    }?;
-                    write!(f, " ")?;
+                        Value::String(s) => text.push_str(s),
+                        v => text.push_str(&v.to_string()),
+                    };
+                    text.push(' ');
                 }
-                Ok(())
+                text
             }
-            Value::String(v) => write!(f, "{}", v),
-            v => write!(f, "{}", v),
-        }?;
-        Ok(())
+            Value::String(v) => v.clone(),
+            v => v.to_string(),
+        }
+    }
+}
+
+impl Display for LogItem {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "[{}] {}", self.level_label(), self.text())
     }
 }
 
@@ -0,0 +1,466 @@
+use super::event::TraceEvent;
+
+use anyhow::Result;
+use regex::Regex;
+use std::iter::Peekable;
+use std::str::{Chars, FromStr};
+
+/// A filter expression evaluated by wrangler against each `TraceEvent`.
+///
+/// Unlike a `TraceFilter`, which is sent to the tail, an expression can combine conditions
+/// with `&&`, `||`, `!` and parentheses. e.g.
+///
+/// ```text
+/// status >= 500 && url ~ "/api/"
+/// !(method == GET) || header.user-agent ~ "curl"
+/// log ~ "timeout" || exception
+/// ```
+///
+/// `~` and `!~` match a regular expression, and a field on its own tests that it is present.
+#[derive(Debug, Clone)]
+pub enum Expression {
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    Compare(Field, Operator, Literal),
+    Present(Field),
+}
+
+/// A field of a `TraceEvent` that can be used in an `Expression`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Field {
+    Status,
+    Method,
+    Url,
+    Outcome,
+    Type,
+    Script,
+    Colo,
+    Cron,
+    Queue,
+    Log,
+    Exception,
+    Header(String),
+}
+
+/// A comparison between a field and a literal.
+#[derive(Debug, Clone)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Matches(Regex),
+    NotMatches(Regex),
+}
+
+/// A literal value on the right side of a comparison.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Number(f64),
+    String(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    String(String),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Matches,
+    NotMatches,
+}
+
+impl FromStr for Field {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "status" => Ok(Field::Status),
+            "method" => Ok(Field::Method),
+            "url" => Ok(Field::Url),
+            "outcome" => Ok(Field::Outcome),
+            "type" => Ok(Field::Type),
+            "script" => Ok(Field::Script),
+            "colo" => Ok(Field::Colo),
+            "cron" => Ok(Field::Cron),
+            "queue" => Ok(Field::Queue),
+            "log" => Ok(Field::Log),
+            "exception" => Ok(Field::Exception),
+            _ => match s.strip_prefix("header.") {
+                Some(name) if !name.is_empty() => Ok(Field::Header(name.to_lowercase())),
+                _ => anyhow::bail!(
+                    "Unknown field `{}`, expected one of status, method, url, outcome, type, \
+                     script, colo, cron, queue, log, exception or header.<name>",
+                    s
+                ),
+            },
+        }
+    }
+}
+
+impl Field {
+    /// The values of the field for an event, which may be several for logs and exceptions.
+    fn values(&self, event: &TraceEvent) -> Vec<String> {
+        let request = event.event.request.as_ref();
+        let values = match self {
+            Field::Status => event
+                .event
+                .response
+                .as_ref()
+                .map(|response| response.status.to_string()),
+            Field::Method => request.map(|request| request.method.clone()),
+            Field::Url => request.map(|request| request.url.clone()),
+            Field::Outcome => Some(event.outcome.clone()),
+            Field::Type => event.event.event_type().map(|t| t.to_string()),
+            Field::Script => event.script_name.clone(),
+            Field::Colo => request
+                .and_then(|request| request.cf.as_ref())
+                .map(|cf| cf.colo.clone()),
+            Field::Cron => event.event.cron.clone(),
+            Field::Queue => event.event.queue.clone(),
+            Field::Log => return event.logs.iter().map(|log| log.text()).collect(),
+            Field::Exception => {
+                return event
+                    .exceptions
+                    .iter()
+                    .map(|exception| format!("{}: {}", exception.name, exception.message))
+                    .collect()
+            }
+            Field::Header(name) => request.and_then(|request| {
+                request
+                    .headers
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                    .map(|(_, value)| value.clone())
+            }),
+        };
+        values.into_iter().collect()
+    }
+}
+
+impl Operator {
+    /// Tests a single value of a field against a literal.
+    fn test(&self, value: &str, literal: &Literal) -> bool {
+        let number = value.trim().parse::<f64>().ok();
+        let ordering = match (number, literal) {
+            (Some(number), Literal::Number(literal)) => number.partial_cmp(literal),
+            (_, Literal::String(literal)) => Some(value.cmp(literal.as_str())),
+            (None, Literal::Number(_)) => None,
+        };
+        match self {
+            Operator::Equal => ordering == Some(std::cmp::Ordering::Equal),
+            Operator::NotEqual => ordering != Some(std::cmp::Ordering::Equal),
+            Operator::Less => ordering == Some(std::cmp::Ordering::Less),
+            Operator::LessOrEqual => {
+                matches!(ordering, Some(o) if o != std::cmp::Ordering::Greater)
+            }
+            Operator::Greater => ordering == Some(std::cmp::Ordering::Greater),
+            Operator::GreaterOrEqual => {
+                matches!(ordering, Some(o) if o != std::cmp::Ordering::Less)
+            }
+            Operator::Matches(regex) => regex.is_match(value),
+            Operator::NotMatches(regex) => !regex.is_match(value),
+        }
+    }
+
+    /// Negative operators must hold for every value, the others for any value.
+    fn is_negative(&self) -> bool {
+        matches!(self, Operator::NotEqual | Operator::NotMatches(_))
+    }
+}
+
+impl Expression {
+    /// Tests if an event matches the expression.
+    pub fn matches(&self, event: &TraceEvent) -> bool {
+        match self {
+            Expression::And(left, right) => left.matches(event) && right.matches(event),
+            Expression::Or(left, right) => left.matches(event) || right.matches(event),
+            Expression::Not(expression) => !expression.matches(event),
+            Expression::Present(field) => !field.values(event).is_empty(),
+            Expression::Compare(field, operator, literal) => {
+                let values = field.values(event);
+                if operator.is_negative() {
+                    values.iter().all(|value| operator.test(value, literal))
+                } else {
+                    values.iter().any(|value| operator.test(value, literal))
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Expression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+        let expression = parser.or()?;
+        match parser.peek() {
+            None => Ok(expression),
+            Some(token) => anyhow::bail!("Unexpected {:?} in filter expression", token),
+        }
+    }
+}
+
+/// Splits an expression into tokens.
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        let token = match c {
+            ' ' | '\t' | '\n' => {
+                chars.next();
+                continue;
+            }
+            '(' => single(&mut chars, Token::Open),
+            ')' => single(&mut chars, Token::Close),
+            '~' => single(&mut chars, Token::Matches),
+            '&' => double(&mut chars, '&', Token::And)?,
+            '|' => double(&mut chars, '|', Token::Or)?,
+            '=' => double(&mut chars, '=', Token::Equal)?,
+            '!' => {
+                chars.next();
+                match chars.peek() {
+                    Some('=') => single(&mut chars, Token::NotEqual),
+                    Some('~') => single(&mut chars, Token::NotMatches),
+                    _ => Token::Not,
+                }
+            }
+            '<' | '>' => {
+                chars.next();
+                let or_equal = chars.peek() == Some(&'=');
+                if or_equal {
+                    chars.next();
+                }
+                match (c, or_equal) {
+                    ('<', false) => Token::Less,
+                    ('<', true) => Token::LessOrEqual,
+                    ('>', false) => Token::Greater,
+                    _ => Token::GreaterOrEqual,
+                }
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => match chars.next() {
+                            Some(escaped) => string.push(escaped),
+                            None => anyhow::bail!("Unterminated string in filter expression"),
+                        },
+                        Some(end) if end == c => break,
+                        Some(other) => string.push(other),
+                        None => anyhow::bail!("Unterminated string in filter expression"),
+                    }
+                }
+                Token::String(string)
+            }
+            c if c.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_ascii_digit() || c == '.' {
+                        number.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                Token::Number(number.parse()?)
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut ident = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_alphanumeric() || matches!(c, '_' | '-' | '.') {
+                        ident.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                Token::Ident(ident)
+            }
+            _ => anyhow::bail!("Unexpected `{}` in filter expression", c),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn single(chars: &mut Peekable<Chars>, token: Token) -> Token {
+    chars.next();
+    token
+}
+
+fn double(chars: &mut Peekable<Chars>, expected: char, token: Token) -> Result<Token> {
+    chars.next();
+    match chars.next() {
+        Some(c) if c == expected => Ok(token),
+        _ => anyhow::bail!("Expected `{}{}` in filter expression", expected, expected),
+    }
+}
+
+/// A recursive descent parser, where `&&` binds tighter than `||`.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Expression> {
+        let mut expression = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            expression = Expression::Or(Box::new(expression), Box::new(self.and()?));
+        }
+        Ok(expression)
+    }
+
+    fn and(&mut self) -> Result<Expression> {
+        let mut expression = self.unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            expression = Expression::And(Box::new(expression), Box::new(self.unary()?));
+        }
+        Ok(expression)
+    }
+
+    fn unary(&mut self) -> Result<Expression> {
+        match self.next() {
+            Some(Token::Not) => Ok(Expression::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let expression = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expression),
+                    _ => anyhow::bail!("Expected `)` in filter expression"),
+                }
+            }
+            Some(Token::Ident(ident)) => self.comparison(ident.parse()?),
+            Some(token) => anyhow::bail!("Expected a field but found {:?}", token),
+            None => anyhow::bail!("Unexpected end of filter expression"),
+        }
+    }
+
+    fn comparison(&mut self, field: Field) -> Result<Expression> {
+        let operator = match self.peek() {
+            Some(Token::Equal) => Operator::Equal,
+            Some(Token::NotEqual) => Operator::NotEqual,
+            Some(Token::Less) => Operator::Less,
+            Some(Token::LessOrEqual) => Operator::LessOrEqual,
+            Some(Token::Greater) => Operator::Greater,
+            Some(Token::GreaterOrEqual) => Operator::GreaterOrEqual,
+            Some(Token::Matches) | Some(Token::NotMatches) => {
+                let negated = self.next() == Some(Token::NotMatches);
+                let regex = match self.next() {
+                    Some(Token::String(pattern)) | Some(Token::Ident(pattern)) => {
+                        Regex::new(&pattern)?
+                    }
+                    _ => anyhow::bail!("Expected a pattern after `~` in filter expression"),
+                };
+                let operator = if negated {
+                    Operator::NotMatches(regex)
+                } else {
+                    Operator::Matches(regex)
+                };
+                return Ok(Expression::Compare(
+                    field,
+                    operator,
+                    Literal::String(String::new()),
+                ));
+            }
+            _ => return Ok(Expression::Present(field)),
+        };
+        self.next();
+        let literal = match self.next() {
+            Some(Token::Number(number)) => Literal::Number(number),
+            Some(Token::String(string)) | Some(Token::Ident(string)) => Literal::String(string),
+            _ => anyhow::bail!("Expected a value after {:?} in filter expression", operator),
+        };
+        Ok(Expression::Compare(field, operator, literal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event() -> TraceEvent {
+        serde_json::from_str(
+            r#"{
+                "scriptName": "worker",
+                "timestamp": 0,
+                "outcome": "ok",
+                "logs": [{"level": "log", "message": ["request timed out"]}],
+                "exceptions": [],
+                "event": {
+                    "request": {
+                        "url": "https://example.com/api/users",
+                        "method": "POST",
+                        "headers": {"User-Agent": "curl/7.79.1"}
+                    },
+                    "response": {"status": 503}
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn matches(expression: &str) -> bool {
+        expression.parse::<Expression>().unwrap().matches(&event())
+    }
+
+    #[test]
+    fn it_compares_fields() {
+        assert!(matches(r#"status >= 500 && url ~ "/api/""#));
+        assert!(matches("method == POST"));
+        assert!(matches("status != 200"));
+        assert!(!matches("status < 500"));
+        assert!(matches(r#"header.user-agent ~ "^curl""#));
+        assert!(matches(r#"log ~ 'timed out'"#));
+        assert!(!matches("exception"));
+        assert!(matches("!exception && type == fetch"));
+    }
+
+    #[test]
+    fn it_respects_precedence() {
+        assert!(matches("method == GET && status == 200 || status == 503"));
+        assert!(!matches(
+            "method == GET && (status == 200 || status == 503)"
+        ));
+    }
+
+    #[test]
+    fn it_rejects_invalid_expressions() {
+        assert!("bogus == 1".parse::<Expression>().is_err());
+        assert!("status >=".parse::<Expression>().is_err());
+        assert!("(status == 200".parse::<Expression>().is_err());
+        assert!("status = 200".parse::<Expression>().is_err());
+        assert!(r#"url ~ "unterminated"#.parse::<Expression>().is_err());
+    }
+}
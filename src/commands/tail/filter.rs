@@ -6,7 +6,27 @@ use std::convert::From;
 ///
 /// wrangler does not handle any of the filtering,
 /// it only uploads them to the `WebSocketTail`.
-pub trait TraceFilter: erased_serde::Serialize {}
+pub trait TraceFilter: erased_serde::Serialize + TraceFilterClone {}
+
+/// Allows boxed filters to be cloned, so each tail can be sent its own copy.
+pub trait TraceFilterClone {
+    fn clone_box(&self) -> Box<dyn TraceFilter>;
+}
+
+impl<T> TraceFilterClone for T
+where
+    T: 'static + TraceFilter + Clone,
+{
+    fn clone_box(&self) -> Box<dyn TraceFilter> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn TraceFilter> {
+    fn clone(&self) -> Box<dyn TraceFilter> {
+        self.clone_box()
+    }
+}
 
 #[derive(Debug, Clone, Serialize)]
 pub struct OutcomeFilter {
@@ -0,0 +1,202 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use serde_json::Value;
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use url::Url;
+
+use crate::http::feature::headers;
+
+/// Where, and how, to relay `TraceEvent`s received by a tail.
+#[derive(Debug, Clone)]
+pub struct ForwardOptions {
+    pub url: Option<Url>,
+    pub syslog: Option<String>,
+    pub headers: Vec<(String, String)>,
+    pub batch_size: usize,
+    pub batch_interval: Duration,
+}
+
+/// Relays tail events to an external collector in the background.
+///
+/// Events are batched and sent as a JSON array to an HTTP endpoint and/or
+/// as one RFC 5424 message per event to a syslog server over UDP.
+#[derive(Debug, Clone)]
+pub struct Forwarder {
+    sender: mpsc::UnboundedSender<Value>,
+}
+
+impl Forwarder {
+    /// Starts forwarding; the returned task finishes once every `Forwarder` is dropped
+    /// and the last batch has been flushed.
+    pub fn spawn(options: ForwardOptions) -> Result<(Self, JoinHandle<()>)> {
+        let sink = Sink::new(&options)?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let handle = tokio::spawn(forward(sink, options, receiver));
+        Ok((Self { sender }, handle))
+    }
+
+    /// Queues a raw tail message to be forwarded.
+    pub fn send(&self, message: &str) {
+        match serde_json::from_str::<Value>(message) {
+            Ok(event) => {
+                if self.sender.send(event).is_err() {
+                    log::warn!("Failed to forward tail event: forwarder has stopped");
+                }
+            }
+            Err(err) => log::warn!("Failed to forward tail event: {}", err),
+        }
+    }
+}
+
+async fn forward(
+    sink: Sink,
+    options: ForwardOptions,
+    mut receiver: mpsc::UnboundedReceiver<Value>,
+) {
+    let mut batch = Vec::with_capacity(options.batch_size);
+    loop {
+        match tokio::time::timeout(options.batch_interval, receiver.recv()).await {
+            Ok(Some(event)) => {
+                batch.push(event);
+                if batch.len() >= options.batch_size {
+                    sink.flush(&mut batch).await;
+                }
+            }
+            Ok(None) => {
+                sink.flush(&mut batch).await;
+                break;
+            }
+            Err(_) => sink.flush(&mut batch).await,
+        }
+    }
+}
+
+struct Sink {
+    http: Option<(reqwest::Client, Url)>,
+    syslog: Option<UdpSocket>,
+    hostname: String,
+}
+
+impl Sink {
+    fn new(options: &ForwardOptions) -> Result<Self> {
+        let http = match &options.url {
+            Some(url) => {
+                let mut default_headers = headers(None);
+                default_headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+                for (name, value) in options.headers.iter() {
+                    default_headers.insert(
+                        HeaderName::from_bytes(name.as_bytes())?,
+                        HeaderValue::from_str(value)?,
+                    );
+                }
+                let client = reqwest::Client::builder()
+                    .default_headers(default_headers)
+                    .build()?;
+                Some((client, url.clone()))
+            }
+            None => None,
+        };
+        let syslog = match &options.syslog {
+            Some(address) => {
+                // bound before the runtime polls it, since `spawn` is not async
+                let socket = std::net::UdpSocket::bind("0.0.0.0:0")?;
+                socket.connect(address)?;
+                socket.set_nonblocking(true)?;
+                Some(UdpSocket::from_std(socket)?)
+            }
+            None => None,
+        };
+        Ok(Self {
+            http,
+            syslog,
+            hostname: sys_info::hostname().unwrap_or_else(|_| "-".to_string()),
+        })
+    }
+
+    async fn flush(&self, batch: &mut Vec<Value>) {
+        if batch.is_empty() {
+            return;
+        }
+        if let Some((client, url)) = &self.http {
+            let body = Value::Array(batch.clone()).to_string();
+            match client.post(url.clone()).body(body).send().await {
+                Ok(response) if !response.status().is_success() => log::warn!(
+                    "Failed to forward {} tail events to {}: {}",
+                    batch.len(),
+                    url,
+                    response.status()
+                ),
+                Ok(_) => log::debug!("Forwarded {} tail events to {}", batch.len(), url),
+                Err(err) => log::warn!("Failed to forward tail events to {}: {}", url, err),
+            }
+        }
+        if let Some(socket) = &self.syslog {
+            for event in batch.iter() {
+                if let Err(err) = socket.send(self.syslog_message(event).as_bytes()).await {
+                    log::warn!("Failed to forward tail event to syslog: {}", err);
+                }
+            }
+        }
+        batch.clear();
+    }
+
+    fn syslog_message(&self, event: &Value) -> String {
+        // Facility "user" (1), with severity "error" (3) for failed invocations and
+        // "informational" (6) otherwise.
+        let severity = match event.get("outcome").and_then(Value::as_str) {
+            Some("ok") | None => 6,
+            Some(_) => 3,
+        };
+        let app_name = event
+            .get("scriptName")
+            .and_then(Value::as_str)
+            .unwrap_or("wrangler");
+        format!(
+            "<{}>1 {} {} {} - - - {}",
+            8 + severity,
+            Utc::now().to_rfc3339(),
+            self.hostname,
+            app_name,
+            event
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_forwards_events_to_syslog() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let receiver = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let options = ForwardOptions {
+                url: None,
+                syslog: Some(receiver.local_addr().unwrap().to_string()),
+                headers: Vec::new(),
+                batch_size: 10,
+                batch_interval: Duration::from_secs(1),
+            };
+            let (forwarder, handle) = Forwarder::spawn(options).unwrap();
+            forwarder.send(r#"{"scriptName":"my-worker","outcome":"exception"}"#);
+            drop(forwarder);
+            handle.await.unwrap();
+
+            let mut buf = [0; 1024];
+            let (len, _) = receiver.recv_from(&mut buf).await.unwrap();
+            let message = String::from_utf8_lossy(&buf[..len]);
+            assert!(message.starts_with("<11>1 "));
+            assert!(message.contains(" my-worker - - - "));
+            assert!(message.ends_with(r#"{"outcome":"exception","scriptName":"my-worker"}"#));
+        });
+    }
+}
@@ -6,70 +6,162 @@ pub mod api;
 ///     2. wrangler connects to the log forwarder using a WebSocket.
 ///     3. Upon receipt of messages, wrangler prints log events to stdout.
 pub mod event;
+pub mod expression;
 pub mod filter;
+pub mod forward;
+pub mod output;
+pub mod pretty;
+pub mod sourcemap;
+pub mod stats;
 pub mod websocket;
 
 use crate::settings::global_user::GlobalUser;
+use crate::terminal::message::{Message, StdErr};
 use crate::terminal::styles;
 
 use api::Tail;
+use forward::{ForwardOptions, Forwarder};
+use stats::Stats;
 use websocket::{TailOptions, WebSocketTail};
 
 use anyhow::Result;
+use futures::future::join_all;
 use indicatif::{ProgressBar, ProgressStyle};
+use std::time::Duration;
 use url::Url;
 
 /// Runs a tail session from creation to deletion.
 ///
+/// Several Workers can be tailed at once, in which case one tail is created per Worker
+/// and their WebSockets are multiplexed into a single stream. Each `WebSocketTail` keeps
+/// its own state, so one Worker's tail closing does not end the others.
+///
 /// It can be interrupted by:
 /// * an API error when creating the tail
-/// * a WebSocket error when receiving events
+/// * a WebSocket that could not be reconnected after it was dropped
 /// * a user typing ctrl-c
-/// * an expiration of the tail
+/// * an optional timeout, or an event with an error if `fail_on_error` is set, which closes the
+///   tails of every Worker
+///
+/// A dropped WebSocket, or an expired tail, is reconnected with an exponential backoff.
 ///
 /// A fancy progress bar is also updated throughout the session.
 pub async fn run(
     user: GlobalUser,
     account_id: String,
-    script_name: String,
+    script_names: Vec<String>,
     url: Option<Url>,
-    options: TailOptions,
+    forward: Option<ForwardOptions>,
+    stats: Option<Duration>,
+    mut options: TailOptions,
 ) -> Result<()> {
     let progress = &mut ProgressBar::new_spinner()
         .with_style(ProgressStyle::default_spinner().template("{spinner} {msg}"));
     progress.enable_steady_tick(20);
     progress.set_message("Creating tail...");
 
-    let tail = &mut Tail::new(user, account_id, script_name, url);
-    tail.create().await?;
+    options.prefixed = script_names.len() > 1;
+    let fail_on_error = options.fail_on_error;
+    let mut errors: usize = 0;
+    let forwarder = match forward {
+        Some(forward) => {
+            let (forwarder, handle) = Forwarder::spawn(forward)?;
+            options.forwarder = Some(forwarder);
+            Some(handle)
+        }
+        None => None,
+    };
+    let stats = match stats {
+        Some(interval) => {
+            let (stats, handle) =
+                Stats::spawn(interval, options.format.clone(), options.output.clone());
+            options.stats = Some(stats);
+            Some(handle)
+        }
+        None => None,
+    };
+    let names = script_names
+        .iter()
+        .map(|name| styles::bold(name).to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut tails = Vec::with_capacity(script_names.len());
+    for script_name in script_names {
+        let mut tail = Tail::new(user.clone(), account_id.clone(), script_name, url.clone());
+        if let Err(err) = tail.create().await {
+            delete_all(&mut tails).await?;
+            return Err(err);
+        }
+        tails.push(tail);
+    }
 
-    if tail.is_web_socket() {
+    if tails.iter().all(|tail| tail.is_web_socket()) {
         progress.set_message("Connecting to tail...");
 
-        match &mut WebSocketTail::connect(tail.clone(), options).await {
-            Ok(websocket) => {
-                progress.abandon_with_message(&format!(
-                    "Connected! Streaming logs from {}... (ctrl-c to quit)",
-                    styles::bold(&tail.script_name)
-                ));
+        let mut websockets = Vec::with_capacity(tails.len());
+        let mut unconnected = Vec::new();
+        for tail in tails.drain(..) {
+            match WebSocketTail::connect(tail.clone(), options.clone()).await {
+                Ok(websocket) => websockets.push(websocket),
+                Err(err) => {
+                    progress.println(format!("{}", err));
+                    unconnected.push(tail);
+                }
+            }
+        }
+
+        if websockets.is_empty() {
+            progress.abandon_with_message("Failed to connect to any tail");
+        } else {
+            progress.abandon_with_message(&format!(
+                "Connected! Streaming logs from {}... (ctrl-c to quit)",
+                names
+            ));
 
+            join_all(websockets.iter_mut().map(|websocket| async move {
                 if let Err(err) = websocket.update().await {
                     log::warn!("{}", err);
                 };
-                if let Err(err) = websocket.read().await {
-                    log::warn!("{}", err);
+                loop {
+                    if let Err(err) = websocket.read().await {
+                        log::warn!("{}", err);
+                    }
+                    if websocket.closed
+                        || !websocket.disconnected
+                        || websocket.options.stop.is_stopped()
+                    {
+                        break;
+                    }
+                    if let Err(err) = websocket.reconnect().await {
+                        StdErr::warn(&err.to_string());
+                        break;
+                    }
                 }
+            }))
+            .await;
+            errors = websockets.iter().map(|websocket| websocket.errors).sum();
+            let reconnects: usize = websockets
+                .iter()
+                .map(|websocket| websocket.reconnects)
+                .sum();
+            if reconnects > 0 {
+                StdErr::info(&format!(
+                    "The tail was interrupted and reconnected {} time(s)",
+                    reconnects
+                ));
             }
-            Err(err) => progress.abandon_with_message(&format!("{}", err)),
         }
+
+        // Tails may have been re-created while reconnecting, so delete those instead.
+        tails = unconnected;
+        tails.extend(websockets.into_iter().map(|websocket| websocket.tail));
     } else {
         progress.set_message(&format!(
             "Forwarding logs from {} to {} (ctrl-c to quit)",
-            styles::bold(&tail.script_name),
+            names,
             styles::url(
-                tail.url
-                    .clone()
-                    .map(String::from)
+                url.map(String::from)
                     .unwrap_or_else(|| "an endpoint".to_owned())
             )
         ));
@@ -81,6 +173,35 @@ pub async fn run(
         }
     }
 
-    tail.delete().await?;
+    // Every copy of the forwarder has to be dropped before it flushes its last batch.
+    drop(options);
+    if let Some(handle) = forwarder {
+        if let Err(err) = handle.await {
+            log::warn!("Failed to flush forwarded tail events: {}", err);
+        }
+    }
+    if let Some(handle) = stats {
+        if let Err(err) = handle.await {
+            log::warn!("Failed to print tail stats: {}", err);
+        }
+    }
+
+    delete_all(&mut tails).await?;
+
+    if fail_on_error && errors > 0 {
+        anyhow::bail!("Received {} event(s) with errors", errors);
+    }
     Ok(())
 }
+
+/// Deletes every tail, even if deleting one of them fails.
+async fn delete_all(tails: &mut [Tail]) -> Result<()> {
+    let mut result = Ok(());
+    for tail in tails.iter_mut() {
+        if let Err(err) = tail.delete().await {
+            log::warn!("{}", err);
+            result = Err(err);
+        }
+    }
+    result
+}
@@ -0,0 +1,177 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+
+/// The destination of tail output lines.
+///
+/// Tails of several Workers share the same output, so writing is synchronized.
+#[derive(Clone)]
+pub enum TailOutput {
+    Stdout,
+    File(Arc<Mutex<RotatingFile>>),
+}
+
+impl Default for TailOutput {
+    fn default() -> Self {
+        TailOutput::Stdout
+    }
+}
+
+impl TailOutput {
+    /// Writes to a file, rotating it once it grows past `rotate_size` bytes.
+    pub fn file(path: PathBuf, rotate_size: Option<u64>, rotate_count: usize) -> Result<Self> {
+        let file = RotatingFile::open(path, rotate_size, rotate_count)?;
+        Ok(TailOutput::File(Arc::new(Mutex::new(file))))
+    }
+
+    /// Writes a line of output.
+    pub fn write_line(&self, line: &str) -> Result<()> {
+        match self {
+            TailOutput::Stdout => {
+                println!("{}", line);
+                Ok(())
+            }
+            TailOutput::File(file) => match file.lock() {
+                Ok(mut file) => file.write_line(line),
+                Err(_) => anyhow::bail!("Failed to write tail output: file lock was poisoned"),
+            },
+        }
+    }
+}
+
+/// A log file that is rotated by size, keeping up to `rotate_count` old files
+/// named `<path>.1` (newest) to `<path>.<rotate_count>` (oldest).
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    rotate_size: Option<u64>,
+    rotate_count: usize,
+}
+
+impl RotatingFile {
+    pub fn open(path: PathBuf, rotate_size: Option<u64>, rotate_count: usize) -> Result<Self> {
+        let (file, size) = open_append(&path)?;
+        Ok(Self {
+            path,
+            file,
+            size,
+            rotate_size,
+            rotate_count,
+        })
+    }
+
+    pub fn write_line(&mut self, line: &str) -> Result<()> {
+        let len = line.len() as u64 + 1;
+        if let Some(rotate_size) = self.rotate_size {
+            if self.size > 0 && self.size + len > rotate_size {
+                self.rotate()?;
+            }
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += len;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        self.file.flush()?;
+        if self.rotate_count == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let oldest = rotated_path(&self.path, self.rotate_count);
+            if oldest.exists() {
+                fs::remove_file(&oldest)?;
+            }
+            for n in (1..self.rotate_count).rev() {
+                let from = rotated_path(&self.path, n);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(&self.path, n + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+        log::info!("Rotated tail output file: {}", self.path.display());
+
+        let (file, size) = open_append(&self.path)?;
+        self.file = file;
+        self.size = size;
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> Result<(File, u64)> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata()?.len();
+    Ok((file, size))
+}
+
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{}", n));
+    PathBuf::from(rotated)
+}
+
+/// Parses a size such as `1048576`, `512K`, `10MB` or `1G` into bytes.
+pub fn parse_size(input: &str) -> Result<u64> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (digits, unit) = input.split_at(split);
+    let number: u64 = match digits.parse() {
+        Ok(number) => number,
+        Err(_) => anyhow::bail!("Invalid size: {}", input),
+    };
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        _ => anyhow::bail!("Invalid size unit \"{}\", use one of: B, KB, MB, GB", unit),
+    };
+    match number.checked_mul(multiplier) {
+        Some(size) => Ok(size),
+        None => anyhow::bail!("Size is too large: {}", input),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_parses_sizes() {
+        assert_eq!(parse_size("100").unwrap(), 100);
+        assert_eq!(parse_size("512K").unwrap(), 512 * 1024);
+        assert_eq!(parse_size("10MB").unwrap(), 10 * 1024 * 1024);
+        assert_eq!(parse_size("1g").unwrap(), 1024 * 1024 * 1024);
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("10 parsecs").is_err());
+        assert!(parse_size("18446744073709551615K").is_err());
+    }
+
+    #[test]
+    fn it_rotates_files() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("tail.log");
+        let mut file = RotatingFile::open(path.clone(), Some(10), 2).unwrap();
+        for line in &["aaaaaaaa", "bbbbbbbb", "cccccccc", "dddddddd"] {
+            file.write_line(line).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "dddddddd\n");
+        assert_eq!(
+            fs::read_to_string(rotated_path(&path, 1)).unwrap(),
+            "cccccccc\n"
+        );
+        assert_eq!(
+            fs::read_to_string(rotated_path(&path, 2)).unwrap(),
+            "bbbbbbbb\n"
+        );
+        assert!(!rotated_path(&path, 3).exists());
+    }
+}
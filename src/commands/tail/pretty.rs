@@ -0,0 +1,294 @@
+use super::event::{ExceptionItem, LogItem, TraceEvent};
+use super::sourcemap::SourceMaps;
+
+use chrono::{Local, TimeZone};
+use console::style;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::sync::Arc;
+
+/// The layout used when no template is provided.
+const DEFAULT_TEMPLATE: &str = "[{timestamp}] [{colo}] [{outcome}] [{type}] {event}";
+
+static PLACEHOLDER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{(\w+)\}").unwrap());
+
+/// Options to customize how `TraceEvent`s are pretty-printed.
+///
+/// A template is a string with placeholders, e.g. `"{timestamp} {method} {url} {status}"`.
+/// The supported placeholders are `timestamp`, `colo`, `outcome`, `type`, `event`, `method`,
+/// `url`, `status`, `cron`, `script`, `ray`, `duration`, `logs` and `exceptions`. If a template
+/// does not include `logs` or `exceptions`, they are printed on separate lines below it.
+///
+/// When grouped, those lines are indented below the event along with its ray ID and duration,
+/// so each invocation reads as a single block.
+///
+/// Stack traces of exceptions are mapped back to the original sources when source maps are
+/// available.
+#[derive(Debug, Clone, Default)]
+pub struct PrettyFormat {
+    pub template: Option<String>,
+    pub show_headers: bool,
+    pub truncate: Option<usize>,
+    pub colorize_outcome: bool,
+    pub group: bool,
+    pub source_maps: Option<Arc<SourceMaps>>,
+}
+
+impl PrettyFormat {
+    /// Renders an event, which may span multiple lines.
+    pub fn render(&self, event: &TraceEvent) -> String {
+        let template = self.template.as_deref().unwrap_or(DEFAULT_TEMPLATE);
+        let mut output = PLACEHOLDER
+            .replace_all(template, |caps: &Captures| {
+                self.field(event, &caps[1])
+                    .unwrap_or_else(|| caps[0].to_string())
+            })
+            .into_owned();
+
+        if self.colorize_outcome {
+            let plain = console::strip_ansi_codes(&output).into_owned();
+            output = event.outcome_style().apply_to(plain).to_string();
+        }
+
+        let mut details = Vec::new();
+        if self.show_headers {
+            if let Some(request) = &event.event.request {
+                for (name, value) in request.headers.iter() {
+                    details.push((">", format!("{}: {}", name, value)));
+                }
+            }
+        }
+        if !template.contains("{logs}") {
+            for log in event.logs.iter() {
+                details.push(("|", self.log(log)));
+            }
+        }
+        if !template.contains("{exceptions}") {
+            for err in event.exceptions.iter() {
+                details.push(("!", self.exception(err)));
+            }
+        }
+
+        if self.group {
+            let summary = [self.field(event, "ray"), self.field(event, "duration")]
+                .iter()
+                .flatten()
+                .filter(|value| !value.is_empty())
+                .cloned()
+                .collect::<Vec<_>>();
+            if !summary.is_empty() {
+                output.push_str(&format!(" {}", style(summary.join(" · ")).dim()));
+            }
+            // Every line of the invocation hangs off the same bar, so it reads as one block.
+            for (symbol, detail) in details.iter() {
+                for (i, line) in detail.lines().enumerate() {
+                    let symbol = if i > 0 || *symbol == "|" { " " } else { symbol };
+                    output.push_str(&format!(
+                        "\n  {} {} {}",
+                        style("│").dim(),
+                        style(symbol).dim(),
+                        line
+                    ));
+                }
+            }
+        } else {
+            for (symbol, detail) in details.iter() {
+                output.push_str(&format!("\n {} {}", style(symbol).dim(), detail));
+            }
+        }
+        output
+    }
+
+    fn field(&self, event: &TraceEvent, name: &str) -> Option<String> {
+        let request = event.event.request.as_ref();
+        let value = match name {
+            "timestamp" => style(
+                Local
+                    .timestamp_millis(event.timestamp)
+                    .format("%Y-%m-%d %H:%M:%S"),
+            )
+            .dim()
+            .to_string(),
+            // TODO(soon): add colo to scheduled event.
+            "colo" => style(
+                request
+                    .and_then(|r| r.cf.as_ref())
+                    .map(|cf| cf.colo.as_str())
+                    .unwrap_or("?"),
+            )
+            .dim()
+            .to_string(),
+            "outcome" => event.outcome_label().to_string(),
+            "type" => style(
+                event
+                    .event
+                    .event_type()
+                    .map(|t| t.to_string())
+                    .unwrap_or_else(|| "?".to_string()),
+            )
+            .cyan()
+            .to_string(),
+            "event" => {
+                let item = &event.event;
+                if let Some(request) = request {
+                    format!("{} {}", request.method, style(&request.url).bold())
+                } else if let Some(cron) = &item.cron {
+                    cron.clone()
+                } else if let Some(queue) = &item.queue {
+                    queue.clone()
+                } else if let Some(time) = item.scheduled_time {
+                    format!(
+                        "scheduled for {}",
+                        Local
+                            .timestamp_millis(time as i64)
+                            .format("%Y-%m-%d %H:%M:%S")
+                    )
+                } else {
+                    "<unknown event>".to_string()
+                }
+            }
+            "method" => request.map(|r| r.method.clone()).unwrap_or_default(),
+            "url" => request
+                .map(|r| style(&r.url).bold().to_string())
+                .unwrap_or_default(),
+            "status" => event
+                .event
+                .response
+                .as_ref()
+                .map(|r| r.status.to_string())
+                .unwrap_or_default(),
+            "cron" => event.event.cron.clone().unwrap_or_default(),
+            "script" => event.script_name.clone().unwrap_or_default(),
+            "ray" => event
+                .ray_id()
+                .map(|ray| format!("ray {}", ray))
+                .unwrap_or_default(),
+            "duration" => event
+                .wall_time
+                .map(|time| format!("{:.0}ms", time))
+                .unwrap_or_default(),
+            "logs" => event
+                .logs
+                .iter()
+                .map(|log| self.log(log))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            "exceptions" => event
+                .exceptions
+                .iter()
+                .map(|err| self.exception(err))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    fn exception(&self, err: &ExceptionItem) -> String {
+        let mut text = err.to_string();
+        if let Some(stack) = &err.stack {
+            let stack = match &self.source_maps {
+                Some(source_maps) => source_maps.symbolicate(stack),
+                None => stack.clone(),
+            };
+            // The first line of a V8 stack repeats the name and message of the exception.
+            for frame in stack
+                .lines()
+                .filter(|line| line.trim_start().starts_with("at "))
+            {
+                text.push_str(&format!("\n     {}", style(frame.trim()).dim()));
+            }
+        }
+        text
+    }
+
+    fn log(&self, log: &LogItem) -> String {
+        let mut text = log.text();
+        if let Some(max) = self.truncate {
+            if text.chars().count() > max {
+                text = text.chars().take(max).collect();
+                text.push('…');
+            }
+        }
+        format!("[{}] {}", log.level_label(), text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event() -> TraceEvent {
+        serde_json::from_str(
+            r#"{
+                "scriptName": "my-worker",
+                "timestamp": 1620000000000,
+                "outcome": "exception",
+                "logs": [{ "level": "log", "message": ["Hello", "World"] }],
+                "exceptions": [{ "name": "Error", "message": "oops" }],
+                "event": {
+                    "request": {
+                        "url": "https://example.com/api",
+                        "method": "POST",
+                        "headers": { "x-foo": "bar" }
+                    },
+                    "response": { "status": 500 }
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn it_renders_templates() {
+        console::set_colors_enabled(false);
+        let format = PrettyFormat {
+            template: Some("{script} {method} {url} {status} {unknown} {logs}".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            format.render(&event()),
+            "my-worker POST https://example.com/api 500 {unknown} [Info] Hello World \n ! [Error] oops"
+        );
+    }
+
+    #[test]
+    fn it_truncates_logs_and_shows_headers() {
+        console::set_colors_enabled(false);
+        let format = PrettyFormat {
+            template: Some("{outcome}".to_string()),
+            show_headers: true,
+            truncate: Some(5),
+            colorize_outcome: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            format.render(&event()),
+            "Error\n > x-foo: bar\n | [Info] Hello…\n ! [Error] oops"
+        );
+    }
+
+    #[test]
+    fn it_groups_lines_by_request() {
+        console::set_colors_enabled(false);
+        let mut event = event();
+        event
+            .event
+            .request
+            .as_mut()
+            .unwrap()
+            .headers
+            .insert("cf-ray".to_string(), "6f3c2a".to_string());
+        event.wall_time = Some(12.0);
+        event.exceptions[0].stack = Some("Error: oops\n    at handle (worker.js:1:2)".to_string());
+        let format = PrettyFormat {
+            template: Some("{outcome}".to_string()),
+            group: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            format.render(&event),
+            "Error ray 6f3c2a · 12ms\n  │   [Info] Hello World \n  │ ! [Error] oops\n  │        at handle (worker.js:1:2)"
+        );
+    }
+}
@@ -0,0 +1,324 @@
+use crate::settings::toml::{Target, TargetType, UploadFormat};
+use crate::wranglerjs::Bundle;
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A V8 stack frame, e.g. `    at handleRequest (worker.js:1:2345)` or `    at worker.js:1:2345`.
+static STACK_FRAME: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(?P<at>\s*at\s+)(?:(?P<function>[^(]*?)\s+\()?(?P<file>[^()\s]+):(?P<line>\d+):(?P<column>\d+)\)?\s*$",
+    )
+    .unwrap()
+});
+
+/// Source maps found in the local build output, used to symbolicate stack traces.
+#[derive(Debug, Default)]
+pub struct SourceMaps {
+    /// Source maps keyed by the file name of the script they map.
+    maps: HashMap<String, SourceMap>,
+}
+
+impl SourceMaps {
+    /// Loads the source maps from the build output of a target, if there are any.
+    pub fn for_target(target: &Target) -> Option<Self> {
+        let dir = build_dir(target)?;
+        match Self::load(&dir) {
+            Ok(maps) if !maps.maps.is_empty() => {
+                log::info!("Loaded source maps from {}", dir.display());
+                Some(maps)
+            }
+            Ok(_) => None,
+            Err(err) => {
+                log::debug!("Failed to load source maps from {}: {}", dir.display(), err);
+                None
+            }
+        }
+    }
+
+    /// Loads every `*.map` file in a directory.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let mut maps = HashMap::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(true, |ext| ext != "map") {
+                continue;
+            }
+            match SourceMap::parse(&fs::read_to_string(&path)?) {
+                Ok(map) => {
+                    // `index.js.map` maps `index.js`, unless the map says otherwise.
+                    let file = map
+                        .file
+                        .as_deref()
+                        .and_then(|file| file.rsplit('/').next())
+                        .map(String::from)
+                        .or_else(|| {
+                            path.file_stem()
+                                .map(|stem| stem.to_string_lossy().into_owned())
+                        });
+                    if let Some(file) = file {
+                        maps.insert(file, map);
+                    }
+                }
+                Err(err) => log::debug!("Failed to parse {}: {}", path.display(), err),
+            }
+        }
+        Ok(Self { maps })
+    }
+
+    /// Maps every frame of a stack trace back to its original source, when possible.
+    pub fn symbolicate(&self, stack: &str) -> String {
+        stack
+            .lines()
+            .map(|line| self.symbolicate_frame(line))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn symbolicate_frame(&self, frame: &str) -> String {
+        STACK_FRAME
+            .replace(frame, |caps: &Captures| {
+                let file = caps["file"].rsplit('/').next().unwrap_or_default();
+                let map = match self.maps.get(file) {
+                    Some(map) => map,
+                    // The runtime may not know the file name, so a single map is assumed to match.
+                    None if self.maps.len() == 1 => self.maps.values().next().unwrap(),
+                    None => return caps[0].to_string(),
+                };
+                let line = caps["line"].parse::<usize>().unwrap_or_default();
+                let column = caps["column"].parse::<usize>().unwrap_or_default();
+                match map.lookup(line.saturating_sub(1), column.saturating_sub(1)) {
+                    Some(original) => {
+                        let location = format!(
+                            "{}:{}:{}",
+                            original.source,
+                            original.line + 1,
+                            original.column + 1
+                        );
+                        let function = original
+                            .name
+                            .or_else(|| caps.name("function").map(|f| f.as_str()));
+                        match function {
+                            Some(function) => format!("{}{} ({})", &caps["at"], function, location),
+                            None => format!("{}{}", &caps["at"], location),
+                        }
+                    }
+                    None => caps[0].to_string(),
+                }
+            })
+            .into_owned()
+    }
+}
+
+/// Finds the directory where the build output of a target is written.
+fn build_dir(target: &Target) -> Option<PathBuf> {
+    if let Some(build) = &target.build {
+        if let UploadFormat::Modules { dir, .. } = &build.upload {
+            return Some(dir.clone());
+        }
+    }
+    let package_dir = target.package_dir().ok()?;
+    match target.target_type {
+        TargetType::Webpack if target.entries.is_some() => {
+            Some(Bundle::new(&package_dir).modules_dir())
+        }
+        TargetType::Webpack => Some(package_dir.join("worker")),
+        _ => Some(package_dir.join("dist")),
+    }
+}
+
+/// A revision 3 source map.
+#[derive(Debug, Deserialize)]
+struct RawSourceMap {
+    file: Option<String>,
+    #[serde(rename = "sourceRoot")]
+    source_root: Option<String>,
+    sources: Vec<String>,
+    #[serde(default)]
+    names: Vec<String>,
+    mappings: String,
+}
+
+#[derive(Debug)]
+struct SourceMap {
+    file: Option<String>,
+    sources: Vec<String>,
+    names: Vec<String>,
+    /// The mappings of each generated line, sorted by column.
+    lines: Vec<Vec<Mapping>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Mapping {
+    column: usize,
+    source: usize,
+    line: usize,
+    original_column: usize,
+    name: Option<usize>,
+}
+
+/// A location in an original source file.
+struct Original<'a> {
+    source: &'a str,
+    line: usize,
+    column: usize,
+    name: Option<&'a str>,
+}
+
+impl SourceMap {
+    fn parse(json: &str) -> Result<Self> {
+        let raw: RawSourceMap = serde_json::from_str(json)?;
+        let sources = match raw.source_root.as_deref() {
+            Some(root) if !root.is_empty() => raw
+                .sources
+                .iter()
+                .map(|source| format!("{}/{}", root.trim_end_matches('/'), source))
+                .collect(),
+            _ => raw.sources,
+        };
+
+        // Every field but the generated column is relative to the previous segment in the file.
+        let (mut source, mut line, mut column, mut name) = (0i64, 0i64, 0i64, 0i64);
+        let mut lines = Vec::new();
+        for generated in raw.mappings.split(';') {
+            let mut mappings = Vec::new();
+            let mut generated_column = 0i64;
+            for segment in generated.split(',').filter(|segment| !segment.is_empty()) {
+                let fields = decode_vlq(segment)?;
+                generated_column += fields[0];
+                if fields.len() < 4 {
+                    continue;
+                }
+                source += fields[1];
+                line += fields[2];
+                column += fields[3];
+                let mapping_name = if fields.len() >= 5 {
+                    name += fields[4];
+                    Some(name as usize)
+                } else {
+                    None
+                };
+                mappings.push(Mapping {
+                    column: generated_column as usize,
+                    source: source as usize,
+                    line: line as usize,
+                    original_column: column as usize,
+                    name: mapping_name,
+                });
+            }
+            mappings.sort_by_key(|mapping| mapping.column);
+            lines.push(mappings);
+        }
+
+        Ok(Self {
+            file: raw.file,
+            sources,
+            names: raw.names,
+            lines,
+        })
+    }
+
+    /// Finds the original location of a zero-based line and column in the generated file.
+    fn lookup(&self, line: usize, column: usize) -> Option<Original> {
+        let mappings = self.lines.get(line)?;
+        let index = mappings.partition_point(|mapping| mapping.column <= column);
+        let mapping = mappings.get(index.checked_sub(1)?)?;
+        Some(Original {
+            source: self.sources.get(mapping.source)?,
+            line: mapping.line,
+            column: mapping.original_column,
+            name: mapping
+                .name
+                .and_then(|name| self.names.get(name))
+                .map(String::as_str),
+        })
+    }
+}
+
+/// Decodes the base64 VLQ fields of a mapping segment.
+fn decode_vlq(segment: &str) -> Result<Vec<i64>> {
+    let mut fields = Vec::new();
+    let (mut value, mut shift) = (0i64, 0);
+    for c in segment.bytes() {
+        let digit = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => anyhow::bail!("Invalid character in source map mappings: {}", c as char),
+        } as i64;
+        value = value
+            .checked_add((digit & 0b11111) << shift)
+            .ok_or_else(|| {
+                anyhow::anyhow!("Too large value in source map mappings: {}", segment)
+            })?;
+        if digit & 0b100000 != 0 {
+            // the values are 32-bit, so they have at most 7 digits
+            shift += 5;
+            if shift > 30 {
+                anyhow::bail!("Too large value in source map mappings: {}", segment);
+            }
+        } else {
+            let negative = value & 1 == 1;
+            value >>= 1;
+            fields.push(if negative { -value } else { value });
+            value = 0;
+            shift = 0;
+        }
+    }
+    if shift != 0 {
+        anyhow::bail!("Truncated segment in source map mappings: {}", segment);
+    }
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source_maps() -> SourceMaps {
+        let map = SourceMap::parse(
+            r#"{
+                "version": 3,
+                "file": "worker.js",
+                "sources": ["src/index.ts"],
+                "names": ["handleRequest"],
+                "mappings": "AAAA;AACAA,MAAM"
+            }"#,
+        )
+        .unwrap();
+        let mut maps = HashMap::new();
+        maps.insert("worker.js".to_string(), map);
+        SourceMaps { maps }
+    }
+
+    #[test]
+    fn it_decodes_vlq() {
+        assert_eq!(decode_vlq("AACAA").unwrap(), vec![0, 0, 1, 0, 0]);
+        assert_eq!(decode_vlq("MAAM").unwrap(), vec![6, 0, 0, 6]);
+        assert_eq!(decode_vlq("D").unwrap(), vec![-1]);
+        assert_eq!(decode_vlq("gB").unwrap(), vec![16]);
+        assert!(decode_vlq("g").is_err());
+        assert_eq!(decode_vlq("ggggggC").unwrap(), vec![1 << 30]);
+        assert!(decode_vlq("gggggggggggggggB").is_err());
+    }
+
+    #[test]
+    fn it_symbolicates_stack_frames() {
+        let maps = source_maps();
+        assert_eq!(
+            maps.symbolicate("Error: oops\n    at a (worker.js:2:3)\n    at worker.js:2:10"),
+            "Error: oops\n    at handleRequest (src/index.ts:2:1)\n    at src/index.ts:2:7"
+        );
+        assert_eq!(
+            maps.symbolicate("    at other.js:1:1"),
+            "    at src/index.ts:1:1"
+        );
+    }
+}
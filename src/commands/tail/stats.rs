@@ -0,0 +1,233 @@
+use super::event::TraceEvent;
+use super::output::TailOutput;
+use super::websocket::TailFormat;
+
+use chrono::Local;
+use console::style;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use url::Url;
+
+/// How many of the most requested URLs are shown per interval.
+const TOP_URLS: usize = 5;
+
+/// The part of a `TraceEvent` that is aggregated.
+#[derive(Debug)]
+struct Sample {
+    url: Option<String>,
+    is_error: bool,
+    wall_time: Option<f64>,
+}
+
+/// Aggregates `TraceEvent`s in the background, printing a summary of each interval
+/// instead of the events themselves.
+#[derive(Debug, Clone)]
+pub struct Stats {
+    sender: mpsc::UnboundedSender<Sample>,
+}
+
+impl Stats {
+    /// Starts aggregating; the returned task finishes once every `Stats` is dropped
+    /// and the last interval has been printed.
+    pub fn spawn(
+        interval: Duration,
+        format: TailFormat,
+        output: TailOutput,
+    ) -> (Self, JoinHandle<()>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let handle = tokio::spawn(aggregate(interval, format, output, receiver));
+        (Self { sender }, handle)
+    }
+
+    /// Records an event in the current interval.
+    pub fn record(&self, event: &TraceEvent) {
+        let url = event.event.request.as_ref().map(|request| {
+            // Query strings would split the same route into many entries.
+            match Url::parse(&request.url) {
+                Ok(url) => format!("{}{}", url.host_str().unwrap_or_default(), url.path()),
+                Err(_) => request.url.clone(),
+            }
+        });
+        let sample = Sample {
+            url,
+            is_error: event.is_error(),
+            wall_time: event.wall_time,
+        };
+        if self.sender.send(sample).is_err() {
+            log::warn!("Failed to record tail event: stats have stopped");
+        }
+    }
+}
+
+async fn aggregate(
+    interval: Duration,
+    format: TailFormat,
+    output: TailOutput,
+    mut receiver: mpsc::UnboundedReceiver<Sample>,
+) {
+    let mut window = Window::default();
+    let mut deadline = Instant::now() + interval;
+    loop {
+        match tokio::time::timeout_at(deadline, receiver.recv()).await {
+            Ok(Some(sample)) => window.add(sample),
+            Ok(None) => {
+                if window.requests > 0 {
+                    print(&window.summary(), &format, &output);
+                }
+                break;
+            }
+            Err(_) => {
+                print(&window.summary(), &format, &output);
+                window = Window::default();
+                deadline += interval;
+            }
+        }
+    }
+}
+
+fn print(summary: &Summary, format: &TailFormat, output: &TailOutput) {
+    let result = match format {
+        TailFormat::Pretty => output.write_line(&summary.to_string()),
+        TailFormat::Json | TailFormat::Ndjson => match serde_json::to_string(summary) {
+            Ok(json) => output.write_line(&json),
+            Err(err) => Err(err.into()),
+        },
+    };
+    if let Err(err) = result {
+        log::warn!("Failed to print tail stats: {}", err);
+    }
+}
+
+/// The events received during one interval.
+#[derive(Debug, Default)]
+struct Window {
+    requests: usize,
+    errors: usize,
+    wall_times: Vec<f64>,
+    urls: HashMap<String, usize>,
+}
+
+impl Window {
+    fn add(&mut self, sample: Sample) {
+        self.requests += 1;
+        if sample.is_error {
+            self.errors += 1;
+        }
+        if let Some(wall_time) = sample.wall_time {
+            self.wall_times.push(wall_time);
+        }
+        if let Some(url) = sample.url {
+            *self.urls.entry(url).or_default() += 1;
+        }
+    }
+
+    fn summary(&mut self) -> Summary {
+        self.wall_times
+            .sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let mut top_urls: Vec<(String, usize)> = self.urls.drain().collect();
+        top_urls.sort_by(|(a_url, a), (b_url, b)| b.cmp(a).then_with(|| a_url.cmp(b_url)));
+        top_urls.truncate(TOP_URLS);
+        Summary {
+            timestamp: Local::now().format("%H:%M:%S").to_string(),
+            requests: self.requests,
+            errors: self.errors,
+            error_rate: if self.requests > 0 {
+                self.errors as f64 / self.requests as f64
+            } else {
+                0.0
+            },
+            p50: percentile(&self.wall_times, 0.50),
+            p95: percentile(&self.wall_times, 0.95),
+            top_urls,
+        }
+    }
+}
+
+/// The aggregated metrics of an interval.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Summary {
+    timestamp: String,
+    requests: usize,
+    errors: usize,
+    error_rate: f64,
+    p50: Option<f64>,
+    p95: Option<f64>,
+    top_urls: Vec<(String, usize)>,
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let millis = |time: Option<f64>| match time {
+            Some(time) => format!("{:.0}ms", time),
+            None => "-".to_owned(),
+        };
+        let error_rate = format!("{:.1}%", self.error_rate * 100.0);
+        let error_rate = if self.errors > 0 {
+            style(error_rate).red()
+        } else {
+            style(error_rate).green()
+        };
+        write!(
+            f,
+            "[{}] {} requests | {} errors | p50 {} | p95 {}",
+            style(&self.timestamp).dim(),
+            style(self.requests).bold(),
+            error_rate,
+            millis(self.p50),
+            millis(self.p95)
+        )?;
+        for (url, count) in self.top_urls.iter() {
+            write!(f, "\n  {:>6}  {}", count, url)?;
+        }
+        Ok(())
+    }
+}
+
+/// Picks the nearest-rank percentile of sorted values.
+fn percentile(sorted: &[f64], percentile: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (percentile * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_computes_percentiles() {
+        let values: Vec<f64> = (1..=100).map(f64::from).collect();
+        assert_eq!(percentile(&values, 0.50), Some(50.0));
+        assert_eq!(percentile(&values, 0.95), Some(95.0));
+        assert_eq!(percentile(&[7.0], 0.95), Some(7.0));
+        assert_eq!(percentile(&[], 0.50), None);
+    }
+
+    #[test]
+    fn it_summarizes_a_window() {
+        let mut window = Window::default();
+        for (url, is_error, wall_time) in [
+            ("example.com/api", false, 10.0),
+            ("example.com/api", true, 30.0),
+            ("example.com/", false, 20.0),
+        ] {
+            window.add(Sample {
+                url: Some(url.to_owned()),
+                is_error,
+                wall_time: Some(wall_time),
+            });
+        }
+        let summary = window.summary();
+        assert_eq!(summary.requests, 3);
+        assert_eq!(summary.errors, 1);
+        assert_eq!(summary.p50, Some(20.0));
+        assert_eq!(summary.top_urls[0], ("example.com/api".to_owned(), 2));
+    }
+}
@@ -1,25 +1,46 @@
 use crate::http::feature::user_agent;
+use crate::terminal::message::{Message as TerminalMessage, StdErr};
 
 use super::api::Tail;
-use super::event::{TraceEvent, PROTOCOL_ID};
+use super::event::{EventType, TraceEvent, PROTOCOL_ID};
+use super::expression::Expression;
 use super::filter::TraceFilter;
+use super::forward::Forwarder;
+use super::output::TailOutput;
+use super::pretty::PrettyFormat;
+use super::stats::Stats;
 
 use anyhow::Result;
+use console::style;
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::borrow::Cow;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::TcpStream;
+use tokio::sync::Notify;
+use tokio::time::Instant;
 use tokio_tungstenite::tungstenite::error::Error::{AlreadyClosed, ConnectionClosed};
 use tokio_tungstenite::tungstenite::handshake::client::Request;
 use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
 use tokio_tungstenite::tungstenite::protocol::{CloseFrame, Message};
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 
+/// The number of times to try reconnecting before giving up on a tail.
+const MAX_RECONNECT_ATTEMPTS: u32 = 8;
+/// The longest delay between two reconnection attempts.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
 /// The format to print a `TraceEvent`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TailFormat {
     Json,
+    Ndjson,
     Pretty,
 }
 
@@ -29,18 +50,76 @@ impl FromStr for TailFormat {
         match string {
             "pretty" => Ok(TailFormat::Pretty),
             "json" => Ok(TailFormat::Json),
+            "ndjson" => Ok(TailFormat::Ndjson),
             _ => Ok(TailFormat::Json),
         }
     }
 }
 
+/// Stops every tail of a `wrangler tail` at once, shared by the copies of the `TailOptions`.
+#[derive(Clone, Default)]
+pub struct Stop {
+    stopped: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+}
+
+impl Stop {
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once `stop` is called, even if it was called before.
+    pub async fn stopped(&self) {
+        let notified = self.notify.notified();
+        if self.is_stopped() {
+            return;
+        }
+        notified.await
+    }
+}
+
 /// Options that are sent to the `WebSocketTail`.
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 pub struct TailOptions {
     #[serde(skip_serializing)]
     pub once: bool,
     #[serde(skip_serializing)]
     pub format: TailFormat,
+    #[serde(skip_serializing)]
+    pub pretty: PrettyFormat,
+    /// Event types to keep, filtered by wrangler rather than the tail.
+    #[serde(skip_serializing)]
+    pub event_types: Vec<EventType>,
+    /// Only show events with exceptions or error outcomes.
+    #[serde(skip_serializing)]
+    pub errors_only: bool,
+    /// Stop the tail as soon as an event with an error is received.
+    #[serde(skip_serializing)]
+    pub fail_on_error: bool,
+    /// An expression that events must match, evaluated by wrangler rather than the tail.
+    #[serde(skip_serializing)]
+    pub expression: Option<Expression>,
+    /// Stop the tail after a period of time.
+    #[serde(skip_serializing)]
+    pub timeout: Option<Duration>,
+    /// Prefixes pretty-printed lines with the worker name, used when tailing several workers.
+    #[serde(skip_serializing)]
+    pub prefixed: bool,
+    #[serde(skip_serializing)]
+    pub output: TailOutput,
+    #[serde(skip_serializing)]
+    pub forwarder: Option<Forwarder>,
+    /// Aggregates events into metrics, which are printed instead of the events.
+    #[serde(skip_serializing)]
+    pub stats: Option<Stats>,
+    /// Closes the other tails when one of them stops because of `fail_on_error`.
+    #[serde(skip_serializing)]
+    pub stop: Stop,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub filters: Vec<Box<dyn TraceFilter>>,
 }
@@ -49,15 +128,42 @@ pub struct TailOptions {
 pub struct WebSocketTail {
     pub tail: Tail,
     pub options: TailOptions,
-    pub websocket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    pub websocket: Socket,
     pub closed: bool,
+    /// Set when the WebSocket was dropped by the other end, rather than closed by wrangler.
+    pub disconnected: bool,
+    /// The number of times the WebSocket was reconnected.
+    pub reconnects: usize,
+    /// When the tail should stop, if it has a timeout.
+    deadline: Option<Instant>,
+    /// The number of events that passed the client-side filters.
+    pub events: usize,
+    /// The number of those events that had an exception or an error outcome.
+    pub errors: usize,
 }
 
 impl WebSocketTail {
     /// Connects to WebSocket tail.
     pub async fn connect(tail: Tail, options: TailOptions) -> Result<Self> {
+        let websocket = Self::open(&tail).await?;
+        let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+        Ok(Self {
+            tail,
+            options,
+            websocket,
+            closed: false,
+            disconnected: false,
+            reconnects: 0,
+            deadline,
+            events: 0,
+            errors: 0,
+        })
+    }
+
+    /// Opens a WebSocket to the URL of a tail.
+    async fn open(tail: &Tail) -> Result<Socket> {
         if tail.id.is_none() && tail.url.is_none() && !tail.is_web_socket() {
-            anyhow::bail!("Precondition failed for WebSocket tail: {:?}", &tail);
+            anyhow::bail!("Precondition failed for WebSocket tail: {:?}", tail);
         }
         let request = Request::builder()
             .uri(&tail.url.clone().map(String::from).unwrap())
@@ -66,64 +172,266 @@ impl WebSocketTail {
             .body(())?;
         log::info!("Connecting to WebSocket tail: {:?}", request);
         match tokio_tungstenite::connect_async(request).await {
-            Ok((websocket, _)) => Ok(Self {
-                tail,
-                options,
-                websocket,
-                closed: false,
-            }),
+            Ok((websocket, _)) => Ok(websocket),
             Err(err) => anyhow::bail!("Failed to create WebSocket tail: {}", err),
         }
     }
 
+    /// Reconnects a WebSocket that was dropped, backing off exponentially between attempts.
+    ///
+    /// If the tail has expired in the meantime, it is deleted and created again. Once
+    /// reconnected, a marker is printed with the length of the gap, since any event sent
+    /// while disconnected is lost.
+    pub async fn reconnect(&mut self) -> Result<()> {
+        let disconnected_at = Instant::now();
+        let mut delay = Duration::from_secs(1);
+        for attempt in 1..=MAX_RECONNECT_ATTEMPTS {
+            log::info!(
+                "Reconnecting to WebSocket tail in {:?} (attempt {})",
+                delay,
+                attempt
+            );
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {},
+                _ = tokio::signal::ctrl_c() => {
+                    self.closed = true;
+                    return Ok(());
+                }
+            }
+            delay = std::cmp::min(delay * 2, MAX_RECONNECT_DELAY);
+
+            let websocket = match Self::open(&self.tail).await {
+                Ok(websocket) => websocket,
+                Err(err) => {
+                    log::info!("{}, re-creating the tail", err);
+                    if let Err(err) = self.recreate().await {
+                        log::warn!("{}", err);
+                        continue;
+                    }
+                    match Self::open(&self.tail).await {
+                        Ok(websocket) => websocket,
+                        Err(err) => {
+                            log::warn!("{}", err);
+                            continue;
+                        }
+                    }
+                }
+            };
+
+            self.websocket = websocket;
+            self.disconnected = false;
+            self.reconnects += 1;
+            self.mark_gap(disconnected_at.elapsed())?;
+            return self.update().await;
+        }
+        anyhow::bail!(
+            "Failed to reconnect to the tail of {} after {} attempts",
+            self.tail.script_name,
+            MAX_RECONNECT_ATTEMPTS
+        )
+    }
+
+    /// Deletes the tail, which may have expired, and creates a new one.
+    async fn recreate(&mut self) -> Result<()> {
+        if let Err(err) = self.tail.delete().await {
+            log::debug!("{}", err);
+            self.tail.id = None;
+            self.tail.url = None;
+        }
+        self.tail.create().await
+    }
+
+    /// Notes that the stream was interrupted, and for how long.
+    ///
+    /// The marker goes to stderr for JSON formats, so it does not break their output.
+    fn mark_gap(&self, gap: Duration) -> Result<()> {
+        let marker = format!(
+            "--- reconnected to {} after {:.1}s, events in the gap were missed (reconnect #{}) ---",
+            self.tail.script_name,
+            gap.as_secs_f64(),
+            self.reconnects
+        );
+        match self.options.format {
+            TailFormat::Pretty => self.print(&style(marker).yellow().to_string()),
+            TailFormat::Json | TailFormat::Ndjson => {
+                StdErr::warn(&marker);
+                Ok(())
+            }
+        }
+    }
+
     /// Reads a message from the WebSocket and prints it.
     pub async fn read_once(&mut self) -> Result<()> {
+        let stop = self.options.stop.clone();
         tokio::select! {
             frame = self.websocket.next() => {
                 match frame {
                     Some(Ok(message)) if message.is_text() || message.is_binary() => {
-                        match self.options.format {
-                            TailFormat::Json => {
-                                println!("{}", message);
-                                Ok(())
-                            },
-                            TailFormat::Pretty => match serde_json::from_str::<TraceEvent>(&message.to_string()) {
-                                Ok(event) => {
-                                    println!("{}", event);
-                                    Ok(())
-                                },
-                                Err(err) => {
-                                    log::debug!("Failed to pretty-print tail: {}", err);
-                                    self.close(CloseCode::Protocol, "wrangler is closing due to a protocol violation").await
-                                },
-                            }
+                        let message = message.to_string();
+                        if self.accepts(&message) {
+                            self.events += 1;
+                            self.handle(&message).await
+                        } else {
+                            Ok(())
                         }
                     },
                     Some(Ok(message)) if message.is_close() => {
-                        anyhow::bail!("Received close from WebSocket tail: {}", message)
+                        log::info!("Received close from WebSocket tail: {}", message);
+                        self.disconnected = true;
+                        Ok(())
                     },
                     Some(Err(err)) => {
-                        log::debug!("Received error from WebSocket tail: {}", err);
-                        self.close(CloseCode::Abnormal, "wrangler is closing due to an error").await
+                        log::info!("Received error from WebSocket tail: {}", err);
+                        self.disconnected = true;
+                        Ok(())
+                    },
+                    Some(Ok(_)) => Ok(()),
+                    None => {
+                        log::info!("WebSocket tail ended");
+                        self.disconnected = true;
+                        Ok(())
                     },
-                    _ => Ok(()),
                 }
             },
+            _ = stop.stopped() => {
+                self.close(
+                    CloseCode::Normal,
+                    "wrangler is closing after another tail received an error",
+                )
+                .await
+            }
             _ = tokio::signal::ctrl_c() => {
                 self.close(CloseCode::Away, "wrangler is closing due to ctrl-c").await
             }
         }
     }
 
+    /// Tests if a message passes the client-side filters, counting it if it is an error.
+    fn accepts(&mut self, message: &str) -> bool {
+        let options = &self.options;
+        if options.event_types.is_empty()
+            && options.expression.is_none()
+            && !options.errors_only
+            && !options.fail_on_error
+        {
+            return true;
+        }
+        let event = match serde_json::from_str::<TraceEvent>(message) {
+            Ok(event) => event,
+            Err(err) => {
+                log::debug!("Failed to parse tail for filtering: {}", err);
+                return true;
+            }
+        };
+        if !options.event_types.is_empty()
+            && !event
+                .event
+                .event_type()
+                .map_or(false, |t| options.event_types.contains(&t))
+        {
+            return false;
+        }
+        if let Some(expression) = &options.expression {
+            if !expression.matches(&event) {
+                return false;
+            }
+        }
+        let is_error = event.is_error();
+        if options.errors_only && !is_error {
+            return false;
+        }
+        if is_error {
+            self.errors += 1;
+        }
+        true
+    }
+
+    /// Forwards and prints a message.
+    async fn handle(&mut self, message: &str) -> Result<()> {
+        if let Some(forwarder) = &self.options.forwarder {
+            forwarder.send(message);
+        }
+        if let Some(stats) = &self.options.stats {
+            match serde_json::from_str::<TraceEvent>(message) {
+                Ok(event) => stats.record(&event),
+                Err(err) => log::debug!("Failed to parse tail for stats: {}", err),
+            }
+            return Ok(());
+        }
+        match self.options.format {
+            TailFormat::Json => self.options.output.write_line(message),
+            TailFormat::Ndjson => match serde_json::from_str::<Value>(message) {
+                // Re-serialize the event so it is guaranteed to fit on a single line.
+                Ok(event) => self.options.output.write_line(&event.to_string()),
+                Err(err) => {
+                    log::debug!("Failed to parse tail as JSON: {}", err);
+                    self.close(
+                        CloseCode::Protocol,
+                        "wrangler is closing due to a protocol violation",
+                    )
+                    .await
+                }
+            },
+            TailFormat::Pretty => match serde_json::from_str::<TraceEvent>(message) {
+                Ok(event) => self.print(&self.options.pretty.render(&event)),
+                Err(err) => {
+                    log::debug!("Failed to pretty-print tail: {}", err);
+                    self.close(
+                        CloseCode::Protocol,
+                        "wrangler is closing due to a protocol violation",
+                    )
+                    .await
+                }
+            },
+        }
+    }
+
+    /// Prints a pretty-printed event, prefixing each line with the worker name if needed.
+    fn print(&self, output: &str) -> Result<()> {
+        if self.options.prefixed {
+            let prefix = style(format!("[{}]", self.tail.script_name)).cyan();
+            for line in output.lines() {
+                self.options
+                    .output
+                    .write_line(&format!("{} {}", prefix, line))?;
+            }
+            Ok(())
+        } else {
+            self.options.output.write_line(output)
+        }
+    }
+
     /// Reads and prints messages from the WebSocket in a loop.
+    ///
+    /// Returns once the WebSocket is closed by wrangler or dropped by the other end.
     pub async fn read(&mut self) -> Result<()> {
         loop {
-            if self.closed {
+            if self.closed || self.disconnected {
                 break Ok(());
             }
-            match self.read_once().await {
+            let result = match self.deadline {
+                Some(deadline) => match tokio::time::timeout_at(deadline, self.read_once()).await {
+                    Ok(result) => result,
+                    Err(_) => {
+                        break self
+                            .close(CloseCode::Normal, "wrangler is closing after the timeout")
+                            .await
+                    }
+                },
+                None => self.read_once().await,
+            };
+            match result {
                 Err(err) => break Err(err),
-                Ok(_) if self.options.once => {
+                Ok(_) if self.options.fail_on_error && self.errors > 0 => {
+                    self.options.stop.stop();
+                    break self
+                        .close(
+                            CloseCode::Normal,
+                            "wrangler is closing after receiving an error",
+                        )
+                        .await;
+                }
+                Ok(_) if self.options.once && self.events > 0 => {
                     break self
                         .close(
                             CloseCode::Normal,
@@ -0,0 +1,302 @@
+//! Generates TypeScript declarations of the bindings of a Worker from its configuration, so that
+//! TypeScript Workers are checked against the bindings they are deployed with.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::settings::toml::{Builder, Target, TargetType, UploadFormat};
+use crate::terminal::message::{Message, StdOut};
+
+/// Writes the declarations of the bindings of `target`, and of `secrets`, to `output`.
+pub fn types(target: &Target, secrets: &[String], output: &Path) -> Result<()> {
+    fs::write(output, declarations(target, secrets))?;
+    StdOut::success(&format!(
+        "Declared the bindings of {} in {}",
+        target.name,
+        output.display()
+    ));
+    Ok(())
+}
+
+/// Declares the bindings as the properties of `Env` for the modules format, and as globals for
+/// the service worker format.
+fn declarations(target: &Target, secrets: &[String]) -> String {
+    let modules = target.entries.is_some()
+        || (target.target_type == TargetType::JavaScript || target.custom_build().is_some())
+            && matches!(
+                target.build,
+                Some(Builder {
+                    upload: UploadFormat::Modules { .. },
+                    ..
+                })
+            );
+
+    let mut out = String::from(
+        "// Generated by `wrangler types` from the configuration of the Worker. Do not edit.\n\n",
+    );
+    if modules {
+        out.push_str("interface Env {\n");
+        for (name, ts_type) in bindings(target, secrets) {
+            out.push_str(&format!("  {}: {};\n", property_name(&name), ts_type));
+        }
+        out.push_str("}\n");
+        if target.site.is_some() {
+            out.push_str("\ndeclare module \"__STATIC_CONTENT_MANIFEST\" {\n  const manifest: string;\n  export default manifest;\n}\n");
+        }
+    } else {
+        out.push_str("declare global {\n");
+        for (name, ts_type) in bindings(target, secrets) {
+            // a global is declared by its name, which has to be an identifier
+            if !is_identifier(&name) {
+                StdOut::warn(&format!(
+                    "Not declaring the binding {:?}, which is not a JavaScript identifier and can't be a global of a service worker",
+                    name
+                ));
+                continue;
+            }
+            out.push_str(&format!("  const {}: {};\n", name, ts_type));
+        }
+        if target.site.is_some() {
+            out.push_str("  const __STATIC_CONTENT_MANIFEST: string;\n");
+        }
+        out.push_str("}\n\nexport {};\n");
+    }
+    out
+}
+
+/// The names of the bindings of a Worker and their TypeScript types.
+fn bindings(target: &Target, secrets: &[String]) -> Vec<(String, String)> {
+    let mut bindings = Vec::new();
+    for namespace in &target.kv_namespaces {
+        bindings.push((namespace.binding.clone(), "KVNamespace".to_string()));
+    }
+    if target.site.is_some() {
+        bindings.push(("__STATIC_CONTENT".to_string(), "KVNamespace".to_string()));
+    }
+    for bucket in &target.r2_buckets {
+        bindings.push((bucket.binding.clone(), "R2Bucket".to_string()));
+    }
+    for service in &target.services {
+        bindings.push((service.binding.clone(), "Fetcher".to_string()));
+    }
+    let classes = target
+        .durable_objects
+        .as_ref()
+        .and_then(|durable_objects| durable_objects.classes.as_ref());
+    for class in classes.into_iter().flatten() {
+        bindings.push((class.binding.clone(), "DurableObjectNamespace".to_string()));
+    }
+    let mut vars: Vec<_> = target.vars.iter().flatten().collect();
+    vars.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, value) in vars {
+        bindings.push((name.clone(), ts_type(value)));
+    }
+    let mut text_blobs: Vec<_> = target
+        .text_blobs
+        .iter()
+        .flat_map(|blobs| blobs.keys())
+        .collect();
+    text_blobs.sort();
+    for name in text_blobs {
+        bindings.push((name.clone(), "string".to_string()));
+    }
+    let mut wasm_modules: Vec<_> = target
+        .wasm_modules
+        .iter()
+        .flat_map(|modules| modules.keys())
+        .collect();
+    wasm_modules.sort();
+    for name in wasm_modules {
+        bindings.push((name.clone(), "WebAssembly.Module".to_string()));
+    }
+    if target.target_type == TargetType::Rust && target.custom_build().is_none() {
+        bindings.push(("wasm".to_string(), "WebAssembly.Module".to_string()));
+    }
+    for secret in secrets {
+        bindings.push((secret.clone(), "string".to_string()));
+    }
+    bindings
+}
+
+/// The type of a var, which is bound as plain text if it is a string and as JSON otherwise.
+fn ts_type(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(_) => "boolean".to_string(),
+        Value::Number(_) => "number".to_string(),
+        Value::String(_) => "string".to_string(),
+        Value::Array(items) => {
+            let mut types: Vec<String> = items.iter().map(ts_type).collect();
+            types.sort();
+            types.dedup();
+            match types.len() {
+                0 => "unknown[]".to_string(),
+                1 if !types[0].contains(' ') => format!("{}[]", types[0]),
+                _ => format!("({})[]", types.join(" | ")),
+            }
+        }
+        Value::Object(fields) => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|(name, value)| format!("{}: {}", property_name(name), ts_type(value)))
+                .collect();
+            format!("{{ {} }}", fields.join("; "))
+        }
+    }
+}
+
+/// Quotes the name of a property unless it is an identifier.
+fn property_name(name: &str) -> String {
+    if is_identifier(name) {
+        name.to_string()
+    } else {
+        serde_json::to_string(name).unwrap_or_else(|_| name.to_string())
+    }
+}
+
+/// Whether a name can be declared as it is, which the reserved words of JavaScript can't.
+fn is_identifier(name: &str) -> bool {
+    name.chars()
+        .next()
+        .map_or(false, |c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+        && !RESERVED_WORDS.contains(&name)
+}
+
+const RESERVED_WORDS: &[&str] = &[
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    use crate::settings::toml::KvNamespace;
+
+    #[test]
+    fn it_declares_bindings_as_globals_of_service_workers() {
+        let target = Target {
+            target_type: TargetType::Webpack,
+            kv_namespaces: vec![KvNamespace {
+                id: "0f2ac74b498b48028cb68387c421e279".to_string(),
+                binding: "CACHE".to_string(),
+            }],
+            vars: Some(
+                vec![
+                    ("REGION".to_string(), json!("eu")),
+                    (
+                        "LIMITS".to_string(),
+                        json!({ "rps": 10, "burst-size": [1, 2] }),
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            ..Default::default()
+        };
+        assert_eq!(
+            declarations(&target, &["API_TOKEN".to_string()]),
+            "// Generated by `wrangler types` from the configuration of the Worker. Do not edit.\n\ndeclare global {\n  const CACHE: KVNamespace;\n  const LIMITS: { \"burst-size\": number[]; rps: number };\n  const REGION: string;\n  const API_TOKEN: string;\n}\n\nexport {};\n"
+        );
+    }
+
+    #[test]
+    fn it_skips_the_bindings_of_service_workers_that_are_not_identifiers() {
+        let target = Target {
+            target_type: TargetType::JavaScript,
+            vars: Some(
+                vec![
+                    ("API-URL".to_string(), json!("https://example.com")),
+                    ("class".to_string(), json!("premium")),
+                    ("$REGION".to_string(), json!("eu")),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            ..Default::default()
+        };
+        assert_eq!(
+            declarations(&target, &[]),
+            "// Generated by `wrangler types` from the configuration of the Worker. Do not edit.\n\ndeclare global {\n  const $REGION: string;\n}\n\nexport {};\n"
+        );
+    }
+
+    #[test]
+    fn it_declares_bindings_as_the_env_of_modules() {
+        let target = Target {
+            target_type: TargetType::JavaScript,
+            build: Some(Builder {
+                command: None,
+                cwd: Default::default(),
+                watch_dir: Default::default(),
+                upload: UploadFormat::Modules {
+                    main: "index.mjs".to_string(),
+                    dir: Default::default(),
+                    rules: None,
+                },
+                wasm: None,
+                rust: None,
+                define: None,
+                hooks: None,
+                compat: None,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            declarations(&target, &["API-TOKEN".to_string()]),
+            "// Generated by `wrangler types` from the configuration of the Worker. Do not edit.\n\ninterface Env {\n  \"API-TOKEN\": string;\n}\n"
+        );
+    }
+}
@@ -1,5 +1,7 @@
+use crate::commands::limits::{fetch_limits, AccountLimits};
 use crate::http;
 use crate::settings::global_user::GlobalUser;
+use crate::settings::{cache, profile};
 use crate::terminal::message::{Message, StdOut};
 use crate::terminal::{emoji, styles};
 use cloudflare::endpoints::account::{self, Account};
@@ -9,6 +11,98 @@ use cloudflare::framework::response::ApiFailure;
 
 use anyhow::Result;
 use prettytable::{Cell, Row, Table};
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+const API_BASE: &str = "https://api.cloudflare.com/client/v4";
+/// Memberships rarely change, so they are only fetched again after this long.
+const MEMBERSHIP_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Everything wrangler knows about the current credentials, printed by `whoami --json`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WhoamiOutput {
+    pub auth_type: &'static str,
+    pub email: Option<String>,
+    pub token: Option<TokenOutput>,
+    pub accounts: Vec<AccountOutput>,
+    pub zones: Vec<ZoneOutput>,
+    pub missing_permissions: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limits: Option<Vec<AccountLimits>>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenOutput {
+    pub id: Option<String>,
+    pub status: Option<String>,
+    pub expires_on: Option<String>,
+    pub not_before: Option<String>,
+    /// The names of the permission groups granted by the token's policies.
+    pub permission_groups: Option<Vec<String>>,
+    pub policies: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountOutput {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZoneOutput {
+    pub id: String,
+    pub name: String,
+    pub account_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct V4Response<T> {
+    success: bool,
+    #[serde(default)]
+    errors: Vec<V4Error>,
+    result: Option<T>,
+    result_info: Option<ResultInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct V4Error {
+    code: u16,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResultInfo {
+    total_pages: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenStatus {
+    id: String,
+    status: String,
+    expires_on: Option<String>,
+    not_before: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenDetails {
+    policies: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct ZoneItem {
+    id: String,
+    name: String,
+    account: Option<ZoneAccount>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ZoneAccount {
+    id: String,
+}
 
 /// Return a string representing the token type based on user
 fn get_token_type(
@@ -40,6 +134,9 @@ pub fn whoami(user: &GlobalUser) -> Result<()> {
         GlobalUser::OAuthTokenAuth { .. } => {
             get_token_type(user, &mut missing_permissions, "OAuth")?
         }
+        GlobalUser::ServiceTokenAuth {
+            access_client_id, ..
+        } => format!("a Service Token, with the client ID '{}'", access_client_id),
     };
 
     let accounts = fetch_accounts(user)?;
@@ -73,6 +170,189 @@ pub fn whoami(user: &GlobalUser) -> Result<()> {
     Ok(())
 }
 
+/// Tells CI who it is, verifying the token and listing what it can access as JSON, with the
+/// limits of its accounts if `limits` is set.
+pub fn whoami_json(user: &GlobalUser, limits: bool) -> Result<()> {
+    let mut missing_permissions: Vec<String> = Vec::with_capacity(2);
+    let client = http::legacy_auth_client(user);
+
+    let (auth_type, email, token) = match user {
+        GlobalUser::GlobalKeyAuth { email, .. } => ("globalApiKey", Some(email.clone()), None),
+        GlobalUser::ApiTokenAuth { .. } => {
+            let email = fetch_auth_token_email(user, &mut missing_permissions)?;
+            ("apiToken", email, Some(fetch_token(&client)?))
+        }
+        GlobalUser::OAuthTokenAuth {
+            expiration_time, ..
+        } => {
+            let email = fetch_auth_token_email(user, &mut missing_permissions)?;
+            let token = TokenOutput {
+                expires_on: Some(expiration_time.clone()),
+                ..Default::default()
+            };
+            ("oauthToken", email, Some(token))
+        }
+        GlobalUser::ServiceTokenAuth { .. } => ("serviceToken", None, None),
+    };
+
+    let accounts = fetch_accounts(user)?;
+    if accounts.is_empty() && !matches!(user, GlobalUser::GlobalKeyAuth { .. }) {
+        missing_permissions.push("Account Settings: Read".to_string());
+    }
+    let zones = match fetch_zones(&client)? {
+        Some(zones) => zones,
+        None => {
+            missing_permissions.push("Zone: Read".to_string());
+            Vec::new()
+        }
+    };
+
+    let inactive = token
+        .as_ref()
+        .and_then(|token| token.status.as_deref())
+        .filter(|status| *status != "active")
+        .map(String::from);
+
+    StdOut::as_json(&WhoamiOutput {
+        auth_type,
+        email,
+        token,
+        accounts: accounts
+            .into_iter()
+            .map(|account| AccountOutput {
+                id: account.id,
+                name: account.name,
+            })
+            .collect(),
+        zones,
+        missing_permissions,
+        limits: if limits {
+            Some(fetch_limits(user)?)
+        } else {
+            None
+        },
+    });
+
+    if let Some(status) = inactive {
+        anyhow::bail!("Your token has status \"{}\", not \"active\"", status);
+    }
+    Ok(())
+}
+
+/// Verify an API token, and list its policies if the token is allowed to read them.
+fn fetch_token(client: &Client) -> Result<TokenOutput> {
+    let addr = format!("{}/user/tokens/verify", API_BASE);
+    let response: V4Response<TokenStatus> = client.get(&addr).send()?.json()?;
+    let status = match response.result {
+        Some(status) if response.success => status,
+        _ => anyhow::bail!(
+            "Authentication check failed. Please make sure your API token is correct."
+        ),
+    };
+
+    // Reading the policies of a token needs the "API Tokens: Read" permission.
+    let addr = format!("{}/user/tokens/{}", API_BASE, status.id);
+    let policies = match client.get(&addr).send()?.json::<V4Response<TokenDetails>>() {
+        Ok(V4Response {
+            success: true,
+            result: Some(details),
+            ..
+        }) => Some(details.policies),
+        _ => None,
+    };
+    let permission_groups = policies.as_ref().and_then(Value::as_array).map(|policies| {
+        let mut names: Vec<String> = policies
+            .iter()
+            .filter_map(|policy| policy["permission_groups"].as_array())
+            .flatten()
+            .filter_map(|group| group["name"].as_str().map(String::from))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    });
+
+    Ok(TokenOutput {
+        id: Some(status.id),
+        status: Some(status.status),
+        expires_on: status.expires_on,
+        not_before: status.not_before,
+        permission_groups,
+        policies,
+    })
+}
+
+/// Fetch every zone the user can access, or `None` if they are not allowed to list zones.
+fn fetch_zones(client: &Client) -> Result<Option<Vec<ZoneOutput>>> {
+    let mut zones = Vec::new();
+    let mut page = 1;
+    loop {
+        let addr = format!("{}/zones?per_page=50&page={}", API_BASE, page);
+        let response: V4Response<Vec<ZoneItem>> = client.get(&addr).send()?.json()?;
+        if !response.success {
+            if response.errors.iter().any(|error| error.code == 9109) {
+                return Ok(None);
+            }
+            anyhow::bail!("Failed to list zones: {:?}", response.errors);
+        }
+        zones.extend(
+            response
+                .result
+                .unwrap_or_default()
+                .into_iter()
+                .map(|zone| ZoneOutput {
+                    id: zone.id,
+                    name: zone.name,
+                    account_id: zone.account.map(|account| account.id),
+                }),
+        );
+        let total_pages = response
+            .result_info
+            .and_then(|info| info.total_pages)
+            .unwrap_or(1);
+        if page >= total_pages {
+            break;
+        }
+        page += 1;
+    }
+    Ok(Some(zones))
+}
+
+/// Fetch the accounts of the user, reusing the list fetched by a recent command.
+pub(crate) fn fetch_accounts_cached(user: &GlobalUser) -> Result<Vec<AccountOutput>> {
+    let name = format!("accounts-{}", profile::active_profile());
+    if let Some(accounts) = cache::read(&name, MEMBERSHIP_CACHE_TTL) {
+        return Ok(accounts);
+    }
+    let accounts: Vec<AccountOutput> = fetch_accounts(user)?
+        .into_iter()
+        .map(|account| AccountOutput {
+            id: account.id,
+            name: account.name,
+        })
+        .collect();
+    if let Err(e) = cache::write(&name, &accounts) {
+        log::info!("Failed to cache accounts: {}", e);
+    }
+    Ok(accounts)
+}
+
+/// Fetch the zones the user can access, reusing the list fetched by a recent command.
+pub(crate) fn fetch_zones_cached(user: &GlobalUser) -> Result<Vec<ZoneOutput>> {
+    let name = format!("zones-{}", profile::active_profile());
+    if let Some(zones) = cache::read(&name, MEMBERSHIP_CACHE_TTL) {
+        return Ok(zones);
+    }
+    let zones = match fetch_zones(&http::legacy_auth_client(user))? {
+        Some(zones) => zones,
+        None => anyhow::bail!("Your token is not allowed to list zones (missing \"Zone: Read\")"),
+    };
+    if let Err(e) = cache::write(&name, &zones) {
+        log::info!("Failed to cache zones: {}", e);
+    }
+    Ok(zones)
+}
+
 /// Print information either containing the user's account IDs,
 /// or at least tell them where to get them.
 pub fn display_account_id_maybe() {
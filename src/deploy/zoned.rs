@@ -8,7 +8,7 @@ use cloudflare::framework::apiclient::ApiClient;
 
 use crate::http;
 use crate::settings::global_user::GlobalUser;
-use crate::settings::toml::{Route, RouteConfig};
+use crate::settings::toml::{Route, RouteConfig, RouteEntry, RouteZone};
 use crate::terminal::message::{Message, StdOut};
 
 #[derive(Clone, Debug, PartialEq)]
@@ -21,23 +21,18 @@ impl ZonedTarget {
     pub fn build(script_name: &str, route_config: &RouteConfig) -> Result<Self> {
         match route_config.zone_id.as_ref() {
             Some(zone_id) if !zone_id.is_empty() => {
-                let new_route = |route: &String| Route {
-                    id: None,
-                    script: Some(script_name.to_string()),
-                    pattern: route.to_string(),
-                };
                 let routes: Vec<Route> = route_config
                     .route
                     .iter()
-                    .map(new_route)
-                    .chain(route_config.routes.iter().flatten().filter_map(|route| {
-                        if route.is_empty() {
-                            StdOut::warn("your configuration file contains an empty route");
-                            None
-                        } else {
-                            Some(new_route(route))
-                        }
-                    }))
+                    .map(|route| new_route(script_name, route))
+                    .chain(
+                        route_config
+                            .routes
+                            .iter()
+                            .flatten()
+                            .filter(|route| route.zone().is_none())
+                            .filter_map(|route| non_empty_route(script_name, route)),
+                    )
                     .collect();
 
                 Ok(Self {
@@ -49,6 +44,38 @@ impl ZonedTarget {
         }
     }
 
+    /// Builds a target for each zone named by the `routes` entries with a zone of their own.
+    /// `zone_id_of` resolves the id of a zone from its name.
+    pub fn build_per_zone<F>(
+        script_name: &str,
+        route_config: &RouteConfig,
+        zone_id_of: F,
+    ) -> Result<Vec<Self>>
+    where
+        F: Fn(&str) -> Result<String>,
+    {
+        let mut targets: Vec<Self> = Vec::new();
+        for entry in route_config.routes.iter().flatten() {
+            let zone_id = match entry.zone() {
+                Some(RouteZone::Id(id)) => id.to_string(),
+                Some(RouteZone::Name(name)) => zone_id_of(name)?,
+                None => continue,
+            };
+            let route = match non_empty_route(script_name, entry) {
+                Some(route) => route,
+                None => continue,
+            };
+            match targets.iter_mut().find(|target| target.zone_id == zone_id) {
+                Some(target) => target.routes.push(route),
+                None => targets.push(Self {
+                    zone_id,
+                    routes: vec![route],
+                }),
+            }
+        }
+        Ok(targets)
+    }
+
     pub fn deploy(&self, user: &GlobalUser) -> Result<Vec<String>> {
         log::info!("publishing to zone {}", self.zone_id);
 
@@ -60,6 +87,23 @@ impl ZonedTarget {
     }
 }
 
+fn new_route(script_name: &str, pattern: &str) -> Route {
+    Route {
+        id: None,
+        script: Some(script_name.to_string()),
+        pattern: pattern.to_string(),
+    }
+}
+
+fn non_empty_route(script_name: &str, entry: &RouteEntry) -> Option<Route> {
+    if entry.pattern().is_empty() {
+        StdOut::warn("your configuration file contains an empty route");
+        None
+    } else {
+        Some(new_route(script_name, entry.pattern()))
+    }
+}
+
 pub fn publish_routes(
     user: &GlobalUser,
     zoned_config: &ZonedTarget,
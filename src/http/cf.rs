@@ -6,6 +6,7 @@ use cloudflare::framework::auth::Credentials;
 use cloudflare::framework::response::ApiFailure;
 use cloudflare::framework::{Environment, HttpApiClient, HttpApiClientConfig};
 use http::StatusCode;
+use reqwest::header::{HeaderMap, HeaderValue};
 
 use anyhow::Result;
 
@@ -32,10 +33,36 @@ pub fn get_environment() -> Result<Environment> {
     Ok(Environment::Custom(env_hostname))
 }
 
+/// The default headers of the clients of cloudflare-rs, with the headers of a service token that
+/// its credentials can't have.
+pub fn auth_headers(user: &GlobalUser) -> Result<HeaderMap> {
+    let mut headers = headers(None);
+    add_service_token_headers(&mut headers, user)?;
+    Ok(headers)
+}
+
+pub(super) fn add_service_token_headers(headers: &mut HeaderMap, user: &GlobalUser) -> Result<()> {
+    if let GlobalUser::ServiceTokenAuth {
+        access_client_id,
+        access_client_secret,
+    } = user
+    {
+        headers.insert(
+            "CF-Access-Client-Id",
+            HeaderValue::from_str(access_client_id)?,
+        );
+        headers.insert(
+            "CF-Access-Client-Secret",
+            HeaderValue::from_str(access_client_secret)?,
+        );
+    }
+    Ok(())
+}
+
 pub fn cf_v4_client(user: &GlobalUser) -> Result<HttpApiClient> {
     let config = HttpApiClientConfig {
         http_timeout: Duration::from_secs(DEFAULT_HTTP_TIMEOUT_SECONDS),
-        default_headers: headers(None),
+        default_headers: auth_headers(user)?,
     };
 
     let environment = get_environment()?;
@@ -46,7 +73,7 @@ pub fn cf_v4_client(user: &GlobalUser) -> Result<HttpApiClient> {
 pub fn cf_v4_api_client_async(user: &GlobalUser) -> Result<async_api::Client> {
     let config = HttpApiClientConfig {
         http_timeout: Duration::from_secs(DEFAULT_HTTP_TIMEOUT_SECONDS),
-        default_headers: headers(None),
+        default_headers: auth_headers(user)?,
     };
 
     let environment = get_environment()?;
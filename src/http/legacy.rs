@@ -3,6 +3,7 @@ use reqwest::header::{HeaderMap, HeaderValue};
 use reqwest::redirect::Policy;
 use std::time::Duration;
 
+use crate::http::cf::add_service_token_headers;
 use crate::http::{feature::headers, Feature, DEFAULT_HTTP_TIMEOUT_SECONDS};
 use crate::settings::global_user::GlobalUser;
 
@@ -62,5 +63,7 @@ fn add_auth_headers(headers: &mut HeaderMap, user: &GlobalUser) {
             headers.insert("X-Auth-Email", HeaderValue::from_str(email).unwrap());
             headers.insert("X-Auth-Key", HeaderValue::from_str(api_key).unwrap());
         }
+        GlobalUser::ServiceTokenAuth { .. } => add_service_token_headers(headers, user)
+            .expect("service tokens are checked when they are loaded"),
     }
 }
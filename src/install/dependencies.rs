@@ -1,2 +1,3 @@
 pub const WASM_PACK_VERSION: &str = "0.10.0";
 pub const GENERATE_VERSION: &str = "0.5.0";
+pub const WASM_OPT_VERSION: &str = "105.0.0";
@@ -29,7 +29,7 @@ pub fn install_cargo_generate() -> Result<PathBuf> {
     let version = Version::parse(dependencies::GENERATE_VERSION)?;
     install(tool_name, tool_author, is_binary, version)?
         .binary(tool_name)
-        .map_err(|e| anyhow::Error::from(e.compat()))
+        .map_err(|e| anyhow!(e.compat()))
 }
 
 pub fn install_wasm_pack() -> Result<PathBuf> {
@@ -42,6 +42,17 @@ pub fn install_wasm_pack() -> Result<PathBuf> {
         .map_err(|e| anyhow!(e.compat()))
 }
 
+/// Installs wasm-opt from the releases of binaryen.
+pub fn install_wasm_opt() -> Result<PathBuf> {
+    let tool_name = "wasm-opt";
+    let tool_author = "WebAssembly";
+    let is_binary = true;
+    let version = Version::parse(dependencies::WASM_OPT_VERSION)?;
+    install(tool_name, tool_author, is_binary, version)?
+        .binary(tool_name)
+        .map_err(|e| anyhow!(e.compat()))
+}
+
 pub fn install(
     tool_name: &str,
     owner: &str,
@@ -141,6 +152,24 @@ fn prebuilt_url(tool_name: &str, owner: &str, version: &str) -> Option<String> {
             "https://workers.cloudflare.com/get-wranglerjs-binary/{0}/v{1}.tar.gz",
             tool_name, version
         ))
+    } else if tool_name == "wasm-opt" {
+        // binaryen versions its releases with a single number
+        let version = version.split('.').next()?;
+        let target = if target::LINUX && target::x86_64 {
+            "x86_64-linux"
+        } else if target::MACOS && target::x86_64 {
+            "x86_64-macos"
+        } else if target::MACOS && target::aarch64 {
+            "arm64-macos"
+        } else if target::WINDOWS && target::x86_64 {
+            "x86_64-windows"
+        } else {
+            return None;
+        };
+        Some(format!(
+            "https://github.com/{0}/binaryen/releases/download/version_{1}/binaryen-version_{1}-{2}.tar.gz",
+            owner, version, target
+        ))
     } else {
         let target = if target::LINUX && target::x86_64 {
             "x86_64-unknown-linux-musl"
@@ -1,18 +1,24 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
 use std::time::Duration;
 
 use anyhow::Result;
 use indicatif::ProgressBar;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
 
 use cloudflare::endpoints::workerskv::delete_bulk::DeleteBulk;
 use cloudflare::endpoints::workerskv::write_bulk::KeyValuePair;
 use cloudflare::endpoints::workerskv::write_bulk::WriteBulk;
 use cloudflare::framework::apiclient::ApiClient;
 use cloudflare::framework::auth::Credentials;
+use cloudflare::framework::response::ApiFailure;
 use cloudflare::framework::{HttpApiClient, HttpApiClientConfig};
 
 use crate::commands::kv::format_error;
 use crate::http;
-use crate::http::feature::headers;
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
 
@@ -21,13 +27,24 @@ const API_MAX_PAIRS: usize = 10000;
 // hammering it with large requests.
 pub const BATCH_KEY_MAX: usize = API_MAX_PAIRS / 2;
 const UPLOAD_MAX_SIZE: usize = 50 * 1024 * 1024;
+// How many times a batch is sent when the API fails in a way that may not happen again.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// A key-value pair with the metadata that the pairs of cloudflare-rs can't have.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PairWithMetadata {
+    #[serde(flatten)]
+    pub pair: KeyValuePair,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+}
 
 // Create a special API client that has a longer timeout than usual, given that KV operations
 // can be lengthy if payloads are large.
 fn bulk_api_client(user: &GlobalUser) -> Result<HttpApiClient> {
     let config = HttpApiClientConfig {
         http_timeout: Duration::from_secs(5 * 60),
-        default_headers: headers(None),
+        default_headers: http::auth_headers(user)?,
     };
 
     let environment = http::get_environment()?;
@@ -44,15 +61,8 @@ pub fn put(
 ) -> Result<()> {
     let client = bulk_api_client(user)?;
 
-    for b in batch_keys_values(pairs) {
-        match client.request(&WriteBulk {
-            account_identifier: target.account_id.load()?,
-            namespace_identifier: namespace_id,
-            bulk_key_value_pairs: b.to_owned(),
-        }) {
-            Ok(_) => {}
-            Err(e) => anyhow::bail!("{}", format_error(e)),
-        }
+    for b in batch_keys_values(pairs, BATCH_KEY_MAX) {
+        write_batch(&client, target.account_id.load()?, namespace_id, &b)?;
 
         if let Some(pb) = &progress_bar {
             pb.inc(b.len() as u64);
@@ -62,6 +72,316 @@ pub fn put(
     Ok(())
 }
 
+/// Writes `pairs` in batches of at most `batch_key_max` pairs, `concurrency` batches at a time.
+/// Once a batch fails, the batches that haven't started are not sent.
+pub fn put_parallel(
+    target: &Target,
+    user: &GlobalUser,
+    namespace_id: &str,
+    pairs: Vec<KeyValuePair>,
+    batch_key_max: usize,
+    concurrency: usize,
+    progress_bar: &Option<ProgressBar>,
+) -> Result<()> {
+    let account_id = target.account_id.load()?.to_owned();
+    let batches = Arc::new(Mutex::new(batch_keys_values(pairs, batch_key_max)));
+
+    let workers: Vec<_> = (0..concurrency.max(1))
+        .map(|_| {
+            let batches = Arc::clone(&batches);
+            let user = user.clone();
+            let account_id = account_id.clone();
+            let namespace_id = namespace_id.to_owned();
+            let progress_bar = progress_bar.clone();
+            thread::spawn(move || -> Result<()> {
+                let client = bulk_api_client(&user)?;
+                loop {
+                    let batch = match batches.lock().unwrap().pop() {
+                        Some(batch) => batch,
+                        None => return Ok(()),
+                    };
+                    if let Err(e) = write_batch(&client, &account_id, &namespace_id, &batch) {
+                        batches.lock().unwrap().clear();
+                        return Err(e);
+                    }
+                    if let Some(pb) = &progress_bar {
+                        pb.inc(batch.len() as u64);
+                    }
+                }
+            })
+        })
+        .collect();
+
+    let mut result = Ok(());
+    for worker in workers {
+        let worker_result = worker
+            .join()
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("A thread uploading to Workers KV panicked")));
+        if result.is_ok() {
+            result = worker_result;
+        }
+    }
+    result
+}
+
+/// Writes pairs in batches as they are pushed, so that they don't have to be in memory at once.
+/// Each batch is sent once it is as large as a batch of `put`, and the last one by `finish`.
+/// The batches are sent by `concurrency` threads, and `push` waits while they are all busy so that
+/// at most one batch per thread is waiting in memory.
+pub struct StreamingPut {
+    user: GlobalUser,
+    account_id: String,
+    namespace_id: String,
+    // the bulk endpoint of cloudflare-rs doesn't send metadata
+    with_metadata: bool,
+    concurrency: usize,
+    batch: Vec<PairWithMetadata>,
+    batch_bytes: usize,
+    progress_bar: Option<ProgressBar>,
+    // the threads are started with the first batch
+    sender: Option<SyncSender<Vec<PairWithMetadata>>>,
+    workers: Vec<JoinHandle<Result<()>>>,
+    failed: Arc<AtomicBool>,
+}
+
+impl StreamingPut {
+    pub fn new(
+        target: &Target,
+        user: &GlobalUser,
+        namespace_id: &str,
+        with_metadata: bool,
+        progress_bar: Option<ProgressBar>,
+    ) -> Result<StreamingPut> {
+        let account_id = target.account_id.load()?;
+        StreamingPut::for_account(user, account_id, namespace_id, with_metadata, progress_bar)
+    }
+
+    /// Writes to a namespace of an account other than the one of a target.
+    pub fn for_account(
+        user: &GlobalUser,
+        account_id: &str,
+        namespace_id: &str,
+        with_metadata: bool,
+        progress_bar: Option<ProgressBar>,
+    ) -> Result<StreamingPut> {
+        Ok(StreamingPut {
+            user: user.clone(),
+            account_id: account_id.to_owned(),
+            namespace_id: namespace_id.to_owned(),
+            with_metadata,
+            concurrency: 1,
+            batch: Vec::new(),
+            batch_bytes: 0,
+            progress_bar,
+            sender: None,
+            workers: Vec::new(),
+            failed: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Sends up to `concurrency` batches at a time, instead of one.
+    pub fn concurrency(mut self, concurrency: usize) -> StreamingPut {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn push(&mut self, pair: PairWithMetadata) -> Result<()> {
+        let size = pair_size(&pair);
+        if self.batch.len() + 1 > BATCH_KEY_MAX || self.batch_bytes + size > UPLOAD_MAX_SIZE {
+            self.flush()?;
+        }
+        self.batch_bytes += size;
+        self.batch.push(pair);
+        Ok(())
+    }
+
+    /// Sends the last batch and waits for all the batches to be written.
+    pub fn finish(mut self) -> Result<()> {
+        self.flush()?;
+        self.join()
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+        // the pairs that follow a failed batch are not sent
+        if self.failed.load(Ordering::SeqCst) {
+            return self.join();
+        }
+        if self.sender.is_none() {
+            self.start();
+        }
+        let batch = std::mem::take(&mut self.batch);
+        self.batch_bytes = 0;
+        let sent = self.sender.as_ref().map(|sender| sender.send(batch));
+        match sent {
+            Some(Ok(())) => Ok(()),
+            // every thread stopped, after an error
+            _ => self.join(),
+        }
+    }
+
+    fn start(&mut self) {
+        let (sender, receiver) = mpsc::sync_channel(self.concurrency);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..self.concurrency {
+            let receiver = Arc::clone(&receiver);
+            let user = self.user.clone();
+            let account_id = self.account_id.clone();
+            let namespace_id = self.namespace_id.clone();
+            let with_metadata = self.with_metadata;
+            let progress_bar = self.progress_bar.clone();
+            let failed = Arc::clone(&self.failed);
+            self.workers.push(thread::spawn(move || {
+                let result = write_batches(
+                    &receiver,
+                    &user,
+                    &account_id,
+                    &namespace_id,
+                    with_metadata,
+                    &progress_bar,
+                );
+                if result.is_err() {
+                    failed.store(true, Ordering::SeqCst);
+                }
+                result
+            }));
+        }
+        self.sender = Some(sender);
+    }
+
+    /// Waits for the threads to write the batches they were sent, and returns the first error.
+    fn join(&mut self) -> Result<()> {
+        // the threads stop once the batches that were sent are all received
+        self.sender = None;
+        let mut result = Ok(());
+        for worker in self.workers.drain(..) {
+            let worker_result = worker.join().unwrap_or_else(|_| {
+                Err(anyhow::anyhow!("A thread uploading to Workers KV panicked"))
+            });
+            if result.is_ok() {
+                result = worker_result;
+            }
+        }
+        if result.is_ok() && self.failed.load(Ordering::SeqCst) {
+            result = Err(anyhow::anyhow!(
+                "The upload to Workers KV stopped after an error"
+            ));
+        }
+        result
+    }
+}
+
+/// Writes the batches that a thread of `StreamingPut` receives until there are no more.
+fn write_batches(
+    receiver: &Mutex<Receiver<Vec<PairWithMetadata>>>,
+    user: &GlobalUser,
+    account_id: &str,
+    namespace_id: &str,
+    with_metadata: bool,
+    progress_bar: &Option<ProgressBar>,
+) -> Result<()> {
+    let client = bulk_api_client(user)?;
+    let legacy_client = http::legacy_auth_client(user);
+    loop {
+        // the lock is released once a batch is received
+        let batch = match receiver.lock().unwrap().recv() {
+            Ok(batch) => batch,
+            Err(_) => return Ok(()),
+        };
+        if with_metadata {
+            write_batch_with_metadata(&legacy_client, account_id, namespace_id, &batch)?;
+        } else {
+            let pairs: Vec<KeyValuePair> = batch.iter().map(|pair| pair.pair.clone()).collect();
+            write_batch(&client, account_id, namespace_id, &pairs)?;
+        }
+        if let Some(pb) = progress_bar {
+            pb.inc(batch.len() as u64);
+        }
+    }
+}
+
+fn pair_size(pair: &PairWithMetadata) -> usize {
+    pair.pair.key.len()
+        + pair.pair.value.len()
+        + pair.metadata.as_ref().map_or(0, |m| m.to_string().len())
+}
+
+fn write_batch(
+    client: &HttpApiClient,
+    account_id: &str,
+    namespace_id: &str,
+    batch: &[KeyValuePair],
+) -> Result<()> {
+    with_retries(batch.len(), || {
+        client
+            .request(&WriteBulk {
+                account_identifier: account_id,
+                namespace_identifier: namespace_id,
+                bulk_key_value_pairs: batch.to_vec(),
+            })
+            .map(|_| ())
+    })
+}
+
+fn write_batch_with_metadata(
+    client: &reqwest::blocking::Client,
+    account_id: &str,
+    namespace_id: &str,
+    batch: &[PairWithMetadata],
+) -> Result<()> {
+    let api_endpoint = format!(
+        "https://api.cloudflare.com/client/v4/accounts/{}/storage/kv/namespaces/{}/bulk",
+        account_id, namespace_id
+    );
+    with_retries(batch.len(), || {
+        let res = client
+            .put(&api_endpoint)
+            .timeout(Duration::from_secs(5 * 60))
+            .json(&batch)
+            .send()
+            .map_err(ApiFailure::Invalid)?;
+        let status = res.status();
+        if status.is_success() {
+            Ok(())
+        } else {
+            Err(ApiFailure::Error(status, res.json().unwrap_or_default()))
+        }
+    })
+}
+
+// Sends a batch again after a server error, a rate limit or a network error, waiting a little
+// longer each time.
+fn with_retries(len: usize, send: impl Fn() -> Result<(), ApiFailure>) -> Result<()> {
+    let mut attempt = 1;
+    loop {
+        match send() {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < MAX_ATTEMPTS && is_transient(&e) => {
+                log::info!(
+                    "Retrying a batch of {} keys after attempt {} failed: {}",
+                    len,
+                    attempt,
+                    format_error(e)
+                );
+                thread::sleep(Duration::from_secs(1 << (attempt - 1)));
+                attempt += 1;
+            }
+            Err(e) => anyhow::bail!("{}", format_error(e)),
+        }
+    }
+}
+
+fn is_transient(e: &ApiFailure) -> bool {
+    match e {
+        ApiFailure::Error(status, _) => {
+            status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+        }
+        ApiFailure::Invalid(_) => true,
+    }
+}
+
 pub fn delete(
     target: &Target,
     user: &GlobalUser,
@@ -89,16 +409,25 @@ pub fn delete(
     Ok(())
 }
 
-fn batch_keys_values(mut pairs: Vec<KeyValuePair>) -> Vec<Vec<KeyValuePair>> {
-    let mut batches: Vec<Vec<KeyValuePair>> = Vec::new();
+fn batch_keys_values(pairs: Vec<KeyValuePair>, batch_key_max: usize) -> Vec<Vec<KeyValuePair>> {
+    batch(pairs, batch_key_max, |pair| {
+        pair.key.len() + pair.value.len()
+    })
+}
+
+fn batch<T: Clone>(
+    mut pairs: Vec<T>,
+    batch_key_max: usize,
+    size: impl Fn(&T) -> usize,
+) -> Vec<Vec<T>> {
+    let mut batches: Vec<Vec<T>> = Vec::new();
 
     if !pairs.is_empty() {
         // Iterate over all key-value pairs and create batches of uploads, each of which are
-        // maximum 5K key-value pairs in size OR maximum ~50MB in size. Upload each batch
-        // as it is created.
+        // maximum `batch_key_max` key-value pairs in size OR maximum ~50MB in size.
         let mut key_count = 0;
         let mut key_pair_bytes = 0;
-        let mut key_value_batch: Vec<KeyValuePair> = Vec::new();
+        let mut key_value_batch: Vec<T> = Vec::new();
 
         while !(pairs.is_empty() && key_value_batch.is_empty()) {
             if pairs.is_empty() {
@@ -107,9 +436,9 @@ fn batch_keys_values(mut pairs: Vec<KeyValuePair>) -> Vec<Vec<KeyValuePair>> {
                 key_value_batch.clear();
             } else {
                 let pair = pairs.pop().unwrap();
-                if key_count + 1 > BATCH_KEY_MAX
+                if key_count + 1 > batch_key_max
                 // Keep upload size small to keep KV bulk API happy
-                || key_pair_bytes + pair.key.len() + pair.value.len() > UPLOAD_MAX_SIZE
+                || key_pair_bytes + size(&pair) > UPLOAD_MAX_SIZE
                 {
                     batches.push(key_value_batch.to_vec());
                     key_count = 0;
@@ -119,7 +448,7 @@ fn batch_keys_values(mut pairs: Vec<KeyValuePair>) -> Vec<Vec<KeyValuePair>> {
 
                 // Add the popped key-value pair to the running batch of key-value pair uploads
                 key_count += 1;
-                key_pair_bytes = key_pair_bytes + pair.key.len() + pair.value.len();
+                key_pair_bytes += size(&pair);
                 key_value_batch.push(pair);
             }
         }
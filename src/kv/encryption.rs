@@ -0,0 +1,161 @@
+//! Client-side encryption of values with age (https://age-encryption.org), so that KV only stores
+//! values that the holders of the identities of their recipients can read. The keys are made with
+//! `age-keygen`, and values are encrypted to their public keys, like `age --recipient` would.
+
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use age::{x25519, Decryptor, Encryptor, IdentityFile};
+use anyhow::{anyhow, Result};
+
+use crate::kv::bulk::PairWithMetadata;
+
+const AGE_PREFIX: &str = "age:";
+
+/// A recipient of `--encrypt`, in the form `age:<public key>`.
+#[derive(Clone)]
+pub struct Recipient(x25519::Recipient);
+
+impl FromStr for Recipient {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let key = s.strip_prefix(AGE_PREFIX).ok_or_else(|| {
+            anyhow!("Recipients should be in the form age:<public key>, e.g. age:age1...")
+        })?;
+        let recipient = key
+            .parse()
+            .map_err(|e| anyhow!("`{}` is not the public key of an age identity: {}", key, e))?;
+        Ok(Recipient(recipient))
+    }
+}
+
+impl fmt::Debug for Recipient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", AGE_PREFIX, self.0)
+    }
+}
+
+/// Encrypts a value to all the recipients, any of which can decrypt it.
+pub fn encrypt(recipients: &[Recipient], value: &[u8]) -> Result<Vec<u8>> {
+    let recipients = recipients
+        .iter()
+        .map(|recipient| Box::new(recipient.0.clone()) as Box<dyn age::Recipient>)
+        .collect();
+    let mut encrypted = Vec::new();
+    let mut writer = Encryptor::with_recipients(recipients)
+        .wrap_output(&mut encrypted)
+        .map_err(|e| anyhow!("Failed to encrypt the value: {}", e))?;
+    writer.write_all(value)?;
+    writer.finish()?;
+    Ok(encrypted)
+}
+
+/// Encrypts the value of a pair, which is then uploaded in base64 since it is binary. The metadata
+/// is not encrypted.
+pub fn encrypt_pair(
+    recipients: &[Recipient],
+    mut pair: PairWithMetadata,
+) -> Result<PairWithMetadata> {
+    let value = if pair.pair.base64.unwrap_or(false) {
+        base64::decode(&pair.pair.value)
+            .map_err(|e| anyhow!("The value of key `{}` is not base64: {}", pair.pair.key, e))?
+    } else {
+        pair.pair.value.into_bytes()
+    };
+    pair.pair.value = base64::encode(encrypt(recipients, &value)?);
+    pair.pair.base64 = Some(true);
+    Ok(pair)
+}
+
+/// The identities of an identity file of `--decrypt`, as written by `age-keygen`.
+pub struct Identities(Vec<x25519::Identity>);
+
+impl Identities {
+    pub fn from_file(path: &Path) -> Result<Identities> {
+        let identities = IdentityFile::from_file(path.to_string_lossy().into_owned())
+            .map_err(|e| anyhow!("Failed to read the identities of {}: {}", path.display(), e))?
+            .into_identities();
+        if identities.is_empty() {
+            anyhow::bail!("{} has no age identities", path.display());
+        }
+        Ok(Identities(identities))
+    }
+
+    /// Decrypts a value that was encrypted to one of the identities.
+    pub fn decrypt(&self, value: &[u8]) -> Result<Vec<u8>> {
+        let decryptor = match Decryptor::new(value) {
+            Ok(Decryptor::Recipients(decryptor)) => decryptor,
+            Ok(Decryptor::Passphrase(_)) => {
+                anyhow::bail!("The value is encrypted with a passphrase rather than to a recipient")
+            }
+            Err(e) => anyhow::bail!("The value is not encrypted with age: {}", e),
+        };
+        let mut reader = decryptor
+            .decrypt(self.0.iter().map(|identity| identity as &dyn age::Identity))
+            .map_err(|e| anyhow!("Failed to decrypt the value: {}", e))?;
+        let mut decrypted = Vec::new();
+        reader.read_to_end(&mut decrypted)?;
+        Ok(decrypted)
+    }
+
+    /// Decrypts the value of a pair that `encrypt_pair` encrypted, keeping it in base64 only if
+    /// it is not UTF-8.
+    pub fn decrypt_pair(&self, mut pair: PairWithMetadata) -> Result<PairWithMetadata> {
+        let value = if pair.pair.base64.unwrap_or(false) {
+            base64::decode(&pair.pair.value)?
+        } else {
+            pair.pair.value.into_bytes()
+        };
+        let decrypted = self
+            .decrypt(&value)
+            .map_err(|e| anyhow!("{} (key `{}`)", e, pair.pair.key))?;
+        let (value, base64) = match String::from_utf8(decrypted) {
+            Ok(value) => (value, None),
+            Err(e) => (base64::encode(e.as_bytes()), Some(true)),
+        };
+        pair.pair.value = value;
+        pair.pair.base64 = base64;
+        Ok(pair)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cloudflare::endpoints::workerskv::write_bulk::KeyValuePair;
+
+    #[test]
+    fn it_decrypts_pairs_it_encrypted() {
+        let identity = x25519::Identity::generate();
+        let recipient = Recipient(identity.to_public());
+        let pair = PairWithMetadata {
+            pair: KeyValuePair {
+                key: "secret".to_string(),
+                value: "hunter2".to_string(),
+                expiration: None,
+                expiration_ttl: None,
+                base64: None,
+            },
+            metadata: None,
+        };
+
+        let encrypted = encrypt_pair(&[recipient], pair).unwrap();
+        assert_eq!(encrypted.pair.base64, Some(true));
+        assert_ne!(encrypted.pair.value, "hunter2");
+
+        let decrypted = Identities(vec![identity]).decrypt_pair(encrypted).unwrap();
+        assert_eq!(decrypted.pair.value, "hunter2");
+        assert_eq!(decrypted.pair.base64, None);
+    }
+
+    #[test]
+    fn it_parses_age_recipients() {
+        let recipient = "age:age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p";
+        assert!(recipient.parse::<Recipient>().is_ok());
+        assert!(recipient[4..].parse::<Recipient>().is_err());
+        assert!("age:age1invalid".parse::<Recipient>().is_err());
+    }
+}
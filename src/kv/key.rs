@@ -1,4 +1,6 @@
 use anyhow::Result;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
 use serde_json::value::Value as JsonValue;
 
 use cloudflare::endpoints::workerskv::list_namespace_keys::ListNamespaceKeys;
@@ -8,8 +10,12 @@ use cloudflare::framework::apiclient::ApiClient;
 use cloudflare::framework::response::ApiFailure;
 use cloudflare::framework::HttpApiClient;
 
+use crate::commands::kv::format_error;
 use crate::settings::toml::Target;
 
+// The most keys the API lists at once
+const PAGE_MAX: usize = 1000;
+
 pub struct KeyList {
     keys_result: Option<Vec<Key>>,
     prefix: Option<String>,
@@ -18,6 +24,8 @@ pub struct KeyList {
     namespace_id: String,
     cursor: Option<String>,
     init_fetch: bool,
+    // How many more keys are listed, if limited
+    remaining: Option<usize>,
 }
 
 impl KeyList {
@@ -35,13 +43,29 @@ impl KeyList {
             namespace_id: namespace_id.to_string(),
             cursor: None,
             init_fetch: false,
+            remaining: None,
         };
         Ok(iter)
     }
 
+    /// Lists at most `limit` keys, starting at the `cursor` that an earlier listing stopped at.
+    pub fn page(mut self, cursor: Option<String>, limit: Option<usize>) -> KeyList {
+        self.cursor = cursor;
+        self.remaining = limit;
+        self
+    }
+
+    /// Where the listing stopped, if some keys were not listed because of the limit.
+    pub fn cursor(&self) -> Option<&str> {
+        self.cursor.as_deref()
+    }
+
     fn request_params(&self) -> ListNamespaceKeys {
         let params = ListNamespaceKeysParams {
-            limit: None, // Defaults to 1000 (the maximum)
+            // Defaults to 1000 (the maximum)
+            limit: self
+                .remaining
+                .map(|remaining| remaining.min(PAGE_MAX) as u16),
             cursor: self.cursor.to_owned(),
             prefix: self.prefix.to_owned(),
         };
@@ -60,45 +84,50 @@ impl KeyList {
             Ok(success) => {
                 self.cursor = extract_cursor(success.result_info.clone());
                 log::info!("{:?}", self.cursor);
-                Ok(success.result)
+                // the keys are popped from the end
+                let mut keys = success.result;
+                keys.reverse();
+                Ok(keys)
             }
             Err(e) => Err(e),
         }
     }
+
+    fn next_key(&mut self) -> Option<Result<Key, ApiFailure>> {
+        // a page may have no keys even if more follow
+        loop {
+            // Attempt to extract next key from vector of keys in KeyList.
+            // If no key vector or no keys left, go to fallback case below to
+            // attempt to fetch the next page of keys from the Workers KV API.
+            if let Some(key) = self.keys_result.as_mut().and_then(Vec::pop) {
+                return Some(Ok(key));
+            }
+            // Fallback case (if no remaining keys are found)
+            if self.cursor.is_none() && self.init_fetch {
+                return None; // Nothing left to fetch
+            }
+            // At this point, initial fetch is being performed.
+            self.init_fetch = true;
+            match self.get_batch() {
+                Ok(keys) => self.keys_result = Some(keys),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
 }
 
 impl Iterator for KeyList {
     type Item = Result<Key, ApiFailure>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        // Attempt to extract next key from vector of keys in KeyList.
-        // If no key vector or no keys left, go to fallback case below to
-        // attempt to fetch the next page of keys from the Workers KV API.
-        if let Some(mut keys) = self.keys_result.to_owned() {
-            let key = keys.pop();
-            self.keys_result = Some(keys);
-
-            if let Some(k) = key {
-                return Some(Ok(k));
-            }
+        if self.remaining == Some(0) {
+            return None;
         }
-        // Fallback case (if no remaining keys are found)
-        if self.cursor.is_none() && self.init_fetch {
-            None // Nothing left to fetch
-        } else {
-            if !self.init_fetch {
-                // At this point, initial fetch is being performed.
-                self.init_fetch = true;
-            }
-            match self.get_batch() {
-                Ok(mut keys) => {
-                    let key = keys.pop();
-                    self.keys_result = Some(keys);
-                    key.map(Ok)
-                }
-                Err(e) => Some(Err(e)),
-            }
+        let key = self.next_key();
+        if let (Some(Ok(_)), Some(remaining)) = (&key, self.remaining.as_mut()) {
+            *remaining -= 1;
         }
+        key
     }
 }
 
@@ -113,3 +142,76 @@ fn extract_cursor(result_info: Option<JsonValue>) -> Option<String> {
         Some(returned_cursor)
     }
 }
+
+/// A key with the metadata and expiration that the keys of cloudflare-rs don't have. It is
+/// serialized with all of its fields, so that listings are parsed the same whatever the keys have.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct KeyWithMetadata {
+    pub name: String,
+    pub expiration: Option<i64>,
+    pub metadata: Option<JsonValue>,
+}
+
+#[derive(Deserialize)]
+struct KeysWithMetadata {
+    result: Vec<KeyWithMetadata>,
+    result_info: Option<JsonValue>,
+}
+
+/// Lists every key of a namespace with its metadata, following the cursors. `client` is an
+/// authenticated client of http::legacy_auth_client.
+pub fn list_with_metadata(
+    client: &Client,
+    target: &Target,
+    namespace_id: &str,
+) -> Result<Vec<KeyWithMetadata>> {
+    let mut keys = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let (page, next) = list_page(client, target, namespace_id, None, cursor.as_deref(), None)?;
+        keys.extend(page);
+        cursor = next;
+        if cursor.is_none() {
+            return Ok(keys);
+        }
+    }
+}
+
+/// Lists a page of at most `limit` keys with their metadata, starting at `cursor`, and returns the
+/// cursor of the page that follows if there is one.
+pub fn list_page(
+    client: &Client,
+    target: &Target,
+    namespace_id: &str,
+    prefix: Option<&str>,
+    cursor: Option<&str>,
+    limit: Option<usize>,
+) -> Result<(Vec<KeyWithMetadata>, Option<String>)> {
+    let api_endpoint = format!(
+        "https://api.cloudflare.com/client/v4/accounts/{}/storage/kv/namespaces/{}/keys",
+        target.account_id.load()?,
+        namespace_id
+    );
+    let mut request = client.get(&api_endpoint);
+    if let Some(prefix) = prefix {
+        request = request.query(&[("prefix", prefix)]);
+    }
+    if let Some(cursor) = cursor {
+        request = request.query(&[("cursor", cursor)]);
+    }
+    if let Some(limit) = limit {
+        request = request.query(&[("limit", limit.min(PAGE_MAX))]);
+    }
+    let res = request.send()?;
+    let status = res.status();
+    if !status.is_success() {
+        let errors = res.json().unwrap_or_default();
+        anyhow::bail!("{}", format_error(ApiFailure::Error(status, errors)));
+    }
+    let page: KeysWithMetadata = res.json()?;
+    let cursor = page
+        .result_info
+        .and_then(|info| info["cursor"].as_str().map(str::to_string))
+        .filter(|cursor| !cursor.is_empty());
+    Ok((page.result, cursor))
+}
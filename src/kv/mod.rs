@@ -1,3 +1,4 @@
 pub mod bulk;
+pub mod encryption;
 pub mod key;
 pub mod namespace;
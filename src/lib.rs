@@ -10,6 +10,7 @@ extern crate erased_serde;
 use cloudflare::framework::response::ApiErrors;
 
 mod build;
+mod bundler;
 pub mod cli;
 pub mod preview;
 pub use build::build_target;
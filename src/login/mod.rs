@@ -3,41 +3,78 @@ pub mod http;
 use anyhow::Result;
 use chrono::{DateTime, Duration, Utc};
 
-use oauth2::basic::BasicClient;
+use oauth2::basic::{
+    BasicErrorResponse, BasicRevocationErrorResponse, BasicTokenIntrospectionResponse,
+    BasicTokenType,
+};
 use oauth2::reqwest::http_client;
 
 use oauth2::{
-    AuthType, AuthUrl, AuthorizationCode, ClientId, CsrfToken, PkceCodeChallenge, RedirectUrl,
-    RefreshToken, Scope, TokenResponse, TokenUrl,
+    AuthType, AuthUrl, AuthorizationCode, Client, ClientId, CsrfToken, ExtraTokenFields,
+    PkceCodeChallenge, RedirectUrl, RefreshToken, Scope, StandardRevocableToken,
+    StandardTokenResponse, TokenResponse, TokenUrl,
 };
+use serde::{Deserialize, Serialize};
 
-use crate::terminal::{interactive, open_browser};
+use crate::terminal::message::{Message, StdErr, StdOut};
+use crate::terminal::{interactive, open_browser, styles};
 
 use crate::cli::login::SCOPES_LIST;
 use crate::commands::config::global_config;
 use crate::commands::logout::invalidate_oauth_token;
 use crate::login::http::http_server_get_params;
-use crate::settings::{get_global_config_path, global_user::GlobalUser};
+use crate::settings::{get_global_config_path, global_user::GlobalUser, profile};
 
 pub static CLIENT_ID: &str = "54d11594-84e4-41aa-b438-e81b8fa78ee7";
 pub static AUTH_URL: &str = "https://dash.cloudflare.com/oauth2/auth";
 static TOKEN_URL: &str = "https://dash.cloudflare.com/oauth2/token";
 static CALLBACK_URL: &str = "http://localhost:8976/oauth/callback";
 
-pub fn run(scopes: Option<&[String]>) -> Result<()> {
+/// Access tokens are refreshed this long before they expire, so that they do not expire in the
+/// middle of a command.
+const REFRESH_MARGIN_SECS: i64 = 5 * 60;
+/// Users are warned this long before they need to run `wrangler login` again.
+const LOGIN_EXPIRY_WARNING_DAYS: i64 = 7;
+
+/// The fields of a token response besides the standard ones, which tell when the refresh token
+/// stops working, and so when `wrangler login` must be run again.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct RefreshTokenExpiry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    refresh_token_expires_in: Option<u64>,
+}
+
+impl ExtraTokenFields for RefreshTokenExpiry {}
+
+type LoginTokenResponse = StandardTokenResponse<RefreshTokenExpiry, BasicTokenType>;
+
+type LoginClient = Client<
+    BasicErrorResponse,
+    LoginTokenResponse,
+    BasicTokenType,
+    BasicTokenIntrospectionResponse,
+    StandardRevocableToken,
+    BasicRevocationErrorResponse,
+>;
+
+// Create oauth2 client
+fn client() -> Result<LoginClient> {
     let auth_url = AuthUrl::new(AUTH_URL.to_string())?;
     let token_url = TokenUrl::new(TOKEN_URL.to_string())?;
     let redirect_url = RedirectUrl::new(CALLBACK_URL.to_string())?;
 
-    // Create oauth2 client
-    let client = BasicClient::new(
+    Ok(LoginClient::new(
         ClientId::new(CLIENT_ID.to_string()),
         None,
         auth_url,
         Some(token_url),
     )
     .set_redirect_uri(redirect_url)
-    .set_auth_type(AuthType::RequestBody);
+    .set_auth_type(AuthType::RequestBody))
+}
+
+pub fn run(scopes: Option<&[String]>, keychain: bool) -> Result<()> {
+    let client = client()?;
 
     let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
 
@@ -144,83 +181,143 @@ pub fn run(scopes: Option<&[String]>) -> Result<()> {
 
     // Invalidate previous OAuth token if present
     invalidate_oauth_token("`wrangler login`".to_string());
-    global_config(&user, false)?;
+    global_config(&user, false, keychain)?;
+    record_login(&token_response)?;
 
     Ok(())
 }
 
-// Refresh an expired access token
+// Record when the tokens were issued and, if the server says so, when they stop working
+fn record_login(token_response: &LoginTokenResponse) -> Result<()> {
+    let config_path = get_global_config_path();
+    let now = Utc::now();
+    profile::set_logged_in_at(&config_path, &now.to_rfc3339())?;
+    if let Some(expires_in) = token_response.extra_fields().refresh_token_expires_in {
+        let expires_at = now + Duration::seconds(expires_in as i64);
+        profile::set_login_expires_at(&config_path, &expires_at.to_rfc3339())?;
+    }
+    Ok(())
+}
+
+// Renew the access token of `wrangler login` now, without waiting for it to expire
+pub fn refresh() -> Result<()> {
+    let mut user = GlobalUser::new()?;
+    if !matches!(user, GlobalUser::OAuthTokenAuth { .. }) {
+        anyhow::bail!("Only credentials created by `wrangler login` can be refreshed")
+    }
+    refresh_oauth_token(&mut user)?;
+    StdOut::success(&format!(
+        "Refreshed your access token, which now expires at {}",
+        user.get_expiration_time()
+    ));
+    warn_if_login_expires_soon(&user);
+    Ok(())
+}
+
+// Warn users whose `wrangler login` is about to stop working, so that it doesn't happen in CI
+pub fn warn_if_login_expires_soon(user: &GlobalUser) {
+    if !matches!(user, GlobalUser::OAuthTokenAuth { .. }) {
+        return;
+    }
+    // Only the server knows how long a login lasts, so nothing is said when it didn't tell
+    let expires_at = match profile::login_expires_at(&get_global_config_path())
+        .and_then(|time| DateTime::parse_from_rfc3339(&time).ok())
+    {
+        Some(time) => time,
+        None => return,
+    };
+    let remaining = expires_at.signed_duration_since(Utc::now());
+    if remaining < Duration::days(LOGIN_EXPIRY_WARNING_DAYS) {
+        let status = if remaining.num_days() > 0 {
+            format!("expires in {} days", remaining.num_days())
+        } else if remaining.num_seconds() > 0 {
+            "expires today".to_string()
+        } else {
+            "may have expired".to_string()
+        };
+        StdErr::warn(&format!(
+            "Your login {}. Run {} again to renew it.",
+            status,
+            styles::highlight("`wrangler login`")
+        ));
+    }
+}
+
+// Refresh an access token which has expired or is about to
 pub fn check_update_oauth_token(user: &mut GlobalUser) -> Result<()> {
     if let GlobalUser::OAuthTokenAuth { .. } = user {
-        log::debug!("Refreshing access token..");
-
         let expiration_time = DateTime::parse_from_rfc3339(user.get_expiration_time())?;
         let current_time = Utc::now();
         // Note: duration can panic if the time elapsed (in seconds) cannot be stored in i64
         let duration = current_time.signed_duration_since(expiration_time);
 
-        // Access token expired
-        // Refresh token before 20 seconds from actual expiration time to avoid minute details
-        if duration.num_seconds() >= -20 {
-            let auth_url = AuthUrl::new(AUTH_URL.to_string())?;
-            let token_url = TokenUrl::new(TOKEN_URL.to_string())?;
-            let redirect_url = RedirectUrl::new(CALLBACK_URL.to_string())?;
-
-            // Create oauth2 client
-            let client = BasicClient::new(
-                ClientId::new(CLIENT_ID.to_string()),
-                None,
-                auth_url,
-                Some(token_url),
-            )
-            .set_redirect_uri(redirect_url)
-            .set_auth_type(AuthType::RequestBody);
-
-            // Exchange refresh token with new access token
-            let refresh_token = user.get_refresh_token();
-            let token_response = client
-                .exchange_refresh_token(&RefreshToken::new(refresh_token.to_string()))
-                .request(http_client)?;
-
-            // Set new access token
-            let access_token = token_response.access_token().secret();
-            user.set_oauth_token(access_token.to_string());
-
-            // Set new refresh token
-            let new_refresh_token = token_response.refresh_token();
-            if let Some(token) = new_refresh_token {
-                user.set_refresh_token(token.secret().to_string());
-            } else {
-                anyhow::bail!(display_error_info(
-                    "Failed to receive refresh token while updating access token."
-                ))
-            }
-
-            // Set new expiration time
-            let expires_in = match token_response.expires_in() {
-                Some(time) => time,
-                None => anyhow::bail!(display_error_info(
-                    "Failed to receive access_token expire time while updating access token."
-                )),
-            };
-            let expiration_time =
-                match Utc::now().checked_add_signed(Duration::from_std(expires_in)?) {
-                    Some(time) => time,
-                    None => anyhow::bail!(display_error_info(
-                    "Failed to calculate access_token expiration time while updating access token."
-                )),
-                };
-            let expiration_time = expiration_time.to_rfc3339();
-            user.set_expiration_time(expiration_time);
-
-            // Update configuration file on disk
-            let config_file = get_global_config_path();
-            user.to_file(&config_file)?
+        // Refresh the token a few minutes before it expires, so that it stays valid for the
+        // whole command instead of failing midway through
+        if duration.num_seconds() >= -REFRESH_MARGIN_SECS {
+            refresh_oauth_token(user)?;
         }
     }
     Ok(())
 }
 
+// Exchange the refresh token for a new access token and refresh token
+pub fn refresh_oauth_token(user: &mut GlobalUser) -> Result<()> {
+    if let GlobalUser::OAuthTokenAuth { .. } = user {
+        log::debug!("Refreshing access token..");
+        let client = client()?;
+
+        // Exchange refresh token with new access token
+        let refresh_token = user.get_refresh_token();
+        let token_response = match client
+            .exchange_refresh_token(&RefreshToken::new(refresh_token.to_string()))
+            .request(http_client)
+        {
+            Ok(response) => response,
+            Err(e) => anyhow::bail!(
+                "Failed to refresh your access token ({}); your login may have expired. Please run `wrangler login` again.",
+                e
+            ),
+        };
+
+        // Set new access token
+        let access_token = token_response.access_token().secret();
+        user.set_oauth_token(access_token.to_string());
+
+        // Set new refresh token
+        let new_refresh_token = token_response.refresh_token();
+        if let Some(token) = new_refresh_token {
+            user.set_refresh_token(token.secret().to_string());
+        } else {
+            anyhow::bail!(display_error_info(
+                "Failed to receive refresh token while updating access token."
+            ))
+        }
+
+        // Set new expiration time
+        let expires_in = match token_response.expires_in() {
+            Some(time) => time,
+            None => anyhow::bail!(display_error_info(
+                "Failed to receive access_token expire time while updating access token."
+            )),
+        };
+        let expiration_time = match Utc::now().checked_add_signed(Duration::from_std(expires_in)?) {
+            Some(time) => time,
+            None => anyhow::bail!(display_error_info(
+                "Failed to calculate access_token expiration time while updating access token."
+            )),
+        };
+        let expiration_time = expiration_time.to_rfc3339();
+        user.set_expiration_time(expiration_time);
+
+        // Update configuration file on disk
+        let config_file = get_global_config_path();
+        user.to_file(&config_file)?;
+        // The refresh token was replaced, so the login lasts from now
+        record_login(&token_response)?
+    }
+    Ok(())
+}
+
 // Adds additional info besides an error message
 pub fn display_error_info(error_msg: &str) -> String {
     let error_info = format!("{} Please run `wrangler login` again. If the error persists, consider reporting the issue through `wrangler report`.", error_msg);
@@ -4,11 +4,18 @@ extern crate text_io;
 extern crate tokio;
 
 use std::env;
+use std::path::Path;
 
+use wrangler::cli::alias;
+use wrangler::cli::config::ConfigCommand;
 use wrangler::cli::{exec, Cli, Command};
 use wrangler::commands;
+use wrangler::commands::generate::GitOptions;
 use wrangler::installer;
 use wrangler::reporter;
+use wrangler::settings::{self, profile, toml::CONFIG_FILE_NAMES};
+use wrangler::terminal::message::{Message, StdErr};
+use wrangler::terminal::styles;
 use wrangler::version::check_for_updates;
 
 use anyhow::Result;
@@ -38,23 +45,134 @@ fn main() -> Result<()> {
 }
 
 fn run() -> Result<()> {
-    let cli = Cli::from_args();
+    let mut cli = Cli::from_iter(alias::expand(env::args_os().collect())?);
+    // Projects may use wrangler.json or wrangler.yaml instead of the default wrangler.toml
+    if cli.config == Path::new(CONFIG_FILE_NAMES[0]) && !cli.config.exists() {
+        if let Some(config) = settings::toml::find_config_file(Path::new(".")) {
+            cli.config = config;
+        }
+    }
+    // e.g. `--env production` is implied on the branch main with `[env_mapping] main = "production"`
+    if cli.environment.is_none() {
+        if let Some((environment, branch)) =
+            settings::toml::env_mapping::environment_for_branch(&cli.config)?
+        {
+            StdErr::info(&format!(
+                "Using the environment {} for the git branch {}",
+                styles::highlight(&environment),
+                styles::bold(&branch)
+            ));
+            cli.environment = Some(environment);
+        }
+    }
     let cli_params = cli.clone();
 
+    if let Some(profile) = &cli.profile {
+        profile::set_active_profile(profile)?;
+    } else {
+        profile::validate_active_profile()?;
+    }
+    profile::apply_color_preference(&settings::get_global_config_path())?;
+    if let Some(environment) = &cli.environment {
+        // e.g. $CF_API_TOKEN_PRODUCTION is used as $CF_API_TOKEN with `--env production`
+        settings::apply_environment_overrides(environment);
+    }
+    if let Some(account_id) = &cli.account_id {
+        settings::toml::set_account_id_override(account_id);
+    }
+    if cli.strict {
+        settings::toml::set_strict_mode();
+    }
+
     match cli.command {
-        Command::Config { api_key, no_verify } => exec::configure(api_key, no_verify),
+        Command::Config {
+            command: Some(ConfigCommand::ListProfiles),
+            ..
+        } => exec::list_profiles(),
+        Command::Config {
+            command: Some(ConfigCommand::UseKeychain),
+            ..
+        } => exec::use_keychain(),
+        Command::Config {
+            command: Some(ConfigCommand::Migrate),
+            ..
+        } => exec::migrate(&cli_params),
+        Command::Config {
+            command: Some(ConfigCommand::Schema { out }),
+            ..
+        } => exec::schema(out),
+        Command::Config {
+            api_key,
+            service_token,
+            no_verify,
+            keychain,
+            default_account_id,
+            command: None,
+        } => exec::configure(
+            api_key,
+            service_token,
+            no_verify,
+            keychain,
+            default_account_id,
+        ),
         Command::Generate {
             name,
             site,
             template,
+            branch,
+            defines,
             target_type,
-        } => exec::generate(name, site, template, target_type),
+            yes,
+            list,
+            search,
+            workspace,
+            no_git,
+            git_branch,
+            commit,
+            no_install,
+        } => {
+            let git = GitOptions::new(no_git, git_branch, commit);
+            if !workspace.is_empty() {
+                return exec::generate_workspace(name, workspace, &git);
+            }
+            let template = if list || search.is_some() {
+                match exec::browse_templates(search.as_deref(), yes)? {
+                    Some(template) => Some(template),
+                    // the templates were only listed, since the user could not pick one
+                    None => return Ok(()),
+                }
+            } else {
+                template
+            };
+            exec::generate(
+                name,
+                site,
+                template,
+                branch,
+                defines,
+                target_type,
+                yes,
+                &git,
+                !no_install,
+            )
+        }
         Command::Init {
             name,
             site,
             target_type,
-        } => exec::init(name, site, target_type),
-        Command::Build => exec::build(&cli_params),
+            force,
+            yes,
+            from_dash,
+        } => exec::init(name, site, target_type, force, yes, from_dash),
+        Command::Build {
+            watch,
+            analyze,
+            treemap,
+            reproducible,
+            output,
+        } => exec::build(watch, analyze, treemap, reproducible, output, &cli_params),
+        Command::Check => exec::check(&cli_params),
+        Command::Types { output, secrets } => exec::types(&output, secrets, &cli_params),
         Command::Preview {
             method,
             url,
@@ -70,6 +188,11 @@ fn run() -> Result<()> {
             upstream_protocol,
             inspect,
             unauthenticated,
+            upload_assets,
+            kv_propagation_delay,
+            simulate_limits,
+            subrequest_limit,
+            cpu_limit,
         } => exec::dev(
             host,
             ip,
@@ -79,50 +202,99 @@ fn run() -> Result<()> {
             &cli_params,
             inspect,
             unauthenticated,
+            upload_assets,
+            kv_propagation_delay,
+            simulate_limits,
+            subrequest_limit,
+            cpu_limit,
         ),
-        Command::Whoami => exec::whoami(),
+        Command::Whoami { json, limits } => exec::whoami(json, limits),
         Command::Publish {
             release,
             output,
             migration,
-        } => exec::publish(release, output, migration, &cli_params),
+            reproducible,
+            preview_branch,
+        } => exec::publish(
+            release,
+            output,
+            migration,
+            reproducible,
+            preview_branch,
+            &cli_params,
+        ),
+        Command::PreviewEnv(preview_env) => exec::preview_env(preview_env, &cli_params),
         Command::Subdomain { name } => exec::subdomain(name, &cli_params),
         Command::Route(route) => exec::route(route, &cli_params),
         Command::Secret(secret) => exec::secret(secret, &cli_params),
+        Command::Sites(sites) => exec::sites(sites, &cli_params),
+        Command::Add(add) => exec::add(add, &cli_params),
         Command::R2(r2) => exec::r2_bucket(r2, &cli_params),
         Command::KvNamespace(namespace) => exec::kv_namespace(namespace, &cli_params),
         Command::KvKey(key) => exec::kv_key(key, &cli_params),
         Command::KvBulk(bulk) => exec::kv_bulk(bulk, &cli_params),
+        Command::Logs(logs) => exec::logs(logs, &cli_params),
         Command::Tail {
             name,
+            names,
+            all,
             url,
             format,
+            pretty,
+            output,
+            rotate_size,
+            rotate_count,
+            forward,
             once,
+            stats,
+            interval,
             sampling_rate,
+            errors_only,
+            fail_on_error,
+            timeout,
             status,
+            event_type,
             method,
             header,
             ip_address,
             search,
+            filter,
             ..
         } => exec::tail(
             name,
+            names,
+            all,
             url,
             format,
+            pretty,
+            output,
+            rotate_size,
+            rotate_count,
+            forward,
             once,
+            stats,
+            interval,
             sampling_rate,
+            errors_only,
+            fail_on_error,
+            timeout,
             status,
+            event_type,
             method,
             header,
             ip_address,
             search,
+            filter,
             &cli_params,
         ),
         Command::Login {
             scopes,
             scopes_list,
-        } => exec::login(&scopes, scopes_list),
-        Command::Logout => exec::logout(),
+            keychain,
+            default_account_id,
+            refresh,
+        } => exec::login(&scopes, scopes_list, keychain, default_account_id, refresh),
+        Command::Logout { keep_remote } => exec::logout(keep_remote),
         Command::Report { log } => commands::report::run(log.as_deref()).map(|_| {
             eprintln!("Report submission sucessful. Thank you!");
         }),
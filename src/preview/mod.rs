@@ -38,6 +38,9 @@ pub fn preview(
             anyhow::bail!("wrangler preview does not support previewing modules scripts. Please use wrangler dev instead.");
         }
     }
+    if target.entries.is_some() {
+        anyhow::bail!("wrangler preview does not support previewing modules scripts. Please use wrangler dev instead.");
+    }
 
     if target.durable_objects.is_some() {
         anyhow::bail!("wrangler preview is not yet supported for scripts that use Durable Objects. Please use wrangler dev instead.");
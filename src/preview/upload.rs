@@ -8,7 +8,7 @@ use crate::http;
 use crate::kv::bulk;
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::Target;
-use crate::sites::{add_namespace, sync, AssetManifest};
+use crate::sites::{self, add_namespace, sync, AssetManifest};
 use crate::terminal::message::{Message, StdOut};
 use crate::upload;
 
@@ -70,7 +70,7 @@ pub fn upload(
                         StdOut::info("Uploading updated files...");
                     }
 
-                    bulk::put(target, user, &site_namespace.id, to_upload, &None)?;
+                    sites::upload(target, user, &site_namespace.id, to_upload, &None)?;
 
                     let preview = authenticated_upload(&client, target, Some(asset_manifest))?;
                     if !to_delete.is_empty() {
@@ -147,7 +147,7 @@ fn authenticated_upload(
     );
     log::info!("address: {}", create_address);
 
-    let script_upload_form = upload::form::build(target, asset_manifest, None)?;
+    let script_upload_form = upload::form::build(target, asset_manifest, None, None)?;
 
     let res = client
         .post(&create_address)
@@ -190,7 +190,7 @@ fn unauthenticated_upload(target: &Target) -> Result<Preview> {
         target.site = None;
     }
 
-    let script_upload_form = upload::form::build(&target, None, None)?;
+    let script_upload_form = upload::form::build(&target, None, None, None)?;
     let client = http::client();
     let res = client
         .post(create_address)
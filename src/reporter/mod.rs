@@ -1,4 +1,7 @@
-use crate::settings::{self, toml::Manifest};
+use crate::settings::{
+    self,
+    toml::{Manifest, RouteEntry},
+};
 use crate::{
     commands::DEFAULT_CONFIG_PATH,
     settings::toml::{DurableObjects, UploadFormat},
@@ -56,6 +59,14 @@ pub fn read_log(log: Option<&Path>) -> Result<Report> {
 
 /// gathers necessary error report information, and stores on disk until uploaded. the
 pub fn generate_report(panic_info: Option<&PanicInfo>) {
+    if !settings::profile::error_reports_enabled(&settings::get_global_config_path()) {
+        match panic_info {
+            Some(info) => eprintln!("\nOops! Wrangler encountered an error:\n\n{}", info),
+            None => eprintln!("Oops! Wrangler encountered an error.\n"),
+        }
+        return;
+    }
+
     let project_info = load_project_info();
     let mut report = Report {
         uuid: Uuid::new_v4(),
@@ -236,7 +247,10 @@ fn load_project_info() -> ProjectInfo {
         }
 
         if let Some(routes) = manifest.routes {
-            project_info.base.insert("routes".into(), routes.join(","));
+            let patterns = routes.iter().map(RouteEntry::pattern).collect::<Vec<_>>();
+            project_info
+                .base
+                .insert("routes".into(), patterns.join(","));
         }
 
         if let Some(route) = manifest.route {
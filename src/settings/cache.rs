@@ -0,0 +1,49 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::get_wrangler_home_dir;
+
+/// The directory where responses of slow-changing API calls are cached.
+fn cache_dir() -> PathBuf {
+    get_wrangler_home_dir().join("cache")
+}
+
+fn cache_path(name: &str) -> PathBuf {
+    cache_dir().join(name).with_extension("json")
+}
+
+/// Reads a cached value, unless it is missing, unreadable or older than `ttl`.
+pub fn read<T: DeserializeOwned>(name: &str, ttl: Duration) -> Option<T> {
+    let path = cache_path(name);
+    let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+    let age = SystemTime::now()
+        .duration_since(modified)
+        .unwrap_or_default();
+    if age > ttl {
+        log::info!("Cache {} is stale", path.display());
+        return None;
+    }
+    let contents = fs::read_to_string(&path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// Caches a value until it is overwritten or becomes stale.
+pub fn write<T: Serialize>(name: &str, value: &T) -> Result<()> {
+    fs::create_dir_all(cache_dir())?;
+    fs::write(cache_path(name), serde_json::to_string(value)?)?;
+    Ok(())
+}
+
+/// Removes every cached value, e.g. when the credentials change.
+pub fn clear() -> Result<()> {
+    let dir = cache_dir();
+    if dir.exists() {
+        fs::remove_dir_all(dir)?;
+    }
+    Ok(())
+}
@@ -6,6 +6,46 @@ use config::{ConfigError, Source, Value};
 
 const PREFIX_PATTERN: &str = "CF_";
 
+/// Variables that can be set for a single environment of `wrangler.toml` by suffixing them with
+/// its name, e.g. `CF_API_TOKEN_PRODUCTION` is used instead of `CF_API_TOKEN` with
+/// `--env production`.
+pub const PER_ENVIRONMENT_VARS: [&str; 7] = [
+    "CF_API_TOKEN",
+    "CF_API_KEY",
+    "CF_EMAIL",
+    "CF_ACCESS_CLIENT_ID",
+    "CF_ACCESS_CLIENT_SECRET",
+    "CF_ACCOUNT_ID",
+    "CF_ZONE_ID",
+];
+
+/// Returns the name of the variable overriding `var` for an environment, e.g. `CF_ZONE_ID_STAGING`.
+pub fn per_environment_var(var: &str, environment: &str) -> String {
+    let suffix: String = environment
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}_{}", var, suffix)
+}
+
+/// Replaces the variables shared by every environment with the overrides of the selected
+/// environment, so that credentials are picked up wherever they are read.
+pub fn apply_environment_overrides(environment: &str) {
+    for var in PER_ENVIRONMENT_VARS.iter() {
+        let name = per_environment_var(var, environment);
+        if let Ok(value) = env::var(&name) {
+            log::info!("Using ${} as ${}", name, var);
+            env::set_var(var, value);
+        }
+    }
+}
+
 pub trait QueryEnvironment {
     fn get_var(&self, var: &'static str) -> Result<String, std::env::VarError>;
 
@@ -141,4 +181,16 @@ mod tests {
 
         assert_eq!(environment.collect().unwrap(), expected_env_vars);
     }
+
+    #[test]
+    fn it_names_per_environment_vars() {
+        assert_eq!(
+            per_environment_var("CF_API_TOKEN", "production"),
+            "CF_API_TOKEN_PRODUCTION"
+        );
+        assert_eq!(
+            per_environment_var("CF_ZONE_ID", "eu-staging"),
+            "CF_ZONE_ID_EU_STAGING"
+        );
+    }
 }
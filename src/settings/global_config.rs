@@ -1,6 +1,8 @@
 use std::env;
 use std::path::{Path, PathBuf};
 
+use super::profile;
+
 pub const DEFAULT_CONFIG_FILE_NAME: &str = "default.toml";
 
 pub fn get_wrangler_home_dir() -> PathBuf {
@@ -15,9 +17,10 @@ pub fn get_wrangler_home_dir() -> PathBuf {
     }
 }
 
+/// Returns the configuration file of the selected profile, `default.toml` unless another
+/// profile is selected with `--profile` or `$WRANGLER_PROFILE`.
 pub fn get_global_config_path() -> PathBuf {
-    let home_dir = get_wrangler_home_dir();
-    let global_config_file = home_dir.join("config").join(DEFAULT_CONFIG_FILE_NAME);
+    let global_config_file = profile::profile_config_path(&profile::active_profile());
     log::info!("Using global config file: {}", global_config_file.display());
     global_config_file
 }
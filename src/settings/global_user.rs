@@ -3,17 +3,27 @@ use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 use cloudflare::framework::auth::Credentials;
+use reqwest::header::HeaderValue;
 use serde::{Deserialize, Serialize};
 
 use crate::login::check_update_oauth_token;
-use crate::settings::{get_global_config_path, Environment, QueryEnvironment};
+use crate::settings::{get_global_config_path, keychain, profile, Environment, QueryEnvironment};
+use crate::terminal::message::{Message, StdErr};
 use crate::terminal::{emoji, styles};
 
 const CF_API_TOKEN: &str = "CF_API_TOKEN";
 const CF_API_KEY: &str = "CF_API_KEY";
 const CF_EMAIL: &str = "CF_EMAIL";
+const CF_ACCESS_CLIENT_ID: &str = "CF_ACCESS_CLIENT_ID";
+const CF_ACCESS_CLIENT_SECRET: &str = "CF_ACCESS_CLIENT_SECRET";
 
-static ENV_VAR_WHITELIST: [&str; 3] = [CF_API_TOKEN, CF_API_KEY, CF_EMAIL];
+static ENV_VAR_WHITELIST: [&str; 5] = [
+    CF_API_TOKEN,
+    CF_API_KEY,
+    CF_EMAIL,
+    CF_ACCESS_CLIENT_ID,
+    CF_ACCESS_CLIENT_SECRET,
+];
 
 #[cfg(test)]
 use std::io::Write;
@@ -33,6 +43,11 @@ pub enum GlobalUser {
         email: String,
         api_key: String,
     },
+    /// A service token of an account, for machines that may not use the tokens of users.
+    ServiceTokenAuth {
+        access_client_id: String,
+        access_client_secret: String,
+    },
 }
 
 impl GlobalUser {
@@ -116,6 +131,26 @@ impl GlobalUser {
         }
     }
 
+    /// A service token, whose client ID and secret must be valid values of the headers that they
+    /// are sent in.
+    pub fn service_token(access_client_id: String, access_client_secret: String) -> Result<Self> {
+        for (name, value) in &[
+            ("client ID", &access_client_id),
+            ("client secret", &access_client_secret),
+        ] {
+            if HeaderValue::from_str(value).is_err() {
+                anyhow::bail!(
+                    "The {} of the service token has characters that can't be sent in an HTTP header",
+                    name
+                );
+            }
+        }
+        Ok(Self::ServiceTokenAuth {
+            access_client_id,
+            access_client_secret,
+        })
+    }
+
     fn from_env<T: 'static + QueryEnvironment>(environment: T) -> Option<Result<Self>>
     where
         T: config::Source + Send + Sync,
@@ -133,7 +168,7 @@ impl GlobalUser {
         }
     }
 
-    fn from_file(config_path: PathBuf) -> Result<Self> {
+    pub(crate) fn from_file(config_path: PathBuf) -> Result<Self> {
         let mut s = config::Config::new();
 
         let config_str = config_path
@@ -148,7 +183,14 @@ impl GlobalUser {
                 config_str
             );
 
-            match s.merge(config::File::with_name(config_str)) {
+            let source = if keychain::uses_keychain(&config_path) {
+                log::info!("Reading credentials from the keychain");
+                let secret = keychain::load(&config_path)?;
+                s.merge(config::File::from_str(&secret, config::FileFormat::Toml))
+            } else {
+                s.merge(config::File::with_name(config_str))
+            };
+            match source {
                 Ok(_) => (),
                 Err(_) => {
                     let error_info = "\nFailed to read information from configuration file.";
@@ -169,9 +211,38 @@ impl GlobalUser {
         // convert in-memory representation of authentication method to on-disk format
         let toml: std::string::String = toml::to_string(self)?;
 
+        // once a profile uses the keychain it keeps using it, unless it is unavailable
+        if keychain::uses_keychain(config_path) {
+            match self.to_keychain(config_path) {
+                Ok(()) => return Ok(()),
+                Err(e) => StdErr::warn(&format!(
+                    "{}; writing credentials to {} instead",
+                    e,
+                    config_path.display()
+                )),
+            }
+        }
+
+        // the profile's settings (e.g. its default account ID) live in the same file, so keep them
+        let settings = profile::settings(config_path);
+
         // create configuration path if non-existent, and write to configuration file
         fs::create_dir_all(&config_path.parent().unwrap())?;
         fs::write(&config_path, toml)?;
+        profile::restore_settings(config_path, settings)?;
+
+        Ok(())
+    }
+
+    /// Stores the credentials in the OS keychain, leaving only a marker in the
+    /// configuration file.
+    pub fn to_keychain(&self, config_path: &Path) -> Result<()> {
+        let toml: std::string::String = toml::to_string(self)?;
+        let settings = profile::settings(config_path);
+
+        keychain::store(config_path, &toml)?;
+        fs::create_dir_all(&config_path.parent().unwrap())?;
+        keychain::write_marker(config_path, settings)?;
 
         Ok(())
     }
@@ -184,18 +255,29 @@ impl GlobalUser {
         let api_key = config.get_str("api_key");
         let refresh_token = config.get_str("refresh_token");
         let expiration_time = config.get_str("expiration_time");
+        let access_client_id = config.get_str("access_client_id");
+        let access_client_secret = config.get_str("access_client_secret");
 
         // The only cases that are not allowed are:
         //      1) (partial or complete) OAuth token + API token
         //      2) (partial or complete) OAuth token + Global API key (partial or complete)
-        //      3) Invalid authentication methods (e.g. partial Oauth token, partial Global API key, and empty configuration file + no environment variables)
+        //      3) (partial or complete) service token + any other authentication method
+        //      4) Invalid authentication methods (e.g. partial Oauth token, partial Global API key, and empty configuration file + no environment variables)
         // API token has priority over global API key both in environment variables and in configuration file
+        let has_service_token = access_client_id.is_ok() || access_client_secret.is_ok();
         if (api_token.is_ok()
             && (oauth_token.is_ok() || refresh_token.is_ok() || expiration_time.is_ok()))
             || ((oauth_token.is_ok() || refresh_token.is_ok() || expiration_time.is_ok())
                 && (email.is_ok() || api_key.is_ok()))
+            || (has_service_token
+                && (api_token.is_ok()
+                    || oauth_token.is_ok()
+                    || refresh_token.is_ok()
+                    || expiration_time.is_ok()
+                    || email.is_ok()
+                    || api_key.is_ok()))
         {
-            let error_info = "\nMore than one authentication method (e.g. API token and OAuth token, or OAuth token and Global API key) has been found in the configuration file. Please use only one.";
+            let error_info = "\nMore than one authentication method (e.g. API token and OAuth token, or OAuth token and Global API key, or a service token and any other) has been found in the configuration file. Please use only one.";
             let wrangler_logout_msg = styles::highlight("`wrangler logout`");
             let config_path = get_global_config_path();
             let more_info = format!("{}\nIf you'd like to edit the configuration file, it can be found at {}. Consider also running {} to clean up the configuration file.", error_info, config_path.to_str().unwrap(), wrangler_logout_msg);
@@ -203,6 +285,10 @@ impl GlobalUser {
             Self::show_config_err_info(Some(more_info), config)
         } else if let Ok(api_token) = api_token {
             Ok(Self::ApiTokenAuth { api_token })
+        } else if let (Ok(access_client_id), Ok(access_client_secret)) =
+            (access_client_id, access_client_secret)
+        {
+            Self::service_token(access_client_id, access_client_secret)
         } else if let (Ok(email), Ok(api_key)) = (email, api_key) {
             Ok(Self::GlobalKeyAuth { email, api_key })
         } else if let (Ok(oauth_token), Ok(refresh_token), Ok(expiration_time)) =
@@ -261,6 +347,9 @@ impl From<GlobalUser> for Credentials {
                 key: api_key,
                 email,
             },
+            // cloudflare-rs has no credentials of service tokens, so they get an empty key and are
+            // only sent in the default headers of its clients by http::auth_headers
+            GlobalUser::ServiceTokenAuth { .. } => Credentials::Service { key: String::new() },
         }
     }
 }
@@ -568,6 +657,51 @@ mod tests {
         assert!(new_user.is_err());
     }
 
+    #[test]
+    fn it_succeeds_with_service_token_in_env() {
+        let mut mock_env = MockEnvironment::default();
+        mock_env.set(CF_ACCESS_CLIENT_ID, "clientid.access");
+        mock_env.set(CF_ACCESS_CLIENT_SECRET, "clientsecret");
+
+        let tmp_dir = tempdir().unwrap();
+        let config_dir = test_config_dir(&tmp_dir, None).unwrap();
+
+        let new_user = GlobalUser::build(mock_env, config_dir).unwrap();
+        assert_eq!(
+            new_user,
+            GlobalUser::ServiceTokenAuth {
+                access_client_id: "clientid.access".to_string(),
+                access_client_secret: "clientsecret".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn it_fails_if_service_token_is_not_a_header_value() {
+        let mut mock_env = MockEnvironment::default();
+        mock_env.set(CF_ACCESS_CLIENT_ID, "clientid.access");
+        mock_env.set(CF_ACCESS_CLIENT_SECRET, "client\nsecret");
+
+        let tmp_dir = tempdir().unwrap();
+        let config_dir = test_config_dir(&tmp_dir, None).unwrap();
+
+        let new_user = GlobalUser::build(mock_env, config_dir);
+        assert!(new_user.is_err());
+    }
+
+    #[test]
+    fn it_fails_if_service_token_and_api_token_both_exist() {
+        let mut mock_env = MockEnvironment::default();
+        mock_env.set(CF_API_TOKEN, "apitoken");
+        mock_env.set(CF_ACCESS_CLIENT_ID, "clientid.access");
+
+        let tmp_dir = tempdir().unwrap();
+        let config_dir = test_config_dir(&tmp_dir, None).unwrap();
+
+        let new_user = GlobalUser::build(mock_env, config_dir);
+        assert!(new_user.is_err());
+    }
+
     #[test]
     fn it_succeeds_with_no_config() {
         let mut mock_env = MockEnvironment::default();
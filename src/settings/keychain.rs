@@ -0,0 +1,121 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+
+/// The key that marks a configuration file whose credentials live in the OS keychain.
+const CREDENTIAL_STORE_KEY: &str = "credential_store";
+const KEYCHAIN: &str = "keychain";
+/// The service name that keychain entries are filed under; the account is the profile name.
+#[cfg(feature = "keychain")]
+const SERVICE: &str = "wrangler";
+
+/// Tests if the credentials of a configuration file are stored in the OS keychain.
+pub fn uses_keychain(config_path: &Path) -> bool {
+    let table = match fs::read_to_string(config_path)
+        .ok()
+        .and_then(|contents| toml::from_str::<toml::value::Table>(&contents).ok())
+    {
+        Some(table) => table,
+        None => return false,
+    };
+    matches!(table.get(CREDENTIAL_STORE_KEY), Some(toml::Value::String(store)) if store == KEYCHAIN)
+}
+
+/// Replaces the contents of a configuration file with a marker pointing to the keychain,
+/// keeping anything that is not a credential (e.g. the default account ID).
+pub fn write_marker(config_path: &Path, settings: toml::value::Table) -> Result<()> {
+    let mut table = settings;
+    table.insert(
+        CREDENTIAL_STORE_KEY.to_string(),
+        toml::Value::String(KEYCHAIN.to_string()),
+    );
+    fs::write(config_path, toml::to_string(&table)?)?;
+    Ok(())
+}
+
+/// Names the keychain entry of a configuration file after its profile.
+#[cfg(feature = "keychain")]
+fn entry(config_path: &Path) -> keyring::Entry {
+    let profile = config_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(super::profile::DEFAULT_PROFILE);
+    keyring::Entry::new(SERVICE, profile)
+}
+
+/// Reads the serialized credentials of a configuration file from the keychain.
+#[cfg(feature = "keychain")]
+pub fn load(config_path: &Path) -> Result<String> {
+    match entry(config_path).get_password() {
+        Ok(secret) => Ok(secret),
+        Err(keyring::Error::NoEntry) => anyhow::bail!(
+            "No credentials were found in the keychain for {}. Try running `wrangler login` or `wrangler config`",
+            config_path.display()
+        ),
+        Err(err) => anyhow::bail!("Failed to read credentials from the keychain: {}", err),
+    }
+}
+
+/// Writes the serialized credentials of a configuration file to the keychain.
+#[cfg(feature = "keychain")]
+pub fn store(config_path: &Path, secret: &str) -> Result<()> {
+    match entry(config_path).set_password(secret) {
+        Ok(()) => Ok(()),
+        Err(err) => anyhow::bail!("Failed to write credentials to the keychain: {}", err),
+    }
+}
+
+/// Removes the credentials of a configuration file from the keychain.
+#[cfg(feature = "keychain")]
+pub fn delete(config_path: &Path) -> Result<()> {
+    match entry(config_path).delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(err) => anyhow::bail!("Failed to remove credentials from the keychain: {}", err),
+    }
+}
+
+#[cfg(not(feature = "keychain"))]
+pub fn load(_config_path: &Path) -> Result<String> {
+    anyhow::bail!(unsupported())
+}
+
+#[cfg(not(feature = "keychain"))]
+pub fn store(_config_path: &Path, _secret: &str) -> Result<()> {
+    anyhow::bail!(unsupported())
+}
+
+#[cfg(not(feature = "keychain"))]
+pub fn delete(_config_path: &Path) -> Result<()> {
+    anyhow::bail!(unsupported())
+}
+
+#[cfg(not(feature = "keychain"))]
+fn unsupported() -> &'static str {
+    "This build of wrangler does not support the OS keychain; rebuild it with `--features keychain`"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_detects_the_keychain_marker() {
+        let tmp_dir = tempdir().unwrap();
+        let config_path = tmp_dir.path().join("default.toml");
+        fs::write(&config_path, "api_token = \"thisisanapitoken\"\n").unwrap();
+        assert!(!uses_keychain(&config_path));
+
+        let mut settings = toml::value::Table::new();
+        settings.insert(
+            "account_id".to_string(),
+            toml::Value::String("0123456789abcdef".to_string()),
+        );
+        write_marker(&config_path, settings).unwrap();
+        assert!(uses_keychain(&config_path));
+        let contents = fs::read_to_string(&config_path).unwrap();
+        assert!(!contents.contains("api_token"));
+        assert!(contents.contains("account_id = \"0123456789abcdef\""));
+    }
+}
@@ -1,8 +1,12 @@
 pub mod binding;
-mod environment;
+pub mod cache;
+pub(crate) mod environment;
 mod global_config;
 pub mod global_user;
+pub mod keychain;
+pub mod profile;
+pub mod project;
 pub mod toml;
 
-pub use environment::{Environment, QueryEnvironment};
+pub use environment::{apply_environment_overrides, Environment, QueryEnvironment};
 pub use global_config::{get_global_config_path, get_wrangler_home_dir, DEFAULT_CONFIG_FILE_NAME};
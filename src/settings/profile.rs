@@ -0,0 +1,378 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use chrono::Utc;
+
+use super::get_wrangler_home_dir;
+
+/// Selects the authentication profile, overridden by the `--profile` flag.
+pub const WRANGLER_PROFILE: &str = "WRANGLER_PROFILE";
+/// The profile used when none is selected, stored in `default.toml` like it always was.
+pub const DEFAULT_PROFILE: &str = "default";
+
+const ACCOUNT_ID_KEY: &str = "account_id";
+const LOGGED_IN_AT_KEY: &str = "logged_in_at";
+const LOGIN_EXPIRES_AT_KEY: &str = "login_expires_at";
+const COMPATIBILITY_DATE_KEY: &str = "compatibility_date";
+const COLOR_KEY: &str = "color";
+const ERROR_REPORTS_KEY: &str = "error_reports";
+const GIT_KEY: &str = "git";
+const GIT_BRANCH_KEY: &str = "git_branch";
+const GIT_COMMIT_KEY: &str = "git_commit";
+const GITIGNORE_KEY: &str = "gitignore";
+/// Keys of a configuration file that are not credentials, kept when credentials are rewritten.
+const SETTINGS_KEYS: [&str; 10] = [
+    ACCOUNT_ID_KEY,
+    LOGGED_IN_AT_KEY,
+    LOGIN_EXPIRES_AT_KEY,
+    COMPATIBILITY_DATE_KEY,
+    COLOR_KEY,
+    ERROR_REPORTS_KEY,
+    GIT_KEY,
+    GIT_BRANCH_KEY,
+    GIT_COMMIT_KEY,
+    GITIGNORE_KEY,
+];
+
+/// Returns the name of the selected profile. `$WRANGLER_PROFILE` is ignored unless it is a valid
+/// profile name, which `validate_active_profile` reports when wrangler starts.
+pub fn active_profile() -> String {
+    match env::var(WRANGLER_PROFILE) {
+        Ok(profile) if validate_profile_name(&profile).is_ok() => profile,
+        _ => DEFAULT_PROFILE.to_string(),
+    }
+}
+
+/// Fails if `$WRANGLER_PROFILE` isn't a valid profile name, e.g. a path outside of the profiles.
+pub fn validate_active_profile() -> Result<()> {
+    match env::var(WRANGLER_PROFILE) {
+        Ok(profile) if !profile.is_empty() => validate_profile_name(&profile)
+            .map_err(|e| anyhow::anyhow!("{} in ${}", e, WRANGLER_PROFILE)),
+        _ => Ok(()),
+    }
+}
+
+/// Selects a profile for the rest of the command.
+pub fn set_active_profile(profile: &str) -> Result<()> {
+    validate_profile_name(profile)?;
+    env::set_var(WRANGLER_PROFILE, profile);
+    Ok(())
+}
+
+/// Profile names become file names, so they are kept to a safe set of characters.
+pub fn validate_profile_name(profile: &str) -> Result<()> {
+    if profile.is_empty()
+        || !profile
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        anyhow::bail!(
+            "Invalid profile name \"{}\": only letters, numbers, `-` and `_` are allowed",
+            profile
+        )
+    }
+    Ok(())
+}
+
+/// The directory where every profile is stored.
+pub fn profiles_dir() -> PathBuf {
+    get_wrangler_home_dir().join("config")
+}
+
+/// The path of the configuration file of a profile.
+pub fn profile_config_path(profile: &str) -> PathBuf {
+    profiles_dir().join(profile).with_extension("toml")
+}
+
+/// Lists the names of every profile that has a configuration file.
+pub fn list_profiles() -> Result<Vec<String>> {
+    let dir = profiles_dir();
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut profiles = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().map_or(false, |ext| ext == "toml") {
+            if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+                profiles.push(stem.to_string());
+            }
+        }
+    }
+    profiles.sort();
+    Ok(profiles)
+}
+
+/// Returns the default account ID stored in a profile's configuration file, if any.
+pub fn default_account_id(config_path: &Path) -> Option<String> {
+    read_key(config_path, ACCOUNT_ID_KEY)
+}
+
+/// Stores a default account ID in a profile's configuration file, next to its credentials.
+pub fn set_default_account_id(config_path: &Path, account_id: &str) -> Result<()> {
+    write_key(config_path, ACCOUNT_ID_KEY, account_id)
+}
+
+/// Returns when the user of a profile last ran `wrangler login` or had its tokens refreshed, as
+/// an RFC 3339 timestamp.
+pub fn logged_in_at(config_path: &Path) -> Option<String> {
+    read_key(config_path, LOGGED_IN_AT_KEY)
+}
+
+/// Records when the user of a profile ran `wrangler login` or had its tokens refreshed.
+pub fn set_logged_in_at(config_path: &Path, time: &str) -> Result<()> {
+    write_key(config_path, LOGGED_IN_AT_KEY, time)
+}
+
+/// Returns when the refresh token of a profile stops working, as an RFC 3339 timestamp, if the
+/// server told.
+pub fn login_expires_at(config_path: &Path) -> Option<String> {
+    read_key(config_path, LOGIN_EXPIRES_AT_KEY)
+}
+
+/// Records when the refresh token of a profile stops working.
+pub fn set_login_expires_at(config_path: &Path, time: &str) -> Result<()> {
+    write_key(config_path, LOGIN_EXPIRES_AT_KEY, time)
+}
+
+/// Returns the compatibility date used by projects that don't set their own. The setting is
+/// either a date or `"today"`, which always picks the current date.
+pub fn default_compatibility_date(config_path: &Path) -> Option<String> {
+    match read_key(config_path, COMPATIBILITY_DATE_KEY)?.as_str() {
+        "today" => Some(Utc::now().format("%F").to_string()),
+        date => Some(date.to_string()),
+    }
+}
+
+/// Returns whether output should be colored: `Some(true)` for `color = "always"`,
+/// `Some(false)` for `"never"`, and `None` to detect it from the terminal, which is the default.
+pub fn color_preference(config_path: &Path) -> Result<Option<bool>> {
+    match read_key(config_path, COLOR_KEY).as_deref() {
+        None | Some("auto") => Ok(None),
+        Some("always") => Ok(Some(true)),
+        Some("never") => Ok(Some(false)),
+        Some(other) => anyhow::bail!(
+            "Invalid color setting \"{}\" in {}: expected \"auto\", \"always\" or \"never\"",
+            other,
+            config_path.display()
+        ),
+    }
+}
+
+/// Applies the color preference of a profile to the rest of the command.
+pub fn apply_color_preference(config_path: &Path) -> Result<()> {
+    if let Some(enabled) = color_preference(config_path)? {
+        console::set_colors_enabled(enabled);
+        console::set_colors_enabled_stderr(enabled);
+    }
+    Ok(())
+}
+
+/// Returns whether error reports are written when wrangler crashes. They are only ever sent to
+/// Cloudflare by running `wrangler report`, but `error_reports = false` turns them off entirely.
+pub fn error_reports_enabled(config_path: &Path) -> bool {
+    fs::read_to_string(config_path)
+        .ok()
+        .and_then(|contents| toml::from_str::<toml::value::Table>(&contents).ok())
+        .and_then(|table| table.get(ERROR_REPORTS_KEY).and_then(toml::Value::as_bool))
+        .unwrap_or(true)
+}
+
+/// Returns whether new projects get a git repository, which `git = false` turns off.
+pub fn git_enabled(config_path: &Path) -> bool {
+    read_table(config_path)
+        .and_then(|table| table.get(GIT_KEY).and_then(toml::Value::as_bool))
+        .unwrap_or(true)
+}
+
+/// Returns whether the files of new projects are committed as the first commit of their
+/// repository, which `git_commit = true` turns on.
+pub fn git_commit_enabled(config_path: &Path) -> bool {
+    read_table(config_path)
+        .and_then(|table| table.get(GIT_COMMIT_KEY).and_then(toml::Value::as_bool))
+        .unwrap_or(false)
+}
+
+/// Returns the branch that the repositories of new projects start on, like `"main"`.
+pub fn default_git_branch(config_path: &Path) -> Option<String> {
+    read_key(config_path, GIT_BRANCH_KEY)
+}
+
+/// Returns the patterns that the `.gitignore` of new projects has, if the user chose them.
+pub fn gitignore_entries(config_path: &Path) -> Option<Vec<String>> {
+    let table = read_table(config_path)?;
+    let entries = table.get(GITIGNORE_KEY)?.as_array()?;
+    Some(
+        entries
+            .iter()
+            .filter_map(|entry| entry.as_str().map(str::to_string))
+            .collect(),
+    )
+}
+
+/// Returns the settings of a profile's configuration file, i.e. everything but credentials.
+pub fn settings(config_path: &Path) -> toml::value::Table {
+    let mut settings = toml::value::Table::new();
+    if let Some(table) = fs::read_to_string(config_path)
+        .ok()
+        .and_then(|contents| toml::from_str::<toml::value::Table>(&contents).ok())
+    {
+        for (key, value) in table {
+            if SETTINGS_KEYS.contains(&key.as_str()) {
+                settings.insert(key, value);
+            }
+        }
+    }
+    settings
+}
+
+/// Adds settings saved with `settings` back to a rewritten configuration file.
+pub fn restore_settings(config_path: &Path, settings: toml::value::Table) -> Result<()> {
+    if settings.is_empty() {
+        return Ok(());
+    }
+    let mut table = match fs::read_to_string(config_path) {
+        Ok(contents) => toml::from_str::<toml::value::Table>(&contents)?,
+        Err(_) => toml::value::Table::new(),
+    };
+    for (key, value) in settings {
+        table.insert(key, value);
+    }
+    fs::write(config_path, toml::to_string(&table)?)?;
+    Ok(())
+}
+
+fn read_table(config_path: &Path) -> Option<toml::value::Table> {
+    let contents = fs::read_to_string(config_path).ok()?;
+    toml::from_str::<toml::value::Table>(&contents).ok()
+}
+
+fn read_key(config_path: &Path, key: &str) -> Option<String> {
+    let contents = fs::read_to_string(config_path).ok()?;
+    let table = toml::from_str::<toml::value::Table>(&contents).ok()?;
+    match table.get(key) {
+        Some(toml::Value::String(value)) if !value.is_empty() => Some(value.clone()),
+        _ => None,
+    }
+}
+
+fn write_key(config_path: &Path, key: &str, value: &str) -> Result<()> {
+    let mut table = match fs::read_to_string(config_path) {
+        Ok(contents) => toml::from_str::<toml::value::Table>(&contents)?,
+        Err(_) => toml::value::Table::new(),
+    };
+    table.insert(key.to_string(), toml::Value::String(value.to_string()));
+    fs::write(config_path, toml::to_string(&table)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_validates_profile_names() {
+        assert!(validate_profile_name("work").is_ok());
+        assert!(validate_profile_name("client_a-2").is_ok());
+        assert!(validate_profile_name("").is_err());
+        assert!(validate_profile_name("../default").is_err());
+        assert!(validate_profile_name("my profile").is_err());
+    }
+
+    #[test]
+    fn it_stores_the_default_account_id_next_to_credentials() {
+        let tmp_dir = tempdir().unwrap();
+        let config_path = tmp_dir.path().join("work.toml");
+        fs::write(&config_path, "api_token = \"thisisanapitoken\"\n").unwrap();
+        assert_eq!(default_account_id(&config_path), None);
+
+        set_default_account_id(&config_path, "0123456789abcdef").unwrap();
+        assert_eq!(
+            default_account_id(&config_path),
+            Some("0123456789abcdef".to_string())
+        );
+        let contents = fs::read_to_string(&config_path).unwrap();
+        assert!(contents.contains("api_token = \"thisisanapitoken\""));
+    }
+
+    #[test]
+    fn it_keeps_settings_when_credentials_are_rewritten() {
+        let tmp_dir = tempdir().unwrap();
+        let config_path = tmp_dir.path().join("work.toml");
+        fs::write(&config_path, "oauth_token = \"old\"\n").unwrap();
+        set_default_account_id(&config_path, "0123456789abcdef").unwrap();
+        set_logged_in_at(&config_path, "2021-05-03T00:00:00+00:00").unwrap();
+        set_login_expires_at(&config_path, "2021-06-02T00:00:00+00:00").unwrap();
+
+        let saved = settings(&config_path);
+        assert!(!saved.contains_key("oauth_token"));
+        fs::write(&config_path, "oauth_token = \"new\"\n").unwrap();
+        restore_settings(&config_path, saved).unwrap();
+
+        let contents = fs::read_to_string(&config_path).unwrap();
+        assert!(contents.contains("oauth_token = \"new\""));
+        assert_eq!(
+            default_account_id(&config_path),
+            Some("0123456789abcdef".to_string())
+        );
+        assert_eq!(
+            logged_in_at(&config_path),
+            Some("2021-05-03T00:00:00+00:00".to_string())
+        );
+        assert_eq!(
+            login_expires_at(&config_path),
+            Some("2021-06-02T00:00:00+00:00".to_string())
+        );
+    }
+
+    #[test]
+    fn it_reads_machine_wide_defaults() {
+        let tmp_dir = tempdir().unwrap();
+        let config_path = tmp_dir.path().join("default.toml");
+        assert_eq!(default_compatibility_date(&config_path), None);
+        assert_eq!(color_preference(&config_path).unwrap(), None);
+        assert!(error_reports_enabled(&config_path));
+
+        fs::write(
+            &config_path,
+            "compatibility_date = \"2022-03-01\"\ncolor = \"never\"\nerror_reports = false\n",
+        )
+        .unwrap();
+        assert_eq!(
+            default_compatibility_date(&config_path),
+            Some("2022-03-01".to_string())
+        );
+        assert_eq!(color_preference(&config_path).unwrap(), Some(false));
+        assert!(!error_reports_enabled(&config_path));
+
+        fs::write(
+            &config_path,
+            "compatibility_date = \"today\"\ncolor = \"red\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            default_compatibility_date(&config_path),
+            Some(Utc::now().format("%F").to_string())
+        );
+        assert!(color_preference(&config_path).is_err());
+
+        assert!(git_enabled(&config_path));
+        assert!(!git_commit_enabled(&config_path));
+        assert_eq!(gitignore_entries(&config_path), None);
+        fs::write(
+            &config_path,
+            "git = false\ngit_commit = true\ngit_branch = \"main\"\ngitignore = [\"node_modules\", \"dist\"]\n",
+        )
+        .unwrap();
+        assert!(!git_enabled(&config_path));
+        assert!(git_commit_enabled(&config_path));
+        assert_eq!(default_git_branch(&config_path), Some("main".to_string()));
+        assert_eq!(
+            gitignore_entries(&config_path),
+            Some(vec!["node_modules".to_string(), "dist".to_string()])
+        );
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+/// The directory where wrangler remembers choices made for the current project.
+const PROJECT_DIR: &str = ".wrangler";
+const PROJECT_CONFIG_FILE: &str = "config";
+const PROJECT_CACHE_DIR: &str = "cache";
+const ACCOUNT_ID_KEY: &str = "account_id";
+const ZONES_KEY: &str = "zones";
+const ENV_KEY: &str = "env";
+const PREVIEW_BRANCHES_KEY: &str = "preview_branches";
+
+/// The path of the project configuration file, `.wrangler/config` in the working directory.
+pub fn project_config_path() -> PathBuf {
+    let dir = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    dir.join(PROJECT_DIR).join(PROJECT_CONFIG_FILE)
+}
+
+/// The directory where builds of the current project are cached, `.wrangler/cache` in the working
+/// directory.
+pub fn project_cache_dir() -> PathBuf {
+    let dir = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    dir.join(PROJECT_DIR).join(PROJECT_CACHE_DIR)
+}
+
+/// Returns the account ID picked for an environment of this project, if any.
+pub fn account_id(env: Option<&str>) -> Option<String> {
+    read_key(&project_config_path(), env, &[ACCOUNT_ID_KEY])
+}
+
+/// Remembers the account ID picked for an environment of this project.
+pub fn set_account_id(env: Option<&str>, account_id: &str) -> Result<()> {
+    write_key(&project_config_path(), env, &[ACCOUNT_ID_KEY], account_id)
+}
+
+/// Returns the zone ID picked for the routes of an environment on `host`, if any.
+pub fn zone_id(env: Option<&str>, host: &str) -> Option<String> {
+    read_key(&project_config_path(), env, &[ZONES_KEY, host])
+}
+
+/// Remembers the zone ID picked for the routes of an environment on `host`.
+pub fn set_zone_id(env: Option<&str>, host: &str, zone_id: &str) -> Result<()> {
+    write_key(&project_config_path(), env, &[ZONES_KEY, host], zone_id)
+}
+
+/// Returns the workers that `--preview-branch` published for an environment, with their branch.
+pub fn preview_branches(env: Option<&str>) -> BTreeMap<String, String> {
+    read_table(&project_config_path(), env, &[PREVIEW_BRANCHES_KEY])
+}
+
+/// Remembers that `--preview-branch` published `worker_name` for `branch`.
+pub fn add_preview_branch(env: Option<&str>, worker_name: &str, branch: &str) -> Result<()> {
+    write_key(
+        &project_config_path(),
+        env,
+        &[PREVIEW_BRANCHES_KEY, worker_name],
+        branch,
+    )
+}
+
+/// Forgets the worker of a preview branch, once it is deleted.
+pub fn remove_preview_branch(env: Option<&str>, worker_name: &str) -> Result<()> {
+    remove_key(
+        &project_config_path(),
+        env,
+        &[PREVIEW_BRANCHES_KEY, worker_name],
+    )
+}
+
+/// The keys of a value in the project configuration file, which is laid out like `wrangler.toml`:
+/// the choices of the top-level environment are at the top, and those of the others under
+/// `[env.<name>]`.
+fn path<'a>(env: Option<&'a str>, keys: &[&'a str]) -> Vec<&'a str> {
+    let mut path = match env {
+        Some(env) => vec![ENV_KEY, env],
+        None => vec![],
+    };
+    path.extend_from_slice(keys);
+    path
+}
+
+fn read_key(config_path: &Path, env: Option<&str>, keys: &[&str]) -> Option<String> {
+    let contents = fs::read_to_string(config_path).ok()?;
+    let mut value = &toml::Value::Table(toml::from_str::<toml::value::Table>(&contents).ok()?);
+    for key in path(env, keys) {
+        value = value.get(key)?;
+    }
+    match value {
+        toml::Value::String(value) if !value.is_empty() => Some(value.clone()),
+        _ => None,
+    }
+}
+
+fn read_table(config_path: &Path, env: Option<&str>, keys: &[&str]) -> BTreeMap<String, String> {
+    let table = fs::read_to_string(config_path)
+        .ok()
+        .and_then(|contents| toml::from_str::<toml::value::Table>(&contents).ok());
+    let mut value = match &table {
+        Some(table) => table,
+        None => return BTreeMap::new(),
+    };
+    for key in path(env, keys) {
+        value = match value.get(key).and_then(toml::Value::as_table) {
+            Some(value) => value,
+            None => return BTreeMap::new(),
+        };
+    }
+    value
+        .iter()
+        .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+        .collect()
+}
+
+fn write_key(config_path: &Path, env: Option<&str>, keys: &[&str], value: &str) -> Result<()> {
+    let mut root = match fs::read_to_string(config_path) {
+        Ok(contents) => toml::from_str::<toml::value::Table>(&contents)?,
+        Err(_) => toml::value::Table::new(),
+    };
+    let path = path(env, keys);
+    let (last, tables) = path.split_last().unwrap();
+    let mut table = &mut root;
+    for key in tables {
+        let entry = table
+            .entry(key.to_string())
+            .or_insert_with(|| toml::Value::Table(toml::value::Table::new()));
+        if !entry.is_table() {
+            *entry = toml::Value::Table(toml::value::Table::new());
+        }
+        table = entry.as_table_mut().unwrap();
+    }
+    table.insert(last.to_string(), toml::Value::String(value.to_string()));
+    if let Some(dir) = config_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(config_path, toml::to_string(&root)?)?;
+    log::info!("Remembered {} in {}", path.join("."), config_path.display());
+    Ok(())
+}
+
+fn remove_key(config_path: &Path, env: Option<&str>, keys: &[&str]) -> Result<()> {
+    let mut root = match fs::read_to_string(config_path) {
+        Ok(contents) => toml::from_str::<toml::value::Table>(&contents)?,
+        Err(_) => return Ok(()),
+    };
+    let path = path(env, keys);
+    let (last, tables) = path.split_last().unwrap();
+    let mut table = &mut root;
+    for key in tables {
+        table = match table.get_mut(*key).and_then(toml::Value::as_table_mut) {
+            Some(table) => table,
+            None => return Ok(()),
+        };
+    }
+    if table.remove(*last).is_some() {
+        fs::write(config_path, toml::to_string(&root)?)?;
+        log::info!("Forgot {} in {}", path.join("."), config_path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_remembers_choices_per_environment_and_host() {
+        let tmp_dir = tempdir().unwrap();
+        let config_path = tmp_dir.path().join(PROJECT_DIR).join(PROJECT_CONFIG_FILE);
+        let account = [ACCOUNT_ID_KEY];
+        assert_eq!(read_key(&config_path, None, &account), None);
+
+        write_key(&config_path, None, &account, "0123456789abcdef").unwrap();
+        write_key(&config_path, Some("staging"), &account, "fedcba9876543210").unwrap();
+        write_key(&config_path, None, &[ZONES_KEY, "example.com"], "a1").unwrap();
+        write_key(&config_path, None, &[ZONES_KEY, "example.net"], "b2").unwrap();
+        assert_eq!(
+            read_key(&config_path, None, &account),
+            Some("0123456789abcdef".to_string())
+        );
+        assert_eq!(
+            read_key(&config_path, Some("staging"), &account),
+            Some("fedcba9876543210".to_string())
+        );
+        assert_eq!(read_key(&config_path, Some("production"), &account), None);
+        assert_eq!(
+            read_key(&config_path, None, &[ZONES_KEY, "example.com"]),
+            Some("a1".to_string())
+        );
+        assert_eq!(
+            read_key(&config_path, None, &[ZONES_KEY, "example.net"]),
+            Some("b2".to_string())
+        );
+        assert_eq!(
+            read_key(&config_path, Some("staging"), &[ZONES_KEY, "example.com"]),
+            None
+        );
+    }
+
+    #[test]
+    fn it_remembers_the_workers_of_preview_branches() {
+        let tmp_dir = tempdir().unwrap();
+        let config_path = tmp_dir.path().join(PROJECT_DIR).join(PROJECT_CONFIG_FILE);
+        let keys = |worker| [PREVIEW_BRANCHES_KEY, worker];
+        write_key(&config_path, None, &keys("worker-a"), "a").unwrap();
+        write_key(&config_path, None, &keys("worker-b"), "feature/b").unwrap();
+        write_key(&config_path, Some("staging"), &keys("worker-c"), "c").unwrap();
+
+        let branches = read_table(&config_path, None, &[PREVIEW_BRANCHES_KEY]);
+        assert_eq!(branches.len(), 2);
+        assert_eq!(branches["worker-b"], "feature/b");
+
+        remove_key(&config_path, None, &keys("worker-a")).unwrap();
+        remove_key(&config_path, None, &keys("worker-z")).unwrap();
+        assert_eq!(
+            read_table(&config_path, None, &[PREVIEW_BRANCHES_KEY])
+                .keys()
+                .collect::<Vec<_>>(),
+            vec!["worker-b"]
+        );
+        assert_eq!(
+            read_table(&config_path, Some("staging"), &[PREVIEW_BRANCHES_KEY]).len(),
+            1
+        );
+    }
+}
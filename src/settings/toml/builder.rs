@@ -1,9 +1,11 @@
+use std::collections::BTreeMap;
 use std::env;
 use std::path::PathBuf;
 use std::process::Command;
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use crate::upload::form::ModuleType;
 
@@ -18,7 +20,90 @@ pub struct Builder {
     pub cwd: PathBuf,
     #[serde(default = "watch_dir")]
     pub watch_dir: PathBuf,
+    /// Defaults to the service worker format.
+    #[serde(default)]
     pub upload: UploadFormat,
+    pub wasm: Option<WasmOpt>,
+    pub rust: Option<RustBuild>,
+    /// Expressions replaced by values when webpack projects are bundled. Strings may refer to
+    /// environment variables, `$NAME`, and to the output of commands, `$(command)`.
+    pub define: Option<BTreeMap<String, Value>>,
+    /// Commands run before and after the build.
+    pub hooks: Option<BuildHooks>,
+    /// Whether APIs that the Workers runtime does not have fail the build.
+    pub compat: Option<CompatCheck>,
+}
+
+/// The `[env.<name>.build]` table. Each of its settings overrides the one of the top level
+/// `[build]`, e.g. to only use a different `command` for a minified production bundle.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct BuilderOverride {
+    pub command: Option<String>,
+    pub cwd: Option<PathBuf>,
+    pub watch_dir: Option<PathBuf>,
+    pub upload: Option<UploadFormat>,
+    pub wasm: Option<WasmOpt>,
+    pub rust: Option<RustBuild>,
+    /// Added to the `define` of the top level `[build]`, replacing the values of the same keys.
+    pub define: Option<BTreeMap<String, Value>>,
+    pub hooks: Option<BuildHooks>,
+    pub compat: Option<CompatCheck>,
+}
+
+impl BuilderOverride {
+    /// Resolves the build of an environment from the top level build, if any.
+    pub fn apply(&self, base: Option<&Builder>) -> Result<Builder> {
+        let upload = match (&self.upload, base) {
+            (Some(upload), _) => upload.clone(),
+            (None, Some(base)) => base.upload.clone(),
+            (None, None) if self.command.is_some() => anyhow::bail!(
+                "`upload` is required in the build of an environment when there is no top level [build]"
+            ),
+            (None, None) => UploadFormat::default(),
+        };
+        Ok(Builder {
+            command: self
+                .command
+                .clone()
+                .or_else(|| base.and_then(|base| base.command.clone())),
+            cwd: self
+                .cwd
+                .clone()
+                .or_else(|| base.map(|base| base.cwd.clone()))
+                .unwrap_or_else(project_root),
+            watch_dir: self
+                .watch_dir
+                .clone()
+                .or_else(|| base.map(|base| base.watch_dir.clone()))
+                .unwrap_or_else(watch_dir),
+            upload,
+            wasm: self
+                .wasm
+                .clone()
+                .or_else(|| base.and_then(|base| base.wasm.clone())),
+            rust: self
+                .rust
+                .clone()
+                .or_else(|| base.and_then(|base| base.rust.clone())),
+            define: match (base.and_then(|base| base.define.as_ref()), &self.define) {
+                (Some(base), Some(define)) => {
+                    let mut base = base.clone();
+                    base.extend(define.clone());
+                    Some(base)
+                }
+                (base, define) => define.as_ref().or(base).cloned(),
+            },
+            hooks: self
+                .hooks
+                .clone()
+                .or_else(|| base.and_then(|base| base.hooks.clone())),
+            compat: self
+                .compat
+                .clone()
+                .or_else(|| base.and_then(|base| base.compat.clone())),
+        })
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
@@ -36,6 +121,90 @@ pub enum UploadFormat {
     },
 }
 
+impl Default for UploadFormat {
+    fn default() -> Self {
+        UploadFormat::ServiceWorker {}
+    }
+}
+
+/// The `[build.wasm]` table of Rust projects, which optimizes the WebAssembly built by wasm-pack
+/// with wasm-opt. It also makes workers of WASI modules, built by cargo or by the build command
+/// of a javascript project.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct WasmOpt {
+    /// Passed to wasm-opt as `-O<opt_level>`, one of [`WASM_OPT_LEVELS`].
+    #[serde(default = "opt_level")]
+    pub opt_level: String,
+    /// Whether the WebAssembly is a WASI command, which a generated worker runs for every request.
+    #[serde(default)]
+    pub wasi: bool,
+    /// The WASI module that the build command of a javascript project builds.
+    pub module: Option<PathBuf>,
+}
+
+/// The optimization levels of wasm-opt: `s` and `z` optimize for size, `z` more aggressively.
+pub const WASM_OPT_LEVELS: &[&str] = &["0", "1", "2", "3", "4", "s", "z"];
+
+impl WasmOpt {
+    pub fn flag(&self) -> Result<String> {
+        anyhow::ensure!(
+            WASM_OPT_LEVELS.contains(&self.opt_level.as_str()),
+            "Invalid opt_level \"{}\" in [build.wasm], expected one of {}",
+            self.opt_level,
+            WASM_OPT_LEVELS.join(", ")
+        );
+        Ok(format!("-O{}", self.opt_level))
+    }
+}
+
+/// The `[build.hooks]` table: commands run in `cwd` before and after every build, of any type of
+/// project, even when nothing changed since the last build. A build fails as soon as one of them
+/// fails.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct BuildHooks {
+    #[serde(default)]
+    pub pre: Vec<String>,
+    #[serde(default)]
+    pub post: Vec<String>,
+}
+
+/// The `[build.compat]` table of the check of built scripts for APIs that the Workers runtime
+/// does not have, like `fs`, `process.exit` or `XMLHttpRequest`, which runs after every build
+/// when the table is set.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CompatCheck {
+    #[serde(default)]
+    pub severity: Severity,
+    /// The APIs that are not reported, e.g. because the code using them never runs in Workers.
+    #[serde(default)]
+    pub allow: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Off,
+    Warn,
+    Error,
+}
+
+impl Default for Severity {
+    fn default() -> Self {
+        Severity::Warn
+    }
+}
+
+/// The `[build.rust]` table of Rust projects whose root is a cargo workspace.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RustBuild {
+    /// The name of the member crate of the workspace that is the Worker.
+    pub package: String,
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct ModuleRule {
@@ -54,6 +223,10 @@ fn watch_dir() -> PathBuf {
     project_root().join(WATCH_DIR)
 }
 
+fn opt_level() -> String {
+    "s".to_string()
+}
+
 fn upload_dir() -> PathBuf {
     std::env::current_dir().unwrap().join(UPLOAD_DIR)
 }
@@ -117,25 +290,95 @@ impl Builder {
 
     pub fn build_command(&self) -> Option<(&str, Command)> {
         match &self.command {
-            Some(cmd) => {
-                let mut c = if cfg!(target_os = "windows") {
-                    let args: Vec<&str> = cmd.split_whitespace().collect();
-                    let mut c = Command::new("cmd");
-                    c.arg("/C");
-                    c.args(args.as_slice());
-                    c
-                } else {
-                    let mut c = Command::new("sh");
-                    c.arg("-c");
-                    c.arg(cmd);
-                    c
-                };
-
-                c.current_dir(&self.cwd);
-
-                Some((cmd, c))
-            }
+            Some(cmd) => Some((cmd, self.shell_command(cmd))),
             None => None,
         }
     }
+
+    /// A command run by the shell in `cwd`, like the build command.
+    pub fn shell_command(&self, cmd: &str) -> Command {
+        let mut c = if cfg!(target_os = "windows") {
+            let args: Vec<&str> = cmd.split_whitespace().collect();
+            let mut c = Command::new("cmd");
+            c.arg("/C");
+            c.args(args.as_slice());
+            c
+        } else {
+            let mut c = Command::new("sh");
+            c.arg("-c");
+            c.arg(cmd);
+            c
+        };
+
+        c.current_dir(&self.cwd);
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_overrides_the_top_level_build_per_key() {
+        let base = Builder {
+            command: Some("npm run build".to_string()),
+            cwd: PathBuf::from("app"),
+            watch_dir: PathBuf::from("app/src"),
+            upload: UploadFormat::ServiceWorker {},
+            wasm: None,
+            rust: None,
+            define: None,
+            hooks: None,
+            compat: None,
+        };
+        let production = BuilderOverride {
+            command: Some("npm run build:prod".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            production.apply(Some(&base)).unwrap(),
+            Builder {
+                command: Some("npm run build:prod".to_string()),
+                ..base.clone()
+            }
+        );
+        assert!(production.apply(None).is_err());
+    }
+
+    #[test]
+    fn it_uploads_a_service_worker_by_default() {
+        let build: Builder =
+            toml::from_str("command = \"npm run build\"\ncwd = \".\"\nwatch_dir = \"src\"")
+                .unwrap();
+        assert_eq!(build.command.as_deref(), Some("npm run build"));
+        assert_eq!(build.watch_dir, PathBuf::from("src"));
+        assert_eq!(build.upload, UploadFormat::ServiceWorker {});
+    }
+
+    #[test]
+    fn it_validates_the_wasm_opt_level() {
+        let wasm: WasmOpt = toml::from_str("opt_level = \"z\"").unwrap();
+        assert_eq!(wasm.flag().unwrap(), "-Oz");
+        let wasm: WasmOpt = toml::from_str("").unwrap();
+        assert_eq!(wasm.flag().unwrap(), "-Os");
+        let wasm: WasmOpt = toml::from_str("opt_level = \"5\"").unwrap();
+        assert!(wasm.flag().is_err());
+    }
+
+    #[test]
+    fn it_warns_about_unsupported_apis_by_default() {
+        let compat: CompatCheck = toml::from_str("allow = [\"fs\"]").unwrap();
+        assert_eq!(compat.severity, Severity::Warn);
+        let compat: CompatCheck = toml::from_str("severity = \"error\"").unwrap();
+        assert_eq!(compat.severity, Severity::Error);
+        assert!(compat.allow.is_empty());
+    }
+
+    #[test]
+    fn it_defaults_the_hooks_of_a_stage_to_none() {
+        let hooks: BuildHooks = toml::from_str("pre = [\"npm ci\"]").unwrap();
+        assert_eq!(hooks.pre, vec!["npm ci".to_string()]);
+        assert!(hooks.post.is_empty());
+    }
 }
@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// What bundles the Worker of a webpack project.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Bundler {
+    /// webpack, run by node with the wranglerjs wrapper.
+    Webpack,
+    /// The bundler built into wrangler, which needs neither node nor npm.
+    Builtin,
+}
+
+impl Default for Bundler {
+    fn default() -> Self {
+        Bundler::Webpack
+    }
+}
@@ -9,4 +9,12 @@ pub struct Dev {
     pub port: Option<u16>,
     pub local_protocol: Option<Protocol>,
     pub upstream_protocol: Option<Protocol>,
+    /// How many seconds the writes to KV namespaces take to be seen by the worker.
+    pub kv_propagation_delay: Option<u64>,
+    /// Whether requests going over the limits of production fail.
+    pub simulate_limits: Option<bool>,
+    /// How many subrequests a request can make.
+    pub subrequest_limit: Option<u32>,
+    /// How many seconds a request can take.
+    pub cpu_limit: Option<u64>,
 }
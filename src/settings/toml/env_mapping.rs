@@ -0,0 +1,110 @@
+//! The `[env_mapping]` table of a configuration file, which picks the environment of commands run
+//! without `--env` from the current git branch, like `main = "production"` and
+//! `"release/*" = "staging"`. A branch named exactly by a key wins over the patterns, and a longer
+//! pattern wins over a shorter one. In CI, where the branch is often checked out as a detached
+//! HEAD, the branch is read from `$GITHUB_HEAD_REF`, `$GITHUB_REF_NAME` or `$CI_COMMIT_REF_NAME`.
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::Result;
+use globset::GlobBuilder;
+use serde_json::Value;
+
+use super::read_value;
+
+const BRANCH_VARS: [&str; 3] = ["GITHUB_HEAD_REF", "GITHUB_REF_NAME", "CI_COMMIT_REF_NAME"];
+
+/// The environment that `[env_mapping]` of a configuration file maps the current git branch to,
+/// along with the branch.
+pub fn environment_for_branch(config_path: &Path) -> Result<Option<(String, String)>> {
+    if !config_path.exists() {
+        return Ok(None);
+    }
+    let mapping = match read_value(config_path)?.get("env_mapping") {
+        Some(Value::Object(mapping)) => mapping.clone(),
+        Some(_) => anyhow::bail!("[env_mapping] must be a table of branches and environments"),
+        None => return Ok(None),
+    };
+    let branch = match current_branch() {
+        Some(branch) => branch,
+        None => return Ok(None),
+    };
+    let mut patterns = Vec::new();
+    for (pattern, environment) in mapping {
+        let environment = match environment {
+            Value::String(environment) => environment,
+            _ => anyhow::bail!(
+                "The environment of `{}` in [env_mapping] must be a string",
+                pattern
+            ),
+        };
+        patterns.push((pattern, environment));
+    }
+    Ok(match_branch(&branch, &patterns)?.map(|environment| (environment, branch)))
+}
+
+fn match_branch(branch: &str, patterns: &[(String, String)]) -> Result<Option<String>> {
+    if let Some((_, environment)) = patterns.iter().find(|(pattern, _)| pattern == branch) {
+        return Ok(Some(environment.clone()));
+    }
+    let mut matched: Option<&(String, String)> = None;
+    for entry in patterns {
+        let glob = GlobBuilder::new(&entry.0)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "`{}` of [env_mapping] is not a valid pattern: {}",
+                    entry.0,
+                    e
+                )
+            })?
+            .compile_matcher();
+        if glob.is_match(branch) && matched.map_or(true, |m| entry.0.len() > m.0.len()) {
+            matched = Some(entry);
+        }
+    }
+    Ok(matched.map(|(_, environment)| environment.clone()))
+}
+
+/// The git branch checked out in the current directory, or the branch of the CI job.
+pub fn current_branch() -> Option<String> {
+    let output = Command::new("git")
+        .args(&["symbolic-ref", "--quiet", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success());
+    if let Some(output) = output {
+        let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if !branch.is_empty() {
+            return Some(branch);
+        }
+    }
+    BRANCH_VARS
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|branch| !branch.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_maps_branches_to_environments() {
+        let patterns: Vec<(String, String)> = vec![
+            ("main".to_string(), "production".to_string()),
+            ("release/*".to_string(), "staging".to_string()),
+            ("release/hotfix-*".to_string(), "hotfix".to_string()),
+            ("*".to_string(), "dev".to_string()),
+        ];
+        let environment = |branch| match_branch(branch, &patterns).unwrap();
+        assert_eq!(environment("main"), Some("production".to_string()));
+        assert_eq!(environment("release/1.2"), Some("staging".to_string()));
+        assert_eq!(environment("release/hotfix-3"), Some("hotfix".to_string()));
+        assert_eq!(environment("feature"), Some("dev".to_string()));
+        assert_eq!(environment("feature/login"), None);
+    }
+}
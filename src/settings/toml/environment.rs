@@ -4,11 +4,13 @@ use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
 use serde_with::rust::string_empty_as_none;
 
-use crate::settings::toml::builder::Builder;
+use crate::settings::toml::builder::BuilderOverride;
+use crate::settings::toml::bundler::Bundler;
 use crate::settings::toml::durable_objects::DurableObjects;
 use crate::settings::toml::kv_namespace::ConfigKvNamespace;
 use crate::settings::toml::r2_bucket::ConfigR2Bucket;
-use crate::settings::toml::route::RouteConfig;
+use crate::settings::toml::route::{RouteConfig, RouteEntry};
+use crate::settings::toml::service::ServiceBinding;
 use crate::settings::toml::site::Site;
 use crate::settings::toml::triggers::Triggers;
 
@@ -20,17 +22,21 @@ pub struct Environment {
     pub workers_dev: Option<bool>,
     #[serde(default, with = "string_empty_as_none")]
     pub route: Option<String>,
-    pub routes: Option<Vec<String>>,
+    pub routes: Option<Vec<RouteEntry>>,
     #[serde(default, with = "string_empty_as_none")]
     pub zone_id: Option<String>,
     pub webpack_config: Option<String>,
-    pub build: Option<Builder>,
+    pub node_compat: Option<bool>,
+    pub bundler: Option<Bundler>,
+    pub minify: Option<bool>,
+    pub build: Option<BuilderOverride>,
     pub private: Option<bool>,
     pub site: Option<Site>,
     #[serde(alias = "kv-namespaces")]
     pub kv_namespaces: Option<Vec<ConfigKvNamespace>>,
     pub r2_buckets: Option<Vec<ConfigR2Bucket>>,
-    pub vars: Option<HashMap<String, String>>,
+    pub services: Option<Vec<ServiceBinding>>,
+    pub vars: Option<HashMap<String, serde_json::Value>>,
     pub text_blobs: Option<HashMap<String, PathBuf>>,
     pub triggers: Option<Triggers>,
     pub durable_objects: Option<DurableObjects>,
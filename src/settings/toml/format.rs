@@ -0,0 +1,321 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use config::{Config, File, FileFormat};
+use serde_json::Value;
+
+/// The names of the configuration files looked up in a project, in order of preference.
+pub const CONFIG_FILE_NAMES: [&str; 4] = [
+    "wrangler.toml",
+    "wrangler.json",
+    "wrangler.yaml",
+    "wrangler.yml",
+];
+
+/// The key of a configuration file naming a base file whose settings it overrides.
+const EXTENDS_KEY: &str = "extends";
+
+/// Arrays of bindings, which are added to the bindings of the base file instead of replacing them.
+const APPENDED_KEYS: [&str; 6] = [
+    "kv_namespaces",
+    "kv-namespaces",
+    "r2_buckets",
+    "services",
+    "classes",
+    "bindings",
+];
+
+/// The formats a project configuration file can be written in, detected from its extension.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl ConfigFormat {
+    pub fn from_path(config_path: &Path) -> Result<Self> {
+        match config_path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Ok(ConfigFormat::Toml),
+            Some("json") => Ok(ConfigFormat::Json),
+            Some("yaml") | Some("yml") => Ok(ConfigFormat::Yaml),
+            _ => anyhow::bail!(
+                "Cannot tell the format of {}; configuration files must end with .toml, .json, .yaml or .yml",
+                config_path.display()
+            ),
+        }
+    }
+
+    pub(super) fn file_format(self) -> FileFormat {
+        match self {
+            ConfigFormat::Toml => FileFormat::Toml,
+            ConfigFormat::Json => FileFormat::Json,
+            ConfigFormat::Yaml => FileFormat::Yaml,
+        }
+    }
+}
+
+/// Reads a configuration file into a generic value, e.g. to look for unknown keys. Unlike
+/// `Manifest::new`, `CF_*` environment variables are not applied.
+///
+/// A file can extend a base file with `extends = "../wrangler.base.toml"`, relative to itself.
+/// Its settings override the ones of the base file key by key, tables like `[vars]` or
+/// `[env.production]` being merged recursively, except for arrays of bindings such as
+/// `kv_namespaces`, `r2_buckets` or Durable Object classes, which are appended to the ones of
+/// the base file. A base file can itself extend another file.
+///
+/// The settings of the local file next to it (see [`local_config_path`]) are then applied over
+/// the result with the same rules, except that every array replaces the one it overrides, so
+/// that a developer can e.g. use their own `kv_namespaces` without touching the shared file.
+pub fn read_value(config_path: &Path) -> Result<Value> {
+    let mut value = read_extended_value(config_path, &mut Vec::new())?;
+
+    let local_path = local_config_path(config_path);
+    if local_path.exists() {
+        log::info!("Applying the local settings of {}", local_path.display());
+        let local = read_file_value(&local_path)?;
+        anyhow::ensure!(
+            local.get(EXTENDS_KEY).is_none(),
+            "`extends` cannot be used in the local configuration file {}",
+            local_path.display()
+        );
+        merge(&mut value, local, "", false);
+    }
+    Ok(value)
+}
+
+/// The file whose settings override the ones of a configuration file on this machine only, e.g.
+/// `wrangler.local.toml` for `wrangler.toml`. It is meant to be ignored by git.
+pub fn local_config_path(config_path: &Path) -> PathBuf {
+    let stem = config_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_name = match config_path.extension() {
+        Some(ext) => format!("{}.local.{}", stem, ext.to_string_lossy()),
+        None => format!("{}.local", stem),
+    };
+    config_path.with_file_name(file_name)
+}
+
+/// Loads a configuration file, along with the files it extends and its local settings.
+pub(super) fn load_config(config_path: &Path) -> Result<Config> {
+    let mut config = Config::new();
+    if read_file_value(config_path)?.get(EXTENDS_KEY).is_some()
+        || local_config_path(config_path).exists()
+    {
+        config = Config::try_from(&read_value(config_path)?)?;
+    } else {
+        let format = ConfigFormat::from_path(config_path)?;
+        config.merge(File::from(config_path).format(format.file_format()))?;
+    }
+    Ok(config)
+}
+
+fn read_file_value(config_path: &Path) -> Result<Value> {
+    let format = ConfigFormat::from_path(config_path)?;
+    let mut config = Config::new();
+    config.merge(File::from(config_path).format(format.file_format()))?;
+    Ok(config.try_into()?)
+}
+
+fn read_extended_value(config_path: &Path, seen: &mut Vec<PathBuf>) -> Result<Value> {
+    let canonical = config_path.canonicalize()?;
+    anyhow::ensure!(
+        !seen.contains(&canonical),
+        "{} extends itself through `extends`",
+        config_path.display()
+    );
+    seen.push(canonical);
+
+    let mut value = read_file_value(config_path)?;
+    let base = match value
+        .as_object_mut()
+        .and_then(|table| table.remove(EXTENDS_KEY))
+    {
+        None => return Ok(value),
+        Some(Value::String(base)) => base,
+        Some(_) => anyhow::bail!(
+            "`extends` in {} must be the path of a configuration file",
+            config_path.display()
+        ),
+    };
+    let base_path = config_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(&base);
+    anyhow::ensure!(
+        base_path.exists(),
+        "{} extends {}, which does not exist",
+        config_path.display(),
+        base_path.display()
+    );
+
+    let mut merged = read_extended_value(&base_path, seen)?;
+    merge(&mut merged, value, "", true);
+    Ok(merged)
+}
+
+/// Overrides the settings of a base file with the ones of a file extending it. Arrays of
+/// bindings are appended to the ones of the base file when `append_bindings` is set.
+fn merge(base: &mut Value, value: Value, key: &str, append_bindings: bool) {
+    match (base, value) {
+        (Value::Object(base), Value::Object(table)) => {
+            for (key, value) in table {
+                match base.get_mut(&key) {
+                    Some(base) => merge(base, value, &key, append_bindings),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(base), Value::Array(items))
+            if append_bindings && APPENDED_KEYS.contains(&key) =>
+        {
+            base.extend(items)
+        }
+        (base, value) => *base = value,
+    }
+}
+
+/// Finds the configuration file of a project in `dir`, whatever its format.
+pub fn find_config_file(dir: &Path) -> Option<PathBuf> {
+    CONFIG_FILE_NAMES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn it_detects_formats_from_extensions() {
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("wrangler.toml")).unwrap(),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("config/wrangler.json")).unwrap(),
+            ConfigFormat::Json
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("wrangler.yml")).unwrap(),
+            ConfigFormat::Yaml
+        );
+        assert!(ConfigFormat::from_path(Path::new("wrangler")).is_err());
+    }
+
+    #[test]
+    fn it_prefers_toml_when_finding_config_files() {
+        let tmp_dir = tempdir().unwrap();
+        assert_eq!(find_config_file(tmp_dir.path()), None);
+
+        fs::write(tmp_dir.path().join("wrangler.yaml"), "name: worker\n").unwrap();
+        assert_eq!(
+            find_config_file(tmp_dir.path()),
+            Some(tmp_dir.path().join("wrangler.yaml"))
+        );
+
+        fs::write(tmp_dir.path().join("wrangler.toml"), "name = \"worker\"\n").unwrap();
+        assert_eq!(
+            find_config_file(tmp_dir.path()),
+            Some(tmp_dir.path().join("wrangler.toml"))
+        );
+    }
+
+    #[test]
+    fn it_merges_extended_files() {
+        let tmp_dir = tempdir().unwrap();
+        fs::write(
+            tmp_dir.path().join("wrangler.base.toml"),
+            r#"
+account_id = "0123456789abcdef"
+type = "javascript"
+kv_namespaces = [{ binding = "SHARED", id = "1" }]
+
+[vars]
+region = "eu"
+debug = "false"
+"#,
+        )
+        .unwrap();
+        fs::create_dir(tmp_dir.path().join("worker")).unwrap();
+        let config_path = tmp_dir.path().join("worker").join("wrangler.json");
+        fs::write(
+            &config_path,
+            r#"{
+                "extends": "../wrangler.base.toml",
+                "name": "worker",
+                "kv_namespaces": [{ "binding": "OWN", "id": "2" }],
+                "vars": { "debug": "true" }
+            }"#,
+        )
+        .unwrap();
+
+        let value = read_value(&config_path).unwrap();
+        assert_eq!(value["name"], "worker");
+        assert_eq!(value["account_id"], "0123456789abcdef");
+        assert_eq!(value.get("extends"), None);
+        let bindings = value["kv_namespaces"].as_array().unwrap();
+        assert_eq!(bindings.len(), 2);
+        assert_eq!(bindings[0]["binding"], "SHARED");
+        assert_eq!(bindings[1]["binding"], "OWN");
+        assert_eq!(value["vars"]["region"], "eu");
+        assert_eq!(value["vars"]["debug"], "true");
+    }
+
+    #[test]
+    fn it_applies_local_settings() {
+        let tmp_dir = tempdir().unwrap();
+        let config_path = tmp_dir.path().join("wrangler.toml");
+        fs::write(
+            &config_path,
+            r#"
+name = "worker"
+account_id = "shared"
+kv_namespaces = [{ binding = "CACHE", id = "1" }]
+
+[vars]
+region = "eu"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            local_config_path(&config_path),
+            tmp_dir.path().join("wrangler.local.toml")
+        );
+        fs::write(
+            local_config_path(&config_path),
+            r#"
+account_id = "mine"
+kv_namespaces = [{ binding = "CACHE", id = "2" }]
+
+[vars]
+debug = "true"
+"#,
+        )
+        .unwrap();
+
+        let value = read_value(&config_path).unwrap();
+        assert_eq!(value["name"], "worker");
+        assert_eq!(value["account_id"], "mine");
+        let bindings = value["kv_namespaces"].as_array().unwrap();
+        assert_eq!(bindings.len(), 1);
+        assert_eq!(bindings[0]["id"], "2");
+        assert_eq!(value["vars"]["region"], "eu");
+        assert_eq!(value["vars"]["debug"], "true");
+    }
+
+    #[test]
+    fn it_rejects_cyclic_extends() {
+        let tmp_dir = tempdir().unwrap();
+        let config_path = tmp_dir.path().join("wrangler.toml");
+        fs::write(&config_path, "extends = \"wrangler.toml\"\n").unwrap();
+        assert!(read_value(&config_path).is_err());
+    }
+}
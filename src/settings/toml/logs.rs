@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+/// Where `wrangler logs query` finds the past events of a worker.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LogsConfig {
+    /// The R2 bucket that a Logpush job of the `workers_trace_events` dataset writes to.
+    pub logpush_bucket: String,
+    /// The path in the bucket that the job writes under, if any.
+    pub logpush_prefix: Option<String>,
+}
@@ -1,10 +1,11 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use config::{Config, File};
+use config::Config;
 
 use anyhow::{anyhow, Result};
 use chrono::Utc;
@@ -14,45 +15,90 @@ use serde_with::rust::string_empty_as_none;
 
 use super::migrations::{MigrationConfig, MigrationTag, Migrations};
 use super::UsageModel;
-use crate::commands::whoami::fetch_accounts;
+use crate::commands::whoami::{fetch_accounts_cached, fetch_zones_cached};
 use crate::commands::{validate_worker_name, whoami, DEFAULT_CONFIG_PATH};
 use crate::deploy::{self, DeployTarget, DeploymentSet};
+use crate::settings::environment::per_environment_var;
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::builder::Builder;
+use crate::settings::toml::bundler::Bundler;
 use crate::settings::toml::dev::Dev;
 use crate::settings::toml::durable_objects::DurableObjects;
 use crate::settings::toml::environment::Environment;
+use crate::settings::toml::format::{load_config, read_value};
 use crate::settings::toml::kv_namespace::{ConfigKvNamespace, KvNamespace};
+use crate::settings::toml::logs::LogsConfig;
+use crate::settings::toml::notifications::Notifications;
 use crate::settings::toml::r2_bucket::{ConfigR2Bucket, R2Bucket};
-use crate::settings::toml::route::RouteConfig;
+use crate::settings::toml::route::{RouteConfig, RouteEntry};
+use crate::settings::toml::schema;
+use crate::settings::toml::service::ServiceBinding;
 use crate::settings::toml::site::Site;
+use crate::settings::toml::tail::TailConfig;
 use crate::settings::toml::target_type::TargetType;
 use crate::settings::toml::triggers::Triggers;
 use crate::settings::toml::Target;
+use crate::settings::{get_global_config_path, profile, project};
 use crate::terminal::{
-    emoji,
+    emoji, interactive,
     message::{Message, StdOut},
     styles,
 };
 
+/// The variables that override the keys of the same name in `wrangler.toml`, without `CF_`.
+const MANIFEST_ENV_VARS: [&str; 2] = ["CF_ACCOUNT_ID", "CF_ZONE_ID"];
+
+/// The account passed with `--account-id`, which wins over every `account_id` in `wrangler.toml`.
+static ACCOUNT_ID_OVERRIDE: OnceCell<String> = OnceCell::new();
+
+/// Use an account for every environment, instead of the ones in `wrangler.toml`.
+pub fn set_account_id_override(account_id: &str) {
+    if ACCOUNT_ID_OVERRIDE.set(account_id.to_string()).is_err() {
+        log::debug!("The account ID was already overridden");
+    }
+}
+
+/// Whether `--strict` was passed, rejecting unknown keys like `strict = true` does.
+static STRICT_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Reject unknown keys in configuration files, even without `strict = true`.
+pub fn set_strict_mode() {
+    STRICT_MODE.store(true, Ordering::Relaxed);
+}
+
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct Manifest {
     #[serde(default)]
     pub name: String,
     #[serde(rename = "type")]
     pub target_type: TargetType,
+    /// The entry point of the Worker, used instead of the `main` of package.json.
+    pub main: Option<PathBuf>,
+    /// The entry point of each handler of a Worker in the modules format, like
+    /// `scheduled = "src/cron.ts"`, bundled into separate chunks by the builtin bundler.
+    pub entries: Option<BTreeMap<String, PathBuf>>,
     #[serde(default)]
     pub account_id: LazyAccountId,
     pub workers_dev: Option<bool>,
     #[serde(default, with = "string_empty_as_none")]
     pub route: Option<String>,
-    pub routes: Option<Vec<String>>,
+    pub routes: Option<Vec<RouteEntry>>,
     #[serde(default, with = "string_empty_as_none")]
     pub zone_id: Option<String>,
     pub webpack_config: Option<String>,
+    /// Polyfills the built-in modules of Node.js, such as `buffer` or `events`, in the bundle
+    /// built by webpack.
+    pub node_compat: Option<bool>,
+    /// Bundles webpack projects with webpack, or with the bundler built into wrangler.
+    pub bundler: Option<Bundler>,
+    /// Minifies the script bundled by the builtin bundler.
+    pub minify: Option<bool>,
     pub build: Option<Builder>,
     pub private: Option<bool>,
     pub dev: Option<Dev>,
+    pub tail: Option<TailConfig>,
+    pub notifications: Option<Notifications>,
+    pub logs: Option<LogsConfig>,
     pub triggers: Option<Triggers>,
     pub migrations: Option<Vec<MigrationConfig>>,
     #[serde(default, with = "string_empty_as_none")]
@@ -65,12 +111,15 @@ pub struct Manifest {
     #[serde(alias = "kv-namespaces")]
     pub kv_namespaces: Option<Vec<ConfigKvNamespace>>,
     pub r2_buckets: Option<Vec<ConfigR2Bucket>>,
+    pub services: Option<Vec<ServiceBinding>>,
     // TODO: maybe one day, serde toml support will allow us to serialize sites
     // as a TOML inline table (this would prevent confusion with environments too!)
     pub site: Option<Site>,
-    pub vars: Option<HashMap<String, String>>,
+    pub vars: Option<HashMap<String, serde_json::Value>>,
     pub text_blobs: Option<HashMap<String, PathBuf>>,
     pub wasm_modules: Option<HashMap<String, PathBuf>>,
+    /// Reject unknown keys instead of ignoring them.
+    pub strict: Option<bool>,
 }
 
 impl Manifest {
@@ -78,12 +127,12 @@ impl Manifest {
         let file_name = config_path.file_name().unwrap().to_str().unwrap();
         let mut message = format!("{} not found", file_name);
         if config_path.to_str().unwrap() == DEFAULT_CONFIG_PATH {
-            message.push_str("; run `wrangler init` to create one.");
+            message.push_str("; run `wrangler init` to create one, or pass a wrangler.json or wrangler.yaml file with `--config`.");
         }
         anyhow::ensure!(config_path.exists(), message);
         let config = read_config(config_path)?;
 
-        let manifest: Manifest = match config.try_into() {
+        let mut manifest: Manifest = match config.try_into() {
             Ok(m) => m,
             Err(e) => {
                 if e.to_string().contains("unknown field `kv-namespaces`") {
@@ -94,11 +143,64 @@ impl Manifest {
             }
         };
 
+        if STRICT_MODE.load(Ordering::Relaxed) || manifest.strict.unwrap_or_default() {
+            check_for_unknown_keys(config_path)?;
+        }
         check_for_duplicate_names(&manifest)?;
+        manifest.apply_environment_overrides();
+
+        if manifest.compatibility_date.is_none() {
+            // Machine wide defaults are applied beneath the project's own settings
+            manifest.compatibility_date =
+                profile::default_compatibility_date(&get_global_config_path());
+            if let Some(date) = &manifest.compatibility_date {
+                log::info!("Using the default compatibility date {}", date);
+            }
+        }
 
         Ok(manifest)
     }
 
+    /// Let `CF_ACCOUNT_ID_<ENV>` and `CF_ZONE_ID_<ENV>` override the IDs of each environment,
+    /// so that environments living in different accounts can be deployed without editing
+    /// `wrangler.toml`. `--account-id` overrides all of them.
+    fn apply_environment_overrides(&mut self) {
+        if let Some(account_id) = ACCOUNT_ID_OVERRIDE.get() {
+            log::info!("Using the account ID passed with --account-id");
+            self.account_id = Some(account_id.clone()).into();
+            if let Some(envs) = &mut self.env {
+                for environment in envs.values_mut() {
+                    environment.account_id = Some(account_id.clone());
+                }
+            }
+            self.apply_zone_overrides();
+            return;
+        }
+
+        if let Some(envs) = &mut self.env {
+            for (name, environment) in envs.iter_mut() {
+                let account_var = per_environment_var("CF_ACCOUNT_ID", name);
+                if let Ok(account_id) = env::var(&account_var) {
+                    log::info!("Using ${} as the account ID of [env.{}]", account_var, name);
+                    environment.account_id = Some(account_id);
+                }
+            }
+        }
+        self.apply_zone_overrides();
+    }
+
+    fn apply_zone_overrides(&mut self) {
+        if let Some(envs) = &mut self.env {
+            for (name, environment) in envs.iter_mut() {
+                let zone_var = per_environment_var("CF_ZONE_ID", name);
+                if let Ok(zone_id) = env::var(&zone_var) {
+                    log::info!("Using ${} as the zone ID of [env.{}]", zone_var, name);
+                    environment.zone_id = Some(zone_id);
+                }
+            }
+        }
+    }
+
     pub fn generate(
         name: String,
         target_type: Option<TargetType>,
@@ -175,8 +277,9 @@ impl Manifest {
             }
         }
 
-        config_template_doc["compatibility_date"] =
-            toml_edit::value(Utc::now().format("%F").to_string());
+        let compatibility_date = profile::default_compatibility_date(&get_global_config_path())
+            .unwrap_or_else(|| Utc::now().format("%F").to_string());
+        config_template_doc["compatibility_date"] = toml_edit::value(compatibility_date);
 
         // TODO: https://github.com/cloudflare/wrangler/issues/773
 
@@ -212,39 +315,79 @@ impl Manifest {
     }
 
     pub fn get_deployments(&self, env: Option<&str>) -> Result<DeploymentSet> {
-        let script = self.worker_name(env);
+        self.deployments(env, false)
+    }
+
+    /// Like `get_deployments`, but asks the user which zone routes without a `zone_id` are in
+    /// when it can't be told from their names, and remembers it for the environment and the
+    /// hosts of the routes. Only `wrangler publish` should ask.
+    pub fn pick_deployments(&self, env: Option<&str>) -> Result<DeploymentSet> {
+        self.deployments(env, true)
+    }
+
+    fn deployments(&self, env_name: Option<&str>, pick_zone: bool) -> Result<DeploymentSet> {
+        let script = self.worker_name(env_name);
         validate_worker_name(&script)?;
 
         let mut deployments = DeploymentSet::new();
 
-        let env = self.get_environment(env)?;
+        let env = self.get_environment(env_name)?;
 
         let mut add_routed_deployments = |route_config: &RouteConfig| -> Result<()> {
+            let picked;
+            let route_config = match find_zone_id(env_name, route_config, pick_zone)? {
+                Some(zone_id) => {
+                    picked = RouteConfig {
+                        zone_id: Some(zone_id),
+                        account_id: route_config.account_id.clone(),
+                        workers_dev: route_config.workers_dev,
+                        route: route_config.route.clone(),
+                        routes: route_config.routes.clone(),
+                    };
+                    &picked
+                }
+                None => route_config,
+            };
+
             if route_config.is_zoned() {
-                let zoned = deploy::ZonedTarget::build(&script, route_config)?;
+                let mut zoned_targets = Vec::new();
+                if route_config.zone_id.is_some() {
+                    zoned_targets.push(deploy::ZonedTarget::build(&script, route_config)?);
+                }
+                zoned_targets.extend(deploy::ZonedTarget::build_per_zone(
+                    &script,
+                    route_config,
+                    |zone_name| zone_id_by_name(zone_name, &route_config.account_id),
+                )?);
 
-                if zoned.routes.is_empty() {
+                if zoned_targets.iter().all(|zoned| zoned.routes.is_empty()) {
                     return Ok(());
                 }
 
-                // This checks all of the configured routes for the wildcard ending and warns
-                // the user that their site may not work as expected without it.
-                if self.site.is_some() {
-                    let no_star_routes = zoned
-                        .routes
-                        .iter()
-                        .filter(|r| !r.pattern.ends_with('*'))
-                        .map(|r| r.pattern.as_str())
-                        .collect::<Vec<_>>();
-                    if !no_star_routes.is_empty() {
-                        StdOut::warn(&format!(
-                            "The following routes in your configuration file should have a trailing * to apply the Worker on every path, otherwise your site will not behave as expected.\n{}",
-                            no_star_routes.join("\n"))
-                        );
+                for zoned in zoned_targets {
+                    if zoned.routes.is_empty() {
+                        continue;
                     }
-                }
 
-                deployments.push(DeployTarget::Zoned(zoned));
+                    // This checks all of the configured routes for the wildcard ending and warns
+                    // the user that their site may not work as expected without it.
+                    if self.site.is_some() {
+                        let no_star_routes = zoned
+                            .routes
+                            .iter()
+                            .filter(|r| !r.pattern.ends_with('*'))
+                            .map(|r| r.pattern.as_str())
+                            .collect::<Vec<_>>();
+                        if !no_star_routes.is_empty() {
+                            StdOut::warn(&format!(
+                                "The following routes in your configuration file should have a trailing * to apply the Worker on every path, otherwise your site will not behave as expected.\n{}",
+                                no_star_routes.join("\n"))
+                            );
+                        }
+                    }
+
+                    deployments.push(DeployTarget::Zoned(zoned));
+                }
             }
 
             if route_config.is_zoneless() {
@@ -252,14 +395,12 @@ impl Manifest {
                 deployments.push(DeployTarget::Zoneless(zoneless));
             }
 
-            if !route_config.is_zoned()
+            if route_config.zone_id.is_none()
                 && !route_config.is_zoneless()
-                && (route_config.route.is_some()
-                    || (route_config.routes.is_some()
-                        && !route_config.routes.as_ref().unwrap().is_empty()))
+                && route_config.has_shared_zone_routes()
             {
                 anyhow::bail!(
-                    "Routes specified with no zone, specify `zone_id` in your wrangler.toml"
+                    "Routes specified with no zone, specify `zone_id` in your wrangler.toml, or the `zone_name` of each route"
                 )
             }
 
@@ -267,11 +408,12 @@ impl Manifest {
         };
 
         if let Some(env) = env {
-            if let Some(env_route_cfg) = env.route_config(
+            if let Some(mut env_route_cfg) = env.route_config(
                 self.account_id.if_present().cloned(),
                 self.zone_id.clone(),
                 self.workers_dev,
             ) {
+                env_route_cfg.account_id = env_route_cfg.account_id.for_env(env_name);
                 add_routed_deployments(&env_route_cfg)
             } else {
                 let config = self.route_config();
@@ -286,25 +428,18 @@ impl Manifest {
         }?;
 
         let crons = match env {
-            Some(e) => {
-                let account_id = match e.account_id.as_ref() {
-                    Some(id) => id,
-                    None => self.account_id.load()?,
-                };
-                e.triggers
-                    .as_ref()
-                    .or_else(|| self.triggers.as_ref())
-                    .map(|t| (t.crons.as_slice(), account_id))
-            }
-            None => match self.triggers.as_ref() {
-                None => None,
-                Some(t) => Some((t.crons.as_slice(), self.account_id.load()?)),
-            },
+            Some(e) => e
+                .triggers
+                .as_ref()
+                .or_else(|| self.triggers.as_ref())
+                .map(|t| t.crons.as_slice()),
+            None => self.triggers.as_ref().map(|t| t.crons.as_slice()),
         };
 
-        if let Some((crons, account)) = crons {
+        if let Some(crons) = crons {
+            let account = self.get_account_id(env_name)?;
             let scheduled = deploy::ScheduleTarget {
-                account_id: account.clone(),
+                account_id: account,
                 script_name: script.clone(),
                 crons: crons.to_vec(),
             };
@@ -330,7 +465,11 @@ impl Manifest {
                 return Ok(account_id.to_string());
             }
         }
-        self.account_id.load().map(String::from)
+        self.account_id
+            .clone()
+            .for_env(environment_name)
+            .load()
+            .map(String::from)
     }
 
     pub fn get_target(&self, environment_name: Option<&str>, preview: bool) -> Result<Target> {
@@ -368,15 +507,21 @@ impl Manifest {
         Not inherited: Must be defined for every environment individually.
         */
         let mut target = Target {
-            target_type: self.target_type.clone(),       // Top level
-            account_id: self.account_id.clone(),         // Inherited
+            target_type: self.target_type.clone(), // Top level
+            account_id: self.account_id.clone().for_env(environment_name), // Inherited
+            main: self.main.clone(),               // Top level
+            entries: self.entries.clone(),         // Top level
             webpack_config: self.webpack_config.clone(), // Inherited
-            build: self.build.clone(),                   // Inherited
+            node_compat: self.node_compat.unwrap_or_default(), // Inherited
+            bundler: self.bundler.unwrap_or_default(), // Inherited
+            minify: self.minify.unwrap_or_default(), // Inherited
+            build: self.build.clone(),             // Inherited
             // importantly, the top level name will be modified
             // to include the name of the environment
             name: self.name.clone(), // Inherited
             kv_namespaces: get_namespaces(self.kv_namespaces.clone(), preview)?, // Not inherited
             r2_buckets: get_buckets(self.r2_buckets.clone(), preview)?, // Not inherited
+            services: self.services.clone().unwrap_or_default(), // Not inherited
             durable_objects: self.durable_objects.clone(), // Not inherited
             migrations: match (preview, &self.migrations) {
                 (false, Some(migrations)) => Some(Migrations::List {
@@ -404,8 +549,17 @@ impl Manifest {
             if let Some(webpack_config) = &environment.webpack_config {
                 target.webpack_config = Some(webpack_config.clone());
             }
+            if let Some(node_compat) = environment.node_compat {
+                target.node_compat = node_compat;
+            }
+            if let Some(bundler) = environment.bundler {
+                target.bundler = bundler;
+            }
+            if let Some(minify) = environment.minify {
+                target.minify = minify;
+            }
             if let Some(build) = &environment.build {
-                target.build = Some(build.clone());
+                target.build = Some(build.apply(self.build.as_ref())?);
             }
 
             // don't inherit kv namespaces because it is an anti-pattern to use the same namespaces across multiple environments
@@ -414,6 +568,9 @@ impl Manifest {
             // don't inherit r2 buckets because it is an anti-pattern to use the same buckets across multiple environments
             target.r2_buckets = get_buckets(environment.r2_buckets.clone(), preview)?;
 
+            // don't inherit service bindings, since each environment binds its own Workers
+            target.services = environment.services.clone().unwrap_or_default();
+
             // don't inherit durable object configuration
             target.durable_objects = environment.durable_objects.clone();
 
@@ -426,6 +583,9 @@ impl Manifest {
             target.vars = environment.vars.clone();
         }
 
+        target.validate_main()?;
+        target.validate_entries()?;
+
         Ok(target)
     }
 
@@ -573,15 +733,25 @@ impl Manifest {
     }
 }
 
-#[derive(Debug, Clone, Default, PartialEq)]
-pub struct LazyAccountId(OnceCell<String>);
+#[derive(Debug, Clone, Default)]
+pub struct LazyAccountId {
+    id: OnceCell<String>,
+    /// The environment whose account is picked, which is remembered separately for each.
+    env: Option<String>,
+}
+
+impl PartialEq for LazyAccountId {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
 
 impl Serialize for LazyAccountId {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        self.0.get().serialize(serializer)
+        self.id.get().serialize(serializer)
     }
 }
 
@@ -601,20 +771,26 @@ impl<'de> Deserialize<'de> for LazyAccountId {
 
 impl From<Option<String>> for LazyAccountId {
     fn from(opt: Option<String>) -> Self {
-        let cell = OnceCell::new();
+        let id = OnceCell::new();
         if let Some(val) = opt {
-            cell.set(val).unwrap();
+            id.set(val).unwrap();
         }
-        Self(cell)
+        Self { id, env: None }
     }
 }
 
 impl LazyAccountId {
+    /// Picks the account of an environment, rather than the top-level one, when none is set.
+    pub(crate) fn for_env(mut self, env: Option<&str>) -> Self {
+        self.env = env.map(str::to_string);
+        self
+    }
+
     /// Return the `account_id` in `wrangler.toml`, if present.
     ///
     /// Use this with caution; prefer `maybe_load` instead where possible.
     fn if_present(&self) -> Option<&String> {
-        self.0.get()
+        self.id.get()
     }
 
     /// If `account_id` can be inferred automatically, do so;
@@ -622,13 +798,25 @@ impl LazyAccountId {
     ///
     /// Note that *unlike* `load`, this will never prompt the user or warn.
     pub(crate) fn maybe_load(&self) -> Option<String> {
-        if let Some(id) = self.0.get() {
+        if let Some(id) = self.id.get() {
             return Some(id.to_owned());
         }
 
+        if let Some(id) = ACCOUNT_ID_OVERRIDE.get() {
+            return Some(id.clone());
+        }
+
+        if let Some(id) = project::account_id(self.env.as_deref()) {
+            return Some(id);
+        }
+
+        if let Some(id) = profile::default_account_id(&get_global_config_path()) {
+            return Some(id);
+        }
+
         if let Some(mut accounts) = GlobalUser::new()
             .ok()
-            .and_then(|user| fetch_accounts(&user).ok())
+            .and_then(|user| fetch_accounts_cached(&user).ok())
         {
             if accounts.len() == 1 {
                 return Some(accounts.pop().unwrap().id);
@@ -639,21 +827,61 @@ impl LazyAccountId {
     }
 
     /// Load the account ID, possibly prompting the user.
+    ///
+    /// Without an `account_id` in `wrangler.toml` or `--account-id`, the account picked for
+    /// this project in `.wrangler/config` is used, then the default account of the selected profile, and
+    /// finally the accounts of the user. When the user has several accounts, they are asked
+    /// to pick one, which is remembered for the environment of the project.
     #[cfg_attr(test, allow(unreachable_code))]
     pub(crate) fn load(&self) -> Result<&String> {
-        self.0.get_or_try_init(|| {
+        self.id.get_or_try_init(|| {
             #[cfg(test)]
             // don't try to fetch the accounts for this ID, since it's not valid.
             anyhow::bail!("tried to load account id");
 
+            if let Some(id) = ACCOUNT_ID_OVERRIDE.get() {
+                return Ok(id.clone());
+            }
+
+            if let Some(id) = project::account_id(self.env.as_deref()) {
+                return Ok(id);
+            }
+
+            if let Some(id) = profile::default_account_id(&get_global_config_path()) {
+                return Ok(id);
+            }
+
             let user = GlobalUser::new()?;
-            match fetch_accounts(&user)?.as_slice() {
+            match fetch_accounts_cached(&user)?.as_slice() {
                 [] => {
                     StdOut::user_error("Your authentication token does not match any account ID.");
                     whoami::display_account_id_maybe();
                     anyhow::bail!("field `account_id` is required")
                 }
-                [single] => Ok(single.id.clone()),
+                [single] => {
+                    StdOut::info(&format!(
+                        "Using {} ({}), the only account you have access to. Set `account_id` or pass `--account-id` to silence this notice.",
+                        single.name, single.id
+                    ));
+                    Ok(single.id.clone())
+                }
+                multiple if interactive::is_interactive() => {
+                    let options = multiple
+                        .iter()
+                        .map(|account| format!("{} ({})", account.name, account.id))
+                        .collect::<Vec<_>>();
+                    let index = interactive::select(
+                        "You have multiple accounts. Which one should this project use?",
+                        &options,
+                    )?;
+                    let id = multiple[index].id.clone();
+                    project::set_account_id(self.env.as_deref(), &id)?;
+                    StdOut::info(&format!(
+                        "Remembered this account in {}; pass `--account-id` to use another one",
+                        project::project_config_path().display()
+                    ));
+                    Ok(id)
+                }
                 _multiple => {
                     StdOut::user_error("You have multiple accounts.");
                     whoami::display_account_id_maybe();
@@ -664,6 +892,129 @@ impl LazyAccountId {
     }
 }
 
+/// Find the zone of routes configured without a `zone_id`.
+///
+/// The zone picked for the environment and the hosts of the routes in `.wrangler/config` is used
+/// first. Otherwise, a zone whose name matches the host of a route is used. When none or several
+/// match and `pick` is set, the user is asked to pick one, which is remembered. Returns `None`
+/// when no zone is needed or none could be found.
+#[cfg_attr(test, allow(unreachable_code))]
+fn find_zone_id(
+    env: Option<&str>,
+    route_config: &RouteConfig,
+    pick: bool,
+) -> Result<Option<String>> {
+    if route_config.zone_id.is_some() || !route_config.has_shared_zone_routes() {
+        return Ok(None);
+    }
+
+    #[cfg(test)]
+    // don't try to fetch the zones of the user, since there is none.
+    return Ok(None);
+
+    let mut hosts = route_config
+        .shared_zone_patterns()
+        .into_iter()
+        .map(route_host)
+        .collect::<Vec<_>>();
+    hosts.sort_unstable();
+    hosts.dedup();
+    if let Some(id) = hosts.iter().find_map(|host| project::zone_id(env, host)) {
+        return Ok(Some(id));
+    }
+
+    let user = match GlobalUser::new() {
+        Ok(user) => user,
+        Err(_) => return Ok(None),
+    };
+    let mut zones = fetch_zones_cached(&user)?;
+    if let Some(account_id) = route_config.account_id.maybe_load() {
+        zones.retain(|zone| {
+            zone.account_id
+                .as_ref()
+                .map_or(true, |id| *id == account_id)
+        });
+    }
+
+    let matching = zones
+        .iter()
+        .filter(|zone| hosts.iter().any(|host| route_in_zone(host, &zone.name)))
+        .collect::<Vec<_>>();
+
+    match matching.as_slice() {
+        [single] => {
+            StdOut::info(&format!("Using zone {} for your routes", single.name));
+            Ok(Some(single.id.clone()))
+        }
+        _ if pick && interactive::is_interactive() && !zones.is_empty() => {
+            let candidates = if matching.is_empty() {
+                zones.iter().collect::<Vec<_>>()
+            } else {
+                matching
+            };
+            let options = candidates
+                .iter()
+                .map(|zone| format!("{} ({})", zone.name, zone.id))
+                .collect::<Vec<_>>();
+            let index = interactive::select("Which zone are your routes in?", &options)?;
+            let zone = candidates[index];
+            for host in &hosts {
+                project::set_zone_id(env, host, &zone.id)?;
+            }
+            StdOut::info(&format!(
+                "Remembered this zone in {}; set `zone_id` to use another one",
+                project::project_config_path().display()
+            ));
+            Ok(Some(zone.id.clone()))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Find the id of the zone named by the `zone_name` of a route.
+#[cfg_attr(test, allow(unreachable_code))]
+fn zone_id_by_name(zone_name: &str, account_id: &LazyAccountId) -> Result<String> {
+    #[cfg(test)]
+    // don't try to fetch the zones of the user, since there is none.
+    return Err(anyhow!("zone {} cannot be looked up in tests", zone_name));
+
+    let user = GlobalUser::new()?;
+    let account_id = account_id.maybe_load();
+    fetch_zones_cached(&user)?
+        .into_iter()
+        .find(|zone| {
+            zone.name.eq_ignore_ascii_case(zone_name)
+                && match (&zone.account_id, &account_id) {
+                    (Some(zone_account), Some(account_id)) => zone_account == account_id,
+                    _ => true,
+                }
+        })
+        .map(|zone| zone.id)
+        .ok_or_else(|| {
+            anyhow!(
+                "Could not find the zone {} in your account; check the `zone_name` of your routes, or set their `zone_id` instead",
+                zone_name
+            )
+        })
+}
+
+/// Tests if the host of a route pattern, e.g. `*.example.com/api/*`, belongs to a zone.
+fn route_in_zone(pattern: &str, zone_name: &str) -> bool {
+    let host = route_host(pattern);
+    host == zone_name || host.ends_with(&format!(".{}", zone_name))
+}
+
+/// The host of a route pattern without its wildcard, like `example.com` for
+/// `https://*.example.com/api/*`.
+fn route_host(pattern: &str) -> &str {
+    let pattern = pattern
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_start_matches('*')
+        .trim_start_matches('.');
+    pattern.split('/').next().unwrap_or_default()
+}
+
 impl FromStr for Manifest {
     type Err = toml::de::Error;
 
@@ -672,16 +1023,31 @@ impl FromStr for Manifest {
     }
 }
 
-fn read_config(config_path: &Path) -> Result<Config> {
-    let mut config = Config::new();
+fn check_for_unknown_keys(config_path: &Path) -> Result<()> {
+    let unknown = schema::unknown_keys(&read_value(config_path)?);
+    if !unknown.is_empty() {
+        let keys = unknown
+            .iter()
+            .map(|key| format!("- {}", key))
+            .collect::<Vec<_>>()
+            .join("\n");
+        anyhow::bail!(
+            "{} contains keys that wrangler does not know, and would otherwise ignore:\n{}",
+            config_path.display(),
+            keys
+        )
+    }
+    Ok(())
+}
 
-    let config_str = config_path
-        .to_str()
-        .expect("project config path should be a string");
-    config.merge(File::with_name(config_str))?;
+fn read_config(config_path: &Path) -> Result<Config> {
+    let mut config = load_config(config_path)?;
 
-    // Eg.. `CF_ACCOUNT_AUTH_KEY=farts` would set the `account_auth_key` key
-    config.merge(config::Environment::with_prefix("CF"))?;
+    // Eg.. `CF_ACCOUNT_ID=abc` sets the `account_id` key. Other `CF_` variables, like the
+    // credentials or the `CF_ACCOUNT_ID_<ENV>` overrides, are not keys of `wrangler.toml`.
+    config.merge(crate::settings::Environment::with_whitelist(
+        MANIFEST_ENV_VARS.to_vec(),
+    ))?;
 
     Ok(config)
 }
@@ -823,7 +1189,7 @@ mod tests {
             }]),
             site: Some(Default::default()),
             vars: Some(
-                vec![("FOO".to_string(), "some value".to_string())]
+                vec![("FOO".to_string(), "some value".into())]
                     .into_iter()
                     .collect(),
             ),
@@ -831,4 +1197,23 @@ mod tests {
         };
         assert!(toml::to_string(&manifest).is_ok());
     }
+
+    #[test]
+    fn route_in_zone() {
+        assert!(super::route_in_zone("example.com/*", "example.com"));
+        assert!(super::route_in_zone("*.example.com/api/*", "example.com"));
+        assert!(super::route_in_zone(
+            "https://api.example.com/*",
+            "example.com"
+        ));
+        assert!(!super::route_in_zone("notexample.com/*", "example.com"));
+        assert!(!super::route_in_zone(
+            "example.com.evil.net/*",
+            "example.com"
+        ));
+        assert_eq!(
+            super::route_host("https://*.example.com/api/*"),
+            "example.com"
+        );
+    }
 }
@@ -1,24 +1,43 @@
 mod builder;
+mod bundler;
 mod dev;
 mod durable_objects;
+pub mod env_mapping;
 mod environment;
+mod format;
 mod kv_namespace;
+mod logs;
 mod manifest;
 pub mod migrations;
+mod notifications;
 mod r2_bucket;
 mod route;
+pub mod schema;
+mod service;
 mod site;
+mod tail;
 pub(crate) mod target;
 mod target_type;
-mod triggers;
+pub mod triggers;
 
-pub use builder::{ModuleRule, UploadFormat};
+pub use builder::{
+    BuildHooks, Builder, BuilderOverride, CompatCheck, ModuleRule, RustBuild, Severity,
+    UploadFormat, WasmOpt, WASM_OPT_LEVELS,
+};
+pub use bundler::Bundler;
 pub use durable_objects::{DurableObjects, DurableObjectsClass};
+pub use format::{
+    find_config_file, local_config_path, read_value, ConfigFormat, CONFIG_FILE_NAMES,
+};
 pub use kv_namespace::{ConfigKvNamespace, KvNamespace};
-pub use manifest::Manifest;
+pub use logs::LogsConfig;
+pub use manifest::{set_account_id_override, set_strict_mode, Manifest};
+pub use notifications::{NotificationEvent, Notifications};
 pub use r2_bucket::{ConfigR2Bucket, R2Bucket};
-pub use route::{Route, RouteConfig};
-pub use site::Site;
+pub use route::{CustomRoute, Route, RouteConfig, RouteEntry, RouteZone};
+pub use service::ServiceBinding;
+pub use site::{ContentEncoding, HashAlgorithm, LargeFiles, NotFoundHandling, Site};
+pub use tail::TailConfig;
 pub use target::Target;
 pub use target_type::TargetType;
 
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+/// Where to tell about the publishes and secret changes of a worker, in `[notifications]`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Notifications {
+    /// The URL that a JSON payload is posted to for every event, such as a Slack webhook.
+    pub webhook: String,
+    /// The events to post, only publishes by default.
+    pub events: Option<Vec<NotificationEvent>>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationEvent {
+    Publish,
+    Secret,
+}
+
+impl Notifications {
+    pub fn wants(&self, event: NotificationEvent) -> bool {
+        match &self.events {
+            Some(events) => events.contains(&event),
+            None => event == NotificationEvent::Publish,
+        }
+    }
+}
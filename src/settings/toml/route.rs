@@ -21,11 +21,61 @@ impl From<&WorkersRoute> for Route {
     }
 }
 
+/// An entry of `routes`. A bare pattern is deployed to the zone of `zone_id`, while a table
+/// names its own zone, e.g. `{ pattern = "example.com/*", zone_name = "example.com" }`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum RouteEntry {
+    Pattern(String),
+    Custom(CustomRoute),
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomRoute {
+    pub pattern: String,
+    pub zone_id: Option<String>,
+    pub zone_name: Option<String>,
+}
+
+/// The zone a route entry is deployed to, when it is not the zone of `zone_id`.
+#[derive(Debug, PartialEq)]
+pub enum RouteZone<'a> {
+    Id(&'a str),
+    Name(&'a str),
+}
+
+impl RouteEntry {
+    pub fn pattern(&self) -> &str {
+        match self {
+            RouteEntry::Pattern(pattern) => pattern,
+            RouteEntry::Custom(route) => &route.pattern,
+        }
+    }
+
+    pub fn zone(&self) -> Option<RouteZone> {
+        match self {
+            RouteEntry::Pattern(_) => None,
+            RouteEntry::Custom(route) => match (&route.zone_id, &route.zone_name) {
+                (Some(id), _) => Some(RouteZone::Id(id)),
+                (None, Some(name)) => Some(RouteZone::Name(name)),
+                (None, None) => None,
+            },
+        }
+    }
+}
+
+impl From<&str> for RouteEntry {
+    fn from(pattern: &str) -> Self {
+        RouteEntry::Pattern(pattern.to_string())
+    }
+}
+
 #[derive(Debug)]
 pub struct RouteConfig {
     pub workers_dev: Option<bool>,
     pub route: Option<String>,
-    pub routes: Option<Vec<String>>,
+    pub routes: Option<Vec<RouteEntry>>,
     pub zone_id: Option<String>,
     pub account_id: LazyAccountId,
 }
@@ -41,11 +91,43 @@ impl RouteConfig {
         }
     }
 
+    /// The patterns of `route` and of the `routes` without a zone of their own, which are all
+    /// deployed to the zone of `zone_id`.
+    pub fn shared_zone_patterns(&self) -> Vec<&str> {
+        self.route
+            .iter()
+            .map(String::as_str)
+            .chain(
+                self.routes
+                    .iter()
+                    .flatten()
+                    .filter(|route| route.zone().is_none())
+                    .map(RouteEntry::pattern),
+            )
+            .collect()
+    }
+
+    /// Tests if some routes are deployed to the zone of `zone_id`.
+    pub fn has_shared_zone_routes(&self) -> bool {
+        self.route.is_some()
+            || self
+                .routes
+                .iter()
+                .flatten()
+                .any(|route| route.zone().is_none())
+    }
+
     pub fn is_zoneless(&self) -> bool {
         self.workers_dev.unwrap_or_default()
     }
 
     pub fn is_zoned(&self) -> bool {
-        self.has_routes_defined() && self.zone_id.is_some()
+        self.has_routes_defined()
+            && (self.zone_id.is_some()
+                || self
+                    .routes
+                    .iter()
+                    .flatten()
+                    .any(|route| route.zone().is_some()))
     }
 }
@@ -0,0 +1,661 @@
+use std::cmp;
+
+use serde_json::{json, Map, Value};
+
+use crate::settings::toml::WASM_OPT_LEVELS;
+use crate::upload::form::ModuleType;
+
+/// Keys that can be set at the top level of a configuration file.
+pub const MANIFEST_KEYS: &[&str] = &[
+    "extends",
+    "name",
+    "type",
+    "main",
+    "entries",
+    "account_id",
+    "workers_dev",
+    "route",
+    "routes",
+    "zone_id",
+    "webpack_config",
+    "node_compat",
+    "bundler",
+    "minify",
+    "build",
+    "private",
+    "dev",
+    "tail",
+    "notifications",
+    "logs",
+    "triggers",
+    "migrations",
+    "usage_model",
+    "compatibility_date",
+    "compatibility_flags",
+    "durable_objects",
+    "env",
+    "kv_namespaces",
+    "kv-namespaces",
+    "r2_buckets",
+    "services",
+    "site",
+    "vars",
+    "text_blobs",
+    "wasm_modules",
+    "strict",
+    "alias",
+    "env_mapping",
+];
+
+/// Keys that can be set in an `[env.<name>]` table.
+pub const ENVIRONMENT_KEYS: &[&str] = &[
+    "name",
+    "account_id",
+    "workers_dev",
+    "route",
+    "routes",
+    "zone_id",
+    "webpack_config",
+    "node_compat",
+    "bundler",
+    "minify",
+    "build",
+    "private",
+    "site",
+    "kv_namespaces",
+    "kv-namespaces",
+    "services",
+    "vars",
+    "text_blobs",
+    "triggers",
+    "durable_objects",
+];
+
+const KV_NAMESPACE_KEYS: &[&str] = &["binding", "id", "preview_id"];
+const R2_BUCKET_KEYS: &[&str] = &["binding", "bucket_name", "preview_bucket_name"];
+const SERVICE_KEYS: &[&str] = &["binding", "service", "environment"];
+const DURABLE_OBJECTS_KEYS: &[&str] = &["classes", "bindings"];
+const DURABLE_OBJECTS_CLASS_KEYS: &[&str] = &["binding", "name", "class_name", "script_name"];
+const TRIGGERS_KEYS: &[&str] = &["crons"];
+const ROUTE_KEYS: &[&str] = &["pattern", "zone_id", "zone_name"];
+const MIGRATION_KEYS: &[&str] = &[
+    "tag",
+    "new_classes",
+    "deleted_classes",
+    "renamed_classes",
+    "transferred_classes",
+];
+const SITE_KEYS: &[&str] = &[
+    "bucket",
+    "entry-point",
+    "include",
+    "exclude",
+    "upload_concurrency",
+    "headers",
+    "not_found_handling",
+    "spa",
+    "precompress",
+    "large_files",
+    "large_files_bucket",
+    "hash_algorithm",
+    "hash_length",
+    "hash_html",
+    "asset_map",
+];
+const DEV_KEYS: &[&str] = &[
+    "ip",
+    "port",
+    "local_protocol",
+    "upstream_protocol",
+    "kv_propagation_delay",
+    "simulate_limits",
+    "subrequest_limit",
+    "cpu_limit",
+];
+const TAIL_KEYS: &[&str] = &[
+    "format_template",
+    "show_headers",
+    "truncate",
+    "colorize_outcome",
+    "group",
+];
+const NOTIFICATIONS_KEYS: &[&str] = &["webhook", "events"];
+const LOGS_KEYS: &[&str] = &["logpush_bucket", "logpush_prefix"];
+const BUILD_KEYS: &[&str] = &[
+    "command",
+    "cwd",
+    "watch_dir",
+    "upload",
+    "wasm",
+    "rust",
+    "define",
+    "hooks",
+    "compat",
+];
+const UPLOAD_KEYS: &[&str] = &["format", "main", "dir", "rules"];
+const MODULE_RULE_KEYS: &[&str] = &["type", "globs", "fallthrough"];
+const WASM_KEYS: &[&str] = &["opt_level", "wasi", "module"];
+const RUST_BUILD_KEYS: &[&str] = &["package"];
+const HOOKS_KEYS: &[&str] = &["pre", "post"];
+const COMPAT_KEYS: &[&str] = &["severity", "allow"];
+
+/// A key of a configuration file that wrangler does not know, and would silently ignore.
+#[derive(Debug, PartialEq)]
+pub struct UnknownKey {
+    /// The dotted path of the key, e.g. `env.production.kv-namespace`.
+    pub path: String,
+    /// The known key it was probably meant to be.
+    pub suggestion: Option<&'static str>,
+}
+
+impl std::fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "unknown key `{}`", self.path)?;
+        if let Some(suggestion) = self.suggestion {
+            write!(f, ", did you mean `{}`?", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists the keys of a parsed configuration file that do not match the schema. Tables whose
+/// keys are checked when they are deserialized (e.g. `[build]`) are skipped.
+pub fn unknown_keys(config: &Value) -> Vec<UnknownKey> {
+    let mut unknown = Vec::new();
+    check_table(config, "", MANIFEST_KEYS, &mut unknown);
+    if let Some(Value::Object(envs)) = config.get("env") {
+        for (name, env) in envs {
+            check_table(
+                env,
+                &format!("env.{}.", name),
+                ENVIRONMENT_KEYS,
+                &mut unknown,
+            );
+        }
+    }
+    unknown.sort_by(|a, b| a.path.cmp(&b.path));
+    unknown
+}
+
+fn check_table(table: &Value, prefix: &str, known: &[&'static str], unknown: &mut Vec<UnknownKey>) {
+    let table = match table {
+        Value::Object(table) => table,
+        _ => return,
+    };
+    for (key, value) in table {
+        if !known.contains(&key.as_str()) {
+            unknown.push(UnknownKey {
+                path: format!("{}{}", prefix, key),
+                suggestion: suggest(key, known),
+            });
+            continue;
+        }
+        let nested: &[&'static str] = match key.as_str() {
+            "kv_namespaces" | "kv-namespaces" => KV_NAMESPACE_KEYS,
+            "r2_buckets" => R2_BUCKET_KEYS,
+            "services" => SERVICE_KEYS,
+            "routes" => ROUTE_KEYS,
+            "triggers" => TRIGGERS_KEYS,
+            "durable_objects" => {
+                for field in DURABLE_OBJECTS_KEYS {
+                    if let Some(Value::Array(classes)) = value.get(field) {
+                        for (i, class) in classes.iter().enumerate() {
+                            let prefix = format!("{}{}.{}[{}].", prefix, key, field, i);
+                            check_table(class, &prefix, DURABLE_OBJECTS_CLASS_KEYS, unknown);
+                        }
+                    }
+                }
+                DURABLE_OBJECTS_KEYS
+            }
+            _ => continue,
+        };
+        match value {
+            Value::Array(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    check_table(item, &format!("{}{}[{}].", prefix, key, i), nested, unknown);
+                }
+            }
+            _ => check_table(value, &format!("{}{}.", prefix, key), nested, unknown),
+        }
+    }
+}
+
+/// Suggests the known key closest to a misspelled one, if any is close enough.
+pub fn suggest(key: &str, known: &[&'static str]) -> Option<&'static str> {
+    let normalized = key.to_lowercase().replace('-', "_");
+    known
+        .iter()
+        .map(|candidate| (*candidate, edit_distance(&normalized, candidate)))
+        .filter(|(candidate, distance)| *distance <= cmp::max(2, candidate.len() / 4))
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(candidate, _)| candidate)
+}
+
+/// A JSON Schema of configuration files, for editors to complete and validate them. It is built
+/// from the same key lists as [`unknown_keys`], so both always agree on what is valid.
+pub fn json_schema() -> Value {
+    let mut schema = object_schema(MANIFEST_KEYS);
+    schema["$schema"] = json!("http://json-schema.org/draft-07/schema#");
+    schema["title"] = json!("wrangler configuration");
+    schema["required"] = json!(["name"]);
+    schema
+}
+
+fn object_schema(keys: &[&str]) -> Value {
+    let properties = keys
+        .iter()
+        .map(|key| {
+            let schema = property_schema(key).unwrap_or_else(|| json!({}));
+            (key.to_string(), schema)
+        })
+        .collect::<Map<String, Value>>();
+    json!({
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": false,
+    })
+}
+
+fn array_of(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+fn map_of(values: Value) -> Value {
+    json!({ "type": "object", "additionalProperties": values })
+}
+
+/// The schema of the value of a known key. Keys are unique enough across the tables of a
+/// configuration file that the nested tables share this lookup.
+fn property_schema(key: &str) -> Option<Value> {
+    let string = json!({ "type": "string" });
+    let boolean = json!({ "type": "boolean" });
+    let strings = array_of(string.clone());
+
+    let schema = match key {
+        "extends"
+        | "name"
+        | "account_id"
+        | "route"
+        | "zone_id"
+        | "webpack_config"
+        | "id"
+        | "preview_id"
+        | "binding"
+        | "bucket_name"
+        | "preview_bucket_name"
+        | "service"
+        | "environment"
+        | "class_name"
+        | "script_name"
+        | "command"
+        | "cwd"
+        | "watch_dir"
+        | "main"
+        | "dir"
+        | "bucket"
+        | "entry-point"
+        | "tag"
+        | "from"
+        | "to"
+        | "from_script"
+        | "format_template"
+        | "pattern"
+        | "large_files_bucket"
+        | "asset_map"
+        | "module"
+        | "zone_name"
+        | "webhook"
+        | "logpush_bucket"
+        | "logpush_prefix" => string,
+        "workers_dev" | "private" | "strict" | "node_compat" | "minify" | "fallthrough"
+        | "show_headers" | "colorize_outcome" | "group" | "spa" | "hash_html" | "wasi"
+        | "simulate_limits" => boolean,
+        "compatibility_flags"
+        | "crons"
+        | "globs"
+        | "include"
+        | "exclude"
+        | "new_classes"
+        | "deleted_classes" => strings,
+        "routes" => {
+            let mut route = object_schema(ROUTE_KEYS);
+            route["required"] = json!(["pattern"]);
+            array_of(json!({ "oneOf": [string, route] }))
+        }
+        "type" => json!({ "enum": ["javascript", "rust", "webpack"] }),
+        "bundler" => json!({ "enum": ["webpack", "builtin"] }),
+        "usage_model" => json!({ "enum": ["bundled", "unbound"] }),
+        "compatibility_date" => json!({
+            "type": "string",
+            "pattern": "^\\d{4}-\\d{2}-\\d{2}$",
+        }),
+        "local_protocol" | "upstream_protocol" => json!({ "enum": ["http", "https"] }),
+        "port" | "truncate" | "kv_propagation_delay" | "cpu_limit" => {
+            json!({ "type": "integer", "minimum": 0 })
+        }
+        "upload_concurrency" | "subrequest_limit" => json!({ "type": "integer", "minimum": 1 }),
+        "ip" => json!({
+            "type": "string",
+            "anyOf": [{ "format": "ipv4" }, { "format": "ipv6" }],
+        }),
+        "env" => map_of(object_schema(ENVIRONMENT_KEYS)),
+        "kv_namespaces" | "kv-namespaces" => array_of(object_schema(KV_NAMESPACE_KEYS)),
+        "r2_buckets" => array_of(object_schema(R2_BUCKET_KEYS)),
+        "services" => {
+            let mut service = object_schema(SERVICE_KEYS);
+            service["required"] = json!(["binding", "service"]);
+            array_of(service)
+        }
+        "triggers" => object_schema(TRIGGERS_KEYS),
+        "durable_objects" => object_schema(DURABLE_OBJECTS_KEYS),
+        "classes" | "bindings" => array_of(object_schema(DURABLE_OBJECTS_CLASS_KEYS)),
+        "migrations" => array_of(object_schema(MIGRATION_KEYS)),
+        "renamed_classes" => array_of(object_schema(&["from", "to"])),
+        "transferred_classes" => array_of(object_schema(&["from", "from_script", "to"])),
+        "site" => object_schema(SITE_KEYS),
+        "dev" => object_schema(DEV_KEYS),
+        "tail" => object_schema(TAIL_KEYS),
+        "notifications" => {
+            let mut notifications = object_schema(NOTIFICATIONS_KEYS);
+            notifications["required"] = json!(["webhook"]);
+            notifications
+        }
+        "events" => array_of(json!({ "enum": ["publish", "secret"] })),
+        "logs" => {
+            let mut logs = object_schema(LOGS_KEYS);
+            logs["required"] = json!(["logpush_bucket"]);
+            logs
+        }
+        "build" => object_schema(BUILD_KEYS),
+        "upload" => {
+            let mut upload = object_schema(UPLOAD_KEYS);
+            upload["required"] = json!(["format"]);
+            upload
+        }
+        "format" => json!({ "enum": ["service-worker", "modules"] }),
+        "wasm" => object_schema(WASM_KEYS),
+        "opt_level" => json!({ "enum": WASM_OPT_LEVELS }),
+        "rust" => {
+            let mut rust = object_schema(RUST_BUILD_KEYS);
+            rust["required"] = json!(["package"]);
+            rust
+        }
+        "package" => string,
+        "hooks" => object_schema(HOOKS_KEYS),
+        "pre" | "post" => strings,
+        "compat" => object_schema(COMPAT_KEYS),
+        "severity" => json!({ "enum": ["off", "warn", "error"] }),
+        "allow" => strings,
+        "define" => json!({
+            "type": "object",
+            "additionalProperties": { "type": ["string", "number", "boolean"] },
+        }),
+        "rules" => {
+            let mut rule = object_schema(MODULE_RULE_KEYS);
+            let module_types = ModuleType::iter().map(ModuleType::name).collect::<Vec<_>>();
+            rule["properties"]["type"] = json!({ "enum": module_types });
+            rule["required"] = json!(["type", "globs"]);
+            array_of(rule)
+        }
+        "headers" => map_of(map_of(string)),
+        "not_found_handling" => json!({ "enum": ["single-page-app", "404-page"] }),
+        "precompress" => array_of(json!({ "enum": ["br", "gzip"] })),
+        "large_files" => json!({ "enum": ["chunk", "r2"] }),
+        "hash_algorithm" => json!({ "enum": ["xxhash64", "sha256"] }),
+        "hash_length" => json!({ "type": "integer", "minimum": 1, "maximum": 64 }),
+        "vars" => map_of(json!({})),
+        "entries" | "text_blobs" | "wasm_modules" | "env_mapping" => map_of(string),
+        "alias" => map_of(json!({
+            "type": ["string", "array"],
+            "items": { "type": "string" },
+        })),
+        _ => return None,
+    };
+    Some(schema)
+}
+
+/// The Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + if ca == *cb { 0 } else { 1 };
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::{self, DeserializeOwned, Visitor};
+    use serde::Deserializer;
+    use serde_json::json;
+
+    use crate::settings::toml::dev::Dev;
+    use crate::settings::toml::environment::Environment;
+    use crate::settings::toml::migrations::DurableObjectsMigration;
+    use crate::settings::toml::triggers::Triggers;
+    use crate::settings::toml::{
+        BuildHooks, Builder, BuilderOverride, CompatCheck, ConfigKvNamespace, ConfigR2Bucket,
+        CustomRoute, DurableObjects, DurableObjectsClass, LogsConfig, Manifest, ModuleRule,
+        Notifications, RustBuild, ServiceBinding, Site, TailConfig, WasmOpt,
+    };
+
+    /// Records the fields that serde asks for when deserializing a struct, and stops there.
+    struct FieldNames(Vec<&'static str>);
+
+    impl<'de> Deserializer<'de> for &mut FieldNames {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            self.0.extend_from_slice(fields);
+            Err(de::Error::custom("recorded the fields"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf option
+            unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    fn fields<T: DeserializeOwned>() -> Vec<&'static str> {
+        let mut names = FieldNames(Vec::new());
+        let _ = T::deserialize(&mut names);
+        assert!(!names.0.is_empty(), "no fields recorded");
+        names.0
+    }
+
+    #[test]
+    fn it_suggests_close_keys() {
+        assert_eq!(
+            suggest("kv_namespace", MANIFEST_KEYS),
+            Some("kv_namespaces")
+        );
+        assert_eq!(suggest("Workers-Dev", MANIFEST_KEYS), Some("workers_dev"));
+        assert_eq!(
+            suggest("compatability_date", MANIFEST_KEYS),
+            Some("compatibility_date")
+        );
+        assert_eq!(suggest("something_else", MANIFEST_KEYS), None);
+    }
+
+    #[test]
+    fn it_describes_every_key_in_the_json_schema() {
+        let tables = [
+            MANIFEST_KEYS,
+            ENVIRONMENT_KEYS,
+            KV_NAMESPACE_KEYS,
+            R2_BUCKET_KEYS,
+            SERVICE_KEYS,
+            DURABLE_OBJECTS_KEYS,
+            DURABLE_OBJECTS_CLASS_KEYS,
+            TRIGGERS_KEYS,
+            ROUTE_KEYS,
+            MIGRATION_KEYS,
+            SITE_KEYS,
+            DEV_KEYS,
+            TAIL_KEYS,
+            NOTIFICATIONS_KEYS,
+            LOGS_KEYS,
+            BUILD_KEYS,
+            UPLOAD_KEYS,
+            MODULE_RULE_KEYS,
+            WASM_KEYS,
+            RUST_BUILD_KEYS,
+            HOOKS_KEYS,
+            COMPAT_KEYS,
+        ];
+        for key in tables.iter().flat_map(|keys| keys.iter()) {
+            assert!(property_schema(key).is_some(), "no schema for `{}`", key);
+        }
+
+        let schema = json_schema();
+        assert_eq!(schema["properties"]["workers_dev"]["type"], "boolean");
+        let env = &schema["properties"]["env"]["additionalProperties"];
+        assert_eq!(
+            env["properties"]["kv_namespaces"]["items"]["properties"]["preview_id"]["type"],
+            "string"
+        );
+        assert!(env["properties"].get("migrations").is_none());
+    }
+
+    #[test]
+    fn it_lists_the_keys_of_the_configuration_structs() {
+        // Each key list with the struct it describes, and the keys it has that are not fields:
+        // the aliases of fields and the keys read before deserializing. `[build.upload]` is an
+        // internally tagged enum, whose fields serde can't tell.
+        let mut migration_fields = fields::<DurableObjectsMigration>();
+        migration_fields.push("tag");
+        let tables: Vec<(&str, &[&str], Vec<&str>, &[&str])> = vec![
+            (
+                "MANIFEST_KEYS",
+                MANIFEST_KEYS,
+                fields::<Manifest>(),
+                &["extends", "alias", "env_mapping", "kv-namespaces"],
+            ),
+            (
+                "ENVIRONMENT_KEYS",
+                ENVIRONMENT_KEYS,
+                fields::<Environment>(),
+                &["kv-namespaces"],
+            ),
+            (
+                "KV_NAMESPACE_KEYS",
+                KV_NAMESPACE_KEYS,
+                fields::<ConfigKvNamespace>(),
+                &[],
+            ),
+            (
+                "R2_BUCKET_KEYS",
+                R2_BUCKET_KEYS,
+                fields::<ConfigR2Bucket>(),
+                &[],
+            ),
+            (
+                "SERVICE_KEYS",
+                SERVICE_KEYS,
+                fields::<ServiceBinding>(),
+                &[],
+            ),
+            (
+                "DURABLE_OBJECTS_KEYS",
+                DURABLE_OBJECTS_KEYS,
+                fields::<DurableObjects>(),
+                &["bindings"],
+            ),
+            (
+                "DURABLE_OBJECTS_CLASS_KEYS",
+                DURABLE_OBJECTS_CLASS_KEYS,
+                fields::<DurableObjectsClass>(),
+                &["name"],
+            ),
+            ("TRIGGERS_KEYS", TRIGGERS_KEYS, fields::<Triggers>(), &[]),
+            ("ROUTE_KEYS", ROUTE_KEYS, fields::<CustomRoute>(), &[]),
+            ("MIGRATION_KEYS", MIGRATION_KEYS, migration_fields, &[]),
+            ("SITE_KEYS", SITE_KEYS, fields::<Site>(), &[]),
+            ("DEV_KEYS", DEV_KEYS, fields::<Dev>(), &[]),
+            ("TAIL_KEYS", TAIL_KEYS, fields::<TailConfig>(), &[]),
+            (
+                "NOTIFICATIONS_KEYS",
+                NOTIFICATIONS_KEYS,
+                fields::<Notifications>(),
+                &[],
+            ),
+            ("LOGS_KEYS", LOGS_KEYS, fields::<LogsConfig>(), &[]),
+            ("BUILD_KEYS", BUILD_KEYS, fields::<Builder>(), &[]),
+            ("BUILD_KEYS", BUILD_KEYS, fields::<BuilderOverride>(), &[]),
+            (
+                "MODULE_RULE_KEYS",
+                MODULE_RULE_KEYS,
+                fields::<ModuleRule>(),
+                &[],
+            ),
+            ("WASM_KEYS", WASM_KEYS, fields::<WasmOpt>(), &[]),
+            (
+                "RUST_BUILD_KEYS",
+                RUST_BUILD_KEYS,
+                fields::<RustBuild>(),
+                &[],
+            ),
+            ("HOOKS_KEYS", HOOKS_KEYS, fields::<BuildHooks>(), &[]),
+            ("COMPAT_KEYS", COMPAT_KEYS, fields::<CompatCheck>(), &[]),
+        ];
+        for (name, keys, fields, extra) in tables {
+            for field in &fields {
+                assert!(keys.contains(field), "`{}` is missing from {}", field, name);
+            }
+            for key in keys {
+                assert!(
+                    fields.contains(key) || extra.contains(key),
+                    "`{}` of {} is not a field",
+                    key,
+                    name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn it_finds_unknown_keys_in_environments_and_bindings() {
+        let config = json!({
+            "name": "worker",
+            "rout": "example.com/*",
+            "kv_namespaces": [{ "binding": "KV", "id": "123", "preview": "456" }],
+            "env": {
+                "production": { "name": "worker-production", "kv-namespace": [] }
+            }
+        });
+        assert_eq!(
+            unknown_keys(&config),
+            vec![
+                UnknownKey {
+                    path: "env.production.kv-namespace".to_string(),
+                    suggestion: Some("kv_namespaces"),
+                },
+                UnknownKey {
+                    path: "kv_namespaces[0].preview".to_string(),
+                    suggestion: None,
+                },
+                UnknownKey {
+                    path: "rout".to_string(),
+                    suggestion: Some("route"),
+                },
+            ]
+        );
+    }
+}
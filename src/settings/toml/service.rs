@@ -0,0 +1,37 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::settings::binding::Binding;
+
+/// A binding to another Worker, whose `fetch` the Worker can call without going through the
+/// internet, e.g. `{ binding = "AUTH", service = "auth", environment = "production" }`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServiceBinding {
+    pub binding: String,
+    /// The name of the Worker.
+    pub service: String,
+    /// The environment of the Worker, its production one when `None`.
+    pub environment: Option<String>,
+}
+
+impl fmt::Display for ServiceBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "binding: {}, service: {}", self.binding, self.service)?;
+        if let Some(environment) = &self.environment {
+            write!(f, ", environment: {}", environment)?;
+        }
+        Ok(())
+    }
+}
+
+impl ServiceBinding {
+    pub fn binding(&self) -> Binding {
+        Binding::new_service(
+            self.binding.clone(),
+            self.service.clone(),
+            self.environment.clone(),
+        )
+    }
+}
@@ -1,11 +1,12 @@
+use std::collections::BTreeMap;
 use std::env;
-use std::fs;
+use std::fmt;
 use std::path::PathBuf;
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-use crate::commands::generate::run_generate;
+use crate::sites::worker;
 
 const SITE_ENTRY_POINT: &str = "workers-site";
 
@@ -17,6 +18,106 @@ pub struct Site {
     pub entry_point: Option<PathBuf>,
     pub include: Option<Vec<String>>,
     pub exclude: Option<Vec<String>>,
+    /// How many batches of assets are uploaded at once.
+    pub upload_concurrency: Option<usize>,
+    /// The headers of the responses for the paths that match a pattern, like `/assets/*`.
+    pub headers: Option<BTreeMap<String, BTreeMap<String, String>>>,
+    /// What is served for the paths that have no asset.
+    pub not_found_handling: Option<NotFoundHandling>,
+    /// A shorthand for `not_found_handling = "single-page-app"`.
+    pub spa: Option<bool>,
+    /// The encodings of the variants of the assets that are compressed when they are uploaded,
+    /// from the most preferred.
+    pub precompress: Option<Vec<ContentEncoding>>,
+    /// Where the assets too large for a value of Workers KV are stored.
+    pub large_files: Option<LargeFiles>,
+    /// The binding of the R2 bucket of `large_files = "r2"`.
+    pub large_files_bucket: Option<String>,
+    /// The hash of the content of an asset in its key.
+    pub hash_algorithm: Option<HashAlgorithm>,
+    /// How many hexadecimal digits of the hash are in the key of an asset.
+    pub hash_length: Option<usize>,
+    /// Whether the keys of HTML pages have a hash, which they need to be cached by the worker.
+    pub hash_html: Option<bool>,
+    /// Where `wrangler publish` writes the keys of the assets by their path, as JSON.
+    pub asset_map: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ContentEncoding {
+    #[serde(rename = "br")]
+    Brotli,
+    #[serde(rename = "gzip")]
+    Gzip,
+}
+
+impl ContentEncoding {
+    /// The extension of the compressed variants of assets.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ContentEncoding::Brotli => "br",
+            ContentEncoding::Gzip => "gz",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum HashAlgorithm {
+    /// The hash of older versions of wrangler, whose keys don't change.
+    #[serde(rename = "xxhash64")]
+    XxHash64,
+    #[serde(rename = "sha256")]
+    Sha256,
+}
+
+impl HashAlgorithm {
+    /// How many hexadecimal digits a hash has at most.
+    pub fn max_length(self) -> usize {
+        match self {
+            HashAlgorithm::XxHash64 => 16,
+            HashAlgorithm::Sha256 => 64,
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HashAlgorithm::XxHash64 => write!(f, "xxhash64"),
+            HashAlgorithm::Sha256 => write!(f, "sha256"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum LargeFiles {
+    /// Splits them into values of Workers KV, which the worker of the site joins.
+    #[serde(rename = "chunk")]
+    Chunk,
+    /// Uploads them to the R2 bucket of `large_files_bucket`, with the access key of R2 in
+    /// `R2_ACCESS_KEY_ID` and `R2_SECRET_ACCESS_KEY`.
+    #[serde(rename = "r2")]
+    R2,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum NotFoundHandling {
+    /// Serves `index.html` to the pages that have no asset, whose routes the app handles.
+    #[serde(rename = "single-page-app")]
+    SinglePageApp,
+    /// Serves `404.html` with the status 404.
+    #[serde(rename = "404-page")]
+    NotFoundPage,
+}
+
+impl NotFoundHandling {
+    /// The asset served instead of those that don't exist.
+    pub fn page(self) -> &'static str {
+        match self {
+            NotFoundHandling::SinglePageApp => "index.html",
+            NotFoundHandling::NotFoundPage => "404.html",
+        }
+    }
 }
 
 impl Site {
@@ -27,6 +128,18 @@ impl Site {
         }
     }
 
+    /// What is served for the paths that have no asset, from `not_found_handling` or `spa`.
+    pub fn not_found_handling(&self) -> Result<Option<NotFoundHandling>> {
+        match (self.not_found_handling, self.spa) {
+            (Some(handling), Some(true)) if handling != NotFoundHandling::SinglePageApp => {
+                anyhow::bail!("`spa = true` conflicts with the not_found_handling of [site]")
+            }
+            (Some(handling), _) => Ok(Some(handling)),
+            (None, Some(true)) => Ok(Some(NotFoundHandling::SinglePageApp)),
+            (None, _) => Ok(None),
+        }
+    }
+
     // if the user has configured `site.entry-point`, use that
     // as the build directory. Otherwise use the default const
     // SITE_ENTRY_POINT
@@ -41,15 +154,8 @@ impl Site {
 
     pub fn scaffold_worker(&self) -> Result<()> {
         let entry_point = &self.entry_point()?;
-        let template = "https://github.com/cloudflare/worker-sites-init";
-
         if !entry_point.exists() {
-            log::info!("Generating a new workers site project");
-            run_generate(entry_point.file_name().unwrap().to_str().unwrap(), template)?;
-
-            // This step is to prevent having a git repo within a git repo after
-            // generating the scaffold into an existing project.
-            fs::remove_dir_all(&entry_point.join(".git"))?;
+            worker::scaffold(entry_point)?;
         }
 
         Ok(())
@@ -63,6 +169,17 @@ impl Default for Site {
             entry_point: Some(PathBuf::from(SITE_ENTRY_POINT)),
             include: None,
             exclude: None,
+            upload_concurrency: None,
+            headers: None,
+            not_found_handling: None,
+            spa: None,
+            precompress: None,
+            large_files: None,
+            large_files_bucket: None,
+            hash_algorithm: None,
+            hash_length: None,
+            hash_html: None,
+            asset_map: None,
         }
     }
 }
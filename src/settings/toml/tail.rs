@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+/// Defaults for `wrangler tail`, overridden by command line flags.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TailConfig {
+    pub format_template: Option<String>,
+    pub show_headers: Option<bool>,
+    pub truncate: Option<usize>,
+    pub colorize_outcome: Option<bool>,
+    pub group: Option<bool>,
+}
@@ -1,30 +1,44 @@
+use super::bundler::Bundler;
 use super::durable_objects::DurableObjects;
 use super::kv_namespace::KvNamespace;
 pub(crate) use super::manifest::LazyAccountId;
 use super::r2_bucket::R2Bucket;
+use super::service::ServiceBinding;
 use super::site::Site;
 use super::target_type::TargetType;
 use super::UsageModel;
-use super::{builder::Builder, migrations::Migrations};
+use super::{
+    builder::{Builder, UploadFormat},
+    migrations::Migrations,
+};
+use crate::upload::package::Package;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 
 use std::path::PathBuf;
 
+use anyhow::Result;
+
 #[derive(Clone, Debug, Default)]
 pub struct Target {
     pub account_id: LazyAccountId,
     pub kv_namespaces: Vec<KvNamespace>,
     pub r2_buckets: Vec<R2Bucket>,
+    pub services: Vec<ServiceBinding>,
     pub durable_objects: Option<DurableObjects>,
     pub migrations: Option<Migrations>,
     pub name: String,
     pub target_type: TargetType,
+    pub main: Option<PathBuf>,
+    pub entries: Option<BTreeMap<String, PathBuf>>,
     pub webpack_config: Option<String>,
+    pub node_compat: bool,
+    pub bundler: Bundler,
+    pub minify: bool,
     pub build: Option<Builder>,
     pub site: Option<Site>,
-    pub vars: Option<HashMap<String, String>>,
+    pub vars: Option<HashMap<String, serde_json::Value>>,
     pub text_blobs: Option<HashMap<String, PathBuf>>,
     pub usage_model: Option<UsageModel>,
     pub wasm_modules: Option<HashMap<String, PathBuf>>,
@@ -37,6 +51,88 @@ impl Target {
         self.kv_namespaces.push(kv_namespace);
     }
 
+    /// The script of the Worker: `main` when it is set, otherwise the `main` of the package.json
+    /// of the project. The script is the entry of the webpack build for webpack projects, and
+    /// the uploaded script for javascript projects and projects built by their own command.
+    pub fn entry_point(&self) -> Result<PathBuf> {
+        match &self.main {
+            Some(main) => {
+                anyhow::ensure!(
+                    main.exists(),
+                    "The entry point of your Worker ({}) set in `main` could not be found.",
+                    main.display()
+                );
+                Ok(main.clone())
+            }
+            None => {
+                let package_dir = self.package_dir()?;
+                let package = Package::new(&package_dir)?;
+                Ok(package_dir.join(package.main(&package_dir)?))
+            }
+        }
+    }
+
+    /// The `[build]` of a target built by its own `command`, which replaces the build of its
+    /// type, e.g. wasm-pack or webpack. Its artifacts are uploaded as `[build.upload]` says.
+    pub fn custom_build(&self) -> Option<&Builder> {
+        self.build.as_ref().filter(|build| build.command.is_some())
+    }
+
+    /// Rejects `main` where another setting decides the entry point.
+    pub(super) fn validate_main(&self) -> Result<()> {
+        if self.main.is_none() {
+            return Ok(());
+        }
+        let uploads_modules = matches!(
+            &self.build,
+            Some(Builder {
+                upload: UploadFormat::Modules { .. },
+                ..
+            })
+        );
+        match (&self.target_type, self.custom_build()) {
+            (TargetType::Rust, None) => {
+                anyhow::bail!("`main` cannot be used in Rust projects, whose entry point is built by wasm-pack")
+            }
+            (TargetType::JavaScript, _) | (_, Some(_)) if uploads_modules => anyhow::bail!(
+                "`main` cannot be used with the modules format; set the main module with `main` in the [build.upload] section instead"
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Rejects `entries` where the builtin bundler does not bundle the Worker.
+    pub(super) fn validate_entries(&self) -> Result<()> {
+        let entries = match &self.entries {
+            Some(entries) => entries,
+            None => return Ok(()),
+        };
+        anyhow::ensure!(
+            self.target_type == TargetType::Webpack && self.bundler == Bundler::Builtin,
+            "`entries` can only be used in webpack projects with `bundler = \"builtin\"`"
+        );
+        anyhow::ensure!(
+            self.main.is_none(),
+            "`main` cannot be used with `entries`; set the entry point of the fetch handler with `entries.fetch` instead"
+        );
+        anyhow::ensure!(!entries.is_empty(), "`entries` needs at least one handler");
+        for handler in entries.keys() {
+            let valid = handler
+                .chars()
+                .next()
+                .map_or(false, |c| !c.is_ascii_digit())
+                && handler
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_');
+            anyhow::ensure!(
+                valid,
+                "`{}` in `entries` is not the name of a handler, like `fetch` or `scheduled`",
+                handler
+            );
+        }
+        Ok(())
+    }
+
     pub fn package_dir(&self) -> Result<PathBuf, std::io::Error> {
         // if `site` is configured, we want to isolate worker code
         // and build artifacts away from static site application code.
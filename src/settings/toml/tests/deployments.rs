@@ -541,6 +541,45 @@ fn it_gets_deployments_with_routes_and_workers_dev_true() {
     }
 }
 
+#[test]
+fn it_gets_deployments_per_route_zone() {
+    let script_name = "routes_with_zones";
+    let toml_string = format!(
+        r#"
+name = "{}"
+type = "webpack"
+zone_id = "{}"
+routes = [
+    {{ pattern = "{}" }},
+    {{ pattern = "other.tld/*", zone_id = "otherzoneid" }},
+    {{ pattern = "blog.other.tld/*", zone_id = "otherzoneid" }},
+]
+"#,
+        script_name, ZONE_ID, PATTERN
+    );
+    let manifest = Manifest::from_str(&toml_string).unwrap();
+
+    let new_route = |pattern: &str| Route {
+        id: None,
+        script: Some(script_name.to_string()),
+        pattern: pattern.to_string(),
+    };
+    let expected_deployments = vec![
+        DeployTarget::Zoned(ZonedTarget {
+            zone_id: ZONE_ID.to_string(),
+            routes: vec![new_route(PATTERN)],
+        }),
+        DeployTarget::Zoned(ZonedTarget {
+            zone_id: "otherzoneid".to_string(),
+            routes: vec![new_route("other.tld/*"), new_route("blog.other.tld/*")],
+        }),
+    ];
+
+    let actual_deployments = manifest.get_deployments(None).unwrap();
+
+    assert_eq!(actual_deployments, expected_deployments);
+}
+
 // ENVIRONMENT TESTS
 // Top level empty
 #[test]
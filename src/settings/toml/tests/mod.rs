@@ -33,6 +33,100 @@ fn it_builds_from_environments_config() {
     assert!(target.kv_namespaces.is_empty());
 }
 
+#[test]
+fn it_builds_from_json_and_yaml_config() {
+    for extension in &["json", "yaml"] {
+        let config_path = base_fixture_path()
+            .join("environments")
+            .with_extension(extension);
+        let manifest = Manifest::new(&config_path).unwrap();
+        assert_eq!(
+            manifest,
+            Manifest::new(&toml_fixture_path("environments")).unwrap()
+        );
+
+        let target = manifest.get_target(Some("production"), false).unwrap();
+        assert_eq!(target.name, "staging-worker");
+    }
+}
+
+#[test]
+fn it_rejects_unknown_keys_in_strict_mode() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let config_path = tmp_dir.path().join("wrangler.toml");
+    let config = "name = \"worker\"\ntype = \"javascript\"\nkv-namespace = []\n";
+
+    fs::write(&config_path, config).unwrap();
+    assert!(Manifest::new(&config_path).is_ok());
+
+    fs::write(&config_path, format!("strict = true\n{}", config)).unwrap();
+    let err = Manifest::new(&config_path).unwrap_err().to_string();
+    assert!(err.contains("unknown key `kv-namespace`, did you mean `kv_namespaces`?"));
+}
+
+#[test]
+fn it_uses_main_as_the_entry_point() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let main = tmp_dir.path().join("index.js");
+    fs::write(&main, "addEventListener('fetch', () => {})").unwrap();
+
+    let manifest = Manifest::from_str(&format!(
+        "name = \"worker\"\ntype = \"javascript\"\nmain = {:?}\n",
+        main.display().to_string()
+    ))
+    .unwrap();
+    let target = manifest.get_target(None, false).unwrap();
+    assert_eq!(target.entry_point().unwrap(), main);
+
+    let manifest = Manifest::from_str(&format!(
+        "name = \"worker\"\ntype = \"javascript\"\nmain = {:?}\n\n[build.upload]\nformat = \"modules\"\nmain = \"index.mjs\"\n",
+        main.display().to_string()
+    ))
+    .unwrap();
+    assert!(manifest.get_target(None, false).is_err());
+}
+
+#[test]
+fn it_uses_main_in_projects_built_by_their_own_command() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let main = tmp_dir.path().join("index.js");
+    fs::write(&main, "addEventListener('fetch', () => {})").unwrap();
+    let config = format!(
+        "name = \"worker\"\ntype = \"rust\"\nmain = {:?}\n",
+        main.display().to_string()
+    );
+
+    assert!(Manifest::from_str(&config)
+        .unwrap()
+        .get_target(None, false)
+        .is_err());
+
+    let manifest = Manifest::from_str(&format!(
+        "{}\n[build]\ncommand = \"worker-build --release\"\n",
+        config
+    ))
+    .unwrap();
+    let target = manifest.get_target(None, false).unwrap();
+    assert!(target.custom_build().is_some());
+    assert_eq!(target.entry_point().unwrap(), main);
+}
+
+#[test]
+fn it_inherits_the_bundler_in_environments() {
+    let manifest = Manifest::from_str(
+        "name = \"worker\"\ntype = \"webpack\"\nbundler = \"builtin\"\n\n[env.production]\nminify = true\n\n[env.legacy]\nbundler = \"webpack\"\n",
+    )
+    .unwrap();
+    let target = manifest.get_target(None, false).unwrap();
+    assert_eq!(target.bundler, Bundler::Builtin);
+    assert!(!target.minify);
+    let target = manifest.get_target(Some("production"), false).unwrap();
+    assert_eq!(target.bundler, Bundler::Builtin);
+    assert!(target.minify);
+    let target = manifest.get_target(Some("legacy"), false).unwrap();
+    assert_eq!(target.bundler, Bundler::Webpack);
+}
+
 #[test]
 fn it_builds_from_environments_config_with_kv() {
     let toml_path = toml_fixture_path("kv_namespaces");
@@ -123,6 +217,26 @@ fn it_builds_from_environments_config_with_r2() {
     }
 }
 
+#[test]
+fn it_does_not_inherit_services_in_environments() {
+    let toml_path = toml_fixture_path("services");
+    let manifest = Manifest::new(&toml_path).unwrap();
+
+    let target = manifest.get_target(None, false).unwrap();
+    assert_eq!(
+        target.services,
+        vec![ServiceBinding {
+            binding: "AUTH".to_string(),
+            service: "auth".to_string(),
+            environment: None,
+        }]
+    );
+
+    let target = manifest.get_target(Some("staging"), false).unwrap();
+    assert_eq!(target.services.len(), 1);
+    assert_eq!(target.services[0].environment.as_deref(), Some("staging"));
+}
+
 #[test]
 fn parses_same_from_config_path_as_string() {
     env::remove_var("CF_ACCOUNT_ID");
@@ -1,6 +1,93 @@
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct Triggers {
     pub crons: Vec<String>,
 }
+
+/// The fields of a cron expression, with their ranges and the names they accept.
+const CRON_FIELDS: [(&str, u32, u32, &[&str]); 5] = [
+    ("minute", 0, 59, &[]),
+    ("hour", 0, 23, &[]),
+    ("day of month", 1, 31, &["L", "W", "?"]),
+    (
+        "month",
+        1,
+        12,
+        &[
+            "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+        ],
+    ),
+    (
+        "day of week",
+        0,
+        7,
+        &["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT", "L", "?"],
+    ),
+];
+
+/// Checks that a cron expression has five valid fields, e.g. `*/30 9-17 * * MON-FRI`.
+pub fn validate_cron(cron: &str) -> Result<()> {
+    let fields: Vec<&str> = cron.split_whitespace().collect();
+    anyhow::ensure!(
+        fields.len() == CRON_FIELDS.len(),
+        "\"{}\" has {} fields, but a cron expression needs 5 (minute, hour, day of month, month and day of week)",
+        cron,
+        fields.len()
+    );
+    for (field, (name, min, max, names)) in fields.iter().zip(CRON_FIELDS.iter()) {
+        for part in field.split(',') {
+            if !valid_cron_part(part, *min, *max, names) {
+                anyhow::bail!("\"{}\" has an invalid {} field \"{}\"", cron, name, field)
+            }
+        }
+    }
+    Ok(())
+}
+
+fn valid_cron_part(part: &str, min: u32, max: u32, names: &[&str]) -> bool {
+    let (range, step) = match part.split_once('/') {
+        Some((range, step)) => (range, Some(step)),
+        None => (part, None),
+    };
+    if let Some(step) = step {
+        if !matches!(step.parse::<u32>(), Ok(step) if step > 0) {
+            return false;
+        }
+    }
+    let valid_value = |value: &str| {
+        names.contains(&value.to_ascii_uppercase().as_str())
+            // e.g. `5L` (last friday) or `15W` (nearest weekday) and `5#3` (third friday)
+            || value
+                .trim_end_matches(|c| c == 'L' || c == 'W')
+                .split('#')
+                .next()
+                .and_then(|value| value.parse::<u32>().ok())
+                .map_or(false, |value| value >= min && value <= max)
+    };
+    match range {
+        "*" => true,
+        range => match range.split_once('-') {
+            Some((from, to)) => valid_value(from) && valid_value(to),
+            None => valid_value(range),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_validates_cron_expressions() {
+        assert!(validate_cron("* * * * *").is_ok());
+        assert!(validate_cron("*/30 9-17 * * MON-FRI").is_ok());
+        assert!(validate_cron("0 0 1,15 JAN-JUN 0").is_ok());
+        assert!(validate_cron("0 0 L * 5#3").is_ok());
+        assert!(validate_cron("* * * *").is_err());
+        assert!(validate_cron("60 * * * *").is_err());
+        assert!(validate_cron("*/0 * * * *").is_err());
+        assert!(validate_cron("* * * FOO *").is_err());
+    }
+}
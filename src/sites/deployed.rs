@@ -0,0 +1,238 @@
+//! The record of the assets that `wrangler publish` deployed, which it keeps in the namespace of
+//! the site next to them, for `wrangler sites manifest` to show and for `wrangler sites prune` to
+//! tell the keys that are served from the orphaned ones.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use cloudflare::endpoints::workerskv::write_bulk::KeyValuePair;
+use cloudflare::framework::response::ApiFailure;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use super::large_files;
+use super::manifest::AssetManifest;
+use super::precompress;
+use crate::commands::kv;
+use crate::http;
+use crate::kv::bulk;
+use crate::settings::global_user::GlobalUser;
+use crate::settings::toml::{ContentEncoding, Target};
+
+/// The key of the record, which no asset has, since the keys of assets have a hash.
+pub const DEPLOYED_MANIFEST_KEY: &str = "__wrangler_deployed_manifest.json";
+
+const ENCODINGS: [ContentEncoding; 2] = [ContentEncoding::Brotli, ContentEncoding::Gzip];
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeployedAsset {
+    pub key: String,
+    /// The size of the file, in bytes.
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// The keys of the compressed variants of the asset.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<String>,
+    /// The keys of the chunks of a large file, or of the marker of its object of R2.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parts: Vec<String>,
+}
+
+// Sorted by path like the asset manifest
+pub type DeployedManifest = BTreeMap<String, DeployedAsset>;
+
+/// The record of the files of `directory` that `asset_manifest` maps to their keys.
+pub fn new(directory: &Path, asset_manifest: &AssetManifest) -> Result<DeployedManifest> {
+    let mut deployed = DeployedManifest::new();
+    for (path, key) in asset_manifest {
+        // the variants and the parts are listed with their asset
+        if is_variant(asset_manifest, path, key) || is_part(asset_manifest, path, key) {
+            continue;
+        }
+        let variants = ENCODINGS
+            .iter()
+            .map(|&encoding| precompress::variant(key, encoding))
+            .filter(|variant_key| asset_manifest.values().any(|key| key == variant_key))
+            .collect();
+        // the paths of the parts have the same prefix, so they are next to each other
+        let prefix = large_files::part(path, "");
+        let parts = asset_manifest
+            .range(prefix.clone()..)
+            .take_while(|(part_path, _)| part_path.starts_with(&prefix))
+            .filter(|(part_path, part_key)| is_part(asset_manifest, part_path, part_key))
+            .map(|(_, part_key)| part_key.clone())
+            .collect();
+        deployed.insert(
+            path.clone(),
+            DeployedAsset {
+                key: key.clone(),
+                size: fs::metadata(directory.join(path))?.len(),
+                hash: hash_of(path, key),
+                variants,
+                parts,
+            },
+        );
+    }
+    Ok(deployed)
+}
+
+/// The keys that the deployed site serves, and the key of the record itself.
+pub fn keys(deployed: &DeployedManifest) -> HashSet<String> {
+    let mut keys: HashSet<String> = deployed
+        .values()
+        .flat_map(|asset| {
+            std::iter::once(&asset.key)
+                .chain(&asset.variants)
+                .chain(&asset.parts)
+        })
+        .cloned()
+        .collect();
+    keys.insert(DEPLOYED_MANIFEST_KEY.to_string());
+    keys
+}
+
+/// Writes the record of a publish to the namespace of the site.
+pub fn put(
+    target: &Target,
+    user: &GlobalUser,
+    namespace_id: &str,
+    deployed: &DeployedManifest,
+) -> Result<()> {
+    let pair = KeyValuePair {
+        key: DEPLOYED_MANIFEST_KEY.to_string(),
+        value: serde_json::to_string(deployed)?,
+        expiration: None,
+        expiration_ttl: None,
+        base64: None,
+    };
+    bulk::put(target, user, namespace_id, vec![pair], &None)
+}
+
+/// Writes the keys of the deployed assets by their path to the `asset_map` of `[site]`, for other
+/// build tools to rewrite the paths of the assets that they refer to.
+pub fn write_asset_map(path: &Path, deployed: &DeployedManifest) -> Result<()> {
+    let asset_map: BTreeMap<&str, &str> = deployed
+        .iter()
+        .map(|(asset_path, asset)| (asset_path.as_str(), asset.key.as_str()))
+        .collect();
+    fs::write(path, serde_json::to_string_pretty(&asset_map)?).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to write the asset_map of [site] to {}: {}",
+            path.display(),
+            e
+        )
+    })
+}
+
+/// Reads the record of the last publish, which sites published by older versions of wrangler
+/// don't have.
+pub fn get(
+    target: &Target,
+    user: &GlobalUser,
+    namespace_id: &str,
+) -> Result<Option<DeployedManifest>> {
+    let api_endpoint = format!(
+        "https://api.cloudflare.com/client/v4/accounts/{}/storage/kv/namespaces/{}/values/{}",
+        target.account_id.load()?,
+        namespace_id,
+        DEPLOYED_MANIFEST_KEY
+    );
+    let res = http::legacy_auth_client(user).get(&api_endpoint).send()?;
+    let status = res.status();
+    if status == StatusCode::NOT_FOUND {
+        Ok(None)
+    } else if status.is_success() {
+        Ok(Some(res.json()?))
+    } else {
+        let errors = res.json().unwrap_or_default();
+        anyhow::bail!(kv::format_error(ApiFailure::Error(status, errors)))
+    }
+}
+
+/// Whether the entry of the asset manifest is the compressed variant of another one.
+fn is_variant(asset_manifest: &AssetManifest, path: &str, key: &str) -> bool {
+    ENCODINGS.iter().any(|&encoding| {
+        let suffix = format!(".{}", encoding.extension());
+        path.strip_suffix(&suffix)
+            .and_then(|asset_path| asset_manifest.get(asset_path))
+            .map_or(false, |asset_key| {
+                precompress::variant(asset_key, encoding) == key
+            })
+    })
+}
+
+/// Whether the entry of the asset manifest is a part of a large file.
+fn is_part(asset_manifest: &AssetManifest, path: &str, key: &str) -> bool {
+    path.rsplit_once('#')
+        .and_then(|(asset_path, suffix)| {
+            let asset_key = asset_manifest.get(asset_path)?;
+            Some(large_files::part(asset_key, suffix) == key)
+        })
+        .unwrap_or(false)
+}
+
+/// The hash in the key of an asset, like `ec717eb213` in `sitemap.ec717eb213.xml`.
+fn hash_of(path: &str, key: &str) -> Option<String> {
+    let (parent, file_name) = match path.rfind('/') {
+        Some(i) => path.split_at(i + 1),
+        None => ("", path),
+    };
+    let file_name = Path::new(file_name);
+    let stem = file_name.file_stem()?.to_str()?;
+    let hash = key.strip_prefix(&format!("{}{}.", parent, stem))?;
+    let hash = match file_name.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => hash.strip_suffix(&format!(".{}", ext))?,
+        None => hash,
+    };
+    Some(hash.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_records_assets_with_their_variants() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("css")).unwrap();
+        fs::write(dir.path().join("css/site.css"), "body {}").unwrap();
+        fs::write(dir.path().join("LICENSE"), "MIT").unwrap();
+
+        let mut asset_manifest = AssetManifest::new();
+        asset_manifest.insert("css/site.css".into(), "css/site.1a2b3c4d5e.css".into());
+        asset_manifest.insert(
+            "css/site.css.br".into(),
+            "css/site.1a2b3c4d5e.css.br".into(),
+        );
+        asset_manifest.insert("LICENSE".into(), "LICENSE.6f7a8b9c0d".into());
+
+        let deployed = new(dir.path(), &asset_manifest).unwrap();
+        assert_eq!(deployed.len(), 2);
+        let css = &deployed["css/site.css"];
+        assert_eq!(css.size, 7);
+        assert_eq!(css.hash.as_deref(), Some("1a2b3c4d5e"));
+        assert_eq!(css.variants, vec!["css/site.1a2b3c4d5e.css.br"]);
+        assert_eq!(deployed["LICENSE"].hash.as_deref(), Some("6f7a8b9c0d"));
+
+        let mut asset_manifest = AssetManifest::new();
+        asset_manifest.insert("LICENSE".into(), "LICENSE.6f7a8b9c0d".into());
+        asset_manifest.insert("LICENSE#0".into(), "LICENSE.6f7a8b9c0d#0".into());
+        asset_manifest.insert("LICENSE#1".into(), "LICENSE.6f7a8b9c0d#1".into());
+        asset_manifest.insert("LICENSE.txt".into(), "LICENSE.0123456789.txt".into());
+        fs::write(dir.path().join("LICENSE.txt"), "MIT").unwrap();
+        let chunked = new(dir.path(), &asset_manifest).unwrap();
+        assert_eq!(chunked.len(), 2);
+        assert_eq!(
+            chunked["LICENSE"].parts,
+            vec!["LICENSE.6f7a8b9c0d#0", "LICENSE.6f7a8b9c0d#1"]
+        );
+        assert!(chunked["LICENSE.txt"].parts.is_empty());
+
+        let keys = keys(&deployed);
+        assert_eq!(keys.len(), 4);
+        assert!(keys.contains(DEPLOYED_MANIFEST_KEY));
+    }
+}
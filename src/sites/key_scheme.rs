@@ -0,0 +1,125 @@
+//! How the keys of assets are made from their path and content, which `hash_algorithm`,
+//! `hash_length` and `hash_html` of `[site]` change. A key with a hash is never overwritten, so
+//! the assets whose content doesn't change are not uploaded again.
+
+use std::path::Path;
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+
+use super::{generate_path_and_key, generate_path_with_hash, get_digest};
+use crate::settings::toml::{HashAlgorithm, Site};
+
+const DEFAULT_HASH_LENGTH: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyScheme {
+    algorithm: HashAlgorithm,
+    length: usize,
+    hash_html: bool,
+}
+
+impl KeyScheme {
+    pub fn new(site: Option<&Site>) -> Result<KeyScheme> {
+        let algorithm = site
+            .and_then(|site| site.hash_algorithm)
+            .unwrap_or(HashAlgorithm::XxHash64);
+        let length = site
+            .and_then(|site| site.hash_length)
+            .unwrap_or(DEFAULT_HASH_LENGTH);
+        let max_length = algorithm.max_length();
+        anyhow::ensure!(
+            (1..=max_length).contains(&length),
+            "The hash_length of [site] must be from 1 to {} for {}",
+            max_length,
+            algorithm
+        );
+        Ok(KeyScheme {
+            algorithm,
+            length,
+            hash_html: site.and_then(|site| site.hash_html).unwrap_or(true),
+        })
+    }
+
+    /// Whether the key of a file has a hash of its content.
+    pub fn is_hashed(&self, path: &Path) -> bool {
+        self.hash_html || !is_html(path)
+    }
+
+    /// The url-safe path of a file of the bucket `directory`, and its key.
+    pub fn path_and_key(
+        &self,
+        path: &Path,
+        directory: &Path,
+        value: &[u8],
+        b64_value: &str,
+    ) -> Result<(String, String)> {
+        let (url_safe_path, _) = generate_path_and_key(path, directory, None)?;
+        if !self.is_hashed(path) {
+            return Ok((url_safe_path.clone(), url_safe_path));
+        }
+        let digest = match self.algorithm {
+            // the hash of the encoded value, which the keys of older versions of wrangler have
+            HashAlgorithm::XxHash64 => get_digest(b64_value.to_string()),
+            HashAlgorithm::Sha256 => format!("{:x}", Sha256::digest(value)),
+        };
+        // the digest of xxHash has no leading zeros, so it may be shorter than its maximum
+        let digest = digest.get(..self.length).unwrap_or(&digest).to_string();
+        let relative_path = path.strip_prefix(directory)?;
+        Ok((
+            url_safe_path,
+            generate_path_with_hash(relative_path, digest)?,
+        ))
+    }
+}
+
+fn is_html(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(false, |ext| {
+            ext.eq_ignore_ascii_case("html") || ext.eq_ignore_ascii_case("htm")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_makes_keys_by_the_scheme_of_the_site() {
+        let directory = Path::new("./build");
+        let path = Path::new("./build/css/site.css");
+        let value = b"body {}";
+        let b64_value = base64::encode(value);
+
+        // the keys of older versions of wrangler
+        let scheme = KeyScheme::new(None).unwrap();
+        assert_eq!(
+            scheme
+                .path_and_key(path, directory, value, &b64_value)
+                .unwrap(),
+            generate_path_and_key(path, directory, Some(b64_value.clone())).unwrap()
+        );
+
+        let site: Site = toml::from_str(
+            "bucket = \"build\"\nhash_algorithm = \"sha256\"\nhash_length = 16\nhash_html = false\n",
+        )
+        .unwrap();
+        let scheme = KeyScheme::new(Some(&site)).unwrap();
+        let (_, key) = scheme
+            .path_and_key(path, directory, value, &b64_value)
+            .unwrap();
+        assert_eq!(key, "css/site.62368a1a29259b30.css");
+        let html = Path::new("./build/index.html");
+        assert!(!scheme.is_hashed(html));
+        assert_eq!(
+            scheme
+                .path_and_key(html, directory, value, &b64_value)
+                .unwrap(),
+            ("index.html".to_string(), "index.html".to_string())
+        );
+
+        let site: Site = toml::from_str("bucket = \"build\"\nhash_length = 17\n").unwrap();
+        assert!(KeyScheme::new(Some(&site)).is_err());
+    }
+}
@@ -0,0 +1,134 @@
+//! The assets too large for a value of Workers KV, which `large_files` of `[site]` splits into
+//! chunks or uploads to an R2 bucket. The asset manifest lists their parts next to them, like
+//! `video.mp4#0` and `video.mp4#1` for chunks or `video.mp4#r2` for an object of R2, for the
+//! worker of the site to find them.
+
+use std::fs;
+use std::time::Duration;
+
+use anyhow::Result;
+use cloudflare::endpoints::workerskv::write_bulk::KeyValuePair;
+
+use crate::http::r2::{self, R2Client};
+use crate::settings::toml::{LargeFiles, Target};
+
+/// Small enough for a few chunks to fit in a batch of a bulk upload.
+const CHUNK_SIZE: usize = 10 * 1024 * 1024;
+const R2_PART: &str = "r2";
+
+/// The path or key of a part of a large file.
+pub fn part(path_or_key: &str, suffix: &str) -> String {
+    format!("{}#{}", path_or_key, suffix)
+}
+
+/// The parts of a large file, with their path in the asset manifest. The part of an object of R2
+/// is only a marker, whose value is the path of the file, for `put_r2_objects` to upload it.
+pub fn parts(
+    large_files: LargeFiles,
+    path: &str,
+    key: &str,
+    value: &[u8],
+) -> Vec<(String, KeyValuePair)> {
+    match large_files {
+        LargeFiles::Chunk => value
+            .chunks(CHUNK_SIZE)
+            .enumerate()
+            .map(|(i, chunk)| {
+                let suffix = i.to_string();
+                let pair = KeyValuePair {
+                    key: part(key, &suffix),
+                    value: base64::encode(chunk),
+                    expiration: None,
+                    expiration_ttl: None,
+                    base64: Some(true),
+                };
+                (part(path, &suffix), pair)
+            })
+            .collect(),
+        LargeFiles::R2 => {
+            let pair = KeyValuePair {
+                key: part(key, R2_PART),
+                value: path.to_string(),
+                expiration: None,
+                expiration_ttl: None,
+                base64: None,
+            };
+            vec![(part(path, R2_PART), pair)]
+        }
+    }
+}
+
+/// Uploads the files of the markers of R2 objects in `pairs`, before the markers are uploaded so
+/// that the worker never finds a marker without its object. They are uploaded with the
+/// S3-compatible API of R2, whose access key is in `R2_ACCESS_KEY_ID` and `R2_SECRET_ACCESS_KEY`.
+pub fn put_r2_objects(target: &Target, pairs: &[KeyValuePair]) -> Result<()> {
+    // the values of assets are all encoded in base64, unlike those of markers
+    let suffix = part("", R2_PART);
+    let markers: Vec<(&str, &KeyValuePair)> = pairs
+        .iter()
+        .filter(|pair| pair.base64.is_none())
+        .filter_map(|pair| Some((pair.key.strip_suffix(&suffix)?, pair)))
+        .collect();
+    if markers.is_empty() {
+        return Ok(());
+    }
+    let site = match &target.site {
+        Some(site) => site,
+        None => return Ok(()),
+    };
+    let binding = site.large_files_bucket.as_deref().unwrap_or_default();
+    let bucket = target
+        .r2_buckets
+        .iter()
+        .find(|bucket| bucket.binding == binding)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "The large_files_bucket of [site] is not the binding of any of your r2_buckets: \"{}\"",
+                binding
+            )
+        })?;
+
+    let client = R2Client::new(&target.account_id.load()?, &bucket.bucket_name)?;
+    for (object_key, marker) in markers {
+        let file = site.bucket.join(&marker.value);
+        log::info!("Uploading {} to R2", file.display());
+        let res = client
+            .put_object(object_key, fs::read(&file)?)
+            .timeout(Duration::from_secs(5 * 60))
+            .send()?;
+        if !res.status().is_success() {
+            anyhow::bail!(
+                "Failed to upload {} to the R2 bucket {}: {}",
+                file.display(),
+                bucket.bucket_name,
+                r2::error_message(res)?
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_splits_large_files_into_parts() {
+        let value = vec![0u8; CHUNK_SIZE * 2 + 1];
+        let chunks = parts(
+            LargeFiles::Chunk,
+            "video.mp4",
+            "video.0123456789.mp4",
+            &value,
+        );
+        let paths: Vec<&str> = chunks.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, vec!["video.mp4#0", "video.mp4#1", "video.mp4#2"]);
+        assert_eq!(chunks[2].1.key, "video.0123456789.mp4#2");
+        assert_eq!(chunks[2].1.value, base64::encode(&[0u8]));
+
+        let markers = parts(LargeFiles::R2, "video.mp4", "video.0123456789.mp4", &value);
+        assert_eq!(markers[0].0, "video.mp4#r2");
+        assert_eq!(markers[0].1.key, "video.0123456789.mp4#r2");
+        assert_eq!(markers[0].1.value, "video.mp4");
+    }
+}
@@ -1,3 +1,4 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
-pub type AssetManifest = HashMap<String, String>;
+// Sorted by path, so that the manifest uploaded for the same files is always the same
+pub type AssetManifest = BTreeMap<String, String>;
@@ -1,10 +1,19 @@
 extern crate base64;
 
+pub mod deployed;
+mod key_scheme;
+mod large_files;
 mod manifest;
+mod precompress;
+mod settings;
 mod sync;
+pub mod worker;
+mod wranglerignore;
 
 pub use manifest::AssetManifest;
-pub use sync::sync;
+pub use settings::{SiteSettings, SETTINGS_BINDING};
+pub use sync::{remote_keys, sync};
+pub use wranglerignore::{WranglerIgnore, WRANGLERIGNORE};
 
 use std::collections::HashSet;
 use std::error::Error;
@@ -22,21 +31,32 @@ use twox_hash::XxHash64;
 
 use cloudflare::endpoints::workerskv::write_bulk::KeyValuePair;
 
-use crate::kv::namespace::{upsert, UpsertedNamespace};
+use crate::http;
+use crate::kv::bulk;
+use crate::kv::namespace::{self, upsert, UpsertedNamespace};
 use crate::settings::global_user::GlobalUser;
 use crate::settings::toml::{KvNamespace, Target};
 use crate::terminal::message::{Message, StdErr};
+use key_scheme::KeyScheme;
+
 pub const KEY_MAX_SIZE: usize = 512;
 // Oddly enough, metadata.len() returns a u64, not usize.
 pub const VALUE_MAX_SIZE: u64 = 25 * 1024 * 1024;
+// Small enough for the batches of a site to be spread over the threads uploading them
+const UPLOAD_BATCH_KEY_MAX: usize = 500;
+const DEFAULT_UPLOAD_CONCURRENCY: usize = 4;
 
-// Updates given Target with kv_namespace binding for a static site assets KV namespace.
-pub fn add_namespace(user: &GlobalUser, target: &mut Target, preview: bool) -> Result<KvNamespace> {
-    let title = if preview {
+fn namespace_title(target: &Target, preview: bool) -> String {
+    if preview {
         format!("__{}-{}", target.name, "workers_sites_assets_preview")
     } else {
         format!("__{}-{}", target.name, "workers_sites_assets")
-    };
+    }
+}
+
+// Updates given Target with kv_namespace binding for a static site assets KV namespace.
+pub fn add_namespace(user: &GlobalUser, target: &mut Target, preview: bool) -> Result<KvNamespace> {
+    let title = namespace_title(target, preview);
 
     let site_namespace = match upsert(target, user, title)? {
         UpsertedNamespace::Created(namespace) => {
@@ -63,6 +83,43 @@ pub fn add_namespace(user: &GlobalUser, target: &mut Target, preview: bool) -> R
     Ok(site_namespace)
 }
 
+/// The ID of the namespace of a published site, without creating it like `add_namespace`.
+pub fn namespace_id(user: &GlobalUser, target: &Target) -> Result<String> {
+    let title = namespace_title(target, false);
+    let client = http::cf_v4_client(user)?;
+    namespace::list(&client, target)?
+        .into_iter()
+        .find(|namespace| namespace.title == title)
+        .map(|namespace| namespace.id)
+        .ok_or_else(|| anyhow!("The site of {} has not been published yet", target.name))
+}
+
+/// Uploads the assets of a site, `[site] upload_concurrency` batches at a time, retrying the
+/// batches that fail because of the network or the API.
+pub fn upload(
+    target: &Target,
+    user: &GlobalUser,
+    namespace_id: &str,
+    to_upload: Vec<KeyValuePair>,
+    progress_bar: &Option<ProgressBar>,
+) -> Result<()> {
+    large_files::put_r2_objects(target, &to_upload)?;
+    let concurrency = target
+        .site
+        .as_ref()
+        .and_then(|site| site.upload_concurrency)
+        .unwrap_or(DEFAULT_UPLOAD_CONCURRENCY);
+    bulk::put_parallel(
+        target,
+        user,
+        namespace_id,
+        to_upload,
+        UPLOAD_BATCH_KEY_MAX,
+        concurrency,
+        progress_bar,
+    )
+}
+
 #[derive(Debug, Clone)]
 pub struct NotADirectoryError;
 
@@ -86,6 +143,13 @@ pub fn directory_keys_values(
             let mut asset_manifest = AssetManifest::new();
             let mut file_list: Vec<String> = Vec::new();
             let dir_walker = get_dir_iterator(target, directory)?;
+            let precompress = target
+                .site
+                .as_ref()
+                .and_then(|site| site.precompress.clone())
+                .unwrap_or_default();
+            let large_files = target.site.as_ref().and_then(|site| site.large_files);
+            let scheme = KeyScheme::new(target.site.as_ref())?;
             let spinner_style =
                 ProgressStyle::default_spinner().template("{spinner}   Preparing {msg}...");
             let spinner = ProgressBar::new_spinner().with_style(spinner_style);
@@ -98,7 +162,10 @@ pub fn directory_keys_values(
                     spinner.set_message(&format!("{}", path.display()));
 
                     file_list.push(path.to_str().unwrap().to_string());
-                    validate_file_size(path)?;
+                    let is_large = fs::metadata(path)?.len() > VALUE_MAX_SIZE;
+                    if large_files.is_none() {
+                        validate_file_size(path)?;
+                    }
 
                     let value = std::fs::read(path)?;
 
@@ -106,18 +173,60 @@ pub fn directory_keys_values(
                     let b64_value = base64::encode(&value);
 
                     let (url_safe_path, key) =
-                        generate_path_and_key(path, directory, Some(b64_value.clone()))?;
+                        scheme.path_and_key(path, directory, &value, &b64_value)?;
 
                     validate_key_size(&key)?;
 
+                    // skip uploading existing keys, if configured to do so. The content of a key
+                    // without a hash may have changed, so it is always uploaded
+                    let is_hashed = scheme.is_hashed(path);
+                    let exists = |key: &String| {
+                        is_hashed && exclude.map_or(false, |remote_keys| remote_keys.contains(key))
+                    };
+
+                    if let Some(large_files) = large_files.filter(|_| is_large) {
+                        for (part_path, pair) in
+                            large_files::parts(large_files, &url_safe_path, &key, &value)
+                        {
+                            validate_key_size(&pair.key)?;
+                            asset_manifest.insert(part_path, pair.key.clone());
+                            if !exists(&pair.key) {
+                                upload_vec.push(pair);
+                            }
+                        }
+                        // the worker of the site finds the parts next to the asset
+                        asset_manifest.insert(url_safe_path, key);
+                        continue;
+                    }
+
+                    // the variants are in the manifest for the worker to know which ones exist
+                    for &encoding in &precompress {
+                        if !precompress::is_compressible(path, value.len()) {
+                            break;
+                        }
+                        let variant_key = precompress::variant(&key, encoding);
+                        validate_key_size(&variant_key)?;
+                        let variant_path = precompress::variant(&url_safe_path, encoding);
+                        // a variant is only uploaded when it is smaller than the asset
+                        if exists(&variant_key) {
+                            asset_manifest.insert(variant_path, variant_key);
+                        } else if let Some(compressed) = precompress::compress(&value, encoding)? {
+                            asset_manifest.insert(variant_path, variant_key.clone());
+                            upload_vec.push(KeyValuePair {
+                                key: variant_key,
+                                value: base64::encode(&compressed),
+                                expiration: None,
+                                expiration_ttl: None,
+                                base64: Some(true),
+                            });
+                        }
+                    }
+
                     // asset manifest should always contain all files
                     asset_manifest.insert(url_safe_path, key.clone());
 
-                    // skip uploading existing keys, if configured to do so
-                    if let Some(remote_keys) = exclude {
-                        if remote_keys.contains(&key) {
-                            continue;
-                        }
+                    if exists(&key) {
+                        continue;
                     }
 
                     upload_vec.push(KeyValuePair {
@@ -150,7 +259,7 @@ fn validate_file_size(path: &Path) -> Result<()> {
 
     if file_len > VALUE_MAX_SIZE {
         anyhow::bail!(
-            "File `{}` of {} bytes exceeds the maximum value size limit of {} bytes",
+            "File `{}` of {} bytes exceeds the maximum value size limit of {} bytes. Set `large_files` of [site] to \"chunk\" or \"r2\" to upload it anyway",
             path.display(),
             file_len,
             VALUE_MAX_SIZE
@@ -183,10 +292,42 @@ fn get_dir_iterator(target: &Target, directory: &Path) -> Result<Walk> {
     };
 
     let ignore = build_ignore(target, directory)?;
-    Ok(WalkBuilder::new(directory)
-        .standard_filters(false)
-        .overrides(ignore)
-        .build())
+    let mut walker = WalkBuilder::new(directory);
+    walker.standard_filters(false).overrides(ignore);
+    // unlike ignore files read by the walker, this applies to what the overrides include too
+    if let Some(wranglerignore) = WranglerIgnore::load(directory)? {
+        log::info!("Ignoring the files listed in {}", WRANGLERIGNORE);
+        walker.filter_entry(move |entry| {
+            let is_dir = entry
+                .file_type()
+                .map_or(false, |file_type| file_type.is_dir());
+            !wranglerignore.is_ignored(entry.path(), is_dir)
+        });
+    }
+    Ok(walker.build())
+}
+
+/// Whether a file of the bucket `directory` is an asset of the site, which `include`, `exclude`
+/// and the `.wranglerignore` of the bucket don't leave out.
+pub fn is_asset(target: &Target, directory: &Path, path: &Path) -> Result<bool> {
+    let ignore = build_ignore(target, directory)?;
+    let wranglerignore = WranglerIgnore::load(directory)?;
+    let relative = path.strip_prefix(directory)?;
+    let depth = relative.components().count();
+    let mut ancestor = directory.to_path_buf();
+    // like the walker, which doesn't enter the directories that are left out
+    for (i, component) in relative.components().enumerate() {
+        ancestor.push(component);
+        let is_dir = i + 1 < depth;
+        if ignore.matched(&ancestor, is_dir).is_ignore()
+            || wranglerignore.as_ref().map_or(false, |wranglerignore| {
+                wranglerignore.is_ignored(&ancestor, is_dir)
+            })
+        {
+            return Ok(false);
+        }
+    }
+    Ok(path.is_file())
 }
 
 fn build_ignore(target: &Target, directory: &Path) -> Result<Override> {
@@ -327,18 +468,24 @@ mod tests {
     use std::path::{Path, PathBuf};
     use tempfile::TempDir;
 
-    use crate::settings::toml::{Site, Target, TargetType};
+    use crate::settings::toml::{Bundler, Site, Target, TargetType};
 
     fn make_target(site: Site) -> Target {
         Target {
             account_id: None.into(),
             kv_namespaces: Vec::new(),
             r2_buckets: Vec::new(),
+            services: Vec::new(),
             durable_objects: None,
             migrations: None,
             name: "".to_string(),
             target_type: TargetType::JavaScript,
+            main: None,
+            entries: None,
             webpack_config: None,
+            node_compat: false,
+            bundler: Bundler::default(),
+            minify: false,
             site: Some(site),
             build: None,
             vars: None,
@@ -667,6 +814,31 @@ mod tests {
         assert_eq!(actual_url_safe_path, expected_url_safe_path);
     }
 
+    #[test]
+    fn it_ignores_files_listed_in_wranglerignore() {
+        let mut site = Site::default();
+        site.bucket = PathBuf::from("fake");
+        site.include = Some(vec!["*.js*".to_string(), WRANGLERIGNORE.to_string()]);
+        let target = make_target(site);
+
+        let tmpdir = TempDir::new().unwrap();
+        let dir = tmpdir.path();
+        fs::create_dir(dir.join("build")).unwrap();
+        fs::write(dir.join(WRANGLERIGNORE), "*.map\nbuild/\n").unwrap();
+        for file in &["index.js", "index.js.map", "build/chunk.js"] {
+            fs::File::create(dir.join(file)).unwrap();
+        }
+
+        let (_, asset_manifest, _) = directory_keys_values(&target, dir, None).unwrap();
+        let paths: Vec<&String> = asset_manifest.keys().collect();
+        assert_eq!(paths, vec!["index.js"]);
+
+        assert!(is_asset(&target, dir, &dir.join("index.js")).unwrap());
+        assert!(!is_asset(&target, dir, &dir.join("index.js.map")).unwrap());
+        assert!(!is_asset(&target, dir, &dir.join("build/chunk.js")).unwrap());
+        assert!(!is_asset(&target, dir, &dir.join("missing.js")).unwrap());
+    }
+
     #[test]
     fn it_removes_bucket_dir_prefix() {
         let path = Path::new("./build/path/to/asset.ext");
@@ -0,0 +1,91 @@
+//! The variants of the assets of a site that are compressed when they are uploaded, which the
+//! worker of the site serves to the clients that accept their encoding, instead of compressing
+//! the assets for every response.
+
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::Result;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::settings::toml::ContentEncoding;
+
+/// Assets smaller than this gain too little from being compressed.
+const MIN_SIZE: usize = 1024;
+const COMPRESSIBLE_EXTENSIONS: &[&str] = &[
+    "css",
+    "csv",
+    "htm",
+    "html",
+    "ico",
+    "js",
+    "json",
+    "map",
+    "md",
+    "mjs",
+    "svg",
+    "txt",
+    "wasm",
+    "webmanifest",
+    "xml",
+];
+
+/// Whether an asset is worth compressing.
+pub fn is_compressible(path: &Path, size: usize) -> bool {
+    size >= MIN_SIZE
+        && path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map_or(false, |ext| {
+                COMPRESSIBLE_EXTENSIONS.contains(&ext.to_lowercase().as_str())
+            })
+}
+
+/// The path or key of the variant of an asset, like `index.html.br` for `index.html`.
+pub fn variant(path_or_key: &str, encoding: ContentEncoding) -> String {
+    format!("{}.{}", path_or_key, encoding.extension())
+}
+
+/// Compresses an asset, returning `None` when that doesn't make it smaller.
+pub fn compress(value: &[u8], encoding: ContentEncoding) -> Result<Option<Vec<u8>>> {
+    let compressed = match encoding {
+        ContentEncoding::Brotli => {
+            let mut compressed = Vec::new();
+            {
+                // the best quality, with the default window of 4 MiB
+                let mut writer = brotli::CompressorWriter::new(&mut compressed, 4096, 11, 22);
+                writer.write_all(value)?;
+            }
+            compressed
+        }
+        ContentEncoding::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+            encoder.write_all(value)?;
+            encoder.finish()?
+        }
+    };
+    Ok(Some(compressed).filter(|compressed| compressed.len() < value.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_compresses_text_assets() {
+        let html = "<p>Hello, world!</p>\n".repeat(100);
+        assert!(is_compressible(Path::new("index.html"), html.len()));
+        assert!(!is_compressible(Path::new("index.html"), 100));
+        assert!(!is_compressible(Path::new("photo.jpg"), html.len()));
+
+        for encoding in &[ContentEncoding::Brotli, ContentEncoding::Gzip] {
+            let compressed = compress(html.as_bytes(), *encoding).unwrap().unwrap();
+            assert!(compressed.len() < html.len());
+        }
+        assert_eq!(
+            variant("index.1a2b3c4d5e.html", ContentEncoding::Gzip),
+            "index.1a2b3c4d5e.html.gz"
+        );
+    }
+}
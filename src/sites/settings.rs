@@ -0,0 +1,183 @@
+//! The settings of `[site]` that the worker of a site applies when it serves assets, bound to it
+//! as JSON.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use reqwest::header::{HeaderName, HeaderValue};
+use serde::Serialize;
+
+use crate::settings::toml::{ContentEncoding, LargeFiles, NotFoundHandling, Site};
+
+pub const SETTINGS_BINDING: &str = "__STATIC_CONTENT_SETTINGS";
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct SiteSettings {
+    /// Applied in order, so that the headers of longer patterns win.
+    headers: Vec<HeaderRule>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    not_found_handling: Option<NotFoundHandling>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    precompress: Vec<ContentEncoding>,
+    /// The binding of the R2 bucket of the large files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    large_files_bucket: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct HeaderRule {
+    pattern: String,
+    headers: BTreeMap<String, String>,
+}
+
+impl SiteSettings {
+    pub fn new(site: &Site) -> Result<SiteSettings> {
+        let mut headers = Vec::new();
+        for (pattern, values) in site.headers.iter().flatten() {
+            anyhow::ensure!(
+                !pattern.is_empty(),
+                "The patterns of [site.headers] can't be empty"
+            );
+            for (name, value) in values {
+                HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+                    anyhow::anyhow!("Invalid header name \"{}\" in [site.headers]", name)
+                })?;
+                HeaderValue::from_str(value).map_err(|_| {
+                    anyhow::anyhow!("Invalid value of the header {} in [site.headers]", name)
+                })?;
+            }
+            headers.push(HeaderRule {
+                pattern: pattern.clone(),
+                headers: values.clone(),
+            });
+        }
+        headers.sort_by_key(|rule| rule.pattern.len());
+        let large_files_bucket = match site.large_files {
+            Some(LargeFiles::R2) => Some(site.large_files_bucket.clone().ok_or_else(|| {
+                anyhow::anyhow!(
+                    "`large_files = \"r2\"` needs the binding of an R2 bucket in large_files_bucket of [site]"
+                )
+            })?),
+            _ => None,
+        };
+        Ok(SiteSettings {
+            headers,
+            not_found_handling: site.not_found_handling()?,
+            precompress: site.precompress.clone().unwrap_or_default(),
+            large_files_bucket,
+        })
+    }
+
+    /// The asset served for paths that have no asset, which the bucket must have.
+    pub fn fallback_page(&self) -> Option<&'static str> {
+        self.not_found_handling.map(NotFoundHandling::page)
+    }
+
+    /// The headers that the worker of the site sets for a path, those of longer patterns last.
+    pub fn headers<'a>(&'a self, pathname: &'a str) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.headers
+            .iter()
+            .filter(move |rule| matches(&rule.pattern, pathname))
+            .flat_map(|rule| rule.headers.iter())
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Whether the worker of the site has nothing to apply.
+    pub fn is_empty(&self) -> bool {
+        *self == SiteSettings::default()
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+/// Whether a pattern like "/assets/*" or "*.html", whose * matches anything, matches the path,
+/// like the worker of the site does.
+fn matches(pattern: &str, pathname: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match pathname.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts: Vec<&str> = parts.collect();
+    let (last, middle) = match parts.split_last() {
+        Some(split) => split,
+        None => return rest.is_empty(),
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_orders_header_rules_from_the_shortest_pattern() {
+        let site: Site = toml::from_str(
+            r#"
+            bucket = "public"
+
+            [headers."/assets/*"]
+            Cache-Control = "public, max-age=31536000, immutable"
+
+            [headers."/*"]
+            Cache-Control = "no-cache"
+            X-Frame-Options = "DENY"
+            "#,
+        )
+        .unwrap();
+        let settings = SiteSettings::new(&site).unwrap();
+        let headers: Vec<_> = settings.headers("/assets/app.js").collect();
+        assert_eq!(
+            headers,
+            vec![
+                ("Cache-Control", "no-cache"),
+                ("X-Frame-Options", "DENY"),
+                ("Cache-Control", "public, max-age=31536000, immutable")
+            ]
+        );
+        assert!(matches("*.html", "/about/index.html"));
+        assert!(matches("/a*b*c", "/abbc"));
+        assert!(!matches("/a*b*c", "/acb"));
+        assert!(!matches("/assets/*", "/index.html"));
+        assert_eq!(
+            settings.to_json().unwrap(),
+            r#"{"headers":[{"pattern":"/*","headers":{"Cache-Control":"no-cache","X-Frame-Options":"DENY"}},{"pattern":"/assets/*","headers":{"Cache-Control":"public, max-age=31536000, immutable"}}]}"#
+        );
+
+        let site: Site = toml::from_str(
+            r#"
+            bucket = "public"
+
+            [headers."/*"]
+            "Cache Control" = "no-cache"
+            "#,
+        )
+        .unwrap();
+        assert!(SiteSettings::new(&site).is_err());
+        assert!(SiteSettings::new(&Site::new("public")).unwrap().is_empty());
+
+        let site: Site = toml::from_str("bucket = \"public\"\nspa = true\n").unwrap();
+        let settings = SiteSettings::new(&site).unwrap();
+        assert_eq!(settings.fallback_page(), Some("index.html"));
+        assert_eq!(
+            settings.to_json().unwrap(),
+            r#"{"headers":[],"not_found_handling":"single-page-app"}"#
+        );
+        let site: Site =
+            toml::from_str("bucket = \"public\"\nspa = true\nnot_found_handling = \"404-page\"\n")
+                .unwrap();
+        assert!(SiteSettings::new(&site).is_err());
+
+        let site: Site = toml::from_str("bucket = \"public\"\nlarge_files = \"r2\"\n").unwrap();
+        assert!(SiteSettings::new(&site).is_err());
+    }
+}
@@ -4,6 +4,7 @@ use std::path::Path;
 use anyhow::Result;
 use cloudflare::endpoints::workerskv::write_bulk::KeyValuePair;
 
+use super::deployed::DEPLOYED_MANIFEST_KEY;
 use super::directory_keys_values;
 use super::manifest::AssetManifest;
 use crate::commands::kv;
@@ -26,17 +27,9 @@ pub fn sync(
     // Turn it into a HashSet. This will be used by upload() to figure out which
     // files to exclude from upload (because their current version already exists in
     // the Workers KV remote).
-    let client = http::cf_v4_client(user)?;
-    let remote_keys_iter = KeyList::new(target, client, namespace_id, None)?;
-    let mut remote_keys: HashSet<String> = HashSet::new();
-    for remote_key in remote_keys_iter {
-        match remote_key {
-            Ok(remote_key) => {
-                remote_keys.insert(remote_key.name);
-            }
-            Err(e) => anyhow::bail!(kv::format_error(e)),
-        }
-    }
+    let mut remote_keys = remote_keys(target, user, namespace_id)?;
+    // the record of the last publish is replaced, not deleted
+    remote_keys.remove(DEPLOYED_MANIFEST_KEY);
 
     let (diff_files_to_upload, asset_manifest, _): (Vec<KeyValuePair>, AssetManifest, _) =
         directory_keys_values(target, path, Some(&remote_keys))?;
@@ -55,6 +48,41 @@ pub fn sync(
         .map(|key| key.to_owned())
         .collect();
 
-    StdErr::success("Success");
+    // the manifest has every local file, including those whose key is already in KV
+    let unchanged = asset_manifest.len() - diff_files_to_upload.len();
+    StdErr::info(&summary(
+        diff_files_to_upload.len(),
+        unchanged,
+        to_delete.len(),
+    ));
     Ok((diff_files_to_upload, to_delete, asset_manifest))
 }
+
+/// The keys in the namespace of a site.
+pub fn remote_keys(
+    target: &Target,
+    user: &GlobalUser,
+    namespace_id: &str,
+) -> Result<HashSet<String>> {
+    let client = http::cf_v4_client(user)?;
+    let remote_keys_iter = KeyList::new(target, client, namespace_id, None)?;
+    let mut remote_keys: HashSet<String> = HashSet::new();
+    for remote_key in remote_keys_iter {
+        match remote_key {
+            Ok(remote_key) => {
+                remote_keys.insert(remote_key.name);
+            }
+            Err(e) => anyhow::bail!(kv::format_error(e)),
+        }
+    }
+    Ok(remote_keys)
+}
+
+/// Describes what a sync does, like "3 site assets to upload, 997 unchanged, 1 to delete".
+fn summary(to_upload: usize, unchanged: usize, to_delete: usize) -> String {
+    let assets = if to_upload == 1 { "asset" } else { "assets" };
+    format!(
+        "{} site {} to upload, {} unchanged, {} to delete",
+        to_upload, assets, unchanged, to_delete
+    )
+}
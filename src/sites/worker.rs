@@ -0,0 +1,233 @@
+//! The worker that serves the assets of a Workers Site, which wrangler writes to the entry point
+//! of the site when it doesn't exist. It applies the settings of `[site]`, which wrangler binds
+//! to it as JSON in `__STATIC_CONTENT_SETTINGS`.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::settings::toml::Site;
+use crate::terminal::message::{Message, StdErr};
+
+use super::settings::{SiteSettings, SETTINGS_BINDING};
+
+const PACKAGE_JSON: &str = r#"{
+  "private": true,
+  "name": "worker",
+  "version": "1.0.0",
+  "description": "A template for kick starting a Cloudflare Workers project",
+  "main": "index.js",
+  "dependencies": {
+    "@cloudflare/kv-asset-handler": "^0.2.0",
+    "mime": "^3.0.0"
+  }
+}
+"#;
+
+const SCRIPT: &str = r#"import { getAssetFromKV, mapRequestToAsset } from "@cloudflare/kv-asset-handler";
+import mime from "mime";
+
+// The settings of [site] in wrangler.toml, bound by wrangler when it uploads the site
+const SETTINGS =
+  typeof __STATIC_CONTENT_SETTINGS === "undefined"
+    ? {}
+    : JSON.parse(__STATIC_CONTENT_SETTINGS);
+// The keys of the assets and of their compressed variants
+const MANIFEST =
+  typeof __STATIC_CONTENT_MANIFEST === "undefined"
+    ? {}
+    : JSON.parse(__STATIC_CONTENT_MANIFEST);
+const EXTENSIONS = { br: "br", gzip: "gz" };
+
+addEventListener("fetch", (event) => {
+  event.respondWith(handleEvent(event));
+});
+
+async function handleEvent(event) {
+  try {
+    return await serve(event, event.request, 200);
+  } catch (e) {
+    const fallback = e.status === 404 && fallbackPage(event.request);
+    if (fallback) {
+      try {
+        const request = new Request(new URL(fallback.path, event.request.url), event.request);
+        return await serve(event, request, fallback.status);
+      } catch (e) {}
+    }
+    return new Response(e.message || e.toString(), { status: e.status || 500 });
+  }
+}
+
+// Serves the asset of a request, or a variant of it compressed in an encoding the client accepts
+async function serve(event, request, status) {
+  const asset = mapRequestToAsset(request);
+  const url = new URL(asset.url);
+  const pathname = url.pathname;
+  const body = await largeFile(event, request, pathname);
+  if (body) {
+    const response = new Response(body, {
+      status,
+      headers: { "Content-Type": contentType(pathname) },
+    });
+    applyHeaders(new URL(request.url).pathname, response.headers);
+    return response;
+  }
+  const encoding = acceptedEncoding(event.request, pathname);
+  if (encoding) {
+    url.pathname += `.${EXTENSIONS[encoding]}`;
+  }
+  const page = await getAssetFromKV(event, {
+    mapRequestToAsset: () => new Request(url, asset),
+  });
+  // a compressed body is sent as it is, instead of being compressed again
+  const response = new Response(page.body, {
+    status,
+    headers: page.headers,
+    encodeBody: encoding ? "manual" : "automatic",
+  });
+  if (encoding) {
+    response.headers.set("Content-Encoding", encoding);
+    response.headers.set("Content-Type", contentType(pathname));
+  }
+  if (SETTINGS.precompress) {
+    response.headers.append("Vary", "Accept-Encoding");
+  }
+  applyHeaders(new URL(request.url).pathname, response.headers);
+  return response;
+}
+
+// The first encoding of precompress of [site] that the client accepts and the asset has
+function acceptedEncoding(request, pathname) {
+  const accepted = (request.headers.get("Accept-Encoding") || "")
+    .split(",")
+    .map((encoding) => encoding.split(";")[0].trim());
+  const path = manifestPath(pathname);
+  return (SETTINGS.precompress || []).find(
+    (encoding) => accepted.includes(encoding) && `${path}.${EXTENSIONS[encoding]}` in MANIFEST
+  );
+}
+
+// The body of an asset too large for a value of KV, which is in chunks or in R2, by large_files
+// of [site]
+async function largeFile(event, request, pathname) {
+  if (!["GET", "HEAD"].includes(request.method)) {
+    return null;
+  }
+  const path = manifestPath(pathname);
+  if (`${path}#r2` in MANIFEST) {
+    const key = MANIFEST[`${path}#r2`].replace(/#r2$/, "");
+    const object = await globalThis[SETTINGS.large_files_bucket].get(key);
+    if (object === null) {
+      throw missingPart(path, "r2");
+    }
+    return object.body;
+  }
+  if (!(`${path}#0` in MANIFEST)) {
+    return null;
+  }
+  const chunk = (i) => __STATIC_CONTENT.get(MANIFEST[`${path}#${i}`], "stream");
+  // the first chunk is read before responding, so that a missing one fails the response
+  const first = await chunk(0);
+  if (first === null) {
+    throw missingPart(path, 0);
+  }
+  const { readable, writable } = new TransformStream();
+  event.waitUntil(
+    (async () => {
+      let body = first;
+      for (let i = 1; ; i++) {
+        await body.pipeTo(writable, { preventClose: true });
+        if (!(`${path}#${i}` in MANIFEST)) {
+          return writable.close();
+        }
+        body = await chunk(i);
+        if (body === null) {
+          // the response has started, so only its body can fail
+          return writable.abort(missingPart(path, i));
+        }
+      }
+    })()
+  );
+  return readable;
+}
+
+// The error of a part of a large file that is not where the asset manifest says, like a chunk
+// that was deleted from KV
+function missingPart(path, part) {
+  const error = new Error(`${path}#${part} is missing, publish the site again to upload it`);
+  error.status = 500;
+  return error;
+}
+
+// The path of an asset in the manifest, like index.html for /index.html
+function manifestPath(pathname) {
+  return decodeURIComponent(pathname).replace(/^\//, "");
+}
+
+// The type of an asset, which isn't that of its compressed variant
+function contentType(pathname) {
+  const type = mime.getType(pathname) || "application/octet-stream";
+  return type.startsWith("text") || type === "application/javascript"
+    ? `${type}; charset=utf-8`
+    : type;
+}
+
+// The page served for a path that has no asset, by not_found_handling of [site]
+function fallbackPage(request) {
+  switch (SETTINGS.not_found_handling) {
+    case "single-page-app":
+      // the app routes pages, not the scripts or images that are missing
+      return (request.headers.get("Accept") || "").includes("text/html")
+        ? { path: "/index.html", status: 200 }
+        : null;
+    case "404-page":
+      return { path: "/404.html", status: 404 };
+    default:
+      return null;
+  }
+}
+
+// Sets the headers of [site.headers] whose pattern matches the path, the longest pattern last
+function applyHeaders(pathname, headers) {
+  for (const rule of SETTINGS.headers || []) {
+    if (matches(rule.pattern, pathname)) {
+      for (const [name, value] of Object.entries(rule.headers)) {
+        headers.set(name, value);
+      }
+    }
+  }
+}
+
+// Whether a pattern like "/assets/*" or "*.html", whose * matches anything, matches the path
+function matches(pattern, pathname) {
+  const escape = (part) => part.replace(/[.+?^${}()|[\]\\]/g, "\\$&");
+  return new RegExp(`^${pattern.split("*").map(escape).join(".*")}$`).test(pathname);
+}
+"#;
+
+/// Writes the worker of a site to `entry_point`.
+pub fn scaffold(entry_point: &Path) -> Result<()> {
+    log::info!("Generating a new workers site project");
+    fs::create_dir_all(entry_point)?;
+    fs::write(entry_point.join("package.json"), PACKAGE_JSON)?;
+    fs::write(entry_point.join("index.js"), SCRIPT)?;
+    Ok(())
+}
+
+/// Warns when `[site]` has settings that the worker of the site ignores, because it was written
+/// by an older version of wrangler or by a template.
+pub fn warn_if_settings_are_ignored(site: &Site) -> Result<()> {
+    if SiteSettings::new(site)?.is_empty() {
+        return Ok(());
+    }
+    let script = fs::read_to_string(site.entry_point()?.join("index.js")).unwrap_or_default();
+    if !script.contains(SETTINGS_BINDING) {
+        StdErr::warn(&format!(
+            "The worker of your site doesn't read {}, so it ignores the settings of [site]. Remove {} to have wrangler write one that does.",
+            SETTINGS_BINDING,
+            site.entry_point()?.display()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,35 @@
+//! `.wranglerignore`, a file in gitignore syntax listing the files of a directory that are never
+//! uploaded, like sourcemaps or build leftovers, whatever `include` and `exclude` say.
+
+use std::ffi::OsStr;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+pub const WRANGLERIGNORE: &str = ".wranglerignore";
+
+#[derive(Clone, Debug)]
+pub struct WranglerIgnore(Gitignore);
+
+impl WranglerIgnore {
+    /// Reads the `.wranglerignore` of `dir`, if it has one.
+    pub fn load(dir: &Path) -> Result<Option<WranglerIgnore>> {
+        let path = dir.join(WRANGLERIGNORE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let mut builder = GitignoreBuilder::new(dir);
+        if let Some(e) = builder.add(&path) {
+            return Err(anyhow!("Invalid {}: {}", path.display(), e));
+        }
+        Ok(Some(WranglerIgnore(builder.build()?)))
+    }
+
+    /// Whether `path`, which is in the directory of the `.wranglerignore`, is ignored. The
+    /// `.wranglerignore` itself always is.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        path.file_name() == Some(OsStr::new(WRANGLERIGNORE))
+            || self.0.matched_path_or_any_parents(path, is_dir).is_ignore()
+    }
+}
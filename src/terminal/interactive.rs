@@ -1,6 +1,16 @@
 use anyhow::Result;
 use atty::Stream;
 use std::io::{self, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether `--yes` was passed, taking the default of every prompt.
+static ACCEPT_DEFAULTS: AtomicBool = AtomicBool::new(false);
+
+/// Never prompt, as if stdin were not a terminal, so that the defaults or the values of flags
+/// are used instead.
+pub fn set_accept_defaults() {
+    ACCEPT_DEFAULTS.store(true, Ordering::Relaxed);
+}
 
 // For interactively handling reading in a string
 pub fn get_user_input(prompt_string: &str) -> String {
@@ -52,6 +62,35 @@ pub fn confirm(prompt_string: &str) -> Result<bool> {
     }
 }
 
+/// Tests if the user can answer prompts, i.e. stdin is not a pipe.
+pub fn is_interactive() -> bool {
+    !ACCEPT_DEFAULTS.load(Ordering::Relaxed) && atty::is(Stream::Stdin)
+}
+
+// For interactively choosing one of many options, e.g. which account to use.
+// Returns the index of the chosen option.
+pub fn select(prompt_string: &str, options: &[String]) -> Result<usize> {
+    anyhow::ensure!(!options.is_empty(), "There is nothing to choose from");
+    println!("{}", prompt_string);
+    for (i, option) in options.iter().enumerate() {
+        println!("  {}) {}", i + 1, option);
+    }
+    loop {
+        let response: String = read!("{}\n");
+        match parse_selection(&response, options.len()) {
+            Some(index) => return Ok(index),
+            None => println!("Response must be a number between 1 and {}", options.len()),
+        }
+    }
+}
+
+fn parse_selection(response: &str, len: usize) -> Option<usize> {
+    match response.trim().parse::<usize>() {
+        Ok(n) if n >= 1 && n <= len => Some(n - 1),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -63,4 +102,13 @@ mod tests {
         let truncated_str = strip_trailing_whitespace(test_str);
         assert_eq!(truncated_str, "mysecret")
     }
+
+    #[test]
+    fn it_parses_selections_within_range() {
+        assert_eq!(parse_selection("2\r", 3), Some(1));
+        assert_eq!(parse_selection(" 1 ", 3), Some(0));
+        assert_eq!(parse_selection("0", 3), None);
+        assert_eq!(parse_selection("4", 3), None);
+        assert_eq!(parse_selection("two", 3), None);
+    }
 }
@@ -16,6 +16,14 @@ pub fn cyan<D>(msg: D) -> StyledObject<D> {
     style(msg).cyan().bold()
 }
 
+pub fn added<D>(msg: D) -> StyledObject<D> {
+    style(msg).green()
+}
+
+pub fn removed<D>(msg: D) -> StyledObject<D> {
+    style(msg).red()
+}
+
 pub fn bold<D>(msg: D) -> StyledObject<D> {
     style(msg).bold()
 }
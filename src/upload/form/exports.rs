@@ -0,0 +1,125 @@
+use std::collections::HashSet;
+use std::fs;
+
+use anyhow::Result;
+use regex::Regex;
+
+use super::project_assets::ModuleManifest;
+use super::ModuleType;
+use crate::settings::toml::DurableObjectsClass;
+
+/// The Durable Object classes implemented by the uploaded script itself, as opposed to the
+/// ones bound from another script with `script_name`.
+fn own_classes(classes: &[DurableObjectsClass]) -> impl Iterator<Item = &DurableObjectsClass> {
+    classes.iter().filter(|class| class.script_name.is_none())
+}
+
+/// Fails when Durable Object classes are bound to a service worker script, which cannot
+/// implement them, instead of letting the upload be rejected.
+pub(super) fn check_service_worker(classes: &[DurableObjectsClass]) -> Result<()> {
+    if let Some(class) = own_classes(classes).next() {
+        anyhow::bail!(
+            "Durable Object class {} of binding {} must be exported by a module, but this Worker is built in the service worker format; set `format = \"modules\"` in the [build.upload] section of your configuration, or set the `script_name` of the Worker implementing it",
+            class.class_name,
+            class.binding
+        )
+    }
+    Ok(())
+}
+
+/// Fails when a Durable Object class is not exported by the main module.
+pub(super) fn check_modules(
+    manifest: &ModuleManifest,
+    classes: &[DurableObjectsClass],
+) -> Result<()> {
+    if own_classes(classes).next().is_none() {
+        return Ok(());
+    }
+    let main = match manifest.modules.get(&format!("./{}", manifest.main)) {
+        Some(main) if main.module_type == ModuleType::ESModule => main,
+        _ => return Ok(()),
+    };
+    let exports = match module_exports(&fs::read_to_string(&main.path)?) {
+        Some(exports) => exports,
+        None => {
+            log::info!("{} re-exports other modules", manifest.main);
+            return Ok(());
+        }
+    };
+
+    let missing = own_classes(classes)
+        .filter(|class| !exports.contains(&class.class_name))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        let list = missing
+            .iter()
+            .map(|class| format!("- {} (binding {})", class.class_name, class.binding))
+            .collect::<Vec<_>>();
+        let names = missing
+            .iter()
+            .map(|class| class.class_name.as_str())
+            .collect::<Vec<_>>();
+        anyhow::bail!(
+            "These Durable Object classes are not exported by the main module {}:\n{}\nExport them from it, e.g. with `export {{ {} }}`",
+            manifest.main,
+            list.join("\n"),
+            names.join(", ")
+        )
+    }
+    Ok(())
+}
+
+/// Lists the names exported by an ES module. This looks for `export` statements rather than
+/// parsing JavaScript, which is enough for the output of bundlers. Returns `None` when the
+/// module re-exports every name of another module with `export * from`, since they cannot be
+/// known without following it.
+fn module_exports(source: &str) -> Option<HashSet<String>> {
+    let declaration =
+        Regex::new(r"\bexport\s+(?:async\s+)?(?:class|function\*?|const|let|var)\s+([\w$]+)")
+            .unwrap();
+    let list = Regex::new(r"\bexport\s*\{([^}]*)\}").unwrap();
+    let namespace = Regex::new(r"\bexport\s*\*\s*(?:as\s+([\w$]+)\s*)?from\b").unwrap();
+
+    let mut exports = HashSet::new();
+    for captures in namespace.captures_iter(source) {
+        exports.insert(captures.get(1)?.as_str().to_string());
+    }
+    for captures in declaration.captures_iter(source) {
+        exports.insert(captures[1].to_string());
+    }
+    for captures in list.captures_iter(source) {
+        for specifier in captures[1].split(',') {
+            // `local as exported`
+            if let Some(name) = specifier.split_whitespace().last() {
+                exports.insert(name.to_string());
+            }
+        }
+    }
+    Some(exports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_finds_the_exports_of_a_module() {
+        let source = r#"
+export class Counter {}
+export async function handle() {}
+class Room {}
+const worker = { fetch() {} };
+export { Room as ChatRoom, worker as default };
+export * as utils from "./utils.mjs";
+"#;
+        let exports = module_exports(source).unwrap();
+        let mut exports = exports.iter().map(String::as_str).collect::<Vec<_>>();
+        exports.sort_unstable();
+        assert_eq!(
+            exports,
+            vec!["ChatRoom", "Counter", "default", "handle", "utils"]
+        );
+
+        assert_eq!(module_exports("export * from \"./counter.mjs\";"), None);
+    }
+}
@@ -1,3 +1,4 @@
+mod exports;
 mod modules_worker;
 mod plain_text;
 mod project_assets;
@@ -11,9 +12,12 @@ use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
 
+use crate::build::wasi;
+use crate::bundler;
 use crate::settings::binding;
 use crate::settings::toml::{Target, TargetType, UploadFormat, UsageModel};
-use crate::sites::AssetManifest;
+use crate::sites::{AssetManifest, SiteSettings, SETTINGS_BINDING};
+use crate::terminal::message::{Message, StdOut};
 use crate::wranglerjs;
 
 use plain_text::PlainText;
@@ -23,18 +27,22 @@ use text_blob::TextBlob;
 use wasm_module::WasmModule;
 
 // TODO: https://github.com/cloudflare/wrangler/issues/1083
-use super::{krate, Package};
+use super::krate;
 
+/// The form of the upload of a worker. `script_prelude` is put before the script of a service
+/// worker, which `wrangler dev` uses to change the bindings that the worker sees.
 pub fn build(
     target: &Target,
     asset_manifest: Option<AssetManifest>,
     session_config: Option<serde_json::Value>,
+    script_prelude: Option<&str>,
 ) -> Result<Form> {
     let target_type = &target.target_type;
     let compatibility_date = target.compatibility_date.clone();
     let compatibility_flags = target.compatibility_flags.clone();
     let kv_namespaces = &target.kv_namespaces;
     let r2_buckets = &target.r2_buckets;
+    let services = &target.services;
     let durable_object_classes = target
         .durable_objects
         .as_ref()
@@ -44,6 +52,7 @@ pub fn build(
     let mut plain_texts: Vec<PlainText> = Vec::new();
     let mut wasm_modules: Vec<WasmModule> = Vec::new();
     let usage_model = target.usage_model;
+    let script_prelude = script_prelude.map(str::to_string);
 
     if let Some(blobs) = &target.text_blobs {
         for (key, blob_path) in blobs.iter() {
@@ -72,10 +81,40 @@ pub fn build(
         text_blobs.push(text_blob);
     }
 
-    match target_type {
-        TargetType::Rust => {
+    if let Some(site) = &target.site {
+        let settings = SiteSettings::new(site)?;
+        text_blobs.push(TextBlob::new(
+            settings.to_json()?,
+            SETTINGS_BINDING.to_string(),
+        )?);
+    }
+
+    if let Some(module) = wasi::module(target)? {
+        log::info!("WASI module detected. Publishing...");
+        wasm_modules.push(WasmModule::new(module, "wasm".to_string())?);
+        let assets = ServiceWorkerAssets {
+            script_path: wasi::write_shim()?,
+            compatibility_date,
+            compatibility_flags,
+            wasm_modules,
+            kv_namespaces: kv_namespaces.to_vec(),
+            r2_buckets: r2_buckets.to_vec(),
+            services: services.to_vec(),
+            durable_object_classes,
+            text_blobs,
+            plain_texts,
+            usage_model,
+            script_prelude,
+        };
+
+        return service_worker::build_form(&assets, session_config);
+    }
+
+    // a project built by its own command is uploaded like a javascript project
+    match (target_type, target.custom_build()) {
+        (TargetType::Rust, None) => {
             log::info!("Rust project detected. Publishing...");
-            let name = krate::Krate::new("./")?.name.replace("-", "_");
+            let name = krate::Krate::for_target(target)?.name.replace("-", "_");
             // TODO: move into build?
             build_generated_dir()?;
             concat_js(&name)?;
@@ -93,21 +132,21 @@ pub fn build(
                 wasm_modules,
                 kv_namespaces: kv_namespaces.to_vec(),
                 r2_buckets: r2_buckets.to_vec(),
+                services: services.to_vec(),
                 durable_object_classes,
                 text_blobs,
                 plain_texts,
                 usage_model,
+                script_prelude,
             };
 
             service_worker::build_form(&assets, session_config)
         }
-        TargetType::JavaScript => match &target.build {
+        (TargetType::JavaScript, _) | (_, Some(_)) => match &target.build {
             Some(config) => match &config.upload {
                 UploadFormat::ServiceWorker {} => {
                     log::info!("Plain JavaScript project detected. Publishing...");
-                    let package_dir = target.package_dir()?;
-                    let package = Package::new(&package_dir)?;
-                    let script_path = package_dir.join(package.main(&package_dir)?);
+                    let script_path = bundler::script_path(target)?;
 
                     let assets = ServiceWorkerAssets {
                         script_path,
@@ -116,15 +155,18 @@ pub fn build(
                         wasm_modules,
                         kv_namespaces: kv_namespaces.to_vec(),
                         r2_buckets: r2_buckets.to_vec(),
+                        services: services.to_vec(),
                         durable_object_classes,
                         text_blobs,
                         plain_texts,
                         usage_model,
+                        script_prelude,
                     };
 
                     service_worker::build_form(&assets, session_config)
                 }
                 UploadFormat::Modules { main, dir, rules } => {
+                    warn_if_prelude_is_ignored(&script_prelude);
                     let migration = match &target.migrations {
                         Some(migrations) => migrations.api_migration()?,
                         None => None,
@@ -137,6 +179,7 @@ pub fn build(
                         module_config.get_modules()?,
                         kv_namespaces.to_vec(),
                         r2_buckets.to_vec(),
+                        services.to_vec(),
                         durable_object_classes,
                         migration,
                         text_blobs,
@@ -149,9 +192,7 @@ pub fn build(
             },
             None => {
                 log::info!("Plain JavaScript project detected. Publishing...");
-                let package_dir = target.package_dir()?;
-                let package = Package::new(&package_dir)?;
-                let script_path = package.main(&package_dir)?;
+                let script_path = bundler::script_path(target)?;
 
                 let assets = ServiceWorkerAssets {
                     script_path,
@@ -160,16 +201,46 @@ pub fn build(
                     wasm_modules,
                     kv_namespaces: kv_namespaces.to_vec(),
                     r2_buckets: r2_buckets.to_vec(),
+                    services: services.to_vec(),
                     durable_object_classes,
                     text_blobs,
                     plain_texts,
                     usage_model,
+                    script_prelude,
                 };
 
                 service_worker::build_form(&assets, session_config)
             }
         },
-        TargetType::Webpack => {
+        (TargetType::Webpack, None) if target.entries.is_some() => {
+            log::info!("webpack project with entries detected. Publishing...");
+            warn_if_prelude_is_ignored(&script_prelude);
+            let migration = match &target.migrations {
+                Some(migrations) => migrations.api_migration()?,
+                None => None,
+            };
+
+            let package_dir = target.package_dir()?;
+            let modules_dir = wranglerjs::Bundle::new(&package_dir).modules_dir();
+            let main = format!("./{}", bundler::MAIN_MODULE);
+            let module_config = ModuleConfig::new(&main, &modules_dir, &None);
+            let assets = ModulesAssets::new(
+                compatibility_date,
+                compatibility_flags,
+                module_config.get_modules()?,
+                kv_namespaces.to_vec(),
+                r2_buckets.to_vec(),
+                services.to_vec(),
+                durable_object_classes,
+                migration,
+                text_blobs,
+                plain_texts,
+                usage_model,
+            )?;
+
+            modules_worker::build_form(&assets, session_config)
+        }
+        (TargetType::Webpack, None) => {
             log::info!("webpack project detected. Publishing...");
             // TODO: https://github.com/cloudflare/wrangler/issues/850
             let package_dir = target.package_dir()?;
@@ -191,10 +262,12 @@ pub fn build(
                 wasm_modules,
                 kv_namespaces: kv_namespaces.to_vec(),
                 r2_buckets: r2_buckets.to_vec(),
+                services: services.to_vec(),
                 durable_object_classes,
                 text_blobs,
                 plain_texts,
                 usage_model,
+                script_prelude,
             };
 
             service_worker::build_form(&assets, session_config)
@@ -202,6 +275,13 @@ pub fn build(
     }
 }
 
+// The bindings of modules are passed to their handlers, which a prelude can't change
+fn warn_if_prelude_is_ignored(script_prelude: &Option<String>) {
+    if script_prelude.is_some() {
+        StdOut::warn("Workers in the modules format are not delayed by `kv_propagation_delay`, nor limited by `subrequest_limit`");
+    }
+}
+
 fn get_asset_manifest_blob(asset_manifest: AssetManifest) -> Result<String> {
     let asset_manifest = serde_json::to_string(&asset_manifest)?;
     Ok(asset_manifest)
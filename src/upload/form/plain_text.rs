@@ -1,19 +1,46 @@
 use super::binding::Binding;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
+/// A variable of `[vars]`. Strings are bound as plain text, and any other value (numbers,
+/// booleans, arrays or tables) as JSON, so workers get them back with their types.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct PlainText {
     pub name: String,
-    pub value: String,
+    pub value: Value,
 }
 
 impl PlainText {
-    pub fn new(name: String, value: String) -> Result<Self> {
+    pub fn new(name: String, value: Value) -> Result<Self> {
         Ok(Self { name, value })
     }
 
     pub fn binding(&self) -> Binding {
-        Binding::new_plain_text(self.name.clone(), self.value.clone())
+        match &self.value {
+            Value::String(text) => Binding::new_plain_text(self.name.clone(), text.clone()),
+            json => Binding::new_json(self.name.clone(), json.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn it_binds_structured_values_as_json() {
+        let text = PlainText::new("REGION".to_string(), json!("eu")).unwrap();
+        assert_eq!(
+            serde_json::to_value(text.binding()).unwrap(),
+            json!({ "type": "plain_text", "name": "REGION", "text": "eu" })
+        );
+
+        let limits = PlainText::new("LIMITS".to_string(), json!({ "rps": 10 })).unwrap();
+        assert_eq!(
+            serde_json::to_value(limits.binding()).unwrap(),
+            json!({ "type": "json", "name": "LIMITS", "json": { "rps": 10 } })
+        );
     }
 }
@@ -7,6 +7,7 @@ use path_slash::PathExt; // Path::to_slash()
 use serde::{Deserialize, Serialize};
 
 use super::binding::Binding;
+use super::exports;
 use super::filestem_from_path;
 use super::plain_text::PlainText;
 use super::text_blob::TextBlob;
@@ -15,6 +16,7 @@ use super::UsageModel;
 
 use crate::settings::toml::{
     migrations::ApiMigration, DurableObjectsClass, KvNamespace, ModuleRule, R2Bucket,
+    ServiceBinding,
 };
 use std::collections::{HashMap, HashSet};
 
@@ -26,10 +28,13 @@ pub struct ServiceWorkerAssets {
     pub wasm_modules: Vec<WasmModule>,
     pub kv_namespaces: Vec<KvNamespace>,
     pub r2_buckets: Vec<R2Bucket>,
+    pub services: Vec<ServiceBinding>,
     pub durable_object_classes: Vec<DurableObjectsClass>,
     pub text_blobs: Vec<TextBlob>,
     pub plain_texts: Vec<PlainText>,
     pub usage_model: Option<UsageModel>,
+    /// The script put before the one of `script_path`.
+    pub script_prelude: Option<String>,
 }
 
 impl ServiceWorkerAssets {
@@ -48,6 +53,10 @@ impl ServiceWorkerAssets {
             let binding = r2.binding();
             bindings.push(binding);
         }
+        for service in &self.services {
+            let binding = service.binding();
+            bindings.push(binding);
+        }
         for do_ns in &self.durable_object_classes {
             let binding = do_ns.binding();
             bindings.push(binding);
@@ -322,6 +331,7 @@ pub struct ModulesAssets {
     pub manifest: ModuleManifest,
     pub kv_namespaces: Vec<KvNamespace>,
     pub r2_buckets: Vec<R2Bucket>,
+    pub services: Vec<ServiceBinding>,
     pub durable_object_classes: Vec<DurableObjectsClass>,
     pub migration: Option<ApiMigration>,
     pub text_blobs: Vec<TextBlob>,
@@ -337,18 +347,22 @@ impl ModulesAssets {
         manifest: ModuleManifest,
         kv_namespaces: Vec<KvNamespace>,
         r2_buckets: Vec<R2Bucket>,
+        services: Vec<ServiceBinding>,
         durable_object_classes: Vec<DurableObjectsClass>,
         migration: Option<ApiMigration>,
         text_blobs: Vec<TextBlob>,
         plain_texts: Vec<PlainText>,
         usage_model: Option<UsageModel>,
     ) -> Result<Self> {
+        exports::check_modules(&manifest, &durable_object_classes)?;
+
         Ok(Self {
             compatibility_date,
             compatibility_flags,
             manifest,
             kv_namespaces,
             r2_buckets,
+            services,
             durable_object_classes,
             migration,
             text_blobs,
@@ -371,6 +385,10 @@ impl ModulesAssets {
             let binding = r2.binding();
             bindings.push(binding);
         }
+        for service in &self.services {
+            let binding = service.binding();
+            bindings.push(binding);
+        }
         for class in &self.durable_object_classes {
             let binding = class.binding();
             bindings.push(binding);
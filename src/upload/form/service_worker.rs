@@ -1,10 +1,12 @@
+use std::fs;
+
 use anyhow::Result;
 use reqwest::blocking::multipart::{Form, Part};
 use serde::Serialize;
 
 use crate::settings::binding::Binding;
 
-use super::{ServiceWorkerAssets, UsageModel};
+use super::{exports, ServiceWorkerAssets, UsageModel};
 
 #[derive(Serialize, Debug)]
 struct Metadata {
@@ -21,6 +23,8 @@ pub fn build_form(
     assets: &ServiceWorkerAssets,
     session_config: Option<serde_json::Value>,
 ) -> Result<Form> {
+    exports::check_service_worker(&assets.durable_object_classes)?;
+
     let mut form = Form::new();
 
     // The preview service in particular streams the request form, and requires that the
@@ -38,7 +42,19 @@ pub fn build_form(
 }
 
 fn add_files(mut form: Form, assets: &ServiceWorkerAssets) -> Result<Form> {
-    form = form.file(assets.script_name()?, assets.script_path())?;
+    form = match &assets.script_prelude {
+        Some(prelude) => {
+            let script_path = assets.script_path();
+            let script = fs::read_to_string(&script_path)?;
+            let mut part =
+                Part::text(format!("{}{}", prelude, script)).mime_str("application/javascript")?;
+            if let Some(file_name) = script_path.file_name() {
+                part = part.file_name(file_name.to_string_lossy().into_owned());
+            }
+            form.part(assets.script_name()?, part)
+        }
+        None => form.file(assets.script_name()?, assets.script_path())?,
+    };
 
     for wasm_module in &assets.wasm_modules {
         form = form.file(wasm_module.filename(), wasm_module.path())?;
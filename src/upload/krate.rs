@@ -1,12 +1,18 @@
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
 use anyhow::Result;
 use serde::{self, Deserialize};
 
+use crate::settings::toml::Target;
+
 #[derive(Debug, Deserialize)]
 pub struct Krate {
     pub name: String,
+    /// The directory of the crate, which wasm-pack builds.
+    #[serde(skip)]
+    pub dir: PathBuf,
 }
 
 #[derive(Debug, Deserialize)]
@@ -14,7 +20,46 @@ struct KrateManifest {
     pub package: Krate,
 }
 
+/// The part of the output of `cargo metadata` that locates the members of a workspace.
+#[derive(Debug, Deserialize)]
+struct WorkspaceMetadata {
+    packages: Vec<WorkspacePackage>,
+    workspace_root: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkspacePackage {
+    name: String,
+    manifest_path: PathBuf,
+    #[serde(default)]
+    dependencies: Vec<WorkspaceDependency>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkspaceDependency {
+    /// The directory of a path dependency.
+    path: Option<PathBuf>,
+}
+
+/// The local files that the crates of a cargo workspace are built from.
+#[derive(Debug)]
+pub struct LocalSources {
+    /// The root of the workspace, whose `Cargo.lock` pins the dependencies.
+    pub workspace_root: PathBuf,
+    /// The directories of the members of the workspace and of their path dependencies.
+    pub crate_dirs: Vec<PathBuf>,
+}
+
 impl Krate {
+    /// The crate of a Rust project: the member set in [build.rust] of the cargo workspace in the
+    /// current directory, or else the crate in the current directory.
+    pub fn for_target(target: &Target) -> Result<Krate> {
+        match target.build.as_ref().and_then(|build| build.rust.as_ref()) {
+            Some(rust) => Krate::workspace_member("./", &rust.package),
+            None => Krate::new("./"),
+        }
+    }
+
     pub fn new(krate_path: &str) -> Result<Krate> {
         let manifest_path = Path::new(krate_path).join("Cargo.toml");
         if !manifest_path.is_file() {
@@ -28,6 +73,72 @@ impl Krate {
         let cargo_toml: String = fs::read_to_string(manifest_path)?.parse()?;
         let krate: KrateManifest = toml::from_str(&cargo_toml)?;
 
-        Ok(krate.package)
+        Ok(Krate {
+            dir: PathBuf::from(krate_path),
+            ..krate.package
+        })
+    }
+
+    /// Finds the member `package` of the cargo workspace at `workspace_path` with
+    /// `cargo metadata`, which knows where members are even when they are globbed.
+    pub fn workspace_member(workspace_path: &str, package: &str) -> Result<Krate> {
+        match metadata(workspace_path)?
+            .packages
+            .into_iter()
+            .find(|member| member.name == package)
+        {
+            Some(member) => Ok(Krate {
+                name: member.name,
+                dir: member
+                    .manifest_path
+                    .parent()
+                    .map_or_else(|| PathBuf::from(workspace_path), Path::to_path_buf),
+            }),
+            None => anyhow::bail!(
+                "`{}` in [build.rust] is not a member of the cargo workspace in `{}`",
+                package,
+                workspace_path
+            ),
+        }
+    }
+}
+
+/// The local sources of the cargo workspace of the crate at `krate_path`, which may be outside of
+/// it, as path dependencies and the root of a workspace of which the crate is a member are.
+pub fn local_sources(krate_path: &str) -> Result<LocalSources> {
+    let metadata = metadata(krate_path)?;
+    let mut crate_dirs = Vec::new();
+    for package in metadata.packages {
+        if let Some(dir) = package.manifest_path.parent() {
+            crate_dirs.push(dir.to_path_buf());
+        }
+        crate_dirs.extend(
+            package
+                .dependencies
+                .into_iter()
+                .filter_map(|dependency| dependency.path),
+        );
+    }
+    crate_dirs.sort();
+    crate_dirs.dedup();
+    Ok(LocalSources {
+        workspace_root: metadata.workspace_root,
+        crate_dirs,
+    })
+}
+
+/// The members of the cargo workspace at `workspace_path`, without resolving their dependencies.
+fn metadata(workspace_path: &str) -> Result<WorkspaceMetadata> {
+    let output = Command::new("cargo")
+        .args(&["metadata", "--no-deps", "--format-version", "1"])
+        .current_dir(workspace_path)
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "could not read the cargo workspace in `{}`:\n{}",
+            workspace_path,
+            String::from_utf8_lossy(&output.stderr)
+        )
     }
+    Ok(serde_json::from_slice(&output.stdout)?)
 }
@@ -1,5 +1,5 @@
 pub mod form;
-mod krate;
+pub mod krate;
 pub mod package;
 
 use indicatif::{ProgressBar, ProgressStyle};
@@ -22,7 +22,7 @@ pub fn script(
         target.name,
     );
 
-    let script_upload_form = form::build(target, asset_manifest, None)?;
+    let script_upload_form = form::build(target, asset_manifest, None, None)?;
 
     let style = ProgressStyle::default_spinner().template("{spinner}   {msg}");
     let spinner = ProgressBar::new_spinner().with_style(style);
@@ -1,26 +1,35 @@
 mod watcher;
 use ignore::overrides::OverrideBuilder;
 use ignore::WalkBuilder;
-pub use watcher::wait_for_changes;
+pub use watcher::{wait_for_changes, wait_for_changes_matching};
 
-use crate::settings::toml::{Target, TargetType};
+use crate::commands::dev::dev_vars;
+use crate::settings::toml::{Bundler, Target, TargetType};
 use crate::terminal::message::{Message, StdOut};
+use crate::upload::krate::Krate;
 use crate::wranglerjs;
-use crate::{build::command, build_target};
-use crate::{commands, install};
+use crate::{
+    build::command, build::use_cargo_cache, build::wasi, build::wasm_pack_args, build_target,
+};
+use crate::{bundler, commands, install};
 
 use anyhow::Result;
 use notify::{self, RecursiveMode, Watcher};
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::{self, SendError, Sender};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 pub const COOLDOWN_PERIOD: Duration = Duration::from_millis(2000);
 const JAVASCRIPT_PATH: &str = "./";
 const RUST_PATH: &str = "./";
 
 // Paths to ignore live watching in Rust Workers
-const RUST_IGNORE: &[&str] = &["pkg", "target", "worker/generated"];
+const RUST_IGNORE: &[&str] = &["pkg", "target", "worker/generated", ".wrangler"];
+
+// Paths to ignore live watching in webpack projects, the first of which holds the bundle
+const WEBPACK_IGNORE: &[&str] = &["worker", "node_modules", ".wrangler"];
 
 // watch a project for changes and re-build it when necessary,
 // outputting a build event to tx.
@@ -31,17 +40,35 @@ pub fn watch_and_build(
 ) -> Result<()> {
     let target_type = &target.target_type;
     let build = target.build.clone();
+    // the build command of any type of project re-runs on changes in watch_dir
+    if let Some(config) = target.custom_build() {
+        config.verify_watch_dir()?;
+        let target = target.clone();
+        let watch_dir = config.watch_dir.clone();
+        thread::spawn(move || build_on_changes_in(&target, &watch_dir, tx, refresh_session_sender));
+        return Ok(());
+    }
     match target_type {
         TargetType::JavaScript => {
+            // Checked here so that an invalid watch_dir fails the command, not the watcher thread
+            if let Some(config) = &build {
+                config.verify_watch_dir()?;
+            }
             let target = target.clone();
             thread::spawn::<_, Result<()>>(move || {
-                let (watcher_tx, watcher_rx) = mpsc::channel();
-                let mut watcher = notify::watcher(watcher_tx, Duration::from_secs(1))?;
-
                 match build {
+                    Some(config) => {
+                        build_on_changes_in(&target, &config.watch_dir, tx, refresh_session_sender)
+                    }
                     None => {
+                        let (watcher_tx, watcher_rx) = mpsc::channel();
+                        let mut watcher = notify::watcher(watcher_tx, Duration::from_secs(1))?;
                         watcher.watch(JAVASCRIPT_PATH, RecursiveMode::Recursive)?;
                         StdOut::info(&format!("watching {:?}", &JAVASCRIPT_PATH));
+                        // a TypeScript entry point is transpiled again, to a script that is
+                        // ignored like the bundle of webpack projects
+                        let transpiles = bundler::transpiles(&target);
+                        let root = fs::canonicalize(JAVASCRIPT_PATH)?;
 
                         loop {
                             match wait_for_changes(
@@ -49,7 +76,20 @@ pub fn watch_and_build(
                                 refresh_session_sender.clone(),
                                 COOLDOWN_PERIOD,
                             ) {
-                                Ok(_path) => {
+                                Ok(path) => {
+                                    // `wrangler dev` watches it on its own
+                                    if dev_vars::is_dev_vars(&path) {
+                                        continue;
+                                    }
+                                    if transpiles {
+                                        if is_ignored(&path, &root, WEBPACK_IGNORE) {
+                                            continue;
+                                        }
+                                        if let Err(e) = build_target(&target) {
+                                            StdOut::user_error(&e.to_string());
+                                            continue;
+                                        }
+                                    }
                                     if let Some(tx) = tx.clone() {
                                         tx.send(())?;
                                     }
@@ -63,40 +103,19 @@ pub fn watch_and_build(
                             }
                         }
                     }
-                    Some(config) => {
-                        config.verify_watch_dir()?;
-                        watcher.watch(config.watch_dir, notify::RecursiveMode::Recursive)?;
-
-                        loop {
-                            match wait_for_changes(
-                                &watcher_rx,
-                                refresh_session_sender.clone(),
-                                COOLDOWN_PERIOD,
-                            ) {
-                                Ok(_path) => match build_target(&target) {
-                                    Ok(output) => {
-                                        StdOut::success(&output);
-                                        if let Some(tx) = tx.clone() {
-                                            tx.send(())?;
-                                        }
-                                    }
-                                    Err(e) => StdOut::user_error(&e.to_string()),
-                                },
-                                Err(e) => {
-                                    if !e.is::<SendError<Option<()>>>() {
-                                        log::debug!("{:?}", e);
-                                        StdOut::user_error("Something went wrong while watching.")
-                                    }
-                                }
-                            }
-                        }
-                    }
                 }
             });
         }
         TargetType::Rust => {
-            let binary_path = install::install_wasm_pack()?;
-            let args = ["build", "--target", "no-modules"];
+            // WASI modules are built by cargo, which the build of the target runs
+            let wasm_pack = if wasi::is_enabled(target) {
+                None
+            } else {
+                let binary_path = install::install_wasm_pack()?;
+                let args = wasm_pack_args(target, &Krate::for_target(target)?)?;
+                Some((binary_path, args))
+            };
+            let target = target.clone();
 
             thread::spawn::<_, Result<()>>(move || {
                 let (watcher_tx, watcher_rx) = mpsc::channel();
@@ -132,9 +151,22 @@ pub fn watch_and_build(
                         COOLDOWN_PERIOD,
                     ) {
                         Ok(_path) => {
-                            let command = command(&args, &binary_path);
-                            let command_name = format!("{:?}", command);
-                            if commands::run(command, &command_name).is_ok() {
+                            let built = match &wasm_pack {
+                                Some((binary_path, args)) => {
+                                    let mut command = command(args, binary_path);
+                                    use_cargo_cache(&mut command);
+                                    let command_name = format!("{:?}", command);
+                                    commands::run(command, &command_name).is_ok()
+                                }
+                                None => match build_target(&target) {
+                                    Ok(_) => true,
+                                    Err(e) => {
+                                        StdOut::user_error(&e.to_string());
+                                        false
+                                    }
+                                },
+                            };
+                            if built {
                                 if let Some(tx) = tx.clone() {
                                     tx.send(())?;
                                 }
@@ -149,6 +181,53 @@ pub fn watch_and_build(
                 }
             });
         }
+        TargetType::Webpack if target.bundler == Bundler::Builtin => {
+            let target = target.clone();
+            let package_dir = target.package_dir()?;
+            thread::spawn::<_, Result<()>>(move || {
+                let (watcher_tx, watcher_rx) = mpsc::channel();
+                let mut watcher = notify::watcher(watcher_tx, Duration::from_secs(1))?;
+
+                watcher.watch(&package_dir, RecursiveMode::Recursive)?;
+                StdOut::info(&format!("watching {:?}", &package_dir));
+                if let Some(site) = &target.site {
+                    if Path::new(&site.bucket).exists() {
+                        watcher.watch(&site.bucket, RecursiveMode::Recursive)?;
+                        log::info!("watching static sites asset file {:?}", &site.bucket);
+                    }
+                }
+
+                loop {
+                    match wait_for_changes(
+                        &watcher_rx,
+                        refresh_session_sender.clone(),
+                        COOLDOWN_PERIOD,
+                    ) {
+                        Ok(path) => {
+                            // The bundle written by the previous build changes worker/
+                            if is_ignored(&path, &package_dir, WEBPACK_IGNORE) {
+                                continue;
+                            }
+                            match build_target(&target) {
+                                Ok(output) => {
+                                    StdOut::success(&output);
+                                    if let Some(tx) = tx.clone() {
+                                        tx.send(())?;
+                                    }
+                                }
+                                Err(e) => StdOut::user_error(&e.to_string()),
+                            }
+                        }
+                        Err(e) => {
+                            if !e.is::<SendError<Option<()>>>() {
+                                log::debug!("{:?}", e);
+                                StdOut::user_error("Something went wrong while watching.")
+                            }
+                        }
+                    }
+                }
+            });
+        }
         TargetType::Webpack => {
             wranglerjs::run_build_and_watch(target, tx)?;
         }
@@ -156,3 +235,101 @@ pub fn watch_and_build(
 
     Ok(())
 }
+
+// re-build a project every time something in `watch_dir` changes, as `[build]` of a
+// project built by its own command says, outputting a build event to tx.
+fn build_on_changes_in(
+    target: &Target,
+    watch_dir: &Path,
+    tx: Option<mpsc::Sender<()>>,
+    refresh_session_sender: Option<Sender<Option<()>>>,
+) -> Result<()> {
+    let (watcher_tx, watcher_rx) = mpsc::channel();
+    let mut watcher = notify::watcher(watcher_tx, Duration::from_secs(1))?;
+    watcher.watch(watch_dir, RecursiveMode::Recursive)?;
+    StdOut::info(&format!("watching {:?}", watch_dir));
+
+    loop {
+        match wait_for_changes(&watcher_rx, refresh_session_sender.clone(), COOLDOWN_PERIOD) {
+            Ok(_path) => match build_target(target) {
+                Ok(output) => {
+                    StdOut::success(&output);
+                    if let Some(tx) = tx.clone() {
+                        tx.send(())?;
+                    }
+                }
+                Err(e) => StdOut::user_error(&e.to_string()),
+            },
+            Err(e) => {
+                if !e.is::<SendError<Option<()>>>() {
+                    log::debug!("{:?}", e);
+                    StdOut::user_error("Something went wrong while watching.")
+                }
+            }
+        }
+    }
+}
+
+// build a project, then re-build it every time its sources change, reporting
+// how long each build took. Unlike watch_and_build this blocks, and every
+// build goes through build_target.
+pub fn build_on_changes(target: &Target) -> Result<()> {
+    let (root, ignore) = match (&target.target_type, target.custom_build()) {
+        (_, Some(config)) => {
+            config.verify_watch_dir()?;
+            (config.watch_dir.clone(), &[][..])
+        }
+        (TargetType::JavaScript, None) if bundler::transpiles(target) => {
+            (target.package_dir()?, WEBPACK_IGNORE)
+        }
+        (TargetType::JavaScript, None) => {
+            timed_build(target);
+            StdOut::info("There is nothing to rebuild without a `command` in [build], so not watching for changes");
+            return Ok(());
+        }
+        (TargetType::Rust, None) => (PathBuf::from(RUST_PATH), RUST_IGNORE),
+        (TargetType::Webpack, None) => (target.package_dir()?, WEBPACK_IGNORE),
+    };
+    // notify reports absolute paths, which the ignored paths are matched against
+    let root = fs::canonicalize(root)?;
+
+    timed_build(target);
+
+    let (watcher_tx, watcher_rx) = mpsc::channel();
+    let mut watcher = notify::watcher(watcher_tx, Duration::from_secs(1))?;
+    // the root is watched so that the files and directories created in it are
+    // too, and the changes of ignored paths, like those of the build itself,
+    // neither start a build nor delay one
+    watcher.watch(&root, RecursiveMode::Recursive)?;
+    StdOut::info(&format!("watching {:?}", &root));
+
+    loop {
+        match wait_for_changes_matching(&watcher_rx, None, COOLDOWN_PERIOD, |path| {
+            !is_ignored(path, &root, ignore)
+        }) {
+            Ok(_path) => timed_build(target),
+            Err(e) => {
+                log::debug!("{:?}", e);
+                StdOut::user_error("Something went wrong while watching.")
+            }
+        }
+    }
+}
+
+fn timed_build(target: &Target) {
+    let start = Instant::now();
+    match build_target(target) {
+        Ok(output) => StdOut::success(&format!("{} ({:.2?})", output, start.elapsed())),
+        Err(e) => StdOut::user_error(&format!(
+            "Build failed after {:.2?}: {}",
+            start.elapsed(),
+            e
+        )),
+    }
+}
+
+fn is_ignored(path: &Path, root: &Path, ignore: &[&str]) -> bool {
+    path.strip_prefix(root).map_or(false, |path| {
+        ignore.iter().any(|ignore| path.starts_with(ignore))
+    })
+}
@@ -1,8 +1,8 @@
 use notify::DebouncedEvent;
 use std::{
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::mpsc::{Receiver, Sender},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use anyhow::{anyhow, Result};
@@ -15,6 +15,17 @@ pub fn wait_for_changes(
     rx: &Receiver<DebouncedEvent>,
     check_channel: Option<Sender<Option<()>>>,
     cooldown: Duration,
+) -> Result<PathBuf> {
+    wait_for_changes_matching(rx, check_channel, cooldown, |_| true)
+}
+
+// Like wait_for_changes, but only the changes of paths that `matches` accepts
+// are detected, or extend the cooldown; the others are dropped.
+pub fn wait_for_changes_matching(
+    rx: &Receiver<DebouncedEvent>,
+    check_channel: Option<Sender<Option<()>>>,
+    cooldown: Duration,
+    matches: impl Fn(&Path) -> bool,
 ) -> Result<PathBuf> {
     loop {
         let event = rx.recv()?;
@@ -24,12 +35,28 @@ pub fn wait_for_changes(
             check_channel.send(None)?;
         }
         match get_changed_path_from_event(event) {
-            Ok(Some(path)) => {
+            Ok(Some(path)) if matches(&path) => {
                 StdOut::working("Detected changes...");
                 // wait for cooldown
-                while rx.recv_timeout(cooldown).is_ok() {}
+                let mut deadline = Instant::now() + cooldown;
+                loop {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    match rx.recv_timeout(timeout) {
+                        Ok(event) => {
+                            if let Ok(Some(path)) = get_changed_path_from_event(event) {
+                                if matches(&path) {
+                                    deadline = Instant::now() + cooldown;
+                                }
+                            }
+                        }
+                        Err(_) => break,
+                    }
+                }
                 return Ok(path);
             }
+            Ok(Some(_)) => {
+                continue; // a change of a path we don't care about
+            }
             Ok(None) => {
                 continue; // was an event type we don't care about, continue
             }
@@ -53,3 +80,32 @@ fn get_changed_path_from_event(event: DebouncedEvent) -> Result<Option<PathBuf>>
         _ => Ok(None),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn it_only_detects_the_changes_of_matching_paths() {
+        let (tx, rx) = mpsc::channel();
+        tx.send(DebouncedEvent::Create(PathBuf::from(
+            "/app/worker/script.js",
+        )))
+        .unwrap();
+        tx.send(DebouncedEvent::Write(PathBuf::from("/app/src/index.js")))
+            .unwrap();
+        tx.send(DebouncedEvent::Write(PathBuf::from(
+            "/app/worker/script.js",
+        )))
+        .unwrap();
+        drop(tx);
+
+        let path = wait_for_changes_matching(&rx, None, Duration::from_millis(10), |path| {
+            !path.starts_with("/app/worker")
+        })
+        .unwrap();
+        assert_eq!(path, PathBuf::from("/app/src/index.js"));
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -82,6 +82,11 @@ impl Bundle {
     pub fn script_path(&self) -> PathBuf {
         PathBuf::from(&self.out).join("script.js")
     }
+
+    /// Where the modules of a bundle split by the builtin bundler are written.
+    pub fn modules_dir(&self) -> PathBuf {
+        PathBuf::from(&self.out).join("modules")
+    }
 }
 
 #[cfg(test)]
@@ -105,6 +110,8 @@ mod tests {
         let out = create_temp_dir("it_writes_the_bundle_script");
         let wranglerjs_output = WranglerjsOutput {
             errors: vec![],
+            polyfills: vec![],
+            modules: vec![],
             script: "foo".to_string(),
             wasm: None,
         };
@@ -122,6 +129,8 @@ mod tests {
         let out = create_temp_dir("it_writes_the_bundle_wasm");
         let wranglerjs_output = WranglerjsOutput {
             errors: vec![],
+            polyfills: vec![],
+            modules: vec![],
             script: "".to_string(),
             wasm: Some("abc".to_string()),
         };
@@ -138,6 +147,8 @@ mod tests {
     fn it_has_errors() {
         let wranglerjs_output = WranglerjsOutput {
             errors: vec!["a".to_string(), "b".to_string()],
+            polyfills: vec![],
+            modules: vec![],
             script: "".to_string(),
             wasm: None,
         };
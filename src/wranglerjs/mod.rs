@@ -22,10 +22,11 @@ use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 use semver::Version;
 
+use crate::build::define;
+use crate::build::diagnostics::{self, BuildError};
 use crate::install;
 use crate::settings::toml::Target;
 use crate::terminal::message::{Message, StdErr, StdOut};
-use crate::upload::package::Package;
 use crate::watch::{wait_for_changes, COOLDOWN_PERIOD};
 
 use guarded_command::GuardedCommand;
@@ -36,8 +37,8 @@ use guarded_command::GuardedCommand;
 // executable and wait for completion. The file will receive a serialized
 // {WranglerjsOutput} struct.
 // Note that the ability to pass a fd is platform-specific
-pub fn run_build(target: &Target) -> Result<WranglerjsOutput> {
-    let (mut command, temp_file, bundle) = setup_build(target)?;
+pub fn run_build(target: &Target, defines: &[(String, String)]) -> Result<WranglerjsOutput> {
+    let (mut command, temp_file, bundle) = setup_build(target, defines)?;
 
     log::info!("Running {:?}", command);
 
@@ -51,6 +52,9 @@ pub fn run_build(target: &Target) -> Result<WranglerjsOutput> {
 
         let custom_webpack = target.webpack_config.is_some();
         write_wranglerjs_output(&bundle, &wranglerjs_output, custom_webpack)?;
+        if target.node_compat {
+            StdOut::info(&wranglerjs_output.polyfills_message());
+        }
         Ok(wranglerjs_output)
     } else {
         anyhow::bail!("failed to execute `{:?}`: exited with {}", command, status)
@@ -58,7 +62,9 @@ pub fn run_build(target: &Target) -> Result<WranglerjsOutput> {
 }
 
 pub fn run_build_and_watch(target: &Target, tx: Option<Sender<()>>) -> Result<()> {
-    let (mut command, temp_file, bundle) = setup_build(target)?;
+    // webpack watches the sources, with the values of [build.define] of when it started
+    let defines = define::definitions(target)?;
+    let (mut command, temp_file, bundle) = setup_build(target, &defines)?;
     command.arg("--watch=1");
 
     let is_site = target.site.clone();
@@ -129,16 +135,14 @@ fn write_wranglerjs_output(
     custom_webpack: bool,
 ) -> Result<()> {
     if output.has_errors() {
-        StdErr::user_error(output.get_errors().as_str());
-        if custom_webpack {
-            anyhow::bail!(
+        let errors = output.get_errors();
+        StdErr::user_error(&errors);
+        let message = if custom_webpack {
             "webpack returned an error. Try configuring `entry` in your webpack config relative to the current working directory, or setting `context = __dirname` in your webpack config."
-        );
         } else {
-            anyhow::bail!(
             "webpack returned an error. You may be able to resolve this issue by running npm install."
-        );
-        }
+        };
+        return Err(BuildError::new(message.to_string(), diagnostics::parse(&errors)).into());
     }
 
     bundle.write(output)?;
@@ -151,7 +155,10 @@ fn write_wranglerjs_output(
 }
 
 //setup a build to run wranglerjs, return the command, the ipc temp file, and the bundle
-fn setup_build(target: &Target) -> Result<(Command, PathBuf, Bundle)> {
+fn setup_build(
+    target: &Target,
+    defines: &[(String, String)],
+) -> Result<(Command, PathBuf, Bundle)> {
     for tool in &["node", "npm"] {
         env_dep_installed(tool)?;
     }
@@ -187,6 +194,21 @@ fn setup_build(target: &Target) -> Result<(Command, PathBuf, Bundle)> {
 
     command.arg(format!("--wasm-binding={}", bundle.get_wasm_binding()));
 
+    if target.node_compat {
+        command.arg("--node-compat=1");
+    }
+
+    if !defines.is_empty() {
+        let definitions: serde_json::Map<String, serde_json::Value> = defines
+            .iter()
+            .map(|(key, code)| (key.clone(), serde_json::Value::String(code.clone())))
+            .collect();
+        command.arg(format!(
+            "--define={}",
+            serde_json::Value::Object(definitions)
+        ));
+    }
+
     let custom_webpack_config_path = match &target.webpack_config {
         Some(webpack_config) => Some(PathBuf::from(&webpack_config)),
         None => {
@@ -201,9 +223,15 @@ fn setup_build(target: &Target) -> Result<(Command, PathBuf, Bundle)> {
     // if webpack_config is not configured in the manifest
     // we infer the entry based on {package.json} and pass it to {wranglerjs}
     if let Some(webpack_config_path) = custom_webpack_config_path {
+        if let Some(main) = &target.main {
+            StdOut::warn(&format!(
+                "`main` ({}) is ignored because the entry of the build is set by your webpack configuration",
+                main.display()
+            ));
+        }
         build_with_custom_webpack(&mut command, &webpack_config_path);
     } else {
-        build_with_default_webpack(&mut command, &package_dir)?;
+        build_with_default_webpack(&mut command, target)?;
     }
 
     Ok((command, temp_file, bundle))
@@ -216,15 +244,16 @@ fn build_with_custom_webpack(command: &mut Command, webpack_config_path: &Path)
     ));
 }
 
-fn build_with_default_webpack(command: &mut Command, package_dir: &Path) -> Result<()> {
-    let package = Package::new(package_dir)?;
-    let package_main = package_dir
-        .join(package.main(package_dir)?)
-        .to_str()
-        .unwrap()
-        .to_string();
+fn build_with_default_webpack(command: &mut Command, target: &Target) -> Result<()> {
+    let entry = target.entry_point()?;
+    // webpack would resolve an entry such as `src/index.js` as a module in node_modules
+    let entry = if entry.is_relative() {
+        env::current_dir()?.join(entry)
+    } else {
+        entry
+    };
     command.arg("--no-webpack-config=1");
-    command.arg(format!("--use-entry={}", package_main));
+    command.arg(format!("--use-entry={}", entry.to_str().unwrap()));
     Ok(())
 }
 
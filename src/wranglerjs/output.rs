@@ -1,5 +1,5 @@
 use crate::terminal::emoji;
-use flate2::write::ZlibEncoder;
+use flate2::write::{GzEncoder, ZlibEncoder};
 use flate2::Compression;
 use number_prefix::NumberPrefix;
 use serde::Deserialize;
@@ -14,6 +14,39 @@ pub struct WranglerjsOutput {
     pub script: String,
     // Errors emitted by {wranglerjs}, if any
     pub errors: Vec<String>,
+    // Node.js polyfills bundled because of `node_compat`
+    #[serde(default)]
+    pub polyfills: Vec<Polyfill>,
+    // Sizes of the modules of the bundle, for `wrangler build --analyze`
+    #[serde(default)]
+    pub modules: Vec<ModuleSize>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Polyfill {
+    pub name: String,
+    // Size of the polyfill in the bundle, before compression
+    pub size: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ModuleSize {
+    pub name: String,
+    // Size of the module in the bundle, before and after compression
+    pub size: u64,
+    pub gzip_size: u64,
+}
+
+impl ModuleSize {
+    pub fn new(name: String, code: &[u8]) -> Self {
+        let mut e = GzEncoder::new(Vec::new(), Compression::default());
+        e.write_all(code).expect("could not write module buffer");
+        ModuleSize {
+            name,
+            size: code.len() as u64,
+            gzip_size: e.finish().expect("failed to compress module").len() as u64,
+        }
+    }
 }
 
 impl WranglerjsOutput {
@@ -72,6 +105,30 @@ impl WranglerjsOutput {
     pub fn project_size(&self) -> String {
         Self::project_size_message(self.project_size_bytes())
     }
+
+    pub fn polyfills_message(&self) -> String {
+        if self.polyfills.is_empty() {
+            return "No Node.js polyfill was needed by your project.".to_string();
+        }
+        let total: u64 = self.polyfills.iter().map(|polyfill| polyfill.size).sum();
+        let lines = self
+            .polyfills
+            .iter()
+            .map(|polyfill| format!("- {} ({})", polyfill.name, human_size(polyfill.size)))
+            .collect::<Vec<_>>();
+        format!(
+            "Injected these Node.js polyfills, adding {} to your project:\n{}",
+            human_size(total),
+            lines.join("\n")
+        )
+    }
+}
+
+pub fn human_size(bytes: u64) -> String {
+    match NumberPrefix::binary(bytes as f64) {
+        NumberPrefix::Standalone(bytes) => format!("{} bytes", bytes),
+        NumberPrefix::Prefixed(prefix, n) => format!("{:.0} {}B", n, prefix),
+    }
 }
 
 #[cfg(test)]
@@ -92,6 +149,8 @@ mod tests {
     fn it_returns_project_size_with_wasm() {
         let wranglerjs_output = WranglerjsOutput {
             errors: vec![],
+            polyfills: vec![],
+            modules: vec![],
             script: "abcdefg".to_string(),
             wasm: Some("123456".to_string()),
         };
@@ -103,6 +162,8 @@ mod tests {
     fn it_returns_project_size_without_wasm() {
         let wranglerjs_output = WranglerjsOutput {
             errors: vec![],
+            polyfills: vec![],
+            modules: vec![],
             script: "abcdefg".to_string(),
             wasm: None,
         };
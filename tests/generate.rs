@@ -56,6 +56,22 @@ fn it_generates_toml_file_in_correct_directory() {
     cleanup(expected_name);
 }
 
+#[test]
+fn it_generates_a_durable_objects_starter() {
+    let name = "durable-starter";
+    Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .args(&["generate", name, "--type", "durable-objects"])
+        .assert()
+        .success();
+
+    let wranglertoml_text = fs::read_to_string(format!("{}/wrangler.toml", name)).unwrap();
+    assert!(wranglertoml_text.contains("name = \"durable-starter\""));
+    assert!(wranglertoml_text.contains("new_classes = [\"Counter\"]"));
+    assert!(Path::new(&format!("{}/src/index.mjs", name)).exists());
+    cleanup(name);
+}
+
 pub fn generate(name: Option<&str>, template: Option<&str>, project_type: Option<&str>) {
     let mut wrangler = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
     if name.is_none() && template.is_none() && project_type.is_none() {
@@ -31,6 +31,25 @@ fn init_fails_if_wrangler_toml_exists() {
     cleanup(name);
 }
 
+#[test]
+fn init_replaces_wrangler_toml_with_force() {
+    let name = "init3";
+    generate(Some(name));
+
+    let wranglertoml_path = format!("{}/wrangler.toml", name);
+    fs::write(&wranglertoml_path, "name = \"old\"\n").unwrap();
+
+    init()
+        .args(&["replaced", "--force", "--yes"])
+        .current_dir(Path::new(name))
+        .assert()
+        .success();
+    let wranglertoml_text = fs::read_to_string(&wranglertoml_path).unwrap();
+    assert!(wranglertoml_text.contains("name = \"replaced\""));
+
+    cleanup(name);
+}
+
 fn init() -> Command {
     let mut wrangler = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
     wrangler.arg("init");
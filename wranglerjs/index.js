@@ -1,6 +1,8 @@
 const webpack = require("webpack");
-const { join } = require("path");
+const { dirname, join } = require("path");
 const fs = require("fs");
+const zlib = require("zlib");
+const { polyfillPackages, polyfillSizes } = require("./polyfills");
 
 const WEBPACK_OUTPUT_FILENAME = "worker.js";
 const WEBPACK_OUTPUT_SOURCEMAPFILENAME = WEBPACK_OUTPUT_FILENAME + ".map";
@@ -14,6 +16,33 @@ function warn(...msg) {
   console.warn("Warning: " + msg.join(" "));
 }
 
+// The paths of the polyfills of Node.js built-in modules that webpack 4 uses, or `null` for the
+// modules without one. node-libs-browser is a dependency of webpack, wherever npm installed it.
+function nodeLibs() {
+  const webpackDir = dirname(require.resolve("webpack/package.json"));
+  return require(require.resolve("node-libs-browser", { paths: [webpackDir] }));
+}
+
+// Resolves the built-in modules of Node.js to their polyfills and provides the `Buffer` and
+// `process` globals, even where the configuration turned them off. Webpack 4 only does so for
+// the modules and globals that `node` doesn't disable.
+function enableNodeCompat(config, libs) {
+  const alias = {};
+  for (const [name, path] of Object.entries(libs)) {
+    if (path !== null) {
+      alias[name + "$"] = path;
+    }
+  }
+  config.resolve = config.resolve || {};
+  config.resolve.alias = Object.assign(alias, config.resolve.alias);
+  config.plugins = (config.plugins || []).concat(
+    new webpack.ProvidePlugin({
+      Buffer: [libs.buffer, "Buffer"],
+      process: libs.process,
+    })
+  );
+}
+
 function filterByExtension(ext) {
   return (v) => new RegExp(`\\.${ext}$`).test(v);
 }
@@ -25,7 +54,12 @@ function filterByExtension(ext) {
       throw error("malformed arguments");
     }
 
-    const [name, value] = e.split("=");
+    // values such as the JSON of --define may contain `=` themselves
+    const separator = e.indexOf("=");
+    const [name, value] =
+      separator === -1
+        ? [e, undefined]
+        : [e.slice(0, separator), e.slice(separator + 1)];
     const normalizedName = name.replace("--", "");
     obj[normalizedName] = value;
     return obj;
@@ -59,6 +93,22 @@ function filterByExtension(ext) {
   }
   config.target = "webworker";
 
+  if (args["define"] !== undefined) {
+    config.plugins = (config.plugins || []).concat(
+      new webpack.DefinePlugin(JSON.parse(args["define"]))
+    );
+  }
+
+  const libs = args["node-compat"] === "1" ? nodeLibs() : null;
+  if (libs !== null) {
+    if (config.node === false) {
+      warn(
+        "`node: false` in your webpack configuration is ignored because `node_compat` is enabled"
+      );
+    }
+    enableNodeCompat(config, libs);
+  }
+
   // The worker runtime will set the name of the script to `worker.js`,
   // regardless of what's specified in the sourcemap.
   // We can tell webpack to name the generated worker by configuring the output.
@@ -107,8 +157,24 @@ function filterByExtension(ext) {
         wasm: null,
         script: "",
         errors: jsonStats.errors,
+        polyfills: [],
+        modules: (jsonStats.modules || []).map((module) => ({
+          name: module.name,
+          size: module.size,
+          gzip_size:
+            typeof module.source === "string"
+              ? zlib.gzipSync(module.source).length
+              : 0,
+        })),
       };
 
+      if (libs !== null) {
+        bundle.polyfills = polyfillSizes(
+          jsonStats.modules || [],
+          polyfillPackages(libs)
+        );
+      }
+
       const wasmModuleAsset = Object.keys(assets).find(
         filterByExtension("wasm")
       );
@@ -0,0 +1,66 @@
+// Finds the modules of a bundle that polyfill Node.js built-in modules, by the packages that
+// node-libs-browser resolves them to. Those packages are wherever npm installed them: under
+// webpack's own node_modules, or hoisted next to it.
+
+const NODE_MODULES = "/node_modules/";
+
+function normalize(path) {
+  return path.replace(/\\/g, "/");
+}
+
+// The directory of the package a file belongs to, e.g. `/a/node_modules/buffer` for
+// `/a/node_modules/buffer/index.js`.
+function packageDir(file) {
+  file = normalize(file);
+  const start = file.lastIndexOf(NODE_MODULES);
+  if (start === -1) {
+    return null;
+  }
+  const rest = file.slice(start + NODE_MODULES.length).split("/");
+  const name = rest[0].startsWith("@") ? rest.slice(0, 2).join("/") : rest[0];
+  return file.slice(0, start + NODE_MODULES.length) + name;
+}
+
+// The packages of the polyfills of node-libs-browser, from their directory to their name.
+function polyfillPackages(libs) {
+  const packages = new Map();
+  for (const file of Object.values(libs)) {
+    if (file === null) {
+      continue;
+    }
+    const dir = packageDir(file);
+    if (dir !== null) {
+      packages.set(dir, dir.slice(dir.lastIndexOf(NODE_MODULES) + NODE_MODULES.length));
+    }
+  }
+  return packages;
+}
+
+// The polyfill package a module was resolved to, if any, ignoring its loaders.
+function polyfillOf(identifier, packages) {
+  if (typeof identifier !== "string") {
+    return undefined;
+  }
+  const file = identifier.slice(identifier.lastIndexOf("!") + 1);
+  return packages.get(packageDir(file));
+}
+
+// The size of each polyfill in a bundle, counting the dependencies of a polyfill, like the
+// `ieee754` of `buffer`, as part of it.
+function polyfillSizes(modules, packages) {
+  const sizes = {};
+  for (const module of modules) {
+    const issuers = (module.issuerPath || []).map((issuer) => issuer.identifier);
+    const name = [module.identifier, ...issuers.reverse()]
+      .map((identifier) => polyfillOf(identifier, packages))
+      .find((name) => name !== undefined);
+    if (name !== undefined) {
+      sizes[name] = (sizes[name] || 0) + module.size;
+    }
+  }
+  return Object.keys(sizes)
+    .sort()
+    .map((name) => ({ name, size: sizes[name] }));
+}
+
+module.exports = { packageDir, polyfillPackages, polyfillSizes };
@@ -0,0 +1,51 @@
+const assert = require("assert");
+const { packageDir, polyfillPackages, polyfillSizes } = require("./polyfills");
+
+assert.strictEqual(
+  packageDir("/app/node_modules/buffer/index.js"),
+  "/app/node_modules/buffer"
+);
+assert.strictEqual(
+  packageDir("C:\\app\\node_modules\\@scope\\pkg\\lib\\a.js"),
+  "C:/app/node_modules/@scope/pkg"
+);
+assert.strictEqual(packageDir("/app/src/index.js"), null);
+
+// npm hoists the polyfills next to webpack, e.g. in the directory wranglerjs is installed in
+const hoisted = "/home/me/.wrangler/wranglerjs-1.19.0/node_modules";
+// unless another version of them is already there
+const nested = hoisted + "/webpack/node_modules";
+const libs = {
+  buffer: hoisted + "/buffer/index.js",
+  events: hoisted + "/events/events.js",
+  process: nested + "/process/browser.js",
+  child_process: null,
+};
+const packages = polyfillPackages(libs);
+
+const entry = { identifier: "/project/src/index.js" };
+const buffer = {
+  identifier: "/project/node_modules/babel-loader/lib/index.js!" + libs.buffer,
+};
+const modules = [
+  { ...entry, size: 100, issuerPath: null },
+  { ...buffer, size: 50, issuerPath: [entry] },
+  {
+    identifier: hoisted + "/ieee754/index.js",
+    size: 5,
+    issuerPath: [entry, buffer],
+  },
+  { identifier: libs.process, size: 20, issuerPath: [entry] },
+  {
+    identifier: "/project/node_modules/lodash/lodash.js",
+    size: 500,
+    issuerPath: [entry],
+  },
+];
+
+assert.deepStrictEqual(polyfillSizes(modules, packages), [
+  { name: "buffer", size: 55 },
+  { name: "process", size: 20 },
+]);
+
+console.log("polyfills: ok");